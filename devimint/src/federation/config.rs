@@ -14,6 +14,7 @@
 use fedimint_wallet_client::config::{
     WalletGenParams, WalletGenParamsConsensus, WalletGenParamsLocal,
 };
+use fedimint_wallet_client::envs::FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN_ENV;
 use fedimint_wallet_server::WalletInit;
 use fedimintd::default_esplora_server;
 use fedimintd::envs::FM_DISABLE_META_MODULE_ENV;
@@ -59,6 +60,8 @@ pub fn attach_default_module_init_params(
                 finality_delay,
                 client_default_bitcoin_rpc: default_esplora_server(network),
                 fee_consensus: fedimint_wallet_client::config::FeeConsensus::default(),
+                taproot: is_env_var_set(FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN_ENV),
+                recovery: None,
             },
         },
     );
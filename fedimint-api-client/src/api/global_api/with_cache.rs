@@ -13,7 +13,7 @@
 use fedimint_core::endpoint_constants::{
     ADD_CONFIG_GEN_PEER_ENDPOINT, ADD_PEER_SETUP_CODE_ENDPOINT, API_ANNOUNCEMENTS_ENDPOINT,
     AUDIT_ENDPOINT, AUTH_ENDPOINT, AWAIT_SESSION_OUTCOME_ENDPOINT, AWAIT_TRANSACTION_ENDPOINT,
-    BACKUP_ENDPOINT, BACKUP_STATISTICS_ENDPOINT, CONFIG_GEN_PEERS_ENDPOINT,
+    BACKUP_ENDPOINT, BACKUP_STATISTICS_ENDPOINT, CONFIG_GEN_PEERS_ENDPOINT, EVENT_LOG_ENDPOINT,
     FEDIMINTD_VERSION_ENDPOINT, GUARDIAN_CONFIG_BACKUP_ENDPOINT, RECOVER_ENDPOINT,
     RESTART_FEDERATION_SETUP_ENDPOINT, SESSION_COUNT_ENDPOINT, SESSION_STATUS_ENDPOINT,
     SESSION_STATUS_V2_ENDPOINT, SET_LOCAL_PARAMS_ENDPOINT, SET_PASSWORD_ENDPOINT,
@@ -37,6 +37,7 @@
 use fedimint_core::transaction::{SerdeTransaction, Transaction, TransactionSubmissionOutcome};
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{NumPeersExt, PeerId, TransactionId, apply, async_trait_maybe_send};
+use fedimint_eventlog::{EventLogId, PersistedLogEntry};
 use fedimint_logging::LOG_CLIENT_NET_API;
 use futures::future::join_all;
 use itertools::Itertools;
@@ -545,6 +546,20 @@ async fn backup_statistics(&self, auth: ApiAuth) -> FederationResult<BackupStati
         .await
     }
 
+    async fn event_log(
+        &self,
+        pos: Option<EventLogId>,
+        limit: u64,
+        auth: ApiAuth,
+    ) -> FederationResult<Vec<PersistedLogEntry>> {
+        self.request_admin(
+            EVENT_LOG_ENDPOINT,
+            ApiRequestErased::new((pos, limit)),
+            auth,
+        )
+        .await
+    }
+
     async fn fedimintd_version(&self, peer_id: PeerId) -> PeerResult<String> {
         self.request_single_peer(
             FEDIMINTD_VERSION_ENDPOINT.to_owned(),
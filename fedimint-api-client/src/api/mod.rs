@@ -35,6 +35,7 @@
 use fedimint_core::{
     NumPeersExt, PeerId, TransactionId, apply, async_trait_maybe_send, dyn_newtype_define, util,
 };
+use fedimint_eventlog::{EventLogId, PersistedLogEntry};
 use fedimint_logging::{LOG_CLIENT_NET_API, LOG_NET_API, LOG_NET_WS};
 use futures::channel::oneshot;
 use futures::future::pending;
@@ -584,6 +585,15 @@ async fn sign_api_announcement(
 
     /// Fetch the backup statistics from the federation (admin endpoint)
     async fn backup_statistics(&self, auth: ApiAuth) -> FederationResult<BackupStatistics>;
+
+    /// Fetch up to `limit` entries of the guardian's event log starting at
+    /// `pos` (admin endpoint)
+    async fn event_log(
+        &self,
+        pos: Option<EventLogId>,
+        limit: u64,
+        auth: ApiAuth,
+    ) -> FederationResult<Vec<PersistedLogEntry>>;
 }
 
 pub fn deserialize_outcome<R>(
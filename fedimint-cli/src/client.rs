@@ -1,5 +1,8 @@
+mod export;
+
 use std::collections::BTreeMap;
 use std::ffi;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -22,7 +25,8 @@
 use fedimint_mint_client::{
     MintClientModule, OOBNotes, SelectNotesWithAtleastAmount, SelectNotesWithExactAmount,
 };
-use fedimint_wallet_client::{WalletClientModule, WithdrawState};
+use fedimint_wallet_client::silent_payment::SilentPaymentAddress;
+use fedimint_wallet_client::{WalletClientModule, WithdrawState, descriptor_script_pubkey};
 use futures::StreamExt;
 use itertools::Itertools;
 use lightning_invoice::{Bolt11InvoiceDescription, Description};
@@ -32,6 +36,7 @@
 use time::format_description::well_known::iso8601;
 use tracing::{debug, info, warn};
 
+use self::export::{ExportFormat, parse_export_time};
 use crate::metadata_from_clap_cli;
 
 #[derive(Debug, Clone)]
@@ -169,8 +174,24 @@ pub enum ClientCmd {
     Withdraw {
         #[clap(long)]
         amount: BitcoinAmountOrAll,
+        #[clap(long, required_unless_present_any = ["script", "descriptor", "silent_payment"])]
+        address: Option<bitcoin::Address<NetworkUnchecked>>,
+        /// Pay to an explicit output script (hex encoded) instead of an
+        /// address
+        #[clap(long, conflicts_with_all = ["address", "descriptor", "silent_payment", "batched"])]
+        script: Option<bitcoin::ScriptBuf>,
+        /// Pay to the script of a non-wildcard output descriptor instead of an
+        /// address
+        #[clap(long, conflicts_with_all = ["address", "script", "silent_payment", "batched"])]
+        descriptor: Option<String>,
+        /// Pay to a BIP-352 silent payment address instead of an address, only
+        /// supported by taproot federations
+        #[clap(long, conflicts_with_all = ["address", "script", "descriptor", "batched"])]
+        silent_payment: Option<String>,
+        /// Pay out together with other withdrawals in a single transaction
+        /// once the current batch closes, sharing the transaction fees
         #[clap(long)]
-        address: bitcoin::Address<NetworkUnchecked>,
+        batched: bool,
     },
     /// Upload the (encrypted) snapshot of mint notes to federation
     Backup {
@@ -197,6 +218,23 @@ pub enum ClientCmd {
         #[clap(long, default_value = "10")]
         limit: usize,
     },
+    /// Export a ledger of all payments (ecash, onchain and lightning) for
+    /// accounting purposes
+    Export {
+        #[clap(long, value_enum, default_value = "json")]
+        format: ExportFormat,
+        /// File to write the ledger to, required for CSV
+        #[clap(long)]
+        output: Option<PathBuf>,
+        /// Only include entries at or after this date (`YYYY-MM-DD`) or RFC
+        /// 3339 time
+        #[clap(long, value_parser = parse_export_time)]
+        since: Option<SystemTime>,
+        /// Only include entries before this date (`YYYY-MM-DD`) or RFC 3339
+        /// time
+        #[clap(long, value_parser = parse_export_time)]
+        until: Option<SystemTime>,
+    },
     /// Call a module subcommand
     // Make `--help` be passed to the module handler, not root cli one
     #[command(disable_help_flag = true)]
@@ -212,6 +250,13 @@ pub enum ClientCmd {
     SessionCount,
 }
 
+/// Where a withdrawal pays to
+enum WithdrawDestination {
+    Address(bitcoin::Address),
+    Script(bitcoin::ScriptBuf),
+    SilentPayment(SilentPaymentAddress),
+}
+
 pub async fn handle_command(
     command: ClientCmd,
     client: ClientHandleArc,
@@ -571,26 +616,73 @@ struct OperationOutput {
                 "operations": operations,
             }))
         }
-        ClientCmd::Withdraw { amount, address } => {
+        ClientCmd::Export {
+            format,
+            output,
+            since,
+            until,
+        } => {
+            let rows = export::export_ledger(&client, since, until).await;
+
+            export::output_ledger(&rows, format, output)
+        }
+        ClientCmd::Withdraw {
+            amount,
+            address,
+            script,
+            descriptor,
+            silent_payment,
+            batched,
+        } => {
             let wallet_module = client.get_first_module::<WalletClientModule>()?;
-            let address = address.require_network(wallet_module.get_network())?;
+            let network = wallet_module.get_network();
+            let destination = match (address, script, descriptor, silent_payment) {
+                (Some(address), None, None, None) => {
+                    WithdrawDestination::Address(address.require_network(network)?)
+                }
+                (None, Some(script), None, None) => WithdrawDestination::Script(script),
+                (None, None, Some(descriptor), None) => {
+                    WithdrawDestination::Script(descriptor_script_pubkey(&descriptor)?)
+                }
+                (None, None, None, Some(address)) => WithdrawDestination::SilentPayment(
+                    SilentPaymentAddress::parse(&address, network)?,
+                ),
+                _ => bail!(
+                    "Exactly one of address, script, descriptor or silent payment address must be given"
+                ),
+            };
+            let get_fees = async |amount: bitcoin::Amount| match &destination {
+                WithdrawDestination::Address(address) if batched => {
+                    wallet_module.get_batched_withdraw_fees(address).await
+                }
+                WithdrawDestination::Address(address) => {
+                    wallet_module.get_withdraw_fees(address, amount).await
+                }
+                WithdrawDestination::Script(script) => {
+                    wallet_module
+                        .get_withdraw_to_script_fees(script, amount)
+                        .await
+                }
+                WithdrawDestination::SilentPayment(address) => {
+                    wallet_module
+                        .get_withdraw_to_silent_payment_fees(address, amount)
+                        .await
+                }
+            };
             let (amount, fees) = match amount {
                 // If the amount is "all", then we need to subtract the fees from
                 // the amount we are withdrawing
                 BitcoinAmountOrAll::All => {
                     let balance =
                         bitcoin::Amount::from_sat(client.get_balance().await.msats / 1000);
-                    let fees = wallet_module.get_withdraw_fees(&address, balance).await?;
+                    let fees = get_fees(balance).await?;
                     let amount = balance.checked_sub(fees.amount());
                     if amount.is_none() {
                         bail!("Not enough funds to pay fees");
                     }
                     (amount.unwrap(), fees)
                 }
-                BitcoinAmountOrAll::Amount(amount) => (
-                    amount,
-                    wallet_module.get_withdraw_fees(&address, amount).await?,
-                ),
+                BitcoinAmountOrAll::Amount(amount) => (amount, get_fees(amount).await?),
             };
             let absolute_fees = fees.amount();
 
@@ -599,7 +691,26 @@ struct OperationOutput {
                 "Attempting withdraw with fees: {fees:?}"
             );
 
-            let operation_id = wallet_module.withdraw(&address, amount, fees, ()).await?;
+            let operation_id = match &destination {
+                WithdrawDestination::Address(address) if batched => {
+                    wallet_module
+                        .withdraw_batched(address, amount, fees, ())
+                        .await?
+                }
+                WithdrawDestination::Address(address) => {
+                    wallet_module.withdraw(address, amount, fees, ()).await?
+                }
+                WithdrawDestination::Script(script) => {
+                    wallet_module
+                        .withdraw_to_script(script, amount, fees, ())
+                        .await?
+                }
+                WithdrawDestination::SilentPayment(address) => {
+                    wallet_module
+                        .withdraw_to_silent_payment(address, amount, fees, ())
+                        .await?
+                }
+            };
 
             let mut updates = wallet_module
                 .subscribe_withdraw_updates(operation_id)
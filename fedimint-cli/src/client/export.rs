@@ -0,0 +1,879 @@
+//! Ledger export of the client's operation and event logs, meant to be
+//! imported into accounting tools.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use clap::ValueEnum;
+use fedimint_client::ClientHandleArc;
+use fedimint_client::module::oplog::OperationLogEntry;
+use fedimint_core::Amount;
+use fedimint_core::core::OperationId;
+use fedimint_eventlog::EventLogId;
+use fedimint_ln_client::{LightningOperationMeta, LightningOperationMetaVariant};
+use fedimint_lnv2_client::LightningOperationMeta as LightningOperationMetaV2;
+use fedimint_logging::LOG_CLIENT;
+use fedimint_mint_client::{MintOperationMeta, MintOperationMetaVariant};
+use fedimint_wallet_client::events::DepositConfirmed;
+use fedimint_wallet_client::{WalletOperationMeta, WalletOperationMetaVariant};
+use serde::Serialize;
+use serde_json::{Value, json};
+use time::format_description::well_known::{Iso8601, Rfc3339};
+use time::{Date, OffsetDateTime};
+use tracing::warn;
+
+use super::time_to_iso8601;
+
+/// Number of operations/events fetched from the database at once
+const EXPORT_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Incoming => "incoming",
+            Direction::Outgoing => "outgoing",
+        }
+    }
+}
+
+/// A single line of the exported ledger
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerRow {
+    #[serde(skip)]
+    time: SystemTime,
+    /// Creation time of the operation or time of the event (ISO 8601, UTC)
+    pub timestamp: String,
+    /// Operation id or, for rows derived from the event log, `event-<id>`
+    pub id: String,
+    pub module_kind: String,
+    pub direction: Direction,
+    pub amount: Amount,
+    /// Fees paid on top of `amount` (outgoing) or deducted from it (incoming)
+    pub fee: Amount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<String>,
+    /// Name of the last known state of the operation, `None` if it's still
+    /// pending or the outcome wasn't cached yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+}
+
+/// Parses either an RFC 3339 timestamp or a plain `YYYY-MM-DD` date, the
+/// latter being interpreted as midnight UTC.
+pub fn parse_export_time(s: &str) -> anyhow::Result<SystemTime> {
+    let time = match OffsetDateTime::parse(s, &Rfc3339) {
+        Ok(time) => time,
+        Err(_) => Date::parse(s, &Iso8601::DEFAULT)
+            .with_context(|| format!("Invalid date or time: {s}"))?
+            .midnight()
+            .assume_utc(),
+    };
+
+    Ok(SystemTime::from(time))
+}
+
+/// Whether `time` lies within `[since, until)`, either bound being optional
+fn in_export_range(time: SystemTime, since: Option<SystemTime>, until: Option<SystemTime>) -> bool {
+    since.is_none_or(|since| since <= time) && until.is_none_or(|until| time < until)
+}
+
+/// Collects ledger rows of all operations and events in `[since, until)`,
+/// ordered by time.
+pub async fn export_ledger(
+    client: &ClientHandleArc,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+) -> Vec<LedgerRow> {
+    let in_range = |time| in_export_range(time, since, until);
+
+    let mut rows = vec![];
+
+    let mut last_seen = None;
+    'operations: loop {
+        let page = client
+            .operation_log()
+            .paginate_operations_rev(EXPORT_PAGE_SIZE, last_seen)
+            .await;
+
+        let Some((last_key, _)) = page.last() else {
+            break;
+        };
+        last_seen = Some(*last_key);
+
+        for (key, entry) in page {
+            if since.is_some_and(|since| key.creation_time < since) {
+                break 'operations;
+            }
+
+            if in_range(key.creation_time) {
+                rows.extend(operation_row(key.operation_id, key.creation_time, &entry));
+            }
+        }
+    }
+
+    let mut pos = EventLogId::LOG_START;
+    loop {
+        let events = client
+            .get_event_log(Some(pos), EXPORT_PAGE_SIZE as u64)
+            .await;
+
+        let Some(last_event) = events.last() else {
+            break;
+        };
+        pos = last_event.event_id.saturating_add(1);
+
+        for event in events {
+            let time = UNIX_EPOCH + Duration::from_micros(event.timestamp);
+
+            if !in_range(time) {
+                continue;
+            }
+
+            // Deposits via the pegin monitor don't map to a single operation,
+            // so they are only accounted for via the event log
+            let Some((module_kind, _)) = event.module else {
+                continue;
+            };
+
+            if module_kind != fedimint_wallet_client::KIND
+                || event.event_kind != <DepositConfirmed as fedimint_eventlog::Event>::KIND
+            {
+                continue;
+            }
+
+            let Ok(deposit) = serde_json::from_value::<DepositConfirmed>(event.value) else {
+                warn!(target: LOG_CLIENT, event_id = ?event.event_id, "Could not decode deposit event");
+                continue;
+            };
+
+            rows.push(LedgerRow {
+                time,
+                timestamp: time_to_iso8601(&time),
+                id: format!("event-{}", json!(event.event_id)),
+                module_kind: module_kind.to_string(),
+                direction: Direction::Incoming,
+                amount: deposit.amount,
+                fee: Amount::ZERO,
+                counterparty: Some(format!("{}:{}", deposit.txid, deposit.out_idx)),
+                outcome: Some("confirmed".to_string()),
+            });
+        }
+    }
+
+    rows.sort_by_key(|row| row.time);
+
+    rows
+}
+
+fn operation_row(
+    operation_id: OperationId,
+    time: SystemTime,
+    entry: &OperationLogEntry,
+) -> Option<LedgerRow> {
+    let module_kind = entry.operation_module_kind();
+    let meta = entry.meta::<Value>();
+
+    let (direction, amount, fee, counterparty) = match module_kind {
+        "mint" => {
+            let meta = parse_meta::<MintOperationMeta>(operation_id, meta)?;
+
+            match meta.variant {
+                MintOperationMetaVariant::Reissuance { .. }
+                | MintOperationMetaVariant::HtlcClaim { .. }
+                | MintOperationMetaVariant::HtlcRefund { .. } => {
+                    (Direction::Incoming, meta.amount, Amount::ZERO, None)
+                }
+                MintOperationMetaVariant::SpendOOB { .. }
+                | MintOperationMetaVariant::HtlcOffer { .. } => {
+                    (Direction::Outgoing, meta.amount, Amount::ZERO, None)
+                }
+            }
+        }
+        "wallet" => {
+            let meta = parse_meta::<WalletOperationMeta>(operation_id, meta)?;
+
+            match meta.variant {
+                // Accounted for via `DepositConfirmed` events
+                WalletOperationMetaVariant::Deposit { .. } => return None,
+                WalletOperationMetaVariant::Withdraw {
+                    address,
+                    amount,
+                    fee,
+                    ..
+                } => (
+                    Direction::Outgoing,
+                    amount.into(),
+                    fee.amount().into(),
+                    Some(address.assume_checked().to_string()),
+                ),
+                WalletOperationMetaVariant::WithdrawToScript {
+                    script_pubkey,
+                    amount,
+                    fee,
+                    ..
+                } => (
+                    Direction::Outgoing,
+                    amount.into(),
+                    fee.amount().into(),
+                    Some(script_pubkey.to_hex_string()),
+                ),
+                WalletOperationMetaVariant::WithdrawToSilentPayment {
+                    address,
+                    amount,
+                    fee,
+                    ..
+                } => (
+                    Direction::Outgoing,
+                    amount.into(),
+                    fee.amount().into(),
+                    Some(address.spend_key.to_string()),
+                ),
+                WalletOperationMetaVariant::RbfWithdraw { rbf, .. } => (
+                    Direction::Outgoing,
+                    Amount::ZERO,
+                    rbf.fees.amount().into(),
+                    Some(rbf.txid.to_string()),
+                ),
+            }
+        }
+        "ln" => {
+            let meta = parse_meta::<LightningOperationMeta>(operation_id, meta)?;
+
+            match meta.variant {
+                LightningOperationMetaVariant::Pay(pay) => (
+                    Direction::Outgoing,
+                    Amount::from_msats(pay.invoice.amount_milli_satoshis().unwrap_or_default()),
+                    pay.fee,
+                    Some(pay.invoice.get_payee_pub_key().to_string()),
+                ),
+                LightningOperationMetaVariant::Receive { invoice, .. } => (
+                    Direction::Incoming,
+                    Amount::from_msats(invoice.amount_milli_satoshis().unwrap_or_default()),
+                    Amount::ZERO,
+                    None,
+                ),
+                LightningOperationMetaVariant::RecurringPaymentReceive(receive) => (
+                    Direction::Incoming,
+                    Amount::from_msats(receive.invoice.amount_milli_satoshis().unwrap_or_default()),
+                    Amount::ZERO,
+                    None,
+                ),
+                // Deprecated, the claimed amount isn't part of the meta
+                #[allow(deprecated)]
+                LightningOperationMetaVariant::Claim { .. } => return None,
+            }
+        }
+        "lnv2" => {
+            let meta = parse_meta::<LightningOperationMetaV2>(operation_id, meta)?;
+
+            match meta {
+                LightningOperationMetaV2::Send(ref send) => (
+                    Direction::Outgoing,
+                    Amount::from_msats(send.invoice.amount_milli_satoshis().unwrap_or_default()),
+                    send.gateway_fee(),
+                    Some(send.invoice.payee_pub_key().to_string()),
+                ),
+                LightningOperationMetaV2::SendMultiPath(ref send) => (
+                    Direction::Outgoing,
+                    Amount::from_msats(send.invoice.amount_milli_satoshis().unwrap_or_default()),
+                    send.gateway_fee(),
+                    Some(send.invoice.payee_pub_key().to_string()),
+                ),
+                LightningOperationMetaV2::Receive(receive) => {
+                    let invoice_amount = Amount::from_msats(
+                        receive.invoice.amount_milli_satoshis().unwrap_or_default(),
+                    );
+
+                    (
+                        Direction::Incoming,
+                        receive.contract.commitment.amount,
+                        invoice_amount.saturating_sub(receive.contract.commitment.amount),
+                        None,
+                    )
+                }
+            }
+        }
+        _ => return None,
+    };
+
+    Some(LedgerRow {
+        time,
+        timestamp: time_to_iso8601(&time),
+        id: operation_id.fmt_full().to_string(),
+        module_kind: module_kind.to_owned(),
+        direction,
+        amount,
+        fee,
+        counterparty,
+        outcome: entry.outcome::<Value>().as_ref().map(outcome_name),
+    })
+}
+
+fn parse_meta<M: serde::de::DeserializeOwned>(operation_id: OperationId, meta: Value) -> Option<M> {
+    serde_json::from_value(meta)
+        .inspect_err(|err| {
+            warn!(target: LOG_CLIENT, operation_id = %operation_id.fmt_short(), err = %err, "Could not decode operation meta, skipping");
+        })
+        .ok()
+}
+
+/// Operation states are serialized as externally tagged enums, so the name of
+/// the state is either the value itself or the only key of the object.
+fn outcome_name(outcome: &Value) -> String {
+    match outcome {
+        Value::String(state) => state.clone(),
+        Value::Object(map) if map.len() == 1 => map.keys().next().expect("len is 1").clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Renders the rows in the requested format
+pub fn render_ledger(rows: &[LedgerRow], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(rows).expect("Ledger rows are serializable")
+        }
+        ExportFormat::Csv => {
+            let mut csv = String::from(
+                "timestamp,id,module_kind,direction,amount_msat,fee_msat,counterparty,outcome\n",
+            );
+
+            for row in rows {
+                writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{},{}",
+                    row.timestamp,
+                    row.id,
+                    csv_escape(&row.module_kind),
+                    row.direction.as_str(),
+                    row.amount.msats,
+                    row.fee.msats,
+                    csv_escape(row.counterparty.as_deref().unwrap_or_default()),
+                    csv_escape(row.outcome.as_deref().unwrap_or_default()),
+                )
+                .expect("Writing to a string can't fail");
+            }
+
+            csv
+        }
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Writes the ledger to `output` if given, otherwise returns it as JSON so it
+/// is printed like the output of any other command.
+pub fn output_ledger(
+    rows: &[LedgerRow],
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> anyhow::Result<Value> {
+    match (output, format) {
+        (Some(path), format) => {
+            std::fs::write(&path, render_ledger(rows, format))
+                .with_context(|| format!("Failed to write ledger to {}", path.display()))?;
+
+            Ok(json!({
+                "path": path,
+                "rows": rows.len(),
+            }))
+        }
+        (None, ExportFormat::Json) => Ok(json!({ "rows": rows })),
+        (None, ExportFormat::Csv) => bail!("Exporting as CSV requires --output"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use bitcoin::address::NetworkUnchecked;
+    use fedimint_client::module::module::OutPointRange;
+    use fedimint_client::module::oplog::{JsonStringed, OperationLogEntry, OperationOutcome};
+    use fedimint_core::core::OperationId;
+    use fedimint_core::secp256k1::{PublicKey, SECP256K1, SecretKey};
+    use fedimint_core::util::SafeUrl;
+    use fedimint_core::{Amount, BitcoinHash, OutPoint, TransactionId};
+    use fedimint_ln_client::{
+        LightningOperationMeta, LightningOperationMetaPay, LightningOperationMetaVariant,
+    };
+    use fedimint_ln_common::contracts::ContractId;
+    use fedimint_lnv2_client::{
+        LightningOperationMeta as LightningOperationMetaV2, ReceiveOperationMeta,
+        SendMultiPathOperationMeta, SendOperationMeta, SendPartMeta,
+    };
+    use fedimint_lnv2_common::LightningInvoice;
+    use fedimint_lnv2_common::contracts::{IncomingContract, OutgoingContract, PaymentImage};
+    use fedimint_mint_client::{MintOperationMeta, MintOperationMetaVariant};
+    use fedimint_wallet_client::{
+        PegOutFees, Rbf, WalletOperationMeta, WalletOperationMetaVariant,
+    };
+    use lightning_invoice::Bolt11Invoice;
+    use serde::Serialize;
+    use serde_json::{Value, json};
+
+    use super::{
+        Direction, ExportFormat, LedgerRow, csv_escape, in_export_range, operation_row,
+        parse_export_time, render_ledger,
+    };
+
+    /// An invoice for 10 msat
+    const INVOICE: &str = "lnbc100p1psj9jhxdqud3jxktt5w46x7unfv9kz6mn0v3jsnp4q0d3p2sfluzdx45tqcs\
+        h2pu5qc7lgq0xs578ngs6s0s68ua4h7cvspp5q6rmq35js88zp5dvwrv9m459tnk2zunwj5jalqtyxqulh0l\
+        5gflssp5nf55ny5gcrfl30xuhzj3nphgj27rstekmr9fw3ny5989s300gyus9qyysgqcqpcrzjqw2sxwe993\
+        h5pcm4dxzpvttgza8zhkqxpgffcrf5v25nwpr3cmfg7z54kuqq8rgqqqqqqqq2qqqqq9qq9qrzjqd0ylaqcl\
+        j9424x9m8h2vcukcgnm6s56xfgu3j78zyqzhgs4hlpzvznlugqq9vsqqqqqqqlgqqqqqeqq9qrzjqwldmj9d\
+        ha74df76zhx6l9we0vjdquygcdt3kssupehe64g6yyp5yz5rhuqqwccqqyqqqqlgqqqqjcqq9qrzjqf9e58a\
+        guqr0rcun0ajlvmzq3ek63cw2w282gv3z5uupmuwvgjtq2z55qsqqg6qqqyqqqrtnqqqzq3cqygrzjqvphms\
+        ywntrrhqjcraumvc4y6r8v4z5v593trte429v4hredj7ms5z52usqq9ngqqqqqqqlgqqqqqqgq9qrzjq2v0v\
+        p62g49p7569ev48cmulecsxe59lvaw3wlxm7r982zxa9zzj7z5l0cqqxusqqyqqqqlgqqqqqzsqygarl9fh3\
+        8s0gyuxjjgux34w75dnc6xp2l35j7es3jd4ugt3lu0xzre26yg5m7ke54n2d5sym4xcmxtl8238xxvw5h5h5\
+        j5r6drg6k6zcqj0fcwg";
+
+    const ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn invoice() -> Bolt11Invoice {
+        INVOICE.parse().expect("Invoice is valid")
+    }
+
+    fn public_key() -> PublicKey {
+        SecretKey::from_slice(&[1; 32])
+            .expect("Secret key is valid")
+            .public_key(SECP256K1)
+    }
+
+    fn out_point() -> OutPoint {
+        OutPoint {
+            txid: TransactionId::all_zeros(),
+            out_idx: 0,
+        }
+    }
+
+    fn gateway() -> SafeUrl {
+        SafeUrl::parse("https://gateway.xyz").expect("Url is valid")
+    }
+
+    fn outgoing_contract(amount: Amount) -> OutgoingContract {
+        OutgoingContract {
+            payment_image: PaymentImage::Hash(BitcoinHash::all_zeros()),
+            amount,
+            expiration: 0,
+            claim_pk: public_key(),
+            refund_pk: public_key(),
+            ephemeral_pk: public_key(),
+        }
+    }
+
+    fn entry(module_kind: &str, meta: impl Serialize, outcome: Option<Value>) -> OperationLogEntry {
+        OperationLogEntry::new(
+            module_kind.to_string(),
+            JsonStringed(serde_json::to_value(meta).expect("Meta is serializable")),
+            outcome.map(|outcome| OperationOutcome {
+                time: time(0),
+                outcome: JsonStringed(outcome),
+            }),
+        )
+    }
+
+    fn row(entry: &OperationLogEntry) -> Option<LedgerRow> {
+        operation_row(OperationId([0; 32]), time(0), entry)
+    }
+
+    /// Returns the direction, amount, fee and counterparty of the row
+    fn summary(row: &LedgerRow) -> (Direction, Amount, Amount, Option<&str>) {
+        (
+            row.direction,
+            row.amount,
+            row.fee,
+            row.counterparty.as_deref(),
+        )
+    }
+
+    #[test]
+    fn csv_escape_quotes_special_characters() {
+        assert_eq!(csv_escape(""), "");
+        assert_eq!(csv_escape("plain text"), "plain text");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_escape("line\r\nbreak"), "\"line\r\nbreak\"");
+    }
+
+    #[test]
+    fn csv_rows_escape_their_fields() {
+        let rows = [LedgerRow {
+            time: time(0),
+            timestamp: "1970-01-01T00:00:00Z".to_string(),
+            id: "event-1".to_string(),
+            module_kind: "wallet".to_string(),
+            direction: Direction::Outgoing,
+            amount: Amount::from_msats(1_000),
+            fee: Amount::from_msats(10),
+            counterparty: Some("a,\"b\"".to_string()),
+            outcome: None,
+        }];
+
+        assert_eq!(
+            render_ledger(&rows, ExportFormat::Csv),
+            "timestamp,id,module_kind,direction,amount_msat,fee_msat,counterparty,outcome\n\
+             1970-01-01T00:00:00Z,event-1,wallet,outgoing,1000,10,\"a,\"\"b\"\"\",\n"
+        );
+    }
+
+    #[test]
+    fn parses_dates_and_times() {
+        // 2024-03-01T00:00:00Z
+        let midnight = time(1_709_251_200);
+
+        assert_eq!(parse_export_time("2024-03-01").unwrap(), midnight);
+        assert_eq!(parse_export_time("2024-03-01T00:00:00Z").unwrap(), midnight);
+        assert_eq!(
+            parse_export_time("2024-03-01T01:00:00+01:00").unwrap(),
+            midnight
+        );
+        assert_eq!(
+            parse_export_time("2024-03-01T00:00:01Z").unwrap(),
+            midnight + Duration::from_secs(1)
+        );
+
+        for invalid in [
+            "",
+            "2024-13-01",
+            "2024-02-30",
+            "01.03.2024",
+            "2024-03-01 00:00",
+        ] {
+            assert!(parse_export_time(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn export_range_includes_since_and_excludes_until() {
+        let since = parse_export_time("2024-03-01").unwrap();
+        let until = parse_export_time("2024-03-02").unwrap();
+        let second = Duration::from_secs(1);
+
+        assert!(in_export_range(since, Some(since), Some(until)));
+        assert!(in_export_range(until - second, Some(since), Some(until)));
+        assert!(!in_export_range(since - second, Some(since), Some(until)));
+        assert!(!in_export_range(until, Some(since), Some(until)));
+
+        // Missing bounds don't restrict the range
+        assert!(in_export_range(since - second, None, Some(until)));
+        assert!(in_export_range(until, Some(since), None));
+        assert!(in_export_range(time(0), None, None));
+
+        // An empty range contains nothing
+        assert!(!in_export_range(since, Some(since), Some(since)));
+    }
+
+    #[test]
+    fn maps_mint_operations() {
+        let reissuance = entry(
+            "mint",
+            MintOperationMeta {
+                variant: MintOperationMetaVariant::Reissuance {
+                    legacy_out_point: None,
+                    txid: Some(TransactionId::all_zeros()),
+                    out_point_indices: vec![0],
+                },
+                amount: Amount::from_sats(1),
+                extra_meta: Value::Null,
+            },
+            Some(json!({ "Done": null })),
+        );
+        let reissuance = row(&reissuance).expect("Reissuance is exported");
+
+        assert_eq!(
+            summary(&reissuance),
+            (
+                Direction::Incoming,
+                Amount::from_sats(1),
+                Amount::ZERO,
+                None
+            )
+        );
+        assert_eq!(reissuance.module_kind, "mint");
+        assert_eq!(reissuance.id, OperationId([0; 32]).fmt_full().to_string());
+        assert_eq!(reissuance.outcome.as_deref(), Some("Done"));
+
+        let htlc_refund = entry(
+            "mint",
+            MintOperationMeta {
+                variant: MintOperationMetaVariant::HtlcRefund {
+                    out_point: out_point(),
+                },
+                amount: Amount::from_sats(2),
+                extra_meta: Value::Null,
+            },
+            None,
+        );
+        let htlc_refund = row(&htlc_refund).expect("Refund is exported");
+
+        assert_eq!(
+            summary(&htlc_refund),
+            (
+                Direction::Incoming,
+                Amount::from_sats(2),
+                Amount::ZERO,
+                None
+            )
+        );
+        assert_eq!(htlc_refund.outcome, None);
+    }
+
+    #[test]
+    fn maps_wallet_operations() {
+        let address = ADDRESS
+            .parse::<bitcoin::Address<NetworkUnchecked>>()
+            .expect("Address is valid");
+
+        // Deposits are exported from the event log instead
+        let deposit = entry(
+            "wallet",
+            WalletOperationMeta {
+                variant: WalletOperationMetaVariant::Deposit {
+                    address: address.clone(),
+                    tweak_idx: None,
+                    expires_at: None,
+                },
+                extra_meta: Value::Null,
+            },
+            None,
+        );
+        assert!(row(&deposit).is_none());
+
+        // 1000 sat/kvB for 100 vbytes
+        let fee = PegOutFees::new(1_000, 400);
+
+        let withdraw = entry(
+            "wallet",
+            WalletOperationMeta {
+                variant: WalletOperationMetaVariant::Withdraw {
+                    address,
+                    amount: bitcoin::Amount::from_sat(10_000),
+                    fee,
+                    change: vec![],
+                },
+                extra_meta: Value::Null,
+            },
+            Some(json!("Succeeded")),
+        );
+        let withdraw = row(&withdraw).expect("Withdrawal is exported");
+
+        assert_eq!(
+            summary(&withdraw),
+            (
+                Direction::Outgoing,
+                Amount::from_sats(10_000),
+                Amount::from_sats(100),
+                Some(ADDRESS)
+            )
+        );
+        assert_eq!(withdraw.outcome.as_deref(), Some("Succeeded"));
+
+        let txid = bitcoin::Txid::all_zeros();
+        let rbf = entry(
+            "wallet",
+            WalletOperationMeta {
+                variant: WalletOperationMetaVariant::RbfWithdraw {
+                    rbf: Rbf { fees: fee, txid },
+                    change: vec![],
+                },
+                extra_meta: Value::Null,
+            },
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&rbf).expect("Fee bump is exported")),
+            (
+                Direction::Outgoing,
+                Amount::ZERO,
+                Amount::from_sats(100),
+                Some(txid.to_string().as_str())
+            )
+        );
+    }
+
+    #[test]
+    fn maps_ln_operations() {
+        let payee = invoice().get_payee_pub_key().to_string();
+
+        let pay = entry(
+            "ln",
+            LightningOperationMeta {
+                variant: LightningOperationMetaVariant::Pay(LightningOperationMetaPay {
+                    out_point: out_point(),
+                    invoice: invoice(),
+                    fee: Amount::from_msats(2),
+                    change: vec![],
+                    is_internal_payment: false,
+                    contract_id: ContractId::all_zeros(),
+                    gateway_id: None,
+                }),
+                extra_meta: Value::Null,
+            },
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&pay).expect("Payment is exported")),
+            (
+                Direction::Outgoing,
+                Amount::from_msats(10),
+                Amount::from_msats(2),
+                Some(payee.as_str())
+            )
+        );
+
+        let receive = entry(
+            "ln",
+            LightningOperationMeta {
+                variant: LightningOperationMetaVariant::Receive {
+                    out_point: out_point(),
+                    invoice: invoice(),
+                    gateway_id: None,
+                },
+                extra_meta: Value::Null,
+            },
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&receive).expect("Receive is exported")),
+            (
+                Direction::Incoming,
+                Amount::from_msats(10),
+                Amount::ZERO,
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn maps_lnv2_operations() {
+        let payee = invoice().get_payee_pub_key().to_string();
+        let change_outpoint_range =
+            OutPointRange::new_single(TransactionId::all_zeros(), 0).expect("Range is valid");
+
+        let send = entry(
+            "lnv2",
+            LightningOperationMetaV2::Send(SendOperationMeta {
+                change_outpoint_range,
+                gateway: gateway(),
+                contract: outgoing_contract(Amount::from_msats(15)),
+                invoice: LightningInvoice::Bolt11(invoice()),
+                custom_meta: Value::Null,
+            }),
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&send).expect("Payment is exported")),
+            (
+                Direction::Outgoing,
+                Amount::from_msats(10),
+                Amount::from_msats(5),
+                Some(payee.as_str())
+            )
+        );
+
+        // The gateway fee is the sum of the fees of all parts
+        let send_multi_path = entry(
+            "lnv2",
+            LightningOperationMetaV2::SendMultiPath(SendMultiPathOperationMeta {
+                change_outpoint_range,
+                parts: vec![
+                    SendPartMeta {
+                        gateway: gateway(),
+                        contract: outgoing_contract(Amount::from_msats(5)),
+                        amount: Amount::from_msats(4),
+                    },
+                    SendPartMeta {
+                        gateway: gateway(),
+                        contract: outgoing_contract(Amount::from_msats(8)),
+                        amount: Amount::from_msats(6),
+                    },
+                ],
+                invoice: LightningInvoice::Bolt11(invoice()),
+                custom_meta: Value::Null,
+            }),
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&send_multi_path).expect("Payment is exported")),
+            (
+                Direction::Outgoing,
+                Amount::from_msats(10),
+                Amount::from_msats(3),
+                Some(payee.as_str())
+            )
+        );
+
+        // The amount received is what remains after the gateway's fee
+        let receive = entry(
+            "lnv2",
+            LightningOperationMetaV2::Receive(ReceiveOperationMeta {
+                gateway: gateway(),
+                contract: IncomingContract::new(
+                    tpe::AggregatePublicKey(tpe::G1Affine::generator()),
+                    [0; 32],
+                    [0; 32],
+                    PaymentImage::Hash(BitcoinHash::all_zeros()),
+                    Amount::from_msats(8),
+                    0,
+                    public_key(),
+                    public_key(),
+                    public_key(),
+                ),
+                invoice: LightningInvoice::Bolt11(invoice()),
+                custom_meta: Value::Null,
+            }),
+            None,
+        );
+
+        assert_eq!(
+            summary(&row(&receive).expect("Receive is exported")),
+            (
+                Direction::Incoming,
+                Amount::from_msats(8),
+                Amount::from_msats(2),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn skips_unknown_modules_and_invalid_metas() {
+        assert!(row(&entry("dummy", json!({}), None)).is_none());
+        assert!(row(&entry("mint", json!({ "invalid": true }), None)).is_none());
+        assert!(row(&entry("lnv2", json!(null), None)).is_none());
+    }
+}
@@ -42,7 +42,10 @@
 use fedimint_core::config::{FederationId, FederationIdPrefix};
 use fedimint_core::core::{ModuleInstanceId, OperationId};
 use fedimint_core::db::{Database, DatabaseValue};
+use fedimint_core::encoding::Decodable;
+use fedimint_core::fountain::{self, FountainEncoder};
 use fedimint_core::invite_code::InviteCode;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::module::{ApiAuth, ApiRequestErased};
 use fedimint_core::util::{SafeUrl, backoff_util, handle_version_hash_command, retry};
 use fedimint_core::{Amount, PeerId, TieredMulti, fedimint_build_code_version_env, runtime};
@@ -376,6 +379,15 @@ enum AdminCmd {
     },
     /// Show statistics about client backups stored by the federation
     BackupStatistics,
+    /// Show events recorded by the guardian's modules
+    EventLog {
+        /// Id of the first event to show, defaults to the latest events
+        #[clap(long)]
+        pos: Option<EventLogId>,
+        /// Maximum number of events to show
+        #[clap(long, default_value = "10")]
+        limit: u64,
+    },
 }
 
 #[derive(Debug, Clone, Args)]
@@ -434,6 +446,22 @@ enum EncodeType {
     Notes { notes_json: String },
 }
 
+#[derive(Debug, Clone, Subcommand)]
+enum EncodeFramesType {
+    /// Split an invite code into fountain-coded frames
+    InviteCode { invite_code: InviteCode },
+    /// Split a string of ecash notes into fountain-coded frames
+    Notes { notes: OOBNotes },
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum DecodeFramesType {
+    /// Recover an invite code from fountain-coded frames
+    InviteCode { frames: Vec<String> },
+    /// Recover a string of ecash notes from fountain-coded frames
+    Notes { frames: Vec<String> },
+}
+
 #[derive(Debug, Clone, Subcommand)]
 enum DevCmd {
     /// Send direct method call to the API. If you specify --peer-id, it will
@@ -504,6 +532,27 @@ enum DevCmd {
         encode_type: EncodeType,
     },
 
+    /// Split an invite code or ecash notes into a sequence of frames that are
+    /// small enough for individual QR codes, any sufficiently large subset of
+    /// the frames can be used to recover the original data
+    EncodeFrames {
+        #[clap(subcommand)]
+        encode_type: EncodeFramesType,
+        /// Maximum number of bytes of data per frame
+        #[clap(long, default_value = "200")]
+        max_fragment_len: usize,
+        /// Number of frames to emit, defaults to twice the minimum required
+        #[clap(long)]
+        count: Option<u32>,
+    },
+
+    /// Recover an invite code or ecash notes from frames created by
+    /// `encode-frames`, frames may be missing, repeated or out of order
+    DecodeFrames {
+        #[clap(subcommand)]
+        decode_type: DecodeFramesType,
+    },
+
     /// Gets the current fedimint AlephBFT block count
     SessionCount,
 
@@ -921,6 +970,19 @@ async fn handle_command(&mut self, cli: Opts) -> CliOutputResult {
                     serde_json::to_value(backup_statistics).expect("Can be encoded"),
                 ))
             }
+            Command::Admin(AdminCmd::EventLog { pos, limit }) => {
+                let client = self.client_open(&cli).await?;
+
+                let events = cli
+                    .admin_client(&client.get_peer_urls().await, client.api_secret())
+                    .await?
+                    .event_log(pos, limit, cli.auth()?)
+                    .await?;
+
+                Ok(CliOutput::Raw(
+                    serde_json::to_value(events).expect("Can be encoded"),
+                ))
+            }
             Command::Dev(DevCmd::Api {
                 method,
                 params,
@@ -1086,6 +1148,54 @@ async fn handle_command(&mut self, cli: Opts) -> CliOutputResult {
                     Ok(CliOutput::Raw(notes.to_string().into()))
                 }
             },
+            Command::Dev(DevCmd::EncodeFrames {
+                encode_type,
+                max_fragment_len,
+                count,
+            }) => {
+                let mut encoder = match encode_type {
+                    EncodeFramesType::InviteCode { invite_code } => {
+                        FountainEncoder::from_encodable(&invite_code, max_fragment_len)
+                    }
+                    EncodeFramesType::Notes { notes } => {
+                        FountainEncoder::from_encodable(&notes, max_fragment_len)
+                    }
+                }
+                .map_err_cli()?;
+
+                let count = count.unwrap_or(2 * encoder.seq_len());
+
+                let frames = (0..count)
+                    .map(|_| encoder.next_frame().to_string())
+                    .collect::<Vec<String>>();
+
+                Ok(CliOutput::Raw(json!({
+                    "seq_len": encoder.seq_len(),
+                    "frames": frames,
+                })))
+            }
+            Command::Dev(DevCmd::DecodeFrames { decode_type }) => match decode_type {
+                DecodeFramesType::InviteCode { frames } => {
+                    let message = fountain::decode_frames(frames.iter().map(String::as_str))
+                        .map_err_cli_msg("failed to decode frames")?;
+                    let invite_code = InviteCode::consensus_decode_whole(
+                        &message,
+                        &ModuleDecoderRegistry::default(),
+                    )
+                    .map_err_cli_msg("failed to decode invite code")?;
+                    Ok(CliOutput::InviteCode { invite_code })
+                }
+                DecodeFramesType::Notes { frames } => {
+                    let message = fountain::decode_frames(frames.iter().map(String::as_str))
+                        .map_err_cli_msg("failed to decode frames")?;
+                    let notes = OOBNotes::consensus_decode_whole(
+                        &message,
+                        &ModuleDecoderRegistry::default(),
+                    )
+                    .map_err_cli_msg("failed to decode notes")?;
+                    Ok(CliOutput::Raw(notes.to_string().into()))
+                }
+            },
             Command::Dev(DevCmd::SessionCount) => {
                 let client = self.client_open(&cli).await?;
                 let count = client.api().session_count().await?;
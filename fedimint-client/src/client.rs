@@ -62,7 +62,8 @@
 };
 use fedimint_derive_secret::DerivableSecret;
 use fedimint_eventlog::{
-    DBTransactionEventLogExt as _, Event, EventKind, EventLogEntry, EventLogId, PersistedLogEntry,
+    DBTransactionEventLogExt as _, Event, EventKind, EventLogEntry, EventLogId, EventLogRetention,
+    PersistedLogEntry,
 };
 use fedimint_logging::{LOG_CLIENT, LOG_CLIENT_NET_API, LOG_CLIENT_RECOVERY};
 use futures::stream::FuturesUnordered;
@@ -81,6 +82,7 @@
     PeerLastApiVersionsSummary, PeerLastApiVersionsSummaryKey, apply_migrations_core_client_dbtx,
     get_decoded_client_secret, verify_client_db_integrity_dbtx,
 };
+use crate::event_sink::{DynEventSink, EventFilter, remove_event_sink, run_event_sink};
 use crate::meta::MetaService;
 use crate::module_init::{ClientModuleInitRegistry, DynClientModuleInit, IClientModuleInit};
 use crate::oplog::OperationLog;
@@ -144,6 +146,10 @@ pub struct Client {
     log_event_added_rx: watch::Receiver<()>,
     log_event_added_transient_tx: broadcast::Sender<EventLogEntry>,
     request_hook: ApiRequestHook,
+    event_log_retention: Option<EventLogRetention>,
+    /// Encoded cursor keys of followers using [`Client::handle_events`] that
+    /// are registered before the event log is pruned for the first time
+    event_log_followers: Vec<Vec<u8>>,
 }
 
 impl Client {
@@ -1465,6 +1471,38 @@ pub async fn handle_events<F, R, K>(&self, pos_key: &K, call_fn: F) -> anyhow::R
         .await
     }
 
+    /// Spawns a task forwarding all persisted events matching `filter` to
+    /// `sink` until the client shuts down.
+    ///
+    /// The delivery progress is tracked in the database under `name`, so
+    /// spawning a sink with the same name again after a restart continues with
+    /// the first event that wasn't delivered yet.
+    pub fn spawn_event_sink(
+        &self,
+        name: impl Into<String>,
+        filter: EventFilter,
+        sink: DynEventSink,
+    ) {
+        let name = name.into();
+        self.task_group.spawn_cancellable(
+            format!("event sink {name}"),
+            run_event_sink(
+                self.db.clone(),
+                self.log_event_added_rx.clone(),
+                name,
+                filter,
+                sink,
+            ),
+        );
+    }
+
+    /// Removes the delivery progress of an event sink that won't be spawned
+    /// again, so it doesn't prevent pruning the event log. Must not be called
+    /// while the sink is running.
+    pub async fn remove_event_sink(&self, name: impl Into<String>) {
+        remove_event_sink(&self.db, name.into()).await;
+    }
+
     pub async fn get_event_log(
         &self,
         pos: Option<EventLogId>,
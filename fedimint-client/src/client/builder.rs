@@ -2,6 +2,7 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context as _, anyhow, bail, ensure};
 use bitcoin::key::Secp256k1;
@@ -24,7 +25,8 @@
 use fedimint_core::config::{ClientConfig, ModuleInitRegistry};
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{
-    Database, IDatabaseTransactionOpsCoreTyped as _, verify_module_db_integrity_dbtx,
+    Database, DatabaseKeyPrefix, DatabaseRecord, IDatabaseTransactionOpsCoreTyped as _,
+    verify_module_db_integrity_dbtx,
 };
 use fedimint_core::envs::is_running_in_test_env;
 use fedimint_core::module::ApiVersion;
@@ -34,9 +36,11 @@
 use fedimint_core::{NumPeers, maybe_add_send};
 use fedimint_derive_secret::DerivableSecret;
 use fedimint_eventlog::{
-    DBTransactionEventLogExt as _, EventLogEntry, run_event_log_ordering_task,
+    DBTransactionEventLogExt as _, EventLogEntry, EventLogId, EventLogRetention,
+    run_event_log_ordering_task, run_event_log_pruning_task,
 };
 use fedimint_logging::LOG_CLIENT;
+use futures::StreamExt as _;
 use tokio::sync::{broadcast, watch};
 use tracing::{debug, warn};
 
@@ -49,6 +53,7 @@
     ClientModuleRecoveryState, ClientPreRootSecretHashKey, InitMode, InitState,
     apply_migrations_client_module_dbtx,
 };
+use crate::event_sink::EventSinkCursorPrefix;
 use crate::meta::MetaService;
 use crate::module_init::ClientModuleInitRegistry;
 use crate::oplog::OperationLog;
@@ -67,8 +72,13 @@ pub struct ClientBuilder {
     stopped: bool,
     log_event_added_transient_tx: broadcast::Sender<EventLogEntry>,
     request_hook: ApiRequestHook,
+    event_log_retention: Option<EventLogRetention>,
+    event_log_followers: Vec<Vec<u8>>,
 }
 
+/// How often the event log is pruned if an [`EventLogRetention`] was set
+const EVENT_LOG_PRUNING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 impl ClientBuilder {
     pub(crate) fn new(db: Database) -> Self {
         let meta_service = MetaService::new(LegacyMetaSource::default());
@@ -85,6 +95,8 @@ pub(crate) fn new(db: Database) -> Self {
             meta_service,
             log_event_added_transient_tx,
             request_hook: Arc::new(|api| api),
+            event_log_retention: None,
+            event_log_followers: vec![],
         }
     }
 
@@ -101,6 +113,8 @@ pub(crate) fn from_existing(client: &Client) -> Self {
             connector: client.connector,
             log_event_added_transient_tx: client.log_event_added_transient_tx.clone(),
             request_hook: client.request_hook.clone(),
+            event_log_retention: client.event_log_retention,
+            event_log_followers: client.event_log_followers.clone(),
         }
     }
 
@@ -191,6 +205,27 @@ pub fn with_meta_service(&mut self, meta_service: Arc<MetaService>) {
         self.meta_service = meta_service;
     }
 
+    /// Periodically prune the event log according to `retention`
+    ///
+    /// Entries not yet processed by followers using
+    /// [`Client::handle_events`] or [`Client::spawn_event_sink`] are never
+    /// pruned. Followers using [`Client::handle_events`] that may not be
+    /// running yet when the event log is pruned have to be registered with
+    /// [`Self::with_event_log_follower`].
+    pub fn with_event_log_retention(&mut self, retention: EventLogRetention) {
+        self.event_log_retention = Some(retention);
+    }
+
+    /// Register the follower persisting its cursor under `pos_key` using
+    /// [`Client::handle_events`] before the event log is pruned, so events it
+    /// hasn't processed yet are kept even if it is started later
+    pub fn with_event_log_follower<K>(&mut self, pos_key: &K)
+    where
+        K: DatabaseKeyPrefix + DatabaseRecord<Value = EventLogId>,
+    {
+        self.event_log_followers.push(pos_key.to_bytes());
+    }
+
     async fn migrate_database(&self, db: &Database) -> anyhow::Result<()> {
         // Only apply the client database migrations if the database has been
         // initialized.
@@ -842,6 +877,8 @@ async fn build_stopped(
             client_recovery_progress_receiver,
             meta_service: self.meta_service,
             connector,
+            event_log_retention: self.event_log_retention,
+            event_log_followers: self.event_log_followers,
         });
         client_inner
             .task_group
@@ -869,6 +906,33 @@ async fn build_stopped(
                 log_event_added_transient_tx,
             ),
         );
+
+        if let Some(retention) = client_inner.event_log_retention {
+            // Followers only register themselves once they run, so the ones with
+            // persisted cursors are registered before the first prune
+            let mut dbtx = db.begin_transaction().await;
+            let event_sink_cursor_keys = dbtx
+                .find_by_prefix(&EventSinkCursorPrefix)
+                .await
+                .map(|(key, _)| key.to_bytes())
+                .collect::<Vec<_>>()
+                .await;
+            for pos_key in client_inner
+                .event_log_followers
+                .iter()
+                .cloned()
+                .chain(event_sink_cursor_keys)
+            {
+                dbtx.register_persisted_event_log_follower(pos_key).await;
+            }
+            dbtx.commit_tx().await;
+
+            client_inner.task_group.spawn_cancellable(
+                "event log pruning task",
+                run_event_log_pruning_task(db.clone(), retention, EVENT_LOG_PRUNING_INTERVAL),
+            );
+        }
+
         let client_iface = std::sync::Arc::<Client>::downgrade(&client_inner);
 
         let client_arc = ClientHandle::new(client_inner);
@@ -56,6 +56,8 @@ pub enum DbKeyPrefix {
     ApiUrlAnnouncement = 0x38,
     EventLog = fedimint_eventlog::DB_KEY_PREFIX_EVENT_LOG,
     UnorderedEventLog = fedimint_eventlog::DB_KEY_PREFIX_UNORDERED_EVENT_LOG,
+    EventSinkCursor = 0x3b,
+    EventLogFollowers = fedimint_eventlog::DB_KEY_PREFIX_EVENT_LOG_FOLLOWERS,
 
     DatabaseVersion = fedimint_core::db::DbKeyPrefix::DatabaseVersion as u8,
     ClientBackup = fedimint_core::db::DbKeyPrefix::ClientBackup as u8,
@@ -0,0 +1,254 @@
+use std::fmt::Debug;
+#[cfg(not(target_family = "wasm"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use fedimint_core::core::ModuleKind;
+use fedimint_core::db::{Database, DatabaseKeyPrefix as _, IDatabaseTransactionOpsCoreTyped as _};
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::task::{MaybeSend, MaybeSync};
+use fedimint_core::util::{SafeUrl, backoff_util, retry};
+use fedimint_core::{apply, async_trait_maybe_send, impl_db_lookup, impl_db_record};
+use fedimint_eventlog::{DBTransactionEventLogExt as _, EventKind, EventLogId, PersistedLogEntry};
+use fedimint_logging::LOG_CLIENT_EVENT_LOG;
+use tokio::sync::watch;
+use tracing::{debug, trace, warn};
+
+use crate::db::DbKeyPrefix;
+
+/// Maximum number of events read from the event log at once
+const EVENT_SINK_BATCH_SIZE: u64 = 100;
+
+/// Position of the next event to be delivered to the event sink with the given
+/// name
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct EventSinkCursorKey(pub String);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct EventSinkCursorPrefix;
+
+impl_db_record!(
+    key = EventSinkCursorKey,
+    value = EventLogId,
+    db_prefix = DbKeyPrefix::EventSinkCursor,
+);
+impl_db_lookup!(
+    key = EventSinkCursorKey,
+    query_prefix = EventSinkCursorPrefix
+);
+
+/// Selects which events are forwarded to an event sink
+///
+/// An empty list of kinds or modules matches any kind or module respectively.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    kinds: Vec<EventKind>,
+    modules: Vec<ModuleKind>,
+}
+
+impl EventFilter {
+    /// Filter matching all events
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Also match events of `kind`
+    pub fn with_kind(mut self, kind: EventKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Also match events of `module`. Events not belonging to any module are
+    /// excluded once a module was added.
+    pub fn with_module(mut self, module: ModuleKind) -> Self {
+        self.modules.push(module);
+        self
+    }
+
+    pub fn matches(&self, entry: &PersistedLogEntry) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&entry.event_kind))
+            && (self.modules.is_empty()
+                || entry
+                    .module
+                    .as_ref()
+                    .is_some_and(|(module, _)| self.modules.contains(module)))
+    }
+}
+
+/// Destination persisted events are forwarded to, see
+/// [`crate::Client::spawn_event_sink`]
+///
+/// Delivery is at-least-once: an event whose delivery failed will be retried
+/// and one that was delivered right before a crash may be delivered again, so
+/// receivers should deduplicate using [`PersistedLogEntry::event_id`].
+#[apply(async_trait_maybe_send!)]
+pub trait IEventSink: Debug + MaybeSend + MaybeSync {
+    async fn deliver(&self, entry: &PersistedLogEntry) -> anyhow::Result<()>;
+}
+
+pub type DynEventSink = Arc<dyn IEventSink>;
+
+/// Posts every event as JSON to an HTTP endpoint, any non-success status code
+/// is treated as a failed delivery
+#[derive(Debug, Clone)]
+pub struct WebhookEventSink {
+    url: SafeUrl,
+    client: reqwest::Client,
+}
+
+impl WebhookEventSink {
+    pub fn new(url: SafeUrl) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[apply(async_trait_maybe_send!)]
+impl IEventSink for WebhookEventSink {
+    async fn deliver(&self, entry: &PersistedLogEntry) -> anyhow::Result<()> {
+        self.client
+            .post(self.url.clone().to_unsafe())
+            .json(entry)
+            .send()
+            .await
+            .context("Failed to send event to webhook")?
+            .error_for_status()
+            .context("Webhook rejected event")?;
+
+        Ok(())
+    }
+}
+
+/// Appends every event as a single line of JSON to a local file
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, Clone)]
+pub struct JsonlFileEventSink {
+    path: PathBuf,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl JsonlFileEventSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[apply(async_trait_maybe_send!)]
+impl IEventSink for JsonlFileEventSink {
+    async fn deliver(&self, entry: &PersistedLogEntry) -> anyhow::Result<()> {
+        use tokio::io::AsyncWriteExt as _;
+
+        let mut line = serde_json::to_vec(entry).expect("Can't fail");
+        line.push(b'\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+
+        Ok(())
+    }
+}
+
+/// Forwards all events matching `filter` to `sink`, retrying failed deliveries
+/// indefinitely. The position of the next event to deliver is persisted under
+/// `name`, so the sink resumes where it left off after a restart.
+pub(crate) async fn run_event_sink(
+    db: Database,
+    mut log_event_added: watch::Receiver<()>,
+    name: String,
+    filter: EventFilter,
+    sink: DynEventSink,
+) {
+    let cursor_key = EventSinkCursorKey(name);
+
+    let mut next_id = db
+        .begin_transaction_nc()
+        .await
+        .get_value(&cursor_key)
+        .await
+        .unwrap_or_default();
+
+    debug!(target: LOG_CLIENT_EVENT_LOG, name = %cursor_key.0, ?next_id, "Starting event sink");
+
+    // Register as follower, so events aren't pruned before being delivered
+    save_cursor(&db, &cursor_key, next_id).await;
+
+    loop {
+        // Mark the current state as seen before reading, so we can't miss
+        // events added in between
+        log_event_added.borrow_and_update();
+
+        let mut dbtx = db.begin_transaction_nc().await;
+        let entries = dbtx
+            .get_event_log(Some(next_id), EVENT_SINK_BATCH_SIZE)
+            .await;
+
+        if entries.is_empty() {
+            // A sink created after events were pruned starts with the oldest
+            // remaining one
+            let oldest_id = dbtx.get_oldest_event_log_id().await;
+            if let Some(oldest_id) = oldest_id.filter(|oldest_id| next_id < *oldest_id) {
+                warn!(target: LOG_CLIENT_EVENT_LOG, name = %cursor_key.0, ?next_id, ?oldest_id, "Skipping pruned events");
+                next_id = oldest_id;
+                continue;
+            }
+
+            if log_event_added.changed().await.is_err() {
+                break;
+            }
+            continue;
+        }
+
+        for entry in entries {
+            if filter.matches(&entry) {
+                retry(
+                    format!("Deliver event {:?} to {}", entry.event_id, cursor_key.0),
+                    backoff_util::background_backoff(),
+                    || sink.deliver(&entry),
+                )
+                .await
+                .expect("Retries forever");
+
+                trace!(target: LOG_CLIENT_EVENT_LOG, name = %cursor_key.0, id = ?entry.event_id, "Delivered event");
+
+                save_cursor(&db, &cursor_key, entry.event_id.saturating_add(1)).await;
+            }
+
+            next_id = entry.event_id.saturating_add(1);
+        }
+
+        // Skip over events that didn't match the filter
+        save_cursor(&db, &cursor_key, next_id).await;
+    }
+}
+
+async fn save_cursor(db: &Database, cursor_key: &EventSinkCursorKey, next_id: EventLogId) {
+    let mut dbtx = db.begin_transaction().await;
+    dbtx.insert_entry(cursor_key, &next_id).await;
+    dbtx.set_event_log_follower_cursor(cursor_key.to_bytes(), next_id)
+        .await;
+    dbtx.commit_tx().await;
+}
+
+/// Forgets the delivery progress of the event sink with the given name, which
+/// must not be running anymore, so it doesn't prevent pruning the event log
+pub(crate) async fn remove_event_sink(db: &Database, name: String) {
+    let cursor_key = EventSinkCursorKey(name);
+
+    let mut dbtx = db.begin_transaction().await;
+    dbtx.remove_entry(&cursor_key).await;
+    dbtx.remove_event_log_follower(cursor_key.to_bytes()).await;
+    dbtx.commit_tx().await;
+}
+
+#[cfg(test)]
+mod tests;
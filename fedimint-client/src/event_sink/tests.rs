@@ -0,0 +1,145 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fedimint_core::core::ModuleKind;
+use fedimint_core::db::mem_impl::MemDatabase;
+use fedimint_core::db::{IDatabaseTransactionOpsCoreTyped as _, IRawDatabaseExt as _};
+use fedimint_core::runtime::sleep;
+use fedimint_core::task::TaskGroup;
+use fedimint_core::{apply, async_trait_maybe_send};
+use fedimint_eventlog::{
+    DBTransactionEventLogExt as _, EventKind, EventLogId, PersistedLogEntry,
+    run_event_log_ordering_task,
+};
+use tokio::sync::{broadcast, watch};
+
+use super::{EventFilter, EventSinkCursorKey, IEventSink, run_event_sink};
+
+/// Records delivered events, failing the first delivery attempt
+#[derive(Debug, Default)]
+struct FlakySink {
+    attempts: Mutex<usize>,
+    delivered: Mutex<Vec<PersistedLogEntry>>,
+}
+
+#[apply(async_trait_maybe_send!)]
+impl IEventSink for FlakySink {
+    async fn deliver(&self, entry: &PersistedLogEntry) -> anyhow::Result<()> {
+        let attempt = {
+            let mut attempts = self.attempts.lock().expect("poisoned");
+            *attempts += 1;
+            *attempts
+        };
+
+        if attempt == 1 {
+            anyhow::bail!("Temporarily unavailable");
+        }
+
+        self.delivered.lock().expect("poisoned").push(entry.clone());
+
+        Ok(())
+    }
+}
+
+#[test]
+fn event_filter_matches() {
+    let entry = |kind: &'static str, module: Option<&'static str>| PersistedLogEntry {
+        event_id: EventLogId::LOG_START,
+        event_kind: EventKind::from_static(kind),
+        module: module.map(|module| (ModuleKind::from_static_str(module), 0)),
+        timestamp: 0,
+        value: serde_json::Value::Null,
+    };
+
+    assert!(EventFilter::all().matches(&entry("a", None)));
+
+    let filter = EventFilter::all()
+        .with_kind(EventKind::from_static("a"))
+        .with_module(ModuleKind::from_static_str("mint"));
+    assert!(filter.matches(&entry("a", Some("mint"))));
+    assert!(!filter.matches(&entry("a", None)));
+    assert!(!filter.matches(&entry("a", Some("wallet"))));
+    assert!(!filter.matches(&entry("b", Some("mint"))));
+}
+
+#[tokio::test]
+async fn event_sink_delivers_matching_events() {
+    let db = MemDatabase::new().into_database();
+    let tg = TaskGroup::new();
+
+    let (log_event_added_tx, log_event_added_rx) = watch::channel(());
+    let (log_ordering_wakeup_tx, log_ordering_wakeup_rx) = watch::channel(());
+    let (log_event_added_transient_tx, _log_event_added_transient_rx) = broadcast::channel(1024);
+
+    tg.spawn_cancellable(
+        "event log ordering task",
+        run_event_log_ordering_task(
+            db.clone(),
+            log_ordering_wakeup_rx,
+            log_event_added_tx,
+            log_event_added_transient_tx,
+        ),
+    );
+
+    let sink = Arc::new(FlakySink::default());
+
+    tg.spawn_cancellable(
+        "event sink",
+        run_event_sink(
+            db.clone(),
+            log_event_added_rx,
+            "test".to_string(),
+            EventFilter::all().with_kind(EventKind::from_static("wanted")),
+            sink.clone(),
+        ),
+    );
+
+    for kind in ["wanted", "other", "wanted", "other"] {
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.log_event_raw(
+            log_ordering_wakeup_tx.clone(),
+            EventKind::from_static(kind),
+            None,
+            None,
+            serde_json::to_vec(&kind).expect("Can't fail"),
+            true,
+        )
+        .await;
+        dbtx.commit_tx().await;
+    }
+
+    // Wait for all events to be processed, including the retry after the first
+    // failed delivery
+    loop {
+        let cursor = db
+            .begin_transaction_nc()
+            .await
+            .get_value(&EventSinkCursorKey("test".to_string()))
+            .await;
+
+        if cursor == Some(EventLogId::LOG_START.saturating_add(4)) {
+            break;
+        }
+
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let delivered = sink.delivered.lock().expect("poisoned").clone();
+    assert_eq!(
+        delivered
+            .iter()
+            .map(|entry| entry.event_id)
+            .collect::<Vec<_>>(),
+        vec![
+            EventLogId::LOG_START,
+            EventLogId::LOG_START.saturating_add(2)
+        ]
+    );
+    assert!(
+        delivered
+            .iter()
+            .all(|entry| entry.event_kind == EventKind::from_static("wanted"))
+    );
+
+    tg.shutdown_join_all(None).await.expect("Tasks shut down");
+}
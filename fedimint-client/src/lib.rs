@@ -87,6 +87,9 @@
 /// Database keys used by the client
 pub mod db;
 
+/// Forwarding of event log entries to external destinations
+pub mod event_sink;
+
 /// Management of meta fields
 pub mod meta;
 
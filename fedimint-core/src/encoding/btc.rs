@@ -265,6 +265,22 @@ fn consensus_decode_partial<D: std::io::Read>(
     }
 }
 
+impl Encodable for lightning::offers::invoice::Bolt12Invoice {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.encode().consensus_encode(writer)
+    }
+}
+
+impl Decodable for lightning::offers::invoice::Bolt12Invoice {
+    fn consensus_decode_partial<D: std::io::Read>(
+        d: &mut D,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        Self::try_from(Vec::<u8>::consensus_decode_partial(d, modules)?)
+            .map_err(|e| DecodeError(format_err!("Invalid Bolt12 invoice: {e:?}")))
+    }
+}
+
 impl Encodable for lightning_invoice::RoutingFees {
     fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), Error> {
         self.base_msat.consensus_encode(writer)?;
@@ -0,0 +1,551 @@
+//! Fountain-coded multi-part encoding of data too large for a single QR code.
+//!
+//! The message is split into equally sized fragments. The first frames carry
+//! one fragment each, every frame after that carries the XOR of a
+//! pseudo-randomly chosen subset of the fragments. The subset only depends on
+//! the frame's sequence number and the message checksum, so the receiver can
+//! reconstruct it. Since any frame can fill in for a missed one, a sender can
+//! loop over an endless sequence of frames until the receiver has recovered
+//! the whole message, no matter which frames it missed or scanned twice.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context as _, ensure};
+use bitcoin::hashes::{Hash as _, HashEngine as _, sha256};
+use thiserror::Error;
+
+use crate::base32;
+use crate::encoding::{Decodable, DecodeError, Encodable};
+use crate::module::registry::ModuleDecoderRegistry;
+
+/// Prefix of the string representation of a [`FountainFrame`]. Only uses
+/// characters of the QR code alphanumeric mode, just like the payload.
+const FRAME_PREFIX: &str = "FEDIMINTUR:";
+
+/// Upper bound on the number of fragments of a message we are willing to
+/// decode
+const MAX_FRAGMENTS: u32 = 1 << 16;
+
+/// One part of a fountain-coded message, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq, Encodable, Decodable)]
+pub struct FountainFrame {
+    /// Sequence number of the frame, starting at one
+    pub seq_num: u32,
+    /// Number of fragments the message is split into
+    pub seq_len: u32,
+    /// Length of the message in bytes
+    pub message_len: u32,
+    /// Checksum of the message, also seeds the choice of fragments
+    pub checksum: u32,
+    /// XOR of the fragments chosen for this frame
+    pub data: Vec<u8>,
+}
+
+impl FountainFrame {
+    /// The indices of the fragments combined in this frame
+    pub fn fragment_indices(&self) -> BTreeSet<usize> {
+        choose_fragments(self.seq_num, self.seq_len, self.checksum)
+    }
+}
+
+impl fmt::Display for FountainFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{FRAME_PREFIX}{}",
+            base32::encode(&self.consensus_encode_to_vec()).to_uppercase()
+        )
+    }
+}
+
+impl FromStr for FountainFrame {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let payload = s
+            .trim()
+            .strip_prefix(FRAME_PREFIX)
+            .context("Invalid prefix")?;
+
+        let frame = Self::consensus_decode_whole(
+            &base32::decode(&payload.to_lowercase())?,
+            &ModuleDecoderRegistry::default(),
+        )?;
+
+        Ok(frame)
+    }
+}
+
+/// Splits a message into an endless sequence of [`FountainFrame`]s
+#[derive(Debug, Clone)]
+pub struct FountainEncoder {
+    fragments: Vec<Vec<u8>>,
+    message_len: u32,
+    checksum: u32,
+    seq_num: u32,
+}
+
+impl FountainEncoder {
+    /// Splits `message` into fragments of at most `max_fragment_len` bytes,
+    /// fails if `max_fragment_len` is zero or the message would be split into
+    /// more fragments than a decoder accepts
+    pub fn new(message: &[u8], max_fragment_len: usize) -> Result<Self, FountainError> {
+        if max_fragment_len == 0 {
+            return Err(FountainError::EmptyFragments);
+        }
+
+        let seq_len = message.len().div_ceil(max_fragment_len).max(1);
+
+        if (MAX_FRAGMENTS as usize) < seq_len {
+            return Err(FountainError::TooManyFragments);
+        }
+
+        let fragment_len = message.len().div_ceil(seq_len);
+
+        let fragments = (0..seq_len)
+            .map(|index| {
+                let mut fragment = message
+                    .iter()
+                    .skip(index * fragment_len)
+                    .take(fragment_len)
+                    .copied()
+                    .collect::<Vec<u8>>();
+
+                fragment.resize(fragment_len, 0);
+
+                fragment
+            })
+            .collect();
+
+        Ok(Self {
+            fragments,
+            message_len: u32::try_from(message.len()).expect("Bounded by the number of fragments"),
+            checksum: checksum(message),
+            seq_num: 0,
+        })
+    }
+
+    /// Splits the consensus encoding of `value`, see [`FountainEncoder::new`]
+    pub fn from_encodable<T: Encodable>(
+        value: &T,
+        max_fragment_len: usize,
+    ) -> Result<Self, FountainError> {
+        Self::new(&value.consensus_encode_to_vec(), max_fragment_len)
+    }
+
+    /// The number of fragments the message was split into, which is the
+    /// minimum number of frames required to decode it
+    pub fn seq_len(&self) -> u32 {
+        self.fragments.len() as u32
+    }
+
+    /// Returns whether every fragment has been emitted on its own at least
+    /// once, frames emitted after that only add redundancy
+    pub fn is_complete(&self) -> bool {
+        self.seq_len() <= self.seq_num
+    }
+
+    /// Returns the next frame of the sequence
+    pub fn next_frame(&mut self) -> FountainFrame {
+        self.seq_num = self.seq_num.wrapping_add(1).max(1);
+
+        let mut data = vec![0; self.fragments[0].len()];
+
+        for index in choose_fragments(self.seq_num, self.seq_len(), self.checksum) {
+            xor_into(&mut data, &self.fragments[index]);
+        }
+
+        FountainFrame {
+            seq_num: self.seq_num,
+            seq_len: self.seq_len(),
+            message_len: self.message_len,
+            checksum: self.checksum,
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FountainError {
+    #[error("The fragment length has to be positive")]
+    EmptyFragments,
+    #[error("The message is too large for the fragment length")]
+    TooManyFragments,
+    #[error("The frame belongs to a different message")]
+    DifferentMessage,
+    #[error("The frame is malformed")]
+    MalformedFrame,
+    #[error("The decoded message does not match its checksum")]
+    ChecksumMismatch,
+}
+
+/// Recovers a message from [`FountainFrame`]s received in any order, ignoring
+/// frames that were received before
+#[derive(Debug, Clone, Default)]
+pub struct FountainDecoder {
+    params: Option<(u32, u32, u32, usize)>,
+    received: BTreeSet<u32>,
+    fragments: BTreeMap<usize, Vec<u8>>,
+    /// Frames combining multiple fragments we have not recovered yet
+    mixed: BTreeMap<BTreeSet<usize>, Vec<u8>>,
+    message: Option<Vec<u8>>,
+}
+
+impl FountainDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes a frame and returns whether the message has been recovered
+    pub fn receive(&mut self, frame: &FountainFrame) -> Result<bool, FountainError> {
+        let params = (
+            frame.seq_len,
+            frame.message_len,
+            frame.checksum,
+            frame.data.len(),
+        );
+
+        if frame.seq_num == 0 {
+            return Err(FountainError::MalformedFrame);
+        }
+
+        if let Some(expected) = self.params {
+            if expected != params {
+                return Err(FountainError::DifferentMessage);
+            }
+        } else {
+            let max_message_len = frame.seq_len as usize * frame.data.len();
+            let min_message_len = max_message_len.saturating_sub(frame.data.len()) + 1;
+
+            let is_well_formed = 0 < frame.seq_len
+                && frame.seq_len <= MAX_FRAGMENTS
+                && frame.message_len as usize <= max_message_len
+                && (frame.seq_len == 1 || min_message_len <= frame.message_len as usize);
+
+            if !is_well_formed {
+                return Err(FountainError::MalformedFrame);
+            }
+
+            self.params = Some(params);
+        }
+
+        if self.message.is_some() || !self.received.insert(frame.seq_num) {
+            return Ok(self.message.is_some());
+        }
+
+        self.add_part(frame.fragment_indices(), frame.data.clone());
+
+        if self.fragments.len() == frame.seq_len as usize {
+            let mut message = self
+                .fragments
+                .values()
+                .flatten()
+                .copied()
+                .collect::<Vec<u8>>();
+
+            message.truncate(frame.message_len as usize);
+
+            if checksum(&message) != frame.checksum {
+                return Err(FountainError::ChecksumMismatch);
+            }
+
+            self.message = Some(message);
+            self.mixed.clear();
+        }
+
+        Ok(self.message.is_some())
+    }
+
+    fn add_part(&mut self, indices: BTreeSet<usize>, data: Vec<u8>) {
+        let mut queue = vec![(indices, data)];
+
+        while let Some((mut indices, mut data)) = queue.pop() {
+            // remove the fragments we already know from the part
+            for index in indices.clone() {
+                if let Some(fragment) = self.fragments.get(&index) {
+                    xor_into(&mut data, fragment);
+                    indices.remove(&index);
+                }
+            }
+
+            match indices.len() {
+                0 => {}
+                1 => {
+                    let index = *indices.first().expect("Has one element");
+
+                    // reduce every mixed part containing the new fragment
+                    let reducible = self
+                        .mixed
+                        .keys()
+                        .filter(|mixed| mixed.contains(&index))
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    for mixed in reducible {
+                        let mixed_data = self.mixed.remove(&mixed).expect("Key exists");
+                        queue.push((mixed, mixed_data));
+                    }
+
+                    self.fragments.insert(index, data);
+                }
+                _ => {
+                    self.mixed.entry(indices).or_insert(data);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of fragments recovered so far and the total number
+    /// of fragments, if we received a frame yet
+    pub fn progress(&self) -> Option<(usize, u32)> {
+        self.params
+            .map(|(seq_len, ..)| (self.fragments.len(), seq_len))
+    }
+
+    /// Returns the message once it has been recovered
+    pub fn message(&self) -> Option<&[u8]> {
+        self.message.as_deref()
+    }
+
+    /// Decodes the recovered message as `T`, returns `None` if the message has
+    /// not been recovered yet
+    pub fn decode<T: Decodable>(
+        &self,
+        modules: &ModuleDecoderRegistry,
+    ) -> Option<Result<T, DecodeError>> {
+        self.message
+            .as_ref()
+            .map(|message| T::consensus_decode_whole(message, modules))
+    }
+}
+
+/// Recovers a message from the string representations of its frames
+pub fn decode_frames<'a>(frames: impl IntoIterator<Item = &'a str>) -> anyhow::Result<Vec<u8>> {
+    let mut decoder = FountainDecoder::new();
+
+    for frame in frames {
+        decoder.receive(&frame.parse()?)?;
+    }
+
+    let (recovered, seq_len) = decoder.progress().unwrap_or_default();
+
+    ensure!(
+        decoder.message().is_some(),
+        "Only recovered {recovered} of {seq_len} fragments, more frames are required"
+    );
+
+    Ok(decoder.message.expect("Checked above"))
+}
+
+fn checksum(message: &[u8]) -> u32 {
+    let hash = sha256::Hash::hash(message).to_byte_array();
+
+    u32::from_be_bytes(hash[..4].try_into().expect("Hash has 32 bytes"))
+}
+
+fn xor_into(data: &mut [u8], fragment: &[u8]) {
+    for (byte, other) in data.iter_mut().zip(fragment) {
+        *byte ^= other;
+    }
+}
+
+/// Chooses the fragments combined in the frame `seq_num`. The first `seq_len`
+/// frames carry a single fragment each, later frames combine a number of
+/// fragments drawn from the ideal soliton distribution, which makes small
+/// combinations that are easy to peel off more likely.
+fn choose_fragments(seq_num: u32, seq_len: u32, checksum: u32) -> BTreeSet<usize> {
+    if seq_num <= seq_len {
+        return BTreeSet::from([seq_num.saturating_sub(1) as usize]);
+    }
+
+    let mut rng = FragmentRng::new(seq_num, checksum);
+
+    let weights = (1..=u64::from(seq_len))
+        .map(|degree| u64::from(u32::MAX) / degree)
+        .collect::<Vec<u64>>();
+
+    let mut sample = rng.next_u64() % weights.iter().sum::<u64>();
+    let mut degree = weights.len();
+
+    for (index, weight) in weights.iter().enumerate() {
+        if sample < *weight {
+            degree = index + 1;
+            break;
+        }
+
+        sample -= weight;
+    }
+
+    // partial Fisher-Yates shuffle of the fragment indices
+    let mut indices = (0..seq_len as usize).collect::<Vec<usize>>();
+
+    for i in 0..degree {
+        let j = i + (rng.next_u64() % (indices.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+
+    indices.into_iter().take(degree).collect()
+}
+
+/// Deterministic pseudo-random numbers derived from a frame's sequence number
+/// and the message checksum
+struct FragmentRng {
+    seq_num: u32,
+    checksum: u32,
+    counter: u64,
+}
+
+impl FragmentRng {
+    fn new(seq_num: u32, checksum: u32) -> Self {
+        Self {
+            seq_num,
+            checksum,
+            counter: 0,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut engine = sha256::Hash::engine();
+
+        engine.input(&self.checksum.to_be_bytes());
+        engine.input(&self.seq_num.to_be_bytes());
+        engine.input(&self.counter.to_be_bytes());
+
+        self.counter += 1;
+
+        let hash = sha256::Hash::from_engine(engine).to_byte_array();
+
+        u64::from_be_bytes(hash[..8].try_into().expect("Hash has 32 bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    #[test]
+    fn decodes_from_systematic_frames() {
+        let message = message(1000);
+        let mut encoder = FountainEncoder::new(&message, 64).unwrap();
+        let mut decoder = FountainDecoder::new();
+
+        assert_eq!(encoder.seq_len(), 16);
+
+        while !encoder.is_complete() {
+            decoder.receive(&encoder.next_frame()).unwrap();
+        }
+
+        assert_eq!(decoder.message(), Some(message.as_slice()));
+    }
+
+    #[test]
+    fn tolerates_missing_and_repeated_frames() {
+        let message = message(1000);
+        let mut encoder = FountainEncoder::new(&message, 64).unwrap();
+        let mut decoder = FountainDecoder::new();
+        let mut frames = 0;
+
+        while decoder.message().is_none() {
+            let frame = encoder.next_frame();
+            frames += 1;
+
+            // drop every third frame and receive every other frame twice
+            if frame.seq_num.is_multiple_of(3) {
+                continue;
+            }
+
+            decoder.receive(&frame).unwrap();
+
+            if frame.seq_num.is_multiple_of(2) {
+                decoder.receive(&frame).unwrap();
+            }
+
+            assert!(frames < 1000, "Decoder should have converged by now");
+        }
+
+        assert_eq!(decoder.message(), Some(message.as_slice()));
+    }
+
+    #[test]
+    fn decodes_from_mixed_frames_only() {
+        let message = message(300);
+        let mut encoder = FountainEncoder::new(&message, 32).unwrap();
+        let mut decoder = FountainDecoder::new();
+
+        for _ in 0..encoder.seq_len() {
+            encoder.next_frame();
+        }
+
+        for _ in 0..1000 {
+            if decoder.receive(&encoder.next_frame()).unwrap() {
+                break;
+            }
+        }
+
+        assert_eq!(decoder.message(), Some(message.as_slice()));
+    }
+
+    #[test]
+    fn rejects_invalid_fragment_lengths() {
+        assert_eq!(
+            FountainEncoder::new(&message(100), 0).unwrap_err(),
+            FountainError::EmptyFragments
+        );
+
+        assert_eq!(
+            FountainEncoder::new(&message(MAX_FRAGMENTS as usize + 1), 1).unwrap_err(),
+            FountainError::TooManyFragments
+        );
+    }
+
+    #[test]
+    fn rejects_frames_of_other_messages() {
+        let mut decoder = FountainDecoder::new();
+
+        decoder
+            .receive(
+                &FountainEncoder::new(&message(100), 10)
+                    .unwrap()
+                    .next_frame(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            decoder.receive(
+                &FountainEncoder::new(&message(101), 10)
+                    .unwrap()
+                    .next_frame()
+            ),
+            Err(FountainError::DifferentMessage)
+        );
+    }
+
+    #[test]
+    fn frame_string_roundtrip() {
+        let message = message(100);
+        let mut encoder = FountainEncoder::new(&message, 30).unwrap();
+
+        let frames = (0..10)
+            .map(|_| encoder.next_frame().to_string())
+            .collect::<Vec<_>>();
+
+        assert!(frames.iter().all(|frame| {
+            frame
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase() || c == ':')
+        }));
+
+        assert_eq!(
+            decode_frames(frames.iter().rev().map(String::as_str)).unwrap(),
+            message
+        );
+
+        assert!(decode_frames(frames.iter().take(2).map(String::as_str)).is_err());
+    }
+}
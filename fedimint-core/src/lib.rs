@@ -83,6 +83,8 @@
 pub mod epoch;
 /// Formatting helpers
 pub mod fmt_utils;
+/// Fountain-coded multi-part encoding for QR codes
+pub mod fountain;
 /// Federation invite code
 pub mod invite_code;
 pub mod log;
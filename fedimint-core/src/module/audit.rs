@@ -112,6 +112,15 @@ pub fn from_audit(
             ),
         }
     }
+
+    /// Total liabilities in msat, i.e. the sum of the net assets of all modules
+    /// that owe more than they hold, such as the outstanding ecash of the mint
+    pub fn liabilities(&self) -> u64 {
+        self.module_summaries
+            .values()
+            .map(|summary| summary.net_assets.min(0).unsigned_abs())
+            .sum()
+    }
 }
 
 fn generate_module_summaries<'a>(
@@ -238,6 +247,7 @@ fn creates_audit_summary_from_audit() {
     };
 
     assert_eq!(audit_summary, expected_audit_summary);
+    assert_eq!(audit_summary.liabilities(), 50_000_000);
 }
 
 #[test]
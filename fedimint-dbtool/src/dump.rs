@@ -320,7 +320,8 @@ async fn write_serialized_consensus_range(
             server_db::DbKeyPrefix::Module
             | server_db::DbKeyPrefix::ServerInfo
             | server_db::DbKeyPrefix::DatabaseVersion
-            | server_db::DbKeyPrefix::ClientBackup => {}
+            | server_db::DbKeyPrefix::ClientBackup
+            | server_db::DbKeyPrefix::EventLog => {}
             server_db::DbKeyPrefix::ApiAnnouncements => {
                 push_db_pair_items_no_serde!(
                     dbtx,
@@ -12,34 +12,104 @@
 //! potentially emitting events of its own, and atomically updating persisted
 //! event log position ("cursor") of events that were already processed.
 use std::borrow::Cow;
+use std::fmt;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{
-    Database, DatabaseKey, DatabaseRecord, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped,
-    NonCommittable,
+    Database, DatabaseKey, DatabaseRecord, DatabaseTransaction, IDatabaseTransactionOpsCore as _,
+    IDatabaseTransactionOpsCoreTyped, NonCommittable,
 };
 use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::task::{MaybeSend, MaybeSync};
+use fedimint_core::util::FmtCompact as _;
 use fedimint_core::{Amount, apply, async_trait_maybe_send, impl_db_lookup, impl_db_record};
 use fedimint_logging::LOG_CLIENT_EVENT_LOG;
 use futures::{Future, StreamExt};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, watch};
-use tracing::{debug, trace};
-
-/// DB prefixes hardcoded for use of the event log
-/// `fedimint-eventlog` was extracted from `fedimint-client` to help
-/// include/re-use in other part of the code. But fundamentally its role
-/// is to implement event log in the client.
-/// There is currently no way to inject the prefixes to use for db records,
-/// so we use these constants to keep them in sync. Any other app that will
-/// want to store its own even log, will need to use the exact same prefixes,
-/// which in practice should not be a problem.
+use tracing::{debug, trace, warn};
+
+/// DB prefixes of the event log records, relative to its
+/// [`EventLogNamespace`]
+///
+/// In the [`EventLogNamespace::root`] namespace used by the client these are
+/// prefixes of the whole client database, so they must not be reused for
+/// anything else there.
 pub const DB_KEY_PREFIX_UNORDERED_EVENT_LOG: u8 = 0x3a;
 pub const DB_KEY_PREFIX_EVENT_LOG: u8 = 0x39;
+pub const DB_KEY_PREFIX_EVENT_LOG_FOLLOWERS: u8 = 0x3c;
+
+/// Maximum number of entries removed from the event log in a single database
+/// transaction by [`run_event_log_pruning_task`]
+const EVENT_LOG_PRUNE_BATCH_SIZE: u64 = 1000;
+
+/// Location of an event log inside a [`Database`]
+///
+/// All event log functions work on whatever database or transaction they are
+/// given, so an application can host several independent event logs in one
+/// database by isolating each of them, together with its followers' cursors,
+/// to a distinct namespace:
+///
+/// ```ignore
+/// let namespace = EventLogNamespace::new(vec![MY_EVENT_LOG_PREFIX]);
+///
+/// tg.spawn_cancellable(
+///     "event log ordering task",
+///     run_event_log_ordering_task(namespace.isolate_db(&db), ..),
+/// );
+///
+/// namespace
+///     .isolate_dbtx(&mut dbtx)
+///     .log_event(log_ordering_wakeup_tx, None, event)
+///     .await;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EventLogNamespace(Vec<u8>);
+
+impl EventLogNamespace {
+    /// Namespace storing the event log directly at the root of the database,
+    /// as done by the client
+    pub fn root() -> Self {
+        Self(vec![])
+    }
+
+    /// Namespace storing the event log below `prefix`, which must not be
+    /// used by any other records of the database
+    pub fn new(prefix: Vec<u8>) -> Self {
+        Self(prefix)
+    }
+
+    pub fn prefix(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn isolate_db(&self, db: &Database) -> Database {
+        if self.0.is_empty() {
+            db.clone()
+        } else {
+            db.with_prefix(self.0.clone())
+        }
+    }
+
+    pub fn isolate_dbtx<'a, 'tx, Cap>(
+        &self,
+        dbtx: &'a mut DatabaseTransaction<'tx, Cap>,
+    ) -> DatabaseTransaction<'a, Cap>
+    where
+        'tx: 'a,
+    {
+        if self.0.is_empty() {
+            dbtx.to_ref()
+        } else {
+            dbtx.to_ref_with_prefix(self.0.clone())
+        }
+    }
+}
 
 pub trait Event: serde::Serialize + serde::de::DeserializeOwned {
     const MODULE: Option<ModuleKind>;
@@ -125,6 +195,12 @@ pub const fn from_static(value: &'static str) -> Self {
     }
 }
 
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 impl<'s> From<&'s str> for EventKind {
     fn from(value: &'s str) -> Self {
         Self(Cow::Owned(value.to_owned()))
@@ -171,7 +247,7 @@ pub struct EventLogEntry {
 }
 
 /// Struct used for processing log entries after they have been persisted.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PersistedLogEntry {
     pub event_id: EventLogId,
     pub event_kind: EventKind,
@@ -210,6 +286,41 @@ pub struct PersistedLogEntry {
 
 impl_db_lookup!(key = EventLogId, query_prefix = EventLogIdPrefix);
 
+/// Position of the next event to be processed by a follower of the event log,
+/// identified by arbitrary bytes (usually the encoded key of its own cursor)
+///
+/// Entries at or after the lowest registered position are never pruned.
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct EventLogFollowerKey(pub Vec<u8>);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct EventLogFollowerPrefix;
+
+impl_db_record!(
+    key = EventLogFollowerKey,
+    value = EventLogId,
+    db_prefix = DB_KEY_PREFIX_EVENT_LOG_FOLLOWERS,
+);
+
+impl_db_lookup!(
+    key = EventLogFollowerKey,
+    query_prefix = EventLogFollowerPrefix
+);
+
+/// Which entries to keep when pruning the event log
+///
+/// An entry is kept if it matches any of the configured conditions, so with
+/// neither being set all entries may be pruned. Independently of that, the
+/// newest entry and all entries not yet processed by a registered follower are
+/// always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventLogRetention {
+    /// Keep the `n` newest entries
+    pub keep_last: Option<u64>,
+    /// Keep entries younger than the given duration
+    pub keep_newer_than: Option<Duration>,
+}
+
 #[apply(async_trait_maybe_send!)]
 pub trait DBTransactionEventLogExt {
     async fn log_event_raw(
@@ -257,6 +368,33 @@ async fn get_event_log(
         pos: Option<EventLogId>,
         limit: u64,
     ) -> Vec<PersistedLogEntry>;
+
+    /// [`EventLogId`] of the oldest entry that wasn't pruned yet, `None` if the
+    /// log is empty.
+    async fn get_oldest_event_log_id(&mut self) -> Option<EventLogId>;
+
+    /// Register or update the position of an event log follower, preventing
+    /// entries at or after `next_id` from being pruned.
+    ///
+    /// Followers using [`handle_events`] are registered automatically.
+    async fn set_event_log_follower_cursor(&mut self, follower: Vec<u8>, next_id: EventLogId);
+
+    /// Unregister a follower that won't process any events anymore, so it
+    /// doesn't prevent pruning.
+    async fn remove_event_log_follower(&mut self, follower: Vec<u8>);
+
+    /// Register the follower using [`handle_events`] with the encoded cursor
+    /// key `pos_key` at its persisted cursor, unless it is registered already.
+    ///
+    /// Followers only register themselves once they run, so this has to be
+    /// called for cursors persisted before followers were registered at all,
+    /// or by followers that are started later, before the event log is pruned
+    /// for the first time.
+    async fn register_persisted_event_log_follower(&mut self, pos_key: Vec<u8>);
+
+    /// Remove up to `limit` of the oldest entries of the event log that aren't
+    /// covered by `retention`, returning the number of removed entries.
+    async fn prune_event_log(&mut self, retention: EventLogRetention, limit: u64) -> u64;
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -332,6 +470,105 @@ async fn get_event_log(
             .collect()
             .await
     }
+
+    async fn get_oldest_event_log_id(&mut self) -> Option<EventLogId> {
+        self.find_by_prefix(&EventLogIdPrefixAll)
+            .await
+            .next()
+            .await
+            .map(|(k, _v)| k)
+    }
+
+    async fn set_event_log_follower_cursor(&mut self, follower: Vec<u8>, next_id: EventLogId) {
+        self.insert_entry(&EventLogFollowerKey(follower), &next_id)
+            .await;
+    }
+
+    async fn remove_event_log_follower(&mut self, follower: Vec<u8>) {
+        self.remove_entry(&EventLogFollowerKey(follower)).await;
+    }
+
+    async fn register_persisted_event_log_follower(&mut self, pos_key: Vec<u8>) {
+        if self
+            .get_value(&EventLogFollowerKey(pos_key.clone()))
+            .await
+            .is_some()
+        {
+            return;
+        }
+
+        // A follower that hasn't processed any events yet starts at the beginning
+        // of the event log, just like in `handle_events`
+        let next_id = match self.raw_get_bytes(&pos_key).await.expect("Database error") {
+            Some(bytes) => {
+                match EventLogId::consensus_decode_whole(&bytes, &ModuleDecoderRegistry::default())
+                {
+                    Ok(next_id) => next_id,
+                    Err(err) => {
+                        warn!(target: LOG_CLIENT_EVENT_LOG, err = %err.fmt_compact(), "Persisted event log cursor is invalid");
+                        return;
+                    }
+                }
+            }
+            None => EventLogId::default(),
+        };
+
+        self.set_event_log_follower_cursor(pos_key, next_id).await;
+    }
+
+    async fn prune_event_log(&mut self, retention: EventLogRetention, limit: u64) -> u64 {
+        let Some(oldest_id) = self.get_oldest_event_log_id().await else {
+            return 0;
+        };
+
+        // The newest entry must be kept since it determines the id of the next one
+        let next_id = self.get_next_event_log_id().await;
+        let mut prune_before = next_id.saturating_sub(1);
+
+        if let Some(keep_last) = retention.keep_last {
+            prune_before = prune_before.min(next_id.saturating_sub(keep_last));
+        }
+
+        if let Some(min_follower_id) = self
+            .find_by_prefix(&EventLogFollowerPrefix)
+            .await
+            .map(|(_k, v)| v)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .min()
+        {
+            prune_before = prune_before.min(min_follower_id);
+        }
+
+        let cutoff_usecs = retention.keep_newer_than.map(|keep_newer_than| {
+            u64::try_from(
+                fedimint_core::time::duration_since_epoch()
+                    .saturating_sub(keep_newer_than)
+                    .as_micros(),
+            )
+            .unwrap_or(u64::MAX)
+        });
+
+        let candidates = self
+            .find_by_range(oldest_id..prune_before.min(oldest_id.saturating_add(limit)))
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut pruned = 0;
+        for (id, entry) in candidates {
+            // Timestamps are not strictly monotonic, but close enough for retention
+            if cutoff_usecs.is_some_and(|cutoff_usecs| cutoff_usecs <= entry.ts_usecs) {
+                break;
+            }
+
+            self.remove_entry(&id).await;
+            pruned += 1;
+        }
+
+        pruned
+    }
 }
 
 /// The code that handles new unordered events and rewriters them fully ordered
@@ -425,6 +662,12 @@ pub async fn handle_events<F, R, K>(
 
     trace!(target: LOG_CLIENT_EVENT_LOG, ?next_key, "Handling events");
 
+    let follower = pos_key.to_bytes();
+    let mut dbtx = db.begin_transaction().await;
+    dbtx.set_event_log_follower_cursor(follower.clone(), next_key)
+        .await;
+    dbtx.commit_tx().await;
+
     loop {
         let mut dbtx = db.begin_transaction().await;
 
@@ -434,10 +677,21 @@ pub async fn handle_events<F, R, K>(
 
                 next_key = next_key.next();
                 dbtx.insert_entry(pos_key, &next_key).await;
+                dbtx.set_event_log_follower_cursor(follower.clone(), next_key)
+                    .await;
 
                 dbtx.commit_tx().await;
             }
             _ => {
+                // A follower starting after the events it would process next
+                // were pruned continues with the oldest remaining one
+                let oldest_id = dbtx.get_oldest_event_log_id().await;
+                if let Some(oldest_id) = oldest_id.filter(|oldest_id| next_key < *oldest_id) {
+                    warn!(target: LOG_CLIENT_EVENT_LOG, ?next_key, ?oldest_id, "Skipping pruned events");
+                    next_key = oldest_id;
+                    continue;
+                }
+
                 if log_event_added.changed().await.is_err() {
                     break Ok(());
                 }
@@ -446,6 +700,38 @@ pub async fn handle_events<F, R, K>(
     }
 }
 
+/// Periodically prunes the event log according to `retention`
+pub async fn run_event_log_pruning_task(
+    db: Database,
+    retention: EventLogRetention,
+    interval: Duration,
+) {
+    debug!(target: LOG_CLIENT_EVENT_LOG, ?retention, "Event log pruning task started");
+
+    loop {
+        loop {
+            let mut dbtx = db.begin_transaction().await;
+            let pruned = dbtx
+                .prune_event_log(retention, EVENT_LOG_PRUNE_BATCH_SIZE)
+                .await;
+
+            // Might conflict with followers registering, we'll just try again later
+            if let Err(err) = dbtx.commit_tx_result().await {
+                debug!(target: LOG_CLIENT_EVENT_LOG, err = %err, "Failed to prune event log");
+                break;
+            }
+
+            if pruned == 0 {
+                break;
+            }
+
+            debug!(target: LOG_CLIENT_EVENT_LOG, pruned, "Pruned event log");
+        }
+
+        fedimint_core::runtime::sleep(interval).await;
+    }
+}
+
 /// Filters the `PersistedLogEntries` by the `EventKind` and
 /// `ModuleKind`.
 pub fn filter_events_by_kind<'a, I>(
@@ -545,10 +831,13 @@ fn sort(&mut self) {
 mod tests {
     use std::sync::Arc;
     use std::sync::atomic::AtomicU8;
+    use std::time::Duration;
 
     use anyhow::bail;
-    use fedimint_core::db::IRawDatabaseExt as _;
     use fedimint_core::db::mem_impl::MemDatabase;
+    use fedimint_core::db::{
+        DatabaseKeyPrefix as _, IDatabaseTransactionOpsCoreTyped as _, IRawDatabaseExt as _,
+    };
     use fedimint_core::encoding::{Decodable, Encodable};
     use fedimint_core::impl_db_record;
     use fedimint_core::task::TaskGroup;
@@ -557,7 +846,8 @@ mod tests {
     use tracing::info;
 
     use super::{
-        DBTransactionEventLogExt as _, EventLogId, handle_events, run_event_log_ordering_task,
+        DBTransactionEventLogExt as _, EventLogId, EventLogNamespace, EventLogRetention,
+        handle_events, run_event_log_ordering_task,
     };
     use crate::EventKind;
 
@@ -634,4 +924,220 @@ async fn sanity_handle_events() {
             }
         );
     }
+
+    #[test_log::test(tokio::test)]
+    async fn namespaced_event_logs_are_independent() {
+        let db = MemDatabase::new().into_database();
+        let tg = TaskGroup::new();
+
+        let namespaces = [
+            EventLogNamespace::new(vec![0x50]),
+            EventLogNamespace::new(vec![0x51]),
+        ];
+
+        for (i, namespace) in namespaces.iter().enumerate() {
+            let (log_event_added_tx, mut log_event_added_rx) = watch::channel(());
+            let (log_ordering_wakeup_tx, log_ordering_wakeup_rx) = watch::channel(());
+            let (log_event_added_transient_tx, _log_event_added_transient_rx) =
+                broadcast::channel(1024);
+
+            tg.spawn_cancellable(
+                "event log ordering task",
+                run_event_log_ordering_task(
+                    namespace.isolate_db(&db),
+                    log_ordering_wakeup_rx,
+                    log_event_added_tx,
+                    log_event_added_transient_tx,
+                ),
+            );
+
+            // Log `i + 1` events into each namespace
+            for _ in 0..=i {
+                let mut dbtx = db.begin_transaction().await;
+                namespace
+                    .isolate_dbtx(&mut dbtx)
+                    .log_event_raw(
+                        log_ordering_wakeup_tx.clone(),
+                        EventKind::from(format!("{i}")),
+                        None,
+                        None,
+                        vec![],
+                        true,
+                    )
+                    .await;
+                dbtx.commit_tx().await;
+            }
+
+            while namespace
+                .isolate_db(&db)
+                .begin_transaction_nc()
+                .await
+                .get_next_event_log_id()
+                .await
+                != EventLogId::LOG_START.saturating_add(i as u64 + 1)
+            {
+                log_event_added_rx.changed().await.expect("Task running");
+            }
+        }
+
+        for (i, namespace) in namespaces.iter().enumerate() {
+            let events = namespace
+                .isolate_db(&db)
+                .begin_transaction_nc()
+                .await
+                .get_event_log(None, 10)
+                .await;
+
+            assert_eq!(events.len(), i + 1);
+            assert!(
+                events
+                    .iter()
+                    .all(|event| event.event_kind == EventKind::from(format!("{i}")))
+            );
+        }
+
+        assert!(
+            EventLogNamespace::root()
+                .isolate_db(&db)
+                .begin_transaction_nc()
+                .await
+                .get_event_log(None, 10)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn prune_event_log_respects_retention_and_followers() {
+        let db = MemDatabase::new().into_database();
+        let tg = TaskGroup::new();
+
+        let (log_event_added_tx, mut log_event_added_rx) = watch::channel(());
+        let (log_ordering_wakeup_tx, log_ordering_wakeup_rx) = watch::channel(());
+        let (log_event_added_transient_tx, _log_event_added_transient_rx) =
+            broadcast::channel(1024);
+
+        tg.spawn_cancellable(
+            "event log ordering task",
+            run_event_log_ordering_task(
+                db.clone(),
+                log_ordering_wakeup_rx,
+                log_event_added_tx,
+                log_event_added_transient_tx,
+            ),
+        );
+
+        for i in 0..10 {
+            let mut dbtx = db.begin_transaction().await;
+            dbtx.log_event_raw(
+                log_ordering_wakeup_tx.clone(),
+                EventKind::from(format!("{i}")),
+                None,
+                None,
+                vec![],
+                true,
+            )
+            .await;
+            dbtx.commit_tx().await;
+        }
+
+        while db
+            .begin_transaction_nc()
+            .await
+            .get_next_event_log_id()
+            .await
+            != EventLogId::LOG_START.saturating_add(10)
+        {
+            log_event_added_rx.changed().await.expect("Task running");
+        }
+
+        let prune = |retention| {
+            let db = db.clone();
+            async move {
+                let mut dbtx = db.begin_transaction().await;
+                let pruned = dbtx.prune_event_log(retention, 100).await;
+                dbtx.commit_tx().await;
+                pruned
+            }
+        };
+
+        let keep_last_two = EventLogRetention {
+            keep_last: Some(2),
+            keep_newer_than: None,
+        };
+
+        // A follower that has only processed the first 5 events blocks pruning
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.set_event_log_follower_cursor(vec![0], EventLogId::LOG_START.saturating_add(5))
+            .await;
+        dbtx.commit_tx().await;
+
+        assert_eq!(prune(keep_last_two).await, 5);
+        assert_eq!(prune(keep_last_two).await, 0);
+
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.remove_event_log_follower(vec![0]).await;
+        dbtx.commit_tx().await;
+
+        // A follower whose cursor was persisted before it registered itself blocks
+        // pruning once it is registered at startup
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.insert_entry(&TestLogIdKey, &EventLogId::LOG_START.saturating_add(6))
+            .await;
+        dbtx.register_persisted_event_log_follower(TestLogIdKey.to_bytes())
+            .await;
+        dbtx.commit_tx().await;
+
+        assert_eq!(prune(keep_last_two).await, 1);
+
+        // Registering again does not move the cursor of a registered follower back
+        // to the persisted one
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.set_event_log_follower_cursor(
+            TestLogIdKey.to_bytes(),
+            EventLogId::LOG_START.saturating_add(7),
+        )
+        .await;
+        dbtx.register_persisted_event_log_follower(TestLogIdKey.to_bytes())
+            .await;
+        dbtx.commit_tx().await;
+
+        assert_eq!(prune(keep_last_two).await, 1);
+
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.remove_event_log_follower(TestLogIdKey.to_bytes())
+            .await;
+        dbtx.commit_tx().await;
+
+        // All remaining events are newer than a day
+        assert_eq!(
+            prune(EventLogRetention {
+                keep_last: None,
+                keep_newer_than: Some(Duration::from_secs(60 * 60 * 24)),
+            })
+            .await,
+            0
+        );
+        assert_eq!(prune(keep_last_two).await, 1);
+
+        // The newest event is kept even without any retention
+        assert_eq!(
+            prune(EventLogRetention {
+                keep_last: None,
+                keep_newer_than: None,
+            })
+            .await,
+            1
+        );
+
+        let mut dbtx = db.begin_transaction_nc().await;
+        assert_eq!(
+            dbtx.get_oldest_event_log_id().await,
+            Some(EventLogId::LOG_START.saturating_add(9))
+        );
+        assert_eq!(
+            dbtx.get_next_event_log_id().await,
+            EventLogId::LOG_START.saturating_add(10)
+        );
+    }
 }
@@ -2,37 +2,40 @@
 
 mod envs;
 mod key;
+mod sweep;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
-use anyhow::anyhow;
-use bitcoin::OutPoint;
+use anyhow::{Context, anyhow, bail, ensure};
+use bitcoin::address::NetworkUnchecked;
 use bitcoin::network::Network;
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::{PublicKey, SECP256K1, SecretKey};
+use bitcoin::{Address, OutPoint};
 use clap::{ArgGroup, Parser, Subcommand};
 use fedimint_core::core::LEGACY_HARDCODED_INSTANCE_ID_WALLET;
 use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::epoch::ConsensusItem;
-use fedimint_core::fedimint_build_code_version_env;
 use fedimint_core::module::CommonModuleInit;
 use fedimint_core::module::registry::{ModuleDecoderRegistry, ModuleRegistry};
 use fedimint_core::session_outcome::SignedSessionOutcome;
 use fedimint_core::transaction::Transaction;
 use fedimint_core::util::handle_version_hash_command;
+use fedimint_core::{Feerate, PeerId, fedimint_build_code_version_env};
 use fedimint_logging::TracingSetup;
 use fedimint_rocksdb::RocksDbReadOnly;
 use fedimint_server::config::io::read_server_config;
 use fedimint_server::consensus::db::SignedSessionOutcomePrefix;
 use fedimint_server::core::ServerModule;
-use fedimint_wallet_server::common::config::WalletConfig;
+use fedimint_wallet_server::common::config::{WalletConfig, guardian_recovery_secret_key};
 use fedimint_wallet_server::common::keys::CompressedPublicKey;
 use fedimint_wallet_server::common::tweakable::Tweakable;
 use fedimint_wallet_server::common::{
     PegInDescriptor, SpendableUTXO, WalletCommonInit, WalletInput,
 };
 use fedimint_wallet_server::db::{UTXOKey, UTXOPrefixKey};
-use fedimint_wallet_server::{Wallet, nonce_from_idx};
+use fedimint_wallet_server::{Wallet, combine_shares, nonce_from_idx};
 use futures::stream::StreamExt;
 use hex::FromHex;
 use miniscript::{Descriptor, MiniscriptKey, TranslatePk, Translator};
@@ -47,28 +50,79 @@
 #[command(version)]
 #[command(group(
     ArgGroup::new("keysource")
-        .required(true)
         .args(["config", "descriptor"]),
 ))]
 struct RecoveryTool {
     /// Directory containing server config files
-    #[arg(long = "cfg")]
+    #[arg(long = "cfg", requires = "password")]
     config: Option<PathBuf>,
     /// The password that encrypts the configs
     #[arg(long, env = FM_PASSWORD_ENV, requires = "config")]
-    password: String,
+    password: Option<String>,
     /// Wallet descriptor, can be used instead of --cfg
     #[arg(long)]
     descriptor: Option<PegInDescriptor>,
     /// Wallet secret key, can be used instead of config together with
-    /// --descriptor
+    /// --descriptor. This may also be a designated key of the emergency
+    /// recovery path.
     #[arg(long, requires = "descriptor")]
     key: Option<SecretKey>,
+    /// Share of the FROST group key of a taproot federation as printed by the
+    /// share command, can be used instead of --key together with
+    /// --descriptor. The shares of a threshold of guardians combine into the
+    /// group key, which spends the wallet on its own.
+    #[arg(
+        long = "share",
+        requires = "descriptor",
+        conflicts_with = "key",
+        value_parser = share_parser
+    )]
+    shares: Vec<(PeerId, SecretKey)>,
     /// Network to operate on, has to be specified if --cfg isn't present
     #[arg(long, default_value = "bitcoin", requires = "descriptor")]
     network: Network,
     #[command(subcommand)]
-    strategy: TweakSource,
+    command: Command,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Derive(TweakSource),
+    /// Create a PSBT sweeping all confirmed UTXOs of the on-chain wallet to a
+    /// destination address, signed with this guardian's key only. The PSBTs
+    /// of a threshold of guardians can be merged using the combine command.
+    /// The PSBT of a taproot federation is signed with the group key combined
+    /// from --share and only has to be finalized by the combine command.
+    Sweep {
+        /// Extract UTXOs from a database without module partitioning
+        #[arg(long)]
+        legacy: bool,
+        /// Path to database
+        #[arg(long)]
+        db: PathBuf,
+        /// Address to send all funds to
+        #[arg(long)]
+        destination: Address<NetworkUnchecked>,
+        /// Fee rate of the sweep transaction in sats per vbyte
+        #[arg(long)]
+        fee_rate: u64,
+        /// Spend via the timelocked emergency recovery path, which requires
+        /// signatures of a threshold of recovery keys and UTXOs confirmed at
+        /// least the recovery delay ago
+        #[arg(long)]
+        recovery: bool,
+    },
+    /// Merge the PSBTs created by the sweep command of a threshold of guardians
+    /// into a transaction ready for broadcast. Does not require a key.
+    Combine {
+        /// Base64 encoded PSBTs
+        #[arg(required = true)]
+        psbts: Vec<Psbt>,
+    },
+    /// Print this guardian's share of the FROST group key of a taproot
+    /// federation together with the wallet descriptor. Requires --cfg.
+    Share,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -98,6 +152,14 @@ enum TweakSource {
     },
 }
 
+fn share_parser(share: &str) -> anyhow::Result<(PeerId, SecretKey)> {
+    let (peer, secret) = share
+        .split_once(':')
+        .context("shares have to be formatted as <peer>:<secret key>")?;
+
+    Ok((peer.parse()?, secret.parse()?))
+}
+
 fn tweak_parser(hex: &str) -> anyhow::Result<[u8; 33]> {
     <Vec<u8> as FromHex>::from_hex(hex)?
         .try_into()
@@ -121,8 +183,23 @@ async fn main() -> anyhow::Result<()> {
 
     let opts: RecoveryTool = RecoveryTool::parse();
 
+    if let Command::Combine { psbts } = &opts.command {
+        let tx = sweep::combine_sweep_psbts(psbts.clone())?;
+
+        let combined = CombinedSweep {
+            txid: tx.compute_txid(),
+            transaction: bitcoin::consensus::encode::serialize_hex(&tx),
+        };
+
+        serde_json::to_writer(std::io::stdout().lock(), &combined)
+            .expect("Could not encode to stdout");
+
+        return Ok(());
+    }
+
     let (base_descriptor, base_key, network) = if let Some(config) = opts.config {
-        let cfg = read_server_config(&opts.password, &config).expect("Could not read config file");
+        let password = opts.password.expect("Password is required by clap");
+        let cfg = read_server_config(&password, &config).expect("Could not read config file");
         let wallet_cfg: WalletConfig = cfg
             .get_module_config_typed(LEGACY_HARDCODED_INSTANCE_ID_WALLET)
             .expect("Malformed wallet config");
@@ -130,18 +207,106 @@ async fn main() -> anyhow::Result<()> {
         let base_key = wallet_cfg.private.peg_in_key;
         let network = wallet_cfg.consensus.network.0;
 
+        if let Command::Share = opts.command {
+            ensure!(
+                matches!(base_descriptor, Descriptor::Tr(_)),
+                "Only guardians of taproot federations hold a share of a group key"
+            );
+
+            let share = GuardianShare {
+                share: format!("{}:{}", cfg.local.identity, base_key.display_secret()),
+                descriptor: base_descriptor.to_string(),
+            };
+
+            serde_json::to_writer(std::io::stdout().lock(), &share)
+                .expect("Could not encode to stdout");
+
+            return Ok(());
+        }
+
+        // A share of a FROST group key can not spend on its own
+        ensure!(
+            !matches!(base_descriptor, Descriptor::Tr(_)),
+            "Taproot wallets are recovered with the shares of a threshold of guardians, \
+            print them with the share command and pass them with --share"
+        );
+
         (base_descriptor, base_key, network)
-    } else if let (Some(descriptor), Some(key)) = (opts.descriptor, opts.key) {
+    } else if let Some(descriptor) = opts.descriptor {
+        let key = match (opts.key, &descriptor) {
+            (Some(key), _) => key,
+            (None, Descriptor::Tr(tr)) if !opts.shares.is_empty() => {
+                combine_shares(&tr.internal_key().key, &opts.shares.into_iter().collect())?
+            }
+            _ => bail!("--descriptor requires --key, or --share for taproot descriptors"),
+        };
+
         (descriptor, key, opts.network)
     } else {
-        panic!("Either config or descriptor need to be provided by clap");
+        bail!("Either --cfg or --descriptor and --key have to be provided");
     };
 
-    process_and_print_tweak_source(&opts.strategy, &base_descriptor, &base_key, network).await;
+    match opts.command {
+        Command::Derive(tweak_source) => {
+            process_and_print_tweak_source(&tweak_source, &base_descriptor, &base_key, network)
+                .await;
+        }
+        Command::Sweep {
+            legacy,
+            db,
+            destination,
+            fee_rate,
+            recovery,
+        } => {
+            let destination = destination.require_network(network)?;
+            let utxos = get_utxos(&db, legacy).await;
+
+            let psbt = sweep::create_sweep_psbt(
+                &base_descriptor,
+                &base_key,
+                utxos,
+                destination.script_pubkey(),
+                Feerate {
+                    sats_per_kvb: fee_rate * 1000,
+                },
+                recovery,
+            )?;
+
+            let sweep = PartiallySignedSweep {
+                psbt: psbt.to_string(),
+                amount_sat: psbt.unsigned_tx.output[0].value,
+                fee_sat: psbt.fee()?,
+            };
+
+            serde_json::to_writer(std::io::stdout().lock(), &sweep)
+                .expect("Could not encode to stdout");
+        }
+        Command::Combine { .. } => unreachable!("Handled before reading keys"),
+        Command::Share => bail!("Printing a share requires --cfg"),
+    }
 
     Ok(())
 }
 
+/// Reads all confirmed UTXOs of the on-chain wallet from the database
+async fn get_utxos(db: &Path, legacy: bool) -> Vec<(UTXOKey, SpendableUTXO)> {
+    let db = get_db(db, ModuleRegistry::default()).await;
+
+    let db = if legacy {
+        db
+    } else {
+        db.with_prefix_module_id(LEGACY_HARDCODED_INSTANCE_ID_WALLET)
+            .0
+    };
+
+    db.begin_transaction_nc()
+        .await
+        .find_by_prefix(&UTXOPrefixKey)
+        .await
+        .collect()
+        .await
+}
+
 async fn process_and_print_tweak_source(
     tweak_source: &TweakSource,
     base_descriptor: &Descriptor<CompressedPublicKey>,
@@ -157,20 +322,9 @@ async fn process_and_print_tweak_source(
                 .expect("Could not encode to stdout");
         }
         TweakSource::Utxos { legacy, db } => {
-            let db = get_db(db, ModuleRegistry::default()).await;
-
-            let db = if *legacy {
-                db
-            } else {
-                db.with_prefix_module_id(LEGACY_HARDCODED_INSTANCE_ID_WALLET)
-                    .0
-            };
-
-            let utxos: Vec<ImportableWallet> = db
-                .begin_transaction_nc()
-                .await
-                .find_by_prefix(&UTXOPrefixKey)
+            let utxos: Vec<ImportableWallet> = get_utxos(db, *legacy)
                 .await
+                .into_iter()
                 .map(|(UTXOKey(outpoint), SpendableUTXO { tweak, amount })| {
                     let descriptor = tweak_descriptor(base_descriptor, base_key, &tweak, network);
 
@@ -180,8 +334,7 @@ async fn process_and_print_tweak_source(
                         amount_sat: amount,
                     }
                 })
-                .collect()
-                .await;
+                .collect();
 
             serde_json::to_writer(std::io::stdout().lock(), &utxos)
                 .expect("Could not encode to stdout");
@@ -292,18 +445,26 @@ fn tweak_descriptor(
     tweak: &[u8; 33],
     network: Network,
 ) -> Descriptor<Key> {
-    let secret_key = base_sk.tweak(tweak, SECP256K1);
-    let pub_key = CompressedPublicKey::new(PublicKey::from_secret_key_global(&secret_key));
+    // Our key might either be a guardian's peg-in key, which also implies its
+    // recovery key, or a designated recovery key
+    let keys = [*base_sk, guardian_recovery_secret_key(base_sk)]
+        .into_iter()
+        .map(|secret_key| {
+            let secret_key = secret_key.tweak(tweak, SECP256K1);
+            (
+                CompressedPublicKey::new(PublicKey::from_secret_key_global(&secret_key)),
+                bitcoin::key::PrivateKey {
+                    compressed: true,
+                    network: network.into(),
+                    inner: secret_key,
+                },
+            )
+        })
+        .collect();
+
     base_descriptor
         .tweak(tweak, SECP256K1)
-        .translate_pk(&mut SecretKeyInjector {
-            secret: bitcoin::key::PrivateKey {
-                compressed: true,
-                network: network.into(),
-                inner: secret_key,
-            },
-            public: pub_key,
-        })
+        .translate_pk(&mut SecretKeyInjector { keys })
         .expect("can't fail")
 }
 
@@ -322,20 +483,46 @@ struct ImportableWalletMin {
     descriptor: Descriptor<Key>,
 }
 
-/// Miniscript [`Translator`] that replaces a public key with a private key we
+/// A sweep of the on-chain wallet signed by a single guardian
+#[derive(Debug, Serialize)]
+struct PartiallySignedSweep {
+    /// Base64 encoded PSBT
+    psbt: String,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    amount_sat: bitcoin::Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    fee_sat: bitcoin::Amount,
+}
+
+/// A guardian's share of the FROST group key of a taproot federation
+#[derive(Debug, Serialize)]
+struct GuardianShare {
+    /// The share formatted as argument of --share
+    share: String,
+    /// The wallet descriptor, to be passed as --descriptor
+    descriptor: String,
+}
+
+/// A fully signed sweep of the on-chain wallet
+#[derive(Debug, Serialize)]
+struct CombinedSweep {
+    txid: bitcoin::Txid,
+    /// Hex encoded transaction
+    transaction: String,
+}
+
+/// Miniscript [`Translator`] that replaces public keys with private keys we
 /// know.
 #[derive(Debug)]
 struct SecretKeyInjector {
-    secret: bitcoin::key::PrivateKey,
-    public: CompressedPublicKey,
+    keys: BTreeMap<CompressedPublicKey, bitcoin::key::PrivateKey>,
 }
 
 impl Translator<CompressedPublicKey, Key, ()> for SecretKeyInjector {
     fn pk(&mut self, pk: &CompressedPublicKey) -> Result<Key, ()> {
-        if &self.public == pk {
-            Ok(Key::Private(self.secret))
-        } else {
-            Ok(Key::Public(*pk))
+        match self.keys.get(pk) {
+            Some(secret) => Ok(Key::Private(*secret)),
+            None => Ok(Key::Public(*pk)),
         }
     }
 
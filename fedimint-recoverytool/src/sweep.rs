@@ -0,0 +1,457 @@
+use anyhow::{Context, anyhow, ensure};
+use bitcoin::absolute::LockTime;
+use bitcoin::ecdsa::Signature as EcdsaSig;
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::psbt::{Input, Psbt};
+use bitcoin::secp256k1::{Keypair, Message, PublicKey, SECP256K1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+use fedimint_core::Feerate;
+use fedimint_wallet_server::common::config::{guardian_recovery_secret_key, recovery_delay};
+use fedimint_wallet_server::common::keys::CompressedPublicKey;
+use fedimint_wallet_server::common::tweakable::Tweakable;
+use fedimint_wallet_server::common::{PegInDescriptor, SpendableUTXO};
+use fedimint_wallet_server::db::UTXOKey;
+use miniscript::psbt::PsbtExt;
+use miniscript::{Descriptor, ForEachKey};
+
+/// Creates a PSBT spending all `utxos` to `destination` and signs every input
+/// with the tweaked `base_key`.
+///
+/// For a taproot descriptor `base_key` has to be the group secret key combined
+/// from the FROST shares of a threshold of guardians, which signs every input
+/// via the key path on its own.
+///
+/// If `recovery` is set the sweep spends via the timelocked emergency recovery
+/// path instead, so every input commits to the recovery delay as its relative
+/// timelock and is signed with the recovery key of a guardian's `base_key`
+/// or with `base_key` itself if it is a designated recovery key.
+///
+/// The unsigned transaction only depends on the UTXO set, the destination, the
+/// fee rate and the spending path, so guardians sweeping the same wallet
+/// independently produce PSBTs that can be combined afterwards.
+pub fn create_sweep_psbt(
+    base_descriptor: &PegInDescriptor,
+    base_key: &SecretKey,
+    mut utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    destination: ScriptBuf,
+    fee_rate: Feerate,
+    recovery: bool,
+) -> anyhow::Result<Psbt> {
+    ensure!(!utxos.is_empty(), "There are no UTXOs to sweep");
+
+    let is_descriptor_key = |secret_key: &SecretKey| {
+        let public_key = CompressedPublicKey::new(PublicKey::from_secret_key_global(secret_key));
+        base_descriptor.for_any_key(|key| *key == public_key)
+    };
+
+    let (signing_key, sequence) = if recovery {
+        let delay = recovery_delay(base_descriptor)
+            .context("The wallet descriptor has no emergency recovery path")?;
+
+        let recovery_key = guardian_recovery_secret_key(base_key);
+        let signing_key = if is_descriptor_key(&recovery_key) {
+            recovery_key
+        } else {
+            *base_key
+        };
+
+        (signing_key, Sequence::from_consensus(delay))
+    } else {
+        (*base_key, Sequence::ENABLE_RBF_NO_LOCKTIME)
+    };
+
+    ensure!(
+        is_descriptor_key(&signing_key),
+        "The secret key is not part of the wallet descriptor"
+    );
+
+    // Ensure deterministic ordering of inputs for all guardians
+    utxos.sort_by_key(|(utxo_key, _)| utxo_key.0);
+
+    let input_amount = utxos
+        .iter()
+        .map(|(_, utxo)| utxo.amount)
+        .sum::<bitcoin::Amount>();
+
+    let mut transaction = Transaction {
+        version: bitcoin::transaction::Version(2),
+        lock_time: LockTime::ZERO,
+        input: utxos
+            .iter()
+            .map(|(utxo_key, _)| TxIn {
+                previous_output: utxo_key.0,
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: input_amount,
+            script_pubkey: destination,
+        }],
+    };
+
+    let satisfaction_weight = base_descriptor
+        .max_weight_to_satisfy()
+        .context("Wallet descriptor is not satisfiable")?
+        .to_wu();
+    let total_weight = transaction.weight().to_wu() +
+        2 + // segwit marker and flag
+        satisfaction_weight * utxos.len() as u64;
+    let fees = fee_rate.calculate_fee(total_weight);
+
+    let dust_limit = transaction.output[0].script_pubkey.minimal_non_dust();
+    transaction.output[0].value = input_amount
+        .checked_sub(fees)
+        .filter(|value| *value >= dust_limit)
+        .ok_or_else(|| anyhow!("Fees of {fees} exceed the swept amount of {input_amount}"))?;
+
+    let mut psbt = Psbt::from_unsigned_tx(transaction).expect("Transaction is unsigned");
+
+    for (psbt_input, (_, utxo)) in psbt.inputs.iter_mut().zip(&utxos) {
+        let descriptor = base_descriptor.tweak(&utxo.tweak, SECP256K1);
+
+        *psbt_input = Input {
+            witness_utxo: Some(TxOut {
+                value: utxo.amount,
+                script_pubkey: descriptor.script_pubkey(),
+            }),
+            witness_script: match &descriptor {
+                Descriptor::Wsh(_) => Some(
+                    descriptor
+                        .explicit_script()
+                        .expect("Wsh descriptors have an explicit script"),
+                ),
+                _ => None,
+            },
+            tap_internal_key: match &descriptor {
+                Descriptor::Tr(tr) => Some(tr.internal_key().key.x_only_public_key().0),
+                _ => None,
+            },
+            ..Input::default()
+        };
+    }
+
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .map(|input| input.witness_utxo.clone().expect("Set above"))
+        .collect::<Vec<_>>();
+
+    let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+    for (idx, (psbt_input, (_, utxo))) in psbt.inputs.iter_mut().zip(&utxos).enumerate() {
+        let descriptor = base_descriptor.tweak(&utxo.tweak, SECP256K1);
+        let tweaked_secret = signing_key.tweak(&utxo.tweak, SECP256K1);
+
+        if matches!(descriptor, Descriptor::Tr(_)) {
+            let tx_hash = tx_hasher
+                .taproot_key_spend_signature_hash(
+                    idx,
+                    &Prevouts::All(&prevouts),
+                    TapSighashType::Default,
+                )
+                .expect("Failed to create taproot sighash");
+
+            // The tweaked secret is the secret of the internal key, the signing key
+            // commits to the taproot tweak on top of it
+            let keypair = Keypair::from_secret_key(SECP256K1, &tweaked_secret)
+                .tap_tweak(SECP256K1, None)
+                .to_inner();
+
+            psbt_input.tap_key_sig = Some(bitcoin::taproot::Signature {
+                signature: SECP256K1
+                    .sign_schnorr(&Message::from_digest(tx_hash.to_byte_array()), &keypair),
+                sighash_type: TapSighashType::Default,
+            });
+
+            continue;
+        }
+
+        let tx_hash = tx_hasher
+            .p2wsh_signature_hash(
+                idx,
+                &descriptor
+                    .script_code()
+                    .expect("Segwit v0 descriptors have a script code"),
+                utxo.amount,
+                EcdsaSighashType::All,
+            )
+            .expect("Failed to create segwit sighash");
+
+        let signature = SECP256K1.sign_ecdsa(
+            &Message::from_digest(tx_hash.to_byte_array()),
+            &tweaked_secret,
+        );
+
+        psbt_input.partial_sigs.insert(
+            bitcoin::PublicKey::new(PublicKey::from_secret_key_global(&tweaked_secret)),
+            EcdsaSig::sighash_all(signature),
+        );
+    }
+
+    Ok(psbt)
+}
+
+/// Merges the signatures of partially signed sweep PSBTs and extracts the
+/// final transaction, failing if less than a threshold of guardians signed.
+pub fn combine_sweep_psbts(psbts: Vec<Psbt>) -> anyhow::Result<Transaction> {
+    let mut psbts = psbts.into_iter();
+
+    let mut psbt = psbts.next().context("No PSBTs to combine")?;
+
+    for other in psbts {
+        psbt.combine(other)
+            .context("PSBTs do not spend the same transaction")?;
+    }
+
+    psbt.finalize_mut(SECP256K1).map_err(|errors| {
+        anyhow!(
+            "Failed to finalize PSBT, are there signatures of a threshold of guardians? {errors:?}"
+        )
+    })?;
+
+    Ok(psbt.extract_tx()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{SECP256K1, SecretKey};
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, Txid, WPubkeyHash};
+    use fedimint_core::Feerate;
+    use fedimint_wallet_server::common::config::guardian_recovery_key;
+    use fedimint_wallet_server::common::keys::CompressedPublicKey;
+    use fedimint_wallet_server::common::{PegInDescriptor, SpendableUTXO};
+    use fedimint_wallet_server::db::UTXOKey;
+    use miniscript::descriptor::Wsh;
+
+    use super::{combine_sweep_psbts, create_sweep_psbt};
+
+    #[test]
+    fn threshold_of_sweeps_combines_into_transaction() {
+        let keys = (0..4)
+            .map(|_| SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap())
+            .collect::<Vec<_>>();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                keys.iter()
+                    .map(|key| CompressedPublicKey::new(key.public_key(SECP256K1)))
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let utxos = (0..3)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::from_byte_array(rand::random()),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: core::array::from_fn(|_| rand::random::<u8>()),
+                        amount: bitcoin::Amount::from_sat(100_000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let destination = ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array(rand::random()));
+        let fee_rate = Feerate { sats_per_kvb: 2000 };
+
+        let psbts = keys
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| {
+                // Every guardian may have stored its UTXOs in a different order
+                let mut utxos = utxos.clone();
+                let len = utxos.len();
+                utxos.rotate_left(idx % len);
+
+                create_sweep_psbt(
+                    &descriptor,
+                    key,
+                    utxos,
+                    destination.clone(),
+                    fee_rate,
+                    false,
+                )
+                .expect("Failed to create sweep")
+            })
+            .collect::<Vec<_>>();
+
+        assert!(combine_sweep_psbts(psbts[..2].to_vec()).is_err());
+
+        let transaction =
+            combine_sweep_psbts(psbts[1..].to_vec()).expect("Failed to combine sweeps");
+
+        assert_eq!(transaction.input.len(), 3);
+        assert_eq!(transaction.output.len(), 1);
+        assert_eq!(transaction.output[0].script_pubkey, destination);
+        assert!(
+            transaction
+                .input
+                .iter()
+                .all(|input| !input.witness.is_empty())
+        );
+        assert!(transaction.output[0].value < bitcoin::Amount::from_sat(300_000));
+    }
+
+    #[test]
+    fn threshold_of_recovery_keys_sweeps_after_delay() {
+        let keys = (0..4)
+            .map(|_| SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap())
+            .collect::<Vec<_>>();
+
+        let public_keys = keys
+            .iter()
+            .map(|key| CompressedPublicKey::new(key.public_key(SECP256K1)))
+            .collect::<Vec<_>>();
+
+        let join = |keys: Vec<CompressedPublicKey>| {
+            keys.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let descriptor = PegInDescriptor::from_str(&format!(
+            "wsh(or_d(multi(3,{}),and_v(v:multi(2,{}),older(4320))))",
+            join(public_keys.clone()),
+            join(public_keys.iter().map(guardian_recovery_key).collect()),
+        ))
+        .unwrap();
+
+        let utxos = (0..2)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::from_byte_array(rand::random()),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: core::array::from_fn(|_| rand::random::<u8>()),
+                        amount: bitcoin::Amount::from_sat(100_000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let destination = ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array(rand::random()));
+        let fee_rate = Feerate { sats_per_kvb: 2000 };
+
+        let sweep = |key: &SecretKey, recovery: bool| {
+            create_sweep_psbt(
+                &descriptor,
+                key,
+                utxos.clone(),
+                destination.clone(),
+                fee_rate,
+                recovery,
+            )
+            .expect("Failed to create sweep")
+        };
+
+        // Two guardians can not spend via the multisig path
+        assert!(combine_sweep_psbts(vec![sweep(&keys[0], false), sweep(&keys[1], false)]).is_err());
+
+        let transaction = combine_sweep_psbts(vec![sweep(&keys[0], true), sweep(&keys[1], true)])
+            .expect("Failed to combine recovery sweeps");
+
+        assert_eq!(transaction.input.len(), 2);
+        assert!(transaction.input.iter().all(|input| {
+            input.sequence == Sequence::from_height(4320) && !input.witness.is_empty()
+        }));
+        assert_eq!(transaction.output[0].script_pubkey, destination);
+
+        // A descriptor without a recovery path can not be swept via it
+        let descriptor = PegInDescriptor::Wsh(Wsh::new_sortedmulti(3, public_keys).unwrap());
+        assert!(
+            create_sweep_psbt(&descriptor, &keys[0], utxos, destination, fee_rate, true).is_err()
+        );
+    }
+
+    #[test]
+    fn group_secret_sweeps_taproot_wallet() {
+        let group_secret = SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap();
+
+        let descriptor = PegInDescriptor::new_tr(
+            CompressedPublicKey::new(group_secret.public_key(SECP256K1)),
+            None,
+        )
+        .unwrap();
+
+        let utxos = (0..3)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::from_byte_array(rand::random()),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: core::array::from_fn(|_| rand::random::<u8>()),
+                        amount: bitcoin::Amount::from_sat(100_000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let destination = ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array(rand::random()));
+        let fee_rate = Feerate { sats_per_kvb: 2000 };
+
+        let psbt = create_sweep_psbt(
+            &descriptor,
+            &group_secret,
+            utxos.clone(),
+            destination.clone(),
+            fee_rate,
+            false,
+        )
+        .expect("Failed to create sweep");
+
+        let transaction = combine_sweep_psbts(vec![psbt]).expect("Failed to finalize sweep");
+
+        assert_eq!(transaction.input.len(), 3);
+        assert!(
+            transaction
+                .input
+                .iter()
+                .all(|input| input.witness.len() == 1)
+        );
+        assert_eq!(transaction.output[0].script_pubkey, destination);
+
+        // A single share of the group secret can not sign
+        let share = SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap();
+        assert!(
+            create_sweep_psbt(
+                &descriptor,
+                &share,
+                utxos.clone(),
+                destination.clone(),
+                fee_rate,
+                false
+            )
+            .is_err()
+        );
+
+        // Taproot descriptors have no recovery path
+        assert!(
+            create_sweep_psbt(
+                &descriptor,
+                &group_secret,
+                utxos,
+                destination,
+                fee_rate,
+                true
+            )
+            .is_err()
+        );
+    }
+}
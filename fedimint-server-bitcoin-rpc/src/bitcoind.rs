@@ -1,5 +1,5 @@
 use anyhow::{Context, anyhow};
-use bitcoin::{BlockHash, Network, Transaction};
+use bitcoin::{BlockHash, Network, Transaction, Txid};
 use bitcoincore_rpc::Error::JsonRpc;
 use bitcoincore_rpc::bitcoincore_rpc_json::EstimateMode;
 use bitcoincore_rpc::jsonrpc::Error::Rpc;
@@ -97,6 +97,15 @@ async fn submit_transaction(&self, transaction: Transaction) {
         }
     }
 
+    async fn is_tx_in_mempool(&self, txid: &Txid) -> anyhow::Result<bool> {
+        match block_in_place(|| self.client.get_mempool_entry(txid)) {
+            Ok(_) => Ok(true),
+            // Bitcoin core's RPC returns error code -5 if the transaction is not in the mempool
+            Err(JsonRpc(Rpc(e))) if e.code == -5 => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn get_sync_percentage(&self) -> anyhow::Result<Option<f64>> {
         Ok(Some(
             block_in_place(|| self.client.get_blockchain_info())?.verification_progress,
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use anyhow::{Context, bail};
-use bitcoin::{BlockHash, Network, Transaction};
+use bitcoin::{BlockHash, Network, Transaction, Txid};
 use fedimint_core::Feerate;
 use fedimint_core::envs::BitcoinRpcConfig;
 use fedimint_core::util::SafeUrl;
@@ -112,6 +112,14 @@ async fn submit_transaction(&self, transaction: Transaction) {
         });
     }
 
+    async fn is_tx_in_mempool(&self, txid: &Txid) -> anyhow::Result<bool> {
+        Ok(self
+            .client
+            .get_tx_info(txid)
+            .await?
+            .is_some_and(|tx| !tx.status.confirmed))
+    }
+
     async fn get_sync_percentage(&self) -> anyhow::Result<Option<f64>> {
         Ok(None)
     }
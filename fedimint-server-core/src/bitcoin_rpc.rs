@@ -4,7 +4,7 @@
 
 use anyhow::{Context, Result, ensure};
 use fedimint_core::Feerate;
-use fedimint_core::bitcoin::{Block, BlockHash, Network, Transaction};
+use fedimint_core::bitcoin::{Block, BlockHash, Network, Transaction, Txid};
 use fedimint_core::envs::BitcoinRpcConfig;
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::SafeUrl;
@@ -103,6 +103,15 @@ pub async fn submit_transaction(&self, tx: Transaction) {
             self.rpc.submit_transaction(tx).await;
         }
     }
+
+    pub async fn is_tx_in_mempool(&self, txid: &Txid) -> Result<bool> {
+        ensure!(
+            self.status_receiver.borrow().is_some(),
+            "Not connected to bitcoin backend"
+        );
+
+        self.rpc.is_tx_in_mempool(txid).await
+    }
 }
 
 pub type DynServerBitcoinRpc = Arc<dyn IServerBitcoinRpc>;
@@ -158,6 +167,10 @@ pub trait IServerBitcoinRpc: Debug + Send + Sync + 'static {
     /// when it makes sense.
     async fn submit_transaction(&self, transaction: Transaction);
 
+    /// Returns whether the transaction is unconfirmed but known to the
+    /// node's mempool
+    async fn is_tx_in_mempool(&self, txid: &Txid) -> Result<bool>;
+
     /// Returns the node's estimated chain sync percentage as a float between
     /// 0.0 and 1.0, or `None` if the node doesn't support this feature.
     async fn get_sync_percentage(&self) -> Result<Option<f64>>;
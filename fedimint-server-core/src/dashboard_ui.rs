@@ -9,6 +9,7 @@
 use fedimint_core::module::audit::AuditSummary;
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Feerate, PeerId};
+use fedimint_eventlog::PersistedLogEntry;
 
 use crate::{DynServerModule, ServerModule};
 
@@ -50,6 +51,9 @@ pub trait IDashboardApi {
     /// Get the status of the bitcoin backend
     async fn bitcoin_rpc_status(&self) -> Option<ServerBitcoinRpcStatus>;
 
+    /// Get the `limit` most recent entries of the guardian's event log
+    async fn latest_events(&self, limit: u64) -> Vec<PersistedLogEntry>;
+
     /// Get reference to a server module instance by module kind
     fn get_module_by_kind(&self, kind: ModuleKind) -> Option<&DynServerModule>;
 
@@ -0,0 +1,50 @@
+use fedimint_core::core::ModuleInstanceId;
+use fedimint_core::db::{DatabaseTransaction, GlobalDBTxAccessToken};
+use fedimint_eventlog::{DBTransactionEventLogExt as _, Event, EventLogNamespace};
+use tokio::sync::watch;
+
+/// Handle used by a server module to record events in the guardian's event log
+///
+/// Events are logged as part of the module's database transaction, so they
+/// only become visible if e.g. the input or consensus item they originate from
+/// was processed successfully.
+#[derive(Debug, Clone)]
+pub struct ServerModuleEventLogger {
+    module_instance_id: ModuleInstanceId,
+    global_dbtx_access_token: GlobalDBTxAccessToken,
+    namespace: EventLogNamespace,
+    log_ordering_wakeup_tx: watch::Sender<()>,
+}
+
+impl ServerModuleEventLogger {
+    pub fn new(
+        module_instance_id: ModuleInstanceId,
+        global_dbtx_access_token: GlobalDBTxAccessToken,
+        namespace: EventLogNamespace,
+        log_ordering_wakeup_tx: watch::Sender<()>,
+    ) -> Self {
+        Self {
+            module_instance_id,
+            global_dbtx_access_token,
+            namespace,
+            log_ordering_wakeup_tx,
+        }
+    }
+
+    /// Log `event` as part of the module's `dbtx`
+    pub async fn log_event<E>(&self, dbtx: &mut DatabaseTransaction<'_>, event: E)
+    where
+        E: Event + Send,
+    {
+        let mut global_dbtx = dbtx.global_dbtx(self.global_dbtx_access_token);
+
+        self.namespace
+            .isolate_dbtx(&mut global_dbtx)
+            .log_event(
+                self.log_ordering_wakeup_tx.clone(),
+                Some(self.module_instance_id),
+                event,
+            )
+            .await;
+    }
+}
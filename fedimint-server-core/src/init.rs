@@ -13,15 +13,18 @@
 };
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{Database, DatabaseVersion};
+use fedimint_core::module::audit::AuditSummary;
 use fedimint_core::module::{
     CommonModuleInit, CoreConsensusVersion, IDynCommonModuleInit, ModuleConsensusVersion,
     ModuleInit, SupportedModuleApiVersions,
 };
 use fedimint_core::task::TaskGroup;
 use fedimint_core::{NumPeers, PeerId, apply, async_trait_maybe_send, dyn_newtype_define};
+use tokio::sync::watch;
 
 use crate::bitcoin_rpc::ServerBitcoinRpcMonitor;
 use crate::config::PeerHandleOps;
+use crate::event_log::ServerModuleEventLogger;
 use crate::migration::{
     DynServerDbMigrationFn, ServerDbMigrationFnContext, ServerModuleDbMigrationContext,
     ServerModuleDbMigrationFn,
@@ -54,6 +57,9 @@ async fn init(
         our_peer_id: PeerId,
         module_api: DynModuleApi,
         server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+        event_logger: ServerModuleEventLogger,
+        session_count: watch::Receiver<u64>,
+        audit: watch::Receiver<Option<AuditSummary>>,
     ) -> anyhow::Result<DynServerModule>;
 
     fn validate_params(&self, params: &ConfigGenModuleParams) -> anyhow::Result<()>;
@@ -104,6 +110,9 @@ pub struct ServerModuleInitArgs<S>
     num_peers: NumPeers,
     module_api: DynModuleApi,
     server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+    event_logger: ServerModuleEventLogger,
+    session_count: watch::Receiver<u64>,
+    audit: watch::Receiver<Option<AuditSummary>>,
     // ClientModuleInitArgs needs a bound because sometimes we need
     // to pass associated-types data, so let's just put it here right away
     _marker: marker::PhantomData<S>,
@@ -140,6 +149,24 @@ pub fn module_api(&self) -> &DynModuleApi {
     pub fn server_bitcoin_rpc_monitor(&self) -> ServerBitcoinRpcMonitor {
         self.server_bitcoin_rpc_monitor.clone()
     }
+
+    pub fn event_logger(&self) -> ServerModuleEventLogger {
+        self.event_logger.clone()
+    }
+
+    /// The number of sessions our guardian has finished so far. Since guardians
+    /// may lag behind each other, modules must not use this value in consensus
+    /// logic directly but have to agree on it via consensus items first.
+    pub fn session_count(&self) -> watch::Receiver<u64> {
+        self.session_count.clone()
+    }
+
+    /// The audit of our guardian after the last processed consensus item,
+    /// `None` until the federation has been audited for the first time. Like
+    /// [`Self::session_count`] this must not be used in consensus logic.
+    pub fn audit(&self) -> watch::Receiver<Option<AuditSummary>> {
+        self.audit.clone()
+    }
 }
 /// Module Generation trait with associated types
 ///
@@ -244,6 +271,9 @@ async fn init(
         our_peer_id: PeerId,
         module_api: DynModuleApi,
         server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+        event_logger: ServerModuleEventLogger,
+        session_count: watch::Receiver<u64>,
+        audit: watch::Receiver<Option<AuditSummary>>,
     ) -> anyhow::Result<DynServerModule> {
         let module = <Self as ServerModuleInit>::init(
             self,
@@ -256,6 +286,9 @@ async fn init(
                 _marker: PhantomData,
                 module_api,
                 server_bitcoin_rpc_monitor,
+                event_logger,
+                session_count,
+                audit,
             },
         )
         .await?;
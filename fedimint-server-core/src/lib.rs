@@ -8,6 +8,7 @@
 pub mod bitcoin_rpc;
 pub mod config;
 pub mod dashboard_ui;
+pub mod event_log;
 mod init;
 pub mod migration;
 pub mod net;
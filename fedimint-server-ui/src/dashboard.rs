@@ -16,7 +16,7 @@
 use crate::assets::WithStaticRoutesExt as _;
 use crate::layout::{self};
 use crate::{
-    AuthState, LoginInput, audit, bitcoin, check_auth, general, invite, latency, lnv2,
+    AuthState, LoginInput, audit, bitcoin, check_auth, events, general, invite, latency, lnv2,
     login_form_response, login_submit_response, meta, wallet,
 };
 
@@ -80,6 +80,7 @@ async fn dashboard_view(
     let audit_summary = state.api.federation_audit().await;
     let bitcoin_rpc_url = state.api.bitcoin_rpc_url().await;
     let bitcoin_rpc_status = state.api.bitcoin_rpc_status().await;
+    let latest_events = state.api.latest_events(events::EVENTS_SHOWN).await;
 
     let content = html! {
         div class="row gy-4" {
@@ -108,6 +109,12 @@ async fn dashboard_view(
             }
         }
 
+        div class="row gy-4 mt-2" {
+            div class="col-12" {
+                (events::render(&latest_events))
+            }
+        }
+
         // Conditionally add Lightning V2 UI if the module is available
         @if let Some(lightning) = state.api.get_module::<fedimint_lnv2_server::Lightning>() {
             div class="row gy-4 mt-2" {
@@ -0,0 +1,52 @@
+use fedimint_eventlog::PersistedLogEntry;
+use maud::{Markup, html};
+
+/// Number of events shown on the dashboard
+pub const EVENTS_SHOWN: u64 = 20;
+
+// Function to render the guardian's most recent events, newest first
+pub fn render(events: &[PersistedLogEntry]) -> Markup {
+    html! {
+        div class="card h-100" {
+            div class="card-header dashboard-header" { "Recent Events" }
+            div class="card-body" {
+                @if events.is_empty() {
+                    div class="alert alert-secondary" { "No events recorded yet" }
+                } @else {
+                    table class="table table-striped table-sm" {
+                        thead {
+                            tr {
+                                th { "Time" }
+                                th { "Module" }
+                                th { "Event" }
+                                th { "Details" }
+                            }
+                        }
+                        tbody {
+                            @for event in events.iter().rev() {
+                                tr {
+                                    td { (format_timestamp(event.timestamp)) }
+                                    td {
+                                        @if let Some((kind, id)) = &event.module {
+                                            (format!("{kind} ({id})"))
+                                        } @else {
+                                            "-"
+                                        }
+                                    }
+                                    td { (event.event_kind) }
+                                    td class="text-break" { code { (event.value) } }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn format_timestamp(timestamp_micros: u64) -> String {
+    chrono::DateTime::from_timestamp_micros(timestamp_micros as i64)
+        .map(|dt| dt.to_rfc2822())
+        .unwrap_or("Invalid time".to_string())
+}
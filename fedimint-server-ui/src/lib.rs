@@ -3,6 +3,7 @@
 pub mod bitcoin;
 pub mod dashboard;
 pub(crate) mod error;
+pub mod events;
 pub mod general;
 pub mod invite;
 pub mod latency;
@@ -5,6 +5,7 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
     let network = wallet.network_ui();
     let consensus_block_count = wallet.consensus_block_count_ui().await;
     let consensus_fee_rate = wallet.consensus_feerate_ui().await;
+    let consolidation_fee_rate = wallet.consensus_consolidation_feerate_ui().await;
     let wallet_summary = wallet.get_wallet_summary_ui().await;
     let total_spendable = wallet_summary.total_spendable_balance().to_sat();
     let total_unsigned_change = wallet_summary.total_unsigned_change_balance().to_sat();
@@ -12,6 +13,7 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
     let total_available = total_spendable + total_unconfirmed_change + total_unsigned_change;
     let total_unsigned_outgoing = wallet_summary.total_unsigned_peg_out_balance().to_sat();
     let total_unconfirmed_outgoing = wallet_summary.total_unconfirmed_peg_out_balance().to_sat();
+    let total_cpfp_fees = wallet_summary.total_pending_cpfp_fees().to_sat();
 
     html! {
         div class="card h-100" {
@@ -30,10 +32,24 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         th { "Consensus Fee Rate" }
                         td { (consensus_fee_rate.sats_per_kvb) " sats/kvB" }
                     }
+                    tr {
+                        th { "Consolidation Fee Rate" }
+                        td {
+                            @if let Some(consolidation_fee_rate) = consolidation_fee_rate {
+                                (consolidation_fee_rate.sats_per_kvb) " sats/kvB"
+                            } @else {
+                                "Inactive"
+                            }
+                        }
+                    }
                     tr {
                         th { "Spendable Amount" }
                         td { (total_spendable) " sats" }
                     }
+                    tr {
+                        th { "Spendable UTXOs" }
+                        td { (wallet_summary.spendable_utxos.len()) }
+                    }
                     tr {
                         th { "Unsigned Change Amount" }
                         td { (total_unsigned_change) " sats" }
@@ -54,6 +70,10 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         th { "Unconfirmed Outgoing Amount" }
                         td { (total_unconfirmed_outgoing) " sats" }
                     }
+                    tr {
+                        th { "Pending CPFP Fees" }
+                        td { (total_cpfp_fees) " sats" }
+                    }
                 }
 
                 // Collapsible info section
@@ -67,6 +87,11 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                     div class="collapse" id="balanceInfo" {
                         div class="alert alert-info" {
                             dl class="row mb-0" {
+                                dt class="col-sm-3" { "Consolidation Fee Rate" }
+                                dd class="col-sm-9" {
+                                    "While the fee rate estimates of a threshold of guardians are below their FM_WALLET_CONSOLIDATION_FEERATE in sats/vB, the guardians consolidate small spendable UTXOs into a single one at that estimated fee rate to keep later pegouts cheap. The fees are paid out of the federation's fee income."
+                                }
+
                                 dt class="col-sm-3" { "Spendable Amount" }
                                 dd class="col-sm-9" { "UTXOs that are confirmed and are available to be spend by your users." }
 
@@ -86,6 +111,11 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                                     p class="mb-1" { strong { "Unsigned: " } "Pegout outputs from pegout transactions still waiting for guardian signatures." }
                                     p class="mb-0" { strong { "Unconfirmed: " } "Pegout outputs with threshold of signatures, waiting for blockchain confirmations." }
                                 }
+
+                                dt class="col-sm-3" { "Pending CPFP Fees" }
+                                dd class="col-sm-9" {
+                                    "Fees paid by child transactions that spend the change of stuck transactions to speed up their confirmation. Guardians vote for this automatically once the fee rate rose significantly."
+                                }
                             }
                         }
                     }
@@ -123,6 +153,45 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         }
                     }
 
+                    // CPFP Transactions Table
+                    @if !wallet_summary.cpfp_txs.is_empty() {
+                        div class="mb-4" {
+                            h5 { "CPFP Transactions" }
+                            div class="table-responsive" {
+                                table class="table table-sm" {
+                                    thead {
+                                        tr {
+                                            th { "Fee (sats)" }
+                                            th { "Stuck Transaction" }
+                                            th { "Child Transaction" }
+                                            th { "Status" }
+                                        }
+                                    }
+                                    tbody {
+                                        @for cpfp in &wallet_summary.cpfp_txs {
+                                            tr {
+                                                td { (cpfp.fee.to_sat()) }
+                                                td {
+                                                    a href={ "https://mempool.space/tx/" (cpfp.parent_txid) } class="btn btn-sm btn-outline-primary" target="_blank" {
+                                                        "mempool.space"
+                                                    }
+                                                }
+                                                td {
+                                                    a href={ "https://mempool.space/tx/" (cpfp.txid) } class="btn btn-sm btn-outline-primary" target="_blank" {
+                                                        "mempool.space"
+                                                    }
+                                                }
+                                                td {
+                                                    @if cpfp.signed { "Unconfirmed" } @else { "Unsigned" }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
                     // Pending Change UTXOs Table
                     @if !wallet_summary.unconfirmed_change_utxos.is_empty() {
                         div class="mb-4" {
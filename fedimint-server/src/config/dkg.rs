@@ -10,7 +10,7 @@
 use fedimint_core::net::peers::{DynP2PConnections, Recipient};
 use fedimint_core::{NumPeers, PeerId};
 use fedimint_logging::LOG_NET_PEER_DKG;
-use fedimint_server_core::config::{PeerHandleOps, g1, g2, scalar};
+use fedimint_server_core::config::{PeerHandleOps, eval_poly_scalar, g1, g2, scalar};
 use group::ff::Field;
 use rand::rngs::OsRng;
 use tracing::info;
@@ -201,15 +201,6 @@ pub async fn run_dkg(
     }
 }
 
-fn eval_poly_scalar(coefficients: &[Scalar], x: &Scalar) -> Scalar {
-    coefficients
-        .iter()
-        .copied()
-        .rev()
-        .reduce(|acc, coefficient| acc * x + coefficient)
-        .expect("We have at least one coefficient")
-}
-
 enum DkgStep {
     Broadcast(DkgMessage),
     Messages(Vec<(PeerId, DkgMessage)>),
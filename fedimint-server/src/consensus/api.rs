@@ -28,12 +28,12 @@
     API_ANNOUNCEMENTS_ENDPOINT, AUDIT_ENDPOINT, AUTH_ENDPOINT, AWAIT_SESSION_OUTCOME_ENDPOINT,
     AWAIT_SIGNED_SESSION_OUTCOME_ENDPOINT, AWAIT_TRANSACTION_ENDPOINT, BACKUP_ENDPOINT,
     BACKUP_STATISTICS_ENDPOINT, CLIENT_CONFIG_ENDPOINT, CLIENT_CONFIG_JSON_ENDPOINT,
-    CONSENSUS_ORD_LATENCY_ENDPOINT, FEDERATION_ID_ENDPOINT, FEDIMINTD_VERSION_ENDPOINT,
-    GUARDIAN_CONFIG_BACKUP_ENDPOINT, INVITE_CODE_ENDPOINT, P2P_CONNECTION_STATUS_ENDPOINT,
-    RECOVER_ENDPOINT, SERVER_CONFIG_CONSENSUS_HASH_ENDPOINT, SESSION_COUNT_ENDPOINT,
-    SESSION_STATUS_ENDPOINT, SESSION_STATUS_V2_ENDPOINT, SETUP_STATUS_ENDPOINT, SHUTDOWN_ENDPOINT,
-    SIGN_API_ANNOUNCEMENT_ENDPOINT, STATUS_ENDPOINT, SUBMIT_API_ANNOUNCEMENT_ENDPOINT,
-    SUBMIT_TRANSACTION_ENDPOINT, VERSION_ENDPOINT,
+    CONSENSUS_ORD_LATENCY_ENDPOINT, EVENT_LOG_ENDPOINT, FEDERATION_ID_ENDPOINT,
+    FEDIMINTD_VERSION_ENDPOINT, GUARDIAN_CONFIG_BACKUP_ENDPOINT, INVITE_CODE_ENDPOINT,
+    P2P_CONNECTION_STATUS_ENDPOINT, RECOVER_ENDPOINT, SERVER_CONFIG_CONSENSUS_HASH_ENDPOINT,
+    SESSION_COUNT_ENDPOINT, SESSION_STATUS_ENDPOINT, SESSION_STATUS_V2_ENDPOINT,
+    SETUP_STATUS_ENDPOINT, SHUTDOWN_ENDPOINT, SIGN_API_ANNOUNCEMENT_ENDPOINT, STATUS_ENDPOINT,
+    SUBMIT_API_ANNOUNCEMENT_ENDPOINT, SUBMIT_TRANSACTION_ENDPOINT, VERSION_ENDPOINT,
 };
 use fedimint_core::epoch::ConsensusItem;
 use fedimint_core::module::audit::{Audit, AuditSummary};
@@ -53,6 +53,7 @@
 };
 use fedimint_core::util::{FmtCompact, SafeUrl};
 use fedimint_core::{OutPoint, PeerId, TransactionId, secp256k1};
+use fedimint_eventlog::{DBTransactionEventLogExt as _, EventLogId, PersistedLogEntry};
 use fedimint_logging::LOG_NET_API;
 use fedimint_server_core::bitcoin_rpc::ServerBitcoinRpcMonitor;
 use fedimint_server_core::dashboard_ui::{IDashboardApi, ServerBitcoinRpcStatus};
@@ -69,11 +70,15 @@
 use crate::consensus::db::{AcceptedItemPrefix, AcceptedTransactionKey, SignedSessionOutcomeKey};
 use crate::consensus::engine::get_finished_session_count_static;
 use crate::consensus::transaction::{TxProcessingMode, process_transaction_with_dbtx};
+use crate::db::server_event_log_namespace;
 use crate::metrics::{BACKUP_WRITE_SIZE_BYTES, STORED_BACKUPS_COUNT};
 use crate::net::api::HasApiContext;
 use crate::net::api::announcement::{ApiAnnouncementKey, ApiAnnouncementPrefix};
 use crate::net::p2p::P2PStatusReceivers;
 
+/// Maximum number of event log entries returned by a single request
+const MAX_EVENT_LOG_PAGE_SIZE: u64 = 1000;
+
 #[derive(Clone)]
 pub struct ConsensusApi {
     /// Our server configuration
@@ -195,6 +200,20 @@ pub async fn session_count(&self) -> u64 {
         get_finished_session_count_static(&mut self.db.begin_transaction_nc().await).await
     }
 
+    /// Returns up to `limit` entries of the guardian's event log starting at
+    /// `pos`, or the latest ones if `pos` is `None`
+    pub async fn event_log(&self, pos: Option<EventLogId>, limit: u64) -> Vec<PersistedLogEntry> {
+        let mut dbtx = self.db.begin_transaction_nc().await;
+        let mut dbtx = server_event_log_namespace().isolate_dbtx(&mut dbtx);
+
+        let pos = match pos {
+            Some(pos) => pos,
+            None => dbtx.get_next_event_log_id().await.saturating_sub(limit),
+        };
+
+        dbtx.get_event_log(Some(pos), limit).await
+    }
+
     pub async fn await_signed_session_outcome(&self, index: u64) -> SignedSessionOutcome {
         self.db
             .wait_key_check(&SignedSessionOutcomeKey(index), std::convert::identity)
@@ -274,7 +293,7 @@ fn shutdown(&self, index: Option<u64>) {
         self.shutdown_sender.send_replace(index);
     }
 
-    async fn get_federation_audit(&self) -> ApiResult<AuditSummary> {
+    pub async fn get_federation_audit(&self) -> ApiResult<AuditSummary> {
         let mut dbtx = self.db.begin_transaction_nc().await;
         // Writes are related to compacting audit keys, which we can safely ignore
         // within an API request since the compaction will happen when constructing an
@@ -599,6 +618,10 @@ async fn bitcoin_rpc_status(&self) -> Option<ServerBitcoinRpcStatus> {
         self.bitcoin_rpc_connection.status()
     }
 
+    async fn latest_events(&self, limit: u64) -> Vec<PersistedLogEntry> {
+        self.event_log(None, limit).await
+    }
+
     fn get_module_by_kind(&self, kind: ModuleKind) -> Option<&DynServerModule> {
         self.modules
             .iter_modules()
@@ -848,6 +871,15 @@ pub fn server_endpoints() -> Vec<ApiEndpoint<ConsensusApi>> {
                 Ok(backup_statistics_static(&mut context.dbtx().into_nc()).await)
             }
         },
+        api_endpoint! {
+            EVENT_LOG_ENDPOINT,
+            ApiVersion::new(0, 5),
+            async |fedimint: &ConsensusApi, context, request: (Option<EventLogId>, u64)| -> Vec<PersistedLogEntry> {
+                check_auth(context)?;
+                let (pos, limit) = request;
+                Ok(fedimint.event_log(pos, limit.min(MAX_EVENT_LOG_PAGE_SIZE)).await)
+            }
+        },
     ]
 }
 
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -15,7 +15,7 @@
 use fedimint_core::encoding::Decodable;
 use fedimint_core::endpoint_constants::AWAIT_SIGNED_SESSION_OUTCOME_ENDPOINT;
 use fedimint_core::epoch::ConsensusItem;
-use fedimint_core::module::audit::Audit;
+use fedimint_core::module::audit::{Audit, AuditSummary};
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::module::{ApiRequestErased, SerdeModuleEncoding};
 use fedimint_core::net::peers::DynP2PConnections;
@@ -69,6 +69,8 @@ pub struct ConsensusEngine {
     pub connections: DynP2PConnections<P2PMessage>,
     pub ci_status_senders: BTreeMap<PeerId, watch::Sender<Option<u64>>>,
     pub ord_latency_sender: watch::Sender<Option<Duration>>,
+    pub session_count_sender: watch::Sender<u64>,
+    pub audit_sender: watch::Sender<Option<AuditSummary>>,
     pub task_group: TaskGroup,
     pub data_dir: PathBuf,
     pub checkpoint_retention: u64,
@@ -103,6 +105,8 @@ pub async fn run_single_guardian(&self, task_handle: TaskHandle) -> anyhow::Resu
 
             CONSENSUS_SESSION_COUNT.set(session_index as i64);
 
+            self.session_count_sender.send_replace(session_index);
+
             let mut item_index = self.pending_accepted_items().await.len() as u64;
 
             let session_start_time = std::time::Instant::now();
@@ -164,6 +168,8 @@ pub async fn run_consensus(&self, task_handle: TaskHandle) -> anyhow::Result<()>
 
             CONSENSUS_SESSION_COUNT.set(session_index as i64);
 
+            self.session_count_sender.send_replace(session_index);
+
             info!(target: LOG_CONSENSUS, session_index, "Starting consensus session");
 
             self.run_session(self.connections.clone(), session_index)
@@ -692,8 +698,11 @@ pub async fn process_consensus_item(
             "Processed consensus item"
         );
         let mut audit = Audit::default();
+        let mut module_instance_id_to_kind = HashMap::new();
 
         for (module_instance_id, kind, module) in self.modules.iter_modules() {
+            module_instance_id_to_kind.insert(module_instance_id, kind.as_str().to_string());
+
             let _module_audit_timing =
                 TimeReporter::new(format!("audit module {module_instance_id}")).level(Level::TRACE);
 
@@ -728,6 +737,11 @@ pub async fn process_consensus_item(
             .await
             .expect("Committing consensus epoch failed");
 
+        self.audit_sender.send_replace(Some(AuditSummary::from_audit(
+            &audit,
+            &module_instance_id_to_kind,
+        )));
+
         CONSENSUS_ITEMS_PROCESSED_TOTAL
             .with_label_values(&[&peer.to_usize().to_string()])
             .inc();
@@ -27,9 +27,11 @@
 use fedimint_core::net::peers::DynP2PConnections;
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::FmtCompactAnyhow as _;
+use fedimint_eventlog::run_event_log_ordering_task;
 use fedimint_logging::{LOG_CONSENSUS, LOG_CORE, LOG_NET_API, LOG_NET_IROH};
 use fedimint_server_core::bitcoin_rpc::{DynServerBitcoinRpc, ServerBitcoinRpcMonitor};
 use fedimint_server_core::dashboard_ui::IDashboardApi;
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::migration::apply_migrations_server_dbtx;
 use fedimint_server_core::{DynServerModule, ServerModuleInitRegistry};
 use futures::FutureExt;
@@ -38,13 +40,13 @@
 use jsonrpsee::RpcModule;
 use jsonrpsee::server::ServerHandle;
 use serde_json::Value;
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
 use tracing::{info, warn};
 
 use crate::config::{ServerConfig, ServerConfigLocal};
 use crate::consensus::api::{ConsensusApi, server_endpoints};
-use crate::consensus::engine::ConsensusEngine;
-use crate::db::verify_server_db_integrity_dbtx;
+use crate::consensus::engine::{ConsensusEngine, get_finished_session_count_static};
+use crate::db::{server_event_log_namespace, verify_server_db_integrity_dbtx};
 use crate::envs::{FM_DB_CHECKPOINT_RETENTION_DEFAULT, FM_DB_CHECKPOINT_RETENTION_ENV};
 use crate::net::api::announcement::get_api_urls;
 use crate::net::api::{ApiSecrets, HasApiContext};
@@ -111,6 +113,28 @@ pub async fn run(
         task_group,
     );
 
+    let (session_count_sender, session_count_receiver) = watch::channel(
+        get_finished_session_count_static(&mut db.begin_transaction_nc().await).await,
+    );
+
+    // Can only be populated once the modules have been initialized
+    let (audit_sender, audit_receiver) = watch::channel(None);
+
+    let event_log_namespace = server_event_log_namespace();
+    let (log_event_added_tx, _) = watch::channel(());
+    let (log_ordering_wakeup_tx, log_ordering_wakeup_rx) = watch::channel(());
+    let (log_event_added_transient_tx, _) = broadcast::channel(1024);
+
+    task_group.spawn_cancellable(
+        "event log ordering task",
+        run_event_log_ordering_task(
+            event_log_namespace.isolate_db(&db),
+            log_ordering_wakeup_rx,
+            log_event_added_tx,
+            log_event_added_transient_tx,
+        ),
+    );
+
     for (module_id, module_cfg) in &cfg.consensus.modules {
         match module_init_registry.get(&module_cfg.kind) {
             Some(module_init) => {
@@ -140,15 +164,25 @@ pub async fn run(
                 }
                 dbtx.commit_tx_result().await?;
 
+                let (module_db, global_dbtx_access_token) = db.with_prefix_module_id(*module_id);
+
                 let module = module_init
                     .init(
                         NumPeers::from(cfg.consensus.api_endpoints().len()),
                         cfg.get_module_config(*module_id)?,
-                        db.with_prefix_module_id(*module_id).0,
+                        module_db,
                         task_group,
                         cfg.local.identity,
                         global_api.with_module(*module_id),
                         server_bitcoin_rpc_monitor.clone(),
+                        ServerModuleEventLogger::new(
+                            *module_id,
+                            global_dbtx_access_token,
+                            event_log_namespace.clone(),
+                            log_ordering_wakeup_tx.clone(),
+                        ),
+                        session_count_receiver.clone(),
+                        audit_receiver.clone(),
                     )
                     .await?;
 
@@ -196,6 +230,8 @@ pub async fn run(
         code_version_str,
     };
 
+    audit_sender.send_replace(consensus_api.get_federation_audit().await.ok());
+
     info!(target: LOG_CONSENSUS, "Starting Consensus Api...");
 
     let api_handler = start_consensus_api(
@@ -256,6 +292,8 @@ pub async fn run(
         cfg: cfg.clone(),
         connections,
         ord_latency_sender,
+        session_count_sender,
+        audit_sender,
         ci_status_senders,
         submission_receiver,
         shutdown_receiver,
@@ -6,6 +6,7 @@
 };
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::impl_db_record;
+use fedimint_eventlog::EventLogNamespace;
 use futures::StreamExt as _;
 use strum::{EnumIter, IntoEnumIterator as _};
 
@@ -19,6 +20,8 @@ pub enum DbKeyPrefix {
     // TODO: do we want to split the server DB into consensus/non-consensus?
     ApiAnnouncements = 0x06,
     ServerInfo = 0x07,
+    /// Namespace of the guardian's event log, see [`server_event_log_namespace`]
+    EventLog = 0x08,
 
     DatabaseVersion = fedimint_core::db::DbKeyPrefix::DatabaseVersion as u8,
     ClientBackup = fedimint_core::db::DbKeyPrefix::ClientBackup as u8,
@@ -44,6 +47,12 @@ pub(crate) async fn verify_server_db_integrity_dbtx(dbtx: &mut DatabaseTransacti
     }
 }
 
+/// Namespace the guardian's event log is stored in, isolating it from the
+/// other server records
+pub fn server_event_log_namespace() -> EventLogNamespace {
+    EventLogNamespace::new(vec![DbKeyPrefix::EventLog as u8])
+}
+
 impl std::fmt::Display for DbKeyPrefix {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{self:?}")
@@ -502,6 +502,16 @@ async fn submit_transaction(&self, transaction: bitcoin::Transaction) {
         inner.pending = filtered.into_values().collect();
     }
 
+    async fn is_tx_in_mempool(&self, txid: &bitcoin::Txid) -> Result<bool> {
+        Ok(self
+            .inner
+            .read()
+            .unwrap()
+            .pending
+            .iter()
+            .any(|tx| &tx.compute_txid() == txid))
+    }
+
     async fn get_sync_percentage(&self) -> anyhow::Result<Option<f64>> {
         Ok(None)
     }
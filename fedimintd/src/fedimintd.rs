@@ -40,8 +40,9 @@
 use fedimint_unknown_server::UnknownInit;
 use fedimint_wallet_server::WalletInit;
 use fedimint_wallet_server::common::config::{
-    WalletGenParams, WalletGenParamsConsensus, WalletGenParamsLocal,
+    EmergencyRecoveryParams, WalletGenParams, WalletGenParamsConsensus, WalletGenParamsLocal,
 };
+use fedimint_wallet_server::common::envs::FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN_ENV;
 use futures::FutureExt;
 use tracing::{debug, error, info};
 
@@ -358,6 +359,8 @@ pub fn with_default_modules(self) -> anyhow::Result<Self> {
                         client_default_bitcoin_rpc: default_esplora_server(network),
                         fee_consensus:
                             fedimint_wallet_server::common::config::FeeConsensus::default(),
+                        taproot: is_env_var_set(FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN_ENV),
+                        recovery: EmergencyRecoveryParams::from_env()?,
                     },
                 },
             );
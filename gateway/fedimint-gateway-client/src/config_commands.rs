@@ -2,7 +2,10 @@
 use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
 use fedimint_gateway_client::GatewayRpcClient;
-use fedimint_gateway_common::{ConfigPayload, SetFeesPayload};
+use fedimint_gateway_common::{
+    ConfigPayload, PaymentLimits, SetFeesPayload, SetPaymentLimitsPayload,
+    SetRebalanceWeightPayload,
+};
 
 use crate::print_response;
 
@@ -35,6 +38,38 @@ pub enum ConfigCommands {
         #[clap(long)]
         tx_ppm: Option<u64>,
     },
+    /// Set the federation's target share of the gateway's ecash when
+    /// rebalancing between federations. Omitting the weight excludes the
+    /// federation from rebalancing.
+    SetRebalanceWeight {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        #[clap(long)]
+        weight: Option<u64>,
+    },
+    /// Set the limits on outgoing payments the gateway makes on behalf of the
+    /// federation. Omitted limits are not enforced.
+    SetPaymentLimits {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        /// Maximum amount of a single payment
+        #[clap(long)]
+        max_payment: Option<Amount>,
+
+        /// Maximum volume of payments within the last hour
+        #[clap(long)]
+        max_hourly_volume: Option<Amount>,
+
+        /// Maximum volume of payments within the last day
+        #[clap(long)]
+        max_daily_volume: Option<Amount>,
+
+        /// Maximum number of payments in flight at the same time
+        #[clap(long)]
+        max_in_flight: Option<u64>,
+    },
 }
 
 impl ConfigCommands {
@@ -80,6 +115,36 @@ pub async fn handle(
                     })
                     .await?;
             }
+            Self::SetRebalanceWeight {
+                federation_id,
+                weight,
+            } => {
+                create_client()
+                    .set_rebalance_weight(SetRebalanceWeightPayload {
+                        federation_id,
+                        rebalance_weight: weight,
+                    })
+                    .await?;
+            }
+            Self::SetPaymentLimits {
+                federation_id,
+                max_payment,
+                max_hourly_volume,
+                max_daily_volume,
+                max_in_flight,
+            } => {
+                create_client()
+                    .set_payment_limits(SetPaymentLimitsPayload {
+                        federation_id,
+                        limits: PaymentLimits {
+                            max_payment,
+                            max_hourly_volume,
+                            max_daily_volume,
+                            max_in_flight,
+                        },
+                    })
+                    .await?;
+            }
         }
 
         Ok(())
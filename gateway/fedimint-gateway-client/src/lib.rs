@@ -2,13 +2,14 @@
 use bitcoin::{Address, Txid};
 use fedimint_core::util::SafeUrl;
 use fedimint_gateway_common::{
-    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
-    CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    ChannelInfo, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConfigPayload,
-    ConnectFedPayload, CreateInvoiceForOperatorPayload, CreateOfferPayload, CreateOfferResponse,
-    DepositAddressPayload, DepositAddressRecheckPayload, FederationInfo, GATEWAY_INFO_ENDPOINT,
-    GATEWAY_INFO_POST_ENDPOINT, GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT,
+    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, API_TOKENS_ENDPOINT, ApiTokenInfo, BACKUP_ENDPOINT,
+    BackupPayload, CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
+    CREATE_API_TOKEN_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, ChannelInfo, CloseChannelsWithPeerRequest,
+    CloseChannelsWithPeerResponse, ConfigPayload, ConnectFedPayload, CreateApiTokenPayload,
+    CreateApiTokenResponse, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload, FederationInfo,
+    GATEWAY_INFO_ENDPOINT, GATEWAY_INFO_POST_ENDPOINT, GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT,
     GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GatewayBalances, GatewayFedConfig, GatewayInfo,
     GetInvoiceRequest, GetInvoiceResponse, LEAVE_FED_ENDPOINT, LIST_ACTIVE_CHANNELS_ENDPOINT,
     LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload, ListTransactionsPayload, ListTransactionsResponse,
@@ -16,9 +17,11 @@
     PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT,
     PAYMENT_SUMMARY_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload, PayOfferResponse,
     PaymentLogPayload, PaymentLogResponse, PaymentSummaryPayload, PaymentSummaryResponse,
-    RECEIVE_ECASH_ENDPOINT, ReceiveEcashPayload, ReceiveEcashResponse, SEND_ONCHAIN_ENDPOINT,
-    SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest, SetFeesPayload,
-    SpendEcashPayload, SpendEcashResponse, WITHDRAW_ENDPOINT, WithdrawPayload, WithdrawResponse,
+    RECEIVE_ECASH_ENDPOINT, REVOKE_API_TOKEN_ENDPOINT, ReceiveEcashPayload, ReceiveEcashResponse,
+    RevokeApiTokenPayload, SEND_ONCHAIN_ENDPOINT, SET_FEES_ENDPOINT, SET_PAYMENT_LIMITS_ENDPOINT,
+    SET_REBALANCE_WEIGHT_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest,
+    SetFeesPayload, SetPaymentLimitsPayload, SetRebalanceWeightPayload, SpendEcashPayload,
+    SpendEcashResponse, WITHDRAW_ENDPOINT, WithdrawPayload, WithdrawResponse,
 };
 use lightning_invoice::Bolt11Invoice;
 use reqwest::{Method, StatusCode};
@@ -131,6 +134,28 @@ pub async fn set_fees(&self, payload: SetFeesPayload) -> GatewayRpcResult<()> {
         self.call_post(url, payload).await
     }
 
+    pub async fn set_rebalance_weight(
+        &self,
+        payload: SetRebalanceWeightPayload,
+    ) -> GatewayRpcResult<()> {
+        let url = self
+            .base_url
+            .join(SET_REBALANCE_WEIGHT_ENDPOINT)
+            .expect("invalid base url");
+        self.call_post(url, payload).await
+    }
+
+    pub async fn set_payment_limits(
+        &self,
+        payload: SetPaymentLimitsPayload,
+    ) -> GatewayRpcResult<()> {
+        let url = self
+            .base_url
+            .join(SET_PAYMENT_LIMITS_ENDPOINT)
+            .expect("invalid base url");
+        self.call_post(url, payload).await
+    }
+
     pub async fn create_invoice_for_self(
         &self,
         payload: CreateInvoiceForOperatorPayload,
@@ -313,6 +338,33 @@ pub async fn pay_offer(&self, payload: PayOfferPayload) -> GatewayRpcResult<PayO
         self.call_post(url, payload).await
     }
 
+    pub async fn create_api_token(
+        &self,
+        payload: CreateApiTokenPayload,
+    ) -> GatewayRpcResult<CreateApiTokenResponse> {
+        let url = self
+            .base_url
+            .join(CREATE_API_TOKEN_ENDPOINT)
+            .expect("invalid base url");
+        self.call_post(url, payload).await
+    }
+
+    pub async fn list_api_tokens(&self) -> GatewayRpcResult<Vec<ApiTokenInfo>> {
+        let url = self
+            .base_url
+            .join(API_TOKENS_ENDPOINT)
+            .expect("invalid base url");
+        self.call_get(url).await
+    }
+
+    pub async fn revoke_api_token(&self, payload: RevokeApiTokenPayload) -> GatewayRpcResult<()> {
+        let url = self
+            .base_url
+            .join(REVOKE_API_TOKEN_ENDPOINT)
+            .expect("invalid base url");
+        self.call_post(url, payload).await
+    }
+
     async fn call<P: Serialize, T: DeserializeOwned>(
         &self,
         method: Method,
@@ -5,6 +5,7 @@
 mod general_commands;
 mod lightning_commands;
 mod onchain_commands;
+mod token_commands;
 
 use clap::{CommandFactory, Parser, Subcommand};
 use config_commands::ConfigCommands;
@@ -17,6 +18,7 @@
 use lightning_commands::LightningCommands;
 use onchain_commands::OnchainCommands;
 use serde::Serialize;
+use token_commands::TokenCommands;
 
 #[derive(Parser)]
 #[command(version)]
@@ -26,6 +28,8 @@ struct Cli {
     address: SafeUrl,
     #[command(subcommand)]
     command: Commands,
+    /// The gateway's password or an API token created with `token create`
+    ///
     /// WARNING: Passing in a password from the command line may be less secure!
     #[clap(long)]
     rpcpassword: Option<String>,
@@ -43,6 +47,8 @@ enum Commands {
     Onchain(OnchainCommands),
     #[command(subcommand)]
     Cfg(ConfigCommands),
+    #[command(subcommand)]
+    Token(TokenCommands),
     Completion {
         shell: clap_complete::Shell,
     },
@@ -62,6 +68,7 @@ async fn main() -> anyhow::Result<()> {
         Commands::Ecash(ecash_command) => ecash_command.handle(create_client).await?,
         Commands::Onchain(onchain_command) => onchain_command.handle(create_client).await?,
         Commands::Cfg(config_commands) => config_commands.handle(create_client).await?,
+        Commands::Token(token_commands) => token_commands.handle(create_client).await?,
         Commands::Completion { shell } => {
             clap_complete::generate(
                 shell,
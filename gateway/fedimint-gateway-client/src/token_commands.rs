@@ -0,0 +1,58 @@
+use clap::Subcommand;
+use fedimint_gateway_client::GatewayRpcClient;
+use fedimint_gateway_common::{ApiScope, CreateApiTokenPayload, RevokeApiTokenPayload};
+
+use crate::print_response;
+
+#[derive(Subcommand)]
+pub enum TokenCommands {
+    /// Create a named API token that grants access to the given scopes. The
+    /// token is only displayed once.
+    Create {
+        /// Unique name of the token
+        name: String,
+
+        /// Scope granted to the token, can be repeated
+        #[clap(long = "scope", required = true, value_enum)]
+        scopes: Vec<ApiScope>,
+    },
+    /// List the names and scopes of all API tokens
+    List,
+    /// Revoke the API token with the given name
+    Revoke {
+        /// Name of the token
+        name: String,
+    },
+}
+
+impl TokenCommands {
+    pub async fn handle(
+        self,
+        create_client: impl Fn() -> GatewayRpcClient + Send + Sync,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Create { name, scopes } => {
+                let response = create_client()
+                    .create_api_token(CreateApiTokenPayload {
+                        name,
+                        scopes: scopes.into_iter().collect(),
+                    })
+                    .await?;
+
+                print_response(response);
+            }
+            Self::List => {
+                let response = create_client().list_api_tokens().await?;
+
+                print_response(response);
+            }
+            Self::Revoke { name } => {
+                create_client()
+                    .revoke_api_token(RevokeApiTokenPayload { name })
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::{Duration, SystemTime};
 
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::hashes::sha256;
 use bitcoin::{Address, Network};
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use envs::{
     FM_LDK_ALIAS_ENV, FM_LDK_BITCOIND_RPC_URL, FM_LDK_ESPLORA_SERVER_URL, FM_LDK_NETWORK,
     FM_LND_MACAROON_ENV, FM_LND_RPC_ADDR_ENV, FM_LND_TLS_CERT_ENV, FM_PORT_LDK,
@@ -29,11 +29,13 @@
 
 pub const ADDRESS_ENDPOINT: &str = "/address";
 pub const ADDRESS_RECHECK_ENDPOINT: &str = "/address_recheck";
+pub const API_TOKENS_ENDPOINT: &str = "/api_tokens";
 pub const BACKUP_ENDPOINT: &str = "/backup";
 pub const CONFIGURATION_ENDPOINT: &str = "/config";
 pub const CONNECT_FED_ENDPOINT: &str = "/connect_fed";
 pub const CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt11_invoice_for_operator";
 pub const CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt12_offer_for_operator";
+pub const CREATE_API_TOKEN_ENDPOINT: &str = "/create_api_token";
 pub const GATEWAY_INFO_ENDPOINT: &str = "/info";
 pub const GATEWAY_INFO_POST_ENDPOINT: &str = "/info";
 pub const GET_BALANCES_ENDPOINT: &str = "/balances";
@@ -50,7 +52,10 @@
 pub const PAYMENT_LOG_ENDPOINT: &str = "/payment_log";
 pub const PAYMENT_SUMMARY_ENDPOINT: &str = "/payment_summary";
 pub const RECEIVE_ECASH_ENDPOINT: &str = "/receive_ecash";
+pub const REVOKE_API_TOKEN_ENDPOINT: &str = "/revoke_api_token";
 pub const SET_FEES_ENDPOINT: &str = "/set_fees";
+pub const SET_PAYMENT_LIMITS_ENDPOINT: &str = "/set_payment_limits";
+pub const SET_REBALANCE_WEIGHT_ENDPOINT: &str = "/set_rebalance_weight";
 pub const STOP_ENDPOINT: &str = "/stop";
 pub const SEND_ONCHAIN_ENDPOINT: &str = "/send_onchain";
 pub const SPEND_ECASH_ENDPOINT: &str = "/spend_ecash";
@@ -115,6 +120,11 @@ pub struct FederationConfig {
     pub lightning_fee: PaymentFee,
     pub transaction_fee: PaymentFee,
     pub connector: Connector,
+    /// Target share of the gateway's ecash held in this federation, relative to
+    /// the weights of all other federations that take part in rebalancing. If
+    /// `None`, the federation is excluded from rebalancing.
+    #[serde(default)]
+    pub rebalance_weight: Option<u64>,
 }
 
 /// Information about one of the feds we are connected to
@@ -124,6 +134,25 @@ pub struct FederationInfo {
     pub federation_name: Option<String>,
     pub balance_msat: Amount,
     pub config: FederationConfig,
+    #[serde(default)]
+    pub payment_limits: PaymentLimits,
+}
+
+/// Limits on the outgoing payments the gateway makes on behalf of a
+/// federation, capping the gateway's exposure to a misbehaving federation.
+/// Limits that are `None` are not enforced.
+#[derive(
+    Debug, Clone, Copy, Default, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize,
+)]
+pub struct PaymentLimits {
+    /// Maximum amount of a single outgoing payment
+    pub max_payment: Option<Amount>,
+    /// Maximum volume of outgoing payments within the last hour
+    pub max_hourly_volume: Option<Amount>,
+    /// Maximum volume of outgoing payments within the last day
+    pub max_daily_volume: Option<Amount>,
+    /// Maximum number of outgoing payments that are in flight at the same time
+    pub max_in_flight: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -165,6 +194,21 @@ pub struct SetFeesPayload {
     pub transaction_parts_per_million: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetRebalanceWeightPayload {
+    pub federation_id: FederationId,
+    /// The new rebalancing weight, `None` excludes the federation from
+    /// rebalancing
+    pub rebalance_weight: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetPaymentLimitsPayload {
+    pub federation_id: FederationId,
+    /// The new payment limits, replacing the previous ones
+    pub limits: PaymentLimits,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateInvoiceForOperatorPayload {
     pub amount_msats: u64,
@@ -410,6 +454,67 @@ pub enum PaymentStatus {
     Failed,
 }
 
+/// Permission granted to an API token, each scope unlocks a group of the
+/// gateway's authenticated endpoints. The gateway password always has access
+/// to every endpoint.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Encodable,
+    Decodable,
+    Serialize,
+    Deserialize,
+    ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// Read-only access to the gateway's info, balances, configuration and
+    /// payment history
+    Info,
+    /// Create invoices and offers, pay them and spend ecash
+    Payments,
+    /// Generate deposit addresses, withdraw and manage the on-chain wallet and
+    /// channels of the lightning node
+    Onchain,
+    /// Connect to and leave federations, back them up and set their fees
+    Federations,
+    /// Reveal the gateway's mnemonic
+    Mnemonic,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateApiTokenPayload {
+    /// Unique name of the token, used to identify it when revoking it
+    pub name: String,
+    pub scopes: BTreeSet<ApiScope>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateApiTokenResponse {
+    pub name: String,
+    /// The secret bearer token, it is only returned once and not stored by the
+    /// gateway
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeApiTokenPayload {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct ApiTokenInfo {
+    pub name: String,
+    pub scopes: BTreeSet<ApiScope>,
+    pub created_at_secs: u64,
+}
+
 #[derive(Debug, Clone, Subcommand, Serialize, Deserialize, Eq, PartialEq)]
 pub enum LightningMode {
     #[clap(name = "lnd")]
@@ -1,8 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use bitcoin::hashes::{Hash, sha256};
 use fedimint_api_client::api::net::Connector;
 use fedimint_core::config::FederationId;
+use fedimint_core::core::OperationId;
 use fedimint_core::db::{
     Database, DatabaseTransaction, DatabaseVersion, GeneralDbMigrationFn,
     GeneralDbMigrationFnContext, IDatabaseTransactionOpsCore, IDatabaseTransactionOpsCoreTyped,
@@ -12,7 +13,7 @@
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::{Amount, impl_db_lookup, impl_db_record, push_db_pair_items, secp256k1};
-use fedimint_gateway_common::FederationConfig;
+use fedimint_gateway_common::{ApiScope, FederationConfig, PaymentLimits};
 use fedimint_ln_common::serde_routing_fees;
 use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::PaymentFee;
@@ -27,6 +28,8 @@
 
 pub trait GatewayDbExt {
     fn get_client_database(&self, federation_id: &FederationId) -> Database;
+
+    fn get_rebalance_client_database(&self, federation_id: &FederationId) -> Database;
 }
 
 impl GatewayDbExt for Database {
@@ -35,6 +38,12 @@ fn get_client_database(&self, federation_id: &FederationId) -> Database {
         prefix.append(&mut federation_id.consensus_encode_to_vec());
         self.with_prefix(prefix)
     }
+
+    fn get_rebalance_client_database(&self, federation_id: &FederationId) -> Database {
+        let mut prefix = vec![DbKeyPrefix::RebalanceClientDatabase as u8];
+        prefix.append(&mut federation_id.consensus_encode_to_vec());
+        self.with_prefix(prefix)
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -87,6 +96,42 @@ async fn load_registered_incoming_contract(
         payment_image: PaymentImage,
     ) -> Option<RegisteredIncomingContract>;
 
+    /// Saves an API token under the hash of its secret.
+    async fn save_api_token(&mut self, token_hash: sha256::Hash, api_token: &ApiToken);
+
+    /// Returns the API token whose secret hashes to `token_hash`.
+    async fn load_api_token(&mut self, token_hash: sha256::Hash) -> Option<ApiToken>;
+
+    /// Returns all API tokens, indexed by the hash of their secret.
+    async fn load_api_tokens(&mut self) -> BTreeMap<sha256::Hash, ApiToken>;
+
+    /// Removes the API token with the given name, returning whether it existed.
+    async fn remove_api_token(&mut self, name: &str) -> bool;
+
+    async fn save_payment_limits(&mut self, federation_id: FederationId, limits: &PaymentLimits);
+
+    /// Returns the payment limits of the federation, which default to no limits
+    /// if none have been configured.
+    async fn load_payment_limits(&mut self, federation_id: FederationId) -> PaymentLimits;
+
+    async fn remove_payment_limits(&mut self, federation_id: FederationId);
+
+    async fn save_payment_volume(&mut self, federation_id: FederationId, volume: &PaymentVolume);
+
+    /// Returns the outgoing payments admitted for the federation within the
+    /// last day, which is empty if none have been admitted yet.
+    async fn load_payment_volume(&mut self, federation_id: FederationId) -> PaymentVolume;
+
+    async fn remove_payment_volume(&mut self, federation_id: FederationId);
+
+    async fn save_pending_rebalance(&mut self, pending: &PendingRebalance);
+
+    /// Returns the rebalancing transfer that has been sent but not yet been
+    /// received by its destination federation, if any.
+    async fn load_pending_rebalance(&mut self) -> Option<PendingRebalance>;
+
+    async fn remove_pending_rebalance(&mut self);
+
     /// Reads and serializes structures from the gateway's database for the
     /// purpose for serializing to JSON for inspection.
     async fn dump_database(
@@ -194,6 +239,84 @@ async fn load_registered_incoming_contract(
             .await
     }
 
+    async fn save_api_token(&mut self, token_hash: sha256::Hash, api_token: &ApiToken) {
+        self.insert_new_entry(&ApiTokenKey { token_hash }, api_token)
+            .await;
+    }
+
+    async fn load_api_token(&mut self, token_hash: sha256::Hash) -> Option<ApiToken> {
+        self.get_value(&ApiTokenKey { token_hash }).await
+    }
+
+    async fn load_api_tokens(&mut self) -> BTreeMap<sha256::Hash, ApiToken> {
+        self.find_by_prefix(&ApiTokenKeyPrefix)
+            .await
+            .map(|(key, api_token)| (key.token_hash, api_token))
+            .collect::<BTreeMap<_, _>>()
+            .await
+    }
+
+    async fn remove_api_token(&mut self, name: &str) -> bool {
+        let token_hashes = self
+            .load_api_tokens()
+            .await
+            .into_iter()
+            .filter(|(_, api_token)| api_token.name == name)
+            .map(|(token_hash, _)| token_hash)
+            .collect::<Vec<_>>();
+
+        for token_hash in &token_hashes {
+            self.remove_entry(&ApiTokenKey {
+                token_hash: *token_hash,
+            })
+            .await;
+        }
+
+        !token_hashes.is_empty()
+    }
+
+    async fn save_payment_limits(&mut self, federation_id: FederationId, limits: &PaymentLimits) {
+        self.insert_entry(&PaymentLimitsKey { federation_id }, limits)
+            .await;
+    }
+
+    async fn load_payment_limits(&mut self, federation_id: FederationId) -> PaymentLimits {
+        self.get_value(&PaymentLimitsKey { federation_id })
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn remove_payment_limits(&mut self, federation_id: FederationId) {
+        self.remove_entry(&PaymentLimitsKey { federation_id }).await;
+    }
+
+    async fn save_payment_volume(&mut self, federation_id: FederationId, volume: &PaymentVolume) {
+        self.insert_entry(&PaymentVolumeKey { federation_id }, volume)
+            .await;
+    }
+
+    async fn load_payment_volume(&mut self, federation_id: FederationId) -> PaymentVolume {
+        self.get_value(&PaymentVolumeKey { federation_id })
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn remove_payment_volume(&mut self, federation_id: FederationId) {
+        self.remove_entry(&PaymentVolumeKey { federation_id }).await;
+    }
+
+    async fn save_pending_rebalance(&mut self, pending: &PendingRebalance) {
+        self.insert_entry(&PendingRebalanceKey, pending).await;
+    }
+
+    async fn load_pending_rebalance(&mut self) -> Option<PendingRebalance> {
+        self.get_value(&PendingRebalanceKey).await
+    }
+
+    async fn remove_pending_rebalance(&mut self) {
+        self.remove_entry(&PendingRebalanceKey).await;
+    }
+
     async fn dump_database(
         &mut self,
         prefix_names: Vec<String>,
@@ -216,6 +339,41 @@ async fn dump_database(
                         "Federation Config"
                     );
                 }
+                DbKeyPrefix::ApiToken => {
+                    push_db_pair_items!(
+                        self,
+                        ApiTokenKeyPrefix,
+                        ApiTokenKey,
+                        ApiToken,
+                        gateway_items,
+                        "API Tokens"
+                    );
+                }
+                DbKeyPrefix::PaymentLimits => {
+                    push_db_pair_items!(
+                        self,
+                        PaymentLimitsKeyPrefix,
+                        PaymentLimitsKey,
+                        PaymentLimits,
+                        gateway_items,
+                        "Payment Limits"
+                    );
+                }
+                DbKeyPrefix::PaymentVolume => {
+                    push_db_pair_items!(
+                        self,
+                        PaymentVolumeKeyPrefix,
+                        PaymentVolumeKey,
+                        PaymentVolume,
+                        gateway_items,
+                        "Payment Volume"
+                    );
+                }
+                DbKeyPrefix::PendingRebalance => {
+                    if let Some(pending) = self.load_pending_rebalance().await {
+                        gateway_items.insert("Pending Rebalance".to_string(), Box::new(pending));
+                    }
+                }
                 DbKeyPrefix::GatewayPublicKey => {
                     if let Some(public_key) = self.load_gateway_keypair().await {
                         gateway_items
@@ -238,7 +396,12 @@ enum DbKeyPrefix {
     GatewayConfiguration = 0x07,
     PreimageAuthentication = 0x08,
     RegisteredIncomingContract = 0x09,
+    ApiToken = 0x0a,
+    PaymentLimits = 0x0b,
+    PendingRebalance = 0x0c,
+    PaymentVolume = 0x0d,
     ClientDatabase = 0x10,
+    RebalanceClientDatabase = 0x11,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -253,6 +416,9 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 #[derive(Debug, Encodable, Decodable)]
 struct FederationConfigKeyPrefixV1;
 
+#[derive(Debug, Encodable, Decodable)]
+struct FederationConfigKeyPrefixV2;
+
 #[derive(Debug, Encodable, Decodable)]
 struct FederationConfigKeyPrefix;
 
@@ -288,6 +454,23 @@ pub struct FederationConfigV1 {
     pub connector: Connector,
 }
 
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct FederationConfigKeyV2 {
+    id: FederationId,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct FederationConfigV2 {
+    pub invite_code: InviteCode,
+    // Unique integer identifier per-federation that is assigned when the gateways joins a
+    // federation.
+    #[serde(alias = "mint_channel_id")]
+    pub federation_index: u64,
+    pub lightning_fee: PaymentFee,
+    pub transaction_fee: PaymentFee,
+    pub connector: Connector,
+}
+
 #[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
 struct FederationConfigKey {
     id: FederationId,
@@ -305,6 +488,12 @@ struct FederationConfigKey {
     db_prefix = DbKeyPrefix::FederationConfig,
 );
 
+impl_db_record!(
+    key = FederationConfigKeyV2,
+    value = FederationConfigV2,
+    db_prefix = DbKeyPrefix::FederationConfig,
+);
+
 impl_db_record!(
     key = FederationConfigKey,
     value = FederationConfig,
@@ -319,6 +508,10 @@ struct FederationConfigKey {
     key = FederationConfigKeyV1,
     query_prefix = FederationConfigKeyPrefixV1
 );
+impl_db_lookup!(
+    key = FederationConfigKeyV2,
+    query_prefix = FederationConfigKeyPrefixV2
+);
 impl_db_lookup!(
     key = FederationConfigKey,
     query_prefix = FederationConfigKeyPrefix
@@ -428,6 +621,10 @@ pub fn get_gatewayd_database_migrations() -> BTreeMap<DatabaseVersion, GeneralDb
         DatabaseVersion(4),
         Box::new(|ctx| migrate_to_v5(ctx).boxed()),
     );
+    migrations.insert(
+        DatabaseVersion(5),
+        Box::new(|ctx| migrate_to_v6(ctx).boxed()),
+    );
     migrations
 }
 
@@ -518,14 +715,14 @@ async fn migrate_to_v4(mut ctx: GeneralDbMigrationFnContext<'_>) -> Result<(), a
         .await;
     for (fed_id, _old_config) in configs {
         if let Some(old_federation_config) = dbtx.remove_entry(&fed_id).await {
-            let new_fed_config = FederationConfig {
+            let new_fed_config = FederationConfigV2 {
                 invite_code: old_federation_config.invite_code,
                 federation_index: old_federation_config.federation_index,
                 lightning_fee: old_federation_config.fees.into(),
                 transaction_fee: PaymentFee::TRANSACTION_FEE_DEFAULT,
                 connector: Connector::default(),
             };
-            let new_key = FederationConfigKey { id: fed_id.id };
+            let new_key = FederationConfigKeyV2 { id: fed_id.id };
             dbtx.insert_new_entry(&new_key, &new_fed_config).await;
         }
     }
@@ -566,7 +763,7 @@ async fn migrate_federation_configs(
                 &problem_key[1..33],
                 &ModuleDecoderRegistry::default(),
             ) {
-                if let Ok(federation_config) = FederationConfig::consensus_decode_whole(
+                if let Ok(federation_config) = FederationConfigV2::consensus_decode_whole(
                     &value,
                     &ModuleDecoderRegistry::default(),
                 ) {
@@ -586,7 +783,7 @@ async fn migrate_federation_configs(
     // Migrate all entries of the isolated databases that don't overlap with
     // `FederationConfig` entries.
     let fed_ids = dbtx
-        .find_by_prefix(&FederationConfigKeyPrefix)
+        .find_by_prefix(&FederationConfigKeyPrefixV2)
         .await
         .collect::<BTreeMap<_, _>>()
         .await;
@@ -608,6 +805,38 @@ async fn migrate_federation_configs(
     Ok(())
 }
 
+/// Introduced in v0.8, adds the optional rebalancing weight to each
+/// `FederationConfig`. Existing federations do not take part in rebalancing.
+async fn migrate_to_v6(mut ctx: GeneralDbMigrationFnContext<'_>) -> Result<(), anyhow::Error> {
+    let mut dbtx = ctx.dbtx();
+    migrate_rebalance_weights(&mut dbtx).await
+}
+
+async fn migrate_rebalance_weights(
+    dbtx: &mut DatabaseTransaction<'_>,
+) -> Result<(), anyhow::Error> {
+    let configs = dbtx
+        .find_by_prefix(&FederationConfigKeyPrefixV2)
+        .await
+        .collect::<Vec<_>>()
+        .await;
+    for (fed_id, _old_config) in configs {
+        if let Some(old_federation_config) = dbtx.remove_entry(&fed_id).await {
+            let new_fed_config = FederationConfig {
+                invite_code: old_federation_config.invite_code,
+                federation_index: old_federation_config.federation_index,
+                lightning_fee: old_federation_config.lightning_fee,
+                transaction_fee: old_federation_config.transaction_fee,
+                connector: old_federation_config.connector,
+                rebalance_weight: None,
+            };
+            let new_key = FederationConfigKey { id: fed_id.id };
+            dbtx.insert_new_entry(&new_key, &new_fed_config).await;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Encodable, Decodable)]
 struct RegisteredIncomingContractKey(pub PaymentImage);
 
@@ -625,5 +854,110 @@ pub struct RegisteredIncomingContract {
     db_prefix = DbKeyPrefix::RegisteredIncomingContract,
 );
 
+/// Key of an API token, the token's secret itself is never persisted.
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct ApiTokenKey {
+    token_hash: sha256::Hash,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct ApiTokenKeyPrefix;
+
+/// An API token granting access to a subset of the gateway's authenticated
+/// endpoints.
+#[derive(Debug, Clone, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub scopes: BTreeSet<ApiScope>,
+    pub created_at_secs: u64,
+}
+
+impl_db_record!(
+    key = ApiTokenKey,
+    value = ApiToken,
+    db_prefix = DbKeyPrefix::ApiToken,
+);
+
+impl_db_lookup!(key = ApiTokenKey, query_prefix = ApiTokenKeyPrefix);
+
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct PaymentLimitsKey {
+    federation_id: FederationId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct PaymentLimitsKeyPrefix;
+
+impl_db_record!(
+    key = PaymentLimitsKey,
+    value = PaymentLimits,
+    db_prefix = DbKeyPrefix::PaymentLimits,
+);
+
+impl_db_lookup!(
+    key = PaymentLimitsKey,
+    query_prefix = PaymentLimitsKeyPrefix
+);
+
+/// The outgoing payments the gateway has admitted on behalf of a federation
+/// within the last day, which count towards its hourly and daily volume limits.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct PaymentVolume {
+    /// Oldest first
+    pub payments: Vec<AdmittedPayment>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct AdmittedPayment {
+    pub admitted_at_secs: u64,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct PaymentVolumeKey {
+    federation_id: FederationId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct PaymentVolumeKeyPrefix;
+
+impl_db_record!(
+    key = PaymentVolumeKey,
+    value = PaymentVolume,
+    db_prefix = DbKeyPrefix::PaymentVolume,
+);
+
+impl_db_lookup!(
+    key = PaymentVolumeKey,
+    query_prefix = PaymentVolumeKeyPrefix
+);
+
+/// A transfer between federations made by the rebalancing task. While it is
+/// pending no further transfers are planned, since the balances of the source
+/// and destination federation do not yet reflect it.
+#[derive(Debug, Clone, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct PendingRebalance {
+    pub source: FederationId,
+    pub destination: FederationId,
+    /// The amount sent to the destination federation's peg-in address
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: bitcoin::Amount,
+    /// The peg-out fees paid by the source federation on top of `amount`
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub fees: bitcoin::Amount,
+    /// The deposit operation in the destination federation's client
+    pub deposit_operation_id: OperationId,
+    pub created_at_secs: u64,
+}
+
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct PendingRebalanceKey;
+
+impl_db_record!(
+    key = PendingRebalanceKey,
+    value = PendingRebalance,
+    db_prefix = DbKeyPrefix::PendingRebalance,
+);
+
 #[cfg(test)]
 mod migration_tests;
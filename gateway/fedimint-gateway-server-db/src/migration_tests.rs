@@ -16,12 +16,16 @@
 use tracing::info;
 
 use super::{
-    BTreeMap, Connector, DbKeyPrefix, Encodable, FederationConfig, FederationConfigKey,
-    FederationConfigKeyPrefix, FederationConfigKeyV0, FederationConfigV0, FederationId,
-    GatewayConfigurationKeyV0, GatewayConfigurationV0, GatewayDbExt, GatewayPublicKey,
-    IDatabaseTransactionOpsCoreTyped, InviteCode, Keypair, NetworkLegacyEncodingWrapper, OsRng,
+    AdmittedPayment, Amount, ApiScope, ApiToken, ApiTokenKeyPrefix, BTreeMap, BTreeSet, Connector,
+    DbKeyPrefix, Encodable, FederationConfigKeyPrefix, FederationConfigKeyPrefixV2,
+    FederationConfigKeyV0, FederationConfigKeyV2, FederationConfigV0, FederationConfigV2,
+    FederationId, GatewayConfigurationKeyV0, GatewayConfigurationV0, GatewayDbExt,
+    GatewayDbtxNcExt, GatewayPublicKey, IDatabaseTransactionOpsCoreTyped, InviteCode, Keypair,
+    NetworkLegacyEncodingWrapper, OperationId, OsRng, PaymentLimits, PaymentLimitsKeyPrefix,
+    PaymentVolume, PaymentVolumeKeyPrefix, PendingRebalance, PendingRebalanceKey,
     PreimageAuthentication, PreimageAuthenticationPrefix, StreamExt,
-    get_gatewayd_database_migrations, migrate_federation_configs, secp256k1, sha256,
+    get_gatewayd_database_migrations, migrate_federation_configs, migrate_rebalance_weights,
+    secp256k1, sha256,
 };
 
 async fn create_gatewayd_db_data(db: Database) {
@@ -68,6 +72,45 @@ async fn create_gatewayd_db_data(db: Database) {
     dbtx.insert_new_entry(&preimage_auth, &verification_hash)
         .await;
 
+    let api_token = ApiToken {
+        name: "EXAMPLE".to_string(),
+        scopes: BTreeSet::from([ApiScope::Info, ApiScope::Payments]),
+        created_at_secs: 1_700_000_000,
+    };
+    dbtx.save_api_token(
+        sha256::Hash::from_slice(&BYTE_32).expect("Hash should not fail"),
+        &api_token,
+    )
+    .await;
+
+    let payment_limits = PaymentLimits {
+        max_payment: Some(Amount::from_sats(100_000)),
+        max_hourly_volume: Some(Amount::from_sats(1_000_000)),
+        max_daily_volume: Some(Amount::from_sats(10_000_000)),
+        max_in_flight: Some(10),
+    };
+    dbtx.save_payment_limits(federation_id, &payment_limits)
+        .await;
+
+    let pending_rebalance = PendingRebalance {
+        source: federation_id,
+        destination: FederationId::dummy(),
+        amount: bitcoin::Amount::from_sat(100_000),
+        fees: bitcoin::Amount::from_sat(1_000),
+        deposit_operation_id: OperationId::new_random(),
+        created_at_secs: 1_700_000_000,
+    };
+    dbtx.save_pending_rebalance(&pending_rebalance).await;
+
+    let payment_volume = PaymentVolume {
+        payments: vec![AdmittedPayment {
+            admitted_at_secs: 1_700_000_000,
+            amount: Amount::from_sats(1_000),
+        }],
+    };
+    dbtx.save_payment_volume(federation_id, &payment_volume)
+        .await;
+
     dbtx.commit_tx().await;
 }
 
@@ -128,6 +171,39 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                         );
                         info!("Validated PreimageAuthentication");
                     }
+                    // Snapshots taken before the following records were introduced do not
+                    // contain them, but the records they do contain have to be readable
+                    DbKeyPrefix::ApiToken => {
+                        let api_tokens = dbtx
+                            .find_by_prefix(&ApiTokenKeyPrefix)
+                            .await
+                            .collect::<Vec<_>>()
+                            .await;
+                        info!("Validated {} ApiTokens", api_tokens.len());
+                    }
+                    DbKeyPrefix::PaymentLimits => {
+                        let payment_limits = dbtx
+                            .find_by_prefix(&PaymentLimitsKeyPrefix)
+                            .await
+                            .collect::<Vec<_>>()
+                            .await;
+                        info!("Validated {} PaymentLimits", payment_limits.len());
+                    }
+                    DbKeyPrefix::PendingRebalance => {
+                        let pending_rebalance = dbtx.get_value(&PendingRebalanceKey).await;
+                        info!(
+                            "Validated PendingRebalance, present: {}",
+                            pending_rebalance.is_some()
+                        );
+                    }
+                    DbKeyPrefix::PaymentVolume => {
+                        let payment_volumes = dbtx
+                            .find_by_prefix(&PaymentVolumeKeyPrefix)
+                            .await
+                            .collect::<Vec<_>>()
+                            .await;
+                        info!("Validated {} PaymentVolumes", payment_volumes.len());
+                    }
                     _ => {}
                 }
             }
@@ -168,10 +244,10 @@ async fn create_isolated_record(prefix: Vec<u8>, db: &Database) {
     let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
     let mut dbtx = db.begin_transaction().await;
     dbtx.insert_new_entry(
-        &FederationConfigKey {
+        &FederationConfigKeyV2 {
             id: conflicting_fed_id,
         },
-        &FederationConfig {
+        &FederationConfigV2 {
             invite_code: InviteCode::new(
                 SafeUrl::from_str("http://testfed.com").unwrap(),
                 PeerId::from(0),
@@ -187,10 +263,10 @@ async fn create_isolated_record(prefix: Vec<u8>, db: &Database) {
     .await;
 
     dbtx.insert_new_entry(
-        &FederationConfigKey {
+        &FederationConfigKeyV2 {
             id: nonconflicting_fed_id,
         },
-        &FederationConfig {
+        &FederationConfigV2 {
             invite_code: InviteCode::new(
                 SafeUrl::from_str("http://testfed2.com").unwrap(),
                 PeerId::from(0),
@@ -216,7 +292,7 @@ async fn create_isolated_record(prefix: Vec<u8>, db: &Database) {
     let mut dbtx = db.begin_transaction_nc().await;
 
     let num_configs = dbtx
-        .find_by_prefix(&FederationConfigKeyPrefix)
+        .find_by_prefix(&FederationConfigKeyPrefixV2)
         .await
         .collect::<BTreeMap<_, _>>()
         .await
@@ -234,3 +310,116 @@ async fn create_isolated_record(prefix: Vec<u8>, db: &Database) {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rebalance_weight_migration() -> anyhow::Result<()> {
+    let federation_id = FederationId::dummy();
+    let old_config = FederationConfigV2 {
+        invite_code: InviteCode::new(
+            SafeUrl::from_str("http://testfed.com").unwrap(),
+            PeerId::from(0),
+            federation_id,
+            None,
+        ),
+        federation_index: 3,
+        lightning_fee: PaymentFee::TRANSACTION_FEE_DEFAULT,
+        transaction_fee: PaymentFee::SEND_FEE_LIMIT,
+        connector: Connector::Tcp,
+    };
+
+    let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
+    let mut dbtx = db.begin_transaction().await;
+    dbtx.insert_new_entry(&FederationConfigKeyV2 { id: federation_id }, &old_config)
+        .await;
+    dbtx.commit_tx().await;
+
+    let mut migration_dbtx = db.begin_transaction().await;
+    migrate_rebalance_weights(&mut migration_dbtx.to_ref_nc()).await?;
+    migration_dbtx.commit_tx().await;
+
+    let configs = db
+        .begin_transaction_nc()
+        .await
+        .find_by_prefix(&FederationConfigKeyPrefix)
+        .await
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(configs.len(), 1);
+
+    let (key, config) = &configs[0];
+    assert_eq!(key.id, federation_id);
+    assert_eq!(config.invite_code, old_config.invite_code);
+    assert_eq!(config.federation_index, old_config.federation_index);
+    assert_eq!(config.lightning_fee, old_config.lightning_fee);
+    assert_eq!(config.transaction_fee, old_config.transaction_fee);
+    assert_eq!(config.connector, old_config.connector);
+    // Existing federations do not take part in rebalancing
+    assert_eq!(config.rebalance_weight, None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_api_token_payment_limit_and_rebalance_records() -> anyhow::Result<()> {
+    let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
+    create_gatewayd_db_data(db.clone()).await;
+
+    let federation_id = FederationId::dummy();
+    let mut dbtx = db.begin_transaction().await;
+
+    let api_tokens = dbtx.load_api_tokens().await;
+    assert_eq!(api_tokens.len(), 1);
+    let api_token = api_tokens.values().next().expect("API token exists");
+    assert_eq!(api_token.name, "EXAMPLE");
+    assert_eq!(
+        api_token.scopes,
+        BTreeSet::from([ApiScope::Info, ApiScope::Payments])
+    );
+
+    assert_eq!(
+        dbtx.load_payment_limits(federation_id).await.max_in_flight,
+        Some(10)
+    );
+    assert_eq!(
+        dbtx.load_pending_rebalance()
+            .await
+            .expect("Pending rebalance exists")
+            .amount,
+        bitcoin::Amount::from_sat(100_000)
+    );
+    assert_eq!(
+        dbtx.load_payment_volume(federation_id).await.payments.len(),
+        1
+    );
+
+    let dump = dbtx.dump_database(vec![]).await;
+    for name in [
+        "API Tokens",
+        "Payment Limits",
+        "Pending Rebalance",
+        "Payment Volume",
+    ] {
+        assert!(dump.contains_key(name), "{name} is missing from the dump");
+    }
+
+    // Removed records are no longer returned
+    assert!(dbtx.remove_api_token("EXAMPLE").await);
+    dbtx.remove_payment_limits(federation_id).await;
+    dbtx.remove_payment_volume(federation_id).await;
+    dbtx.remove_pending_rebalance().await;
+
+    assert!(dbtx.load_api_tokens().await.is_empty());
+    assert_eq!(
+        dbtx.load_payment_limits(federation_id).await,
+        PaymentLimits::default()
+    );
+    assert_eq!(
+        dbtx.load_payment_volume(federation_id).await,
+        PaymentVolume::default()
+    );
+    assert!(dbtx.load_pending_rebalance().await.is_none());
+
+    dbtx.commit_tx().await;
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeSet;
+
+use axum::http::StatusCode;
+use bitcoin::hashes::{Hash, sha256};
+use fedimint_core::db::Database;
+use fedimint_core::time::duration_since_epoch;
+use fedimint_gateway_common::{
+    ApiScope, ApiTokenInfo, CreateApiTokenPayload, CreateApiTokenResponse, RevokeApiTokenPayload,
+};
+use fedimint_gateway_server_db::{ApiToken, GatewayDbtxNcExt as _};
+use fedimint_logging::LOG_GATEWAY;
+use hex::ToHex;
+use rand::{Rng as _, thread_rng};
+use tracing::info;
+
+use crate::AdminResult;
+use crate::error::AdminGatewayError;
+
+/// Hashes the secret of an API token, only the hash is persisted in the
+/// gateway's database.
+fn hash_api_token(token: &str) -> sha256::Hash {
+    sha256::Hash::hash(token.as_bytes())
+}
+
+/// Creates a new API token with the given scopes. The token's secret is
+/// returned to the caller and only its hash is persisted.
+pub async fn create_api_token(
+    gateway_db: &Database,
+    CreateApiTokenPayload { name, scopes }: CreateApiTokenPayload,
+) -> AdminResult<CreateApiTokenResponse> {
+    if name.is_empty() {
+        return Err(AdminGatewayError::GatewayConfigurationError(
+            "API token name must not be empty".to_string(),
+        ));
+    }
+
+    let mut dbtx = gateway_db.begin_transaction().await;
+
+    if dbtx
+        .load_api_tokens()
+        .await
+        .values()
+        .any(|api_token| api_token.name == name)
+    {
+        return Err(AdminGatewayError::GatewayConfigurationError(format!(
+            "API token {name} already exists"
+        )));
+    }
+
+    let token = thread_rng().r#gen::<[u8; 32]>().encode_hex::<String>();
+    dbtx.save_api_token(
+        hash_api_token(&token),
+        &ApiToken {
+            name: name.clone(),
+            scopes,
+            created_at_secs: duration_since_epoch().as_secs(),
+        },
+    )
+    .await;
+    dbtx.commit_tx().await;
+
+    info!(target: LOG_GATEWAY, %name, "Created API token");
+
+    Ok(CreateApiTokenResponse { name, token })
+}
+
+/// Lists the names and scopes of all API tokens.
+pub async fn list_api_tokens(gateway_db: &Database) -> Vec<ApiTokenInfo> {
+    gateway_db
+        .begin_transaction_nc()
+        .await
+        .load_api_tokens()
+        .await
+        .into_values()
+        .map(|api_token| ApiTokenInfo {
+            name: api_token.name,
+            scopes: api_token.scopes,
+            created_at_secs: api_token.created_at_secs,
+        })
+        .collect()
+}
+
+/// Revokes the API token with the given name.
+pub async fn revoke_api_token(
+    gateway_db: &Database,
+    RevokeApiTokenPayload { name }: RevokeApiTokenPayload,
+) -> AdminResult<()> {
+    let mut dbtx = gateway_db.begin_transaction().await;
+
+    if !dbtx.remove_api_token(&name).await {
+        return Err(AdminGatewayError::GatewayConfigurationError(format!(
+            "API token {name} does not exist"
+        )));
+    }
+
+    dbtx.commit_tx().await;
+
+    info!(target: LOG_GATEWAY, %name, "Revoked API token");
+
+    Ok(())
+}
+
+/// Returns the scopes granted to the API token `token`, or `None` if no such
+/// token exists.
+pub async fn api_token_scopes(gateway_db: &Database, token: &str) -> Option<BTreeSet<ApiScope>> {
+    gateway_db
+        .begin_transaction_nc()
+        .await
+        .load_api_token(hash_api_token(token))
+        .await
+        .map(|api_token| api_token.scopes)
+}
+
+/// Checks whether `token` grants access to a route that requires `scope`.
+///
+/// The token is either the gateway's password, which grants access to every
+/// route, or an API token that is granted `scope`. Routes without a scope are
+/// only accessible with the gateway's password.
+pub async fn authorize(
+    gateway_db: &Database,
+    bcrypt_password_hash: &bcrypt::HashParts,
+    scope: Option<ApiScope>,
+    token: &str,
+) -> Result<(), StatusCode> {
+    if let Some(scopes) = api_token_scopes(gateway_db, token).await {
+        if scope.is_some_and(|scope| scopes.contains(&scope)) {
+            return Ok(());
+        }
+
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if bcrypt::verify(token, &bcrypt_password_hash.to_string())
+        .expect("Bcrypt hash is valid since we just stringified it")
+    {
+        return Ok(());
+    }
+
+    Err(StatusCode::UNAUTHORIZED)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::str::FromStr as _;
+
+    use axum::http::StatusCode;
+    use fedimint_core::db::Database;
+    use fedimint_core::db::mem_impl::MemDatabase;
+    use fedimint_core::module::registry::ModuleDecoderRegistry;
+    use fedimint_gateway_common::{ApiScope, CreateApiTokenPayload, RevokeApiTokenPayload};
+
+    use super::{authorize, create_api_token, list_api_tokens, revoke_api_token};
+
+    const PASSWORD: &str = "password";
+
+    fn gateway() -> (Database, bcrypt::HashParts) {
+        let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
+        let password_hash = bcrypt::HashParts::from_str(
+            &bcrypt::hash(PASSWORD, 4).expect("Hashing the password succeeds"),
+        )
+        .expect("Hash is valid");
+
+        (db, password_hash)
+    }
+
+    async fn create(db: &Database, name: &str, scopes: &[ApiScope]) -> String {
+        create_api_token(
+            db,
+            CreateApiTokenPayload {
+                name: name.to_string(),
+                scopes: scopes.iter().copied().collect(),
+            },
+        )
+        .await
+        .expect("Token is created")
+        .token
+    }
+
+    #[tokio::test]
+    async fn password_grants_every_scope() {
+        let (db, password_hash) = gateway();
+
+        for scope in [None, Some(ApiScope::Info), Some(ApiScope::Mnemonic)] {
+            assert_eq!(
+                authorize(&db, &password_hash, scope, PASSWORD).await,
+                Ok(())
+            );
+        }
+
+        assert_eq!(
+            authorize(&db, &password_hash, None, "wrong password").await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn api_token_is_restricted_to_its_scopes() {
+        let (db, password_hash) = gateway();
+        let token = create(&db, "bot", &[ApiScope::Info, ApiScope::Payments]).await;
+
+        for scope in [ApiScope::Info, ApiScope::Payments] {
+            assert_eq!(
+                authorize(&db, &password_hash, Some(scope), &token).await,
+                Ok(())
+            );
+        }
+
+        for scope in [Some(ApiScope::Onchain), Some(ApiScope::Mnemonic), None] {
+            assert_eq!(
+                authorize(&db, &password_hash, scope, &token).await,
+                Err(StatusCode::FORBIDDEN)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn revoked_api_token_is_rejected() {
+        let (db, password_hash) = gateway();
+        let token = create(&db, "bot", &[ApiScope::Info]).await;
+        let other_token = create(&db, "other", &[ApiScope::Info]).await;
+
+        // Token names are unique
+        assert!(
+            create_api_token(
+                &db,
+                CreateApiTokenPayload {
+                    name: "bot".to_string(),
+                    scopes: BTreeSet::new(),
+                },
+            )
+            .await
+            .is_err()
+        );
+
+        revoke_api_token(
+            &db,
+            RevokeApiTokenPayload {
+                name: "bot".to_string(),
+            },
+        )
+        .await
+        .expect("Token exists");
+
+        assert_eq!(
+            authorize(&db, &password_hash, Some(ApiScope::Info), &token).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize(&db, &password_hash, Some(ApiScope::Info), &other_token).await,
+            Ok(())
+        );
+
+        assert_eq!(
+            list_api_tokens(&db)
+                .await
+                .into_iter()
+                .map(|api_token| api_token.name)
+                .collect::<Vec<_>>(),
+            vec!["other".to_string()]
+        );
+
+        assert!(
+            revoke_api_token(
+                &db,
+                RevokeApiTokenPayload {
+                    name: "bot".to_string(),
+                },
+            )
+            .await
+            .is_err()
+        );
+    }
+}
@@ -17,6 +17,7 @@
 use fedimint_gateway_server_db::GatewayDbExt as _;
 use fedimint_gw_client::GatewayClientInit;
 use fedimint_gwv2_client::GatewayClientInitV2;
+use fedimint_lnv2_client::LightningClientInit;
 
 use crate::error::AdminGatewayError;
 use crate::{AdminResult, Gateway};
@@ -183,6 +184,50 @@ pub async fn build(
         .map_err(AdminGatewayError::ClientCreationError)
     }
 
+    /// Builds the client the gateway uses to send and receive lightning
+    /// payments as a regular user of the federation when it rebalances its
+    /// ecash over lightning. Its funds are returned to the gateway's client
+    /// after every rebalancing transfer.
+    pub async fn build_rebalance_client(
+        &self,
+        config: FederationConfig,
+        gateway_db: &Database,
+        mnemonic: &Mnemonic,
+    ) -> AdminResult<fedimint_client::ClientHandleArc> {
+        let invite_code = config.invite_code.clone();
+        let federation_id = invite_code.federation_id();
+        let db = gateway_db.get_rebalance_client_database(&federation_id);
+
+        Self::verify_client_config(&db, federation_id).await?;
+
+        let mut registry = self.registry.clone();
+        registry.attach(LightningClientInit::default());
+
+        let mut client_builder = Client::builder(db)
+            .await
+            .map_err(AdminGatewayError::ClientCreationError)?;
+        client_builder.with_module_inits(registry);
+        client_builder.with_primary_module_kind(self.primary_module_kind.clone());
+        client_builder.with_connector(config.connector);
+
+        let root_secret = Self::derive_wallet_secret(mnemonic, &federation_id, 1);
+
+        if Client::is_initialized(client_builder.db_no_decoders()).await {
+            client_builder.open(root_secret).await
+        } else {
+            let client_config = config
+                .connector
+                .download_from_invite_code(&invite_code)
+                .await
+                .map_err(AdminGatewayError::ClientCreationError)?;
+            client_builder
+                .join(root_secret, client_config, invite_code.api_secret())
+                .await
+        }
+        .map(Arc::new)
+        .map_err(AdminGatewayError::ClientCreationError)
+    }
+
     /// Verifies that the saved `ClientConfig` contains the expected
     /// federation's config.
     async fn verify_client_config(db: &Database, federation_id: FederationId) -> AdminResult<()> {
@@ -202,11 +247,22 @@ async fn verify_client_config(db: &Database, federation_id: FederationId) -> Adm
     fn derive_federation_secret(
         mnemonic: &Mnemonic,
         federation_id: &FederationId,
+    ) -> DerivableSecret {
+        Self::derive_wallet_secret(mnemonic, federation_id, 0)
+    }
+
+    /// Derives the secret of the wallet with index `wallet` in a federation.
+    /// The gateway's client uses the first wallet and its rebalancing client
+    /// the second one.
+    fn derive_wallet_secret(
+        mnemonic: &Mnemonic,
+        federation_id: &FederationId,
+        wallet: u64,
     ) -> DerivableSecret {
         let global_root_secret = Bip39RootSecretStrategy::<12>::to_root_secret(mnemonic);
         let multi_federation_root_secret = global_root_secret.child_key(ChildId(0));
         let federation_root_secret = multi_federation_root_secret.federation_key(federation_id);
-        let federation_wallet_root_secret = federation_root_secret.child_key(ChildId(0));
+        let federation_wallet_root_secret = federation_root_secret.child_key(ChildId(wallet));
         federation_wallet_root_secret.child_key(ChildId(0))
     }
 
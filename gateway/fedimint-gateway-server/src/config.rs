@@ -2,6 +2,7 @@
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use bitcoin::Network;
 use clap::Parser;
@@ -49,6 +50,25 @@ pub struct GatewayOpts {
     /// The Lightning module to use: LNv1, LNv2, or both
     #[arg(long = "lightning-module-mode", env = envs::FM_GATEWAY_LIGHTNING_MODULE_MODE_ENV, default_value_t = LightningModuleMode::LNv1)]
     lightning_module_mode: LightningModuleMode,
+
+    /// Interval in seconds in which ecash is rebalanced between federations
+    /// according to their rebalancing weights. A new transfer is only planned
+    /// once the previous one has been confirmed by its destination federation.
+    /// Rebalancing is disabled if not set.
+    #[arg(
+        long = "rebalance-interval-secs",
+        env = envs::FM_GATEWAY_REBALANCE_INTERVAL_SECS_ENV
+    )]
+    rebalance_interval_secs: Option<u64>,
+
+    /// Minimum amount in sats that is moved between federations when
+    /// rebalancing
+    #[arg(
+        long = "rebalance-min-amount-sats",
+        env = envs::FM_GATEWAY_REBALANCE_MIN_AMOUNT_SATS_ENV,
+        default_value_t = super::DEFAULT_REBALANCE_MIN_AMOUNT_SATS
+    )]
+    rebalance_min_amount_sats: u64,
 }
 
 impl GatewayOpts {
@@ -71,6 +91,8 @@ pub fn to_gateway_parameters(&self) -> anyhow::Result<GatewayParameters> {
             network: self.network,
             num_route_hints: self.num_route_hints,
             lightning_module_mode: self.lightning_module_mode,
+            rebalance_interval: self.rebalance_interval_secs.map(Duration::from_secs),
+            rebalance_min_amount: bitcoin::Amount::from_sat(self.rebalance_min_amount_sats),
         })
     }
 }
@@ -89,6 +111,8 @@ pub struct GatewayParameters {
     pub network: Network,
     pub num_route_hints: u32,
     pub lightning_module_mode: LightningModuleMode,
+    pub rebalance_interval: Option<Duration>,
+    pub rebalance_min_amount: bitcoin::Amount,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
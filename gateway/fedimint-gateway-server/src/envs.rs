@@ -45,3 +45,12 @@
 /// Bitcoin network is set to mainnet and the lightning module mode is set to
 /// `All`
 pub const FM_GATEWAY_OVERRIDE_LN_MODULE_CHECK_ENV: &str = "FM_GATEWAY_OVERRIDE_LN_MODULE_CHECK";
+
+/// Environment variable that specifies the interval in seconds in which the
+/// gateway rebalances its ecash between federations. If not set, the gateway
+/// does not rebalance.
+pub const FM_GATEWAY_REBALANCE_INTERVAL_SECS_ENV: &str = "FM_GATEWAY_REBALANCE_INTERVAL_SECS";
+
+/// Environment variable that specifies the minimum amount in sats that the
+/// gateway moves between federations when rebalancing.
+pub const FM_GATEWAY_REBALANCE_MIN_AMOUNT_SATS_ENV: &str = "FM_GATEWAY_REBALANCE_MIN_AMOUNT_SATS";
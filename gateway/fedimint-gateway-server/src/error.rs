@@ -2,9 +2,9 @@
 
 use axum::response::{IntoResponse, Response};
 use fedimint_core::config::{FederationId, FederationIdPrefix};
-use fedimint_core::crit;
 use fedimint_core::envs::is_env_var_set;
 use fedimint_core::fmt_utils::OptStacktrace;
+use fedimint_core::{Amount, crit};
 use fedimint_gw_client::pay::OutgoingPaymentError;
 use fedimint_lightning::LightningRpcError;
 use fedimint_logging::LOG_GATEWAY;
@@ -28,6 +28,8 @@ pub enum PublicGatewayError {
     FederationNotConnected(#[from] FederationNotConnected),
     #[error("Failed to receive ecash: {failure_reason}")]
     ReceiveEcashError { failure_reason: String },
+    #[error("{}", .0)]
+    PaymentLimit(#[from] PaymentLimitError),
 }
 
 impl IntoResponse for PublicGatewayError {
@@ -40,6 +42,13 @@ fn into_response(self) -> Response {
             PublicGatewayError::FederationNotConnected(e) => {
                 (e.to_string(), StatusCode::BAD_REQUEST)
             }
+            PublicGatewayError::PaymentLimit(e) => {
+                let status_code = match e {
+                    PaymentLimitError::MaxPaymentExceeded { .. } => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::TOO_MANY_REQUESTS,
+                };
+                (e.to_string(), status_code)
+            }
             PublicGatewayError::ReceiveEcashError { .. } => (
                 "Failed to receive ecash".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -135,6 +144,20 @@ pub enum LNv2Error {
     OutgoingPayment(#[from] anyhow::Error),
 }
 
+/// Public error that indicates an outgoing payment was rejected because it
+/// would exceed the payment limits configured for its federation.
+#[derive(Debug, Error)]
+pub enum PaymentLimitError {
+    #[error("Payment of {amount} exceeds the maximum payment amount of {max}")]
+    MaxPaymentExceeded { amount: Amount, max: Amount },
+    #[error("Payment of {amount} exceeds the remaining hourly volume of {remaining}")]
+    HourlyVolumeExceeded { amount: Amount, remaining: Amount },
+    #[error("Payment of {amount} exceeds the remaining daily volume of {remaining}")]
+    DailyVolumeExceeded { amount: Amount, remaining: Amount },
+    #[error("The maximum of {max} payments in flight has been reached")]
+    TooManyPaymentsInFlight { max: u64 },
+}
+
 /// Public error that indicates the requested federation is not connected to
 /// this gateway.
 #[derive(Debug, Error)]
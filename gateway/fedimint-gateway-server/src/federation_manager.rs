@@ -216,11 +216,14 @@ pub async fn federation_info(
                     federation_id_prefix: federation_id.to_prefix(),
                 })?;
 
+                let payment_limits = dbtx.load_payment_limits(federation_id).await;
+
                 Ok(FederationInfo {
                     federation_id,
                     federation_name: self.federation_name(client).await,
                     balance_msat,
                     config,
+                    payment_limits,
                 })
             })
             .await
@@ -242,11 +245,13 @@ pub async fn federation_info_all_federations(
 
             let config = dbtx.load_federation_config(*federation_id).await;
             if let Some(config) = config {
+                let payment_limits = dbtx.load_payment_limits(*federation_id).await;
                 federation_infos.push(FederationInfo {
                     federation_id: *federation_id,
                     federation_name: self.federation_name(client.value()).await,
                     balance_msat,
                     config,
+                    payment_limits,
                 });
             }
         }
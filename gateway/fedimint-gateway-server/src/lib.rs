@@ -13,12 +13,15 @@
 #![allow(clippy::too_many_lines)]
 #![allow(clippy::large_futures)]
 
+mod api_tokens;
 pub mod client;
 pub mod config;
 pub mod envs;
 mod error;
 mod events;
 mod federation_manager;
+mod payment_limits;
+mod rebalance;
 pub mod rpc_server;
 mod types;
 
@@ -32,6 +35,7 @@
 
 use anyhow::{Context, anyhow, ensure};
 use async_trait::async_trait;
+use axum::http::StatusCode;
 use bitcoin::hashes::sha256;
 use bitcoin::{Address, Network, Txid};
 use clap::Parser;
@@ -50,7 +54,7 @@
 use fedimint_core::config::FederationId;
 use fedimint_core::core::{
     LEGACY_HARDCODED_INSTANCE_ID_MINT, LEGACY_HARDCODED_INSTANCE_ID_WALLET, ModuleInstanceId,
-    ModuleKind,
+    ModuleKind, OperationId,
 };
 use fedimint_core::db::{Database, DatabaseTransaction, apply_migrations};
 use fedimint_core::envs::is_env_var_set;
@@ -66,18 +70,22 @@
 };
 use fedimint_eventlog::{DBTransactionEventLogExt, EventLogId, StructuredPaymentEvents};
 use fedimint_gateway_common::{
-    BackupPayload, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConnectFedPayload,
-    CreateInvoiceForOperatorPayload, CreateOfferPayload, CreateOfferResponse,
-    DepositAddressPayload, DepositAddressRecheckPayload, FederationBalanceInfo, FederationConfig,
-    FederationInfo, GatewayBalances, GatewayFedConfig, GatewayInfo, GetInvoiceRequest,
-    GetInvoiceResponse, LeaveFedPayload, LightningMode, ListTransactionsPayload,
-    ListTransactionsResponse, MnemonicResponse, OpenChannelRequest, PayInvoiceForOperatorPayload,
-    PayOfferPayload, PayOfferResponse, PaymentLogPayload, PaymentLogResponse, PaymentStats,
-    PaymentSummaryPayload, PaymentSummaryResponse, ReceiveEcashPayload, ReceiveEcashResponse,
-    SendOnchainRequest, SetFeesPayload, SpendEcashPayload, SpendEcashResponse, V1_API_ENDPOINT,
-    WithdrawPayload, WithdrawResponse,
+    ApiScope, ApiTokenInfo, BackupPayload, CloseChannelsWithPeerRequest,
+    CloseChannelsWithPeerResponse, ConnectFedPayload, CreateApiTokenPayload,
+    CreateApiTokenResponse, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload,
+    FederationBalanceInfo, FederationConfig, FederationInfo, GatewayBalances, GatewayFedConfig,
+    GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, LeaveFedPayload, LightningMode,
+    ListTransactionsPayload, ListTransactionsResponse, MnemonicResponse, OpenChannelRequest,
+    PayInvoiceForOperatorPayload, PayOfferPayload, PayOfferResponse, PaymentLimits,
+    PaymentLogPayload, PaymentLogResponse, PaymentStats, PaymentSummaryPayload,
+    PaymentSummaryResponse, ReceiveEcashPayload, ReceiveEcashResponse, RevokeApiTokenPayload,
+    SendOnchainRequest, SetFeesPayload, SetPaymentLimitsPayload, SetRebalanceWeightPayload,
+    SpendEcashPayload, SpendEcashResponse, V1_API_ENDPOINT, WithdrawPayload, WithdrawResponse,
+};
+use fedimint_gateway_server_db::{
+    GatewayDbtxNcExt as _, PendingRebalance, get_gatewayd_database_migrations,
 };
-use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, get_gatewayd_database_migrations};
 use fedimint_gw_client::events::compute_lnv1_stats;
 use fedimint_gw_client::pay::{OutgoingPaymentError, OutgoingPaymentErrorType};
 use fedimint_gw_client::{GatewayClientModule, GatewayExtPayStates, IGatewayClientV1};
@@ -95,15 +103,18 @@
 use fedimint_ln_common::config::LightningClientConfig;
 use fedimint_ln_common::contracts::outgoing::OutgoingContractAccount;
 use fedimint_ln_common::contracts::{IdentifiableContract, Preimage};
-use fedimint_lnv2_common::Bolt11InvoiceDescription;
+use fedimint_lnv2_client::{
+    FinalReceiveOperationState, FinalSendOperationState, LightningClientModule,
+};
 use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::{
-    CreateBolt11InvoicePayload, PaymentFee, RoutingInfo, SendPaymentPayload,
+    Bolt12InvoicePayload, CreateBolt11InvoicePayload, PaymentFee, RoutingInfo, SendPaymentPayload,
 };
+use fedimint_lnv2_common::{Bolt11InvoiceDescription, LightningInvoice};
 use fedimint_logging::LOG_GATEWAY;
 use fedimint_mint_client::{
-    MintClientInit, MintClientModule, MintCommonInit, SelectNotesWithAtleastAmount,
-    SelectNotesWithExactAmount,
+    MintClientInit, MintClientModule, MintCommonInit, ReissueExternalNotesState,
+    SelectNotesWithAtleastAmount, SelectNotesWithExactAmount,
 };
 use fedimint_wallet_client::{
     WalletClientInit, WalletClientModule, WalletCommonInit, WithdrawState,
@@ -111,13 +122,15 @@
 use futures::stream::StreamExt;
 use lightning_invoice::{Bolt11Invoice, RoutingFees};
 use rand::thread_rng;
-use tokio::sync::RwLock;
+use serde_json::Value;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, info, info_span, warn};
 
 use crate::config::LightningModuleMode;
 use crate::envs::FM_GATEWAY_MNEMONIC_ENV;
 use crate::error::{AdminGatewayError, LNv1Error, LNv2Error, PublicGatewayError};
 use crate::events::get_events_for_duration;
+use crate::payment_limits::{PaymentLimiter, PaymentPermit};
 use crate::rpc_server::run_webserver;
 use crate::types::PrettyInterceptPaymentRequest;
 
@@ -128,6 +141,21 @@
 /// invoice creation.
 const DEFAULT_NUM_ROUTE_HINTS: u32 = 1;
 
+/// The default minimum amount that is moved between federations when
+/// rebalancing.
+const DEFAULT_REBALANCE_MIN_AMOUNT_SATS: u64 = 100_000;
+
+/// The maximum share of a rebalancing transfer, in percent, that may be spent
+/// on peg-out fees. Transfers that would cost more are postponed.
+const MAX_REBALANCE_FEE_PERCENT: u64 = 2;
+
+/// How long the rebalancing task waits for a transfer to arrive at its
+/// destination federation before it gives up on it and plans the next one.
+const REBALANCE_TRANSFER_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// How long the invoices we create to rebalance over lightning are valid for.
+const REBALANCE_INVOICE_EXPIRY_SECS: u32 = 60 * 60;
+
 /// Default Bitcoin network for testing purposes.
 pub const DEFAULT_NETWORK: Network = Network::Regtest;
 
@@ -233,6 +261,21 @@ pub struct Gateway {
 
     /// The Bitcoin network that the Lightning network is configured to.
     network: Network,
+
+    /// The interval in which ecash is rebalanced between federations, if
+    /// rebalancing is enabled.
+    rebalance_interval: Option<Duration>,
+
+    /// The minimum amount that is moved between federations when rebalancing.
+    rebalance_min_amount: bitcoin::Amount,
+
+    /// Tracks the outgoing payments of every federation to enforce their
+    /// payment limits.
+    payment_limiter: PaymentLimiter,
+
+    /// The clients we use to rebalance over lightning as a regular user of a
+    /// federation, opened on first use.
+    rebalance_clients: Arc<Mutex<BTreeMap<FederationId, ClientHandleArc>>>,
 }
 
 impl std::fmt::Debug for Gateway {
@@ -277,6 +320,8 @@ pub async fn new_with_custom_registry(
                 network,
                 num_route_hints,
                 lightning_module_mode,
+                rebalance_interval: None,
+                rebalance_min_amount: bitcoin::Amount::from_sat(DEFAULT_REBALANCE_MIN_AMOUNT_SATS),
             },
             gateway_db,
             client_builder,
@@ -365,6 +410,7 @@ async fn new(
             state: Arc::new(RwLock::new(gateway_state)),
             client_builder,
             gateway_id: Self::load_or_create_gateway_id(&gateway_db).await,
+            payment_limiter: PaymentLimiter::new(gateway_db.clone()),
             gateway_db,
             versioned_api: gateway_parameters.versioned_api,
             listen: gateway_parameters.listen,
@@ -373,6 +419,9 @@ async fn new(
             bcrypt_password_hash: Arc::new(gateway_parameters.bcrypt_password_hash),
             num_route_hints,
             network,
+            rebalance_interval: gateway_parameters.rebalance_interval,
+            rebalance_min_amount: gateway_parameters.rebalance_min_amount,
+            rebalance_clients: Arc::new(Mutex::new(BTreeMap::new())),
         })
     }
 
@@ -417,6 +466,7 @@ pub async fn run(
         self.register_clients_timer();
         self.load_clients().await?;
         self.start_gateway(runtime);
+        self.start_rebalance_task();
         // start webserver last to avoid handling requests before fully initialized
         let handle = self.task_group.make_handle();
         run_webserver(Arc::new(self)).await?;
@@ -1020,6 +1070,11 @@ async fn handle_pay_invoice_msg(
         debug!(target: LOG_GATEWAY, "Handling pay invoice message");
         let client = self.select_client(payload.federation_id).await?;
         let contract_id = payload.contract_id;
+        // Invoices without an amount are rejected by the gateway module
+        let amount = payload.payment_data.amount().unwrap_or(Amount::ZERO);
+        let _permit = self
+            .admit_outgoing_payment(payload.federation_id, amount, amount)
+            .await?;
         let gateway_module = &client
             .value()
             .get_first_module::<GatewayClientModule>()
@@ -1129,6 +1184,7 @@ pub async fn handle_connect_federation(
             lightning_fee: PaymentFee::TRANSACTION_FEE_DEFAULT,
             transaction_fee: PaymentFee::TRANSACTION_FEE_DEFAULT,
             connector,
+            rebalance_weight: None,
         };
 
         let recover = payload.recover.unwrap_or(false);
@@ -1162,6 +1218,7 @@ pub async fn handle_connect_federation(
             federation_name: federation_manager.federation_name(&client).await,
             balance_msat: client.get_balance().await,
             config: federation_config.clone(),
+            payment_limits: PaymentLimits::default(),
         };
 
         if self.is_running_lnv1() {
@@ -1225,7 +1282,11 @@ pub async fn handle_leave_federation(
             .await?;
 
         dbtx.remove_federation_config(payload.federation_id).await;
+        dbtx.remove_payment_limits(payload.federation_id).await;
+        dbtx.remove_payment_volume(payload.federation_id).await;
         dbtx.commit_tx().await;
+        self.payment_limiter
+            .remove_federation(&payload.federation_id);
         Ok(federation_info)
     }
 
@@ -1353,6 +1414,86 @@ pub async fn handle_set_fees_msg(
         Ok(())
     }
 
+    /// Handles a request to change the rebalancing weight of a federation.
+    pub async fn handle_set_rebalance_weight_msg(
+        &self,
+        SetRebalanceWeightPayload {
+            federation_id,
+            rebalance_weight,
+        }: SetRebalanceWeightPayload,
+    ) -> AdminResult<()> {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+
+        let mut config =
+            dbtx.load_federation_config(federation_id)
+                .await
+                .ok_or(FederationNotConnected {
+                    federation_id_prefix: federation_id.to_prefix(),
+                })?;
+
+        config.rebalance_weight = rebalance_weight;
+        dbtx.save_federation_config(&config).await;
+        dbtx.commit_tx().await;
+
+        Ok(())
+    }
+
+    /// Handles a request to replace the payment limits of a federation.
+    pub async fn handle_set_payment_limits_msg(
+        &self,
+        SetPaymentLimitsPayload {
+            federation_id,
+            limits,
+        }: SetPaymentLimitsPayload,
+    ) -> AdminResult<()> {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+
+        if dbtx.load_federation_config(federation_id).await.is_none() {
+            return Err(FederationNotConnected {
+                federation_id_prefix: federation_id.to_prefix(),
+            }
+            .into());
+        }
+
+        dbtx.save_payment_limits(federation_id, &limits).await;
+        dbtx.commit_tx().await;
+
+        Ok(())
+    }
+
+    /// Handles a request to create a new API token with the given scopes. The
+    /// token's secret is returned to the caller and only its hash is
+    /// persisted.
+    pub async fn handle_create_api_token_msg(
+        &self,
+        payload: CreateApiTokenPayload,
+    ) -> AdminResult<CreateApiTokenResponse> {
+        api_tokens::create_api_token(&self.gateway_db, payload).await
+    }
+
+    /// Handles a request to list the names and scopes of all API tokens.
+    pub async fn handle_list_api_tokens_msg(&self) -> AdminResult<Vec<ApiTokenInfo>> {
+        Ok(api_tokens::list_api_tokens(&self.gateway_db).await)
+    }
+
+    /// Handles a request to revoke the API token with the given name.
+    pub async fn handle_revoke_api_token_msg(
+        &self,
+        payload: RevokeApiTokenPayload,
+    ) -> AdminResult<()> {
+        api_tokens::revoke_api_token(&self.gateway_db, payload).await
+    }
+
+    /// Checks whether `token` is the gateway's password or an API token that
+    /// grants access to a route that requires `scope`.
+    pub async fn authorize(
+        &self,
+        scope: Option<ApiScope>,
+        token: &str,
+    ) -> std::result::Result<(), StatusCode> {
+        api_tokens::authorize(&self.gateway_db, &self.bcrypt_password_hash, scope, token).await
+    }
+
     /// Generates an onchain address to fund the gateway's lightning node.
     pub async fn handle_get_ln_onchain_address_msg(&self) -> AdminResult<Address> {
         let context = self.get_lightning_context().await?;
@@ -1990,6 +2131,451 @@ async fn check_lnv2_federation_network(
         Ok(())
     }
 
+    /// Spawns a task that periodically moves ecash between the federations
+    /// that take part in rebalancing, if rebalancing is enabled.
+    ///
+    /// Funds are moved from the federation with the largest surplus to the
+    /// federation with the largest deficit over lightning by paying an invoice
+    /// of our own node out of the one and paying an invoice of the other one
+    /// with our node. If that fails before the funds have left the source
+    /// federation we fall back to pegging out directly to a peg-in address of
+    /// the destination federation. At most one transfer is pending at a time.
+    fn start_rebalance_task(&self) {
+        let Some(rebalance_interval) = self.rebalance_interval else {
+            return;
+        };
+
+        info!(target: LOG_GATEWAY, ?rebalance_interval, "Spawning rebalance task...");
+        let gateway = self.clone();
+        self.task_group
+            .spawn_cancellable("rebalance federations", async move {
+                loop {
+                    sleep(rebalance_interval).await;
+
+                    if let Err(err) = gateway.rebalance_federations().await {
+                        warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Failed to rebalance federations");
+                    }
+                }
+            });
+    }
+
+    /// Makes a single transfer between federations that brings the gateway's
+    /// ecash balances closer to the federations' rebalancing weights, after
+    /// waiting for the previous transfer to arrive.
+    async fn rebalance_federations(&self) -> AdminResult<()> {
+        if let Some(pending) = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_pending_rebalance()
+            .await
+        {
+            self.await_pending_rebalance(pending).await?;
+        }
+
+        let configs = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_federation_configs()
+            .await;
+
+        let mut federations = BTreeMap::new();
+        for (federation_id, config) in configs {
+            if let Some(rebalance_weight) = config.rebalance_weight {
+                let balance = self
+                    .select_client(federation_id)
+                    .await?
+                    .value()
+                    .get_balance()
+                    .await;
+                federations.insert(federation_id, (balance, rebalance_weight));
+            }
+        }
+
+        let Some(transfer) = rebalance::plan_rebalance(&federations, self.rebalance_min_amount)
+        else {
+            debug!(target: LOG_GATEWAY, "Federations are balanced, nothing to rebalance");
+            return Ok(());
+        };
+
+        let amount = Amount::from_sats(transfer.amount.to_sat());
+
+        match self
+            .rebalance_into_lightning_node(transfer.source, amount)
+            .await
+        {
+            Ok(()) => {
+                // The funds have left the source federation, so we must not fall back to
+                // a peg-out, which would move them a second time
+                return self
+                    .rebalance_out_of_lightning_node(transfer.destination, amount)
+                    .await
+                    .context("Funds remain in our lightning node")
+                    .map_err(AdminGatewayError::Unexpected);
+            }
+            Err(err) => {
+                warn!(
+                    target: LOG_GATEWAY,
+                    err = %err.fmt_compact_anyhow(),
+                    "Failed to rebalance over lightning, falling back to a peg-out"
+                );
+            }
+        }
+
+        let (deposit_operation_id, address, _) = self
+            .select_client(transfer.destination)
+            .await?
+            .value()
+            .get_first_module::<WalletClientModule>()?
+            .allocate_deposit_address_expert_only(())
+            .await?;
+
+        // The source federation pays the peg-out fees out of the planned amount,
+        // such that its balance decreases by exactly the planned amount
+        let fees = self
+            .select_client(transfer.source)
+            .await?
+            .value()
+            .get_first_module::<WalletClientModule>()?
+            .get_withdraw_fees(&address, transfer.amount)
+            .await?
+            .amount();
+
+        if transfer.amount * MAX_REBALANCE_FEE_PERCENT < fees * 100 {
+            info!(
+                target: LOG_GATEWAY,
+                amount = %transfer.amount,
+                %fees,
+                "Postponing rebalancing, peg-out fees are too high"
+            );
+            return Ok(());
+        }
+
+        let pending = PendingRebalance {
+            source: transfer.source,
+            destination: transfer.destination,
+            amount: transfer.amount - fees,
+            fees,
+            deposit_operation_id,
+            created_at_secs: duration_since_epoch().as_secs(),
+        };
+
+        info!(
+            target: LOG_GATEWAY,
+            source = %pending.source,
+            destination = %pending.destination,
+            amount = %pending.amount,
+            fees = %pending.fees,
+            "Rebalancing federations"
+        );
+
+        // We persist the transfer before sending it, such that a restart while the
+        // peg-out is in flight does not lead us to plan a second transfer
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.save_pending_rebalance(&pending).await;
+        dbtx.commit_tx().await;
+
+        match self
+            .handle_withdraw_msg(WithdrawPayload {
+                federation_id: pending.source,
+                amount: BitcoinAmountOrAll::Amount(pending.amount),
+                address: address.into_unchecked(),
+            })
+            .await
+        {
+            Ok(WithdrawResponse { txid, fees }) => {
+                info!(
+                    target: LOG_GATEWAY,
+                    %txid,
+                    fees = ?fees,
+                    "Sent rebalancing peg-out"
+                );
+
+                Ok(())
+            }
+            Err(err) => {
+                let mut dbtx = self.gateway_db.begin_transaction().await;
+                dbtx.remove_pending_rebalance().await;
+                dbtx.commit_tx().await;
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Moves `amount` from our ecash in `federation_id` into our lightning node
+    /// by paying an invoice of our node as a regular user of the federation.
+    ///
+    /// The payment has to be routed by another gateway of the federation, if we
+    /// routed it ourselves we would claim the payment's ecash in the same
+    /// federation we paid it from and the funds would not move.
+    async fn rebalance_into_lightning_node(
+        &self,
+        federation_id: FederationId,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        self.sweep_rebalance_client(federation_id).await?;
+
+        let rebalance_client = self.rebalance_client(federation_id).await?;
+        let lightning = rebalance_client.get_first_module::<LightningClientModule>()?;
+        let gateway = self.select_other_gateway(&lightning).await?;
+
+        let invoice = self
+            .get_lightning_context()
+            .await?
+            .lnrpc
+            .create_invoice(CreateInvoiceRequest {
+                payment_hash: None,
+                amount_msat: amount.msats,
+                expiry_secs: REBALANCE_INVOICE_EXPIRY_SECS,
+                description: Some(InvoiceDescription::Direct("Rebalancing".to_string())),
+            })
+            .await?
+            .invoice;
+
+        let invoice = Bolt11Invoice::from_str(&invoice)
+            .map_err(|e| anyhow!("Our lightning node created an invalid invoice: {e}"))?;
+
+        // The gateway routing the payment charges its fee on top of the amount
+        let fee_budget = Amount::from_msats(amount.msats * MAX_REBALANCE_FEE_PERCENT / 100);
+
+        Self::transfer_ecash(
+            &self.select_client(federation_id).await?.into_value(),
+            &rebalance_client,
+            amount + fee_budget,
+        )
+        .await?;
+
+        let operation_id = lightning.send(invoice, Some(gateway), Value::Null).await?;
+
+        let state = lightning
+            .await_final_send_operation_state(operation_id)
+            .await?;
+
+        // Return the change and the refund, if the payment failed
+        if let Err(err) = self.sweep_rebalance_client(federation_id).await {
+            warn!(
+                target: LOG_GATEWAY,
+                err = %err.fmt_compact_anyhow(),
+                %federation_id,
+                "Failed to return the funds of the rebalancing client"
+            );
+        }
+
+        ensure!(
+            state == FinalSendOperationState::Success,
+            "Payment into our lightning node did not succeed: {state:?}"
+        );
+
+        info!(
+            target: LOG_GATEWAY,
+            %federation_id,
+            %amount,
+            "Moved ecash into our lightning node"
+        );
+
+        Ok(())
+    }
+
+    /// Moves `amount` from our lightning node into our ecash in
+    /// `federation_id` by paying an invoice another gateway of the federation
+    /// created for us as a regular user of the federation.
+    async fn rebalance_out_of_lightning_node(
+        &self,
+        federation_id: FederationId,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        // Those are the ldk defaults
+        const MAX_DELAY: u64 = 1008;
+
+        let rebalance_client = self.rebalance_client(federation_id).await?;
+        let lightning = rebalance_client.get_first_module::<LightningClientModule>()?;
+        let gateway = self.select_other_gateway(&lightning).await?;
+
+        let (invoice, operation_id) = lightning
+            .receive(
+                amount,
+                REBALANCE_INVOICE_EXPIRY_SECS,
+                Bolt11InvoiceDescription::Direct("Rebalancing".to_string()),
+                Some(gateway),
+                Value::Null,
+            )
+            .await?;
+
+        let max_fee = Amount::from_msats(amount.msats * MAX_REBALANCE_FEE_PERCENT / 100);
+
+        self.get_lightning_context()
+            .await?
+            .lnrpc
+            .pay(invoice, MAX_DELAY, max_fee)
+            .await?;
+
+        let state = lightning
+            .await_final_receive_operation_state(operation_id)
+            .await?;
+
+        ensure!(
+            state == FinalReceiveOperationState::Claimed,
+            "Payment out of our lightning node was not claimed: {state:?}"
+        );
+
+        self.sweep_rebalance_client(federation_id).await?;
+
+        info!(
+            target: LOG_GATEWAY,
+            %federation_id,
+            %amount,
+            "Moved funds from our lightning node into ecash"
+        );
+
+        Ok(())
+    }
+
+    /// Returns the client we use to rebalance over lightning as a regular user
+    /// of the federation, opening it on first use.
+    async fn rebalance_client(&self, federation_id: FederationId) -> AdminResult<ClientHandleArc> {
+        let mut clients = self.rebalance_clients.lock().await;
+
+        if let Some(client) = clients.get(&federation_id) {
+            return Ok(client.clone());
+        }
+
+        let config = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_federation_config(federation_id)
+            .await
+            .ok_or(FederationNotConnected {
+                federation_id_prefix: federation_id.to_prefix(),
+            })?;
+
+        let client = self
+            .client_builder
+            .build_rebalance_client(config, &self.gateway_db, &self.mnemonic)
+            .await?;
+
+        clients.insert(federation_id, client.clone());
+
+        Ok(client)
+    }
+
+    /// Selects a gateway vetted by the federation other than ourselves.
+    async fn select_other_gateway(
+        &self,
+        lightning: &LightningClientModule,
+    ) -> anyhow::Result<SafeUrl> {
+        lightning
+            .vetted_gateways()
+            .await?
+            .into_iter()
+            .find(|gateway| *gateway != self.versioned_api)
+            .context("The federation has not vetted another gateway")
+    }
+
+    /// Returns all ecash of our rebalancing client in `federation_id` to the
+    /// gateway's client.
+    async fn sweep_rebalance_client(&self, federation_id: FederationId) -> anyhow::Result<()> {
+        let rebalance_client = self.rebalance_client(federation_id).await?;
+
+        let balance = rebalance_client.get_balance().await;
+
+        if balance == Amount::ZERO {
+            return Ok(());
+        }
+
+        Self::transfer_ecash(
+            &rebalance_client,
+            &self.select_client(federation_id).await?.into_value(),
+            balance,
+        )
+        .await
+    }
+
+    /// Spends notes worth at least `amount` from one client and reissues them
+    /// with another client of the same federation.
+    async fn transfer_ecash(
+        source: &ClientHandleArc,
+        destination: &ClientHandleArc,
+        amount: Amount,
+    ) -> anyhow::Result<()> {
+        let (_, notes) = source
+            .get_first_module::<MintClientModule>()?
+            .spend_notes_with_selector(
+                &SelectNotesWithAtleastAmount,
+                amount,
+                REBALANCE_TRANSFER_TIMEOUT,
+                false,
+                (),
+            )
+            .await?;
+
+        let mint = destination.get_first_module::<MintClientModule>()?;
+
+        let operation_id = mint.reissue_external_notes(notes, ()).await?;
+
+        let mut updates = mint
+            .subscribe_reissue_external_notes(operation_id)
+            .await?
+            .into_stream();
+
+        while let Some(update) = updates.next().await {
+            if let ReissueExternalNotesState::Failed(e) = update {
+                return Err(anyhow!("Failed to reissue the transferred notes: {e}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the pending rebalancing transfer has been claimed by its
+    /// destination federation, or gives up on it once it timed out.
+    async fn await_pending_rebalance(&self, pending: PendingRebalance) -> AdminResult<()> {
+        let deadline = Duration::from_secs(pending.created_at_secs) + REBALANCE_TRANSFER_TIMEOUT;
+        let remaining = deadline.saturating_sub(duration_since_epoch());
+
+        let claimed = fedimint_core::runtime::timeout(
+            remaining,
+            self.select_client(pending.destination)
+                .await?
+                .value()
+                .get_first_module::<WalletClientModule>()?
+                .await_num_deposits_by_operation_id(pending.deposit_operation_id, 1),
+        )
+        .await;
+
+        match claimed {
+            Ok(result) => {
+                result?;
+
+                info!(
+                    target: LOG_GATEWAY,
+                    source = %pending.source,
+                    destination = %pending.destination,
+                    amount = %pending.amount,
+                    fees = %pending.fees,
+                    "Rebalancing transfer arrived"
+                );
+            }
+            Err(_) => {
+                warn!(
+                    target: LOG_GATEWAY,
+                    source = %pending.source,
+                    destination = %pending.destination,
+                    amount = %pending.amount,
+                    "Rebalancing transfer did not arrive in time, giving up on it"
+                );
+            }
+        }
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.remove_pending_rebalance().await;
+        dbtx.commit_tx().await;
+
+        Ok(())
+    }
+
     /// Checks the Gateway's current state and returns the proper
     /// `LightningContext` if it is available. Sometimes the lightning node
     /// will not be connected and this will return an error.
@@ -2136,6 +2722,8 @@ pub async fn routing_info_v2(
                 // The base fee ensures that the gateway does not loose sats receiving the payment
                 // due to fees paid on the transaction funding the incoming contract
                 receive_fee: transaction_fee,
+                supports_bolt12_invoices: context.lnrpc.supports_bolt12_invoices(),
+                supports_multi_path_payments: context.lnrpc.supports_multi_path_payments(),
             }))
     }
 
@@ -2145,8 +2733,37 @@ async fn send_payment_v2(
         &self,
         payload: SendPaymentPayload,
     ) -> Result<std::result::Result<[u8; 32], Signature>> {
-        self.select_client(payload.federation_id)
-            .await?
+        let client = self.select_client(payload.federation_id).await?;
+
+        // A client may resubmit a payment that has already been started, which
+        // must not count towards the limits a second time.
+        let _permit = if client
+            .value()
+            .operation_exists(OperationId::from_encodable(&payload.contract))
+            .await
+        {
+            None
+        } else {
+            // The contract of a multi-path payment only covers a part of the
+            // invoice, so the maximum payment amount is checked against the
+            // entire invoice amount. Invoices without an amount are rejected by
+            // the gateway module.
+            let payment_amount = payload
+                .invoice
+                .amount_milli_satoshis()
+                .map_or(payload.contract.amount, Amount::from_msats);
+
+            Some(
+                self.admit_outgoing_payment(
+                    payload.federation_id,
+                    payment_amount,
+                    payload.contract.amount,
+                )
+                .await?,
+            )
+        };
+
+        client
             .value()
             .get_first_module::<GatewayClientModuleV2>()
             .expect("Must have client module")
@@ -2156,6 +2773,56 @@ async fn send_payment_v2(
             .map_err(PublicGatewayError::LNv2)
     }
 
+    /// Admits an outgoing payment of `amount`, which may be a single part of a
+    /// multi-path payment of `payment_amount`, on behalf of a federation if it
+    /// stays within the federation's payment limits. The payment counts as in
+    /// flight until the returned permit is dropped.
+    async fn admit_outgoing_payment(
+        &self,
+        federation_id: FederationId,
+        payment_amount: Amount,
+        amount: Amount,
+    ) -> Result<PaymentPermit> {
+        self.payment_limiter
+            .admit(federation_id, payment_amount, amount)
+            .await
+            .map_err(PublicGatewayError::PaymentLimit)
+    }
+
+    /// For the LNv2 protocol, this requests an invoice for a Bolt12 offer from
+    /// the offer's issuer via the connected Lightning node. The invoice is
+    /// returned to the client such that it can commit to its payment hash in
+    /// an outgoing contract before the gateway pays it.
+    async fn fetch_bolt12_invoice_v2(
+        &self,
+        payload: Bolt12InvoicePayload,
+    ) -> Result<LightningInvoice> {
+        if self
+            .routing_info_v2(&payload.federation_id)
+            .await?
+            .is_none()
+        {
+            return Err(PublicGatewayError::LNv2(LNv2Error::OutgoingPayment(
+                anyhow!("Federation {} does not exist", payload.federation_id),
+            )));
+        }
+
+        let lightning_context = self.get_lightning_context().await?;
+
+        if !lightning_context.lnrpc.supports_bolt12_invoices() {
+            return Err(PublicGatewayError::LNv2(LNv2Error::OutgoingPayment(
+                anyhow!("The lightning node does not support paying offers"),
+            )));
+        }
+
+        let invoice = lightning_context
+            .lnrpc
+            .fetch_offer_invoice(payload.offer, payload.amount, payload.payer_note)
+            .await?;
+
+        Ok(LightningInvoice::Bolt12(invoice))
+    }
+
     /// For the LNv2 protocol, this will create an invoice by fetching it from
     /// the connected Lightning node, then save the payment hash so that
     /// incoming lightning payments can be matched as a receive attempt to a
@@ -2361,13 +3028,13 @@ async fn complete_htlc(&self, htlc_response: InterceptPaymentResponse) {
 
     async fn is_direct_swap(
         &self,
-        invoice: &Bolt11Invoice,
+        invoice: &LightningInvoice,
     ) -> anyhow::Result<Option<(IncomingContract, ClientHandleArc)>> {
         let lightning_context = self.get_lightning_context().await?;
-        if lightning_context.lightning_public_key == invoice.get_payee_pub_key() {
+        if lightning_context.lightning_public_key == invoice.payee_pub_key() {
             let (contract, client) = self
                 .get_registered_incoming_contract_and_client_v2(
-                    PaymentImage::Hash(*invoice.payment_hash()),
+                    PaymentImage::Hash(invoice.payment_hash()),
                     invoice
                         .amount_milli_satoshis()
                         .expect("The amount invoice has been previously checked"),
@@ -2381,16 +3048,37 @@ async fn is_direct_swap(
 
     async fn pay(
         &self,
-        invoice: Bolt11Invoice,
+        invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         max_delay: u64,
         max_fee: Amount,
     ) -> std::result::Result<[u8; 32], LightningRpcError> {
         let lightning_context = self.get_lightning_context().await?;
-        lightning_context
-            .lnrpc
-            .pay(invoice, max_delay, max_fee)
-            .await
-            .map(|response| response.preimage.0)
+        match (invoice, part_amount) {
+            (LightningInvoice::Bolt11(invoice), None) => {
+                lightning_context
+                    .lnrpc
+                    .pay(invoice, max_delay, max_fee)
+                    .await
+            }
+            (LightningInvoice::Bolt11(invoice), Some(part_amount)) => {
+                lightning_context
+                    .lnrpc
+                    .pay_partial(invoice, part_amount, max_delay, max_fee)
+                    .await
+            }
+            (LightningInvoice::Bolt12(invoice), None) => {
+                lightning_context
+                    .lnrpc
+                    .pay_bolt12_invoice(invoice, max_delay, max_fee)
+                    .await
+            }
+            (LightningInvoice::Bolt12(..), Some(..)) => Err(LightningRpcError::FailedPayment {
+                failure_reason: "Multi-path payments are only supported for Bolt11 invoices"
+                    .to_string(),
+            }),
+        }
+        .map(|response| response.preimage.0)
     }
 
     async fn min_contract_amount(
@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
+use fedimint_core::db::Database;
+use fedimint_gateway_common::PaymentLimits;
+use fedimint_gateway_server_db::{AdmittedPayment, GatewayDbtxNcExt as _, PaymentVolume};
+
+use crate::error::PaymentLimitError;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Enforces the [`PaymentLimits`] of every federation on the outgoing payments
+/// the gateway makes on its behalf.
+///
+/// The payments admitted within the last day are persisted in the gateway's
+/// database, such that the hourly and daily volumes survive a restart. A
+/// payment counts towards the volume as soon as it is admitted, regardless of
+/// whether it succeeds. The number of payments in flight is only tracked in
+/// memory.
+#[derive(Debug, Clone)]
+pub struct PaymentLimiter {
+    gateway_db: Database,
+    in_flight: Arc<Mutex<BTreeMap<FederationId, u64>>>,
+    /// Serializes admissions, such that concurrent payments of the same
+    /// federation can not both be admitted based on the same volume.
+    admission: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl PaymentLimiter {
+    pub fn new(gateway_db: Database) -> Self {
+        Self {
+            gateway_db,
+            in_flight: Arc::default(),
+            admission: Arc::default(),
+        }
+    }
+
+    /// Admits an outgoing payment of `amount` if it stays within the limits of
+    /// the federation. The maximum payment amount applies to `payment_amount`,
+    /// the amount of the entire payment, since `amount` may only be a single
+    /// part of a multi-path payment. The payment counts as in flight until the
+    /// returned permit is dropped.
+    pub async fn admit(
+        &self,
+        federation_id: FederationId,
+        payment_amount: Amount,
+        amount: Amount,
+    ) -> Result<PaymentPermit, PaymentLimitError> {
+        self.admit_at(
+            federation_id,
+            payment_amount,
+            amount,
+            fedimint_core::time::now(),
+        )
+        .await
+    }
+
+    async fn admit_at(
+        &self,
+        federation_id: FederationId,
+        payment_amount: Amount,
+        amount: Amount,
+        now: SystemTime,
+    ) -> Result<PaymentPermit, PaymentLimitError> {
+        let _admission = self.admission.lock().await;
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        let limits = dbtx.load_payment_limits(federation_id).await;
+
+        if let Some(max) = limits.max_payment {
+            if max < payment_amount {
+                return Err(PaymentLimitError::MaxPaymentExceeded {
+                    amount: payment_amount,
+                    max,
+                });
+            }
+        }
+
+        let permit = self.acquire_in_flight(federation_id, &limits)?;
+
+        let now_secs = now
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+
+        let mut volume = dbtx.load_payment_volume(federation_id).await;
+
+        volume
+            .payments
+            .retain(|payment| now_secs.saturating_sub(payment.admitted_at_secs) <= DAY.as_secs());
+
+        if let Some(max) = limits.max_hourly_volume {
+            let remaining = max.saturating_sub(volume_since(&volume, now_secs, HOUR));
+
+            if remaining < amount {
+                return Err(PaymentLimitError::HourlyVolumeExceeded { amount, remaining });
+            }
+        }
+
+        if let Some(max) = limits.max_daily_volume {
+            let remaining = max.saturating_sub(volume_since(&volume, now_secs, DAY));
+
+            if remaining < amount {
+                return Err(PaymentLimitError::DailyVolumeExceeded { amount, remaining });
+            }
+        }
+
+        volume.payments.push(AdmittedPayment {
+            admitted_at_secs: now_secs,
+            amount,
+        });
+
+        dbtx.save_payment_volume(federation_id, &volume).await;
+        dbtx.commit_tx().await;
+
+        Ok(permit)
+    }
+
+    fn acquire_in_flight(
+        &self,
+        federation_id: FederationId,
+        limits: &PaymentLimits,
+    ) -> Result<PaymentPermit, PaymentLimitError> {
+        let mut in_flight = self.in_flight.lock().expect("lock poisoned");
+        let federation_in_flight = in_flight.entry(federation_id).or_default();
+
+        if let Some(max) = limits.max_in_flight {
+            if max <= *federation_in_flight {
+                return Err(PaymentLimitError::TooManyPaymentsInFlight { max });
+            }
+        }
+
+        *federation_in_flight += 1;
+
+        Ok(PaymentPermit {
+            limiter: self.clone(),
+            federation_id,
+        })
+    }
+
+    /// Forgets the payments in flight of a federation the gateway has left.
+    /// Its persisted volume is removed together with its configuration.
+    pub fn remove_federation(&self, federation_id: &FederationId) {
+        self.in_flight
+            .lock()
+            .expect("lock poisoned")
+            .remove(federation_id);
+    }
+}
+
+fn volume_since(volume: &PaymentVolume, now_secs: u64, window: Duration) -> Amount {
+    volume
+        .payments
+        .iter()
+        .rev()
+        .take_while(|payment| now_secs.saturating_sub(payment.admitted_at_secs) < window.as_secs())
+        .map(|payment| payment.amount)
+        .sum()
+}
+
+/// Marks an admitted payment as in flight for as long as it is held.
+#[derive(Debug)]
+pub struct PaymentPermit {
+    limiter: PaymentLimiter,
+    federation_id: FederationId,
+}
+
+impl Drop for PaymentPermit {
+    fn drop(&mut self) {
+        if let Some(in_flight) = self
+            .limiter
+            .in_flight
+            .lock()
+            .expect("lock poisoned")
+            .get_mut(&self.federation_id)
+        {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use bitcoin::hashes::Hash as _;
+    use fedimint_core::Amount;
+    use fedimint_core::config::FederationId;
+    use fedimint_core::db::Database;
+    use fedimint_core::db::mem_impl::MemDatabase;
+    use fedimint_core::module::registry::ModuleDecoderRegistry;
+    use fedimint_gateway_common::PaymentLimits;
+    use fedimint_gateway_server_db::GatewayDbtxNcExt as _;
+
+    use super::{HOUR, PaymentLimiter};
+    use crate::error::PaymentLimitError;
+
+    fn federation(byte: u8) -> FederationId {
+        FederationId(bitcoin::hashes::sha256::Hash::from_byte_array([byte; 32]))
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    async fn limiter(limits: PaymentLimits) -> (Database, PaymentLimiter) {
+        let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
+
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.save_payment_limits(federation(0), &limits).await;
+        dbtx.commit_tx().await;
+
+        (db.clone(), PaymentLimiter::new(db))
+    }
+
+    #[tokio::test]
+    async fn max_payment_applies_to_entire_payment() {
+        let (_, limiter) = limiter(PaymentLimits {
+            max_payment: Some(Amount::from_sats(1_000)),
+            ..PaymentLimits::default()
+        })
+        .await;
+
+        // A part below the maximum of a payment above the maximum is rejected
+        assert!(matches!(
+            limiter
+                .admit_at(
+                    federation(0),
+                    Amount::from_sats(1_500),
+                    Amount::from_sats(500),
+                    time(0)
+                )
+                .await,
+            Err(PaymentLimitError::MaxPaymentExceeded { .. })
+        ));
+
+        assert!(
+            limiter
+                .admit_at(
+                    federation(0),
+                    Amount::from_sats(1_000),
+                    Amount::from_sats(500),
+                    time(0)
+                )
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn limits_payments_in_flight() {
+        let (_, limiter) = limiter(PaymentLimits {
+            max_in_flight: Some(1),
+            ..PaymentLimits::default()
+        })
+        .await;
+
+        let amount = Amount::from_sats(100);
+        let permit = limiter
+            .admit_at(federation(0), amount, amount, time(0))
+            .await
+            .expect("First payment is admitted");
+
+        assert!(matches!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(0))
+                .await,
+            Err(PaymentLimitError::TooManyPaymentsInFlight { max: 1 })
+        ));
+
+        // The limits of other federations are independent
+        assert!(
+            limiter
+                .admit_at(federation(1), amount, amount, time(0))
+                .await
+                .is_ok()
+        );
+
+        drop(permit);
+
+        assert!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(0))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn volume_windows_expire() {
+        let (_, limiter) = limiter(PaymentLimits {
+            max_hourly_volume: Some(Amount::from_sats(1_000)),
+            max_daily_volume: Some(Amount::from_sats(2_000)),
+            ..PaymentLimits::default()
+        })
+        .await;
+
+        let amount = Amount::from_sats(800);
+        assert!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(0))
+                .await
+                .is_ok()
+        );
+
+        assert!(matches!(
+            limiter.admit_at(federation(0), amount, amount, time(60)).await,
+            Err(PaymentLimitError::HourlyVolumeExceeded { remaining, .. })
+                if remaining == Amount::from_sats(200)
+        ));
+
+        let after_an_hour = HOUR.as_secs();
+        assert!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(after_an_hour))
+                .await
+                .is_ok()
+        );
+
+        assert!(matches!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(2 * after_an_hour))
+                .await,
+            Err(PaymentLimitError::DailyVolumeExceeded { remaining, .. })
+                if remaining == Amount::from_sats(400)
+        ));
+
+        // Payments admitted more than a day ago no longer count
+        let after_a_day = 24 * after_an_hour + 1;
+        assert!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(after_a_day))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn volume_survives_restart() {
+        let (db, limiter) = limiter(PaymentLimits {
+            max_daily_volume: Some(Amount::from_sats(1_000)),
+            ..PaymentLimits::default()
+        })
+        .await;
+
+        let amount = Amount::from_sats(600);
+        assert!(
+            limiter
+                .admit_at(federation(0), amount, amount, time(0))
+                .await
+                .is_ok()
+        );
+
+        let restarted = PaymentLimiter::new(db);
+
+        assert!(matches!(
+            restarted
+                .admit_at(federation(0), amount, amount, time(60))
+                .await,
+            Err(PaymentLimitError::DailyVolumeExceeded { .. })
+        ));
+    }
+}
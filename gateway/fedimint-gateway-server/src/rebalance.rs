@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
+
+/// A transfer of ecash from one federation to another that brings the
+/// gateway's balances closer to the federations' rebalancing weights.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RebalanceTransfer {
+    pub source: FederationId,
+    pub destination: FederationId,
+    pub amount: bitcoin::Amount,
+}
+
+/// Plans the next rebalancing transfer given the ecash balance and the
+/// rebalancing weight of every federation that takes part in rebalancing.
+///
+/// Each federation's target balance is its share of the total balance
+/// according to its weight. The transfer moves funds from the federation with
+/// the largest surplus to the federation with the largest deficit. Returns
+/// `None` if the transfer would be smaller than `min_amount`, since moving
+/// funds between federations is not free.
+pub fn plan_rebalance(
+    federations: &BTreeMap<FederationId, (Amount, u64)>,
+    min_amount: bitcoin::Amount,
+) -> Option<RebalanceTransfer> {
+    let total_weight = federations
+        .values()
+        .map(|(_, weight)| u128::from(*weight))
+        .sum::<u128>();
+
+    if total_weight == 0 {
+        return None;
+    }
+
+    let total_balance = federations
+        .values()
+        .map(|(balance, _)| u128::from(balance.msats))
+        .sum::<u128>();
+
+    // Positive for federations holding more than their target, negative for
+    // federations holding less
+    let deviations = federations
+        .iter()
+        .map(|(federation_id, (balance, weight))| {
+            let target = total_balance * u128::from(*weight) / total_weight;
+            let deviation =
+                i128::from(balance.msats) - i128::try_from(target).expect("Fits into i128");
+            (*federation_id, deviation)
+        })
+        .collect::<Vec<_>>();
+
+    let (source, surplus) = deviations
+        .iter()
+        .max_by_key(|(_, deviation)| *deviation)
+        .copied()?;
+    let (destination, deficit) = deviations
+        .iter()
+        .min_by_key(|(_, deviation)| *deviation)
+        .copied()?;
+
+    if surplus <= 0 || deficit >= 0 {
+        return None;
+    }
+
+    let amount_msats = surplus.min(-deficit).unsigned_abs();
+    let amount = bitcoin::Amount::from_sat(u64::try_from(amount_msats / 1000).ok()?);
+
+    if amount < min_amount {
+        return None;
+    }
+
+    Some(RebalanceTransfer {
+        source,
+        destination,
+        amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bitcoin::hashes::Hash as _;
+    use fedimint_core::Amount;
+    use fedimint_core::config::FederationId;
+
+    use super::{RebalanceTransfer, plan_rebalance};
+
+    fn federation(byte: u8) -> FederationId {
+        FederationId(bitcoin::hashes::sha256::Hash::from_byte_array([byte; 32]))
+    }
+
+    fn sats(sats: u64) -> bitcoin::Amount {
+        bitcoin::Amount::from_sat(sats)
+    }
+
+    #[test]
+    fn moves_surplus_to_largest_deficit() {
+        let federations = BTreeMap::from([
+            (federation(0), (Amount::from_sats(900_000), 1)),
+            (federation(1), (Amount::from_sats(100_000), 1)),
+            (federation(2), (Amount::from_sats(500_000), 1)),
+        ]);
+
+        // Every federation targets 500k sats
+        assert_eq!(
+            plan_rebalance(&federations, sats(1_000)),
+            Some(RebalanceTransfer {
+                source: federation(0),
+                destination: federation(1),
+                amount: sats(400_000),
+            })
+        );
+    }
+
+    #[test]
+    fn respects_weights() {
+        let federations = BTreeMap::from([
+            (federation(0), (Amount::from_sats(500_000), 1)),
+            (federation(1), (Amount::from_sats(500_000), 3)),
+        ]);
+
+        assert_eq!(
+            plan_rebalance(&federations, sats(1_000)),
+            Some(RebalanceTransfer {
+                source: federation(0),
+                destination: federation(1),
+                amount: sats(250_000),
+            })
+        );
+    }
+
+    #[test]
+    fn moves_no_more_than_the_deficit() {
+        let federations = BTreeMap::from([
+            (federation(0), (Amount::from_sats(800_000), 1)),
+            (federation(1), (Amount::from_sats(300_000), 1)),
+            (federation(2), (Amount::from_sats(400_000), 1)),
+        ]);
+
+        // The surplus of 300k exceeds the largest deficit of 200k
+        assert_eq!(
+            plan_rebalance(&federations, sats(1_000)),
+            Some(RebalanceTransfer {
+                source: federation(0),
+                destination: federation(1),
+                amount: sats(200_000),
+            })
+        );
+    }
+
+    #[test]
+    fn skips_small_and_impossible_transfers() {
+        let balanced = BTreeMap::from([
+            (federation(0), (Amount::from_sats(500_000), 1)),
+            (federation(1), (Amount::from_sats(500_000), 1)),
+        ]);
+        assert_eq!(plan_rebalance(&balanced, sats(1_000)), None);
+
+        let nearly_balanced = BTreeMap::from([
+            (federation(0), (Amount::from_sats(500_500), 1)),
+            (federation(1), (Amount::from_sats(499_500), 1)),
+        ]);
+        assert_eq!(plan_rebalance(&nearly_balanced, sats(1_000)), None);
+
+        let unweighted = BTreeMap::from([
+            (federation(0), (Amount::from_sats(900_000), 0)),
+            (federation(1), (Amount::from_sats(100_000), 0)),
+        ]);
+        assert_eq!(plan_rebalance(&unweighted, sats(1_000)), None);
+
+        assert_eq!(plan_rebalance(&BTreeMap::new(), sats(1_000)), None);
+    }
+}
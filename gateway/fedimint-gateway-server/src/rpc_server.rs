@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axum::extract::Request;
+use axum::extract::{Request, State};
 use axum::http::{StatusCode, header};
 use axum::middleware::{self, Next};
 use axum::response::IntoResponse;
@@ -10,29 +10,34 @@
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::FmtCompact;
 use fedimint_gateway_common::{
-    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
-    CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    CloseChannelsWithPeerRequest, ConfigPayload, ConnectFedPayload,
-    CreateInvoiceForOperatorPayload, CreateOfferPayload, DepositAddressPayload,
-    DepositAddressRecheckPayload, GATEWAY_INFO_ENDPOINT, GATEWAY_INFO_POST_ENDPOINT,
-    GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT,
-    GetInvoiceRequest, InfoPayload, LEAVE_FED_ENDPOINT, LIST_ACTIVE_CHANNELS_ENDPOINT,
-    LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload, ListTransactionsPayload, MNEMONIC_ENDPOINT,
-    OPEN_CHANNEL_ENDPOINT, OpenChannelRequest, PAY_INVOICE_FOR_OPERATOR_ENDPOINT,
-    PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT,
-    PayInvoiceForOperatorPayload, PayOfferPayload, PaymentLogPayload, PaymentSummaryPayload,
-    RECEIVE_ECASH_ENDPOINT, ReceiveEcashPayload, SEND_ONCHAIN_ENDPOINT, SET_FEES_ENDPOINT,
-    SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest, SetFeesPayload, SpendEcashPayload,
+    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, API_TOKENS_ENDPOINT, ApiScope, BACKUP_ENDPOINT,
+    BackupPayload, CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
+    CREATE_API_TOKEN_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, CloseChannelsWithPeerRequest, ConfigPayload,
+    ConnectFedPayload, CreateApiTokenPayload, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    DepositAddressPayload, DepositAddressRecheckPayload, GATEWAY_INFO_ENDPOINT,
+    GATEWAY_INFO_POST_ENDPOINT, GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT,
+    GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GetInvoiceRequest, InfoPayload, LEAVE_FED_ENDPOINT,
+    LIST_ACTIVE_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload,
+    ListTransactionsPayload, MNEMONIC_ENDPOINT, OPEN_CHANNEL_ENDPOINT, OpenChannelRequest,
+    PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT,
+    PAYMENT_SUMMARY_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload, PaymentLogPayload,
+    PaymentSummaryPayload, RECEIVE_ECASH_ENDPOINT, REVOKE_API_TOKEN_ENDPOINT, ReceiveEcashPayload,
+    RevokeApiTokenPayload, SEND_ONCHAIN_ENDPOINT, SET_FEES_ENDPOINT, SET_PAYMENT_LIMITS_ENDPOINT,
+    SET_REBALANCE_WEIGHT_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest,
+    SetFeesPayload, SetPaymentLimitsPayload, SetRebalanceWeightPayload, SpendEcashPayload,
     V1_API_ENDPOINT, WITHDRAW_ENDPOINT, WithdrawPayload,
 };
 use fedimint_ln_common::gateway_endpoint_constants::{
     GET_GATEWAY_ID_ENDPOINT, PAY_INVOICE_ENDPOINT,
 };
 use fedimint_lnv2_common::endpoint_constants::{
-    CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
+    BOLT12_INVOICE_ENDPOINT, CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT,
+    SEND_PAYMENT_ENDPOINT,
+};
+use fedimint_lnv2_common::gateway_api::{
+    Bolt12InvoicePayload, CreateBolt11InvoicePayload, SendPaymentPayload,
 };
-use fedimint_lnv2_common::gateway_api::{CreateBolt11InvoicePayload, SendPaymentPayload};
 use fedimint_logging::LOG_GATEWAY;
 use hex::ToHex;
 use serde_json::json;
@@ -93,19 +98,27 @@ fn extract_bearer_token(request: &Request) -> Result<String, StatusCode> {
 /// Middleware to authenticate an incoming request. Routes that are
 /// authenticated with this middleware always require a Bearer token to be
 /// supplied in the Authorization header.
+///
+/// The token is either the gateway's password, which grants access to every
+/// route, or an API token that is granted the route's `scope`. Routes without a
+/// scope are only accessible with the gateway's password.
 async fn auth_middleware(
+    State(scope): State<Option<ApiScope>>,
     Extension(gateway): Extension<Arc<Gateway>>,
     request: Request,
     next: Next,
 ) -> Result<impl IntoResponse, StatusCode> {
     let token = extract_bearer_token(&request)?;
-    if bcrypt::verify(token, &gateway.bcrypt_password_hash.to_string())
-        .expect("Bcrypt hash is valid since we just stringified it")
-    {
-        return Ok(next.run(request).await);
-    }
 
-    Err(StatusCode::UNAUTHORIZED)
+    gateway.authorize(scope, &token).await?;
+
+    Ok(next.run(request).await)
+}
+
+/// Requires requests to the given `routes` to be authenticated with either the
+/// gateway's password or an API token granted `scope`.
+fn authenticated(routes: Router, scope: Option<ApiScope>) -> Router {
+    routes.layer(middleware::from_fn_with_state(scope, auth_middleware))
 }
 
 /// Public routes that are used in the LNv1 protocol
@@ -124,11 +137,13 @@ fn lnv2_routes() -> Router {
             CREATE_BOLT11_INVOICE_ENDPOINT,
             post(create_bolt11_invoice_v2),
         )
+        .route(BOLT12_INVOICE_ENDPOINT, post(fetch_bolt12_invoice_v2))
 }
 
 /// Gateway Webserver Routes. The gateway supports three types of routes
 /// - Always Authenticated: these routes always require a Bearer token. Used by
-///   gateway administrators.
+///   gateway administrators. Most of them can also be accessed with an API
+///   token that is granted the route's [`ApiScope`].
 /// - Authenticated after config: these routes are unauthenticated before
 ///   configuring the gateway to allow the user to set a password. After setting
 ///   the password, they become authenticated.
@@ -146,13 +161,21 @@ fn v1_routes(gateway: Arc<Gateway>, task_group: TaskGroup) -> Router {
         public_routes = public_routes.merge(lnv2_routes());
     }
 
-    // Authenticated routes used for gateway administration
-    let authenticated_routes = Router::new()
-        .route(ADDRESS_ENDPOINT, post(address))
-        .route(WITHDRAW_ENDPOINT, post(withdraw))
-        .route(CONNECT_FED_ENDPOINT, post(connect_fed))
-        .route(LEAVE_FED_ENDPOINT, post(leave_fed))
-        .route(BACKUP_ENDPOINT, post(backup))
+    // Authenticated routes used for gateway administration, grouped by the
+    // scope an API token requires to access them
+    let info_routes = Router::new()
+        .route(GET_INVOICE_ENDPOINT, post(get_invoice))
+        .route(LIST_ACTIVE_CHANNELS_ENDPOINT, get(list_active_channels))
+        .route(LIST_TRANSACTIONS_ENDPOINT, post(list_transactions))
+        .route(GET_BALANCES_ENDPOINT, get(get_balances))
+        .route(PAYMENT_LOG_ENDPOINT, post(payment_log))
+        .route(PAYMENT_SUMMARY_ENDPOINT, post(payment_summary))
+        .route(CONFIGURATION_ENDPOINT, post(configuration))
+        // FIXME: deprecated >= 0.3.0
+        .route(GATEWAY_INFO_POST_ENDPOINT, post(handle_post_info))
+        .route(GATEWAY_INFO_ENDPOINT, get(info));
+
+    let payment_routes = Router::new()
         .route(
             CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
             post(create_invoice_for_operator),
@@ -166,33 +189,48 @@ fn v1_routes(gateway: Arc<Gateway>, task_group: TaskGroup) -> Router {
             post(pay_invoice_operator),
         )
         .route(PAY_OFFER_FOR_OPERATOR_ENDPOINT, post(pay_offer_operator))
-        .route(GET_INVOICE_ENDPOINT, post(get_invoice))
+        .route(SPEND_ECASH_ENDPOINT, post(spend_ecash));
+
+    let onchain_routes = Router::new()
+        .route(ADDRESS_ENDPOINT, post(address))
+        .route(WITHDRAW_ENDPOINT, post(withdraw))
         .route(GET_LN_ONCHAIN_ADDRESS_ENDPOINT, get(get_ln_onchain_address))
         .route(OPEN_CHANNEL_ENDPOINT, post(open_channel))
         .route(
             CLOSE_CHANNELS_WITH_PEER_ENDPOINT,
             post(close_channels_with_peer),
         )
-        .route(LIST_ACTIVE_CHANNELS_ENDPOINT, get(list_active_channels))
-        .route(LIST_TRANSACTIONS_ENDPOINT, post(list_transactions))
         .route(SEND_ONCHAIN_ENDPOINT, post(send_onchain))
-        .route(ADDRESS_RECHECK_ENDPOINT, post(recheck_address))
-        .route(GET_BALANCES_ENDPOINT, get(get_balances))
-        .route(SPEND_ECASH_ENDPOINT, post(spend_ecash))
-        .route(MNEMONIC_ENDPOINT, get(mnemonic))
-        .route(STOP_ENDPOINT, get(stop))
-        .route(PAYMENT_LOG_ENDPOINT, post(payment_log))
-        .route(PAYMENT_SUMMARY_ENDPOINT, post(payment_summary))
+        .route(ADDRESS_RECHECK_ENDPOINT, post(recheck_address));
+
+    let federation_routes = Router::new()
+        .route(CONNECT_FED_ENDPOINT, post(connect_fed))
+        .route(LEAVE_FED_ENDPOINT, post(leave_fed))
+        .route(BACKUP_ENDPOINT, post(backup))
         .route(SET_FEES_ENDPOINT, post(set_fees))
-        .route(CONFIGURATION_ENDPOINT, post(configuration))
-        // FIXME: deprecated >= 0.3.0
-        .route(GATEWAY_INFO_POST_ENDPOINT, post(handle_post_info))
-        .route(GATEWAY_INFO_ENDPOINT, get(info))
-        .layer(middleware::from_fn(auth_middleware));
+        .route(SET_REBALANCE_WEIGHT_ENDPOINT, post(set_rebalance_weight))
+        .route(SET_PAYMENT_LIMITS_ENDPOINT, post(set_payment_limits));
+
+    let mnemonic_routes = Router::new().route(MNEMONIC_ENDPOINT, get(mnemonic));
+
+    // Routes that can only be accessed with the gateway's password
+    let password_routes = Router::new()
+        .route(STOP_ENDPOINT, get(stop))
+        .route(CREATE_API_TOKEN_ENDPOINT, post(create_api_token))
+        .route(API_TOKENS_ENDPOINT, get(list_api_tokens))
+        .route(REVOKE_API_TOKEN_ENDPOINT, post(revoke_api_token));
 
     Router::new()
         .merge(public_routes)
-        .merge(authenticated_routes)
+        .merge(authenticated(info_routes, Some(ApiScope::Info)))
+        .merge(authenticated(payment_routes, Some(ApiScope::Payments)))
+        .merge(authenticated(onchain_routes, Some(ApiScope::Onchain)))
+        .merge(authenticated(
+            federation_routes,
+            Some(ApiScope::Federations),
+        ))
+        .merge(authenticated(mnemonic_routes, Some(ApiScope::Mnemonic)))
+        .merge(authenticated(password_routes, None))
         .layer(Extension(gateway))
         .layer(Extension(task_group))
         .layer(CorsLayer::permissive())
@@ -319,6 +357,24 @@ async fn set_fees(
     Ok(Json(json!(())))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_rebalance_weight(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetRebalanceWeightPayload>,
+) -> Result<impl IntoResponse, AdminGatewayError> {
+    gateway.handle_set_rebalance_weight_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_payment_limits(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetPaymentLimitsPayload>,
+) -> Result<impl IntoResponse, AdminGatewayError> {
+    gateway.handle_set_payment_limits_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn get_ln_onchain_address(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -413,6 +469,15 @@ async fn create_bolt11_invoice_v2(
     Ok(Json(json!(invoice)))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn fetch_bolt12_invoice_v2(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<Bolt12InvoicePayload>,
+) -> Result<impl IntoResponse, PublicGatewayError> {
+    let invoice = gateway.fetch_bolt12_invoice_v2(payload).await?;
+    Ok(Json(json!(invoice)))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn spend_ecash(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -439,6 +504,32 @@ async fn mnemonic(
     Ok(Json(json!(words)))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn create_api_token(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<CreateApiTokenPayload>,
+) -> Result<impl IntoResponse, AdminGatewayError> {
+    let response = gateway.handle_create_api_token_msg(payload).await?;
+    Ok(Json(json!(response)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn list_api_tokens(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<impl IntoResponse, AdminGatewayError> {
+    let api_tokens = gateway.handle_list_api_tokens_msg().await?;
+    Ok(Json(json!(api_tokens)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn revoke_api_token(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<RevokeApiTokenPayload>,
+) -> Result<impl IntoResponse, AdminGatewayError> {
+    gateway.handle_revoke_api_token_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn stop(
     Extension(task_group): Extension<TaskGroup>,
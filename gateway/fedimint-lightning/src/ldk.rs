@@ -727,6 +727,9 @@ fn create_offer(
         Ok(offer.to_string())
     }
 
+    // `ldk-node` requests and pays the invoice for an offer in one step and
+    // handles the invoice internally, so we cannot implement
+    // `ILnRpcClient::fetch_offer_invoice` and leave offers unsupported for LNv2.
     async fn pay_offer(
         &self,
         offer: String,
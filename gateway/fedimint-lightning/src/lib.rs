@@ -22,6 +22,7 @@
 use fedimint_ln_common::route_hints::RouteHint;
 use fedimint_logging::LOG_LIGHTNING;
 use futures::stream::BoxStream;
+use lightning::offers::invoice::Bolt12Invoice;
 use lightning_invoice::Bolt11Invoice;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -151,6 +152,33 @@ async fn pay_private(
         })
     }
 
+    /// Attempts to pay `amount` of the invoice as a single path of a
+    /// multi-path payment, waiting for the payment to complete and returning
+    /// the preimage. The payee will only settle the payment once the remaining
+    /// amount has been paid via other paths, which may be sent by other nodes.
+    ///
+    /// The same idempotency requirements as for [`ILnRpcClient::pay`] apply.
+    /// If this is implemented, [`ILnRpcClient::supports_multi_path_payments`]
+    /// must return true.
+    async fn pay_partial(
+        &self,
+        _invoice: Bolt11Invoice,
+        _amount: Amount,
+        _max_delay: u64,
+        _max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        Err(LightningRpcError::FailedPayment {
+            failure_reason: "Multi-path payments not supported".to_string(),
+        })
+    }
+
+    /// Returns true if the lightning backend is able to pay a part of an
+    /// invoice. If this returns true, [`ILnRpcClient::pay_partial`] must be
+    /// implemented.
+    fn supports_multi_path_payments(&self) -> bool {
+        false
+    }
+
     /// Returns true if the lightning backend supports payments without full
     /// invoices. If this returns true, [`ILnRpcClient::pay_private`] must
     /// be implemented.
@@ -245,6 +273,53 @@ async fn pay_offer(
         amount: Option<Amount>,
         payer_note: Option<String>,
     ) -> Result<Preimage, LightningRpcError>;
+
+    /// Requests an invoice for a BOLT12 offer from the offer's issuer without
+    /// paying it. This allows the payment hash to be committed to before the
+    /// payment is made, which is required for LNv2 outgoing contracts.
+    /// If this is implemented, [`ILnRpcClient::supports_bolt12_invoices`]
+    /// must return true.
+    ///
+    /// None of the bundled backends implement this yet: LND has no native
+    /// BOLT12 support and `ldk-node` only pays offers end to end, without
+    /// exposing the invoice before it is paid. Receiving via offers is out of
+    /// scope for LNv2 altogether, since the node derives the payment hash of
+    /// an offer's invoices itself while an incoming contract commits to a
+    /// payment hash chosen by the client.
+    async fn fetch_offer_invoice(
+        &self,
+        _offer: String,
+        _amount: Option<Amount>,
+        _payer_note: Option<String>,
+    ) -> Result<Bolt12Invoice, LightningRpcError> {
+        Err(LightningRpcError::Bolt12Error {
+            failure_reason: "Fetching invoices for offers is not supported".to_string(),
+        })
+    }
+
+    /// Attempts to pay a BOLT12 invoice previously obtained via
+    /// [`ILnRpcClient::fetch_offer_invoice`], waiting for the payment to
+    /// complete and returning the preimage.
+    ///
+    /// The same idempotency requirements as for [`ILnRpcClient::pay`] apply.
+    async fn pay_bolt12_invoice(
+        &self,
+        _invoice: Bolt12Invoice,
+        _max_delay: u64,
+        _max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        Err(LightningRpcError::Bolt12Error {
+            failure_reason: "Paying Bolt12 invoices is not supported".to_string(),
+        })
+    }
+
+    /// Returns true if the lightning backend is able to request invoices for
+    /// offers without paying them. If this returns true,
+    /// [`ILnRpcClient::fetch_offer_invoice`] and
+    /// [`ILnRpcClient::pay_bolt12_invoice`] must be implemented.
+    fn supports_bolt12_invoices(&self) -> bool {
+        false
+    }
 }
 
 impl dyn ILnRpcClient {
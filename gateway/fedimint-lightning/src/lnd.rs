@@ -18,6 +18,7 @@
 use fedimint_ln_common::route_hints::{RouteHint, RouteHintHop};
 use fedimint_logging::LOG_LIGHTNING;
 use hex::ToHex;
+use lightning_invoice::Bolt11Invoice;
 use secp256k1::PublicKey;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -28,17 +29,19 @@
 };
 use tonic_lnd::lnrpc::channel_point::FundingTxid;
 use tonic_lnd::lnrpc::failure::FailureCode;
+use tonic_lnd::lnrpc::fee_limit::Limit;
+use tonic_lnd::lnrpc::htlc_attempt::HtlcStatus;
 use tonic_lnd::lnrpc::invoice::InvoiceState;
 use tonic_lnd::lnrpc::payment::PaymentStatus;
 use tonic_lnd::lnrpc::{
     ChanInfoRequest, ChannelBalanceRequest, ChannelPoint, CloseChannelRequest, ConnectPeerRequest,
-    GetInfoRequest, Invoice, InvoiceSubscription, LightningAddress, ListChannelsRequest,
-    ListInvoiceRequest, ListPaymentsRequest, ListPeersRequest, OpenChannelRequest,
-    SendCoinsRequest, WalletBalanceRequest,
+    Failure, FeeLimit, GetInfoRequest, Hop, Invoice, InvoiceSubscription, LightningAddress,
+    ListChannelsRequest, ListInvoiceRequest, ListPaymentsRequest, ListPeersRequest, MppRecord,
+    NodePair, OpenChannelRequest, QueryRoutesRequest, SendCoinsRequest, WalletBalanceRequest,
 };
 use tonic_lnd::routerrpc::{
     CircuitKey, ForwardHtlcInterceptResponse, ResolveHoldForwardAction, SendPaymentRequest,
-    TrackPaymentRequest,
+    SendToRouteRequest, TrackPaymentRequest,
 };
 use tonic_lnd::tonic::Code;
 use tonic_lnd::walletrpc::AddrRequest;
@@ -61,6 +64,10 @@
 
 const LND_PAYMENT_TIMEOUT_SECONDS: i32 = 180;
 
+/// The number of routes we try to pay a part of a multi-path payment along
+/// before giving up
+const MAX_PARTIAL_PAYMENT_ATTEMPTS: usize = 5;
+
 #[derive(Clone)]
 pub struct GatewayLndClient {
     /// LND client
@@ -926,12 +933,234 @@ async fn pay_private(
         })
     }
 
+    async fn pay_partial(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Amount,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        let invoice =
+            PrunedInvoice::try_from(invoice).map_err(|_| LightningRpcError::FailedPayment {
+                failure_reason: "Invoice has no amount".to_string(),
+            })?;
+        let payment_hash = invoice.payment_hash.to_byte_array().to_vec();
+        info!(
+            target: LOG_LIGHTNING,
+            payment_hash = %PrettyPaymentHash(&payment_hash),
+            %amount,
+            "LND Paying part of invoice",
+        );
+        let mut client = self.connect().await?;
+
+        // If the payment exists, that means we've already tried to pay our part of the
+        // invoice
+        if let Some(preimage) = self
+            .lookup_payment(payment_hash.clone(), &mut client)
+            .await?
+        {
+            info!(
+                target: LOG_LIGHTNING,
+                payment_hash = %PrettyPaymentHash(&payment_hash),
+                "LND payment already exists for invoice",
+            );
+            let preimage: Vec<u8> = hex::FromHex::from_hex(preimage.as_str()).map_err(|error| {
+                LightningRpcError::FailedPayment {
+                    failure_reason: format!("Failed to convert preimage {error:?}"),
+                }
+            })?;
+
+            return Ok(PayInvoiceResponse {
+                preimage: Preimage(preimage.try_into().expect("Failed to create preimage")),
+            });
+        }
+
+        let to_lnd_int = |value: u64, name: &str| {
+            i64::try_from(value).map_err(|error| LightningRpcError::FailedPayment {
+                failure_reason: format!("{name} exceeds valid LND range {error:?}"),
+            })
+        };
+
+        let dest_features = wire_features_to_lnd_feature_vec(&invoice.destination_features)
+            .map_err(|e| LightningRpcError::FailedPayment {
+                failure_reason: e.to_string(),
+            })?;
+
+        // Unlike SendPayment, QueryRoutes does not add any block padding to the final
+        // CLTV delta, hence we add the same padding of three blocks ourselves.
+        let final_cltv_delta =
+            i32::try_from(invoice.min_final_cltv_delta + 3).map_err(|error| {
+                LightningRpcError::FailedPayment {
+                    failure_reason: format!("final cltv delta exceeds valid LND range {error:?}"),
+                }
+            })?;
+
+        let cltv_limit =
+            u32::try_from(max_delay).map_err(|error| LightningRpcError::FailedPayment {
+                failure_reason: format!("max delay exceeds valid LND range {error:?}"),
+            })?;
+
+        // We need our own public key to exclude our own channels after a local failure
+        let sender: Vec<u8> = client
+            .lightning()
+            .get_info(GetInfoRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetNodeInfo {
+                failure_reason: format!("Failed to get node info {status:?}"),
+            })
+            .and_then(|info| {
+                hex::FromHex::from_hex(&info.into_inner().identity_pubkey).map_err(|e| {
+                    LightningRpcError::FailedToGetNodeInfo {
+                        failure_reason: format!("Failed to parse public key {e:?}"),
+                    }
+                })
+            })?;
+
+        let mut ignored_nodes = vec![];
+        let mut ignored_pairs = vec![];
+        let mut attempt_number = 0;
+
+        // Mission control learns from failed HTLCs as well, but we explicitly
+        // exclude the node or channel that failed such that the next route
+        // avoids it
+        let attempt = loop {
+            attempt_number += 1;
+
+            let mut route = client
+                .lightning()
+                .query_routes(QueryRoutesRequest {
+                    pub_key: invoice.destination.to_string(),
+                    amt_msat: to_lnd_int(amount.msats, "amount")?,
+                    final_cltv_delta,
+                    fee_limit: Some(FeeLimit {
+                        limit: Some(Limit::FixedMsat(to_lnd_int(max_fee.msats, "max_fee_msat")?)),
+                    }),
+                    ignored_nodes: ignored_nodes.clone(),
+                    use_mission_control: true,
+                    ignored_pairs: ignored_pairs.clone(),
+                    cltv_limit,
+                    route_hints: route_hints_to_lnd(&invoice.route_hints),
+                    dest_features: dest_features.clone(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|status| LightningRpcError::FailedPayment {
+                    failure_reason: format!("Failed to find route {status:?}"),
+                })?
+                .into_inner()
+                .routes
+                .into_iter()
+                .next()
+                .ok_or(LightningRpcError::FailedPayment {
+                    failure_reason: "No route found".to_string(),
+                })?;
+
+            // The MPP record tells the payee to hold our HTLC until the HTLCs of all
+            // paths add up to the invoice amount
+            route
+                .hops
+                .last_mut()
+                .ok_or(LightningRpcError::FailedPayment {
+                    failure_reason: "Route has no hops".to_string(),
+                })?
+                .mpp_record = Some(MppRecord {
+                payment_addr: invoice.payment_secret.to_vec(),
+                total_amt_msat: to_lnd_int(invoice.amount.msats, "invoice amount")?,
+            });
+
+            let hops = route.hops.clone();
+
+            debug!(
+                target: LOG_LIGHTNING,
+                payment_hash = %PrettyPaymentHash(&payment_hash),
+                %attempt_number,
+                "LND found route, sending HTLC",
+            );
+            let attempt = client
+                .router()
+                .send_to_route_v2(SendToRouteRequest {
+                    payment_hash: payment_hash.clone(),
+                    route: Some(route),
+                    skip_temp_err: false,
+                })
+                .await
+                .map_err(|status| {
+                    warn!(
+                        target: LOG_LIGHTNING,
+                        status = %status,
+                        payment_hash = %PrettyPaymentHash(&payment_hash),
+                        "LND partial payment request failed",
+                    );
+                    LightningRpcError::FailedPayment {
+                        failure_reason: format!("Failed to make outgoing payment {status:?}"),
+                    }
+                })?
+                .into_inner();
+
+            if attempt.status() == HtlcStatus::Succeeded {
+                break attempt;
+            }
+
+            let failure = attempt.failure.unwrap_or_default();
+
+            warn!(
+                target: LOG_LIGHTNING,
+                payment_hash = %PrettyPaymentHash(&payment_hash),
+                status = ?attempt.status(),
+                code = ?failure.code(),
+                failure_source_index = %failure.failure_source_index,
+                %attempt_number,
+                "LND partial payment attempt failed",
+            );
+
+            let exclusion = route_exclusion(&failure, &sender, &hops);
+
+            match exclusion {
+                Some(RouteExclusion::Node(node))
+                    if attempt_number < MAX_PARTIAL_PAYMENT_ATTEMPTS =>
+                {
+                    ignored_nodes.push(node);
+                }
+                Some(RouteExclusion::Pair(pair))
+                    if attempt_number < MAX_PARTIAL_PAYMENT_ATTEMPTS =>
+                {
+                    ignored_pairs.push(pair);
+                }
+                _ => {
+                    return Err(LightningRpcError::FailedPayment {
+                        failure_reason: format!(
+                            "{:?} after {attempt_number} attempts",
+                            failure.code()
+                        ),
+                    });
+                }
+            }
+        };
+
+        info!(
+            target: LOG_LIGHTNING,
+            payment_hash = %PrettyPaymentHash(&payment_hash),
+            "LND partial payment succeeded for invoice",
+        );
+        Ok(PayInvoiceResponse {
+            preimage: Preimage(attempt.preimage.try_into().map_err(|_| {
+                LightningRpcError::FailedPayment {
+                    failure_reason: "Failed to convert preimage".to_string(),
+                }
+            })?),
+        })
+    }
+
     /// Returns true if the lightning backend supports payments without full
     /// invoices
     fn supports_private_payments(&self) -> bool {
         true
     }
 
+    fn supports_multi_path_payments(&self) -> bool {
+        true
+    }
+
     async fn route_htlcs<'a>(
         self: Box<Self>,
         task_group: &TaskGroup,
@@ -1549,6 +1778,55 @@ async fn pay_offer(
     }
 }
 
+/// The node or channel a partial payment has to avoid on its next route
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RouteExclusion {
+    Node(Vec<u8>),
+    Pair(NodePair),
+}
+
+/// Determines what to exclude from the next route after an HTLC `sender` sent
+/// along `hops` failed. Returns `None` if another route can not succeed, since
+/// the payee itself rejected the HTLC or the source of the failure is unknown.
+fn route_exclusion(failure: &Failure, sender: &[u8], hops: &[Hop]) -> Option<RouteExclusion> {
+    if failure.code() == FailureCode::UnreadableFailure {
+        return None;
+    }
+
+    // Position zero is the sender, so the final hop's index is the payee's
+    let source_index = usize::try_from(failure.failure_source_index).ok()?;
+
+    if hops.len() <= source_index {
+        return None;
+    }
+
+    let node_at = |index: usize| -> Option<Vec<u8>> {
+        if index == 0 {
+            return Some(sender.to_vec());
+        }
+
+        hex::FromHex::from_hex(&hops[index - 1].pub_key).ok()
+    };
+
+    let source = node_at(source_index)?;
+
+    match failure.code() {
+        FailureCode::TemporaryNodeFailure
+        | FailureCode::PermanentNodeFailure
+        | FailureCode::RequiredNodeFeatureMissing
+            if source_index != 0 =>
+        {
+            Some(RouteExclusion::Node(source))
+        }
+        // Any other failure is attributed to the channel the failing node should
+        // have forwarded the HTLC on
+        _ => Some(RouteExclusion::Pair(NodePair {
+            from: source,
+            to: node_at(source_index + 1)?,
+        })),
+    }
+}
+
 fn route_hints_to_lnd(
     route_hints: &[fedimint_ln_common::route_hints::RouteHint],
 ) -> Vec<tonic_lnd::lnrpc::RouteHint> {
@@ -1,8 +1,10 @@
 use fedimint_core::encode_bolt11_invoice_features_without_length;
 use hex::FromHex;
 use lightning::ln::features::Bolt11InvoiceFeatures;
+use tonic_lnd::lnrpc::failure::FailureCode;
+use tonic_lnd::lnrpc::{Failure, Hop, NodePair};
 
-use super::wire_features_to_lnd_feature_vec;
+use super::{RouteExclusion, route_exclusion, wire_features_to_lnd_feature_vec};
 
 #[test]
 fn features_to_lnd() {
@@ -28,3 +30,69 @@ fn features_to_lnd() {
         vec![8, 14, 17, 49, 149]
     );
 }
+
+#[test]
+fn route_exclusion_after_failure() {
+    let sender = vec![0; 33];
+    let hops = (1..=3)
+        .map(|i| Hop {
+            pub_key: hex::encode([i; 33]),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let failure = |code: FailureCode, failure_source_index: u32| {
+        let mut failure = Failure {
+            failure_source_index,
+            ..Default::default()
+        };
+        failure.set_code(code);
+        failure
+    };
+
+    // A local failure excludes our own channel
+    assert_eq!(
+        route_exclusion(
+            &failure(FailureCode::TemporaryChannelFailure, 0),
+            &sender,
+            &hops
+        ),
+        Some(RouteExclusion::Pair(NodePair {
+            from: sender.clone(),
+            to: vec![1; 33],
+        }))
+    );
+
+    // A forwarding node excludes the channel it should have forwarded on
+    assert_eq!(
+        route_exclusion(&failure(FailureCode::FeeInsufficient, 2), &sender, &hops),
+        Some(RouteExclusion::Pair(NodePair {
+            from: vec![2; 33],
+            to: vec![3; 33],
+        }))
+    );
+
+    // A node failure excludes the node altogether
+    assert_eq!(
+        route_exclusion(
+            &failure(FailureCode::TemporaryNodeFailure, 1),
+            &sender,
+            &hops
+        ),
+        Some(RouteExclusion::Node(vec![1; 33]))
+    );
+
+    // Neither a rejection by the payee nor an unknown source can be routed around
+    assert_eq!(
+        route_exclusion(
+            &failure(FailureCode::IncorrectOrUnknownPaymentDetails, 3),
+            &sender,
+            &hops
+        ),
+        None
+    );
+    assert_eq!(
+        route_exclusion(&failure(FailureCode::UnreadableFailure, 1), &sender, &hops),
+        None
+    );
+}
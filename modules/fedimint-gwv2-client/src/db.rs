@@ -0,0 +1,58 @@
+use std::io::Cursor;
+
+use fedimint_core::core::OperationId;
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::secp256k1::Keypair;
+use fedimint_core::{Amount, OutPoint};
+use fedimint_lnv2_common::LightningInvoice;
+use fedimint_lnv2_common::contracts::OutgoingContract;
+
+use crate::GatewayClientStateMachinesV2;
+use crate::send_sm::{SendSMCommon, SendSMState, SendStateMachine};
+
+/// Migrates send state machines created before multi-path payments were
+/// supported, which did not record the part of the invoice amount we pay.
+pub(crate) fn get_v0_migrated_state(
+    operation_id: OperationId,
+    cursor: &mut Cursor<&[u8]>,
+) -> anyhow::Result<Option<(Vec<u8>, OperationId)>> {
+    #[derive(Debug, Clone, Decodable)]
+    pub struct SendSMCommonV0 {
+        operation_id: OperationId,
+        outpoint: OutPoint,
+        contract: OutgoingContract,
+        max_delay: u64,
+        min_contract_amount: Amount,
+        invoice: LightningInvoice,
+        claim_keypair: Keypair,
+    }
+
+    let decoders = ModuleDecoderRegistry::default();
+    let gwv2_sm_variant = u16::consensus_decode_partial(cursor, &decoders)?;
+
+    // If the state machine is not a send state machine, return None
+    if gwv2_sm_variant != 0 {
+        return Ok(None);
+    }
+
+    let _gwv2_sm_len = u16::consensus_decode_partial(cursor, &decoders)?;
+    let v0 = SendSMCommonV0::consensus_decode_partial(cursor, &decoders)?;
+    let state = SendSMState::consensus_decode_partial(cursor, &decoders)?;
+
+    let new_send = GatewayClientStateMachinesV2::Send(SendStateMachine {
+        common: SendSMCommon {
+            operation_id: v0.operation_id,
+            outpoint: v0.outpoint,
+            contract: v0.contract,
+            max_delay: v0.max_delay,
+            min_contract_amount: v0.min_contract_amount,
+            invoice: v0.invoice,
+            claim_keypair: v0.claim_keypair,
+            part_amount: None,
+        },
+        state,
+    });
+
+    Ok(Some((new_send.consensus_encode_to_vec(), operation_id)))
+}
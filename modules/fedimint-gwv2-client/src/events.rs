@@ -28,7 +28,8 @@ pub struct OutgoingPaymentStarted {
     /// gateway's fee)
     pub min_contract_amount: Amount,
 
-    /// The amount requested in the invoice.
+    /// The amount requested in the invoice or the part of it we pay if the
+    /// payment is split across multiple gateways.
     pub invoice_amount: Amount,
 
     /// The max delay of the payment in blocks.
@@ -1,5 +1,6 @@
 mod api;
 mod complete_sm;
+mod db;
 pub mod events;
 mod receive_sm;
 mod send_sm;
@@ -12,10 +13,10 @@
 use anyhow::{anyhow, ensure};
 use async_trait::async_trait;
 use bitcoin::hashes::sha256;
-use bitcoin::secp256k1::Message;
 use events::{IncomingPaymentStarted, OutgoingPaymentStarted};
 use fedimint_api_client::api::DynModuleApi;
 use fedimint_client::ClientHandleArc;
+use fedimint_client_module::db::{ClientModuleMigrationFn, migrate_state};
 use fedimint_client_module::module::init::{ClientModuleInit, ClientModuleInitArgs};
 use fedimint_client_module::module::recovery::NoModuleBackup;
 use fedimint_client_module::module::{ClientContext, ClientModule, IClientModule, OutPointRange};
@@ -27,7 +28,7 @@
 use fedimint_client_module::{DynGlobalClientContext, sm_enum_variant_translation};
 use fedimint_core::config::FederationId;
 use fedimint_core::core::{Decoder, IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId};
-use fedimint_core::db::DatabaseTransaction;
+use fedimint_core::db::{DatabaseTransaction, DatabaseVersion};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::{
     ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
@@ -43,7 +44,6 @@
     LightningCommonInit, LightningInvoice, LightningModuleTypes, LightningOutput, LightningOutputV0,
 };
 use futures::StreamExt;
-use lightning_invoice::Bolt11Invoice;
 use receive_sm::{ReceiveSMState, ReceiveStateMachine};
 use secp256k1::schnorr::Signature;
 use send_sm::{SendSMState, SendStateMachine};
@@ -102,6 +102,17 @@ async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::
             gateway: self.gateway.clone(),
         })
     }
+
+    fn get_database_migrations(&self) -> BTreeMap<DatabaseVersion, ClientModuleMigrationFn> {
+        let mut migrations: BTreeMap<DatabaseVersion, ClientModuleMigrationFn> = BTreeMap::new();
+        migrations.insert(DatabaseVersion(0), |_, active_states, inactive_states| {
+            Box::pin(async {
+                migrate_state(active_states, inactive_states, db::get_v0_migrated_state)
+            })
+        });
+
+        migrations
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -166,6 +177,7 @@ fn output_fee(
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub enum GatewayClientStateMachinesV2 {
     Send(SendStateMachine),
@@ -275,9 +287,7 @@ pub async fn send_payment(
             secp256k1::SECP256K1
                 .verify_schnorr(
                     &payload.auth,
-                    &Message::from_digest(
-                        *payload.invoice.consensus_hash::<sha256::Hash>().as_ref()
-                    ),
+                    &SendPaymentPayload::auth_message(&payload.invoice, payload.part_amount),
                     &payload.contract.refund_pk.x_only_public_key().0,
                 )
                 .is_ok(),
@@ -298,17 +308,30 @@ pub async fn send_payment(
             "Contract Id returned by the federation does not match contract in request"
         );
 
-        let (payment_hash, amount) = match &payload.invoice {
-            LightningInvoice::Bolt11(invoice) => (
-                invoice.payment_hash(),
-                invoice
-                    .amount_milli_satoshis()
-                    .ok_or(anyhow!("Invoice is missing amount"))?,
-            ),
+        let invoice_amount = payload
+            .invoice
+            .amount_milli_satoshis()
+            .ok_or(anyhow!("Invoice is missing amount"))?;
+
+        let amount = match payload.part_amount {
+            Some(part_amount) => {
+                ensure!(
+                    matches!(payload.invoice, LightningInvoice::Bolt11(..)),
+                    "Multi-path payments are only supported for Bolt11 invoices"
+                );
+
+                ensure!(
+                    0 < part_amount.msats && part_amount.msats <= invoice_amount,
+                    "The part amount is not within the invoice amount"
+                );
+
+                part_amount.msats
+            }
+            None => invoice_amount,
         };
 
         ensure!(
-            PaymentImage::Hash(*payment_hash) == payload.contract.payment_image,
+            PaymentImage::Hash(payload.invoice.payment_hash()) == payload.contract.payment_image,
             "The invoices payment hash does not match the contracts payment hash"
         );
 
@@ -326,6 +349,7 @@ pub async fn send_payment(
                 min_contract_amount,
                 invoice: payload.invoice,
                 claim_keypair: self.keypair,
+                part_amount: payload.part_amount,
             },
             state: SendSMState::Sending,
         });
@@ -623,13 +647,16 @@ pub trait IGatewayClientV2: Debug + Send + Sync {
     /// Lightning network.
     async fn is_direct_swap(
         &self,
-        invoice: &Bolt11Invoice,
+        invoice: &LightningInvoice,
     ) -> anyhow::Result<Option<(IncomingContract, ClientHandleArc)>>;
 
-    /// Initiates a payment over the Lightning network.
+    /// Initiates a payment over the Lightning network. If a part amount is
+    /// given we only pay this part of the invoice amount as one path of a
+    /// multi-path payment.
     async fn pay(
         &self,
-        invoice: Bolt11Invoice,
+        invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         max_delay: u64,
         max_fee: Amount,
     ) -> Result<[u8; 32], LightningRpcError>;
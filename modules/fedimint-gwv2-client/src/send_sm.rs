@@ -50,6 +50,9 @@ pub struct SendSMCommon {
     pub min_contract_amount: Amount,
     pub invoice: LightningInvoice,
     pub claim_keypair: Keypair,
+    /// The part of the invoice amount we pay if the payment is split across
+    /// multiple gateways.
+    pub part_amount: Option<Amount>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
@@ -123,6 +126,7 @@ fn transitions(
                         self.common.max_delay,
                         self.common.min_contract_amount,
                         self.common.invoice.clone(),
+                        self.common.part_amount,
                         self.common.contract.clone(),
                     ),
                     move |dbtx, result, old_state| {
@@ -153,10 +157,9 @@ async fn send_payment(
         max_delay: u64,
         min_contract_amount: Amount,
         invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         contract: OutgoingContract,
     ) -> Result<PaymentResponse, Cancelled> {
-        let LightningInvoice::Bolt11(invoice) = invoice;
-
         // The following two checks may fail in edge cases since they have inherent
         // timing assumptions. Therefore, they may only be checked after we have created
         // the state machine such that we can cancel the contract.
@@ -172,12 +175,18 @@ async fn send_payment(
             return Err(Cancelled::Underfunded);
         };
 
-        match context
-            .gateway
-            .is_direct_swap(&invoice)
-            .await
-            .map_err(|e| Cancelled::RegistrationError(e.to_string()))?
-        {
+        // A direct swap transfers the entire invoice amount at once, therefore a
+        // part of a multi-path payment always has to be paid over lightning.
+        let direct_swap = match part_amount {
+            Some(..) => None,
+            None => context
+                .gateway
+                .is_direct_swap(&invoice)
+                .await
+                .map_err(|e| Cancelled::RegistrationError(e.to_string()))?,
+        };
+
+        match direct_swap {
             Some((contract, client)) => {
                 match client
                     .get_first_module::<GatewayClientModuleV2>()
@@ -205,7 +214,7 @@ async fn send_payment(
             None => {
                 let preimage = context
                     .gateway
-                    .pay(invoice, max_delay, max_fee)
+                    .pay(invoice, part_amount, max_delay, max_fee)
                     .await
                     .map_err(|e| Cancelled::LightningRpcError(e.to_string()))?;
                 Ok(PaymentResponse {
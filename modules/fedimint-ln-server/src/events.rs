@@ -0,0 +1,100 @@
+use bitcoin_hashes::sha256;
+use fedimint_core::core::ModuleKind;
+use fedimint_core::{Amount, OutPoint};
+use fedimint_eventlog::{Event, EventKind};
+use fedimint_ln_common::contracts::ContractId;
+use serde::{Deserialize, Serialize};
+
+/// Event that is emitted when a contract is funded by a transaction output
+#[derive(Serialize, Deserialize)]
+pub struct ContractFunded {
+    /// The ID of the funded contract
+    pub contract_id: ContractId,
+
+    /// The output funding the contract
+    pub out_point: OutPoint,
+
+    /// Whether the contract is an incoming rather than an outgoing contract
+    pub incoming: bool,
+
+    /// The amount the contract was funded with
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for ContractFunded {
+    const MODULE: Option<ModuleKind> = Some(fedimint_ln_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("contract-funded");
+}
+
+/// Event that is emitted when funds are claimed from a contract by a
+/// transaction input
+#[derive(Serialize, Deserialize)]
+pub struct ContractClaimed {
+    /// The ID of the claimed contract
+    pub contract_id: ContractId,
+
+    /// The amount being claimed
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for ContractClaimed {
+    const MODULE: Option<ModuleKind> = Some(fedimint_ln_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("contract-claimed");
+}
+
+/// Event that is emitted when a gateway offers to buy a preimage by a
+/// transaction output
+#[derive(Serialize, Deserialize)]
+pub struct IncomingContractOffered {
+    /// The hash of the preimage being offered
+    pub hash: sha256::Hash,
+
+    /// The amount the gateway is offering for the preimage
+    pub amount: Amount,
+}
+
+impl Event for IncomingContractOffered {
+    const MODULE: Option<ModuleKind> = Some(fedimint_ln_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("incoming-contract-offered");
+}
+
+/// Event that is emitted when a threshold of guardians decrypted the preimage
+/// of an incoming contract
+#[derive(Serialize, Deserialize)]
+pub struct PreimageDecrypted {
+    /// The ID of the incoming contract
+    pub contract_id: ContractId,
+
+    /// Whether the decrypted preimage is valid, otherwise the gateway can claim
+    /// back the funds
+    pub valid: bool,
+}
+
+impl Event for PreimageDecrypted {
+    const MODULE: Option<ModuleKind> = Some(fedimint_ln_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("preimage-decrypted");
+}
+
+/// Event that is emitted when a gateway cancels an outgoing contract, so the
+/// user can claim back the funds
+#[derive(Serialize, Deserialize)]
+pub struct OutgoingContractCancelled {
+    /// The ID of the cancelled contract
+    pub contract_id: ContractId,
+}
+
+impl Event for OutgoingContractCancelled {
+    const MODULE: Option<ModuleKind> = Some(fedimint_ln_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("outgoing-contract-cancelled");
+}
@@ -5,6 +5,7 @@
 #![allow(clippy::too_many_lines)]
 
 pub mod db;
+pub mod events;
 use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
 
@@ -57,6 +58,7 @@
 use fedimint_logging::LOG_MODULE_LN;
 use fedimint_server_core::bitcoin_rpc::ServerBitcoinRpcMonitor;
 use fedimint_server_core::config::PeerHandleOps;
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::{ServerModule, ServerModuleInit, ServerModuleInitArgs};
 use futures::StreamExt;
 use metrics::{LN_CANCEL_OUTGOING_CONTRACTS, LN_FUNDED_CONTRACT_SATS, LN_INCOMING_OFFER};
@@ -75,6 +77,10 @@
     LightningAuditItemKeyPrefix, LightningGatewayKey, LightningGatewayKeyPrefix, OfferKey,
     OfferKeyPrefix, ProposeDecryptionShareKey, ProposeDecryptionShareKeyPrefix,
 };
+use crate::events::{
+    ContractClaimed, ContractFunded, IncomingContractOffered, OutgoingContractCancelled,
+    PreimageDecrypted,
+};
 
 mod metrics;
 
@@ -221,6 +227,7 @@ async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::
             cfg: args.cfg().to_typed()?,
             our_peer_id: args.our_peer_id(),
             server_bitcoin_rpc_monitor: args.server_bitcoin_rpc_monitor(),
+            event_logger: args.event_logger(),
         })
     }
 
@@ -342,6 +349,7 @@ pub struct Lightning {
     cfg: LightningConfig,
     our_peer_id: PeerId,
     server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+    event_logger: ServerModuleEventLogger,
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -501,6 +509,16 @@ async fn process_consensus_item<'a, 'b>(
                 *incoming_contract_outcome_preimage = decrypted_preimage.clone();
                 dbtx.insert_entry(&ContractUpdateKey(out_point), &outcome)
                     .await;
+
+                self.event_logger
+                    .log_event(
+                        dbtx,
+                        PreimageDecrypted {
+                            contract_id,
+                            valid: matches!(decrypted_preimage, DecryptedPreimage::Some(_)),
+                        },
+                    )
+                    .await;
             }
             LightningConsensusItem::BlockCount(block_count) => {
                 let current_vote = dbtx
@@ -603,10 +621,23 @@ async fn process_input<'a, 'b, 'c>(
             dbtx.insert_entry(&audit_key, &account.amount).await;
         }
 
+        let fee = self.cfg.consensus.fee_consensus.contract_input;
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                ContractClaimed {
+                    contract_id: input.contract_id,
+                    amount: input.amount,
+                    fee,
+                },
+            )
+            .await;
+
         Ok(InputMeta {
             amount: TransactionItemAmount {
                 amount: input.amount,
-                fee: self.cfg.consensus.fee_consensus.contract_input,
+                fee,
             },
             pub_key,
         })
@@ -704,9 +735,24 @@ async fn process_output<'a, 'b>(
                     dbtx.remove_entry(&OfferKey(offer.hash)).await;
                 }
 
+                let fee = self.cfg.consensus.fee_consensus.contract_output;
+
+                self.event_logger
+                    .log_event(
+                        dbtx,
+                        ContractFunded {
+                            contract_id: contract.contract.contract_id(),
+                            out_point,
+                            incoming: matches!(contract.contract, Contract::Incoming(_)),
+                            amount: contract.amount,
+                            fee,
+                        },
+                    )
+                    .await;
+
                 Ok(TransactionItemAmount {
                     amount: contract.amount,
-                    fee: self.cfg.consensus.fee_consensus.contract_output,
+                    fee,
                 })
             }
             LightningOutputV0::Offer(offer) => {
@@ -740,6 +786,16 @@ async fn process_output<'a, 'b>(
                     LN_INCOMING_OFFER.inc();
                 });
 
+                self.event_logger
+                    .log_event(
+                        dbtx,
+                        IncomingContractOffered {
+                            hash: offer.hash,
+                            amount: offer.amount,
+                        },
+                    )
+                    .await;
+
                 Ok(TransactionItemAmount::ZERO)
             }
             LightningOutputV0::CancelOutgoing {
@@ -797,6 +853,15 @@ async fn process_output<'a, 'b>(
                     LN_CANCEL_OUTGOING_CONTRACTS.inc();
                 });
 
+                self.event_logger
+                    .log_event(
+                        dbtx,
+                        OutgoingContractCancelled {
+                            contract_id: *contract,
+                        },
+                    )
+                    .await;
+
                 Ok(TransactionItemAmount::ZERO)
             }
         }
@@ -1238,6 +1303,7 @@ mod tests {
     use fedimint_core::task::TaskGroup;
     use fedimint_core::util::SafeUrl;
     use fedimint_core::{Amount, Feerate, InPoint, OutPoint, PeerId, TransactionId};
+    use fedimint_eventlog::EventLogNamespace;
     use fedimint_ln_common::config::{
         LightningClientConfig, LightningConfig, LightningGenParams, LightningGenParamsConsensus,
         LightningGenParamsLocal, Network,
@@ -1252,8 +1318,10 @@ mod tests {
     };
     use fedimint_ln_common::{ContractAccount, LightningInput, LightningOutput};
     use fedimint_server_core::bitcoin_rpc::{IServerBitcoinRpc, ServerBitcoinRpcMonitor};
+    use fedimint_server_core::event_log::ServerModuleEventLogger;
     use fedimint_server_core::{ServerModule, ServerModuleInit};
     use rand::rngs::OsRng;
+    use tokio::sync::watch;
 
     use crate::db::{ContractKey, LightningAuditItemKey};
     use crate::{Lightning, LightningInit};
@@ -1298,6 +1366,13 @@ async fn submit_transaction(&self, _transaction: fedimint_core::bitcoin::Transac
             // No-op for mock
         }
 
+        async fn is_tx_in_mempool(
+            &self,
+            _txid: &fedimint_core::bitcoin::Txid,
+        ) -> anyhow::Result<bool> {
+            Err(anyhow::anyhow!("Mock mempool error"))
+        }
+
         async fn get_sync_percentage(&self) -> anyhow::Result<Option<f64>> {
             Err(anyhow::anyhow!("Mock sync percentage error"))
         }
@@ -1342,6 +1417,15 @@ fn build_configs() -> (Vec<LightningConfig>, LightningClientConfig) {
         (server_cfg, client_cfg)
     }
 
+    fn event_logger(db: &Database) -> ServerModuleEventLogger {
+        ServerModuleEventLogger::new(
+            42,
+            db.with_prefix_module_id(42).1,
+            EventLogNamespace::root(),
+            watch::channel(()).0,
+        )
+    }
+
     fn random_pub_key() -> PublicKey {
         generate_keypair(&mut OsRng).1
     }
@@ -1350,6 +1434,7 @@ fn random_pub_key() -> PublicKey {
     async fn encrypted_preimage_only_usable_once() {
         let task_group = TaskGroup::new();
         let (server_cfg, client_cfg) = build_configs();
+        let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
 
         let server = Lightning {
             cfg: server_cfg[0].clone(),
@@ -1359,6 +1444,7 @@ async fn encrypted_preimage_only_usable_once() {
                 Duration::from_secs(1),
                 &task_group,
             ),
+            event_logger: event_logger(&db),
         };
 
         let preimage = [42u8; 32];
@@ -1377,7 +1463,6 @@ async fn encrypted_preimage_only_usable_once() {
             out_idx: 0,
         };
 
-        let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
         let mut dbtx = db.begin_transaction_nc().await;
 
         server
@@ -1430,6 +1515,7 @@ async fn process_input_for_valid_incoming_contracts() {
                 Duration::from_secs(1),
                 &task_group,
             ),
+            event_logger: event_logger(&db),
         };
 
         let preimage = PreimageKey(generate_keypair(&mut OsRng).1.serialize());
@@ -1507,6 +1593,7 @@ async fn process_input_for_valid_outgoing_contracts() {
                 Duration::from_secs(1),
                 &task_group,
             ),
+            event_logger: event_logger(&db),
         };
 
         let preimage = Preimage([42u8; 32]);
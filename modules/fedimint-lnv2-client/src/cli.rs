@@ -1,15 +1,17 @@
+use std::str::FromStr;
 use std::{ffi, iter};
 
 use clap::{Parser, Subcommand};
 use fedimint_core::core::OperationId;
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, PeerId};
+use lightning::offers::offer::Offer;
 use lightning_invoice::Bolt11Invoice;
 use serde::Serialize;
 use serde_json::Value;
 
 use crate::api::LightningFederationApi;
-use crate::{Bolt11InvoiceDescription, LightningClientModule};
+use crate::{Bolt11InvoiceDescription, LightningClientModule, LightningInvoice};
 
 #[derive(Parser, Serialize)]
 enum Opts {
@@ -20,6 +22,26 @@ enum Opts {
         #[arg(long)]
         gateway: Option<SafeUrl>,
     },
+    /// Pay an invoice by splitting the payment across up to `max_parts`
+    /// gateways. For testing you can optionally specify the gateways to route
+    /// with, otherwise gateways will be selected automatically.
+    SendMultiPath {
+        invoice: Bolt11Invoice,
+        #[arg(long, default_value_t = 2)]
+        max_parts: usize,
+        #[arg(long)]
+        gateway: Vec<SafeUrl>,
+    },
+    /// Pay a Bolt12 offer. The amount is required if the offer does not
+    /// specify one. For testing you can optionally specify a gateway to route
+    /// with, otherwise a gateway will be selected automatically.
+    SendOffer {
+        offer: String,
+        #[arg(long)]
+        amount: Option<Amount>,
+        #[arg(long)]
+        gateway: Option<SafeUrl>,
+    },
     /// Await the final state of the send operation.
     AwaitSend { operation_id: OperationId },
     /// Request an invoice. For testing you can optionally specify a gateway to
@@ -69,6 +91,34 @@ pub(crate) async fn handle_cli_command(
         Opts::Send { gateway, invoice } => {
             json(lightning.send(invoice, gateway, Value::Null).await?)
         }
+        Opts::SendMultiPath {
+            invoice,
+            max_parts,
+            gateway,
+        } => json(
+            lightning
+                .send_multi_path(
+                    invoice,
+                    max_parts,
+                    (!gateway.is_empty()).then_some(gateway),
+                    Value::Null,
+                )
+                .await?,
+        ),
+        Opts::SendOffer {
+            offer,
+            amount,
+            gateway,
+        } => {
+            let offer = Offer::from_str(&offer)
+                .map_err(|e| anyhow::anyhow!("Failed to parse Bolt12 offer: {e:?}"))?;
+
+            json(
+                lightning
+                    .send_offer(offer, amount, gateway, Value::Null)
+                    .await?,
+            )
+        }
         Opts::AwaitSend { operation_id } => json(
             lightning
                 .await_final_send_operation_state(operation_id)
@@ -101,7 +151,12 @@ pub(crate) async fn handle_cli_command(
                 )
                 .await,
             ),
-            GatewaysOpts::Select { invoice } => json(lightning.select_gateway(invoice).await?.0),
+            GatewaysOpts::Select { invoice } => json(
+                lightning
+                    .select_gateway(invoice.map(LightningInvoice::Bolt11))
+                    .await?
+                    .0,
+            ),
             GatewaysOpts::List { peer } => match peer {
                 Some(peer) => json(lightning.module_api.gateways_from_peer(peer).await?),
                 None => json(lightning.module_api.gateways().await?),
@@ -1,9 +1,18 @@
+use std::io::Cursor;
+
+use fedimint_core::core::OperationId;
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::secp256k1::PublicKey;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::secp256k1::{Keypair, PublicKey};
 use fedimint_core::util::SafeUrl;
-use fedimint_core::{impl_db_lookup, impl_db_record};
+use fedimint_core::{OutPoint, impl_db_lookup, impl_db_record};
+use fedimint_lnv2_common::LightningInvoice;
+use fedimint_lnv2_common::contracts::OutgoingContract;
 use strum::EnumIter;
 
+use crate::LightningClientStateMachines;
+use crate::send_sm::{SendSMCommon, SendSMState, SendStateMachine};
+
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
 pub enum DbKeyPrefix {
@@ -32,3 +41,47 @@ pub enum DbKeyPrefix {
     db_prefix = DbKeyPrefix::Gateway,
 );
 impl_db_lookup!(key = GatewayKey, query_prefix = GatewayPrefix);
+
+/// Migrates send state machines created before multi-path payments were
+/// supported, which did not record the part of the invoice amount they pay.
+pub(crate) fn get_v0_migrated_state(
+    operation_id: OperationId,
+    cursor: &mut Cursor<&[u8]>,
+) -> anyhow::Result<Option<(Vec<u8>, OperationId)>> {
+    #[derive(Debug, Clone, Decodable)]
+    pub struct SendSMCommonV0 {
+        operation_id: OperationId,
+        outpoint: OutPoint,
+        contract: OutgoingContract,
+        gateway_api: Option<SafeUrl>,
+        invoice: Option<LightningInvoice>,
+        refund_keypair: Keypair,
+    }
+
+    let decoders = ModuleDecoderRegistry::default();
+    let lnv2_sm_variant = u16::consensus_decode_partial(cursor, &decoders)?;
+
+    // If the state machine is not a send state machine, return None
+    if lnv2_sm_variant != 0 {
+        return Ok(None);
+    }
+
+    let _lnv2_sm_len = u16::consensus_decode_partial(cursor, &decoders)?;
+    let v0 = SendSMCommonV0::consensus_decode_partial(cursor, &decoders)?;
+    let state = SendSMState::consensus_decode_partial(cursor, &decoders)?;
+
+    let new_send = LightningClientStateMachines::Send(SendStateMachine {
+        common: SendSMCommon {
+            operation_id: v0.operation_id,
+            outpoint: v0.outpoint,
+            contract: v0.contract,
+            gateway_api: v0.gateway_api,
+            invoice: v0.invoice,
+            refund_keypair: v0.refund_keypair,
+            part_amount: None,
+        },
+        state,
+    });
+
+    Ok(Some((new_send.consensus_encode_to_vec(), operation_id)))
+}
@@ -19,7 +19,8 @@
 use bitcoin::hashes::{Hash, sha256};
 use bitcoin::secp256k1;
 use db::{DbKeyPrefix, GatewayKey};
-use fedimint_api_client::api::DynModuleApi;
+use fedimint_api_client::api::{DynModuleApi, FederationResult};
+use fedimint_client_module::db::{ClientModuleMigrationFn, migrate_state};
 use fedimint_client_module::module::init::{ClientModuleInit, ClientModuleInitArgs};
 use fedimint_client_module::module::recovery::NoModuleBackup;
 use fedimint_client_module::module::{ClientContext, ClientModule, OutPointRange};
@@ -32,7 +33,7 @@
 use fedimint_client_module::{DynGlobalClientContext, sm_enum_variant_translation};
 use fedimint_core::config::FederationId;
 use fedimint_core::core::{IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId};
-use fedimint_core::db::{DatabaseTransaction, IDatabaseTransactionOpsCoreTyped};
+use fedimint_core::db::{DatabaseTransaction, DatabaseVersion, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::{
     ApiAuth, ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
@@ -51,6 +52,9 @@
     LightningOutput, LightningOutputV0,
 };
 use futures::StreamExt;
+use lightning::offers::invoice::Bolt12Invoice;
+use lightning::offers::offer::{self, Offer};
+use lightning::util::ser::{BigSize, Readable, Writeable};
 use lightning_invoice::{Bolt11Invoice, Currency};
 use secp256k1::{Keypair, PublicKey, Scalar, SecretKey, ecdh};
 use serde::{Deserialize, Serialize};
@@ -71,10 +75,16 @@
 /// A two hour buffer in case either the client or gateway go offline
 const CONTRACT_CONFIRMATION_BUFFER: u64 = 12;
 
+/// The range of TLV record types reserved for offers by BOLT 12. An invoice
+/// repeats all records of the offer it was requested for.
+const OFFER_TLV_TYPES: std::ops::Range<u64> = 1..80;
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LightningOperationMeta {
     Send(SendOperationMeta),
     Receive(ReceiveOperationMeta),
+    SendMultiPath(SendMultiPathOperationMeta),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,11 +99,38 @@ pub struct SendOperationMeta {
 impl SendOperationMeta {
     /// Calculate the absolute fee paid to the gateway on success.
     pub fn gateway_fee(&self) -> Amount {
-        match &self.invoice {
-            LightningInvoice::Bolt11(invoice) => self.contract.amount.saturating_sub(
-                Amount::from_msats(invoice.amount_milli_satoshis().expect("Invoice has amount")),
-            ),
-        }
+        self.contract.amount.saturating_sub(Amount::from_msats(
+            self.invoice
+                .amount_milli_satoshis()
+                .expect("Invoice has amount"),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendMultiPathOperationMeta {
+    pub change_outpoint_range: OutPointRange,
+    pub parts: Vec<SendPartMeta>,
+    pub invoice: LightningInvoice,
+    pub custom_meta: Value,
+}
+
+/// A part of a multi-path payment routed via a single gateway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendPartMeta {
+    pub gateway: SafeUrl,
+    pub contract: OutgoingContract,
+    /// The part of the invoice amount paid via this gateway.
+    pub amount: Amount,
+}
+
+impl SendMultiPathOperationMeta {
+    /// Calculate the absolute fee paid to all gateways on success.
+    pub fn gateway_fee(&self) -> Amount {
+        self.parts
+            .iter()
+            .map(|part| part.contract.amount.saturating_sub(part.amount))
+            .sum()
     }
 }
 
@@ -108,12 +145,12 @@ pub struct ReceiveOperationMeta {
 impl ReceiveOperationMeta {
     /// Calculate the absolute fee paid to the gateway on success.
     pub fn gateway_fee(&self) -> Amount {
-        match &self.invoice {
-            LightningInvoice::Bolt11(invoice) => {
-                Amount::from_msats(invoice.amount_milli_satoshis().expect("Invoice has amount"))
-                    .saturating_sub(self.contract.commitment.amount)
-            }
-        }
+        Amount::from_msats(
+            self.invoice
+                .amount_milli_satoshis()
+                .expect("Invoice has amount"),
+        )
+        .saturating_sub(self.contract.commitment.amount)
     }
 }
 
@@ -253,6 +290,17 @@ async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::
         ))
     }
 
+    fn get_database_migrations(&self) -> BTreeMap<DatabaseVersion, ClientModuleMigrationFn> {
+        let mut migrations: BTreeMap<DatabaseVersion, ClientModuleMigrationFn> = BTreeMap::new();
+        migrations.insert(DatabaseVersion(0), |_, active_states, inactive_states| {
+            Box::pin(async {
+                migrate_state(active_states, inactive_states, db::get_v0_migrated_state)
+            })
+        });
+
+        migrations
+    }
+
     fn used_db_prefixes(&self) -> Option<BTreeSet<u8>> {
         Some(
             DbKeyPrefix::iter()
@@ -421,7 +469,7 @@ async fn update_gateway_map(
 
     async fn select_gateway(
         &self,
-        invoice: Option<Bolt11Invoice>,
+        invoice: Option<LightningInvoice>,
     ) -> Result<(SafeUrl, RoutingInfo), SelectGatewayError> {
         let gateways = self
             .module_api
@@ -439,7 +487,7 @@ async fn select_gateway(
                 .module_db()
                 .begin_transaction_nc()
                 .await
-                .get_value(&GatewayKey(invoice.recover_payee_pub_key()))
+                .get_value(&GatewayKey(invoice.payee_pub_key()))
                 .await
                 .filter(|gateway| gateways.contains(gateway))
             {
@@ -467,6 +515,11 @@ async fn routing_info(
             .await
     }
 
+    /// List the gateways vetted by the guardians of the federation.
+    pub async fn vetted_gateways(&self) -> FederationResult<Vec<SafeUrl>> {
+        self.module_api.gateways().await
+    }
+
     /// Pay an invoice. For testing you can optionally specify a gateway to
     /// route with, otherwise a gateway will be selected automatically. If the
     /// invoice was created by a gateway connected to our federation, the same
@@ -488,6 +541,130 @@ pub async fn send(
         invoice: Bolt11Invoice,
         gateway: Option<SafeUrl>,
         custom_meta: Value,
+    ) -> Result<OperationId, SendPaymentError> {
+        if self.cfg.network != invoice.currency().into() {
+            return Err(SendPaymentError::WrongCurrency {
+                invoice_currency: invoice.currency(),
+                federation_currency: self.cfg.network.into(),
+            });
+        }
+
+        self.send_invoice(LightningInvoice::Bolt11(invoice), gateway, custom_meta)
+            .await
+    }
+
+    /// Pay a Bolt12 offer. We request an invoice for the offer via the
+    /// selected gateway, since the client cannot exchange onion messages with
+    /// the offer's issuer itself, and then pay the invoice like we would pay a
+    /// Bolt11 invoice. The amount is required if the offer does not specify
+    /// one in bitcoin and otherwise has to be at least the amount of the offer.
+    ///
+    /// Since the gateway is not trusted to relay the invoice faithfully, we
+    /// verify that the invoice was issued for this offer and signed by its
+    /// issuer before paying it.
+    ///
+    /// Only gateways that advertise support for offers in their routing info
+    /// are used, as not every lightning backend is able to request an invoice
+    /// for an offer without paying it. Currently none of the lightning
+    /// backends bundled with the gateway are, so this only succeeds with
+    /// gateways running a custom backend.
+    ///
+    /// The same fee limits as for [`LightningClientModule::send`] apply.
+    pub async fn send_offer(
+        &self,
+        offer: Offer,
+        amount: Option<Amount>,
+        gateway: Option<SafeUrl>,
+        custom_meta: Value,
+    ) -> Result<OperationId, SendPaymentError> {
+        if !offer.supports_chain(self.cfg.network.chain_hash()) {
+            return Err(SendPaymentError::WrongNetwork);
+        }
+
+        if offer.is_expired() {
+            return Err(SendPaymentError::InvoiceExpired);
+        }
+
+        let amount_msats = match (amount, offer.amount()) {
+            (Some(amount), _) => amount.msats,
+            (None, Some(offer::Amount::Bitcoin { amount_msats })) => amount_msats,
+            (None, _) => return Err(SendPaymentError::InvoiceMissingAmount),
+        };
+
+        let gateway_api = match gateway {
+            Some(gateway_api) => {
+                if !self
+                    .routing_info(&gateway_api)
+                    .await
+                    .map_err(SendPaymentError::GatewayConnectionError)?
+                    .ok_or(SendPaymentError::UnknownFederation)?
+                    .supports_bolt12_invoices
+                {
+                    return Err(SendPaymentError::Bolt12NotSupported);
+                }
+
+                gateway_api
+            }
+            None => self.select_bolt12_gateway().await?,
+        };
+
+        let invoice = self
+            .gateway_conn
+            .bolt12_invoice(
+                gateway_api.clone(),
+                self.federation_id,
+                offer.to_string(),
+                amount,
+                None,
+            )
+            .await
+            .map_err(SendPaymentError::GatewayConnectionError)?;
+
+        let LightningInvoice::Bolt12(bolt12_invoice) = &invoice else {
+            return Err(SendPaymentError::InvalidOfferInvoice);
+        };
+
+        if bolt12_invoice.chain() != self.cfg.network.chain_hash()
+            || !is_invoice_for_offer(bolt12_invoice, &offer, amount_msats)
+        {
+            return Err(SendPaymentError::InvalidOfferInvoice);
+        }
+
+        self.send_invoice(invoice, Some(gateway_api), custom_meta)
+            .await
+    }
+
+    /// Selects a vetted gateway whose lightning node is able to request and
+    /// pay invoices for offers.
+    async fn select_bolt12_gateway(&self) -> Result<SafeUrl, SendPaymentError> {
+        let gateways = self.module_api.gateways().await.map_err(|e| {
+            SendPaymentError::FailedToSelectGateway(SelectGatewayError::FederationError(
+                e.to_string(),
+            ))
+        })?;
+
+        if gateways.is_empty() {
+            return Err(SendPaymentError::FailedToSelectGateway(
+                SelectGatewayError::NoVettedGateways,
+            ));
+        }
+
+        for gateway in gateways {
+            if let Ok(Some(routing_info)) = self.routing_info(&gateway).await {
+                if routing_info.supports_bolt12_invoices {
+                    return Ok(gateway);
+                }
+            }
+        }
+
+        Err(SendPaymentError::Bolt12NotSupported)
+    }
+
+    async fn send_invoice(
+        &self,
+        invoice: LightningInvoice,
+        gateway: Option<SafeUrl>,
+        custom_meta: Value,
     ) -> Result<OperationId, SendPaymentError> {
         let amount = invoice
             .amount_milli_satoshis()
@@ -497,13 +674,6 @@ pub async fn send(
             return Err(SendPaymentError::InvoiceExpired);
         }
 
-        if self.cfg.network != invoice.currency().into() {
-            return Err(SendPaymentError::WrongCurrency {
-                invoice_currency: invoice.currency(),
-                federation_currency: self.cfg.network.into(),
-            });
-        }
-
         let operation_id = self.get_next_operation_id(&invoice).await?;
 
         let (ephemeral_tweak, ephemeral_pk) = generate_ephemeral_tweak(self.keypair.public_key());
@@ -543,7 +713,7 @@ pub async fn send(
             .map_err(|e| SendPaymentError::FederationError(e.to_string()))?;
 
         let contract = OutgoingContract {
-            payment_image: PaymentImage::Hash(*invoice.payment_hash()),
+            payment_image: PaymentImage::Hash(invoice.payment_hash()),
             amount: send_fee.add_to(amount),
             expiration: consensus_block_count + expiration_delta + CONTRACT_CONFIRMATION_BUFFER,
             claim_pk: routing_info.module_public_key,
@@ -567,8 +737,9 @@ pub async fn send(
                         outpoint: range.into_iter().next().unwrap(),
                         contract: contract_clone.clone(),
                         gateway_api: Some(gateway_api_clone.clone()),
-                        invoice: Some(LightningInvoice::Bolt11(invoice_clone.clone())),
+                        invoice: Some(invoice_clone.clone()),
                         refund_keypair,
+                        part_amount: None,
                     },
                     state: SendSMState::Funding,
                 })]
@@ -590,7 +761,240 @@ pub async fn send(
                         change_outpoint_range,
                         gateway: gateway_api.clone(),
                         contract: contract.clone(),
-                        invoice: LightningInvoice::Bolt11(invoice.clone()),
+                        invoice: invoice.clone(),
+                        custom_meta: custom_meta.clone(),
+                    })
+                },
+                transaction,
+            )
+            .await
+            .map_err(|e| SendPaymentError::FinalizationError(e.to_string()))?;
+
+        Ok(operation_id)
+    }
+
+    /// Pay an invoice by splitting the payment into up to `max_parts`
+    /// outgoing contracts that are routed via different gateways, such that
+    /// large payments do not depend on the liquidity of a single gateway. All
+    /// contracts commit to the payment hash of the invoice and are funded in a
+    /// single transaction. For testing you can optionally specify the gateways
+    /// to route with, otherwise online vetted gateways will be selected
+    /// automatically.
+    ///
+    /// Every contract is handled by its own send state machine, hence if a
+    /// gateway fails to pay its part the corresponding contract is refunded
+    /// just like a failed payment via a single gateway. Since the payee only
+    /// reveals the preimage once it has received the entire amount, the
+    /// contracts of the remaining parts will be refunded as well.
+    ///
+    /// Only gateways whose lightning node supports multi-path payments are
+    /// assigned a part. If the invoice was created by a gateway connected to
+    /// our federation or only a single such gateway is available we fall back
+    /// to a payment via a single gateway. The fee limit of [`LightningClientModule::send`] applies
+    /// to every part individually.
+    #[allow(clippy::too_many_lines)]
+    pub async fn send_multi_path(
+        &self,
+        invoice: Bolt11Invoice,
+        max_parts: usize,
+        gateways: Option<Vec<SafeUrl>>,
+        custom_meta: Value,
+    ) -> Result<OperationId, SendPaymentError> {
+        if max_parts == 0 {
+            return Err(SendPaymentError::ZeroParts);
+        }
+
+        if self.cfg.network != invoice.currency().into() {
+            return Err(SendPaymentError::WrongCurrency {
+                invoice_currency: invoice.currency(),
+                federation_currency: self.cfg.network.into(),
+            });
+        }
+
+        let invoice = LightningInvoice::Bolt11(invoice);
+
+        let amount = invoice
+            .amount_milli_satoshis()
+            .ok_or(SendPaymentError::InvoiceMissingAmount)?;
+
+        if invoice.is_expired() {
+            return Err(SendPaymentError::InvoiceExpired);
+        }
+
+        let gateways = if let Some(gateways) = gateways {
+            gateways
+        } else {
+            let gateways = self.module_api.gateways().await.map_err(|e| {
+                SendPaymentError::FailedToSelectGateway(SelectGatewayError::FederationError(
+                    e.to_string(),
+                ))
+            })?;
+
+            if gateways.is_empty() {
+                return Err(SendPaymentError::FailedToSelectGateway(
+                    SelectGatewayError::NoVettedGateways,
+                ));
+            }
+
+            // A direct swap via the gateway that created the invoice does not
+            // require any lightning liquidity
+            if self
+                .client_ctx
+                .module_db()
+                .begin_transaction_nc()
+                .await
+                .get_value(&GatewayKey(invoice.payee_pub_key()))
+                .await
+                .is_some_and(|gateway| gateways.contains(&gateway))
+            {
+                return self.send_invoice(invoice, None, custom_meta).await;
+            }
+
+            gateways
+        };
+
+        let mut routes: Vec<(SafeUrl, RoutingInfo)> = vec![];
+        let mut single_path_gateway = None;
+
+        for gateway in gateways {
+            if routes.len() == max_parts {
+                break;
+            }
+
+            if routes.iter().any(|(selected, _)| *selected == gateway) {
+                continue;
+            }
+
+            if let Ok(Some(routing_info)) = self.routing_info(&gateway).await {
+                if routing_info.lightning_public_key == invoice.payee_pub_key() {
+                    return self.send_invoice(invoice, Some(gateway), custom_meta).await;
+                }
+
+                // A gateway that can not pay a part of the invoice is only used
+                // if it has to pay the entire invoice anyway
+                if routing_info.supports_multi_path_payments {
+                    routes.push((gateway, routing_info));
+                } else if single_path_gateway.is_none() {
+                    single_path_gateway = Some(gateway);
+                }
+            }
+        }
+
+        if routes.len() < 2 {
+            let gateway = routes
+                .pop()
+                .map(|(gateway, _)| gateway)
+                .or(single_path_gateway)
+                .ok_or(SendPaymentError::FailedToSelectGateway(
+                    SelectGatewayError::FailedToFetchRoutingInfo,
+                ))?;
+
+            return self.send_invoice(invoice, Some(gateway), custom_meta).await;
+        }
+
+        let operation_id = self.get_next_operation_id(&invoice).await?;
+
+        let consensus_block_count = self
+            .module_api
+            .consensus_block_count()
+            .await
+            .map_err(|e| SendPaymentError::FederationError(e.to_string()))?;
+
+        // The remainder of the division is added to the first part
+        let num_parts = routes.len() as u64;
+        let part_amounts = (0..num_parts)
+            .map(|i| amount / num_parts + if i == 0 { amount % num_parts } else { 0 })
+            .map(Amount::from_msats);
+
+        let mut parts = vec![];
+        let mut refund_keypairs = vec![];
+
+        for ((gateway, routing_info), part_amount) in routes.into_iter().zip(part_amounts) {
+            let (send_fee, expiration_delta) = routing_info.send_parameters(&invoice);
+
+            if !send_fee.le(&PaymentFee::SEND_FEE_LIMIT) {
+                return Err(SendPaymentError::PaymentFeeExceedsLimit);
+            }
+
+            if EXPIRATION_DELTA_LIMIT < expiration_delta {
+                return Err(SendPaymentError::ExpirationDeltaExceedsLimit);
+            }
+
+            let (ephemeral_tweak, ephemeral_pk) =
+                generate_ephemeral_tweak(self.keypair.public_key());
+
+            let refund_keypair = SecretKey::from_slice(&ephemeral_tweak)
+                .expect("32 bytes, within curve order")
+                .keypair(secp256k1::SECP256K1);
+
+            let contract = OutgoingContract {
+                payment_image: PaymentImage::Hash(invoice.payment_hash()),
+                amount: send_fee.add_to(part_amount.msats),
+                expiration: consensus_block_count + expiration_delta + CONTRACT_CONFIRMATION_BUFFER,
+                claim_pk: routing_info.module_public_key,
+                refund_pk: refund_keypair.public_key(),
+                ephemeral_pk,
+            };
+
+            parts.push(SendPartMeta {
+                gateway,
+                contract,
+                amount: part_amount,
+            });
+            refund_keypairs.push(refund_keypair);
+        }
+
+        let client_outputs = parts
+            .iter()
+            .map(|part| ClientOutput::<LightningOutput> {
+                output: LightningOutput::V0(LightningOutputV0::Outgoing(part.contract.clone())),
+                amount: part.contract.amount,
+            })
+            .collect();
+
+        let parts_clone = parts.clone();
+        let invoice_clone = invoice.clone();
+
+        let client_output_sm = ClientOutputSM::<LightningClientStateMachines> {
+            state_machines: Arc::new(move |range: OutPointRange| {
+                assert_eq!(range.count(), parts_clone.len());
+
+                range
+                    .into_iter()
+                    .zip(parts_clone.iter().zip(refund_keypairs.iter()))
+                    .map(|(outpoint, (part, refund_keypair))| {
+                        LightningClientStateMachines::Send(SendStateMachine {
+                            common: SendSMCommon {
+                                operation_id,
+                                outpoint,
+                                contract: part.contract.clone(),
+                                gateway_api: Some(part.gateway.clone()),
+                                invoice: Some(invoice_clone.clone()),
+                                refund_keypair: *refund_keypair,
+                                part_amount: Some(part.amount),
+                            },
+                            state: SendSMState::Funding,
+                        })
+                    })
+                    .collect()
+            }),
+        };
+
+        let client_output = self.client_ctx.make_client_outputs(ClientOutputBundle::new(
+            client_outputs,
+            vec![client_output_sm],
+        ));
+        let transaction = TransactionBuilder::new().with_outputs(client_output);
+
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                LightningCommonInit::KIND.as_str(),
+                move |change_outpoint_range| {
+                    LightningOperationMeta::SendMultiPath(SendMultiPathOperationMeta {
+                        change_outpoint_range,
+                        parts: parts.clone(),
+                        invoice: invoice.clone(),
                         custom_meta: custom_meta.clone(),
                     })
                 },
@@ -604,10 +1008,19 @@ pub async fn send(
 
     async fn get_next_operation_id(
         &self,
-        invoice: &Bolt11Invoice,
+        invoice: &LightningInvoice,
     ) -> Result<OperationId, SendPaymentError> {
         for payment_attempt in 0..u64::MAX {
-            let operation_id = OperationId::from_encodable(&(invoice.clone(), payment_attempt));
+            // Bolt11 invoices are encoded without the variant to keep the operation ids of
+            // payments started before the introduction of Bolt12 unchanged.
+            let operation_id = match invoice {
+                LightningInvoice::Bolt11(invoice) => {
+                    OperationId::from_encodable(&(invoice.clone(), payment_attempt))
+                }
+                LightningInvoice::Bolt12(..) => {
+                    OperationId::from_encodable(&(invoice.clone(), payment_attempt))
+                }
+            };
 
             if !self.client_ctx.operation_exists(operation_id).await {
                 return Ok(operation_id);
@@ -635,23 +1048,45 @@ async fn get_next_operation_id(
         panic!("We could not find an unused operation id for sending a lightning payment");
     }
 
-    /// Subscribe to all state updates of the send operation.
+    /// Subscribe to all state updates of the send operation. The states of the
+    /// parts of a multi-path payment are aggregated: the operation is
+    /// successful once any part reveals the preimage and it is refunded once
+    /// the contracts of all parts have been refunded.
     pub async fn subscribe_send_operation_state_updates(
         &self,
         operation_id: OperationId,
     ) -> anyhow::Result<UpdateStreamOrOutcome<SendOperationState>> {
         let operation = self.client_ctx.get_operation(operation_id).await?;
+        let num_parts = match operation.meta::<LightningOperationMeta>() {
+            LightningOperationMeta::SendMultiPath(meta) => meta.parts.len(),
+            _ => 1,
+        };
         let mut stream = self.notifier.subscribe(operation_id).await;
         let client_ctx = self.client_ctx.clone();
         let module_api = self.module_api.clone();
 
         Ok(self.client_ctx.outcome_or_updates(operation, operation_id, move || {
             stream! {
+                let mut funding = false;
+                let mut funded = false;
+                let mut refunding = false;
+                let mut refunded = BTreeSet::new();
+
                 loop {
                     if let Some(LightningClientStateMachines::Send(state)) = stream.next().await {
                         match state.state {
-                            SendSMState::Funding => yield SendOperationState::Funding,
-                            SendSMState::Funded => yield SendOperationState::Funded,
+                            SendSMState::Funding => {
+                                if !funding && !funded {
+                                    funding = true;
+                                    yield SendOperationState::Funding;
+                                }
+                            },
+                            SendSMState::Funded => {
+                                if !funded && !refunding {
+                                    funded = true;
+                                    yield SendOperationState::Funded;
+                                }
+                            },
                             SendSMState::Success(preimage) => {
                                 // the preimage has been verified by the state machine previously
                                 assert!(state.common.contract.verify_preimage(&preimage));
@@ -660,11 +1095,20 @@ pub async fn subscribe_send_operation_state_updates(
                                 return;
                             },
                             SendSMState::Refunding(out_points) => {
-                                yield SendOperationState::Refunding;
+                                if !refunding {
+                                    refunding = true;
+                                    yield SendOperationState::Refunding;
+                                }
 
                                 if client_ctx.await_primary_module_outputs(operation_id, out_points.clone()).await.is_ok() {
-                                    yield SendOperationState::Refunded;
-                                    return;
+                                    refunded.insert(state.common.outpoint);
+
+                                    if refunded.len() == num_parts {
+                                        yield SendOperationState::Refunded;
+                                        return;
+                                    }
+
+                                    continue;
                                 }
 
                                 // The gateway may have incorrectly claimed the outgoing contract thereby causing
@@ -999,6 +1443,51 @@ pub enum SelectGatewayError {
     FailedToFetchRoutingInfo,
 }
 
+/// Checks that `invoice` was issued for `offer` by the offer's issuer and
+/// requests exactly `amount_msats` for a single item.
+fn is_invoice_for_offer(invoice: &Bolt12Invoice, offer: &Offer, amount_msats: u64) -> bool {
+    if invoice.amount_msats() != amount_msats || invoice.quantity().is_some() {
+        return false;
+    }
+
+    // The offer records determine the offer id, so an invoice repeating them
+    // exactly was requested for this offer
+    if offer_records(&invoice.encode()).as_deref() != Some(offer.as_ref()) {
+        return false;
+    }
+
+    // The invoice signature was verified against its signing key on decoding,
+    // so the key has to be the one the offer's issuer committed to
+    match offer.signing_pubkey() {
+        Some(signing_pubkey) => invoice.signing_pubkey() == signing_pubkey,
+        None => offer
+            .paths()
+            .iter()
+            .filter_map(|path| path.blinded_hops().last())
+            .any(|hop| hop.blinded_node_id == invoice.signing_pubkey()),
+    }
+}
+
+/// Extracts the offer records from the TLV stream of a Bolt12 invoice
+fn offer_records(mut tlv_stream: &[u8]) -> Option<Vec<u8>> {
+    let mut records = vec![];
+
+    while !tlv_stream.is_empty() {
+        let record = tlv_stream;
+
+        let BigSize(record_type) = BigSize::read(&mut tlv_stream).ok()?;
+        let BigSize(length) = BigSize::read(&mut tlv_stream).ok()?;
+
+        tlv_stream = tlv_stream.get(usize::try_from(length).ok()?..)?;
+
+        if OFFER_TLV_TYPES.contains(&record_type) {
+            records.extend_from_slice(&record[..record.len() - tlv_stream.len()]);
+        }
+    }
+
+    Some(records)
+}
+
 #[derive(Error, Debug, Clone, Eq, PartialEq)]
 pub enum SendPaymentError {
     #[error("The invoice has not amount")]
@@ -1030,6 +1519,14 @@ pub enum SendPaymentError {
         invoice_currency: Currency,
         federation_currency: Currency,
     },
+    #[error("The offer is for the wrong network")]
+    WrongNetwork,
+    #[error("The gateway returned an invoice that does not match the offer")]
+    InvalidOfferInvoice,
+    #[error("The gateway does not support paying offers")]
+    Bolt12NotSupported,
+    #[error("The payment has to be split into at least one part")]
+    ZeroParts,
 }
 
 #[derive(Error, Debug, Clone, Eq, PartialEq)]
@@ -1050,6 +1547,7 @@ pub enum ReceiveError {
     InvalidInvoiceAmount,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub enum LightningClientStateMachines {
     Send(SendStateMachine),
@@ -1,5 +1,4 @@
 use anyhow::ensure;
-use bitcoin::hashes::sha256;
 use fedimint_client_module::DynGlobalClientContext;
 use fedimint_client_module::sm::{ClientSMDatabaseTransaction, State, StateTransition};
 use fedimint_client_module::transaction::{ClientInput, ClientInputBundle};
@@ -8,8 +7,9 @@
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::util::SafeUrl;
 use fedimint_core::util::backoff_util::api_networking_backoff;
-use fedimint_core::{OutPoint, TransactionId, crit, secp256k1, util};
+use fedimint_core::{Amount, OutPoint, TransactionId, crit, secp256k1, util};
 use fedimint_lnv2_common::contracts::OutgoingContract;
+use fedimint_lnv2_common::gateway_api::SendPaymentPayload;
 use fedimint_lnv2_common::{LightningInput, LightningInputV0, OutgoingWitness};
 use fedimint_logging::LOG_CLIENT_MODULE_LNV2;
 use futures::future::pending;
@@ -43,6 +43,9 @@ pub struct SendSMCommon {
     pub gateway_api: Option<SafeUrl>,
     pub invoice: Option<LightningInvoice>,
     pub refund_keypair: Keypair,
+    /// The part of the invoice amount paid by this contract if the payment is
+    /// split across multiple gateways.
+    pub part_amount: Option<Amount>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
@@ -98,6 +101,7 @@ fn transitions(
                             self.common.outpoint,
                             self.common.contract.clone(),
                             self.common.invoice.clone().unwrap(),
+                            self.common.part_amount,
                             self.common.refund_keypair,
                             context.clone(),
                         ),
@@ -156,6 +160,7 @@ fn transition_funding(
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[instrument(target = LOG_CLIENT_MODULE_LNV2, skip(refund_keypair, context))]
     async fn gateway_send_payment(
         gateway_api: SafeUrl,
@@ -163,6 +168,7 @@ async fn gateway_send_payment(
         outpoint: OutPoint,
         contract: OutgoingContract,
         invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         refund_keypair: Keypair,
         context: LightningClientContext,
     ) -> Result<[u8; 32], Signature> {
@@ -175,9 +181,9 @@ async fn gateway_send_payment(
                     outpoint,
                     contract.clone(),
                     invoice.clone(),
-                    refund_keypair.sign_schnorr(secp256k1::Message::from_digest(
-                        *invoice.consensus_hash::<sha256::Hash>().as_ref(),
-                    )),
+                    part_amount,
+                    refund_keypair
+                        .sign_schnorr(SendPaymentPayload::auth_message(&invoice, part_amount)),
                 )
                 .await?;
 
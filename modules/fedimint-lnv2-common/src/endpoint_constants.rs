@@ -9,6 +9,7 @@
 pub const REMOVE_GATEWAY_ENDPOINT: &str = "remove_gateway";
 
 // Gateway endpoints
+pub const BOLT12_INVOICE_ENDPOINT: &str = "/bolt12_invoice";
 pub const CREATE_BOLT11_INVOICE_ENDPOINT: &str = "/create_bolt11_invoice";
 pub const ROUTING_INFO_ENDPOINT: &str = "/routing_info";
 pub const SEND_PAYMENT_ENDPOINT: &str = "/send_payment";
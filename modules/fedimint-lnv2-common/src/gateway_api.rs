@@ -1,7 +1,8 @@
 use std::ops::Add;
 
-use bitcoin::secp256k1::PublicKey;
+use bitcoin::hashes::sha256;
 use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::{Message, PublicKey};
 use fedimint_core::config::FederationId;
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::util::SafeUrl;
@@ -12,7 +13,8 @@
 
 use crate::contracts::{IncomingContract, OutgoingContract};
 use crate::endpoint_constants::{
-    CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
+    BOLT12_INVOICE_ENDPOINT, CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT,
+    SEND_PAYMENT_ENDPOINT,
 };
 use crate::{Bolt11InvoiceDescription, LightningInvoice};
 
@@ -34,6 +36,15 @@ async fn bolt11_invoice(
         expiry_secs: u32,
     ) -> Result<Bolt11Invoice, GatewayConnectionError>;
 
+    async fn bolt12_invoice(
+        &self,
+        gateway_api: SafeUrl,
+        federation_id: FederationId,
+        offer: String,
+        amount: Option<Amount>,
+        payer_note: Option<String>,
+    ) -> Result<LightningInvoice, GatewayConnectionError>;
+
     async fn send_payment(
         &self,
         gateway_api: SafeUrl,
@@ -41,6 +52,7 @@ async fn send_payment(
         outpoint: OutPoint,
         contract: OutgoingContract,
         invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         auth: Signature,
     ) -> Result<Result<[u8; 32], Signature>, GatewayConnectionError>;
 }
@@ -110,6 +122,35 @@ async fn bolt11_invoice(
             .map_err(|e| GatewayConnectionError::Request(e.to_string()))
     }
 
+    async fn bolt12_invoice(
+        &self,
+        gateway_api: SafeUrl,
+        federation_id: FederationId,
+        offer: String,
+        amount: Option<Amount>,
+        payer_note: Option<String>,
+    ) -> Result<LightningInvoice, GatewayConnectionError> {
+        reqwest::Client::new()
+            .post(
+                gateway_api
+                    .join(BOLT12_INVOICE_ENDPOINT)
+                    .expect("'bolt12_invoice' contains no invalid characters for a URL")
+                    .as_str(),
+            )
+            .json(&Bolt12InvoicePayload {
+                federation_id,
+                offer,
+                amount,
+                payer_note,
+            })
+            .send()
+            .await
+            .map_err(|e| GatewayConnectionError::Unreachable(e.to_string()))?
+            .json::<LightningInvoice>()
+            .await
+            .map_err(|e| GatewayConnectionError::Request(e.to_string()))
+    }
+
     async fn send_payment(
         &self,
         gateway_api: SafeUrl,
@@ -117,6 +158,7 @@ async fn send_payment(
         outpoint: OutPoint,
         contract: OutgoingContract,
         invoice: LightningInvoice,
+        part_amount: Option<Amount>,
         auth: Signature,
     ) -> Result<Result<[u8; 32], Signature>, GatewayConnectionError> {
         reqwest::Client::new()
@@ -131,6 +173,7 @@ async fn send_payment(
                 outpoint,
                 contract,
                 invoice,
+                part_amount,
                 auth,
             })
             .send()
@@ -151,15 +194,42 @@ pub struct CreateBolt11InvoicePayload {
     pub expiry_secs: u32,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Bolt12InvoicePayload {
+    pub federation_id: FederationId,
+    pub offer: String,
+    pub amount: Option<Amount>,
+    pub payer_note: Option<String>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct SendPaymentPayload {
     pub federation_id: FederationId,
     pub outpoint: OutPoint,
     pub contract: OutgoingContract,
     pub invoice: LightningInvoice,
+    /// If set, the gateway only pays this part of the invoice amount as one
+    /// path of a multi-path payment, the remaining amount is paid via other
+    /// gateways.
+    pub part_amount: Option<Amount>,
     pub auth: Signature,
 }
 
+impl SendPaymentPayload {
+    /// The message signed with the refund key of the outgoing contract in
+    /// order to authorize the gateway to pay the invoice. A payment of the
+    /// entire invoice amount only commits to the invoice such that the message
+    /// stays compatible with gateways that do not support multi-path payments.
+    pub fn auth_message(invoice: &LightningInvoice, part_amount: Option<Amount>) -> Message {
+        let message_hash = match part_amount {
+            Some(part_amount) => (invoice.clone(), part_amount).consensus_hash::<sha256::Hash>(),
+            None => invoice.consensus_hash::<sha256::Hash>(),
+        };
+
+        Message::from_digest(*message_hash.as_ref())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct RoutingInfo {
     /// The public key of the gateways lightning node. Since this key signs the
@@ -187,11 +257,20 @@ pub struct RoutingInfo {
     pub expiration_delta_default: u64,
     /// This is the fee the gateway charges for an incoming payment.
     pub receive_fee: PaymentFee,
+    /// Whether the gateways lightning node is able to request and pay invoices
+    /// for offers. Gateways that predate this field do not support offers.
+    #[serde(default)]
+    pub supports_bolt12_invoices: bool,
+    /// Whether the gateways lightning node is able to pay a part of an invoice
+    /// as one path of a multi-path payment. Gateways that predate this field
+    /// do not support multi-path payments.
+    #[serde(default)]
+    pub supports_multi_path_payments: bool,
 }
 
 impl RoutingInfo {
-    pub fn send_parameters(&self, invoice: &Bolt11Invoice) -> (PaymentFee, u64) {
-        if invoice.recover_payee_pub_key() == self.lightning_public_key {
+    pub fn send_parameters(&self, invoice: &LightningInvoice) -> (PaymentFee, u64) {
+        if invoice.payee_pub_key() == self.lightning_public_key {
             (self.send_fee_minimum, self.expiration_delta_minimum)
         } else {
             (self.send_fee_default, self.expiration_delta_default)
@@ -14,13 +14,15 @@
 pub mod endpoint_constants;
 pub mod gateway_api;
 
-use bitcoin::hashes::sha256;
+use bitcoin::hashes::{Hash, sha256};
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::secp256k1::schnorr::Signature;
 use config::LightningClientConfig;
 use fedimint_core::core::{Decoder, ModuleInstanceId, ModuleKind};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::{CommonModuleInit, ModuleCommon, ModuleConsensusVersion};
 use fedimint_core::{OutPoint, extensible_associated_module_type, plugin_types_trait_impl_common};
+use lightning::offers::invoice::Bolt12Invoice;
 use lightning_invoice::Bolt11Invoice;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -34,9 +36,48 @@ pub enum Bolt11InvoiceDescription {
     Hash(sha256::Hash),
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Decodable, Encodable)]
 pub enum LightningInvoice {
     Bolt11(Bolt11Invoice),
+    Bolt12(#[serde(with = "::fedimint_core::encoding::as_hex")] Bolt12Invoice),
+}
+
+impl LightningInvoice {
+    /// The payment hash the payee reveals the preimage for on payment.
+    pub fn payment_hash(&self) -> sha256::Hash {
+        match self {
+            LightningInvoice::Bolt11(invoice) => *invoice.payment_hash(),
+            LightningInvoice::Bolt12(invoice) => {
+                sha256::Hash::from_byte_array(invoice.payment_hash().0)
+            }
+        }
+    }
+
+    /// The amount requested by the invoice, a Bolt12 invoice always carries an
+    /// amount even if the offer it was requested for did not.
+    pub fn amount_milli_satoshis(&self) -> Option<u64> {
+        match self {
+            LightningInvoice::Bolt11(invoice) => invoice.amount_milli_satoshis(),
+            LightningInvoice::Bolt12(invoice) => Some(invoice.amount_msats()),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self {
+            LightningInvoice::Bolt11(invoice) => invoice.is_expired(),
+            LightningInvoice::Bolt12(invoice) => invoice.is_expired(),
+        }
+    }
+
+    /// The public key the invoice has been signed with. For a Bolt11 invoice
+    /// this is the public key of the payee's lightning node.
+    pub fn payee_pub_key(&self) -> PublicKey {
+        match self {
+            LightningInvoice::Bolt11(invoice) => invoice.get_payee_pub_key(),
+            LightningInvoice::Bolt12(invoice) => invoice.signing_pubkey(),
+        }
+    }
 }
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("lnv2");
@@ -0,0 +1,30 @@
+use fedimint_core::core::ModuleKind;
+use fedimint_core::util::SafeUrl;
+use fedimint_eventlog::{Event, EventKind};
+use serde::{Deserialize, Serialize};
+
+/// Event that is emitted when the guardian registers a new gateway
+#[derive(Serialize, Deserialize)]
+pub struct GatewayRegistered {
+    /// The API endpoint of the gateway
+    pub gateway: SafeUrl,
+}
+
+impl Event for GatewayRegistered {
+    const MODULE: Option<ModuleKind> = Some(fedimint_lnv2_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("gateway-registered");
+}
+
+/// Event that is emitted when the guardian removes a registered gateway
+#[derive(Serialize, Deserialize)]
+pub struct GatewayRemoved {
+    /// The API endpoint of the gateway
+    pub gateway: SafeUrl,
+}
+
+impl Event for GatewayRemoved {
+    const MODULE: Option<ModuleKind> = Some(fedimint_lnv2_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("gateway-removed");
+}
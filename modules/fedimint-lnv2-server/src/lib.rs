@@ -3,6 +3,7 @@
 #![allow(clippy::module_name_repetitions)]
 
 mod db;
+pub mod events;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
@@ -48,6 +49,7 @@
 use fedimint_logging::LOG_MODULE_LNV2;
 use fedimint_server_core::bitcoin_rpc::ServerBitcoinRpcMonitor;
 use fedimint_server_core::config::{PeerHandleOps, eval_poly_g1};
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::net::check_auth;
 use fedimint_server_core::{ServerModule, ServerModuleInit, ServerModuleInitArgs};
 use futures::StreamExt;
@@ -68,6 +70,7 @@
     OutgoingContractKey, OutgoingContractPrefix, PreimageKey, PreimagePrefix, UnixTimeVoteKey,
     UnixTimeVotePrefix,
 };
+use crate::events::{GatewayRegistered, GatewayRemoved};
 
 #[derive(Debug, Clone)]
 pub struct LightningInit;
@@ -201,6 +204,7 @@ async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::
             cfg: args.cfg().to_typed()?,
             db: args.db().clone(),
             server_bitcoin_rpc_monitor: args.server_bitcoin_rpc_monitor(),
+            event_logger: args.event_logger(),
         })
     }
 
@@ -339,6 +343,7 @@ pub struct Lightning {
     cfg: LightningConfig,
     db: Database,
     server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+    event_logger: ServerModuleEventLogger,
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -612,23 +617,23 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
             api_endpoint! {
                 ADD_GATEWAY_ENDPOINT,
                 ApiVersion::new(0, 0),
-                async |_module: &Lightning, context, gateway: SafeUrl| -> bool {
+                async |module: &Lightning, context, gateway: SafeUrl| -> bool {
                     check_auth(context)?;
 
                     let db = context.db();
 
-                    Ok(Lightning::add_gateway(db, gateway).await)
+                    Ok(module.add_gateway(db, gateway).await)
                 }
             },
             api_endpoint! {
                 REMOVE_GATEWAY_ENDPOINT,
                 ApiVersion::new(0, 0),
-                async |_module: &Lightning, context, gateway: SafeUrl| -> bool {
+                async |module: &Lightning, context, gateway: SafeUrl| -> bool {
                     check_auth(context)?;
 
                     let db = context.db();
 
-                    Ok(Lightning::remove_gateway(db, gateway).await)
+                    Ok(module.remove_gateway(db, gateway).await)
                 }
             },
             api_endpoint! {
@@ -773,20 +778,38 @@ async fn outgoing_contract_expiration(
         Some((contract.contract_id(), expiration))
     }
 
-    async fn add_gateway(db: Database, gateway: SafeUrl) -> bool {
+    async fn add_gateway(&self, db: Database, gateway: SafeUrl) -> bool {
         let mut dbtx = db.begin_transaction().await;
 
-        let is_new_entry = dbtx.insert_entry(&GatewayKey(gateway), &()).await.is_none();
+        let is_new_entry = dbtx
+            .insert_entry(&GatewayKey(gateway.clone()), &())
+            .await
+            .is_none();
+
+        if is_new_entry {
+            self.event_logger
+                .log_event(&mut dbtx.to_ref_nc(), GatewayRegistered { gateway })
+                .await;
+        }
 
         dbtx.commit_tx().await;
 
         is_new_entry
     }
 
-    async fn remove_gateway(db: Database, gateway: SafeUrl) -> bool {
+    async fn remove_gateway(&self, db: Database, gateway: SafeUrl) -> bool {
         let mut dbtx = db.begin_transaction().await;
 
-        let entry_existed = dbtx.remove_entry(&GatewayKey(gateway)).await.is_some();
+        let entry_existed = dbtx
+            .remove_entry(&GatewayKey(gateway.clone()))
+            .await
+            .is_some();
+
+        if entry_existed {
+            self.event_logger
+                .log_event(&mut dbtx.to_ref_nc(), GatewayRemoved { gateway })
+                .await;
+        }
 
         dbtx.commit_tx().await;
 
@@ -814,11 +837,11 @@ pub async fn consensus_unix_time_ui(&self) -> u64 {
     }
 
     pub async fn add_gateway_ui(&self, gateway: SafeUrl) -> bool {
-        Self::add_gateway(self.db.clone(), gateway).await
+        self.add_gateway(self.db.clone(), gateway).await
     }
 
     pub async fn remove_gateway_ui(&self, gateway: SafeUrl) -> bool {
-        Self::remove_gateway(self.db.clone(), gateway).await
+        self.remove_gateway(self.db.clone(), gateway).await
     }
 
     pub async fn gateways_ui(&self) -> Vec<SafeUrl> {
@@ -14,6 +14,14 @@
     GatewayConnection, GatewayConnectionError, PaymentFee, RoutingInfo,
 };
 use fedimint_lnv2_common::{Bolt11InvoiceDescription, LightningInvoice};
+use lightning::blinded_path::payment::{
+    BlindedPaymentPath, Bolt12OfferContext, PaymentConstraints, PaymentContext, ReceiveTlvs,
+};
+use lightning::offers::invoice::{Bolt12Invoice, UnsignedBolt12Invoice};
+use lightning::offers::invoice_request::{InvoiceRequestFields, UnsignedInvoiceRequest};
+use lightning::offers::offer::{Offer, OfferBuilder};
+use lightning::sign::RandomBytes;
+use lightning::types::payment::PaymentHash;
 use lightning_invoice::{
     Bolt11Invoice, Currency, DEFAULT_EXPIRY_TIME, InvoiceBuilder, PaymentSecret,
 };
@@ -26,12 +34,31 @@
 
 const GATEWAY_CRASH_PAYMENT_SECRET: [u8; 32] = [213; 32];
 
+const OFFER_ISSUER_SECRET: [u8; 32] = [2; 32];
+
+/// Offers with this description are answered with an invoice for an offer of
+/// the gateway itself, as a malicious gateway would do to steal the payment
+const STOLEN_OFFER_DESCRIPTION: &str = "stolen";
+
 pub const MOCK_INVOICE_PREIMAGE: [u8; 32] = [1; 32];
 
 pub fn gateway() -> SafeUrl {
     SafeUrl::parse("https://gateway.xyz").expect("Valid Url")
 }
 
+pub fn second_gateway() -> SafeUrl {
+    SafeUrl::parse("https://second.gateway.xyz").expect("Valid Url")
+}
+
+/// A gateway whose lightning node is unable to pay offers
+pub fn no_offers_gateway() -> SafeUrl {
+    SafeUrl::parse("https://no-offers.gateway.xyz").expect("Valid Url")
+}
+
+pub fn single_path_gateway() -> SafeUrl {
+    SafeUrl::parse("https://single-path.gateway.xyz").expect("Valid Url")
+}
+
 pub fn gateway_keypair() -> Keypair {
     SecretKey::from_slice(&GATEWAY_SECRET)
         .expect("32 bytes; within curve order")
@@ -70,6 +97,106 @@ pub fn signet_bolt_11_invoice() -> Bolt11Invoice {
     bolt_11_invoice(PAYABLE_PAYMENT_SECRET, Currency::Signet)
 }
 
+pub fn signet_offer() -> Offer {
+    OfferBuilder::new(gateway_keypair().public_key())
+        .chain(bitcoin::Network::Signet)
+        .amount_msats(1_000_000)
+        .build()
+        .expect("Offer creation failed")
+}
+
+fn offer_issuer_keypair() -> Keypair {
+    SecretKey::from_slice(&OFFER_ISSUER_SECRET)
+        .expect("32 bytes; within curve order")
+        .keypair(SECP256K1)
+}
+
+pub fn payable_offer() -> Offer {
+    OfferBuilder::new(offer_issuer_keypair().public_key())
+        .chain(bitcoin::Network::Regtest)
+        .amount_msats(1_000_000)
+        .build()
+        .expect("Offer creation failed")
+}
+
+pub fn variable_amount_offer() -> Offer {
+    OfferBuilder::new(offer_issuer_keypair().public_key())
+        .chain(bitcoin::Network::Regtest)
+        .build()
+        .expect("Offer creation failed")
+}
+
+pub fn stolen_offer() -> Offer {
+    OfferBuilder::new(offer_issuer_keypair().public_key())
+        .chain(bitcoin::Network::Regtest)
+        .description(STOLEN_OFFER_DESCRIPTION.to_string())
+        .amount_msats(1_000_000)
+        .build()
+        .expect("Offer creation failed")
+}
+
+/// Requests an invoice for `offer` and responds to the request as the offer's
+/// issuer with the secret key of `issuer`
+fn bolt12_invoice(offer: &Offer, issuer: &Keypair, amount: Option<Amount>) -> Bolt12Invoice {
+    let payer = Keypair::new(SECP256K1, &mut OsRng);
+
+    let mut invoice_request = offer
+        .request_invoice(vec![42; 16], payer.public_key())
+        .expect("Offer has no unknown features")
+        .chain(bitcoin::Network::Regtest)
+        .expect("Offer supports regtest");
+
+    if let Some(amount) = amount {
+        invoice_request = invoice_request
+            .amount_msats(amount.msats)
+            .expect("Amount is valid");
+    }
+
+    let invoice_request = invoice_request
+        .build()
+        .expect("Invoice request is valid")
+        .sign(|message: &UnsignedInvoiceRequest| {
+            Ok(SECP256K1.sign_schnorr_no_aux_rand(message.as_ref().as_digest(), &payer))
+        })
+        .expect("Invoice request signing failed");
+
+    let payment_path = BlindedPaymentPath::one_hop(
+        issuer.public_key(),
+        ReceiveTlvs {
+            payment_secret: PaymentSecret([0; 32]),
+            payment_constraints: PaymentConstraints {
+                max_cltv_expiry: u32::MAX,
+                htlc_minimum_msat: 1,
+            },
+            payment_context: PaymentContext::Bolt12Offer(Bolt12OfferContext {
+                offer_id: offer.id(),
+                invoice_request: InvoiceRequestFields {
+                    payer_id: payer.public_key(),
+                    quantity: None,
+                    payer_note_truncated: None,
+                },
+            }),
+        },
+        144,
+        &RandomBytes::new([0; 32]),
+        SECP256K1,
+    )
+    .expect("Blinded path creation failed");
+
+    invoice_request
+        .respond_with(
+            vec![payment_path],
+            PaymentHash(sha256::Hash::hash(&MOCK_INVOICE_PREIMAGE).to_byte_array()),
+        )
+        .expect("Invoice request has no unknown features")
+        .build()
+        .expect("Invoice is valid")
+        .sign(|message: &UnsignedBolt12Invoice| {
+            Ok(SECP256K1.sign_schnorr_no_aux_rand(message.as_ref().as_digest(), issuer))
+        })
+        .expect("Invoice signing failed")
+}
+
 #[derive(Debug)]
 pub struct MockGatewayConnection {
     keypair: Keypair,
@@ -87,7 +214,7 @@ fn default() -> Self {
 impl GatewayConnection for MockGatewayConnection {
     async fn routing_info(
         &self,
-        _gateway_api: SafeUrl,
+        gateway_api: SafeUrl,
         _federation_id: &FederationId,
     ) -> Result<Option<RoutingInfo>, GatewayConnectionError> {
         Ok(Some(RoutingInfo {
@@ -98,6 +225,8 @@ async fn routing_info(
             expiration_delta_default: 500,
             expiration_delta_minimum: 144,
             receive_fee: PaymentFee::TRANSACTION_FEE_DEFAULT,
+            supports_bolt12_invoices: gateway_api != no_offers_gateway(),
+            supports_multi_path_payments: gateway_api != single_path_gateway(),
         }))
     }
 
@@ -127,6 +256,42 @@ async fn bolt11_invoice(
             .unwrap())
     }
 
+    async fn bolt12_invoice(
+        &self,
+        _gateway_api: SafeUrl,
+        _federation_id: FederationId,
+        offer: String,
+        amount: Option<Amount>,
+        _payer_note: Option<String>,
+    ) -> Result<LightningInvoice, GatewayConnectionError> {
+        let offer = offer
+            .parse::<Offer>()
+            .map_err(|e| GatewayConnectionError::Request(format!("Invalid offer: {e:?}")))?;
+
+        if offer
+            .description()
+            .is_some_and(|description| description.to_string() == STOLEN_OFFER_DESCRIPTION)
+        {
+            let gateway_offer = OfferBuilder::new(self.keypair.public_key())
+                .chain(bitcoin::Network::Regtest)
+                .amount_msats(1_000_000)
+                .build()
+                .expect("Offer creation failed");
+
+            return Ok(LightningInvoice::Bolt12(bolt12_invoice(
+                &gateway_offer,
+                &self.keypair,
+                amount,
+            )));
+        }
+
+        Ok(LightningInvoice::Bolt12(bolt12_invoice(
+            &offer,
+            &offer_issuer_keypair(),
+            amount,
+        )))
+    }
+
     async fn send_payment(
         &self,
         _gateway_api: SafeUrl,
@@ -134,6 +299,7 @@ async fn send_payment(
         _outpoint: OutPoint,
         contract: OutgoingContract,
         invoice: LightningInvoice,
+        _part_amount: Option<Amount>,
         _auth: Signature,
     ) -> Result<Result<[u8; 32], Signature>, GatewayConnectionError> {
         match invoice {
@@ -148,6 +314,7 @@ async fn send_payment(
 
                 Ok(Ok(MOCK_INVOICE_PREIMAGE))
             }
+            LightningInvoice::Bolt12(..) => Ok(Ok(MOCK_INVOICE_PREIMAGE)),
         }
     }
 }
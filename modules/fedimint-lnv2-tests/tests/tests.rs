@@ -129,6 +129,154 @@ async fn refund_failed_payment() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn can_pay_invoice_via_multiple_gateways() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    // Print money for client
+    let (op, outpoint) = client
+        .get_first_module::<DummyClientModule>()?
+        .print_money(sats(10_000))
+        .await?;
+
+    client.await_primary_module_output(op, outpoint).await?;
+
+    let operation_id = client
+        .get_first_module::<LightningClientModule>()?
+        .send_multi_path(
+            mock::payable_invoice(),
+            2,
+            Some(vec![mock::gateway(), mock::second_gateway()]),
+            Value::Null,
+        )
+        .await?;
+
+    let operation = client
+        .operation_log()
+        .get_operation(operation_id)
+        .await
+        .ok_or(anyhow::anyhow!("Operation not found"))?;
+
+    match operation.meta::<LightningOperationMeta>() {
+        LightningOperationMeta::SendMultiPath(meta) => {
+            assert_eq!(meta.parts.len(), 2);
+            assert_eq!(
+                meta.parts.iter().map(|part| part.amount).sum::<Amount>(),
+                Amount::from_msats(1_000_000)
+            );
+        }
+        _ => panic!("Operation Meta is not a SendMultiPath variant"),
+    }
+
+    let mut sub = client
+        .get_first_module::<LightningClientModule>()?
+        .subscribe_send_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, SendOperationState::Funding);
+    assert_eq!(sub.ok().await?, SendOperationState::Funded);
+    assert_eq!(
+        sub.ok().await?,
+        SendOperationState::Success(MOCK_INVOICE_PREIMAGE)
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn multi_path_payment_skips_gateways_without_support() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    // Print money for client
+    let (op, outpoint) = client
+        .get_first_module::<DummyClientModule>()?
+        .print_money(sats(10_000))
+        .await?;
+
+    client.await_primary_module_output(op, outpoint).await?;
+
+    // Only one of the gateways supports multi-path payments, so the invoice is
+    // paid in full via that gateway
+    let operation_id = client
+        .get_first_module::<LightningClientModule>()?
+        .send_multi_path(
+            mock::payable_invoice(),
+            2,
+            Some(vec![mock::single_path_gateway(), mock::gateway()]),
+            Value::Null,
+        )
+        .await?;
+
+    let operation = client
+        .operation_log()
+        .get_operation(operation_id)
+        .await
+        .ok_or(anyhow::anyhow!("Operation not found"))?;
+
+    match operation.meta::<LightningOperationMeta>() {
+        LightningOperationMeta::Send(meta) => assert_eq!(meta.gateway, mock::gateway()),
+        _ => panic!("Operation Meta is not a Send variant"),
+    }
+
+    let mut sub = client
+        .get_first_module::<LightningClientModule>()?
+        .subscribe_send_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, SendOperationState::Funding);
+    assert_eq!(sub.ok().await?, SendOperationState::Funded);
+    assert_eq!(
+        sub.ok().await?,
+        SendOperationState::Success(MOCK_INVOICE_PREIMAGE)
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refund_failed_multi_path_payment() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    // Print money for client
+    let (op, outpoint) = client
+        .get_first_module::<DummyClientModule>()?
+        .print_money(sats(10_000))
+        .await?;
+
+    client.await_primary_module_output(op, outpoint).await?;
+
+    let operation_id = client
+        .get_first_module::<LightningClientModule>()?
+        .send_multi_path(
+            mock::unpayable_invoice(),
+            2,
+            Some(vec![mock::gateway(), mock::second_gateway()]),
+            Value::Null,
+        )
+        .await?;
+
+    let mut sub = client
+        .get_first_module::<LightningClientModule>()?
+        .subscribe_send_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, SendOperationState::Funding);
+    assert_eq!(sub.ok().await?, SendOperationState::Funded);
+    assert_eq!(sub.ok().await?, SendOperationState::Refunding);
+    assert_eq!(sub.ok().await?, SendOperationState::Refunded);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn unilateral_refund_of_outgoing_contracts() -> anyhow::Result<()> {
     let fixtures = fixtures();
@@ -202,6 +350,9 @@ async fn claiming_outgoing_contract_triggers_success() -> anyhow::Result<()> {
     let (contract, txid) = match operation.meta::<LightningOperationMeta>() {
         LightningOperationMeta::Send(meta) => (meta.contract, meta.change_outpoint_range.txid),
         LightningOperationMeta::Receive(..) => panic!("Operation Meta is a Receive variant"),
+        LightningOperationMeta::SendMultiPath(..) => {
+            panic!("Operation Meta is a SendMultiPath variant")
+        }
     };
 
     let client_input = ClientInput::<LightningInput> {
@@ -291,3 +442,151 @@ async fn rejects_wrong_network_invoice() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn can_pay_offer() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    // Print money for client
+    let (op, outpoint) = client
+        .get_first_module::<DummyClientModule>()?
+        .print_money(sats(10_000))
+        .await?;
+
+    client.await_primary_module_output(op, outpoint).await?;
+
+    let operation_id = client
+        .get_first_module::<LightningClientModule>()?
+        .send_offer(
+            mock::payable_offer(),
+            None,
+            Some(mock::gateway()),
+            Value::Null,
+        )
+        .await?;
+
+    let mut sub = client
+        .get_first_module::<LightningClientModule>()?
+        .subscribe_send_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, SendOperationState::Funding);
+    assert_eq!(sub.ok().await?, SendOperationState::Funded);
+    assert_eq!(
+        sub.ok().await?,
+        SendOperationState::Success(MOCK_INVOICE_PREIMAGE)
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_offer_invoice_not_matching_offer() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    let lightning = client.get_first_module::<LightningClientModule>()?;
+
+    // The gateway swaps in an invoice for its own offer
+    assert_eq!(
+        lightning
+            .send_offer(
+                mock::stolen_offer(),
+                None,
+                Some(mock::gateway()),
+                Value::Null
+            )
+            .await
+            .expect_err("send_offer did not reject the invoice of another offer"),
+        SendPaymentError::InvalidOfferInvoice
+    );
+
+    // Without an amount in the offer we can not verify the amount of the invoice
+    assert_eq!(
+        lightning
+            .send_offer(
+                mock::variable_amount_offer(),
+                None,
+                Some(mock::gateway()),
+                Value::Null
+            )
+            .await
+            .expect_err("send_offer did not require an amount"),
+        SendPaymentError::InvoiceMissingAmount
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_multi_path_payment_without_parts() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    assert_eq!(
+        client
+            .get_first_module::<LightningClientModule>()?
+            .send_multi_path(
+                mock::payable_invoice(),
+                0,
+                Some(vec![mock::gateway(), mock::second_gateway()]),
+                Value::Null
+            )
+            .await
+            .expect_err("send_multi_path did not reject zero parts"),
+        SendPaymentError::ZeroParts
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_wrong_network_offer() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    assert_eq!(
+        client
+            .get_first_module::<LightningClientModule>()?
+            .send_offer(
+                mock::signet_offer(),
+                None,
+                Some(mock::gateway()),
+                Value::Null
+            )
+            .await
+            .expect_err("send_offer did not fail due to incorrect network"),
+        SendPaymentError::WrongNetwork
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_offer_via_gateway_without_offer_support() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    assert_eq!(
+        client
+            .get_first_module::<LightningClientModule>()?
+            .send_offer(
+                mock::payable_offer(),
+                None,
+                Some(mock::no_offers_gateway()),
+                Value::Null
+            )
+            .await
+            .expect_err("send_offer did not reject a gateway without offer support"),
+        SendPaymentError::Bolt12NotSupported
+    );
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use fedimint_core::core::ModuleKind;
+use fedimint_eventlog::{Event, EventKind};
+use fedimint_meta_common::{MetaKey, MetaValue};
+use serde::{Deserialize, Serialize};
+
+/// Event that is emitted when a threshold of guardians agreed on a new value
+/// for a meta key
+#[derive(Serialize, Deserialize)]
+pub struct MetaValueChanged {
+    /// The key whose value changed
+    pub key: MetaKey,
+
+    /// The revision of the new consensus value
+    pub revision: u64,
+
+    /// The new consensus value
+    pub value: MetaValue,
+}
+
+impl Event for MetaValueChanged {
+    const MODULE: Option<ModuleKind> = Some(fedimint_meta_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("meta-value-changed");
+}
@@ -4,6 +4,7 @@
 #![allow(clippy::missing_errors_doc)]
 
 pub mod db;
+pub mod events;
 
 use std::collections::BTreeMap;
 use std::future;
@@ -46,6 +47,7 @@
     MetaOutputError, MetaOutputOutcome, MetaValue,
 };
 use fedimint_server_core::config::PeerHandleOps;
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::migration::ServerModuleDbMigrationFn;
 use fedimint_server_core::{ServerModule, ServerModuleInit, ServerModuleInitArgs};
 use futures::StreamExt;
@@ -57,6 +59,7 @@
     DbKeyPrefix, MetaConsensusKeyPrefix, MetaDesiredKeyPrefix, MetaSubmissionValue,
     MetaSubmissionsKeyPrefix,
 };
+use crate::events::MetaValueChanged;
 
 /// Generates the module
 #[derive(Debug, Clone)]
@@ -146,6 +149,7 @@ async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::
             our_peer_id: args.our_peer_id(),
             num_peers: args.num_peers(),
             db: args.db().clone(),
+            event_logger: args.event_logger(),
         })
     }
 
@@ -218,6 +222,7 @@ pub struct Meta {
     pub our_peer_id: PeerId,
     pub num_peers: NumPeers,
     pub db: Database,
+    pub event_logger: ServerModuleEventLogger,
 }
 
 impl Meta {
@@ -244,6 +249,7 @@ async fn get_consensus(dbtx: &mut DatabaseTransaction<'_>, key: MetaKey) -> Opti
     }
 
     async fn change_consensus(
+        &self,
         dbtx: &mut DatabaseTransaction<'_, NonCommittable>,
         key: MetaKey,
         value: MetaValue,
@@ -257,10 +263,24 @@ async fn change_consensus(
         let revision = revision.map(|r| r.wrapping_add(1)).unwrap_or_default();
         dbtx.insert_entry(
             &MetaConsensusKey(key),
-            &MetaConsensusValue { revision, value },
+            &MetaConsensusValue {
+                revision,
+                value: value.clone(),
+            },
         )
         .await;
 
+        self.event_logger
+            .log_event(
+                dbtx,
+                MetaValueChanged {
+                    key,
+                    revision,
+                    value,
+                },
+            )
+            .await;
+
         info!(target: LOG_MODULE_META, %key, rev = %revision, len = %value_len, "New consensus value");
 
         for peer_id in matching_submissions {
@@ -386,7 +406,8 @@ async fn process_consensus_item<'a, 'b>(
 
         // if threshold or more, change the consensus value
         if threshold <= matching_submissions.len() {
-            Self::change_consensus(dbtx, key, new_value.value, matching_submissions).await;
+            self.change_consensus(dbtx, key, new_value.value, matching_submissions)
+                .await;
         }
 
         Ok(())
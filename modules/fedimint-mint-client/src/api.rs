@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
+
 use fedimint_api_client::api::{FederationApiExt, FederationResult, IModuleFederationApi};
-use fedimint_core::module::ApiRequestErased;
+use fedimint_core::module::{ApiAuth, ApiRequestErased, ModuleConsensusVersion};
 use fedimint_core::task::{MaybeSend, MaybeSync};
-use fedimint_core::{apply, async_trait_maybe_send};
-use fedimint_mint_common::endpoint_constants::{BLIND_NONCE_USED_ENDPOINT, NOTE_SPENT_ENDPOINT};
-use fedimint_mint_common::{BlindNonce, Nonce};
+use fedimint_core::{Amount, OutPoint, apply, async_trait_maybe_send};
+use fedimint_mint_common::endpoint_constants::{
+    AWAIT_HTLC_PREIMAGE_ENDPOINT, BLIND_NONCE_USED_ENDPOINT, CONSENSUS_SESSION_COUNT_ENDPOINT,
+    HTLC_ENDPOINT, KEYSETS_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT, NOTE_SPENT_ENDPOINT,
+    NOTES_SPENT_ENDPOINT, ROTATE_KEYSET_ENDPOINT,
+};
+use fedimint_mint_common::{
+    BlindNonce, Keyset, KeysetId, MintOutputV1, Nonce, NoteSpendStatus, RotateKeysetRequest,
+};
 
 #[apply(async_trait_maybe_send!)]
 pub trait MintFederationApi {
@@ -12,6 +20,46 @@ pub trait MintFederationApi {
 
     /// Check if an e-cash note was already spent.
     async fn check_note_spent(&self, nonce: Nonce) -> FederationResult<bool>;
+
+    /// Check which of the given e-cash notes, identified by their keyset and
+    /// nonce, can still be redeemed, returning their spend status in the same
+    /// order as `notes`.
+    async fn check_notes_spent(
+        &self,
+        notes: Vec<(KeysetId, Nonce)>,
+    ) -> FederationResult<Vec<NoteSpendStatus>>;
+
+    /// The number of sessions the federation has agreed to have finished, which
+    /// determines whether a hash time lock has expired.
+    async fn consensus_session_count(&self) -> FederationResult<u64>;
+
+    /// The module consensus version the federation has activated, which
+    /// determines the inputs and outputs it accepts.
+    async fn module_consensus_version(&self) -> FederationResult<ModuleConsensusVersion>;
+
+    /// Fetch the hash time lock created at `out_point` if it was neither
+    /// claimed nor refunded yet.
+    async fn htlc(&self, out_point: OutPoint) -> FederationResult<Option<MintOutputV1>>;
+
+    /// Wait for the hash time lock at `out_point` to be claimed and return the
+    /// revealed preimage, or `None` once the lock has expired.
+    async fn await_htlc_preimage(
+        &self,
+        out_point: OutPoint,
+        timeout_session: u64,
+    ) -> Option<[u8; 32]>;
+
+    /// Fetch all keysets the federation has ever issued notes under.
+    async fn keysets(&self) -> FederationResult<BTreeMap<KeysetId, Keyset>>;
+
+    /// Vote for the generation of a new keyset, returns the id of the keyset
+    /// that will be generated once a threshold of guardians voted for it.
+    async fn rotate_keyset(
+        &self,
+        auth: ApiAuth,
+        deprecation_sessions: u64,
+        tiers: Option<Vec<Amount>>,
+    ) -> FederationResult<KeysetId>;
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -34,4 +82,70 @@ async fn check_note_spent(&self, nonce: Nonce) -> FederationResult<bool> {
         )
         .await
     }
+
+    async fn check_notes_spent(
+        &self,
+        notes: Vec<(KeysetId, Nonce)>,
+    ) -> FederationResult<Vec<NoteSpendStatus>> {
+        self.request_current_consensus(
+            NOTES_SPENT_ENDPOINT.to_string(),
+            ApiRequestErased::new(notes),
+        )
+        .await
+    }
+
+    async fn consensus_session_count(&self) -> FederationResult<u64> {
+        self.request_current_consensus(
+            CONSENSUS_SESSION_COUNT_ENDPOINT.to_string(),
+            ApiRequestErased::new(()),
+        )
+        .await
+    }
+
+    async fn module_consensus_version(&self) -> FederationResult<ModuleConsensusVersion> {
+        self.request_current_consensus(
+            MODULE_CONSENSUS_VERSION_ENDPOINT.to_string(),
+            ApiRequestErased::new(()),
+        )
+        .await
+    }
+
+    async fn htlc(&self, out_point: OutPoint) -> FederationResult<Option<MintOutputV1>> {
+        self.request_current_consensus(HTLC_ENDPOINT.to_string(), ApiRequestErased::new(out_point))
+            .await
+    }
+
+    async fn await_htlc_preimage(
+        &self,
+        out_point: OutPoint,
+        timeout_session: u64,
+    ) -> Option<[u8; 32]> {
+        self.request_current_consensus_retry(
+            AWAIT_HTLC_PREIMAGE_ENDPOINT.to_string(),
+            ApiRequestErased::new((out_point, timeout_session)),
+        )
+        .await
+    }
+
+    async fn keysets(&self) -> FederationResult<BTreeMap<KeysetId, Keyset>> {
+        self.request_current_consensus(KEYSETS_ENDPOINT.to_string(), ApiRequestErased::new(()))
+            .await
+    }
+
+    async fn rotate_keyset(
+        &self,
+        auth: ApiAuth,
+        deprecation_sessions: u64,
+        tiers: Option<Vec<Amount>>,
+    ) -> FederationResult<KeysetId> {
+        self.request_admin(
+            ROTATE_KEYSET_ENDPOINT,
+            ApiRequestErased::new(RotateKeysetRequest {
+                deprecation_sessions,
+                tiers,
+            }),
+            auth,
+        )
+        .await
+    }
 }
@@ -124,7 +124,7 @@ pub async fn prepare_plaintext_ecash_backup(
             .collect::<Vec<_>>();
 
         let mut idxes = vec![];
-        for &amount in self.cfg.tbs_pks.tiers() {
+        for amount in self.known_tiers() {
             idxes.push((amount, self.get_next_note_index(dbtx, amount).await));
         }
         let next_note_idx = Tiered::from_iter(idxes);
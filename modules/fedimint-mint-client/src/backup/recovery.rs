@@ -1,5 +1,5 @@
 use std::cmp::max;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use fedimint_client_module::module::init::ClientModuleRecoverArgs;
@@ -15,13 +15,14 @@
 };
 use fedimint_derive_secret::DerivableSecret;
 use fedimint_logging::{LOG_CLIENT_MODULE_MINT, LOG_CLIENT_RECOVERY, LOG_CLIENT_RECOVERY_MINT};
-use fedimint_mint_common::{MintInput, MintOutput, Nonce};
+use fedimint_mint_common::{KeysetId, MintInput, MintOutput, Nonce};
 use serde::{Deserialize, Serialize};
 use tbs::{AggregatePublicKey, BlindedMessage, PublicKeyShare};
 use threshold_crypto::G1Affine;
 use tracing::{debug, info, trace, warn};
 
 use super::EcashBackup;
+use crate::api::MintFederationApi as _;
 use crate::backup::EcashBackupV0;
 use crate::client_db::{
     NextECashNoteIndexKey, NoteKey, RecoveryFinalizedKey, RecoveryStateKey, ReusedNoteIndices,
@@ -30,11 +31,14 @@
 use crate::output::{
     MintOutputCommon, MintOutputStateMachine, MintOutputStatesCreated, NoteIssuanceRequest,
 };
-use crate::{MintClientInit, MintClientModule, MintClientStateMachines, NoteIndex, SpendableNote};
+use crate::{
+    MintClientInit, MintClientModule, MintClientStateMachines, NoteIndex, SpendableNote,
+    issuance_state,
+};
 
 #[derive(Clone, Debug)]
 pub struct MintRecovery {
-    state: MintRecoveryStateV2,
+    state: MintRecoveryStateV3,
     secret: DerivableSecret,
     client_ctx: ClientContext<MintClientModule>,
 }
@@ -66,15 +70,40 @@ async fn new(
             (EcashBackupV0::new_empty(), 0)
         };
 
+        let mut state = MintRecoveryStateV2::from_backup(
+            snapshot,
+            100,
+            config.tbs_pks.clone(),
+            config.peer_tbs_pks.clone(),
+            &secret,
+        );
+
+        // Notes may have been issued to us under later keysets with amount tiers
+        // the genesis keyset of our config does not have
+        match args.module_api().keysets().await {
+            Ok(keysets) => {
+                let tiers = keysets
+                    .values()
+                    .flat_map(|keyset| keyset.tbs_pks.tiers().copied())
+                    .collect::<BTreeSet<Amount>>();
+
+                state.add_amount_tiers(tiers, &secret);
+            }
+            Err(err) => {
+                warn!(
+                    target: LOG_CLIENT_RECOVERY_MINT,
+                    err = %err,
+                    "Failed to fetch keysets, only recovering notes of our config's amount tiers"
+                );
+            }
+        }
+
         Ok((
             MintRecovery {
-                state: MintRecoveryStateV2::from_backup(
-                    snapshot,
-                    100,
-                    config.tbs_pks.clone(),
-                    config.peer_tbs_pks.clone(),
-                    &secret,
-                ),
+                state: MintRecoveryStateV3 {
+                    state,
+                    pending_output_keysets: BTreeMap::new(),
+                },
                 secret,
                 client_ctx: args.context(),
             },
@@ -92,10 +121,16 @@ async fn load_dbtx(
         Ok(dbtx
             .get_value(&RecoveryStateKey)
             .await
-            .and_then(|(state, common)| {
-                if let MintRecoveryState::V2(state) = state {
-                    Some((state, common))
-                } else {
+            .and_then(|(state, common)| match state {
+                MintRecoveryState::V2(state) => Some((
+                    MintRecoveryStateV3 {
+                        state,
+                        pending_output_keysets: BTreeMap::new(),
+                    },
+                    common,
+                )),
+                MintRecoveryState::V3(state) => Some((state, common)),
+                MintRecoveryState::Default { .. } => {
                     warn!(target: LOG_CLIENT_RECOVERY, "Found unknown version recovery state. Ignoring");
                     None
                 }
@@ -121,7 +156,7 @@ async fn store_dbtx(
             .expect("Must be in prefixed database");
         dbtx.insert_entry(
             &RecoveryStateKey,
-            &(MintRecoveryState::V2(self.state.clone()), common.clone()),
+            &(MintRecoveryState::V3(self.state.clone()), common.clone()),
         )
         .await;
     }
@@ -145,7 +180,7 @@ async fn handle_input(
         input: &MintInput,
         _session_idx: u64,
     ) -> anyhow::Result<()> {
-        self.state.handle_input(input);
+        self.state.state.handle_input(input);
         Ok(())
     }
 
@@ -156,13 +191,24 @@ async fn handle_output(
         output: &MintOutput,
         _session_idx: u64,
     ) -> anyhow::Result<()> {
-        self.state.handle_output(out_point, output, &self.secret);
+        if self
+            .state
+            .state
+            .handle_output(out_point, output, &self.secret)
+        {
+            if let MintOutput::V2(output) = output {
+                self.state
+                    .pending_output_keysets
+                    .insert(out_point, output.keyset_id);
+            }
+        }
+
         Ok(())
     }
 
     /// Handle session outcome, adjusting the current state
     async fn finalize_dbtx(&self, dbtx: &mut DatabaseTransaction<'_>) -> anyhow::Result<()> {
-        let finalized = self.state.clone().finalize();
+        let finalized = self.state.state.clone().finalize();
 
         let restored_amount = finalized
             .unconfirmed_notes
@@ -219,6 +265,17 @@ async fn finalize_dbtx(&self, dbtx: &mut DatabaseTransaction<'_>) -> anyhow::Res
         );
 
         for (out_point, amount, issuance_request) in finalized.unconfirmed_notes {
+            let state = match self.state.pending_output_keysets.get(&out_point) {
+                Some(keyset_id) if *keyset_id != KeysetId::GENESIS => issuance_state(
+                    *keyset_id,
+                    BTreeMap::from([(out_point.out_idx, (amount, issuance_request))]),
+                ),
+                _ => crate::output::MintOutputStates::Created(MintOutputStatesCreated {
+                    amount,
+                    issuance_request,
+                }),
+            };
+
             self.client_ctx
                 .add_state_machines_dbtx(
                     dbtx,
@@ -233,12 +290,7 @@ async fn finalize_dbtx(&self, dbtx: &mut DatabaseTransaction<'_>) -> anyhow::Res
                                     )
                                     .expect("Can't overflow"),
                                 },
-                                state: crate::output::MintOutputStates::Created(
-                                    MintOutputStatesCreated {
-                                        amount,
-                                        issuance_request,
-                                    },
-                                ),
+                                state,
                             },
                         )])
                         .collect(),
@@ -293,11 +345,23 @@ fn from(value: CompressedBlindedMessage) -> Self {
 pub enum MintRecoveryState {
     #[encodable(index = 2)]
     V2(MintRecoveryStateV2),
+    #[encodable(index = 3)]
+    V3(MintRecoveryStateV3),
     // index 0 has incompatible db encoding, index 1 was skipped to match with V2
     #[encodable_default]
     Default { variant: u64, bytes: Vec<u8> },
 }
 
+/// Extends [`MintRecoveryStateV2`] by the keysets our pending outputs were
+/// issued under
+#[derive(Clone, Debug, Eq, PartialEq, Decodable, Encodable)]
+pub struct MintRecoveryStateV3 {
+    state: MintRecoveryStateV2,
+    /// Keysets of the pending outputs that were not issued under the genesis
+    /// keyset
+    pending_output_keysets: BTreeMap<OutPoint, KeysetId>,
+}
+
 /// The state machine used for fast-forwarding backup from point when it was
 /// taken to the present time by following epoch history items from the time the
 /// snapshot was taken.
@@ -406,6 +470,16 @@ pub fn from_backup(
         s
     }
 
+    /// Starts looking for notes of the amount `tiers` that are not tiers of
+    /// our config's keyset
+    pub fn add_amount_tiers(&mut self, tiers: BTreeSet<Amount>, secret: &DerivableSecret) {
+        for amount in tiers {
+            if self.tbs_pks.get(amount).is_none() {
+                self.fill_initial_pending_nonces(amount, secret);
+            }
+        }
+    }
+
     /// Fill each tier pool to the gap limit
     fn fill_initial_pending_nonces(&mut self, amount: Amount, secret: &DerivableSecret) {
         debug!(%amount, count=self.gap_limit, "Generating initial set of nonces for amount tier");
@@ -441,28 +515,38 @@ pub fn handle_input(&mut self, input: &MintInput) {
                 self.pending_outputs.remove(&input.note.nonce);
                 self.spendable_notes.remove(&input.note.nonce);
             }
+            MintInput::V1(input) => {
+                self.pending_outputs.remove(&input.note.nonce);
+                self.spendable_notes.remove(&input.note.nonce);
+            }
+            // Hash time locks don't spend any notes
+            MintInput::V2(_) => {}
             MintInput::Default { variant, .. } => {
                 trace!("Ignoring future mint input variant {variant}");
             }
         }
     }
 
+    /// Returns whether the output issues one of our notes
     pub fn handle_output(
         &mut self,
         out_point: OutPoint,
         output: &MintOutput,
         secret: &DerivableSecret,
-    ) {
-        let output = match output {
-            MintOutput::V0(output) => output,
+    ) -> bool {
+        let (output_amount, blind_nonce) = match output {
+            MintOutput::V0(output) => (output.amount, output.blind_nonce),
+            MintOutput::V2(output) => (output.amount, output.blind_nonce),
+            // Hash time locks don't issue any notes
+            MintOutput::V1(_) => return false,
             MintOutput::Default { variant, .. } => {
                 trace!("Ignoring future mint output variant {variant}");
-                return;
+                return false;
             }
         };
 
         if let Some((_issuance_request, note_idx, amount)) =
-            self.used_nonces.get(&output.blind_nonce.0.into())
+            self.used_nonces.get(&blind_nonce.0.into())
         {
             self.burned_total += *amount;
             self.reused_note_indices.push((*amount, *note_idx));
@@ -489,39 +573,43 @@ pub fn handle_output(
         // anything suspicious.
 
         if let Some((issuance_request, note_idx, pending_amount)) =
-            self.pending_nonces.remove(&output.blind_nonce.0.into())
+            self.pending_nonces.remove(&blind_nonce.0.into())
         {
             // the moment we see our blind nonce in the epoch history, correctly or
             // incorrectly used, we know that we must have used
             // already
             self.observe_nonce_idx_being_used(pending_amount, note_idx, secret);
 
-            if pending_amount == output.amount {
+            if pending_amount == output_amount {
                 self.used_nonces.insert(
-                    output.blind_nonce.0.into(),
+                    blind_nonce.0.into(),
                     (issuance_request, note_idx, pending_amount),
                 );
 
                 self.pending_outputs.insert(
                     issuance_request.nonce(),
-                    (out_point, output.amount, issuance_request),
-                );
-            } else {
-                // put it back, incorrect amount
-                self.pending_nonces.insert(
-                    output.blind_nonce.0.into(),
-                    (issuance_request, note_idx, pending_amount),
-                );
-                warn!(
-                    target: LOG_CLIENT_RECOVERY_MINT,
-                    output = ?out_point,
-                    blind_nonce = ?output.blind_nonce.0,
-                    expected_amount = %pending_amount,
-                    found_amount = %output.amount,
-                    "Transaction output contains blind nonce that looks like ours but is of the wrong amount. Ignoring."
+                    (out_point, output_amount, issuance_request),
                 );
+
+                return true;
             }
+
+            // put it back, incorrect amount
+            self.pending_nonces.insert(
+                blind_nonce.0.into(),
+                (issuance_request, note_idx, pending_amount),
+            );
+            warn!(
+                target: LOG_CLIENT_RECOVERY_MINT,
+                output = ?out_point,
+                blind_nonce = ?blind_nonce.0,
+                expected_amount = %pending_amount,
+                found_amount = %output_amount,
+                "Transaction output contains blind nonce that looks like ours but is of the wrong amount. Ignoring."
+            );
         }
+
+        false
     }
 
     /// React to a valid pending nonce being tracked being used in the epoch
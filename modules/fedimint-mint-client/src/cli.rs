@@ -1,16 +1,65 @@
+use std::time::Duration;
 use std::{ffi, iter};
 
 use anyhow::bail;
 use clap::Parser;
+use fedimint_core::Amount;
+use fedimint_core::secp256k1::PublicKey;
+use fedimint_mint_common::DEFAULT_KEYSET_DEPRECATION_SESSIONS;
 use futures::StreamExt;
 use serde::Serialize;
 
-use crate::{MintClientModule, OOBNotes, ReissueExternalNotesState};
+use crate::api::MintFederationApi as _;
+use crate::{MintClientModule, NoteDistributionPolicy, OOBNotes, ReissueExternalNotesState};
 
 #[derive(Parser, Serialize)]
 enum Opts {
     /// Reissue out of band notes
     Reissue { notes: OOBNotes },
+    /// Check which of the out of band notes were already spent or can not be
+    /// redeemed anymore since their keyset expired
+    CheckSpent { notes: OOBNotes },
+    /// Print the public key that others can lock notes to for us
+    LockKey,
+    /// Spend notes that can only be reissued by the given recipient
+    SpendLocked {
+        amount: Amount,
+        recipient: PublicKey,
+        /// Seconds after which the spend is canceled if the notes weren't
+        /// reissued by the recipient yet
+        #[clap(long, default_value_t = 60 * 60 * 24)]
+        timeout: u64,
+        #[clap(long)]
+        include_invite: bool,
+    },
+    /// List the keysets the federation has issued notes under
+    Keysets,
+    /// Vote for the generation of a new keyset (requires admin auth)
+    RotateKeyset {
+        /// Sessions the current keyset stays redeemable after the new one was
+        /// generated
+        #[clap(long, default_value_t = DEFAULT_KEYSET_DEPRECATION_SESSIONS)]
+        deprecation_sessions: u64,
+        /// Amount tiers of the new keyset, defaults to the ones of the current
+        /// keyset
+        #[clap(long, value_delimiter = ',')]
+        tiers: Option<Vec<Amount>>,
+    },
+    /// Print the distribution of notes the wallet aims for
+    NoteDistribution,
+    /// Change the distribution of notes the wallet aims for, unset options keep
+    /// their current value
+    SetNoteDistribution {
+        #[clap(long)]
+        notes_per_denomination: Option<u16>,
+        #[clap(long)]
+        max_notes_per_rebalancing: Option<u16>,
+        #[clap(long)]
+        min_missing_notes: Option<u16>,
+    },
+    /// Reissue surplus notes now if the wallet's note distribution is too far
+    /// from its target
+    Rebalance,
 }
 
 pub(crate) async fn handle_cli_command(
@@ -39,5 +88,81 @@ pub(crate) async fn handle_cli_command(
 
             Ok(serde_json::to_value(amount).expect("JSON serialization failed"))
         }
+        Opts::CheckSpent { notes } => {
+            let spent = mint.check_notes_spent(&notes).await?;
+
+            Ok(serde_json::json!({
+                "spent_amount": spent.iter().map(|(amount, _, _)| *amount).sum::<Amount>(),
+                "spent_notes": spent,
+            }))
+        }
+        Opts::LockKey => Ok(serde_json::to_value(mint.note_lock_keypair().public_key())
+            .expect("JSON serialization failed")),
+        Opts::SpendLocked {
+            amount,
+            recipient,
+            timeout,
+            include_invite,
+        } => {
+            let (operation_id, notes) = mint
+                .spend_notes_locked(
+                    amount,
+                    recipient,
+                    Duration::from_secs(timeout),
+                    include_invite,
+                    (),
+                )
+                .await?;
+
+            Ok(serde_json::json!({
+                "operation_id": operation_id,
+                "notes": notes,
+            }))
+        }
+        Opts::Keysets => Ok(serde_json::to_value(mint.module_api.keysets().await?)
+            .expect("JSON serialization failed")),
+        Opts::RotateKeyset {
+            deprecation_sessions,
+            tiers,
+        } => Ok(
+            serde_json::to_value(mint.rotate_keyset(deprecation_sessions, tiers).await?)
+                .expect("JSON serialization failed"),
+        ),
+        Opts::NoteDistribution => {
+            let policy = mint
+                .note_distribution_policy(
+                    &mut mint.client_ctx.module_db().begin_transaction_nc().await,
+                )
+                .await;
+
+            Ok(serde_json::to_value(policy).expect("JSON serialization failed"))
+        }
+        Opts::SetNoteDistribution {
+            notes_per_denomination,
+            max_notes_per_rebalancing,
+            min_missing_notes,
+        } => {
+            let current = mint
+                .note_distribution_policy(
+                    &mut mint.client_ctx.module_db().begin_transaction_nc().await,
+                )
+                .await;
+
+            let policy = NoteDistributionPolicy {
+                notes_per_denomination: notes_per_denomination
+                    .unwrap_or(current.notes_per_denomination),
+                max_notes_per_rebalancing: max_notes_per_rebalancing
+                    .unwrap_or(current.max_notes_per_rebalancing),
+                min_missing_notes: min_missing_notes.unwrap_or(current.min_missing_notes),
+            };
+
+            mint.set_note_distribution_policy(policy.clone()).await?;
+
+            Ok(serde_json::to_value(policy).expect("JSON serialization failed"))
+        }
+        Opts::Rebalance => {
+            Ok(serde_json::to_value(mint.rebalance_notes().await?)
+                .expect("JSON serialization failed"))
+        }
     }
 }
@@ -8,7 +8,7 @@
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::{Amount, impl_db_lookup, impl_db_record};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
-use fedimint_mint_common::Nonce;
+use fedimint_mint_common::{Keyset, KeysetId, Nonce};
 use serde::Serialize;
 use strum_macros::EnumIter;
 use tracing::debug;
@@ -17,7 +17,7 @@
 use crate::input::{MintInputCommon, MintInputStateMachine, MintInputStateMachineV0};
 use crate::oob::{MintOOBStateMachine, MintOOBStateMachineV0, MintOOBStates, MintOOBStatesV0};
 use crate::output::{MintOutputCommon, MintOutputStateMachine, MintOutputStateMachineV0};
-use crate::{MintClientStateMachines, NoteIndex, SpendableNoteUndecoded};
+use crate::{MintClientStateMachines, NoteDistributionPolicy, NoteIndex, SpendableNoteUndecoded};
 
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
@@ -28,6 +28,10 @@ pub enum DbKeyPrefix {
     RecoveryState = 0x2c,
     RecoveryFinalized = 0x2d,
     ReusedNoteIndices = 0x2e,
+    Keyset = 0x2f,
+    NoteDistributionPolicy = 0x30,
+    LockedNoteIssuance = 0x31,
+    LockedNote = 0x32,
     /// Prefixes between 0xb0..=0xcf shall all be considered allocated for
     /// historical and future external use
     ExternalReservedStart = 0xb0,
@@ -108,6 +112,65 @@ pub struct NoteKey {
     db_prefix = DbKeyPrefix::ReusedNoteIndices,
 );
 
+/// The keysets of the federation other than the genesis keyset of our config
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct KeysetKey(pub KeysetId);
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct KeysetKeyPrefix;
+
+impl_db_record!(
+    key = KeysetKey,
+    value = Keyset,
+    db_prefix = DbKeyPrefix::Keyset,
+);
+impl_db_lookup!(key = KeysetKey, query_prefix = KeysetKeyPrefix);
+
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct NoteDistributionPolicyKey;
+
+impl_db_record!(
+    key = NoteDistributionPolicyKey,
+    value = NoteDistributionPolicy,
+    db_prefix = DbKeyPrefix::NoteDistributionPolicy,
+);
+
+/// Marks a note we are issuing as locked to a recipient, such that it is stored
+/// under a [`LockedNoteKey`] instead of becoming spendable once issued
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct LockedNoteIssuanceKey(pub Nonce);
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct LockedNoteIssuanceKeyPrefix;
+
+impl_db_record!(
+    key = LockedNoteIssuanceKey,
+    value = (),
+    db_prefix = DbKeyPrefix::LockedNoteIssuance,
+);
+impl_db_lookup!(
+    key = LockedNoteIssuanceKey,
+    query_prefix = LockedNoteIssuanceKeyPrefix
+);
+
+/// An issued note that is locked to a recipient but has not been spent
+/// out-of-band to them yet
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct LockedNoteKey {
+    pub amount: Amount,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct LockedNoteKeyPrefix;
+
+impl_db_record!(
+    key = LockedNoteKey,
+    value = SpendableNoteUndecoded,
+    db_prefix = DbKeyPrefix::LockedNote,
+);
+impl_db_lookup!(key = LockedNoteKey, query_prefix = LockedNoteKeyPrefix);
+
 #[derive(Debug, Clone, Encodable, Decodable, Serialize)]
 pub struct CancelledOOBSpendKey(pub OperationId);
 
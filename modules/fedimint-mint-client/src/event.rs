@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use fedimint_core::Amount;
-use fedimint_core::core::ModuleKind;
+use fedimint_core::core::{ModuleKind, OperationId};
 use fedimint_eventlog::{Event, EventKind};
 use fedimint_mint_common::{KIND, Nonce};
 use serde::{Deserialize, Serialize};
@@ -67,3 +67,23 @@ impl Event for OOBNotesReissued {
 
     const KIND: EventKind = EventKind::from_static("oob-notes-reissued");
 }
+
+/// Event that is emitted when the background task reissues notes to bring the
+/// wallet's note distribution closer to its target
+#[derive(Serialize, Deserialize)]
+pub struct NotesRebalanced {
+    /// The operation that reissues the notes
+    pub operation_id: OperationId,
+
+    /// The total amount of the reissued notes
+    pub amount: Amount,
+
+    /// The number of reissued notes
+    pub note_count: usize,
+}
+
+impl Event for NotesRebalanced {
+    const MODULE: Option<ModuleKind> = Some(KIND);
+
+    const KIND: EventKind = EventKind::from_static("notes-rebalanced");
+}
@@ -31,7 +31,7 @@
 use std::fmt::{Display, Formatter};
 use std::io::Read;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{Context as _, anyhow, bail, ensure};
@@ -43,7 +43,8 @@
     DbKeyPrefix, NoteKeyPrefix, RecoveryFinalizedKey, ReusedNoteIndices, migrate_state_to_v2,
     migrate_to_v1,
 };
-use event::{NoteSpent, OOBNotesReissued, OOBNotesSpent};
+use event::{NoteSpent, NotesRebalanced, OOBNotesReissued, OOBNotesSpent};
+use fedimint_api_client::api::DynModuleApi;
 use fedimint_client_module::db::{ClientModuleMigrationFn, migrate_state};
 use fedimint_client_module::module::init::{
     ClientModuleInit, ClientModuleInitArgs, ClientModuleRecoverArgs,
@@ -67,13 +68,14 @@
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::registry::{ModuleDecoderRegistry, ModuleRegistry};
 use fedimint_core::module::{
-    ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
+    ApiAuth, ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
 };
-use fedimint_core::secp256k1::{All, Keypair, Secp256k1};
+use fedimint_core::secp256k1::{All, Keypair, PublicKey, Secp256k1};
+use fedimint_core::task::TaskGroup;
 use fedimint_core::util::{BoxFuture, BoxStream, NextOrPending, SafeUrl};
 use fedimint_core::{
     Amount, OutPoint, PeerId, Tiered, TieredCounts, TieredMulti, TransactionId, apply,
-    async_trait_maybe_send, push_db_pair_items,
+    async_trait_maybe_send, push_db_key_items, push_db_pair_items, runtime,
 };
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
@@ -85,17 +87,20 @@
 use input::MintInputStateCreatedBundle;
 use itertools::Itertools as _;
 use oob::MintOOBStatesCreatedMulti;
-use output::MintOutputStatesCreatedMulti;
+use output::{MintOutputStatesCreatedMulti, MintOutputStatesCreatedMultiKeyset};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use tbs::AggregatePublicKey;
 use thiserror::Error;
+use tokio::sync::Notify;
 use tracing::{debug, warn};
 
+use crate::api::MintFederationApi;
 use crate::backup::EcashBackup;
 use crate::client_db::{
-    CancelledOOBSpendKey, CancelledOOBSpendKeyPrefix, NextECashNoteIndexKey,
-    NextECashNoteIndexKeyPrefix, NoteKey,
+    CancelledOOBSpendKey, CancelledOOBSpendKeyPrefix, KeysetKey, KeysetKeyPrefix,
+    LockedNoteIssuanceKey, LockedNoteIssuanceKeyPrefix, LockedNoteKey, LockedNoteKeyPrefix,
+    NextECashNoteIndexKey, NextECashNoteIndexKeyPrefix, NoteDistributionPolicyKey, NoteKey,
 };
 use crate::input::{MintInputCommon, MintInputStateMachine, MintInputStates};
 use crate::oob::{MintOOBStateMachine, MintOOBStates};
@@ -104,6 +109,9 @@
 };
 
 const MINT_E_CASH_TYPE_CHILD_ID: ChildId = ChildId(0);
+const KEYSET_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const NOTE_REBALANCING_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const MINT_NOTE_LOCK_CHILD_ID: ChildId = ChildId(1);
 
 /// An encapsulation of [`FederationId`] and e-cash notes in the form of
 /// [`TieredMulti<SpendableNote>`] for the purpose of spending e-cash
@@ -130,6 +138,10 @@ enum OOBNotesPart {
         federation_id: FederationId,
     },
     ApiSecret(String),
+    /// E-cash notes that can only be reissued by the recipient they are locked
+    /// to. Sent alongside an empty [`OOBNotesPart::Notes`] part such that
+    /// clients which don't understand locked notes reject them as empty.
+    LockedNotes(TieredMulti<LockedNote>),
     #[encodable_default]
     Default {
         variant: u64,
@@ -165,6 +177,26 @@ pub fn new_with_invite(notes: TieredMulti<SpendableNote>, invite: &InviteCode) -
         Self(data)
     }
 
+    /// Out-of-band notes that can only be reissued by the recipient the
+    /// `locked_notes` are locked to
+    pub fn new_locked(
+        federation_id_prefix: FederationIdPrefix,
+        locked_notes: TieredMulti<LockedNote>,
+    ) -> Self {
+        let mut oob_notes = Self::new(federation_id_prefix, TieredMulti::default());
+        oob_notes.0.push(OOBNotesPart::LockedNotes(locked_notes));
+        oob_notes
+    }
+
+    pub fn new_locked_with_invite(
+        locked_notes: TieredMulti<LockedNote>,
+        invite: &InviteCode,
+    ) -> Self {
+        let mut oob_notes = Self::new_with_invite(TieredMulti::default(), invite);
+        oob_notes.0.push(OOBNotesPart::LockedNotes(locked_notes));
+        oob_notes
+    }
+
     pub fn federation_id_prefix(&self) -> FederationIdPrefix {
         self.0
             .iter()
@@ -186,6 +218,18 @@ pub fn notes(&self) -> &TieredMulti<SpendableNote> {
             .expect("Invariant violated: OOBNotes does not contain any notes")
     }
 
+    /// Returns the notes that are locked to a recipient, which are empty for
+    /// regular bearer notes
+    pub fn locked_notes(&self) -> TieredMulti<LockedNote> {
+        self.0
+            .iter()
+            .find_map(|data| match data {
+                OOBNotesPart::LockedNotes(locked_notes) => Some(locked_notes.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
     pub fn notes_json(&self) -> Result<serde_json::Value, serde_json::Error> {
         let mut notes_map = serde_json::Map::new();
         for notes in &self.0 {
@@ -219,6 +263,10 @@ pub fn notes_json(&self) -> Result<serde_json::Value, serde_json::Error> {
                     );
                 }
                 OOBNotesPart::ApiSecret(_) => { /* already covered inside `Invite` */ }
+                OOBNotesPart::LockedNotes(locked_notes) => {
+                    let locked_notes_json = serde_json::to_value(locked_notes)?;
+                    notes_map.insert("locked_notes".to_string(), locked_notes_json);
+                }
                 OOBNotesPart::Default { variant, bytes } => {
                     notes_map.insert(
                         format!("default_{variant}"),
@@ -339,7 +387,10 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
         let oob_notes: OOBNotes =
             Decodable::consensus_decode_whole(&bytes, &ModuleDecoderRegistry::default())?;
 
-        ensure!(!oob_notes.notes().is_empty(), "OOBNotes cannot be empty");
+        ensure!(
+            !oob_notes.notes().is_empty() || !oob_notes.locked_notes().is_empty(),
+            "OOBNotes cannot be empty"
+        );
 
         Ok(oob_notes)
     }
@@ -384,7 +435,7 @@ fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 impl OOBNotes {
     /// Returns the total value of all notes in msat as `Amount`
     pub fn total_amount(&self) -> Amount {
-        self.notes().total_amount()
+        self.notes().total_amount() + self.locked_notes().total_amount()
     }
 }
 
@@ -457,6 +508,40 @@ pub enum MintOperationMetaVariant {
         requested_amount: Amount,
         oob_notes: OOBNotes,
     },
+    HtlcOffer {
+        offer: HtlcOffer,
+    },
+    HtlcClaim {
+        out_point: OutPoint,
+    },
+    HtlcRefund {
+        out_point: OutPoint,
+    },
+}
+
+/// The distribution of notes the wallet aims for, maintained by a background
+/// task that reissues surplus notes while the wallet is idle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encodable, Decodable)]
+pub struct NoteDistributionPolicy {
+    /// The number of notes to hold of every denomination, as far as the balance
+    /// allows
+    pub notes_per_denomination: u16,
+    /// The maximum number of notes reissued by a single rebalancing
+    pub max_notes_per_rebalancing: u16,
+    /// The number of notes that have to be missing from the target
+    /// distribution before a rebalancing is attempted, which avoids paying
+    /// fees for negligible improvements
+    pub min_missing_notes: u16,
+}
+
+impl Default for NoteDistributionPolicy {
+    fn default() -> Self {
+        Self {
+            notes_per_denomination: 2,
+            max_notes_per_rebalancing: 10,
+            min_missing_notes: 3,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -513,6 +598,41 @@ async fn dump_database(
                         mint_client_items.insert("RecoveryFinalized".to_string(), Box::new(val));
                     }
                 }
+                DbKeyPrefix::NoteDistributionPolicy => {
+                    if let Some(val) = dbtx.get_value(&NoteDistributionPolicyKey).await {
+                        mint_client_items
+                            .insert("NoteDistributionPolicy".to_string(), Box::new(val));
+                    }
+                }
+                DbKeyPrefix::Keyset => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeysetKeyPrefix,
+                        KeysetKey,
+                        Keyset,
+                        mint_client_items,
+                        "Keysets"
+                    );
+                }
+                DbKeyPrefix::LockedNoteIssuance => {
+                    push_db_key_items!(
+                        dbtx,
+                        LockedNoteIssuanceKeyPrefix,
+                        LockedNoteIssuanceKey,
+                        mint_client_items,
+                        "Locked Note Issuances"
+                    );
+                }
+                DbKeyPrefix::LockedNote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        LockedNoteKeyPrefix,
+                        LockedNoteKey,
+                        SpendableNoteUndecoded,
+                        mint_client_items,
+                        "Locked Notes"
+                    );
+                }
                 DbKeyPrefix::RecoveryState
                 | DbKeyPrefix::ReusedNoteIndices
                 | DbKeyPrefix::ExternalReservedStart
@@ -535,6 +655,16 @@ fn supported_api_versions(&self) -> MultiApiVersion {
     }
 
     async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::Module> {
+        let keysets = args
+            .db()
+            .begin_transaction_nc()
+            .await
+            .find_by_prefix(&KeysetKeyPrefix)
+            .await
+            .map(|(key, keyset)| (key.0, keyset))
+            .collect()
+            .await;
+
         Ok(MintClientModule {
             federation_id: *args.federation_id(),
             cfg: args.cfg().clone(),
@@ -542,6 +672,11 @@ async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::
             secp: Secp256k1::new(),
             notifier: args.notifier().clone(),
             client_ctx: args.context(),
+            module_api: args.module_api().clone(),
+            admin_auth: args.admin_auth().cloned(),
+            keysets: Arc::new(Mutex::new(keysets)),
+            keyset_refresh: Arc::new(Notify::new()),
+            task_group: args.task_group().clone(),
         })
     }
 
@@ -612,6 +747,14 @@ pub struct MintClientModule {
     secp: Secp256k1<All>,
     notifier: ModuleNotifier<MintClientStateMachines>,
     pub client_ctx: ClientContext<Self>,
+    module_api: DynModuleApi,
+    admin_auth: Option<ApiAuth>,
+    /// Cache of the keysets in our database, such that we can verify notes
+    /// without accessing it
+    keysets: Arc<Mutex<BTreeMap<KeysetId, Keyset>>>,
+    /// Wakes up the keyset maintenance task before its next scheduled refresh
+    keyset_refresh: Arc<Notify>,
+    task_group: TaskGroup,
 }
 
 // TODO: wrap in Arc
@@ -622,6 +765,8 @@ pub struct MintClientContext {
     pub tbs_pks: Tiered<AggregatePublicKey>,
     pub peer_tbs_pks: BTreeMap<PeerId, Tiered<tbs::PublicKeyShare>>,
     pub secret: DerivableSecret,
+    /// Triggers a refresh of our keysets, e.g. once a transaction was rejected
+    pub keyset_refresh: Arc<Notify>,
     // FIXME: putting a DB ref here is an antipattern, global context should become more powerful
     // but we need to consider it more carefully as its APIs will be harder to change.
     pub module_db: Database,
@@ -649,6 +794,48 @@ impl ClientModule for MintClientModule {
     type ModuleStateMachineContext = MintClientContext;
     type States = MintClientStateMachines;
 
+    async fn start(&self) {
+        self.task_group
+            .spawn_cancellable("keyset maintenance", {
+                let client_ctx = self.client_ctx.clone();
+                let keyset_refresh = self.keyset_refresh.clone();
+
+                async move {
+                    loop {
+                        let module = client_ctx.self_ref();
+
+                        if let Err(err) = module.refresh_keysets().await {
+                            debug!(target: LOG_CLIENT_MODULE_MINT, err = %err, "Failed to refresh keysets");
+                        }
+
+                        if let Err(err) = module.reissue_deprecated_notes().await {
+                            warn!(target: LOG_CLIENT_MODULE_MINT, err = %err, "Failed to reissue notes of superseded keysets");
+                        }
+
+                        // Refresh early if an output was rejected, since we might
+                        // have issued under a keyset that is no longer active
+                        let _ = runtime::timeout(KEYSET_REFRESH_INTERVAL, keyset_refresh.notified())
+                            .await;
+                    }
+                }
+            });
+
+        self.task_group
+            .spawn_cancellable("note distribution maintenance", {
+                let client_ctx = self.client_ctx.clone();
+
+                async move {
+                    loop {
+                        runtime::sleep(NOTE_REBALANCING_INTERVAL).await;
+
+                        if let Err(err) = client_ctx.self_ref().rebalance_notes().await {
+                            warn!(target: LOG_CLIENT_MODULE_MINT, err = %err, "Failed to rebalance notes");
+                        }
+                    }
+                }
+            });
+    }
+
     fn context(&self) -> Self::ModuleStateMachineContext {
         MintClientContext {
             client_ctx: self.client_ctx.clone(),
@@ -656,6 +843,7 @@ fn context(&self) -> Self::ModuleStateMachineContext {
             tbs_pks: self.cfg.tbs_pks.clone(),
             peer_tbs_pks: self.cfg.peer_tbs_pks.clone(),
             secret: self.secret.clone(),
+            keyset_refresh: self.keyset_refresh.clone(),
             module_db: self.client_ctx.module_db().clone(),
         }
     }
@@ -743,11 +931,16 @@ async fn create_final_inputs_and_outputs(
             .map(|input| self.cfg.fee_consensus.fee(input.0.amount))
             .sum();
 
+        let notes_per_denomination = self
+            .note_distribution_policy(dbtx)
+            .await
+            .notes_per_denomination;
+
         let outputs = self
             .create_output(
                 dbtx,
                 operation_id,
-                2,
+                notes_per_denomination,
                 input_amount.saturating_sub(output_amount),
             )
             .await;
@@ -800,6 +993,7 @@ async fn subscribe_balance_changes(&self) -> BoxStream<'static, ()> {
                             state:
                                 MintOutputStates::Created(_)
                                 | MintOutputStates::CreatedMulti(_)
+                                | MintOutputStates::CreatedMultiKeyset(_)
                                 | MintOutputStates::Failed(_)
                                 | MintOutputStates::Aborted(_),
                             ..
@@ -970,6 +1164,8 @@ pub enum ReissueExternalNotesError {
     WrongFederationId,
     #[error("We already reissued these notes")]
     AlreadyReissued,
+    #[error("The notes are locked to another recipient")]
+    LockedToAnotherRecipient,
 }
 
 impl MintClientModule {
@@ -1051,14 +1247,15 @@ pub async fn create_output(
             return ClientOutputBundle::new(vec![], vec![]);
         }
 
+        let (keyset_id, tbs_pks) = self.active_keyset();
+
         let denominations = represent_amount(
             exact_amount,
             &self.get_note_counts_by_denomination(dbtx).await,
-            &self.cfg.tbs_pks,
+            &tbs_pks,
             notes_per_denomination,
             &self.cfg.fee_consensus,
         );
-
         let mut outputs = Vec::new();
         let mut issuance_requests = Vec::new();
 
@@ -1068,11 +1265,12 @@ pub async fn create_output(
 
                 debug!(
                     %amount,
+                    %keyset_id,
                     "Generated issuance request"
                 );
 
                 outputs.push(ClientOutput {
-                    output: MintOutput::new_v0(amount, blind_nonce),
+                    output: issuance_output(amount, blind_nonce, keyset_id),
                     amount,
                 });
 
@@ -1087,13 +1285,14 @@ pub async fn create_output(
                     operation_id,
                     out_point_range,
                 },
-                state: MintOutputStates::CreatedMulti(MintOutputStatesCreatedMulti {
-                    issuance_requests: out_point_range
+                state: issuance_state(
+                    keyset_id,
+                    out_point_range
                         .into_iter()
                         .map(|out_point| out_point.out_idx)
                         .zip(issuance_requests.clone())
                         .collect(),
-                }),
+                ),
             })]
         });
 
@@ -1165,7 +1364,9 @@ pub async fn await_output_finalized(
                         "Failed to finalize transaction: {}",
                         failed.error
                     ))),
-                    MintOutputStates::Created(_) | MintOutputStates::CreatedMulti(_) => None,
+                    MintOutputStates::Created(_)
+                    | MintOutputStates::CreatedMulti(_)
+                    | MintOutputStates::CreatedMultiKeyset(_) => None,
                 }
             });
         pin_mut!(stream);
@@ -1197,11 +1398,20 @@ pub async fn consolidate_notes(
             assert!(MIN_NOTES_PER_TIER <= MAX_NOTES_PER_TIER_TRIGGER);
         }
 
+        // Don't consolidate notes the distribution policy asks us to hold
+        let notes_per_denomination = usize::from(
+            self.note_distribution_policy(dbtx)
+                .await
+                .notes_per_denomination,
+        );
+        let max_notes_per_tier_trigger = MAX_NOTES_PER_TIER_TRIGGER.max(2 * notes_per_denomination);
+        let min_notes_per_tier = MIN_NOTES_PER_TIER.max(notes_per_denomination);
+
         let counts = self.get_note_counts_by_denomination(dbtx).await;
 
         let should_consolidate = counts
             .iter()
-            .any(|(_, count)| MAX_NOTES_PER_TIER_TRIGGER < count);
+            .any(|(_, count)| max_notes_per_tier_trigger < count);
 
         if !should_consolidate {
             return Ok(vec![]);
@@ -1212,7 +1422,7 @@ pub async fn consolidate_notes(
         let excessive_counts: TieredCounts = counts
             .iter()
             .map(|(amount, count)| {
-                let take = (count.saturating_sub(MIN_NOTES_PER_TIER)).min(max_count);
+                let take = (count.saturating_sub(min_notes_per_tier)).min(max_count);
 
                 max_count -= take;
                 (amount, take)
@@ -1253,17 +1463,9 @@ pub fn create_input_from_notes(
         let mut inputs_and_notes = Vec::new();
 
         for (amount, spendable_note) in notes.into_iter_items() {
-            let key = self
-                .cfg
-                .tbs_pks
-                .get(amount)
-                .ok_or(anyhow!("Invalid amount tier: {amount}"))?;
-
             let note = spendable_note.note();
 
-            if !note.verify(*key) {
-                bail!("Invalid note");
-            }
+            self.verify_note(amount, &note)?;
 
             inputs_and_notes.push((
                 ClientInput {
@@ -1278,6 +1480,358 @@ pub fn create_input_from_notes(
         Ok(inputs_and_notes)
     }
 
+    /// Create a mint input from external notes that are locked to our
+    /// [`MintClientModule::note_lock_keypair`]
+    pub fn create_input_from_locked_notes(
+        &self,
+        locked_notes: TieredMulti<LockedNote>,
+    ) -> anyhow::Result<Vec<ClientInput<MintInput>>> {
+        let lock_keypair = self.note_lock_keypair();
+        let mut inputs = Vec::new();
+
+        for (amount, locked_note) in locked_notes.into_iter_items() {
+            self.verify_note(amount, &locked_note.note)?;
+
+            if locked_note.note.nonce != locked_note.lock.nonce() {
+                bail!("Note is not locked by the supplied lock");
+            }
+
+            let Some(recipient_keypair) = locked_note.lock.recipient_keypair(&lock_keypair) else {
+                bail!(ReissueExternalNotesError::LockedToAnotherRecipient);
+            };
+
+            inputs.push(ClientInput {
+                input: MintInput::new_v1(amount, locked_note.note, locked_note.lock),
+                keys: vec![recipient_keypair],
+                amount,
+            });
+        }
+
+        Ok(inputs)
+    }
+
+    /// Checks that the note was signed under the genesis keyset of our config
+    /// or any later keyset of the federation we know of
+    fn verify_note(&self, amount: Amount, note: &Note) -> anyhow::Result<()> {
+        let keysets = self.keysets.lock().expect("lock poisoned");
+
+        let keys = std::iter::once(&self.cfg.tbs_pks)
+            .chain(keysets.values().map(|keyset| &keyset.tbs_pks))
+            .filter_map(|tbs_pks| tbs_pks.get(amount))
+            .collect::<Vec<_>>();
+
+        ensure!(!keys.is_empty(), "Invalid amount tier: {amount}");
+
+        if keys.into_iter().any(|key| note.verify(*key)) {
+            return Ok(());
+        }
+
+        bail!("Invalid note")
+    }
+
+    /// The keyset `note` was signed under, if it was signed by the federation
+    /// under any keyset we know of
+    fn note_keyset_id(&self, amount: Amount, note: &Note) -> Option<KeysetId> {
+        if self
+            .cfg
+            .tbs_pks
+            .get(amount)
+            .is_some_and(|key| note.verify(*key))
+        {
+            return Some(KeysetId::GENESIS);
+        }
+
+        self.keysets
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .find(|(_, keyset)| {
+                keyset
+                    .tbs_pks
+                    .get(amount)
+                    .is_some_and(|key| note.verify(*key))
+            })
+            .map(|(keyset_id, _)| *keyset_id)
+    }
+
+    /// The id and public keys of the keyset the federation issues notes under
+    /// as far as we know, our outputs are rejected if we have not learned about
+    /// a newer keyset yet or use amount tiers it does not have
+    fn active_keyset(&self) -> (KeysetId, Tiered<AggregatePublicKey>) {
+        self.keysets
+            .lock()
+            .expect("lock poisoned")
+            .last_key_value()
+            .map_or_else(
+                || (KeysetId::GENESIS, self.cfg.tbs_pks.clone()),
+                |(keyset_id, keyset)| (*keyset_id, keyset.tbs_pks.clone()),
+            )
+    }
+
+    /// The amount tiers of all keysets we know of, notes of any of them may
+    /// have been issued to us
+    fn known_tiers(&self) -> BTreeSet<Amount> {
+        self.cfg
+            .tbs_pks
+            .tiers()
+            .chain(
+                self.keysets
+                    .lock()
+                    .expect("lock poisoned")
+                    .values()
+                    .flat_map(|keyset| keyset.tbs_pks.tiers()),
+            )
+            .copied()
+            .collect()
+    }
+
+    /// Fetches the keysets of the federation and stores the ones we have not
+    /// seen yet
+    pub async fn refresh_keysets(&self) -> anyhow::Result<()> {
+        let keysets = self.module_api.keysets().await?;
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        for (keyset_id, keyset) in &keysets {
+            if *keyset_id != KeysetId::GENESIS {
+                dbtx.insert_entry(&KeysetKey(*keyset_id), keyset).await;
+            }
+        }
+
+        dbtx.commit_tx().await;
+
+        self.keysets.lock().expect("lock poisoned").extend(
+            keysets
+                .into_iter()
+                .filter(|(keyset_id, _)| *keyset_id != KeysetId::GENESIS),
+        );
+
+        Ok(())
+    }
+
+    /// The public keys of all keysets that have been superseded by a newer
+    /// keyset we know of
+    fn deprecated_tbs_pks(&self) -> Vec<Tiered<AggregatePublicKey>> {
+        let keysets = self.keysets.lock().expect("lock poisoned");
+
+        let Some(active_keyset_id) = keysets.keys().next_back().copied() else {
+            return vec![];
+        };
+
+        std::iter::once(self.cfg.tbs_pks.clone())
+            .chain(
+                keysets
+                    .iter()
+                    .filter(|(keyset_id, _)| **keyset_id != active_keyset_id)
+                    .map(|(_, keyset)| keyset.tbs_pks.clone()),
+            )
+            .collect()
+    }
+
+    /// Reissues all notes signed under a superseded keyset such that they
+    /// don't become worthless once the keyset expires.
+    pub async fn reissue_deprecated_notes(&self) -> anyhow::Result<()> {
+        let selector = SelectDeprecatedNotes {
+            tbs_pks: self.deprecated_tbs_pks(),
+        };
+
+        if selector.tbs_pks.is_empty() {
+            return Ok(());
+        }
+
+        let amount = Self::get_all_spendable_notes(
+            &mut self.client_ctx.module_db().begin_transaction_nc().await,
+        )
+        .await
+        .into_iter_items()
+        .filter(|(amount, note)| selector.is_deprecated(*amount, note))
+        .map(|(amount, _)| amount)
+        .sum::<Amount>();
+
+        if amount == Amount::ZERO {
+            return Ok(());
+        }
+
+        debug!(target: LOG_CLIENT_MODULE_MINT, %amount, "Reissuing notes of superseded keysets");
+
+        self.reissue_own_notes(&selector, amount).await?;
+
+        Ok(())
+    }
+
+    /// Spends the notes of ours selected by `notes_selector` in a transaction
+    /// that issues change notes of the same value to us. The notes are
+    /// removed from our wallet in the database transaction that submits the
+    /// reissue transaction, and are refunded if it is rejected.
+    async fn reissue_own_notes(
+        &self,
+        notes_selector: &impl NotesSelector,
+        amount: Amount,
+    ) -> anyhow::Result<OperationId> {
+        let operation_id = OperationId::new_random();
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        let selected_notes = Self::select_notes(
+            &mut dbtx.to_ref_nc(),
+            notes_selector,
+            amount,
+            FeeConsensus::zero(),
+        )
+        .await?;
+
+        for (amount, note) in selected_notes.iter_items() {
+            debug!(target: LOG_CLIENT_MODULE_MINT, %amount, %note, "Reissuing own note");
+            Self::delete_spendable_note(&self.client_ctx, &mut dbtx.to_ref_nc(), amount, note)
+                .await;
+        }
+
+        let inputs = self.create_input_from_notes(selected_notes)?;
+
+        self.client_ctx
+            .claim_inputs(
+                &mut dbtx.to_ref_nc(),
+                create_bundle_for_inputs(inputs, operation_id),
+                operation_id,
+            )
+            .await?;
+
+        dbtx.commit_tx_result().await?;
+
+        Ok(operation_id)
+    }
+
+    /// The distribution of notes the wallet aims for
+    pub async fn note_distribution_policy(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> NoteDistributionPolicy {
+        dbtx.get_value(&NoteDistributionPolicyKey)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Changes the distribution of notes the wallet aims for, which is used for
+    /// all e-cash issued to us from now on and enforced by reissuing notes in
+    /// the background
+    pub async fn set_note_distribution_policy(
+        &self,
+        policy: NoteDistributionPolicy,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            policy.notes_per_denomination > 0,
+            "The policy has to target at least one note per denomination"
+        );
+        ensure!(
+            policy.max_notes_per_rebalancing > 0,
+            "The policy has to allow reissuing at least one note per rebalancing"
+        );
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+        dbtx.insert_entry(&NoteDistributionPolicyKey, &policy).await;
+        dbtx.commit_tx().await;
+
+        Ok(())
+    }
+
+    /// Reissues a small batch of surplus notes if the wallet holds too few
+    /// notes of some denominations compared to its
+    /// [`NoteDistributionPolicy`]. Returns the operation reissuing the notes,
+    /// if any.
+    ///
+    /// Nothing is reissued while e-cash is being issued or spent, such that
+    /// the rebalancing does not compete with the user for notes.
+    pub async fn rebalance_notes(&self) -> anyhow::Result<Option<OperationId>> {
+        let is_busy = self
+            .client_ctx
+            .get_own_active_states()
+            .await
+            .into_iter()
+            .any(|(state, _)| {
+                matches!(
+                    state,
+                    MintClientStateMachines::Input(_) | MintClientStateMachines::Output(_)
+                )
+            });
+
+        if is_busy {
+            return Ok(None);
+        }
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction_nc().await;
+        let policy = self.note_distribution_policy(&mut dbtx).await;
+        let counts = self.get_note_counts_by_denomination(&mut dbtx).await;
+        drop(dbtx);
+
+        let selected = plan_rebalancing(
+            &counts,
+            &self.active_keyset().1,
+            &policy,
+            &self.cfg.fee_consensus,
+        );
+
+        if selected.is_empty() {
+            return Ok(None);
+        }
+
+        let amount = selected.total_amount();
+        let note_count = selected.count_items();
+
+        debug!(target: LOG_CLIENT_MODULE_MINT, %amount, %note_count, "Rebalancing notes");
+
+        let operation_id = self
+            .reissue_own_notes(&SelectNotesByTierCounts(selected), amount)
+            .await?;
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                NotesRebalanced {
+                    operation_id,
+                    amount,
+                    note_count,
+                },
+            )
+            .await;
+        dbtx.commit_tx().await;
+
+        Ok(Some(operation_id))
+    }
+
+    /// Votes for the generation of a new keyset, returns the id of the keyset
+    /// that will be generated once a threshold of guardians voted for it. The
+    /// current keyset stays redeemable for `deprecation_sessions` after that.
+    /// The new keyset has the amount `tiers` or the ones of the current keyset
+    /// if none are given.
+    pub async fn rotate_keyset(
+        &self,
+        deprecation_sessions: u64,
+        tiers: Option<Vec<Amount>>,
+    ) -> anyhow::Result<KeysetId> {
+        if let Some(tiers) = &tiers {
+            validate_keyset_tiers(tiers)?;
+        }
+
+        let auth = self
+            .admin_auth
+            .clone()
+            .ok_or(anyhow!("Admin auth not set"))?;
+
+        Ok(self
+            .module_api
+            .rotate_keyset(auth, deprecation_sessions, tiers)
+            .await?)
+    }
+
+    /// The key that e-cash notes can be locked to in order to be sent to us,
+    /// see [`MintClientModule::spend_notes_locked`]
+    pub fn note_lock_keypair(&self) -> Keypair {
+        self.secret
+            .child_key(MINT_NOTE_LOCK_CHILD_ID)
+            .to_secp_key(&self.secp)
+    }
+
     async fn spend_notes_oob(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -1452,10 +2006,11 @@ pub async fn reissue_external_notes<M: Serialize + Send>(
         extra_meta: M,
     ) -> anyhow::Result<OperationId> {
         let notes = oob_notes.notes().clone();
+        let locked_notes = oob_notes.locked_notes();
         let federation_id_prefix = oob_notes.federation_id_prefix();
 
         ensure!(
-            notes.total_amount() > Amount::ZERO,
+            oob_notes.total_amount() > Amount::ZERO,
             "Reissuing zero-amount e-cash isn't supported"
         );
 
@@ -1463,19 +2018,40 @@ pub async fn reissue_external_notes<M: Serialize + Send>(
             bail!(ReissueExternalNotesError::WrongFederationId);
         }
 
-        let operation_id = OperationId(
-            notes
-                .consensus_hash::<sha256t::Hash<OOBReissueTag>>()
-                .to_byte_array(),
-        );
+        let operation_id = if locked_notes.is_empty() {
+            OperationId(
+                notes
+                    .consensus_hash::<sha256t::Hash<OOBReissueTag>>()
+                    .to_byte_array(),
+            )
+        } else {
+            OperationId(
+                (&notes, &locked_notes)
+                    .consensus_hash::<sha256t::Hash<OOBReissueTag>>()
+                    .to_byte_array(),
+            )
+        };
 
-        let amount = notes.total_amount();
-        let mint_inputs = self.create_input_from_notes(notes)?;
+        let amount = oob_notes.total_amount();
+        let mut tx = TransactionBuilder::new();
 
-        let tx = TransactionBuilder::new().with_inputs(
-            self.client_ctx
-                .make_dyn(create_bundle_for_inputs(mint_inputs, operation_id)),
-        );
+        if !notes.is_empty() {
+            let mint_inputs = self.create_input_from_notes(notes)?;
+            tx = tx.with_inputs(
+                self.client_ctx
+                    .make_dyn(create_bundle_for_inputs(mint_inputs, operation_id)),
+            );
+        }
+
+        // Locked notes can not be refunded to us if the transaction is rejected,
+        // so there is no state machine tracking them
+        if !locked_notes.is_empty() {
+            let locked_inputs = self.create_input_from_locked_notes(locked_notes)?;
+            tx = tx.with_inputs(
+                self.client_ctx
+                    .make_dyn(ClientInputBundle::new_no_sm(locked_inputs)),
+            );
+        }
 
         let extra_meta = serde_json::to_value(extra_meta)
             .expect("MintClientModule::reissue_external_notes extra_meta is serializable");
@@ -1537,7 +2113,7 @@ pub async fn subscribe_reissue_external_notes(
 
                 (txid, out_points)
             }
-            MintOperationMetaVariant::SpendOOB { .. } => bail!("Operation is not a reissuance"),
+            _ => bail!("Operation is not a reissuance"),
         };
 
         let client_ctx = self.client_ctx.clone();
@@ -1702,10 +2278,510 @@ pub async fn spend_notes_with_selector<M: Serialize + Send>(
             })
     }
 
-    /// Validate the given notes and return the total amount of the notes.
-    /// Validation checks that:
-    /// - the federation ID is correct
-    /// - the note has a valid signature
+    /// Issues e-cash notes of exactly `amount` that are locked to `recipient`
+    /// and removes them from the wallet to be sent to the recipient out of
+    /// band. Unlike bearer notes, locked notes can only be reissued by the
+    /// holder of the recipient's secret key (see
+    /// [`MintClientModule::note_lock_keypair`]), so anyone observing them in
+    /// transit can not steal them.
+    ///
+    /// Every note is locked to a different key derived from `recipient`, so the
+    /// guardians can not link the notes sent to the same recipient.
+    ///
+    /// Since the locked notes have to be issued by the federation first, this
+    /// submits a transaction and waits for the notes to be issued before
+    /// returning them. Afterwards, the spend behaves like one started with
+    /// [`MintClientModule::spend_notes_with_selector`] and can be canceled via
+    /// [`MintClientModule::try_cancel_spend_notes`] as long as the recipient
+    /// hasn't reissued the notes yet.
+    ///
+    /// The lock does not prevent us from reclaiming the notes: we keep the
+    /// secret of every note's refund key, which can spend the note like a
+    /// bearer note until the recipient has reissued it. Hence, the recipient
+    /// should only consider the payment final once it has reissued the notes,
+    /// and we reclaim the notes ourselves if the spend is canceled or times
+    /// out before that.
+    pub async fn spend_notes_locked<M: Serialize + Send>(
+        &self,
+        amount: Amount,
+        recipient: PublicKey,
+        try_cancel_after: Duration,
+        include_invite: bool,
+        extra_meta: M,
+    ) -> anyhow::Result<(OperationId, OOBNotes)> {
+        ensure!(
+            amount > Amount::ZERO,
+            "zero-amount out-of-band spends are not supported"
+        );
+
+        // Otherwise the recipient would not be able to reissue the notes
+        ensure!(
+            self.module_api
+                .module_consensus_version()
+                .await
+                .is_ok_and(|version| LOCKED_NOTE_MODULE_CONSENSUS_VERSION <= version),
+            "The federation does not support locked notes yet"
+        );
+
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::spend_notes_locked extra_meta is serializable");
+
+        let (keyset_id, tbs_pks) = self.active_keyset();
+        let mut remaining_amount = amount;
+        let mut outputs = Vec::new();
+        let mut issuance_requests = Vec::new();
+        let mut locks = Vec::new();
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        for tier in tbs_pks.tiers().rev() {
+            while *tier <= remaining_amount {
+                let secret = self.new_note_secret(*tier, &mut dbtx.to_ref_nc()).await;
+                let (issuance_request, blind_nonce, lock) =
+                    NoteIssuanceRequest::new_locked(&self.secp, &secret, recipient);
+
+                dbtx.insert_new_entry(&LockedNoteIssuanceKey(lock.nonce()), &())
+                    .await;
+
+                outputs.push(ClientOutput {
+                    output: issuance_output(*tier, blind_nonce, keyset_id),
+                    amount: *tier,
+                });
+                issuance_requests.push((*tier, issuance_request));
+                locks.push((*tier, lock));
+
+                remaining_amount -= *tier;
+            }
+        }
+
+        ensure!(
+            remaining_amount == Amount::ZERO,
+            "The amount can not be represented by the federation's denominations"
+        );
+
+        dbtx.commit_tx_result().await?;
+
+        let issuance_operation_id = OperationId::new_random();
+        let num_outputs = outputs.len() as u64;
+
+        let state_generator = Arc::new(move |out_point_range: OutPointRange| {
+            vec![MintClientStateMachines::Output(MintOutputStateMachine {
+                common: MintOutputCommon {
+                    operation_id: issuance_operation_id,
+                    out_point_range,
+                },
+                state: issuance_state(
+                    keyset_id,
+                    out_point_range
+                        .into_iter()
+                        .map(|out_point| out_point.out_idx)
+                        .zip(issuance_requests.clone())
+                        .collect(),
+                ),
+            })]
+        });
+
+        let tx = TransactionBuilder::new().with_outputs(self.client_ctx.make_client_outputs(
+            ClientOutputBundle::new(
+                outputs,
+                vec![ClientOutputSM {
+                    state_machines: state_generator,
+                }],
+            ),
+        ));
+
+        let operation_meta_gen = move |change_range: OutPointRange| MintOperationMeta {
+            variant: MintOperationMetaVariant::Reissuance {
+                legacy_out_point: None,
+                txid: Some(change_range.txid()),
+                out_point_indices: change_range
+                    .into_iter()
+                    .map(|out_point| out_point.out_idx)
+                    .collect(),
+            },
+            amount,
+            extra_meta: serde_json::Value::Null,
+        };
+
+        let change_range = self
+            .client_ctx
+            .finalize_and_submit_transaction(
+                issuance_operation_id,
+                MintCommonInit::KIND.as_str(),
+                operation_meta_gen,
+                tx,
+            )
+            .await?;
+
+        // Our outputs precede the change outputs in the transaction
+        for out_idx in 0..num_outputs {
+            self.await_output_finalized(
+                issuance_operation_id,
+                OutPoint {
+                    txid: change_range.txid(),
+                    out_idx,
+                },
+            )
+            .await?;
+        }
+
+        let federation_id_prefix = self.federation_id.to_prefix();
+
+        self.client_ctx
+            .module_db()
+            .autocommit(
+                |dbtx, _| {
+                    let extra_meta = extra_meta.clone();
+                    let locks = locks.clone();
+                    Box::pin(async move {
+                        let mut spendable_notes = TieredMulti::default();
+                        let mut locked_notes = TieredMulti::default();
+
+                        for (amount, lock) in locks {
+                            let spendable_note = dbtx
+                                .remove_entry(&LockedNoteKey {
+                                    amount,
+                                    nonce: lock.nonce(),
+                                })
+                                .await
+                                .context("Locked note was not issued")?
+                                .decode()?;
+
+                            self.client_ctx
+                                .log_event(
+                                    dbtx,
+                                    NoteSpent {
+                                        nonce: spendable_note.nonce(),
+                                    },
+                                )
+                                .await;
+
+                            locked_notes.push(
+                                amount,
+                                LockedNote {
+                                    note: spendable_note.note(),
+                                    lock,
+                                },
+                            );
+                            spendable_notes.push(amount, spendable_note);
+                        }
+
+                        let operation_id = spendable_notes_to_operation_id(&spendable_notes);
+
+                        let oob_notes = if include_invite {
+                            OOBNotes::new_locked_with_invite(
+                                locked_notes,
+                                &self.client_ctx.get_invite_code().await,
+                            )
+                        } else {
+                            OOBNotes::new_locked(federation_id_prefix, locked_notes)
+                        };
+
+                        let states = vec![MintClientStateMachines::OOB(MintOOBStateMachine {
+                            operation_id,
+                            state: MintOOBStates::CreatedMulti(MintOOBStatesCreatedMulti {
+                                spendable_notes: spendable_notes.into_iter_items().collect(),
+                                timeout: fedimint_core::time::now() + try_cancel_after,
+                            }),
+                        })];
+
+                        self.client_ctx
+                            .add_state_machines_dbtx(
+                                dbtx,
+                                self.client_ctx.map_dyn(states).collect(),
+                            )
+                            .await?;
+                        self.client_ctx
+                            .add_operation_log_entry_dbtx(
+                                dbtx,
+                                operation_id,
+                                MintCommonInit::KIND.as_str(),
+                                MintOperationMeta {
+                                    variant: MintOperationMetaVariant::SpendOOB {
+                                        requested_amount: amount,
+                                        oob_notes: oob_notes.clone(),
+                                    },
+                                    amount,
+                                    extra_meta,
+                                },
+                            )
+                            .await;
+                        self.client_ctx
+                            .log_event(
+                                dbtx,
+                                OOBNotesSpent {
+                                    requested_amount: amount,
+                                    spent_amount: amount,
+                                    timeout: try_cancel_after,
+                                    include_invite,
+                                },
+                            )
+                            .await;
+
+                        Ok((operation_id, oob_notes))
+                    })
+                },
+                Some(100),
+            )
+            .await
+            .map_err(|e| match e {
+                AutocommitError::ClosureError { error, .. } => error,
+                AutocommitError::CommitFailed { last_error, .. } => {
+                    anyhow!("Commit to DB failed: {last_error}")
+                }
+            })
+    }
+
+    /// Locks `amount` of our e-cash in a [`HashTimeLock`] that the holder of
+    /// `claim_key` can claim by revealing the preimage of `payment_hash` until
+    /// the federation has finished `timeout_session` sessions, after which we
+    /// can refund it via [`MintClientModule::refund_htlc`].
+    ///
+    /// To atomically swap e-cash with another federation, the party knowing the
+    /// preimage offers e-cash with a later timeout than the counterparty, who
+    /// only offers their e-cash after verifying the first offer via
+    /// [`MintClientModule::verify_htlc_offer`]. Once the preimage is revealed
+    /// by claiming the counterparty's offer, it can be obtained via
+    /// [`MintClientModule::await_htlc_preimage`] to claim the first offer.
+    pub async fn offer_htlc<M: Serialize + Send>(
+        &self,
+        amount: Amount,
+        claim_key: PublicKey,
+        payment_hash: sha256::Hash,
+        timeout_session: u64,
+        extra_meta: M,
+    ) -> anyhow::Result<(OperationId, HtlcOffer)> {
+        ensure!(
+            amount > Amount::ZERO,
+            "Hash time locks have to lock a non-zero amount"
+        );
+
+        ensure!(
+            self.module_api
+                .module_consensus_version()
+                .await
+                .is_ok_and(|version| HTLC_MODULE_CONSENSUS_VERSION <= version),
+            "The federation does not support hash time locks yet"
+        );
+
+        let operation_id = OperationId::new_random();
+
+        let htlc = HashTimeLock {
+            payment_hash,
+            claim_key,
+            refund_key: self.note_lock_keypair().public_key(),
+            timeout_session,
+        };
+
+        let output = ClientOutput {
+            output: MintOutput::new_v1(amount, htlc),
+            amount,
+        };
+
+        let tx = TransactionBuilder::new().with_outputs(
+            self.client_ctx
+                .make_client_outputs(ClientOutputBundle::new_no_sm(vec![output])),
+        );
+
+        let federation_id = self.federation_id;
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::offer_htlc extra_meta is serializable");
+
+        // Our output precedes the change outputs in the transaction
+        let offer_from_range = move |range: OutPointRange| HtlcOffer {
+            federation_id,
+            out_point: OutPoint {
+                txid: range.txid(),
+                out_idx: 0,
+            },
+            amount,
+            htlc,
+        };
+
+        let range = self
+            .client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                MintCommonInit::KIND.as_str(),
+                move |range| MintOperationMeta {
+                    variant: MintOperationMetaVariant::HtlcOffer {
+                        offer: offer_from_range(range),
+                    },
+                    amount,
+                    extra_meta: extra_meta.clone(),
+                },
+                tx,
+            )
+            .await?;
+
+        self.client_ctx
+            .transaction_updates(operation_id)
+            .await
+            .await_tx_accepted(range.txid())
+            .await
+            .map_err(|e| anyhow!("Hash time lock transaction was rejected: {e}"))?;
+
+        Ok((operation_id, offer_from_range(range)))
+    }
+
+    /// Verifies that the e-cash of the offer is locked on our federation and
+    /// can be claimed by us, which has to be done before offering our side of
+    /// an atomic swap.
+    pub async fn verify_htlc_offer(&self, offer: &HtlcOffer) -> anyhow::Result<()> {
+        ensure!(
+            offer.federation_id == self.federation_id,
+            "The hash time lock was offered on another federation"
+        );
+
+        ensure!(
+            offer.htlc.claim_key == self.note_lock_keypair().public_key(),
+            "The hash time lock can not be claimed by us"
+        );
+
+        let output = self
+            .module_api
+            .htlc(offer.out_point)
+            .await?
+            .context("The hash time lock does not exist or was already spent")?;
+
+        ensure!(
+            output.amount == offer.amount && output.htlc == offer.htlc,
+            "The hash time lock does not match the offer"
+        );
+
+        let session_count = self.module_api.consensus_session_count().await?;
+
+        ensure!(
+            session_count < offer.htlc.timeout_session,
+            "The hash time lock has already expired"
+        );
+
+        Ok(())
+    }
+
+    /// Claims the e-cash of the offer by revealing the `preimage` and reissues
+    /// it into our wallet.
+    pub async fn claim_htlc<M: Serialize + Send>(
+        &self,
+        offer: HtlcOffer,
+        preimage: [u8; 32],
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            offer.htlc.verify_preimage(&preimage),
+            "The preimage does not match the payment hash"
+        );
+
+        self.verify_htlc_offer(&offer).await?;
+
+        self.spend_htlc(
+            offer,
+            HtlcWitness::Claim(preimage),
+            MintOperationMetaVariant::HtlcClaim {
+                out_point: offer.out_point,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    /// Refunds the e-cash of an offer created by
+    /// [`MintClientModule::offer_htlc`] once it has expired without being
+    /// claimed.
+    pub async fn refund_htlc<M: Serialize + Send>(
+        &self,
+        offer: HtlcOffer,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            offer.federation_id == self.federation_id,
+            "The hash time lock was offered on another federation"
+        );
+
+        ensure!(
+            offer.htlc.refund_key == self.note_lock_keypair().public_key(),
+            "The hash time lock can not be refunded by us"
+        );
+
+        let session_count = self.module_api.consensus_session_count().await?;
+
+        ensure!(
+            offer.htlc.timeout_session <= session_count,
+            "The hash time lock expires after session {}, the federation has finished {session_count} sessions",
+            offer.htlc.timeout_session
+        );
+
+        self.spend_htlc(
+            offer,
+            HtlcWitness::Refund,
+            MintOperationMetaVariant::HtlcRefund {
+                out_point: offer.out_point,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    async fn spend_htlc<M: Serialize + Send>(
+        &self,
+        offer: HtlcOffer,
+        witness: HtlcWitness,
+        variant: MintOperationMetaVariant,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let operation_id = OperationId::new_random();
+
+        let input = ClientInput {
+            input: MintInput::new_v2(offer.out_point, witness),
+            keys: vec![self.note_lock_keypair()],
+            amount: offer.amount,
+        };
+
+        let tx = TransactionBuilder::new().with_inputs(
+            self.client_ctx
+                .make_client_inputs(ClientInputBundle::new_no_sm(vec![input])),
+        );
+
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::spend_htlc extra_meta is serializable");
+
+        let range = self
+            .client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                MintCommonInit::KIND.as_str(),
+                move |_| MintOperationMeta {
+                    variant: variant.clone(),
+                    amount: offer.amount,
+                    extra_meta: extra_meta.clone(),
+                },
+                tx,
+            )
+            .await?;
+
+        self.client_ctx
+            .transaction_updates(operation_id)
+            .await
+            .await_tx_accepted(range.txid())
+            .await
+            .map_err(|e| anyhow!("Hash time lock transaction was rejected: {e}"))?;
+
+        Ok(operation_id)
+    }
+
+    /// Waits for the counterparty to claim the e-cash of the offer and returns
+    /// the revealed preimage, or `None` if the offer expired without being
+    /// claimed.
+    pub async fn await_htlc_preimage(&self, offer: &HtlcOffer) -> Option<[u8; 32]> {
+        self.module_api
+            .await_htlc_preimage(offer.out_point, offer.htlc.timeout_session)
+            .await
+    }
+
+    /// Validate the given notes and return the total amount of the notes.
+    /// Validation checks that:
+    /// - the federation ID is correct
+    /// - the note has a valid signature
     /// - the spend key is correct.
     pub fn validate_notes(&self, oob_notes: &OOBNotes) -> anyhow::Result<Amount> {
         let federation_id_prefix = oob_notes.federation_id_prefix();
@@ -1715,15 +2791,15 @@ pub fn validate_notes(&self, oob_notes: &OOBNotes) -> anyhow::Result<Amount> {
             bail!("Federation ID does not match");
         }
 
-        let tbs_pks = &self.cfg.tbs_pks;
+        let known_tiers = self.known_tiers();
 
         for (idx, (amt, snote)) in notes.iter_items().enumerate() {
-            let key = tbs_pks
-                .get(amt)
-                .ok_or_else(|| anyhow!("Note {idx} uses an invalid amount tier {amt}"))?;
+            if !known_tiers.contains(&amt) {
+                bail!("Note {idx} uses an invalid amount tier {amt}");
+            }
 
             let note = snote.note();
-            if !note.verify(*key) {
+            if self.verify_note(amt, &note).is_err() {
                 bail!("Note {idx} has an invalid federation signature");
             }
 
@@ -1733,7 +2809,96 @@ pub fn validate_notes(&self, oob_notes: &OOBNotes) -> anyhow::Result<Amount> {
             }
         }
 
-        Ok(notes.total_amount())
+        let lock_keypair = self.note_lock_keypair();
+
+        for (idx, (amt, locked_note)) in oob_notes.locked_notes().iter_items().enumerate() {
+            if !known_tiers.contains(&amt) {
+                bail!("Locked note {idx} uses an invalid amount tier {amt}");
+            }
+
+            if self.verify_note(amt, &locked_note.note).is_err() {
+                bail!("Locked note {idx} has an invalid federation signature");
+            }
+
+            if locked_note.note.nonce != locked_note.lock.nonce() {
+                bail!("Locked note {idx} is not locked by the supplied lock");
+            }
+
+            if locked_note.lock.recipient_keypair(&lock_keypair).is_none() {
+                bail!("Locked note {idx} is locked to another recipient");
+            }
+        }
+
+        Ok(oob_notes.total_amount())
+    }
+
+    /// Checks which of the given notes can not be redeemed anymore, e.g. to
+    /// reject a payment before reissuing the notes. Returns the amount, nonce
+    /// and status of every note that was spent or whose keyset expired.
+    ///
+    /// All notes are checked with a single request per guardian, and a note
+    /// is only considered redeemable or not once a threshold of guardians
+    /// agree on it.
+    pub async fn check_notes_spent(
+        &self,
+        oob_notes: &OOBNotes,
+    ) -> anyhow::Result<Vec<(Amount, Nonce, NoteSpendStatus)>> {
+        let notes = oob_notes
+            .notes()
+            .iter_items()
+            .map(|(amount, note)| (amount, note.note()))
+            .chain(
+                oob_notes
+                    .locked_notes()
+                    .iter_items()
+                    .map(|(amount, note)| (amount, note.note)),
+            )
+            .collect::<Vec<_>>();
+
+        let mut unredeemable = vec![];
+        let mut checked = vec![];
+
+        for (amount, note) in notes {
+            match self.note_keyset_id(amount, &note) {
+                Some(keyset_id) => checked.push((amount, keyset_id, note.nonce)),
+                // The note was not signed by the federation under any keyset we know of
+                None => unredeemable.push((amount, note.nonce, NoteSpendStatus::Unredeemable)),
+            }
+        }
+
+        let mut statuses = vec![];
+
+        for chunk in checked.chunks(MAX_NOTES_SPENT_REQUEST) {
+            let chunk_statuses = self
+                .module_api
+                .check_notes_spent(
+                    chunk
+                        .iter()
+                        .map(|(_, keyset_id, nonce)| (*keyset_id, *nonce))
+                        .collect(),
+                )
+                .await?;
+
+            ensure!(
+                chunk_statuses.len() == chunk.len(),
+                "Federation returned the spend status of {} notes, expected {}",
+                chunk_statuses.len(),
+                chunk.len()
+            );
+
+            statuses.extend(chunk_statuses);
+        }
+
+        Ok(unredeemable
+            .into_iter()
+            .chain(
+                checked
+                    .into_iter()
+                    .zip(statuses)
+                    .filter(|(_, status)| *status != NoteSpendStatus::Unspent)
+                    .map(|((amount, _, nonce), status)| (amount, nonce, status)),
+            )
+            .collect())
     }
 
     /// Try to cancel a spend operation started with
@@ -1894,6 +3059,83 @@ pub async fn reused_note_secrets(&self) -> Vec<(Amount, NoteIssuanceRequest, Bli
     }
 }
 
+/// Picks the notes to reissue such that the change fills up the tiers holding
+/// fewer notes than `policy` targets for the current balance. Only notes in
+/// excess of the target are picked, largest first, until they cover the
+/// missing notes including fees. Returns no notes if too few are missing.
+fn plan_rebalancing<K>(
+    counts: &TieredCounts,
+    tiers: &Tiered<K>,
+    policy: &NoteDistributionPolicy,
+    fee_consensus: &FeeConsensus,
+) -> TieredCounts {
+    let target = represent_amount(
+        counts.total_amount(),
+        &TieredCounts::default(),
+        tiers,
+        policy.notes_per_denomination,
+        fee_consensus,
+    );
+
+    let missing = target
+        .iter()
+        .map(|(amount, count)| (amount, count.saturating_sub(counts.get(amount))))
+        .collect::<TieredCounts>();
+
+    if missing.count_items() < usize::from(policy.min_missing_notes) {
+        return TieredCounts::default();
+    }
+
+    let required_amount = missing
+        .iter()
+        .map(|(amount, count)| (amount + fee_consensus.fee(amount)) * count as u64)
+        .sum::<Amount>();
+
+    let mut selected = TieredCounts::default();
+    let mut selected_amount = Amount::ZERO;
+
+    for (amount, count) in counts.iter().collect::<Vec<_>>().into_iter().rev() {
+        for _ in 0..count.saturating_sub(target.get(amount)) {
+            if required_amount <= selected_amount
+                || usize::from(policy.max_notes_per_rebalancing) <= selected.count_items()
+            {
+                return selected;
+            }
+
+            selected.inc(amount, 1);
+            selected_amount += amount.saturating_sub(fee_consensus.fee(amount));
+        }
+    }
+
+    selected
+}
+
+/// Issues a note under the given keyset, using the original output variant for
+/// the genesis keyset such that older federations understand our outputs
+fn issuance_output(amount: Amount, blind_nonce: BlindNonce, keyset_id: KeysetId) -> MintOutput {
+    if keyset_id == KeysetId::GENESIS {
+        MintOutput::new_v0(amount, blind_nonce)
+    } else {
+        MintOutput::new_v2(amount, blind_nonce, keyset_id)
+    }
+}
+
+/// The state of the state machine awaiting the signatures for notes issued
+/// under the given keyset
+fn issuance_state(
+    keyset_id: KeysetId,
+    issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+) -> MintOutputStates {
+    if keyset_id == KeysetId::GENESIS {
+        MintOutputStates::CreatedMulti(MintOutputStatesCreatedMulti { issuance_requests })
+    } else {
+        MintOutputStates::CreatedMultiKeyset(MintOutputStatesCreatedMultiKeyset {
+            keyset_id,
+            issuance_requests,
+        })
+    }
+}
+
 pub fn spendable_notes_to_operation_id(
     spendable_selected_notes: &TieredMulti<SpendableNote>,
 ) -> OperationId {
@@ -1974,6 +3216,81 @@ async fn select_notes(
     }
 }
 
+/// Selects the given number of notes of every tier, regardless of the
+/// requested amount
+struct SelectNotesByTierCounts(TieredCounts);
+
+#[apply(async_trait_maybe_send!)]
+impl<Note: Send> NotesSelector<Note> for SelectNotesByTierCounts {
+    async fn select_notes(
+        &self,
+        #[cfg(not(target_family = "wasm"))] stream: impl futures::Stream<Item = (Amount, Note)> + Send,
+        #[cfg(target_family = "wasm")] stream: impl futures::Stream<Item = (Amount, Note)>,
+        _requested_amount: Amount,
+        _fee_consensus: FeeConsensus,
+    ) -> anyhow::Result<TieredMulti<Note>> {
+        let mut remaining = self.0.clone();
+        let mut selected = vec![];
+
+        pin_mut!(stream);
+
+        while let Some((amount, note)) = stream.next().await {
+            if 0 < remaining.get(amount) {
+                remaining.dec(amount);
+                selected.push((amount, note));
+            }
+        }
+
+        ensure!(
+            remaining.is_empty(),
+            "The notes to rebalance are not available"
+        );
+
+        Ok(selected.into_iter().collect())
+    }
+}
+
+/// Selects all notes signed under one of the superseded keysets `tbs_pks`,
+/// regardless of the requested amount
+struct SelectDeprecatedNotes {
+    tbs_pks: Vec<Tiered<AggregatePublicKey>>,
+}
+
+impl SelectDeprecatedNotes {
+    fn is_deprecated(&self, amount: Amount, note: &SpendableNoteUndecoded) -> bool {
+        let Ok(note) = note.clone().decode() else {
+            return false;
+        };
+
+        self.tbs_pks
+            .iter()
+            .filter_map(|tbs_pks| tbs_pks.get(amount))
+            .any(|key| note.note().verify(*key))
+    }
+}
+
+#[apply(async_trait_maybe_send!)]
+impl NotesSelector for SelectDeprecatedNotes {
+    async fn select_notes(
+        &self,
+        #[cfg(not(target_family = "wasm"))] stream: impl futures::Stream<
+            Item = (Amount, SpendableNoteUndecoded),
+        > + Send,
+        #[cfg(target_family = "wasm")] stream: impl futures::Stream<
+            Item = (Amount, SpendableNoteUndecoded),
+        >,
+        _requested_amount: Amount,
+        _fee_consensus: FeeConsensus,
+    ) -> anyhow::Result<TieredMulti<SpendableNoteUndecoded>> {
+        Ok(stream
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .filter(|(amount, note)| self.is_deprecated(*amount, note))
+            .collect())
+    }
+}
+
 // We are using a greedy algorithm to select notes. We start with the largest
 // then proceed to the lowest tiers/denominations.
 // But there is a catch: we don't know if there are enough notes in the lowest
@@ -2212,6 +3529,30 @@ pub fn to_undecoded(&self) -> SpendableNoteUndecoded {
     }
 }
 
+/// A [`Note`] that is locked to a recipient by a [`NoteLock`], such that only
+/// the recipient can spend it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct LockedNote {
+    pub note: Note,
+    pub lock: NoteLock,
+}
+
+impl fmt::Display for LockedNote {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.note.nonce.fmt(f)
+    }
+}
+
+/// E-cash locked in a [`HashTimeLock`] on a federation, which is sent to the
+/// counterparty of an atomic swap so they can verify and eventually claim it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct HtlcOffer {
+    pub federation_id: FederationId,
+    pub out_point: OutPoint,
+    pub amount: Amount,
+    pub htlc: HashTimeLock,
+}
+
 /// A version of [`SpendableNote`] that didn't decode the `signature` yet
 ///
 /// **Note**: signature decoding from raw bytes is faliable, as not all bytes
@@ -2433,8 +3774,8 @@ mod tests {
     use serde_json::json;
 
     use crate::{
-        MintOperationMetaVariant, OOBNotes, OOBNotesPart, SpendableNote, SpendableNoteUndecoded,
-        represent_amount, select_notes_from_stream,
+        MintOperationMetaVariant, NoteDistributionPolicy, OOBNotes, OOBNotesPart, SpendableNote,
+        SpendableNoteUndecoded, plan_rebalancing, represent_amount, select_notes_from_stream,
     };
 
     #[test]
@@ -2483,6 +3824,64 @@ fn denominations(denominations: Vec<(Amount, usize)>) -> TieredCounts {
         );
     }
 
+    #[test]
+    fn plan_rebalancing_reissues_surplus_notes() {
+        let tiers: Tiered<()> = [1, 2, 4, 8]
+            .into_iter()
+            .map(|tier| (Amount::from_sats(tier), ()))
+            .collect();
+
+        let policy = NoteDistributionPolicy {
+            notes_per_denomination: 2,
+            max_notes_per_rebalancing: 10,
+            min_missing_notes: 2,
+        };
+
+        // the target for 24 sats is 1, 1, 2, 2, 2, 4, 4, 8, so the two surplus
+        // notes of 8 sats have to be reissued to fill the smaller tiers
+        let counts = TieredCounts::from_iter([(Amount::from_sats(8), 3)]);
+
+        assert_eq!(
+            plan_rebalancing(&counts, &tiers, &policy, &FeeConsensus::zero()),
+            TieredCounts::from_iter([(Amount::from_sats(8), 2)])
+        );
+
+        // the number of reissued notes is limited by the policy
+        assert_eq!(
+            plan_rebalancing(
+                &counts,
+                &tiers,
+                &NoteDistributionPolicy {
+                    max_notes_per_rebalancing: 1,
+                    ..policy.clone()
+                },
+                &FeeConsensus::zero()
+            ),
+            TieredCounts::from_iter([(Amount::from_sats(8), 1)])
+        );
+
+        // a balanced wallet is left alone
+        let balanced = TieredCounts::from_iter([
+            (Amount::from_sats(1), 2),
+            (Amount::from_sats(2), 3),
+            (Amount::from_sats(4), 2),
+            (Amount::from_sats(8), 1),
+        ]);
+
+        assert!(plan_rebalancing(&balanced, &tiers, &policy, &FeeConsensus::zero()).is_empty());
+
+        // as is a wallet that only misses fewer notes than the policy tolerates
+        let almost_balanced = TieredCounts::from_iter([
+            (Amount::from_sats(1), 2),
+            (Amount::from_sats(2), 2),
+            (Amount::from_sats(4), 4),
+        ]);
+
+        assert!(
+            plan_rebalancing(&almost_balanced, &tiers, &policy, &FeeConsensus::zero()).is_empty()
+        );
+    }
+
     #[test_log::test(tokio::test)]
     async fn select_notes_avg_test() {
         let max_amount = Amount::from_sats(1_000_000);
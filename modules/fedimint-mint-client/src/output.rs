@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::hash;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail};
 use fedimint_api_client::api::{
@@ -10,15 +11,15 @@
 use fedimint_client_module::module::{ClientContext, OutPointRange};
 use fedimint_client_module::sm::{ClientSMDatabaseTransaction, State, StateTransition};
 use fedimint_core::core::{Decoder, OperationId};
-use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
+use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::ApiRequestErased;
-use fedimint_core::secp256k1::{Keypair, Secp256k1, Signing};
-use fedimint_core::{Amount, NumPeersExt, OutPoint, PeerId, Tiered, TransactionId, crit};
+use fedimint_core::secp256k1::{Keypair, PublicKey, Secp256k1, Signing, Verification};
+use fedimint_core::{Amount, NumPeersExt, OutPoint, PeerId, Tiered, TransactionId, crit, runtime};
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
 use fedimint_mint_common::endpoint_constants::AWAIT_OUTPUT_OUTCOME_ENDPOINT;
-use fedimint_mint_common::{BlindNonce, MintOutputOutcome, Nonce};
+use fedimint_mint_common::{BlindNonce, Keyset, KeysetId, MintOutputOutcome, Nonce, NoteLock};
 use futures::future::join_all;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator as _, ParallelIterator as _};
 use serde::{Deserialize, Serialize};
@@ -26,9 +27,10 @@
     AggregatePublicKey, BlindedMessage, BlindedSignature, BlindedSignatureShare, BlindingKey,
     PublicKeyShare, aggregate_signature_shares, blind_message, unblind_signature,
 };
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::client_db::NoteKey;
+use crate::api::MintFederationApi as _;
+use crate::client_db::{KeysetKey, LockedNoteIssuanceKey, LockedNoteKey, NoteKey};
 use crate::event::NoteCreated;
 use crate::{MintClientContext, MintClientModule, SpendableNote};
 
@@ -69,6 +71,9 @@ pub enum MintOutputStates {
     Succeeded(MintOutputStatesSucceeded),
     /// Issuance request was created, we are waiting for blind signatures
     CreatedMulti(MintOutputStatesCreatedMulti),
+    /// Issuance request under a keyset other than the genesis keyset was
+    /// created, we are waiting for blind signatures
+    CreatedMultiKeyset(MintOutputStatesCreatedMultiKeyset),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
@@ -116,6 +121,9 @@ fn transitions(
             MintOutputStates::CreatedMulti(created) => {
                 created.transitions(context, global_context, self.common)
             }
+            MintOutputStates::CreatedMultiKeyset(created) => {
+                created.transitions(context, global_context, self.common)
+            }
             MintOutputStates::Aborted(_)
             | MintOutputStates::Failed(_)
             | MintOutputStates::Succeeded(_) => {
@@ -323,12 +331,17 @@ fn transitions(
     ) -> Vec<StateTransition<MintOutputStateMachine>> {
         let tbs_pks = context.tbs_pks.clone();
         let client_ctx = context.client_ctx.clone();
+        let keyset_refresh = context.keyset_refresh.clone();
 
         vec![
-            // Check if transaction was rejected
+            // Check if transaction was rejected, which happens if the federation
+            // rotated its keyset since we last refreshed ours
             StateTransition::new(
                 Self::await_tx_rejected(global_context.clone(), common),
-                |_dbtx, (), state| Box::pin(async move { Self::transition_tx_rejected(&state) }),
+                move |_dbtx, (), state| {
+                    keyset_refresh.notify_one();
+                    Box::pin(async move { Self::transition_tx_rejected(&state) })
+                },
             ),
             // Check for output outcome
             StateTransition::new(
@@ -364,7 +377,10 @@ async fn await_tx_rejected(global_context: DynGlobalClientContext, common: MintO
     }
 
     fn transition_tx_rejected(old_state: &MintOutputStateMachine) -> MintOutputStateMachine {
-        assert!(matches!(old_state.state, MintOutputStates::CreatedMulti(_)));
+        assert!(matches!(
+            old_state.state,
+            MintOutputStates::CreatedMulti(_) | MintOutputStates::CreatedMultiKeyset(_)
+        ));
 
         MintOutputStateMachine {
             common: old_state.common,
@@ -477,8 +493,10 @@ async fn transition_outcome_ready(
         // and store the resulting note in the database
 
         let mut amount_total = Amount::ZERO;
-        let MintOutputStates::CreatedMulti(created) = old_state.state else {
-            panic!("Unexpected prior state")
+        let issuance_requests = match old_state.state {
+            MintOutputStates::CreatedMulti(created) => created.issuance_requests,
+            MintOutputStates::CreatedMultiKeyset(created) => created.issuance_requests,
+            _ => panic!("Unexpected prior state"),
         };
 
         let mut spendable_notes: Vec<(Amount, SpendableNote)> = vec![];
@@ -496,7 +514,7 @@ async fn transition_outcome_ready(
 
                 // this implies that the mint client config's public keys are inconsistent
                 let (amount, issuance_request) =
-                    created.issuance_requests.get(&out_idx).expect("Must have");
+                    issuance_requests.get(&out_idx).expect("Must have");
 
                 let amount_key = tbs_pks.tier(amount).expect("Must have keys for any amount");
 
@@ -521,6 +539,28 @@ async fn transition_outcome_ready(
                 .await;
 
             amount_total += amount;
+
+            // Locked notes are handed to their recipient by whoever requested them,
+            // they must never be picked by our note selection in the meantime
+            if dbtx
+                .module_tx()
+                .remove_entry(&LockedNoteIssuanceKey(spendable_note.nonce()))
+                .await
+                .is_some()
+            {
+                dbtx.module_tx()
+                    .insert_new_entry(
+                        &LockedNoteKey {
+                            amount,
+                            nonce: spendable_note.nonce(),
+                        },
+                        &spendable_note.to_undecoded(),
+                    )
+                    .await;
+
+                continue;
+            }
+
             if let Some(note) = dbtx
                 .module_tx()
                 .insert_entry(
@@ -544,6 +584,120 @@ async fn transition_outcome_ready(
     }
 }
 
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesCreatedMultiKeyset {
+    pub(crate) keyset_id: KeysetId,
+    pub(crate) issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+}
+
+impl MintOutputStatesCreatedMultiKeyset {
+    fn transitions(
+        &self,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        let client_ctx = context.client_ctx.clone();
+        let keyset_refresh = context.keyset_refresh.clone();
+
+        vec![
+            // Check if transaction was rejected, which happens if the keyset was
+            // superseded since we last refreshed ours
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_tx_rejected(global_context.clone(), common),
+                move |_dbtx, (), state| {
+                    keyset_refresh.notify_one();
+                    Box::pin(
+                        async move { MintOutputStatesCreatedMulti::transition_tx_rejected(&state) },
+                    )
+                },
+            ),
+            // Check for output outcome
+            StateTransition::new(
+                Self::await_outcome_ready(
+                    global_context.clone(),
+                    common,
+                    context.mint_decoder.clone(),
+                    context.module_db.clone(),
+                    self.keyset_id,
+                    self.issuance_requests.clone(),
+                ),
+                move |dbtx, (blinded_signature_shares, tbs_pks), old_state| {
+                    Box::pin(MintOutputStatesCreatedMulti::transition_outcome_ready(
+                        client_ctx.clone(),
+                        dbtx,
+                        blinded_signature_shares,
+                        old_state,
+                        tbs_pks,
+                    ))
+                },
+            ),
+        ]
+    }
+
+    async fn await_outcome_ready(
+        global_context: DynGlobalClientContext,
+        common: MintOutputCommon,
+        module_decoder: Decoder,
+        module_db: Database,
+        keyset_id: KeysetId,
+        issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+    ) -> (
+        Vec<(u64, BTreeMap<PeerId, BlindedSignatureShare>)>,
+        Tiered<AggregatePublicKey>,
+    ) {
+        let keyset = Self::await_keyset(&global_context, &module_db, keyset_id).await;
+
+        let blinded_signature_shares = MintOutputStatesCreatedMulti::await_outcome_ready(
+            global_context,
+            common,
+            module_decoder,
+            issuance_requests,
+            keyset.peer_tbs_pks,
+        )
+        .await;
+
+        (blinded_signature_shares, keyset.tbs_pks)
+    }
+
+    /// Loads the keyset from our database or fetches it from the federation if
+    /// we have not seen it yet, which happens when recovering notes
+    async fn await_keyset(
+        global_context: &DynGlobalClientContext,
+        module_db: &Database,
+        keyset_id: KeysetId,
+    ) -> Keyset {
+        if let Some(keyset) = module_db
+            .begin_transaction_nc()
+            .await
+            .get_value(&KeysetKey(keyset_id))
+            .await
+        {
+            return keyset;
+        }
+
+        let api = global_context.module_api();
+
+        loop {
+            match api.keysets().await {
+                Ok(keysets) => {
+                    if let Some(keyset) = keysets.get(&keyset_id) {
+                        return keyset.clone();
+                    }
+
+                    warn!(target: LOG_CLIENT_MODULE_MINT, %keyset_id, "Federation does not know the keyset of our output yet");
+                }
+                Err(error) => {
+                    debug!(target: LOG_CLIENT_MODULE_MINT, err = %error, "Failed to fetch keysets");
+                }
+            }
+
+            runtime::sleep(Duration::from_secs(10)).await;
+        }
+    }
+}
+
 /// # Panics
 /// If the given `outcome` is not a [`MintOutputOutcome::V0`] outcome.
 pub fn verify_blind_share(
@@ -629,6 +783,43 @@ pub fn new<C>(ctx: &Secp256k1<C>, secret: &DerivableSecret) -> (NoteIssuanceRequ
         (cr, BlindNonce(blinded_nonce))
     }
 
+    /// Generate a request session for a single note locked to a key derived
+    /// from the `recipient`'s static public key and returns it plus the
+    /// corresponding blinded message and the lock. The spend key of the
+    /// request is the refund key derived from `secret` tweaked by the lock,
+    /// which allows us to reclaim the note as long as the recipient has not
+    /// spent it yet.
+    pub fn new_locked<C>(
+        ctx: &Secp256k1<C>,
+        secret: &DerivableSecret,
+        recipient: PublicKey,
+    ) -> (NoteIssuanceRequest, BlindNonce, NoteLock)
+    where
+        C: Signing + Verification,
+    {
+        let refund_key = secret.child_key(SPEND_KEY_CHILD_ID).to_secp_key(ctx);
+        let lock = NoteLock::new(&refund_key, recipient);
+
+        let spend_key = Keypair::from_secret_key(
+            ctx,
+            &refund_key
+                .secret_key()
+                .add_tweak(&lock.tweak())
+                .expect("Tweak is valid with overwhelming probability"),
+        );
+        debug_assert_eq!(Nonce(spend_key.public_key()), lock.nonce());
+
+        let blinding_key = BlindingKey(secret.child_key(BLINDING_KEY_CHILD_ID).to_bls12_381_key());
+        let blinded_nonce = blind_message(lock.nonce().to_message(), blinding_key);
+
+        let cr = NoteIssuanceRequest {
+            spend_key,
+            blinding_key,
+        };
+
+        (cr, BlindNonce(blinded_nonce), lock)
+    }
+
     /// Return nonce of the e-cash note being requested
     pub fn nonce(&self) -> Nonce {
         Nonce(self.spend_key.public_key())
@@ -76,6 +76,13 @@ pub struct MintConfigConsensus {
 pub struct MintConfigPrivate {
     /// Secret keys for blind-signing ecash of varying note denominations
     pub tbs_sks: Tiered<tbs::SecretKeyShare>,
+    /// Secret key for encrypting the key shares of keysets generated at
+    /// runtime before they are stored in the database. It is independent of
+    /// `tbs_sks`, such that a compromise of the genesis keys does not reveal
+    /// the shares of later keysets. Configs generated before keyset rotation
+    /// do not have one, so their guardian can not take part in it.
+    #[serde(default)]
+    pub keyset_encryption_key: Option<[u8; 32]>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable, Hash)]
@@ -1,3 +1,11 @@
 pub const AWAIT_OUTPUT_OUTCOME_ENDPOINT: &str = "await_output_outcome";
 pub const NOTE_SPENT_ENDPOINT: &str = "note_spent";
+pub const NOTES_SPENT_ENDPOINT: &str = "notes_spent";
 pub const BLIND_NONCE_USED_ENDPOINT: &str = "blind_nonce_used";
+pub const AWAIT_HTLC_PREIMAGE_ENDPOINT: &str = "await_htlc_preimage";
+pub const CONSENSUS_SESSION_COUNT_ENDPOINT: &str = "consensus_session_count";
+pub const HTLC_ENDPOINT: &str = "htlc";
+pub const KEYSETS_ENDPOINT: &str = "keysets";
+pub const ROTATE_KEYSET_ENDPOINT: &str = "rotate_keyset";
+pub const MODULE_CONSENSUS_VERSION_ENDPOINT: &str = "module_consensus_version";
+pub const SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT: &str = "supported_module_consensus_version";
@@ -6,20 +6,22 @@
 #![allow(clippy::must_use_candidate)]
 
 use core::fmt;
+use std::collections::BTreeMap;
 use std::hash::Hash;
 
-use bitcoin_hashes::Hash as _;
 use bitcoin_hashes::hex::DisplayHex;
+use bitcoin_hashes::{Hash as _, HashEngine as _, Hmac, HmacEngine, sha256};
 pub use common::{BackupRequest, SignedBackupRequest};
 use config::MintClientConfig;
 use fedimint_core::core::{Decoder, ModuleInstanceId, ModuleKind};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::{CommonModuleInit, ModuleCommon, ModuleConsensusVersion};
 use fedimint_core::{
-    Amount, extensible_associated_module_type, plugin_types_trait_impl_common, secp256k1,
+    Amount, OutPoint, PeerId, Tiered, extensible_associated_module_type,
+    plugin_types_trait_impl_common, secp256k1,
 };
 use serde::{Deserialize, Serialize};
-use tbs::BlindedSignatureShare;
+use tbs::{AggregatePublicKey, BlindedSignatureShare, PublicKeyShare};
 use thiserror::Error;
 use tracing::error;
 
@@ -28,19 +30,76 @@
 pub mod endpoint_constants;
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("mint");
-pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 0);
+pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 3);
+
+/// Module consensus version that introduced notes locked to a recipient
+/// public key, spent via [`MintInputV1`].
+pub const LOCKED_NOTE_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 1);
+
+/// Module consensus version that introduced hash time locked e-cash, created
+/// via [`MintOutputV1`] and spent via [`MintInputV2`], and the session count
+/// votes that determine when a [`HashTimeLock`] expires.
+pub const HTLC_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 2);
+
+/// Module consensus version that introduced the generation of new [`Keyset`]s
+/// at runtime and the issuance of notes under them via [`MintOutputV2`].
+pub const KEYSET_ROTATION_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 3);
+
+/// The maximum number of notes whose spend status can be queried in a single
+/// request to the `notes_spent` endpoint
+pub const MAX_NOTES_SPENT_REQUEST: usize = 10_000;
 
 /// By default, the maximum notes per denomination when change-making for users
 pub const DEFAULT_MAX_NOTES_PER_DENOMINATION: u16 = 3;
 
-/// The mint module currently doesn't define any consensus items and generally
-/// throws an error on encountering one. To allow old clients to still decode
-/// blocks in the future, should we decide to add consensus items, this has to
-/// be an enum with only a default variant.
+/// The number of consensus sessions that notes issued under a superseded
+/// [`Keyset`] remain redeemable for, unless the guardians vote for a different
+/// period when rotating the keyset
+pub const DEFAULT_KEYSET_DEPRECATION_SESSIONS: u64 = 720;
+
+/// The maximum number of amount tiers of a [`Keyset`], which is enough for
+/// powers of two up to the maximum denomination at config generation
+pub const MAX_KEYSET_TIERS: usize = 64;
+
+/// The guardians vote on the number of finished consensus sessions to agree on
+/// when a [`HashTimeLock`] expires and to schedule the generation of new
+/// keysets. A new [`Keyset`] is generated by a distributed key generation that
+/// runs entirely over consensus items once a threshold of guardians voted for
+/// it.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
 pub enum MintConsensusItem {
+    SessionCountVote(u64),
+    KeysetRotationVote(KeysetRotation),
+    /// Publishes the key the peer's shares are encrypted to and commits to
+    /// the hash of the commitments of its deal, as in the first round of the
+    /// key generation at config generation, such that no dealer can choose its
+    /// polynomials depending on the deals of the others
+    KeysetEncryptionKey {
+        keyset_id: KeysetId,
+        key: PublicKeyShare,
+        commitment_hash: sha256::Hash,
+    },
+    KeysetDeal(KeysetDeal),
+    KeysetComplaint {
+        keyset_id: KeysetId,
+        dealer: PeerId,
+    },
+    /// Answers a complaint by revealing the shares the dealer sent to the
+    /// complainer, such that everyone can check them against the dealer's
+    /// commitments
+    KeysetJustification {
+        keyset_id: KeysetId,
+        complainer: PeerId,
+        shares: Tiered<[u8; 32]>,
+    },
+    ModuleConsensusVersion(ModuleConsensusVersion),
     #[encodable_default]
-    Default { variant: u64, bytes: Vec<u8> },
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
 }
 
 impl std::fmt::Display for MintConsensusItem {
@@ -49,6 +108,122 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Identifies a set of mint keys.
+///
+/// The keys of the federation's config form the genesis keyset, later keysets
+/// are generated by the guardians at runtime and numbered consecutively.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    Encodable,
+    Decodable,
+)]
+pub struct KeysetId(pub u64);
+
+impl KeysetId {
+    pub const GENESIS: KeysetId = KeysetId(0);
+
+    #[must_use]
+    pub fn next(self) -> KeysetId {
+        KeysetId(self.0 + 1)
+    }
+}
+
+impl fmt::Display for KeysetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The public keys of a keyset and the consensus sessions it is valid for.
+///
+/// Only the most recent keyset is used to issue notes. Once a keyset has been
+/// superseded its notes remain redeemable until the federation has finished
+/// `expiry_session` consensus sessions, giving clients time to reissue them
+/// under the new keyset.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable)]
+pub struct Keyset {
+    pub tbs_pks: Tiered<AggregatePublicKey>,
+    pub peer_tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+    pub activation_session: u64,
+    pub expiry_session: Option<u64>,
+}
+
+impl Keyset {
+    pub fn is_expired(&self, session_count: u64) -> bool {
+        self.expiry_session
+            .is_some_and(|expiry_session| expiry_session <= session_count)
+    }
+}
+
+/// A guardian's vote for the generation of a new [`Keyset`].
+///
+/// Once the keyset has been created the notes of the keyset it supersedes
+/// remain redeemable for `deprecation_sessions` consensus sessions, the
+/// guardians agree on the median of the periods they voted for. The keyset is
+/// only generated once a threshold of guardians voted for the same `tiers`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct KeysetRotation {
+    pub keyset_id: KeysetId,
+    pub deprecation_sessions: u64,
+    pub tiers: Vec<Amount>,
+}
+
+/// Checks that `tiers` can be used as the amount tiers of a [`Keyset`]: they
+/// have to be sorted without duplicates, include a 1 msat tier such that every
+/// amount can be represented and not exceed [`MAX_KEYSET_TIERS`].
+pub fn validate_keyset_tiers(tiers: &[Amount]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        tiers.first() == Some(&Amount::from_msats(1)),
+        "The smallest amount tier has to be 1 msat"
+    );
+
+    anyhow::ensure!(
+        tiers.windows(2).all(|pair| pair[0] < pair[1]),
+        "Amount tiers have to be sorted without duplicates"
+    );
+
+    anyhow::ensure!(
+        tiers.len() <= MAX_KEYSET_TIERS,
+        "A keyset can not have more than {MAX_KEYSET_TIERS} amount tiers"
+    );
+
+    Ok(())
+}
+
+/// The parameters of the `rotate_keyset` endpoint. Without `tiers` the new
+/// keyset has the amount tiers of the active keyset.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RotateKeysetRequest {
+    pub deprecation_sessions: u64,
+    pub tiers: Option<Vec<Amount>>,
+}
+
+/// A guardian's contribution to the distributed key generation of a new
+/// [`Keyset`].
+///
+/// For every amount tier the dealer commits to the coefficients of a random
+/// polynomial and sends the evaluations of these polynomials at every peer,
+/// encrypted with ChaCha20-Poly1305 under a key derived from the
+/// Diffie-Hellman secret of the `ephemeral_key` and the encryption key the
+/// peer published for this keyset generation. The keyset's secret is the sum
+/// of the constant coefficients of all qualified dealers.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct KeysetDeal {
+    pub keyset_id: KeysetId,
+    pub commitments: Tiered<Vec<PublicKeyShare>>,
+    pub ephemeral_key: PublicKeyShare,
+    pub encrypted_shares: BTreeMap<PeerId, Vec<u8>>,
+}
+
 /// Result of Federation members confirming [`MintOutput`] by contributing
 /// partial signatures via [`MintConsensusItem`]
 ///
@@ -110,6 +285,19 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Whether a note can still be redeemed, as reported by the federation
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum NoteSpendStatus {
+    /// The note was not spent yet and its keyset is still valid
+    Unspent,
+    /// The note was already spent
+    Spent,
+    /// The keyset of the note expired, so the note can not be redeemed whether
+    /// it was spent or not. The spent nonces of an expired keyset are removed
+    /// eventually, so the federation can not tell anymore.
+    Unredeemable,
+}
+
 /// [`Nonce`] but blinded by the user key
 ///
 /// Blinding prevents the Mint from being able to link the transaction spending
@@ -144,12 +332,78 @@ fn decoder() -> Decoder {
     }
 }
 
-extensible_associated_module_type!(MintInput, MintInputV0, UnknownMintInputVariantError);
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum MintInput {
+    V0(MintInputV0),
+    V1(MintInputV1),
+    V2(MintInputV2),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
 
 impl MintInput {
     pub fn new_v0(amount: Amount, note: Note) -> MintInput {
         MintInput::V0(MintInputV0 { amount, note })
     }
+
+    pub fn new_v1(amount: Amount, note: Note, lock: NoteLock) -> MintInput {
+        MintInput::V1(MintInputV1 { amount, note, lock })
+    }
+
+    pub fn new_v2(out_point: OutPoint, witness: HtlcWitness) -> MintInput {
+        MintInput::V2(MintInputV2 { out_point, witness })
+    }
+
+    pub fn maybe_v0_ref(&self) -> Option<&MintInputV0> {
+        match self {
+            MintInput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn ensure_v0_ref(&self) -> Result<&MintInputV0, UnknownMintInputVariantError> {
+        match self {
+            MintInput::V0(v0) => Ok(v0),
+            MintInput::V1(_) => Err(UnknownMintInputVariantError { variant: 1 }),
+            MintInput::V2(_) => Err(UnknownMintInputVariantError { variant: 2 }),
+            MintInput::Default { variant, .. } => {
+                Err(UnknownMintInputVariantError { variant: *variant })
+            }
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown MintInput variant {variant}")]
+pub struct UnknownMintInputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for MintInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            MintInput::V0(inner) => std::fmt::Display::fmt(&inner, f),
+            MintInput::V1(inner) => std::fmt::Display::fmt(&inner, f),
+            MintInput::V2(inner) => std::fmt::Display::fmt(&inner, f),
+            MintInput::Default { variant, .. } => {
+                write!(f, "Unknown variant (variant={variant})")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -164,7 +418,158 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-extensible_associated_module_type!(MintOutput, MintOutputV0, UnknownMintOutputVariantError);
+/// Spends a [`Note`] that is locked to a recipient, authorized by a signature
+/// of the recipient instead of the note's spend key.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintInputV1 {
+    pub amount: Amount,
+    pub note: Note,
+    pub lock: NoteLock,
+}
+
+impl std::fmt::Display for MintInputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Locked Mint Note {}", self.amount)
+    }
+}
+
+/// Locks a [`Note`] to a recipient public key.
+///
+/// The nonce of a locked note is the `refund_key` tweaked with a commitment to
+/// the `recipient`, in the same pay-to-contract fashion as peg-in addresses.
+/// Hence, only the recipient can spend the note via [`MintInputV1`], while the
+/// sender who knows the secret of the `refund_key` can still reclaim the note
+/// via [`MintInputV0`] as long as the recipient has not spent it yet.
+///
+/// The `recipient` is not the static public key the recipient shares with its
+/// senders, but a key derived from it via a secret shared with the
+/// `refund_key`, see [`NoteLock::new`]. Otherwise, the guardians could link
+/// all notes locked to the same recipient once they are spent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct NoteLock {
+    pub refund_key: secp256k1::PublicKey,
+    pub recipient: secp256k1::PublicKey,
+}
+
+impl NoteLock {
+    /// Creates a lock with the given `refund_key` for the recipient with the
+    /// static public key `recipient`. The lock's recipient key is tweaked by
+    /// the ECDH secret of the `refund_key` and `recipient`, such that every
+    /// note is locked to a different key.
+    pub fn new(refund_key: &secp256k1::Keypair, recipient: secp256k1::PublicKey) -> NoteLock {
+        let tweak = Self::recipient_tweak(&recipient, &refund_key.secret_key());
+
+        NoteLock {
+            refund_key: refund_key.public_key(),
+            recipient: recipient
+                .add_exp_tweak(secp256k1::SECP256K1, &tweak)
+                .expect("Tweak is valid with overwhelming probability"),
+        }
+    }
+
+    /// Derives the keypair that can spend a note locked by this lock from the
+    /// static keypair of the recipient, or returns `None` if the note is
+    /// locked to another recipient.
+    pub fn recipient_keypair(&self, recipient: &secp256k1::Keypair) -> Option<secp256k1::Keypair> {
+        let tweak = Self::recipient_tweak(&self.refund_key, &recipient.secret_key());
+
+        let keypair = secp256k1::Keypair::from_secret_key(
+            secp256k1::SECP256K1,
+            &recipient
+                .secret_key()
+                .add_tweak(&tweak)
+                .expect("Tweak is valid with overwhelming probability"),
+        );
+
+        (keypair.public_key() == self.recipient).then_some(keypair)
+    }
+
+    fn recipient_tweak(
+        public_key: &secp256k1::PublicKey,
+        secret_key: &secp256k1::SecretKey,
+    ) -> secp256k1::Scalar {
+        let shared_secret = secp256k1::ecdh::SharedSecret::new(public_key, secret_key);
+
+        secp256k1::Scalar::from_be_bytes(shared_secret.secret_bytes())
+            .expect("Hash is within curve order")
+    }
+
+    /// The tweak that is added to the `refund_key` to derive the nonce
+    pub fn tweak(&self) -> secp256k1::Scalar {
+        let mut engine = HmacEngine::<sha256::Hash>::new(&self.refund_key.serialize());
+        engine.input(&self.recipient.serialize());
+        let tweak = Hmac::from_engine(engine).to_byte_array();
+
+        secp256k1::Scalar::from_be_bytes(tweak).expect("Hash is within curve order")
+    }
+
+    /// The nonce of a note locked by this lock
+    pub fn nonce(&self) -> Nonce {
+        Nonce(
+            self.refund_key
+                .add_exp_tweak(secp256k1::SECP256K1, &self.tweak())
+                .expect("Tweak is valid with overwhelming probability"),
+        )
+    }
+}
+
+/// Claims or refunds the e-cash locked in a [`HashTimeLock`] by a
+/// [`MintOutputV1`] at `out_point`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintInputV2 {
+    pub out_point: OutPoint,
+    pub witness: HtlcWitness,
+}
+
+impl std::fmt::Display for MintInputV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.witness {
+            HtlcWitness::Claim(..) => write!(f, "Claim Hash Time Lock {}", self.out_point),
+            HtlcWitness::Refund => write!(f, "Refund Hash Time Lock {}", self.out_point),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum HtlcWitness {
+    /// Claims the e-cash before the timeout by revealing the preimage
+    Claim([u8; 32]),
+    /// Refunds the e-cash after the timeout
+    Refund,
+}
+
+/// Locks e-cash such that it can either be claimed by the holder of the
+/// `claim_key` by revealing the preimage of the `payment_hash` or refunded to
+/// the holder of the `refund_key` once the federation has finished
+/// `timeout_session` consensus sessions.
+///
+/// Two parties can use hash time locks with the same `payment_hash` on two
+/// different federations to atomically swap e-cash between them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct HashTimeLock {
+    pub payment_hash: sha256::Hash,
+    pub claim_key: secp256k1::PublicKey,
+    pub refund_key: secp256k1::PublicKey,
+    pub timeout_session: u64,
+}
+
+impl HashTimeLock {
+    pub fn verify_preimage(&self, preimage: &[u8; 32]) -> bool {
+        preimage.consensus_hash::<sha256::Hash>() == self.payment_hash
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum MintOutput {
+    V0(MintOutputV0),
+    V1(MintOutputV1),
+    V2(MintOutputV2),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
 
 impl MintOutput {
     pub fn new_v0(amount: Amount, blind_nonce: BlindNonce) -> MintOutput {
@@ -173,6 +578,66 @@ pub fn new_v0(amount: Amount, blind_nonce: BlindNonce) -> MintOutput {
             blind_nonce,
         })
     }
+
+    pub fn new_v1(amount: Amount, htlc: HashTimeLock) -> MintOutput {
+        MintOutput::V1(MintOutputV1 { amount, htlc })
+    }
+
+    pub fn new_v2(amount: Amount, blind_nonce: BlindNonce, keyset_id: KeysetId) -> MintOutput {
+        MintOutput::V2(MintOutputV2 {
+            amount,
+            blind_nonce,
+            keyset_id,
+        })
+    }
+
+    pub fn maybe_v0_ref(&self) -> Option<&MintOutputV0> {
+        match self {
+            MintOutput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn ensure_v0_ref(&self) -> Result<&MintOutputV0, UnknownMintOutputVariantError> {
+        match self {
+            MintOutput::V0(v0) => Ok(v0),
+            MintOutput::V1(_) => Err(UnknownMintOutputVariantError { variant: 1 }),
+            MintOutput::V2(_) => Err(UnknownMintOutputVariantError { variant: 2 }),
+            MintOutput::Default { variant, .. } => {
+                Err(UnknownMintOutputVariantError { variant: *variant })
+            }
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown MintOutput variant {variant}")]
+pub struct UnknownMintOutputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for MintOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            MintOutput::V0(inner) => std::fmt::Display::fmt(&inner, f),
+            MintOutput::V1(inner) => std::fmt::Display::fmt(&inner, f),
+            MintOutput::V2(inner) => std::fmt::Display::fmt(&inner, f),
+            MintOutput::Default { variant, .. } => {
+                write!(f, "Unknown variant (variant={variant})")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -187,6 +652,35 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Locks e-cash of the given amount in a [`HashTimeLock`] until it is claimed
+/// or refunded via [`MintInputV2`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintOutputV1 {
+    pub amount: Amount,
+    pub htlc: HashTimeLock,
+}
+
+impl std::fmt::Display for MintOutputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Hash Time Lock {}", self.amount)
+    }
+}
+
+/// Issues a note under the keyset `keyset_id`, which has to be the keyset the
+/// federation currently issues notes under.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintOutputV2 {
+    pub amount: Amount,
+    pub blind_nonce: BlindNonce,
+    pub keyset_id: KeysetId,
+}
+
+impl std::fmt::Display for MintOutputV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mint Note {} (keyset {})", self.amount, self.keyset_id)
+    }
+}
+
 extensible_associated_module_type!(
     MintOutputOutcome,
     MintOutputOutcomeV0,
@@ -250,6 +744,22 @@ pub enum MintInputError {
     InvalidSignature,
     #[error("The mint input version is not supported by this federation")]
     UnknownInputVariant(#[from] UnknownMintInputVariantError),
+    #[error("The note is not locked by the given lock")]
+    InvalidLock,
+    #[error("No hash time lock exists at the given out point")]
+    UnknownHashTimeLock,
+    #[error("The preimage does not match the payment hash of the hash time lock")]
+    InvalidPreimage,
+    #[error("The hash time lock has expired and can only be refunded")]
+    HashTimeLockExpired,
+    #[error("The hash time lock has not expired yet")]
+    HashTimeLockNotExpired,
+    #[error("The note was issued under a keyset that has expired")]
+    ExpiredKeyset,
+    #[error("Locked notes are not supported by this federation yet")]
+    LockedNotesNotSupported,
+    #[error("Hash time locks are not supported by this federation yet")]
+    HashTimeLocksNotSupported,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Error, Encodable, Decodable)]
@@ -260,4 +770,12 @@ pub enum MintOutputError {
     UnknownOutputVariant(#[from] UnknownMintOutputVariantError),
     #[error("The mint output blind nonce was already used before")]
     BlindNonceAlreadyUsed,
+    #[error("The hash time lock does not lock any e-cash")]
+    ZeroAmountHashTimeLock,
+    #[error("The federation does not issue notes under keyset {0}")]
+    InactiveKeyset(KeysetId),
+    #[error("Hash time locks are not supported by this federation yet")]
+    HashTimeLocksNotSupported,
+    #[error("Keyset rotation is not supported by this federation yet")]
+    KeysetRotationNotSupported,
 }
@@ -1,8 +1,14 @@
+use fedimint_core::bitcoin::hashes::sha256;
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::{Amount, OutPoint, impl_db_lookup, impl_db_record};
-use fedimint_mint_common::{BlindNonce, MintOutputOutcome, Nonce};
+use fedimint_core::module::ModuleConsensusVersion;
+use fedimint_core::{Amount, OutPoint, PeerId, Tiered, impl_db_lookup, impl_db_record};
+use fedimint_mint_common::{
+    BlindNonce, Keyset, KeysetDeal, KeysetId, KeysetRotation, MintOutputOutcome, MintOutputV1,
+    Nonce,
+};
 use serde::Serialize;
 use strum_macros::EnumIter;
+use tbs::{PublicKeyShare, SecretKeyShare};
 
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
@@ -12,6 +18,21 @@ pub enum DbKeyPrefix {
     MintAuditItem = 0x14,
     // 0x15 was previously used for e-cash backups, but removed in DB migration 1
     BlindNonce = 0x16,
+    Htlc = 0x17,
+    HtlcPreimage = 0x18,
+    SessionCountVote = 0x19,
+    Keyset = 0x1a,
+    KeysetSecret = 0x1b,
+    KeysetRotationRequest = 0x1c,
+    KeysetRotationVote = 0x1d,
+    KeysetDkg = 0x1e,
+    KeysetDeal = 0x1f,
+    KeysetDealShares = 0x20,
+    KeysetComplaint = 0x21,
+    NonceCompaction = 0x22,
+    KeysetEncryptionKey = 0x23,
+    KeysetDkgSecret = 0x24,
+    ConsensusVersionVote = 0x25,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -20,7 +41,17 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     }
 }
 
-/// Index for all the spent e-cash note nonces to prevent double spends.
+/// A consensus encoded secret encrypted under a key derived from the dedicated
+/// keyset encryption key, which is only stored in the private config that is
+/// encrypted with the guardian's password. Hence, a copy of the database alone
+/// does not reveal the secret key shares of keysets generated at runtime.
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct EncryptedSecret(pub Vec<u8>);
+
+/// Index for all the spent e-cash note nonces to prevent double spends, maps
+/// to the keyset that was active when the note was spent. The note was issued
+/// under this keyset or an older one, so once it has expired its nonces are
+/// removed, since their notes are rejected before their nonce is checked.
 /// **Extremely safety critical!**
 #[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Serialize)]
 pub struct NonceKey(pub Nonce);
@@ -30,13 +61,14 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 
 impl_db_record!(
     key = NonceKey,
-    value = (),
+    value = KeysetId,
     db_prefix = DbKeyPrefix::NoteNonce,
 );
 impl_db_lookup!(key = NonceKey, query_prefix = NonceKeyPrefix);
 
-/// Index for all the previously used blind nonces. Just a safety net for
-/// clients to not accidentally burn money.
+/// Index for all the previously used blind nonces, maps to the keyset the note
+/// was issued under. Just a safety net for clients to not accidentally burn
+/// money, so it is compacted together with the spent nonces.
 #[derive(Debug, Encodable, Decodable, Serialize)]
 pub struct BlindNonceKey(pub BlindNonce);
 
@@ -45,7 +77,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 
 impl_db_record!(
     key = BlindNonceKey,
-    value = (),
+    value = KeysetId,
     db_prefix = DbKeyPrefix::BlindNonce,
 );
 impl_db_lookup!(key = BlindNonceKey, query_prefix = BlindNonceKeyPrefix);
@@ -75,6 +107,7 @@ pub enum MintAuditItemKey {
     IssuanceTotal,
     Redemption(NonceKey),
     RedemptionTotal,
+    HtlcRedemption(OutPoint),
 }
 
 #[derive(Debug, Encodable, Decodable)]
@@ -89,3 +122,295 @@ pub enum MintAuditItemKey {
     key = MintAuditItemKey,
     query_prefix = MintAuditItemKeyPrefix
 );
+
+/// The hash time locks that have neither been claimed nor refunded yet
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct HtlcKey(pub OutPoint);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct HtlcPrefix;
+
+impl_db_record!(
+    key = HtlcKey,
+    value = MintOutputV1,
+    db_prefix = DbKeyPrefix::Htlc,
+);
+impl_db_lookup!(key = HtlcKey, query_prefix = HtlcPrefix);
+
+/// The preimages revealed by claiming a hash time lock, so the counterparty of
+/// an atomic swap can claim the other side of it
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct HtlcPreimageKey(pub OutPoint);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct HtlcPreimagePrefix;
+
+impl_db_record!(
+    key = HtlcPreimageKey,
+    value = [u8; 32],
+    db_prefix = DbKeyPrefix::HtlcPreimage,
+    notify_on_modify = true
+);
+impl_db_lookup!(key = HtlcPreimageKey, query_prefix = HtlcPreimagePrefix);
+
+#[derive(Debug, Encodable, Decodable, Serialize)]
+pub struct SessionCountVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct SessionCountVotePrefix;
+
+impl_db_record!(
+    key = SessionCountVoteKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::SessionCountVote,
+);
+impl_db_lookup!(
+    key = SessionCountVoteKey,
+    query_prefix = SessionCountVotePrefix
+);
+
+#[derive(Debug, Encodable, Decodable, Serialize)]
+pub struct ConsensusVersionVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct ConsensusVersionVotePrefix;
+
+impl_db_record!(
+    key = ConsensusVersionVoteKey,
+    value = ModuleConsensusVersion,
+    db_prefix = DbKeyPrefix::ConsensusVersionVote,
+);
+impl_db_lookup!(
+    key = ConsensusVersionVoteKey,
+    query_prefix = ConsensusVersionVotePrefix
+);
+
+/// The keysets generated at runtime. The genesis keyset is only stored once it
+/// has been superseded, until then it is derived from the config.
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetKey(pub KeysetId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetPrefix;
+
+impl_db_record!(
+    key = KeysetKey,
+    value = Keyset,
+    db_prefix = DbKeyPrefix::Keyset,
+);
+impl_db_lookup!(key = KeysetKey, query_prefix = KeysetPrefix);
+
+/// Our encrypted secret key shares of the keysets generated at runtime. A
+/// keyset has no entry if we did not receive valid shares from all qualified
+/// dealers.
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetSecretKey(pub KeysetId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetSecretPrefix;
+
+impl_db_record!(
+    key = KeysetSecretKey,
+    value = EncryptedSecret,
+    db_prefix = DbKeyPrefix::KeysetSecret,
+);
+impl_db_lookup!(key = KeysetSecretKey, query_prefix = KeysetSecretPrefix);
+
+/// Set by our guardian's admin to vote for the generation of a new keyset
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetRotationRequestKey;
+
+impl_db_record!(
+    key = KeysetRotationRequestKey,
+    value = KeysetRotation,
+    db_prefix = DbKeyPrefix::KeysetRotationRequest,
+);
+
+#[derive(Debug, Encodable, Decodable, Serialize)]
+pub struct KeysetRotationVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct KeysetRotationVotePrefix;
+
+impl_db_record!(
+    key = KeysetRotationVoteKey,
+    value = KeysetRotation,
+    db_prefix = DbKeyPrefix::KeysetRotationVote,
+);
+impl_db_lookup!(
+    key = KeysetRotationVoteKey,
+    query_prefix = KeysetRotationVotePrefix
+);
+
+/// The distributed key generation of a new keyset that is currently running
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetDkgKey;
+
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct KeysetDkg {
+    pub keyset_id: KeysetId,
+    /// The consensus session count at which the key generation started
+    pub start_session: u64,
+    /// The number of consensus sessions the notes of the superseded keyset
+    /// remain redeemable for
+    pub deprecation_sessions: u64,
+    /// The amount tiers of the new keyset
+    pub tiers: Vec<Amount>,
+}
+
+impl_db_record!(
+    key = KeysetDkgKey,
+    value = KeysetDkg,
+    db_prefix = DbKeyPrefix::KeysetDkg,
+);
+
+/// The encryption key a peer published for the generation of a keyset, deals
+/// encrypt the peer's shares to it
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetEncryptionKeyKey(pub KeysetId, pub PeerId);
+
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetEncryptionKey {
+    pub key: PublicKeyShare,
+    /// The hash of the commitments of the peer's deal, a deal that does not
+    /// match it is rejected
+    pub commitment_hash: sha256::Hash,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetEncryptionKeyPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetEncryptionKeyKeysetPrefix(pub KeysetId);
+
+impl_db_record!(
+    key = KeysetEncryptionKeyKey,
+    value = KeysetEncryptionKey,
+    db_prefix = DbKeyPrefix::KeysetEncryptionKey,
+);
+impl_db_lookup!(
+    key = KeysetEncryptionKeyKey,
+    query_prefix = KeysetEncryptionKeyPrefix,
+    query_prefix = KeysetEncryptionKeyKeysetPrefix
+);
+
+/// Our encrypted [`KeysetDkgSecret`] for the generation of a keyset, created
+/// when we first take part in it and kept until the keyset has been created
+/// such that we can decrypt our shares and answer complaints across restarts
+#[derive(Debug, Clone, Copy, Encodable, Decodable)]
+pub struct KeysetDkgSecretKey(pub KeysetId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetDkgSecretPrefix;
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct KeysetDkgSecret {
+    /// The secret of the encryption key we publish for the keyset
+    pub encryption_key: SecretKeyShare,
+    /// The coefficients of the polynomials we deal, per amount tier
+    pub polynomials: Tiered<Vec<SecretKeyShare>>,
+}
+
+impl_db_record!(
+    key = KeysetDkgSecretKey,
+    value = EncryptedSecret,
+    db_prefix = DbKeyPrefix::KeysetDkgSecret,
+);
+impl_db_lookup!(
+    key = KeysetDkgSecretKey,
+    query_prefix = KeysetDkgSecretPrefix
+);
+
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetDealKey(pub KeysetId, pub PeerId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetDealPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetDealKeysetPrefix(pub KeysetId);
+
+impl_db_record!(
+    key = KeysetDealKey,
+    value = KeysetDeal,
+    db_prefix = DbKeyPrefix::KeysetDeal,
+);
+impl_db_lookup!(
+    key = KeysetDealKey,
+    query_prefix = KeysetDealPrefix,
+    query_prefix = KeysetDealKeysetPrefix
+);
+
+/// The shares we decrypted from a dealer's deal, stored encrypted, or `None` if
+/// they were invalid and we have to complain about the dealer until it reveals
+/// them
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetDealSharesKey(pub KeysetId, pub PeerId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetDealSharesPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetDealSharesKeysetPrefix(pub KeysetId);
+
+impl_db_record!(
+    key = KeysetDealSharesKey,
+    value = Option<EncryptedSecret>,
+    db_prefix = DbKeyPrefix::KeysetDealShares,
+);
+impl_db_lookup!(
+    key = KeysetDealSharesKey,
+    query_prefix = KeysetDealSharesPrefix,
+    query_prefix = KeysetDealSharesKeysetPrefix
+);
+
+/// A complaint of a peer about the dealer of a deal, keyed by keyset, dealer
+/// and complaining peer. The value records whether the dealer has answered the
+/// complaint with valid shares, otherwise the dealer is disqualified.
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeysetComplaintKey(pub KeysetId, pub PeerId, pub PeerId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetComplaintPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeysetComplaintKeysetPrefix(pub KeysetId);
+
+impl_db_record!(
+    key = KeysetComplaintKey,
+    value = bool,
+    db_prefix = DbKeyPrefix::KeysetComplaint,
+);
+impl_db_lookup!(
+    key = KeysetComplaintKey,
+    query_prefix = KeysetComplaintPrefix,
+    query_prefix = KeysetComplaintKeysetPrefix
+);
+
+/// Records that the nonces of an expired keyset have been removed
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct NonceCompactionKey(pub KeysetId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct NonceCompactionPrefix;
+
+/// How many entries the compaction of an expired keyset's nonces removed
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct NonceCompaction {
+    /// The consensus session count at which the nonces were removed
+    pub session: u64,
+    pub spent_nonces: u64,
+    pub blind_nonces: u64,
+    /// The size of the removed keys and values in bytes
+    pub reclaimed_bytes: u64,
+}
+
+impl_db_record!(
+    key = NonceCompactionKey,
+    value = NonceCompaction,
+    db_prefix = DbKeyPrefix::NonceCompaction,
+);
+impl_db_lookup!(
+    key = NonceCompactionKey,
+    query_prefix = NonceCompactionPrefix
+);
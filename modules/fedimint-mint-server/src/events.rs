@@ -0,0 +1,86 @@
+use fedimint_core::core::ModuleKind;
+use fedimint_core::{Amount, OutPoint};
+use fedimint_eventlog::{Event, EventKind};
+use fedimint_mint_common::Nonce;
+use serde::{Deserialize, Serialize};
+
+/// Event that is emitted when an e-cash note is issued by a transaction output
+#[derive(Serialize, Deserialize)]
+pub struct NoteIssued {
+    /// The output issuing the note
+    pub out_point: OutPoint,
+
+    /// The denomination of the note
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for NoteIssued {
+    const MODULE: Option<ModuleKind> = Some(fedimint_mint_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("note-issued");
+}
+
+/// Event that is emitted when an e-cash note is redeemed by a transaction input
+#[derive(Serialize, Deserialize)]
+pub struct NoteRedeemed {
+    /// The nonce of the note, which is now marked as spent
+    pub nonce: Nonce,
+
+    /// The denomination of the note
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for NoteRedeemed {
+    const MODULE: Option<ModuleKind> = Some(fedimint_mint_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("note-redeemed");
+}
+
+/// Event that is emitted when e-cash is locked in a hash time lock by a
+/// transaction output
+#[derive(Serialize, Deserialize)]
+pub struct HashTimeLockCreated {
+    /// The output creating the hash time lock
+    pub out_point: OutPoint,
+
+    /// The amount being locked
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for HashTimeLockCreated {
+    const MODULE: Option<ModuleKind> = Some(fedimint_mint_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("hash-time-lock-created");
+}
+
+/// Event that is emitted when a hash time lock is claimed with its preimage or
+/// refunded after its timeout by a transaction input
+#[derive(Serialize, Deserialize)]
+pub struct HashTimeLockSpent {
+    /// The output that created the hash time lock
+    pub out_point: OutPoint,
+
+    /// The amount being unlocked
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+
+    /// Whether the hash time lock was claimed rather than refunded
+    pub claimed: bool,
+}
+
+impl Event for HashTimeLockSpent {
+    const MODULE: Option<ModuleKind> = Some(fedimint_mint_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("hash-time-lock-spent");
+}
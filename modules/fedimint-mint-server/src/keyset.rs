@@ -0,0 +1,282 @@
+//! Distributed generation of new keysets over consensus items.
+//!
+//! The key generation follows the one we run at config generation: every
+//! guardian deals a random polynomial per amount tier, first committing to the
+//! hash of the commitments to its coefficients, such that it can not choose
+//! its polynomials depending on the deals of the others, and then publishing
+//! these commitments and the evaluations of its polynomials at every peer,
+//! which every peer verifies against them.
+//!
+//! Unlike at config generation we can neither send the shares over private
+//! connections nor assume that every guardian cooperates. Hence, every
+//! guardian publishes an encryption key along with its hash commitment and the
+//! shares are encrypted to it with ChaCha20-Poly1305. Since consensus is a
+//! reliable broadcast channel, a guardian that receives an invalid share can
+//! complain publicly. The dealer answers the complaint by revealing the
+//! complainer's shares, which everyone checks against its commitments, and is
+//! only disqualified if it does not. The keyset is only created if enough
+//! dealers remain such that at least one of them is honest, otherwise its
+//! secret could be known to the dishonest guardians.
+
+use std::collections::BTreeMap;
+
+use fedimint_aead::LessSafeKey;
+use fedimint_core::bitcoin::hashes::sha256;
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::{Amount, PeerId, Tiered};
+use fedimint_derive_secret::{ChildId, DerivableSecret};
+use fedimint_mint_common::{KeysetDeal, KeysetId};
+use fedimint_server_core::config::{eval_poly_g2, eval_poly_scalar, g2, scalar};
+use rand::rngs::OsRng;
+use tbs::{AggregatePublicKey, PublicKeyShare, SecretKeyShare};
+use threshold_crypto::ff::Field;
+use threshold_crypto::group::Curve;
+use threshold_crypto::{G2Affine, G2Projective, Scalar};
+
+const KEYSET_DEAL_TAG: &[u8] = b"fedimint-mint-keyset-deal";
+
+/// Creates the random polynomials of degree `threshold - 1` we deal for every
+/// amount tier in `tiers`.
+pub fn random_polynomials(tiers: &[Amount], threshold: usize) -> Tiered<Vec<SecretKeyShare>> {
+    tiers
+        .iter()
+        .map(|amount| {
+            let polynomial = (0..threshold)
+                .map(|_| SecretKeyShare(Scalar::random(&mut OsRng)))
+                .collect();
+
+            (*amount, polynomial)
+        })
+        .collect()
+}
+
+/// The hash of the commitments to the coefficients of our `polynomials`, which
+/// we publish before our deal
+pub fn commitment_hash(polynomials: &Tiered<Vec<SecretKeyShare>>) -> sha256::Hash {
+    commitments(polynomials).consensus_hash_sha256()
+}
+
+/// Creates our deal of `polynomials` for the keyset `keyset_id`, encrypting
+/// the shares of every peer to its `encryption_keys` entry.
+pub fn deal(
+    keyset_id: KeysetId,
+    polynomials: &Tiered<Vec<SecretKeyShare>>,
+    encryption_keys: &BTreeMap<PeerId, PublicKeyShare>,
+) -> KeysetDeal {
+    let ephemeral_secret = Scalar::random(&mut OsRng);
+
+    let encrypted_shares = encryption_keys
+        .iter()
+        .map(|(peer, key)| {
+            let shared_point = (key.0 * ephemeral_secret).to_affine();
+
+            let encrypted_shares = fedimint_aead::encrypt(
+                reveal_shares(polynomials, *peer).consensus_encode_to_vec(),
+                &share_encryption_key(&shared_point, keyset_id, *peer),
+            )
+            .expect("Encryption with a valid key does not fail");
+
+            (*peer, encrypted_shares)
+        })
+        .collect();
+
+    KeysetDeal {
+        keyset_id,
+        commitments: commitments(polynomials),
+        ephemeral_key: PublicKeyShare(g2(&ephemeral_secret).to_affine()),
+        encrypted_shares,
+    }
+}
+
+/// Checks that a deal matches the dealer's `commitment_hash`, has a commitment
+/// of the right degree for every amount tier in `tiers` and shares for exactly
+/// the peers in `recipients`.
+pub fn is_well_formed(
+    deal: &KeysetDeal,
+    commitment_hash: sha256::Hash,
+    tiers: &[Amount],
+    recipients: &[PeerId],
+    threshold: usize,
+) -> bool {
+    deal.commitments.consensus_hash_sha256() == commitment_hash
+        && deal.commitments.tiers().eq(tiers.iter())
+        && deal
+            .commitments
+            .iter()
+            .all(|(_, commitment)| commitment.len() == threshold)
+        && deal.encrypted_shares.keys().eq(recipients.iter())
+}
+
+/// The unencrypted shares of `peer` for our `polynomials`, which we reveal to
+/// answer a complaint.
+pub fn reveal_shares(polynomials: &Tiered<Vec<SecretKeyShare>>, peer: PeerId) -> Tiered<[u8; 32]> {
+    polynomials
+        .iter()
+        .map(|(amount, polynomial)| {
+            let coefficients = polynomial.iter().map(|c| c.0).collect::<Vec<_>>();
+
+            (
+                amount,
+                eval_poly_scalar(&coefficients, &scalar(&peer)).to_bytes(),
+            )
+        })
+        .collect()
+}
+
+/// Decrypts the shares the dealer sent to `peer` and verifies them against the
+/// dealer's commitments, returns `None` if any of them is invalid.
+pub fn decrypt_shares(
+    deal: &KeysetDeal,
+    peer: PeerId,
+    decryption_key: &SecretKeyShare,
+) -> Option<Tiered<SecretKeyShare>> {
+    let shared_point = (deal.ephemeral_key.0 * decryption_key.0).to_affine();
+
+    let mut ciphertext = deal.encrypted_shares.get(&peer)?.clone();
+
+    let plaintext = fedimint_aead::decrypt(
+        &mut ciphertext,
+        &share_encryption_key(&shared_point, deal.keyset_id, peer),
+    )
+    .ok()?;
+
+    let shares =
+        Tiered::<[u8; 32]>::consensus_decode_whole(plaintext, &ModuleDecoderRegistry::default())
+            .ok()?;
+
+    verify_shares(deal, peer, &shares)
+}
+
+/// Verifies the unencrypted shares of `peer` against the dealer's
+/// commitments, returns `None` if any of them is invalid or missing.
+pub fn verify_shares(
+    deal: &KeysetDeal,
+    peer: PeerId,
+    shares: &Tiered<[u8; 32]>,
+) -> Option<Tiered<SecretKeyShare>> {
+    if !shares.structural_eq(&deal.commitments) {
+        return None;
+    }
+
+    shares
+        .iter()
+        .map(|(amount, share)| {
+            let share = Option::<Scalar>::from(Scalar::from_bytes(share))?;
+
+            let commitment = deal
+                .commitments
+                .get(amount)?
+                .iter()
+                .map(|coefficient| G2Projective::from(coefficient.0))
+                .collect::<Vec<_>>();
+
+            if eval_poly_g2(&commitment, &peer) != g2(&share).to_affine() {
+                return None;
+            }
+
+            Some((amount, SecretKeyShare(share)))
+        })
+        .collect()
+}
+
+/// Sums up the commitments of the qualified deals to obtain the public keys of
+/// the new keyset.
+pub fn combine_public_keys(
+    deals: &[KeysetDeal],
+    tiers: &[Amount],
+    peers: &[PeerId],
+) -> (
+    Tiered<AggregatePublicKey>,
+    BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+) {
+    let commitments = tiers
+        .iter()
+        .map(|amount| {
+            let commitment = deals
+                .iter()
+                .map(|deal| {
+                    deal.commitments
+                        .get(*amount)
+                        .expect("Deals have been checked to be well formed")
+                        .iter()
+                        .map(|coefficient| G2Projective::from(coefficient.0))
+                        .collect::<Vec<_>>()
+                })
+                .reduce(|sum, commitment| {
+                    sum.into_iter()
+                        .zip(commitment)
+                        .map(|(a, b)| a + b)
+                        .collect()
+                })
+                .expect("We have at least one qualified deal");
+
+            (*amount, commitment)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let tbs_pks = commitments
+        .iter()
+        .map(|(amount, commitment)| (*amount, AggregatePublicKey(commitment[0].to_affine())))
+        .collect();
+
+    let peer_tbs_pks = peers
+        .iter()
+        .map(|peer| {
+            let pks = commitments
+                .iter()
+                .map(|(amount, commitment)| {
+                    (*amount, PublicKeyShare(eval_poly_g2(commitment, peer)))
+                })
+                .collect();
+
+            (*peer, pks)
+        })
+        .collect();
+
+    (tbs_pks, peer_tbs_pks)
+}
+
+/// Sums up the shares we received from the qualified dealers to obtain our
+/// secret key share of the new keyset.
+pub fn combine_secret_keys(shares: &[Tiered<SecretKeyShare>]) -> Tiered<SecretKeyShare> {
+    shares
+        .iter()
+        .cloned()
+        .reduce(|sum, shares| {
+            sum.iter()
+                .map(|(amount, share)| {
+                    let other = shares.get(amount).expect("Shares cover the same tiers");
+
+                    (amount, SecretKeyShare(share.0 + other.0))
+                })
+                .collect()
+        })
+        .expect("We have at least one qualified deal")
+}
+
+fn commitments(polynomials: &Tiered<Vec<SecretKeyShare>>) -> Tiered<Vec<PublicKeyShare>> {
+    polynomials
+        .iter()
+        .map(|(amount, polynomial)| {
+            let commitment = polynomial
+                .iter()
+                .map(|coefficient| PublicKeyShare(g2(&coefficient.0).to_affine()))
+                .collect();
+
+            (amount, commitment)
+        })
+        .collect()
+}
+
+/// The key the shares of `peer` for the keyset `keyset_id` are encrypted
+/// with, derived from the Diffie-Hellman secret of the dealer's ephemeral key
+/// and the peer's encryption key
+fn share_encryption_key(shared_point: &G2Affine, keyset_id: KeysetId, peer: PeerId) -> LessSafeKey {
+    LessSafeKey::new(
+        DerivableSecret::new_root(&shared_point.to_compressed(), KEYSET_DEAL_TAG)
+            .child_key(ChildId(keyset_id.0))
+            .child_key(ChildId(peer.to_usize() as u64))
+            .to_chacha20_poly1305_key(),
+    )
+}
@@ -5,30 +5,43 @@
 #![allow(clippy::similar_names)]
 
 pub mod db;
+pub mod events;
+mod keyset;
 mod metrics;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail, ensure};
+use fedimint_aead::LessSafeKey;
+use fedimint_api_client::api::{DynModuleApi, FederationApiExt as _};
 use fedimint_core::config::{
     ConfigGenModuleParams, ServerModuleConfig, ServerModuleConsensusConfig,
     TypedServerModuleConfig, TypedServerModuleConsensusConfig,
 };
 use fedimint_core::core::ModuleInstanceId;
 use fedimint_core::db::{
-    DatabaseTransaction, DatabaseVersion, IDatabaseTransactionOpsCore,
-    IDatabaseTransactionOpsCoreTyped,
+    Database, DatabaseKey as _, DatabaseKeyPrefix as _, DatabaseTransaction, DatabaseVersion,
+    IDatabaseTransactionOpsCore, IDatabaseTransactionOpsCoreTyped,
 };
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::envs::is_running_in_test_env;
+use fedimint_core::module::ApiRequestErased;
 use fedimint_core::module::audit::Audit;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::module::{
-    ApiEndpoint, ApiVersion, CORE_CONSENSUS_VERSION, CoreConsensusVersion, InputMeta,
+    ApiEndpoint, ApiError, ApiVersion, CORE_CONSENSUS_VERSION, CoreConsensusVersion, InputMeta,
     ModuleConsensusVersion, ModuleInit, SupportedModuleApiVersions, TransactionItemAmount,
     api_endpoint,
 };
+use fedimint_core::task::{TaskGroup, sleep, timeout};
+use fedimint_core::util::FmtCompact as _;
 use fedimint_core::{
-    Amount, InPoint, NumPeersExt, OutPoint, PeerId, Tiered, TieredMulti, apply,
-    async_trait_maybe_send, push_db_key_items, push_db_pair_items,
+    Amount, InPoint, NumPeers, NumPeersExt, OutPoint, PeerId, Tiered, TieredMulti, apply,
+    async_trait_maybe_send, push_db_pair_items,
 };
+use fedimint_derive_secret::DerivableSecret;
 use fedimint_logging::LOG_MODULE_MINT;
 pub use fedimint_mint_common as common;
 use fedimint_mint_common::config::{
@@ -37,22 +50,30 @@
 };
 pub use fedimint_mint_common::{BackupRequest, SignedBackupRequest};
 use fedimint_mint_common::{
-    DEFAULT_MAX_NOTES_PER_DENOMINATION, MODULE_CONSENSUS_VERSION, MintCommonInit,
-    MintConsensusItem, MintInput, MintInputError, MintModuleTypes, MintOutput, MintOutputError,
-    MintOutputOutcome,
+    DEFAULT_MAX_NOTES_PER_DENOMINATION, HTLC_MODULE_CONSENSUS_VERSION, HtlcWitness,
+    KEYSET_ROTATION_MODULE_CONSENSUS_VERSION, Keyset, KeysetDeal, KeysetId, KeysetRotation,
+    LOCKED_NOTE_MODULE_CONSENSUS_VERSION, MAX_NOTES_SPENT_REQUEST, MODULE_CONSENSUS_VERSION,
+    MintCommonInit, MintConsensusItem, MintInput, MintInputError, MintInputV2, MintModuleTypes,
+    MintOutput, MintOutputError, MintOutputOutcome, MintOutputV1, Note, NoteSpendStatus,
+    RotateKeysetRequest, UnknownMintInputVariantError, UnknownMintOutputVariantError,
+    validate_keyset_tiers,
 };
-use fedimint_server_core::config::{PeerHandleOps, eval_poly_g2};
+use fedimint_server_core::config::{PeerHandleOps, eval_poly_g2, eval_poly_scalar};
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::migration::{
     ModuleHistoryItem, ServerModuleDbMigrationFn, ServerModuleDbMigrationFnContext,
     ServerModuleDbMigrationFnContextExt as _,
 };
+use fedimint_server_core::net::check_auth;
 use fedimint_server_core::{ServerModule, ServerModuleInit, ServerModuleInitArgs};
+use futures::future::join_all;
 use futures::{FutureExt as _, StreamExt};
 use itertools::Itertools;
 use metrics::{
     MINT_INOUT_FEES_SATS, MINT_INOUT_SATS, MINT_ISSUED_ECASH_FEES_SATS, MINT_ISSUED_ECASH_SATS,
     MINT_REDEEMED_ECASH_FEES_SATS, MINT_REDEEMED_ECASH_SATS,
 };
+use rand::Rng as _;
 use rand::rngs::OsRng;
 use strum::IntoEnumIterator;
 use tbs::{
@@ -62,14 +83,46 @@
 use threshold_crypto::ff::Field;
 use threshold_crypto::group::Curve;
 use threshold_crypto::{G2Projective, Scalar};
-use tracing::{debug, info, warn};
+use tokio::sync::watch;
+use tracing::{debug, error, info, trace, warn};
 
-use crate::common::endpoint_constants::{BLIND_NONCE_USED_ENDPOINT, NOTE_SPENT_ENDPOINT};
+use crate::common::endpoint_constants::{
+    AWAIT_HTLC_PREIMAGE_ENDPOINT, BLIND_NONCE_USED_ENDPOINT, CONSENSUS_SESSION_COUNT_ENDPOINT,
+    HTLC_ENDPOINT, KEYSETS_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT, NOTE_SPENT_ENDPOINT,
+    NOTES_SPENT_ENDPOINT, ROTATE_KEYSET_ENDPOINT, SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT,
+};
 use crate::common::{BlindNonce, Nonce};
 use crate::db::{
-    BlindNonceKey, BlindNonceKeyPrefix, DbKeyPrefix, MintAuditItemKey, MintAuditItemKeyPrefix,
-    MintOutputOutcomeKey, MintOutputOutcomePrefix, NonceKey, NonceKeyPrefix,
+    BlindNonceKey, BlindNonceKeyPrefix, ConsensusVersionVoteKey, ConsensusVersionVotePrefix,
+    DbKeyPrefix, EncryptedSecret, HtlcKey, HtlcPrefix, HtlcPreimageKey, HtlcPreimagePrefix,
+    KeysetComplaintKey, KeysetComplaintKeysetPrefix, KeysetComplaintPrefix, KeysetDealKey,
+    KeysetDealKeysetPrefix, KeysetDealPrefix, KeysetDealSharesKey, KeysetDealSharesKeysetPrefix,
+    KeysetDkg, KeysetDkgKey, KeysetDkgSecret, KeysetDkgSecretKey, KeysetEncryptionKey,
+    KeysetEncryptionKeyKey, KeysetEncryptionKeyKeysetPrefix, KeysetEncryptionKeyPrefix, KeysetKey,
+    KeysetPrefix, KeysetRotationRequestKey, KeysetRotationVoteKey, KeysetRotationVotePrefix,
+    KeysetSecretKey, MintAuditItemKey, MintAuditItemKeyPrefix, MintOutputOutcomeKey,
+    MintOutputOutcomePrefix, NonceCompaction, NonceCompactionKey, NonceCompactionPrefix, NonceKey,
+    NonceKeyPrefix, SessionCountVoteKey, SessionCountVotePrefix,
 };
+use crate::events::{HashTimeLockCreated, HashTimeLockSpent, NoteIssued, NoteRedeemed};
+
+/// The number of consensus sessions after the start of a keyset generation
+/// during which guardians can publish their encryption keys and commit to
+/// their deals
+const KEYSET_DKG_KEY_SESSIONS: u64 = 1;
+
+/// The number of consensus sessions after the start of a keyset generation
+/// until which guardians can submit their deals
+const KEYSET_DKG_DEAL_SESSIONS: u64 = 3;
+
+/// The number of consensus sessions after the start of a keyset generation
+/// until which guardians can complain about the shares they received
+const KEYSET_DKG_COMPLAINT_SESSIONS: u64 = 4;
+
+/// The number of consensus sessions after the start of a keyset generation
+/// after which the new keyset is created from the deals of all dealers that
+/// answered every complaint against them
+const KEYSET_DKG_SESSIONS: u64 = 5;
 
 #[derive(Debug, Clone)]
 pub struct MintInit;
@@ -89,7 +142,14 @@ async fn dump_database(
         for table in filtered_prefixes {
             match table {
                 DbKeyPrefix::NoteNonce => {
-                    push_db_key_items!(dbtx, NonceKeyPrefix, NonceKey, mint, "Used Coins");
+                    push_db_pair_items!(
+                        dbtx,
+                        NonceKeyPrefix,
+                        NonceKey,
+                        KeysetId,
+                        mint,
+                        "Used Coins"
+                    );
                 }
                 DbKeyPrefix::MintAuditItem => {
                     push_db_pair_items!(
@@ -112,14 +172,114 @@ async fn dump_database(
                     );
                 }
                 DbKeyPrefix::BlindNonce => {
-                    push_db_key_items!(
+                    push_db_pair_items!(
                         dbtx,
                         BlindNonceKeyPrefix,
                         BlindNonceKey,
+                        KeysetId,
                         mint,
                         "Used Blind Nonces"
                     );
                 }
+                DbKeyPrefix::Htlc => {
+                    push_db_pair_items!(
+                        dbtx,
+                        HtlcPrefix,
+                        HtlcKey,
+                        MintOutputV1,
+                        mint,
+                        "Hash Time Locks"
+                    );
+                }
+                DbKeyPrefix::HtlcPreimage => {
+                    push_db_pair_items!(
+                        dbtx,
+                        HtlcPreimagePrefix,
+                        HtlcPreimageKey,
+                        [u8; 32],
+                        mint,
+                        "Hash Time Lock Preimages"
+                    );
+                }
+                DbKeyPrefix::SessionCountVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        SessionCountVotePrefix,
+                        SessionCountVoteKey,
+                        u64,
+                        mint,
+                        "Session Count Votes"
+                    );
+                }
+                DbKeyPrefix::ConsensusVersionVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ConsensusVersionVotePrefix,
+                        ConsensusVersionVoteKey,
+                        ModuleConsensusVersion,
+                        mint,
+                        "Consensus Version Votes"
+                    );
+                }
+                DbKeyPrefix::Keyset => {
+                    push_db_pair_items!(dbtx, KeysetPrefix, KeysetKey, Keyset, mint, "Keysets");
+                }
+                DbKeyPrefix::NonceCompaction => {
+                    push_db_pair_items!(
+                        dbtx,
+                        NonceCompactionPrefix,
+                        NonceCompactionKey,
+                        NonceCompaction,
+                        mint,
+                        "Nonce Compactions"
+                    );
+                }
+                DbKeyPrefix::KeysetRotationVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeysetRotationVotePrefix,
+                        KeysetRotationVoteKey,
+                        KeysetRotation,
+                        mint,
+                        "Keyset Rotation Votes"
+                    );
+                }
+                DbKeyPrefix::KeysetDeal => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeysetDealPrefix,
+                        KeysetDealKey,
+                        KeysetDeal,
+                        mint,
+                        "Keyset Deals"
+                    );
+                }
+                DbKeyPrefix::KeysetComplaint => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeysetComplaintPrefix,
+                        KeysetComplaintKey,
+                        bool,
+                        mint,
+                        "Keyset Complaints"
+                    );
+                }
+                DbKeyPrefix::KeysetEncryptionKey => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeysetEncryptionKeyPrefix,
+                        KeysetEncryptionKeyKey,
+                        KeysetEncryptionKey,
+                        mint,
+                        "Keyset Encryption Keys"
+                    );
+                }
+                // Secret key shares are not dumped
+                DbKeyPrefix::KeysetSecret
+                | DbKeyPrefix::KeysetDkgSecret
+                | DbKeyPrefix::KeysetDealShares
+                | DbKeyPrefix::KeysetRotationRequest
+                | DbKeyPrefix::KeysetDkg => {}
             }
         }
 
@@ -143,12 +303,28 @@ fn supported_api_versions(&self) -> SupportedModuleApiVersions {
                 MODULE_CONSENSUS_VERSION.major,
                 MODULE_CONSENSUS_VERSION.minor,
             ),
-            &[(0, 1)],
+            &[(0, 3)],
         )
     }
 
     async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::Module> {
-        Ok(Mint::new(args.cfg().to_typed()?))
+        let peer_supported_consensus_version = Mint::spawn_peer_supported_consensus_version_task(
+            args.module_api().clone(),
+            args.task_group(),
+            args.our_peer_id(),
+        );
+
+        let mint = Mint::new(
+            args.cfg().to_typed()?,
+            args.db().clone(),
+            args.session_count(),
+            peer_supported_consensus_version,
+            args.event_logger(),
+        );
+
+        mint.load_verification_keysets().await;
+
+        Ok(mint)
     }
 
     fn trusted_dealer_gen(
@@ -199,6 +375,7 @@ fn trusted_dealer_gen(
                             .iter()
                             .map(|amount| (*amount, tbs_keys[amount].2[peer.to_usize()]))
                             .collect(),
+                        keyset_encryption_key: Some(OsRng.r#gen()),
                     },
                 };
                 (peer, config)
@@ -231,6 +408,7 @@ async fn distributed_gen(
                     .iter()
                     .map(|(amount, (_, sks))| (*amount, tbs::SecretKeyShare(*sks)))
                     .collect(),
+                keyset_encryption_key: Some(OsRng.r#gen()),
             },
             consensus: MintConfigConsensus {
                 peer_tbs_pks: peers
@@ -324,6 +502,10 @@ fn get_database_migrations(
             DatabaseVersion(1),
             Box::new(|ctx| migrate_db_v1(ctx).boxed()),
         );
+        migrations.insert(
+            DatabaseVersion(2),
+            Box::new(|ctx| migrate_db_v2(ctx).boxed()),
+        );
         migrations
     }
 
@@ -361,7 +543,7 @@ async fn migrate_db_v0(
     for blind_nonce in blind_nonces {
         if migration_context
             .dbtx()
-            .insert_entry(&BlindNonceKey(blind_nonce), &())
+            .insert_entry(&BlindNonceKey(blind_nonce), &KeysetId::GENESIS)
             .await
             .is_some()
         {
@@ -393,6 +575,116 @@ async fn migrate_db_v1(
     Ok(())
 }
 
+// Record the keyset every spent nonce and used blind nonce belongs to, such that
+// they can be removed once their keyset expires
+async fn migrate_db_v2(
+    mut migration_context: ServerModuleDbMigrationFnContext<'_, Mint>,
+) -> anyhow::Result<()> {
+    let keysets = migration_context
+        .dbtx()
+        .find_by_prefix(&KeysetPrefix)
+        .await
+        .map(|(key, keyset)| (key.0, keyset))
+        .collect::<BTreeMap<KeysetId, Keyset>>()
+        .await;
+
+    // Entries we can not attribute to a keyset are kept until the most recent
+    // keyset expires, which is the conservative choice
+    let latest_keyset_id = keysets.keys().last().copied().unwrap_or(KeysetId::GENESIS);
+
+    let mut nonce_keysets = HashMap::new();
+    let mut blind_nonce_keysets = HashMap::new();
+
+    // If the keyset was never rotated every note was issued under the genesis
+    // keyset and we do not have to look at the history
+    if !keysets.is_empty() {
+        let mut history = migration_context.get_typed_module_history_stream().await;
+
+        while let Some(history_item) = history.next().await {
+            match history_item {
+                ModuleHistoryItem::Input(input) => {
+                    let (amount, note) = match input {
+                        MintInput::V0(input) => (input.amount, input.note),
+                        MintInput::V1(input) => (input.amount, input.note),
+                        _ => continue,
+                    };
+
+                    let keyset_id = keysets.iter().find_map(|(keyset_id, keyset)| {
+                        keyset
+                            .tbs_pks
+                            .get(amount)
+                            .is_some_and(|pk| note.verify(*pk))
+                            .then_some(*keyset_id)
+                    });
+
+                    if let Some(keyset_id) = keyset_id {
+                        nonce_keysets.insert(note.nonce, keyset_id);
+                    }
+                }
+                ModuleHistoryItem::Output(output) => {
+                    if let Ok((_, blind_nonce, keyset_id)) = issuance(&output) {
+                        blind_nonce_keysets.insert(blind_nonce, keyset_id);
+                    }
+                }
+                ModuleHistoryItem::ConsensusItem(_) => {}
+            }
+        }
+    }
+
+    let mut migrated = 0usize;
+
+    for key in legacy_keys(&mut migration_context, DbKeyPrefix::NoteNonce).await {
+        let key = NonceKey::from_bytes(&key, &ModuleDecoderRegistry::default())?;
+        let keyset_id = nonce_keysets
+            .get(&key.0)
+            .copied()
+            .unwrap_or(latest_keyset_id);
+
+        migration_context
+            .dbtx()
+            .insert_entry(&key, &keyset_id)
+            .await;
+
+        migrated += 1;
+    }
+
+    for key in legacy_keys(&mut migration_context, DbKeyPrefix::BlindNonce).await {
+        let key = BlindNonceKey::from_bytes(&key, &ModuleDecoderRegistry::default())?;
+        let keyset_id = blind_nonce_keysets
+            .get(&key.0)
+            .copied()
+            .unwrap_or(latest_keyset_id);
+
+        migration_context
+            .dbtx()
+            .insert_entry(&key, &keyset_id)
+            .await;
+
+        migrated += 1;
+    }
+
+    info!(target: LOG_MODULE_MINT, "Attributed {migrated} nonces to their keyset");
+
+    Ok(())
+}
+
+/// The keys under `prefix` that still have the empty value they were stored
+/// with before nonces were attributed to keysets
+async fn legacy_keys(
+    migration_context: &mut ServerModuleDbMigrationFnContext<'_, Mint>,
+    prefix: DbKeyPrefix,
+) -> Vec<Vec<u8>> {
+    migration_context
+        .dbtx()
+        .raw_find_by_prefix(&[prefix as u8])
+        .await
+        .expect("DB error")
+        .filter(|(_, value)| std::future::ready(value.is_empty()))
+        .map(|(key, _)| key)
+        .collect()
+        .await
+}
+
 fn dealer_keygen(
     threshold: usize,
     keys: usize,
@@ -400,10 +692,10 @@ fn dealer_keygen(
     let mut rng = OsRng; // FIXME: pass rng
     let poly: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(&mut rng)).collect();
 
-    let apk = (G2Projective::generator() * eval_polynomial(&poly, &Scalar::zero())).to_affine();
+    let apk = (G2Projective::generator() * eval_poly_scalar(&poly, &Scalar::zero())).to_affine();
 
     let sks: Vec<SecretKeyShare> = (0..keys)
-        .map(|idx| SecretKeyShare(eval_polynomial(&poly, &Scalar::from(idx as u64 + 1))))
+        .map(|idx| SecretKeyShare(eval_poly_scalar(&poly, &Scalar::from(idx as u64 + 1))))
         .collect();
 
     let pks = sks
@@ -414,21 +706,42 @@ fn dealer_keygen(
     (AggregatePublicKey(apk), pks, sks)
 }
 
-fn eval_polynomial(coefficients: &[Scalar], x: &Scalar) -> Scalar {
-    coefficients
-        .iter()
-        .copied()
-        .rev()
-        .reduce(|acc, coefficient| acc * x + coefficient)
-        .expect("We have at least one coefficient")
+/// Derives the key that encrypts the secrets we store in the database from the
+/// dedicated key in our private config
+fn secret_encryption_key(keyset_encryption_key: &[u8; 32]) -> LessSafeKey {
+    LessSafeKey::new(
+        DerivableSecret::new_root(keyset_encryption_key, b"fedimint-mint-keyset-secrets")
+            .to_chacha20_poly1305_key(),
+    )
 }
 
 /// Federated mint member mint
 #[derive(Debug)]
 pub struct Mint {
     cfg: MintConfig,
+    our_id: PeerId,
     sec_key: Tiered<SecretKeyShare>,
+    /// Encrypts the secrets of the keysets generated at runtime before we
+    /// store them in the database, see [`EncryptedSecret`]. Without it we do
+    /// not take part in the generation of keysets.
+    secret_encryption_key: Option<LessSafeKey>,
     pub_key: HashMap<Amount, AggregatePublicKey>,
+    session_count: watch::Receiver<u64>,
+    /// Maximum consensus version supported by *all* our peers. Used to
+    /// automatically activate new consensus versions as soon as everyone
+    /// upgrades.
+    peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+    db: Database,
+    /// The public keys of all keysets and whether they have expired, mirroring
+    /// the database such that `verify_input` can check note signatures
+    keysets: Arc<RwLock<BTreeMap<KeysetId, VerificationKeyset>>>,
+    event_logger: ServerModuleEventLogger,
+}
+
+#[derive(Debug, Clone)]
+struct VerificationKeyset {
+    tbs_pks: Tiered<AggregatePublicKey>,
+    expired: bool,
 }
 #[apply(async_trait_maybe_send!)]
 impl ServerModule for Mint {
@@ -437,30 +750,390 @@ impl ServerModule for Mint {
 
     async fn consensus_proposal(
         &self,
-        _dbtx: &mut DatabaseTransaction<'_>,
+        dbtx: &mut DatabaseTransaction<'_>,
     ) -> Vec<MintConsensusItem> {
-        Vec::new()
+        let mut items = vec![];
+
+        let active_consensus_version = self.consensus_module_consensus_version(dbtx).await;
+
+        // Guardians that do not support hash time locks yet would reject the vote
+        if HTLC_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            items.push(MintConsensusItem::SessionCountVote(
+                *self.session_count.borrow(),
+            ));
+        }
+
+        if KEYSET_ROTATION_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            let next_keyset_id = self.active_keyset_id(dbtx).await.next();
+
+            if let Some(rotation) = dbtx.get_value(&KeysetRotationRequestKey).await {
+                if rotation.keyset_id == next_keyset_id
+                    && dbtx
+                        .get_value(&KeysetRotationVoteKey(self.our_id))
+                        .await
+                        .as_ref()
+                        != Some(&rotation)
+                {
+                    items.push(MintConsensusItem::KeysetRotationVote(rotation));
+                }
+            }
+
+            if let Some(dkg) = dbtx.get_value(&KeysetDkgKey).await {
+                items.extend(self.keyset_dkg_proposal(dbtx, dkg).await);
+            }
+        }
+
+        // Only vote if the version supported by all peers is higher than the
+        // currently active one
+        if let Some(supported_consensus_version) = *self.peer_supported_consensus_version.borrow() {
+            if active_consensus_version < supported_consensus_version {
+                items.push(MintConsensusItem::ModuleConsensusVersion(
+                    supported_consensus_version,
+                ));
+            }
+        }
+
+        items
     }
 
     async fn process_consensus_item<'a, 'b>(
         &'a self,
-        _dbtx: &mut DatabaseTransaction<'b>,
-        _consensus_item: MintConsensusItem,
-        _peer_id: PeerId,
+        dbtx: &mut DatabaseTransaction<'b>,
+        consensus_item: MintConsensusItem,
+        peer_id: PeerId,
     ) -> anyhow::Result<()> {
-        bail!("Mint does not process consensus items");
+        trace!(target: LOG_MODULE_MINT, ?consensus_item, "Processing consensus item proposal");
+
+        match consensus_item {
+            MintConsensusItem::SessionCountVote(vote) => {
+                ensure!(
+                    HTLC_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Session count votes are not active yet"
+                );
+
+                let current_vote = dbtx
+                    .insert_entry(&SessionCountVoteKey(peer_id), &vote)
+                    .await
+                    .unwrap_or(0);
+
+                ensure!(current_vote < vote, "Session count vote is redundant");
+
+                self.finalize_keyset_dkg(dbtx).await;
+
+                self.compact_expired_keysets(dbtx).await;
+
+                self.update_verification_keysets(dbtx).await;
+
+                Ok(())
+            }
+            MintConsensusItem::KeysetRotationVote(rotation) => {
+                self.ensure_keyset_rotation_active(dbtx).await?;
+
+                let current_vote = dbtx
+                    .insert_entry(&KeysetRotationVoteKey(peer_id), &rotation)
+                    .await;
+
+                ensure!(
+                    current_vote.as_ref() != Some(&rotation),
+                    "Keyset rotation vote is redundant"
+                );
+
+                ensure!(
+                    rotation.keyset_id == self.active_keyset_id(dbtx).await.next(),
+                    "Keyset rotation vote is not for the next keyset"
+                );
+
+                validate_keyset_tiers(&rotation.tiers)?;
+
+                if dbtx.get_value(&KeysetDkgKey).await.is_some() {
+                    return Ok(());
+                }
+
+                let mut deprecation_sessions = dbtx
+                    .find_by_prefix(&KeysetRotationVotePrefix)
+                    .await
+                    .map(|entry| entry.1)
+                    .filter(|vote| {
+                        std::future::ready(
+                            vote.keyset_id == rotation.keyset_id && vote.tiers == rotation.tiers,
+                        )
+                    })
+                    .map(|vote| vote.deprecation_sessions)
+                    .collect::<Vec<u64>>()
+                    .await;
+
+                if deprecation_sessions.len() >= self.num_peers().threshold() {
+                    let start_session = self.consensus_session_count(dbtx).await;
+
+                    // Any threshold of votes contains a majority of correct peers, so
+                    // the median lies within the range they voted for
+                    deprecation_sessions.sort_unstable();
+
+                    let deprecation_sessions = deprecation_sessions[deprecation_sessions.len() / 2];
+
+                    info!(
+                        target: LOG_MODULE_MINT,
+                        keyset_id = %rotation.keyset_id,
+                        %start_session,
+                        %deprecation_sessions,
+                        tiers = rotation.tiers.len(),
+                        "Starting keyset generation"
+                    );
+
+                    dbtx.insert_new_entry(
+                        &KeysetDkgKey,
+                        &KeysetDkg {
+                            keyset_id: rotation.keyset_id,
+                            start_session,
+                            deprecation_sessions,
+                            tiers: rotation.tiers,
+                        },
+                    )
+                    .await;
+                }
+
+                Ok(())
+            }
+            MintConsensusItem::KeysetEncryptionKey {
+                keyset_id,
+                key,
+                commitment_hash,
+            } => {
+                self.ensure_keyset_rotation_active(dbtx).await?;
+
+                let dkg = self.running_keyset_dkg(dbtx, keyset_id).await?;
+
+                ensure!(
+                    self.consensus_session_count(dbtx).await
+                        < dkg.start_session + KEYSET_DKG_KEY_SESSIONS,
+                    "The deadline for encryption keys has passed"
+                );
+
+                ensure!(
+                    dbtx.insert_entry(
+                        &KeysetEncryptionKeyKey(keyset_id, peer_id),
+                        &KeysetEncryptionKey {
+                            key,
+                            commitment_hash,
+                        },
+                    )
+                    .await
+                    .is_none(),
+                    "Encryption key is redundant"
+                );
+
+                Ok(())
+            }
+            MintConsensusItem::KeysetDeal(deal) => {
+                self.ensure_keyset_rotation_active(dbtx).await?;
+
+                let dkg = self.running_keyset_dkg(dbtx, deal.keyset_id).await?;
+
+                let session_count = self.consensus_session_count(dbtx).await;
+
+                ensure!(
+                    dkg.start_session + KEYSET_DKG_KEY_SESSIONS <= session_count,
+                    "Encryption keys are still being published"
+                );
+
+                ensure!(
+                    session_count < dkg.start_session + KEYSET_DKG_DEAL_SESSIONS,
+                    "The deadline for deals has passed"
+                );
+
+                let recipients = self.keyset_encryption_keys(dbtx, dkg.keyset_id).await;
+
+                let encryption_key = dbtx
+                    .get_value(&KeysetEncryptionKeyKey(dkg.keyset_id, peer_id))
+                    .await
+                    .ok_or(anyhow!("Dealer did not commit to its deal"))?;
+
+                ensure!(
+                    keyset::is_well_formed(
+                        &deal,
+                        encryption_key.commitment_hash,
+                        &dkg.tiers,
+                        &recipients.keys().copied().collect::<Vec<_>>(),
+                        self.num_peers().threshold(),
+                    ),
+                    "Deal is malformed"
+                );
+
+                ensure!(
+                    dbtx.insert_entry(&KeysetDealKey(dkg.keyset_id, peer_id), &deal)
+                        .await
+                        .is_none(),
+                    "Deal is redundant"
+                );
+
+                // We can only decrypt our shares if we published an encryption key in time
+                if recipients.contains_key(&self.our_id) {
+                    let secret = dbtx
+                        .get_value(&KeysetDkgSecretKey(dkg.keyset_id))
+                        .await
+                        .map(|secret| self.decrypt_secret::<KeysetDkgSecret>(&secret))
+                        .expect("We only publish an encryption key after storing its secret");
+
+                    let shares = keyset::decrypt_shares(&deal, self.our_id, &secret.encryption_key);
+
+                    if shares.is_none() {
+                        warn!(
+                            target: LOG_MODULE_MINT,
+                            keyset_id = %dkg.keyset_id,
+                            dealer = %peer_id,
+                            "Received invalid keyset shares"
+                        );
+                    }
+
+                    dbtx.insert_new_entry(
+                        &KeysetDealSharesKey(dkg.keyset_id, peer_id),
+                        &shares.map(|shares| self.encrypt_secret(&shares)),
+                    )
+                    .await;
+                }
+
+                Ok(())
+            }
+            MintConsensusItem::KeysetComplaint { keyset_id, dealer } => {
+                self.ensure_keyset_rotation_active(dbtx).await?;
+
+                let dkg = self.running_keyset_dkg(dbtx, keyset_id).await?;
+
+                ensure!(
+                    self.consensus_session_count(dbtx).await
+                        < dkg.start_session + KEYSET_DKG_COMPLAINT_SESSIONS,
+                    "The deadline for complaints has passed"
+                );
+
+                ensure!(
+                    dbtx.get_value(&KeysetDealKey(keyset_id, dealer))
+                        .await
+                        .is_some(),
+                    "Complaint is about a dealer without a deal"
+                );
+
+                ensure!(
+                    dbtx.get_value(&KeysetEncryptionKeyKey(keyset_id, peer_id))
+                        .await
+                        .is_some(),
+                    "Complainer did not receive shares"
+                );
+
+                ensure!(
+                    dbtx.insert_entry(&KeysetComplaintKey(keyset_id, dealer, peer_id), &false)
+                        .await
+                        .is_none(),
+                    "Complaint is redundant"
+                );
+
+                warn!(
+                    target: LOG_MODULE_MINT,
+                    %keyset_id,
+                    %dealer,
+                    complainer = %peer_id,
+                    "Peer complained about the shares of a keyset dealer"
+                );
+
+                Ok(())
+            }
+            MintConsensusItem::KeysetJustification {
+                keyset_id,
+                complainer,
+                shares,
+            } => {
+                self.ensure_keyset_rotation_active(dbtx).await?;
+
+                let dkg = self.running_keyset_dkg(dbtx, keyset_id).await?;
+
+                ensure!(
+                    self.consensus_session_count(dbtx).await
+                        < dkg.start_session + KEYSET_DKG_SESSIONS,
+                    "The deadline for justifications has passed"
+                );
+
+                ensure!(
+                    dbtx.get_value(&KeysetComplaintKey(keyset_id, peer_id, complainer))
+                        .await
+                        == Some(false),
+                    "Justification does not answer an open complaint"
+                );
+
+                let deal = dbtx
+                    .get_value(&KeysetDealKey(keyset_id, peer_id))
+                    .await
+                    .expect("Complaints are only accepted about dealers with a deal");
+
+                let shares = keyset::verify_shares(&deal, complainer, &shares)
+                    .ok_or(anyhow!("Justification is invalid"))?;
+
+                dbtx.insert_entry(&KeysetComplaintKey(keyset_id, peer_id, complainer), &true)
+                    .await;
+
+                if complainer == self.our_id {
+                    dbtx.insert_entry(
+                        &KeysetDealSharesKey(keyset_id, peer_id),
+                        &Some(self.encrypt_secret(&shares)),
+                    )
+                    .await;
+                }
+
+                info!(
+                    target: LOG_MODULE_MINT,
+                    %keyset_id,
+                    dealer = %peer_id,
+                    %complainer,
+                    "Keyset dealer answered a complaint"
+                );
+
+                Ok(())
+            }
+            MintConsensusItem::ModuleConsensusVersion(module_consensus_version) => {
+                let current_vote = dbtx
+                    .get_value(&ConsensusVersionVoteKey(peer_id))
+                    .await
+                    .unwrap_or(ModuleConsensusVersion::new(2, 0));
+
+                ensure!(
+                    module_consensus_version > current_vote,
+                    "Module consensus version vote is redundant"
+                );
+
+                dbtx.insert_entry(&ConsensusVersionVoteKey(peer_id), &module_consensus_version)
+                    .await;
+
+                assert!(
+                    self.consensus_module_consensus_version(dbtx).await <= MODULE_CONSENSUS_VERSION,
+                    "Mint module does not support new consensus version, please upgrade the module"
+                );
+
+                Ok(())
+            }
+            MintConsensusItem::Default { variant, .. } => {
+                bail!("Received consensus item with unknown variant {variant}")
+            }
+        }
     }
 
     fn verify_input(&self, input: &MintInput) -> Result<(), MintInputError> {
-        let input = input.ensure_v0_ref()?;
+        let (amount, note) = match input {
+            MintInput::V0(input) => (input.amount, input.note),
+            MintInput::V1(input) => (input.amount, input.note),
+            // Hash time locks can only be verified against the database
+            MintInput::V2(_) => return Ok(()),
+            MintInput::Default { variant, .. } => {
+                return Err(MintInputError::UnknownInputVariant(
+                    UnknownMintInputVariantError { variant: *variant },
+                ));
+            }
+        };
 
-        let amount_key = self
-            .pub_key
-            .get(&input.amount)
-            .ok_or(MintInputError::InvalidAmountTier(input.amount))?;
+        self.verify_note_signature(amount, &note)?;
 
-        if !input.note.verify(*amount_key) {
-            return Err(MintInputError::InvalidSignature);
+        if let MintInput::V1(input) = input {
+            if input.lock.nonce() != note.nonce {
+                return Err(MintInputError::InvalidLock);
+            }
         }
 
         Ok(())
@@ -472,32 +1145,75 @@ async fn process_input<'a, 'b, 'c>(
         input: &'b MintInput,
         _in_point: InPoint,
     ) -> Result<InputMeta, MintInputError> {
-        let input = input.ensure_v0_ref()?;
+        let (amount, note) = match input {
+            MintInput::V0(input) => (input.amount, input.note),
+            MintInput::V1(input) => {
+                if self.consensus_module_consensus_version(dbtx).await
+                    < LOCKED_NOTE_MODULE_CONSENSUS_VERSION
+                {
+                    return Err(MintInputError::LockedNotesNotSupported);
+                }
+
+                (input.amount, input.note)
+            }
+            MintInput::V2(input) => {
+                if self.consensus_module_consensus_version(dbtx).await
+                    < HTLC_MODULE_CONSENSUS_VERSION
+                {
+                    return Err(MintInputError::HashTimeLocksNotSupported);
+                }
+
+                return self.process_htlc_input(dbtx, input).await;
+            }
+            MintInput::Default { variant, .. } => {
+                return Err(MintInputError::UnknownInputVariant(
+                    UnknownMintInputVariantError { variant: *variant },
+                ));
+            }
+        };
+
+        // The note was issued under the active keyset or an earlier one, which is
+        // all we need to know to remove its nonce once its keyset has expired
+        let keyset_id = self.active_keyset_id(dbtx).await;
 
-        debug!(target: LOG_MODULE_MINT, nonce=%(input.note.nonce), "Marking note as spent");
+        debug!(target: LOG_MODULE_MINT, nonce=%(note.nonce), "Marking note as spent");
 
         if dbtx
-            .insert_entry(&NonceKey(input.note.nonce), &())
+            .insert_entry(&NonceKey(note.nonce), &keyset_id)
             .await
             .is_some()
         {
             return Err(MintInputError::SpentCoin);
         }
 
-        dbtx.insert_new_entry(
-            &MintAuditItemKey::Redemption(NonceKey(input.note.nonce)),
-            &input.amount,
-        )
-        .await;
+        dbtx.insert_new_entry(&MintAuditItemKey::Redemption(NonceKey(note.nonce)), &amount)
+            .await;
 
-        let amount = input.amount;
         let fee = self.cfg.consensus.fee_consensus.fee(amount);
 
         calculate_mint_redeemed_ecash_metrics(dbtx, amount, fee);
 
+        self.event_logger
+            .log_event(
+                dbtx,
+                NoteRedeemed {
+                    nonce: note.nonce,
+                    amount,
+                    fee,
+                },
+            )
+            .await;
+
+        // A locked note is spent by its recipient, the lock has been checked to
+        // match the note's nonce in `verify_input`
+        let pub_key = match input {
+            MintInput::V1(input) => input.lock.recipient,
+            _ => *note.spend_key(),
+        };
+
         Ok(InputMeta {
             amount: TransactionItemAmount { amount, fee },
-            pub_key: *input.note.spend_key(),
+            pub_key,
         })
     }
 
@@ -507,41 +1223,106 @@ async fn process_output<'a, 'b>(
         output: &'a MintOutput,
         out_point: OutPoint,
     ) -> Result<TransactionItemAmount, MintOutputError> {
-        let output = output.ensure_v0_ref()?;
+        if let MintOutput::V1(output) = output {
+            if self.consensus_module_consensus_version(dbtx).await < HTLC_MODULE_CONSENSUS_VERSION {
+                return Err(MintOutputError::HashTimeLocksNotSupported);
+            }
 
-        let amount_key = self
-            .sec_key
-            .get(output.amount)
-            .ok_or(MintOutputError::InvalidAmountTier(output.amount))?;
+            if output.amount == Amount::ZERO {
+                return Err(MintOutputError::ZeroAmountHashTimeLock);
+            }
 
-        dbtx.insert_new_entry(
-            &MintOutputOutcomeKey(out_point),
-            &MintOutputOutcome::new_v0(sign_message(output.blind_nonce.0, *amount_key)),
-        )
-        .await;
+            dbtx.insert_new_entry(&HtlcKey(out_point), output).await;
+
+            dbtx.insert_new_entry(&MintAuditItemKey::Issuance(out_point), &output.amount)
+                .await;
+
+            let fee = self.cfg.consensus.fee_consensus.fee(output.amount);
+
+            self.event_logger
+                .log_event(
+                    dbtx,
+                    HashTimeLockCreated {
+                        out_point,
+                        amount: output.amount,
+                        fee,
+                    },
+                )
+                .await;
+
+            return Ok(TransactionItemAmount {
+                amount: output.amount,
+                fee,
+            });
+        }
 
-        dbtx.insert_new_entry(&MintAuditItemKey::Issuance(out_point), &output.amount)
+        if let MintOutput::V2(_) = output {
+            self.ensure_keyset_outputs_active(dbtx).await?;
+        }
+
+        let (amount, blind_nonce, keyset_id) = issuance(output)?;
+
+        if keyset_id != self.active_keyset_id(dbtx).await {
+            return Err(MintOutputError::InactiveKeyset(keyset_id));
+        }
+
+        if !self.keyset_has_tier(dbtx, keyset_id, amount).await {
+            return Err(MintOutputError::InvalidAmountTier(amount));
+        }
+
+        // If we did not obtain our key share of the keyset we can not contribute a
+        // signature share, the client combines the shares of the other guardians
+        if let Some(sec_key) = self.keyset_secret(dbtx, keyset_id).await {
+            let amount_key = sec_key
+                .tier(&amount)
+                .expect("Our key share covers the tiers of its keyset");
+
+            dbtx.insert_new_entry(
+                &MintOutputOutcomeKey(out_point),
+                &MintOutputOutcome::new_v0(sign_message(blind_nonce.0, *amount_key)),
+            )
+            .await;
+        } else {
+            error!(
+                target: LOG_MODULE_MINT,
+                %keyset_id,
+                %out_point,
+                "We have no key share for the active keyset, can not sign the output"
+            );
+        }
+
+        dbtx.insert_new_entry(&MintAuditItemKey::Issuance(out_point), &amount)
             .await;
 
         if dbtx
-            .insert_entry(&BlindNonceKey(output.blind_nonce), &())
+            .insert_entry(&BlindNonceKey(blind_nonce), &keyset_id)
             .await
             .is_some()
         {
             // TODO: make a consensus rule against this
             warn!(
                 target: LOG_MODULE_MINT,
-                denomination = %output.amount,
-                bnonce = ?output.blind_nonce,
+                denomination = %amount,
+                bnonce = ?blind_nonce,
                 "Blind nonce already used, money was burned!"
             );
         }
 
-        let amount = output.amount;
         let fee = self.cfg.consensus.fee_consensus.fee(amount);
 
         calculate_mint_issued_ecash_metrics(dbtx, amount, fee);
 
+        self.event_logger
+            .log_event(
+                dbtx,
+                NoteIssued {
+                    out_point,
+                    amount,
+                    fee,
+                },
+            )
+            .await;
+
         Ok(TransactionItemAmount { amount, fee })
     }
 
@@ -560,16 +1341,28 @@ async fn verify_output_submission<'a, 'b>(
         output: &'a MintOutput,
         _out_point: OutPoint,
     ) -> Result<(), MintOutputError> {
-        let output = output.ensure_v0_ref()?;
+        if let MintOutput::V1(_) = output {
+            if self.consensus_module_consensus_version(dbtx).await < HTLC_MODULE_CONSENSUS_VERSION {
+                return Err(MintOutputError::HashTimeLocksNotSupported);
+            }
 
-        if dbtx
-            .get_value(&BlindNonceKey(output.blind_nonce))
-            .await
-            .is_some()
-        {
+            return Ok(());
+        }
+
+        if let MintOutput::V2(_) = output {
+            self.ensure_keyset_outputs_active(dbtx).await?;
+        }
+
+        let (_, blind_nonce, keyset_id) = issuance(output)?;
+
+        if dbtx.get_value(&BlindNonceKey(blind_nonce)).await.is_some() {
             return Err(MintOutputError::BlindNonceAlreadyUsed);
         }
 
+        if keyset_id != self.active_keyset_id(dbtx).await {
+            return Err(MintOutputError::InactiveKeyset(keyset_id));
+        }
+
         Ok(())
     }
 
@@ -589,7 +1382,9 @@ async fn audit(
                     MintAuditItemKey::Issuance(_) | MintAuditItemKey::IssuanceTotal => {
                         issuances += amount;
                     }
-                    MintAuditItemKey::Redemption(_) | MintAuditItemKey::RedemptionTotal => {
+                    MintAuditItemKey::Redemption(_)
+                    | MintAuditItemKey::HtlcRedemption(_)
+                    | MintAuditItemKey::RedemptionTotal => {
                         redemptions += amount;
                     }
                 }
@@ -616,9 +1411,9 @@ async fn audit(
                     MintAuditItemKey::Issuance(_) | MintAuditItemKey::IssuanceTotal => {
                         -(v.msats as i64)
                     }
-                    MintAuditItemKey::Redemption(_) | MintAuditItemKey::RedemptionTotal => {
-                        v.msats as i64
-                    }
+                    MintAuditItemKey::Redemption(_)
+                    | MintAuditItemKey::HtlcRedemption(_)
+                    | MintAuditItemKey::RedemptionTotal => v.msats as i64,
                 },
             )
             .await;
@@ -630,7 +1425,36 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                 NOTE_SPENT_ENDPOINT,
                 ApiVersion::new(0, 1),
                 async |_module: &Mint, context, nonce: Nonce| -> bool {
-                    Ok(context.dbtx().get_value(&NonceKey(nonce)).await.is_some())
+                    let mut dbtx = context.dbtx();
+
+                    if dbtx.get_value(&NonceKey(nonce)).await.is_some() {
+                        return Ok(true);
+                    }
+
+                    // Without the keyset of the note we can not tell whether the note is
+                    // unspent or its spent nonce was removed with its expired keyset
+                    if dbtx.find_by_prefix(&NonceCompactionPrefix).await.next().await.is_some() {
+                        return Err(ApiError::bad_request(
+                            "Spent nonces of expired keysets were removed, use the notes_spent endpoint".to_string(),
+                        ));
+                    }
+
+                    Ok(false)
+                }
+            },
+            api_endpoint! {
+                NOTES_SPENT_ENDPOINT,
+                ApiVersion::new(0, 3),
+                async |module: &Mint, context, notes: Vec<(KeysetId, Nonce)>| -> Vec<NoteSpendStatus> {
+                    if notes.len() > MAX_NOTES_SPENT_REQUEST {
+                        return Err(ApiError::bad_request(format!(
+                            "Can not check the spend status of more than {MAX_NOTES_SPENT_REQUEST} notes at once"
+                        )));
+                    }
+
+                    let mut dbtx = context.dbtx();
+
+                    Ok(module.notes_spend_status(&mut dbtx, notes).await)
                 }
             },
             api_endpoint! {
@@ -640,26 +1464,119 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                     Ok(context.dbtx().get_value(&BlindNonceKey(blind_nonce)).await.is_some())
                 }
             },
-        ]
-    }
-}
+            api_endpoint! {
+                CONSENSUS_SESSION_COUNT_ENDPOINT,
+                ApiVersion::new(0, 1),
+                async |module: &Mint, context, _params: ()| -> u64 {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
 
-fn calculate_mint_issued_ecash_metrics(
-    dbtx: &mut DatabaseTransaction<'_>,
-    amount: Amount,
-    fee: Amount,
-) {
-    dbtx.on_commit(move || {
-        MINT_INOUT_SATS
-            .with_label_values(&["outgoing"])
-            .observe(amount.sats_f64());
-        MINT_INOUT_FEES_SATS
-            .with_label_values(&["outgoing"])
-            .observe(fee.sats_f64());
-        MINT_ISSUED_ECASH_SATS.observe(amount.sats_f64());
-        MINT_ISSUED_ECASH_FEES_SATS.observe(fee.sats_f64());
-    });
-}
+                    Ok(module.consensus_session_count(&mut dbtx).await)
+                }
+            },
+            api_endpoint! {
+                HTLC_ENDPOINT,
+                ApiVersion::new(0, 1),
+                async |_module: &Mint, context, out_point: OutPoint| -> Option<MintOutputV1> {
+                    Ok(context.dbtx().get_value(&HtlcKey(out_point)).await)
+                }
+            },
+            api_endpoint! {
+                KEYSETS_ENDPOINT,
+                ApiVersion::new(0, 1),
+                async |module: &Mint, context, _params: ()| -> BTreeMap<KeysetId, Keyset> {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+
+                    Ok(module.keysets(&mut dbtx).await)
+                }
+            },
+            api_endpoint! {
+                ROTATE_KEYSET_ENDPOINT,
+                ApiVersion::new(0, 1),
+                async |module: &Mint, context, request: RotateKeysetRequest| -> KeysetId {
+                    check_auth(context)?;
+
+                    if let Some(tiers) = &request.tiers {
+                        validate_keyset_tiers(tiers)
+                            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+                    }
+
+                    let db = context.db();
+
+                    if module
+                        .consensus_module_consensus_version(&mut db.begin_transaction_nc().await)
+                        .await
+                        < KEYSET_ROTATION_MODULE_CONSENSUS_VERSION
+                    {
+                        return Err(ApiError::bad_request(
+                            "Keyset rotation is not supported by this federation yet".to_string(),
+                        ));
+                    }
+
+                    Ok(module.request_keyset_rotation(db, request).await)
+                }
+            },
+            api_endpoint! {
+                MODULE_CONSENSUS_VERSION_ENDPOINT,
+                ApiVersion::new(0, 2),
+                async |module: &Mint, context, _params: ()| -> ModuleConsensusVersion {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+
+                    Ok(module.consensus_module_consensus_version(&mut dbtx).await)
+                }
+            },
+            api_endpoint! {
+                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT,
+                ApiVersion::new(0, 2),
+                async |_module: &Mint, _context, _params: ()| -> ModuleConsensusVersion {
+                    Ok(MODULE_CONSENSUS_VERSION)
+                }
+            },
+            api_endpoint! {
+                AWAIT_HTLC_PREIMAGE_ENDPOINT,
+                ApiVersion::new(0, 1),
+                async |module: &Mint, context, params: (OutPoint, u64)| -> Option<[u8; 32]> {
+                    let db = context.db();
+
+                    Ok(module.await_htlc_preimage(db, params.0, params.1).await)
+                }
+            },
+        ]
+    }
+}
+
+/// The amount, blind nonce and keyset of an output issuing a note
+fn issuance(output: &MintOutput) -> Result<(Amount, BlindNonce, KeysetId), MintOutputError> {
+    match output {
+        MintOutput::V0(output) => Ok((output.amount, output.blind_nonce, KeysetId::GENESIS)),
+        MintOutput::V2(output) => Ok((output.amount, output.blind_nonce, output.keyset_id)),
+        MintOutput::V1(_) => Err(MintOutputError::UnknownOutputVariant(
+            UnknownMintOutputVariantError { variant: 1 },
+        )),
+        MintOutput::Default { variant, .. } => Err(MintOutputError::UnknownOutputVariant(
+            UnknownMintOutputVariantError { variant: *variant },
+        )),
+    }
+}
+
+fn calculate_mint_issued_ecash_metrics(
+    dbtx: &mut DatabaseTransaction<'_>,
+    amount: Amount,
+    fee: Amount,
+) {
+    dbtx.on_commit(move || {
+        MINT_INOUT_SATS
+            .with_label_values(&["outgoing"])
+            .observe(amount.sats_f64());
+        MINT_INOUT_FEES_SATS
+            .with_label_values(&["outgoing"])
+            .observe(fee.sats_f64());
+        MINT_ISSUED_ECASH_SATS.observe(amount.sats_f64());
+        MINT_ISSUED_ECASH_FEES_SATS.observe(fee.sats_f64());
+    });
+}
 
 fn calculate_mint_redeemed_ecash_metrics(
     dbtx: &mut DatabaseTransaction<'_>,
@@ -686,7 +1603,13 @@ impl Mint {
     /// * If the amount tiers for secret and public keys are inconsistent
     /// * If the pub key belonging to the secret key share is not in the pub key
     ///   list.
-    pub fn new(cfg: MintConfig) -> Mint {
+    pub fn new(
+        cfg: MintConfig,
+        db: Database,
+        session_count: watch::Receiver<u64>,
+        peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+        event_logger: ServerModuleEventLogger,
+    ) -> Mint {
         assert!(cfg.private.tbs_sks.tiers().count() > 0);
 
         // The amount tiers are implicitly provided by the key sets, make sure they are
@@ -726,30 +1649,886 @@ pub fn new(cfg: MintConfig) -> Mint {
         // TODO: the aggregate pks should become part of the MintConfigConsensus as they
         // can be obtained by evaluating the polynomial returned by the DKG at
         // zero
-        let aggregate_pub_keys = TieredMulti::new_aggregate_from_tiered_iter(
-            cfg.consensus.peer_tbs_pks.values().cloned(),
-        )
-        .into_iter()
-        .map(|(amt, keys)| {
-            let keys = (0_u64..)
-                .zip(keys)
-                .take(cfg.consensus.peer_tbs_pks.to_num_peers().threshold())
-                .collect();
+        let aggregate_pub_keys: HashMap<Amount, AggregatePublicKey> =
+            TieredMulti::new_aggregate_from_tiered_iter(
+                cfg.consensus.peer_tbs_pks.values().cloned(),
+            )
+            .into_iter()
+            .map(|(amt, keys)| {
+                let keys = (0_u64..)
+                    .zip(keys)
+                    .take(cfg.consensus.peer_tbs_pks.to_num_peers().threshold())
+                    .collect();
 
-            (amt, aggregate_public_key_shares(&keys))
-        })
-        .collect();
+                (amt, aggregate_public_key_shares(&keys))
+            })
+            .collect();
+
+        let genesis_keyset = VerificationKeyset {
+            tbs_pks: aggregate_pub_keys
+                .iter()
+                .map(|(amount, pk)| (*amount, *pk))
+                .collect(),
+            expired: false,
+        };
 
         Mint {
             cfg: cfg.clone(),
+            our_id,
+            secret_encryption_key: cfg
+                .private
+                .keyset_encryption_key
+                .as_ref()
+                .map(secret_encryption_key),
             sec_key: cfg.private.tbs_sks,
             pub_key: aggregate_pub_keys,
+            session_count,
+            peer_supported_consensus_version,
+            db,
+            keysets: Arc::new(RwLock::new(BTreeMap::from([(
+                KeysetId::GENESIS,
+                genesis_keyset,
+            )]))),
+            event_logger,
         }
     }
 
     pub fn pub_key(&self) -> HashMap<Amount, AggregatePublicKey> {
         self.pub_key.clone()
     }
+
+    async fn process_htlc_input(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        input: &MintInputV2,
+    ) -> Result<InputMeta, MintInputError> {
+        // Removing the hash time lock prevents it from being spent twice
+        let output = dbtx
+            .remove_entry(&HtlcKey(input.out_point))
+            .await
+            .ok_or(MintInputError::UnknownHashTimeLock)?;
+
+        let pub_key = match &input.witness {
+            HtlcWitness::Claim(preimage) => {
+                if output.htlc.timeout_session <= self.consensus_session_count(dbtx).await {
+                    return Err(MintInputError::HashTimeLockExpired);
+                }
+
+                if !output.htlc.verify_preimage(preimage) {
+                    return Err(MintInputError::InvalidPreimage);
+                }
+
+                dbtx.insert_entry(&HtlcPreimageKey(input.out_point), preimage)
+                    .await;
+
+                output.htlc.claim_key
+            }
+            HtlcWitness::Refund => {
+                if output.htlc.timeout_session > self.consensus_session_count(dbtx).await {
+                    return Err(MintInputError::HashTimeLockNotExpired);
+                }
+
+                output.htlc.refund_key
+            }
+        };
+
+        dbtx.insert_new_entry(
+            &MintAuditItemKey::HtlcRedemption(input.out_point),
+            &output.amount,
+        )
+        .await;
+
+        let fee = self.cfg.consensus.fee_consensus.fee(output.amount);
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                HashTimeLockSpent {
+                    out_point: input.out_point,
+                    amount: output.amount,
+                    fee,
+                    claimed: matches!(input.witness, HtlcWitness::Claim(_)),
+                },
+            )
+            .await;
+
+        Ok(InputMeta {
+            amount: TransactionItemAmount {
+                amount: output.amount,
+                fee,
+            },
+            pub_key,
+        })
+    }
+
+    async fn consensus_session_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u64 {
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let mut counts = dbtx
+            .find_by_prefix(&SessionCountVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u64>>()
+            .await;
+
+        counts.sort_unstable();
+
+        counts.reverse();
+
+        // The session count we select guarantees that any threshold of correct peers
+        // can increase the consensus session count and any consensus session count
+        // has been confirmed by a threshold of peers.
+        counts.get(num_peers.threshold() - 1).copied().unwrap_or(0)
+    }
+
+    async fn consensus_module_consensus_version(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> ModuleConsensusVersion {
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let mut versions = dbtx
+            .find_by_prefix(&ConsensusVersionVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<ModuleConsensusVersion>>()
+            .await;
+
+        while versions.len() < num_peers.total() {
+            versions.push(ModuleConsensusVersion::new(2, 0));
+        }
+
+        versions.sort_unstable();
+
+        versions[num_peers.max_evil()]
+    }
+
+    fn spawn_peer_supported_consensus_version_task(
+        api_client: DynModuleApi,
+        task_group: &TaskGroup,
+        our_peer_id: PeerId,
+    ) -> watch::Receiver<Option<ModuleConsensusVersion>> {
+        let (sender, receiver) = watch::channel(None);
+        task_group.spawn_cancellable("fetch-peer-consensus-versions", async move {
+            loop {
+                let request_futures = api_client.all_peers().iter().filter_map(|&peer| {
+                    if peer == our_peer_id {
+                        return None;
+                    }
+
+                    let api_client_inner = api_client.clone();
+                    Some(async move {
+                        api_client_inner
+                            .request_single_peer::<ModuleConsensusVersion>(
+                                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT.to_owned(),
+                                ApiRequestErased::default(),
+                                peer,
+                            )
+                            .await
+                            .inspect_err(|err| {
+                                warn!(
+                                    target: LOG_MODULE_MINT,
+                                    %peer,
+                                    err = %err.fmt_compact(),
+                                    "Failed to fetch consensus version from peer"
+                                );
+                            })
+                            .ok()
+                    })
+                });
+
+                let peer_consensus_versions = join_all(request_futures)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .chain(std::iter::once(MODULE_CONSENSUS_VERSION))
+                    .collect::<Vec<_>>();
+
+                // Peers that did not respond might not support the endpoint yet
+                let all_peers_supported_version =
+                    if peer_consensus_versions.len() == api_client.all_peers().len() {
+                        peer_consensus_versions.into_iter().min()
+                    } else {
+                        None
+                    };
+
+                #[allow(clippy::disallowed_methods)]
+                if sender.send(all_peers_supported_version).is_err() {
+                    warn!(target: LOG_MODULE_MINT, "Failed to send consensus version to watch channel, stopping task");
+                    break;
+                }
+
+                if is_running_in_test_env() {
+                    sleep(Duration::from_secs(5)).await;
+                } else {
+                    sleep(Duration::from_secs(600)).await;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    async fn await_htlc_preimage(
+        &self,
+        db: Database,
+        out_point: OutPoint,
+        timeout_session: u64,
+    ) -> Option<[u8; 32]> {
+        loop {
+            timeout(
+                Duration::from_secs(10),
+                db.wait_key_exists(&HtlcPreimageKey(out_point)),
+            )
+            .await
+            .ok();
+
+            // to avoid race conditions we have to check for the preimage and
+            // the timeout in the same database transaction
+            let mut dbtx = db.begin_transaction_nc().await;
+
+            if let Some(preimage) = dbtx.get_value(&HtlcPreimageKey(out_point)).await {
+                return Some(preimage);
+            }
+
+            if timeout_session <= self.consensus_session_count(&mut dbtx).await {
+                return None;
+            }
+        }
+    }
+
+    fn num_peers(&self) -> NumPeers {
+        self.cfg.consensus.peer_tbs_pks.to_num_peers()
+    }
+
+    fn peers(&self) -> Vec<PeerId> {
+        self.cfg.consensus.peer_tbs_pks.keys().copied().collect()
+    }
+
+    /// The keyset generation that is currently running, if it generates the
+    /// keyset `keyset_id`
+    async fn running_keyset_dkg(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        keyset_id: KeysetId,
+    ) -> anyhow::Result<KeysetDkg> {
+        let dkg = dbtx
+            .get_value(&KeysetDkgKey)
+            .await
+            .ok_or(anyhow!("No keyset generation is running"))?;
+
+        ensure!(
+            dkg.keyset_id == keyset_id,
+            "Consensus item is for a different keyset"
+        );
+
+        Ok(dkg)
+    }
+
+    /// The encryption keys the peers published for the generation of the
+    /// keyset, deals contain shares for exactly these peers
+    async fn keyset_encryption_keys(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        keyset_id: KeysetId,
+    ) -> BTreeMap<PeerId, PublicKeyShare> {
+        dbtx.find_by_prefix(&KeysetEncryptionKeyKeysetPrefix(keyset_id))
+            .await
+            .map(|(key, encryption_key)| (key.1, encryption_key.key))
+            .collect()
+            .await
+    }
+
+    /// Loads our secrets for the generation of the keyset, creating them if
+    /// we take part in it for the first time. Since consensus proposals can
+    /// not write to the database we persist them in a separate transaction.
+    async fn keyset_dkg_secret(&self, keyset_id: KeysetId, tiers: &[Amount]) -> KeysetDkgSecret {
+        let mut dbtx = self.db.begin_transaction().await;
+
+        if let Some(secret) = dbtx.get_value(&KeysetDkgSecretKey(keyset_id)).await {
+            return self.decrypt_secret(&secret);
+        }
+
+        let secret = KeysetDkgSecret {
+            encryption_key: SecretKeyShare(Scalar::random(&mut OsRng)),
+            polynomials: keyset::random_polynomials(tiers, self.num_peers().threshold()),
+        };
+
+        dbtx.insert_new_entry(
+            &KeysetDkgSecretKey(keyset_id),
+            &self.encrypt_secret(&secret),
+        )
+        .await;
+
+        dbtx.commit_tx().await;
+
+        secret
+    }
+
+    /// Our consensus items for the running keyset generation in its current
+    /// phase
+    async fn keyset_dkg_proposal(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        dkg: KeysetDkg,
+    ) -> Vec<MintConsensusItem> {
+        let session_count = self.consensus_session_count(dbtx).await;
+        let keyset_id = dkg.keyset_id;
+
+        let mut items = vec![];
+
+        if session_count < dkg.start_session + KEYSET_DKG_KEY_SESSIONS {
+            // Without a key to protect our shares at rest we sit the generation out,
+            // the keyset is created as long as a threshold of peers take part
+            if self.secret_encryption_key.is_none() {
+                return items;
+            }
+
+            if dbtx
+                .get_value(&KeysetEncryptionKeyKey(keyset_id, self.our_id))
+                .await
+                .is_none()
+            {
+                let secret = self.keyset_dkg_secret(keyset_id, &dkg.tiers).await;
+
+                items.push(MintConsensusItem::KeysetEncryptionKey {
+                    keyset_id,
+                    key: derive_pk_share(&secret.encryption_key),
+                    commitment_hash: keyset::commitment_hash(&secret.polynomials),
+                });
+            }
+
+            return items;
+        }
+
+        // If we did not publish an encryption key in time we do not deal either,
+        // since we would not be able to decrypt our own share
+        let Some(secret) = dbtx.get_value(&KeysetDkgSecretKey(keyset_id)).await else {
+            return items;
+        };
+
+        let secret = self.decrypt_secret::<KeysetDkgSecret>(&secret);
+
+        if session_count < dkg.start_session + KEYSET_DKG_DEAL_SESSIONS
+            && dbtx
+                .get_value(&KeysetDealKey(keyset_id, self.our_id))
+                .await
+                .is_none()
+        {
+            items.push(MintConsensusItem::KeysetDeal(keyset::deal(
+                keyset_id,
+                &secret.polynomials,
+                &self.keyset_encryption_keys(dbtx, keyset_id).await,
+            )));
+        }
+
+        if session_count < dkg.start_session + KEYSET_DKG_COMPLAINT_SESSIONS {
+            let invalid_dealers = dbtx
+                .find_by_prefix(&KeysetDealSharesKeysetPrefix(keyset_id))
+                .await
+                .filter_map(|(key, shares)| async move { shares.is_none().then_some(key.1) })
+                .collect::<Vec<PeerId>>()
+                .await;
+
+            for dealer in invalid_dealers {
+                if dbtx
+                    .get_value(&KeysetComplaintKey(keyset_id, dealer, self.our_id))
+                    .await
+                    .is_none()
+                {
+                    items.push(MintConsensusItem::KeysetComplaint { keyset_id, dealer });
+                }
+            }
+        }
+
+        let open_complaints = dbtx
+            .find_by_prefix(&KeysetComplaintKeysetPrefix(keyset_id))
+            .await
+            .filter_map(|(key, justified)| async move {
+                (key.1 == self.our_id && !justified).then_some(key.2)
+            })
+            .collect::<Vec<PeerId>>()
+            .await;
+
+        for complainer in open_complaints {
+            items.push(MintConsensusItem::KeysetJustification {
+                keyset_id,
+                complainer,
+                shares: keyset::reveal_shares(&secret.polynomials, complainer),
+            });
+        }
+
+        items
+    }
+
+    fn genesis_keyset(&self) -> Keyset {
+        Keyset {
+            tbs_pks: self
+                .pub_key
+                .iter()
+                .map(|(amount, pk)| (*amount, *pk))
+                .collect(),
+            peer_tbs_pks: self.cfg.consensus.peer_tbs_pks.clone(),
+            activation_session: 0,
+            expiry_session: None,
+        }
+    }
+
+    /// All keysets the federation has ever issued notes under, including
+    /// expired ones
+    async fn keysets(&self, dbtx: &mut DatabaseTransaction<'_>) -> BTreeMap<KeysetId, Keyset> {
+        let mut keysets = dbtx
+            .find_by_prefix(&KeysetPrefix)
+            .await
+            .map(|(key, keyset)| (key.0, keyset))
+            .collect::<BTreeMap<_, _>>()
+            .await;
+
+        keysets
+            .entry(KeysetId::GENESIS)
+            .or_insert_with(|| self.genesis_keyset());
+
+        keysets
+    }
+
+    /// The keyset we currently issue notes under
+    async fn active_keyset_id(&self, dbtx: &mut DatabaseTransaction<'_>) -> KeysetId {
+        dbtx.find_by_prefix_sorted_descending(&KeysetPrefix)
+            .await
+            .next()
+            .await
+            .map_or(KeysetId::GENESIS, |(key, _)| key.0)
+    }
+
+    /// Whether the keyset `keyset_id` can issue notes of the amount tier
+    async fn keyset_has_tier(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        keyset_id: KeysetId,
+        amount: Amount,
+    ) -> bool {
+        if keyset_id == KeysetId::GENESIS {
+            return self.pub_key.contains_key(&amount);
+        }
+
+        dbtx.get_value(&KeysetKey(keyset_id))
+            .await
+            .is_some_and(|keyset| keyset.tbs_pks.get(amount).is_some())
+    }
+
+    async fn keyset_secret(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        keyset_id: KeysetId,
+    ) -> Option<Tiered<SecretKeyShare>> {
+        if keyset_id == KeysetId::GENESIS {
+            return Some(self.sec_key.clone());
+        }
+
+        dbtx.get_value(&KeysetSecretKey(keyset_id))
+            .await
+            .map(|secret| self.decrypt_secret(&secret))
+    }
+
+    /// The key we encrypt secrets with, we only store secrets once we take
+    /// part in the generation of a keyset, which requires it
+    fn expect_secret_encryption_key(&self) -> &LessSafeKey {
+        self.secret_encryption_key
+            .as_ref()
+            .expect("We only take part in keyset generations with a secret encryption key")
+    }
+
+    /// Encrypts a secret before we store it in the database
+    fn encrypt_secret<T: Encodable>(&self, secret: &T) -> EncryptedSecret {
+        EncryptedSecret(
+            fedimint_aead::encrypt(
+                secret.consensus_encode_to_vec(),
+                self.expect_secret_encryption_key(),
+            )
+            .expect("Encryption with a valid key does not fail"),
+        )
+    }
+
+    /// Decrypts a secret we stored in the database via [`Mint::encrypt_secret`]
+    fn decrypt_secret<T: Decodable>(&self, secret: &EncryptedSecret) -> T {
+        let mut ciphertext = secret.0.clone();
+
+        let plaintext =
+            fedimint_aead::decrypt(&mut ciphertext, self.expect_secret_encryption_key())
+                .expect("The secret was encrypted under our secret encryption key");
+
+        T::consensus_decode_whole(plaintext, &ModuleDecoderRegistry::default())
+            .expect("The secret was encoded by us")
+    }
+
+    async fn ensure_keyset_rotation_active(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            KEYSET_ROTATION_MODULE_CONSENSUS_VERSION
+                <= self.consensus_module_consensus_version(dbtx).await,
+            "Keyset rotation is not active yet"
+        );
+
+        Ok(())
+    }
+
+    async fn ensure_keyset_outputs_active(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Result<(), MintOutputError> {
+        if self.consensus_module_consensus_version(dbtx).await
+            < KEYSET_ROTATION_MODULE_CONSENSUS_VERSION
+        {
+            return Err(MintOutputError::KeysetRotationNotSupported);
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the note's signature under the keyset it was issued under,
+    /// trying the most recent keysets that have the note's amount tier first
+    fn verify_note_signature(&self, amount: Amount, note: &Note) -> Result<(), MintInputError> {
+        let keysets = self.keysets.read().expect("lock poisoned");
+
+        let mut amount_keys = keysets
+            .values()
+            .rev()
+            .filter_map(|keyset| Some((keyset.tbs_pks.get(amount)?, keyset.expired)))
+            .peekable();
+
+        if amount_keys.peek().is_none() {
+            return Err(MintInputError::InvalidAmountTier(amount));
+        }
+
+        for (amount_key, expired) in amount_keys {
+            if note.verify(*amount_key) {
+                if expired {
+                    return Err(MintInputError::ExpiredKeyset);
+                }
+
+                return Ok(());
+            }
+        }
+
+        Err(MintInputError::InvalidSignature)
+    }
+
+    /// The keysets `verify_input` checks notes against as of the current
+    /// consensus session count
+    async fn verification_keysets(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> BTreeMap<KeysetId, VerificationKeyset> {
+        let session_count = self.consensus_session_count(dbtx).await;
+
+        self.keysets(dbtx)
+            .await
+            .into_iter()
+            .map(|(keyset_id, keyset)| {
+                let keyset = VerificationKeyset {
+                    expired: keyset.is_expired(session_count),
+                    tbs_pks: keyset.tbs_pks,
+                };
+
+                (keyset_id, keyset)
+            })
+            .collect()
+    }
+
+    /// Updates the keysets `verify_input` checks notes against once the
+    /// consensus item that created or expired a keyset has been committed,
+    /// such that the following consensus items are verified against them.
+    async fn update_verification_keysets(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let verification_keysets = self.verification_keysets(dbtx).await;
+        let keysets = self.keysets.clone();
+
+        dbtx.on_commit(move || {
+            *keysets.write().expect("lock poisoned") = verification_keysets;
+        });
+    }
+
+    async fn load_verification_keysets(&self) {
+        let verification_keysets = self
+            .verification_keysets(&mut self.db.begin_transaction_nc().await)
+            .await;
+
+        *self.keysets.write().expect("lock poisoned") = verification_keysets;
+    }
+
+    /// The spend status of the notes with the given keysets and nonces. Notes
+    /// of expired or unknown keysets are reported as unredeemable, since their
+    /// spent nonces might have been removed.
+    async fn notes_spend_status(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        notes: Vec<(KeysetId, Nonce)>,
+    ) -> Vec<NoteSpendStatus> {
+        let session_count = self.consensus_session_count(dbtx).await;
+        let keysets = self.keysets(dbtx).await;
+
+        let mut statuses = Vec::with_capacity(notes.len());
+
+        for (keyset_id, nonce) in notes {
+            let status = if keysets
+                .get(&keyset_id)
+                .is_none_or(|keyset| keyset.is_expired(session_count))
+            {
+                NoteSpendStatus::Unredeemable
+            } else if dbtx.get_value(&NonceKey(nonce)).await.is_some() {
+                NoteSpendStatus::Spent
+            } else {
+                NoteSpendStatus::Unspent
+            };
+
+            statuses.push(status);
+        }
+
+        statuses
+    }
+
+    /// Removes the spent nonces and used blind nonces of every keyset that has
+    /// expired since the last compaction. Notes of an expired keyset are
+    /// rejected in `verify_input` before their nonce is looked up, so this can
+    /// not enable a double spend, but it bounds the nonce set by the notes of
+    /// the keysets that are still valid. The `notes_spent` endpoint reports
+    /// such notes as unredeemable rather than unspent.
+    async fn compact_expired_keysets(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let session_count = self.consensus_session_count(dbtx).await;
+
+        for (keyset_id, keyset) in self.keysets(dbtx).await {
+            if !keyset.is_expired(session_count)
+                || dbtx
+                    .get_value(&NonceCompactionKey(keyset_id))
+                    .await
+                    .is_some()
+            {
+                continue;
+            }
+
+            let mut compaction = NonceCompaction {
+                session: session_count,
+                spent_nonces: 0,
+                blind_nonces: 0,
+                reclaimed_bytes: 0,
+            };
+
+            let nonces = dbtx
+                .find_by_prefix(&NonceKeyPrefix)
+                .await
+                .filter(|(_, nonce_keyset_id)| std::future::ready(*nonce_keyset_id == keyset_id))
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+                .await;
+
+            for key in nonces {
+                dbtx.remove_entry(&key).await;
+
+                compaction.spent_nonces += 1;
+                compaction.reclaimed_bytes +=
+                    (key.to_bytes().len() + keyset_id.consensus_encode_to_vec().len()) as u64;
+            }
+
+            let blind_nonces = dbtx
+                .find_by_prefix(&BlindNonceKeyPrefix)
+                .await
+                .filter(|(_, nonce_keyset_id)| std::future::ready(*nonce_keyset_id == keyset_id))
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+                .await;
+
+            for key in blind_nonces {
+                dbtx.remove_entry(&key).await;
+
+                compaction.blind_nonces += 1;
+                compaction.reclaimed_bytes +=
+                    (key.to_bytes().len() + keyset_id.consensus_encode_to_vec().len()) as u64;
+            }
+
+            info!(
+                target: LOG_MODULE_MINT,
+                %keyset_id,
+                spent_nonces = compaction.spent_nonces,
+                blind_nonces = compaction.blind_nonces,
+                reclaimed_bytes = compaction.reclaimed_bytes,
+                "Compacted the nonces of an expired keyset"
+            );
+
+            dbtx.insert_new_entry(&NonceCompactionKey(keyset_id), &compaction)
+                .await;
+        }
+    }
+
+    /// Votes for the generation of a new keyset, after which the notes of the
+    /// active keyset remain redeemable for `deprecation_sessions` consensus
+    /// sessions, returns its id
+    async fn request_keyset_rotation(
+        &self,
+        db: Database,
+        request: RotateKeysetRequest,
+    ) -> KeysetId {
+        let mut dbtx = db.begin_transaction().await;
+
+        let active_keyset_id = self.active_keyset_id(&mut dbtx.to_ref_nc()).await;
+
+        let tiers = match request.tiers {
+            Some(tiers) => tiers,
+            None => self
+                .keysets(&mut dbtx.to_ref_nc())
+                .await
+                .remove(&active_keyset_id)
+                .expect("The active keyset exists")
+                .tbs_pks
+                .tiers()
+                .copied()
+                .collect(),
+        };
+
+        let keyset_id = active_keyset_id.next();
+
+        dbtx.insert_entry(
+            &KeysetRotationRequestKey,
+            &KeysetRotation {
+                keyset_id,
+                deprecation_sessions: request.deprecation_sessions,
+                tiers,
+            },
+        )
+        .await;
+
+        dbtx.commit_tx().await;
+
+        keyset_id
+    }
+
+    /// Creates the new keyset from the deals of all dealers that answered every
+    /// complaint against them once the deadline for justifications has passed
+    async fn finalize_keyset_dkg(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let Some(dkg) = dbtx.get_value(&KeysetDkgKey).await else {
+            return;
+        };
+
+        let session_count = self.consensus_session_count(dbtx).await;
+
+        if session_count < dkg.start_session + KEYSET_DKG_SESSIONS {
+            return;
+        }
+
+        let keyset_id = dkg.keyset_id;
+
+        let disqualified = dbtx
+            .find_by_prefix(&KeysetComplaintKeysetPrefix(keyset_id))
+            .await
+            .filter(|(_, justified)| std::future::ready(!justified))
+            .map(|(key, _)| key.1)
+            .collect::<BTreeSet<PeerId>>()
+            .await;
+
+        let recipients = self.keyset_encryption_keys(dbtx, keyset_id).await;
+
+        let deals = dbtx
+            .find_by_prefix(&KeysetDealKeysetPrefix(keyset_id))
+            .await
+            .filter(|(key, _)| std::future::ready(!disqualified.contains(&key.1)))
+            .map(|(key, deal)| (key.1, deal))
+            .collect::<BTreeMap<PeerId, KeysetDeal>>()
+            .await;
+
+        let shares = dbtx
+            .find_by_prefix(&KeysetDealSharesKeysetPrefix(keyset_id))
+            .await
+            .map(|(key, shares)| (key.1, shares.map(|shares| self.decrypt_secret(&shares))))
+            .collect::<BTreeMap<PeerId, Option<Tiered<SecretKeyShare>>>>()
+            .await;
+
+        if deals.len() < self.num_peers().one_honest() {
+            warn!(
+                target: LOG_MODULE_MINT,
+                %keyset_id,
+                qualified = deals.len(),
+                "Keyset generation failed, not enough dealers qualified"
+            );
+        } else if recipients.len() < self.num_peers().threshold() {
+            // Only the peers that published an encryption key received shares, so
+            // fewer than a threshold of them could not sign notes
+            warn!(
+                target: LOG_MODULE_MINT,
+                %keyset_id,
+                recipients = recipients.len(),
+                "Keyset generation failed, not enough peers published an encryption key"
+            );
+        } else {
+            let (tbs_pks, peer_tbs_pks) = keyset::combine_public_keys(
+                &deals.values().cloned().collect::<Vec<_>>(),
+                &dkg.tiers,
+                &self.peers(),
+            );
+
+            let active_keyset_id = self.active_keyset_id(dbtx).await;
+
+            let mut active_keyset = self
+                .keysets(dbtx)
+                .await
+                .remove(&active_keyset_id)
+                .expect("The active keyset exists");
+
+            active_keyset.expiry_session = Some(session_count + dkg.deprecation_sessions);
+
+            dbtx.insert_entry(&KeysetKey(active_keyset_id), &active_keyset)
+                .await;
+
+            dbtx.insert_new_entry(
+                &KeysetKey(keyset_id),
+                &Keyset {
+                    tbs_pks,
+                    peer_tbs_pks,
+                    activation_session: session_count,
+                    expiry_session: None,
+                },
+            )
+            .await;
+
+            let our_shares = deals
+                .keys()
+                .map(|dealer| shares.get(dealer).cloned().flatten())
+                .collect::<Option<Vec<_>>>();
+
+            if let Some(our_shares) = our_shares {
+                dbtx.insert_new_entry(
+                    &KeysetSecretKey(keyset_id),
+                    &self.encrypt_secret(&keyset::combine_secret_keys(&our_shares)),
+                )
+                .await;
+            } else {
+                error!(
+                    target: LOG_MODULE_MINT,
+                    %keyset_id,
+                    "We did not receive valid shares from every qualified dealer, we can not sign notes under the new keyset"
+                );
+            }
+
+            info!(
+                target: LOG_MODULE_MINT,
+                %keyset_id,
+                qualified = deals.len(),
+                "Generated new keyset"
+            );
+        }
+
+        dbtx.remove_entry(&KeysetDkgKey).await;
+        dbtx.remove_entry(&KeysetRotationRequestKey).await;
+        dbtx.remove_by_prefix(&KeysetRotationVotePrefix).await;
+        dbtx.remove_by_prefix(&KeysetDealKeysetPrefix(keyset_id))
+            .await;
+        dbtx.remove_by_prefix(&KeysetDealSharesKeysetPrefix(keyset_id))
+            .await;
+        dbtx.remove_by_prefix(&KeysetComplaintKeysetPrefix(keyset_id))
+            .await;
+        dbtx.remove_by_prefix(&KeysetEncryptionKeyKeysetPrefix(keyset_id))
+            .await;
+        dbtx.remove_entry(&KeysetDkgSecretKey(keyset_id)).await;
+    }
 }
 
 #[cfg(test)]
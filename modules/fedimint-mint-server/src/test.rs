@@ -2,17 +2,26 @@
 use fedimint_core::config::{
     ClientModuleConfig, ConfigGenModuleParams, EmptyGenParams, ServerModuleConfig,
 };
-use fedimint_core::db::Database;
 use fedimint_core::db::mem_impl::MemDatabase;
+use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped as _};
+use fedimint_core::encoding::Encodable;
 use fedimint_core::module::ModuleConsensusVersion;
 use fedimint_core::module::registry::ModuleRegistry;
-use fedimint_core::{Amount, BitcoinHash, InPoint, PeerId, TransactionId, secp256k1};
+use fedimint_core::{Amount, BitcoinHash, InPoint, OutPoint, PeerId, TransactionId, secp256k1};
+use fedimint_eventlog::EventLogNamespace;
 use fedimint_mint_common::config::FeeConsensus;
-use fedimint_mint_common::{MintInput, Nonce, Note};
+use fedimint_mint_common::{
+    BlindNonce, HTLC_MODULE_CONSENSUS_VERSION, HashTimeLock, HtlcWitness, KeysetId,
+    LOCKED_NOTE_MODULE_CONSENSUS_VERSION, MODULE_CONSENSUS_VERSION, MintConsensusItem, MintInput,
+    MintInputError, MintOutput, MintOutputError, Nonce, Note, NoteLock, RotateKeysetRequest,
+};
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::{ServerModule, ServerModuleInit};
 use tbs::blind_message;
+use tokio::sync::watch;
 
 use crate::common::config::MintGenParamsConsensus;
+use crate::db::{KeysetComplaintKey, KeysetSecretKey, NonceCompactionKey, NonceKey};
 use crate::{
     Mint, MintConfig, MintConfigConsensus, MintConfigLocal, MintConfigPrivate, MintGenParams,
     MintInit,
@@ -51,26 +60,43 @@ fn build_configs() -> (Vec<ServerModuleConfig>, ClientModuleConfig) {
 fn test_new_panic_without_own_pub_key() {
     let (mint_server_cfg1, _) = build_configs();
     let (mint_server_cfg2, _) = build_configs();
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
 
-    Mint::new(MintConfig {
-        local: MintConfigLocal,
-        consensus: MintConfigConsensus {
-            peer_tbs_pks: mint_server_cfg2[0]
-                .to_typed::<MintConfig>()
-                .unwrap()
-                .consensus
-                .peer_tbs_pks,
-            fee_consensus: FeeConsensus::new(1000).expect("Relative fee is within range"),
-            max_notes_per_denomination: 0,
-        },
-        private: MintConfigPrivate {
-            tbs_sks: mint_server_cfg1[0]
-                .to_typed::<MintConfig>()
-                .unwrap()
-                .private
-                .tbs_sks,
+    Mint::new(
+        MintConfig {
+            local: MintConfigLocal,
+            consensus: MintConfigConsensus {
+                peer_tbs_pks: mint_server_cfg2[0]
+                    .to_typed::<MintConfig>()
+                    .unwrap()
+                    .consensus
+                    .peer_tbs_pks,
+                fee_consensus: FeeConsensus::new(1000).expect("Relative fee is within range"),
+                max_notes_per_denomination: 0,
+            },
+            private: MintConfigPrivate {
+                tbs_sks: mint_server_cfg1[0]
+                    .to_typed::<MintConfig>()
+                    .unwrap()
+                    .private
+                    .tbs_sks,
+                keyset_encryption_key: None,
+            },
         },
-    });
+        db.clone(),
+        watch::channel(0).1,
+        watch::channel(None).1,
+        event_logger(&db),
+    );
+}
+
+fn event_logger(db: &Database) -> ServerModuleEventLogger {
+    ServerModuleEventLogger::new(
+        42,
+        db.with_prefix_module_id(42).1,
+        EventLogNamespace::root(),
+        watch::channel(()).0,
+    )
 }
 
 fn issue_note(
@@ -78,7 +104,12 @@ fn issue_note(
     denomination: Amount,
 ) -> (secp256k1::Keypair, Note) {
     let note_key = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
-    let nonce = Nonce(note_key.public_key());
+    let note = sign_nonce(server_cfgs, denomination, Nonce(note_key.public_key()));
+
+    (note_key, note)
+}
+
+fn sign_nonce(server_cfgs: &[ServerModuleConfig], denomination: Amount, nonce: Nonce) -> Note {
     let message = nonce.to_message();
     let blinding_key = tbs::BlindingKey::random();
     let blind_msg = blind_message(message, blinding_key);
@@ -100,13 +131,20 @@ fn issue_note(
     let blind_signature = tbs::aggregate_signature_shares(&bsig_shares);
     let signature = tbs::unblind_signature(blinding_key, blind_signature);
 
-    (note_key, Note { nonce, signature })
+    Note { nonce, signature }
 }
 
 #[test_log::test(tokio::test)]
 async fn test_detect_double_spends() {
     let (mint_server_cfg, _) = build_configs();
-    let mint = Mint::new(mint_server_cfg[0].to_typed().unwrap());
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+    let mint = Mint::new(
+        mint_server_cfg[0].to_typed().unwrap(),
+        db.with_prefix_module_id(42).0,
+        watch::channel(0).1,
+        watch::channel(None).1,
+        event_logger(&db),
+    );
     let (_, tiered) = mint
         .cfg
         .consensus
@@ -117,7 +155,6 @@ async fn test_detect_double_spends() {
     let (_, note) = issue_note(&mint_server_cfg, highest_denomination);
 
     // Normal spend works
-    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
     let input = MintInput::new_v0(highest_denomination, note);
 
     // Double spend in same session is detected
@@ -145,3 +182,627 @@ async fn test_detect_double_spends() {
         Err(_)
     );
 }
+
+#[test_log::test(tokio::test)]
+async fn test_locked_note_requires_recipient() {
+    let (mint_server_cfg, _) = build_configs();
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+    let mint = Mint::new(
+        mint_server_cfg[0].to_typed().unwrap(),
+        db.with_prefix_module_id(42).0,
+        watch::channel(0).1,
+        watch::channel(None).1,
+        event_logger(&db),
+    );
+    let denomination = Amount::from_msats(1);
+
+    let new_public_key =
+        || secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng()).public_key();
+
+    let lock = NoteLock {
+        refund_key: new_public_key(),
+        recipient: new_public_key(),
+    };
+    let note = sign_nonce(&mint_server_cfg, denomination, lock.nonce());
+
+    mint.verify_input(&MintInput::new_v1(denomination, note, lock))
+        .expect("Note is locked by the lock");
+
+    let other_lock = NoteLock {
+        recipient: new_public_key(),
+        ..lock
+    };
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v1(denomination, note, other_lock)),
+        Err(MintInputError::InvalidLock)
+    );
+
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+
+    let mut dbtx = db.begin_transaction_nc().await;
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v1(denomination, note, lock),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::LockedNotesNotSupported)
+    );
+
+    // All but one guardian have to vote for the new consensus version
+    for peer in 0..MINTS - 1 {
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            MintConsensusItem::ModuleConsensusVersion(LOCKED_NOTE_MODULE_CONSENSUS_VERSION),
+            PeerId::from(peer),
+        )
+        .await
+        .expect("Vote is valid");
+    }
+
+    let input_meta = mint
+        .process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v1(denomination, note, lock),
+            in_point,
+        )
+        .await
+        .expect("Spend of locked e-cash works");
+
+    assert_eq!(input_meta.pub_key, lock.recipient);
+}
+
+#[test_log::test(tokio::test)]
+async fn test_htlc_claim_and_refund() {
+    let (mint_server_cfg, _) = build_configs();
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+    let mint = Mint::new(
+        mint_server_cfg[0].to_typed().unwrap(),
+        db.with_prefix_module_id(42).0,
+        watch::channel(0).1,
+        watch::channel(None).1,
+        event_logger(&db),
+    );
+
+    let new_public_key =
+        || secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng()).public_key();
+
+    let preimage = [42; 32];
+    let htlc = HashTimeLock {
+        payment_hash: preimage.consensus_hash(),
+        claim_key: new_public_key(),
+        refund_key: new_public_key(),
+        timeout_session: 10,
+    };
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+
+    let mut dbtx = db.begin_transaction().await;
+    assert_matches!(
+        mint.process_output(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintOutput::new_v1(Amount::from_sats(1), htlc),
+            OutPoint {
+                txid: TransactionId::all_zeros(),
+                out_idx: 0,
+            },
+        )
+        .await,
+        Err(MintOutputError::HashTimeLocksNotSupported)
+    );
+    assert_matches!(
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            MintConsensusItem::SessionCountVote(1),
+            PeerId::from(0),
+        )
+        .await,
+        Err(_)
+    );
+
+    // All but one guardian have to vote for the new consensus version
+    for peer in 0..MINTS - 1 {
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            MintConsensusItem::ModuleConsensusVersion(HTLC_MODULE_CONSENSUS_VERSION),
+            PeerId::from(peer),
+        )
+        .await
+        .expect("Vote is valid");
+    }
+
+    for out_idx in 0..2 {
+        mint.process_output(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintOutput::new_v1(Amount::from_sats(1), htlc),
+            OutPoint {
+                txid: TransactionId::all_zeros(),
+                out_idx,
+            },
+        )
+        .await
+        .expect("Locking e-cash in a hash time lock works");
+    }
+    dbtx.commit_tx().await;
+
+    let claim_out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 0,
+    };
+
+    let mut dbtx = db.begin_transaction_nc().await;
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(claim_out_point, HtlcWitness::Refund),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::HashTimeLockNotExpired)
+    );
+
+    let mut dbtx = db.begin_transaction_nc().await;
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(claim_out_point, HtlcWitness::Claim([0; 32])),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::InvalidPreimage)
+    );
+
+    let mut dbtx = db.begin_transaction().await;
+    let input_meta = mint
+        .process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(claim_out_point, HtlcWitness::Claim(preimage)),
+            in_point,
+        )
+        .await
+        .expect("Claiming the hash time lock with the preimage works");
+    assert_eq!(input_meta.pub_key, htlc.claim_key);
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(claim_out_point, HtlcWitness::Claim(preimage)),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::UnknownHashTimeLock)
+    );
+    dbtx.commit_tx().await;
+
+    assert_eq!(
+        mint.await_htlc_preimage(db.with_prefix_module_id(42).0, claim_out_point, 10)
+            .await,
+        Some(preimage)
+    );
+
+    // Once a threshold of guardians finished the timeout session the hash time
+    // lock can only be refunded
+    let mut dbtx = db.begin_transaction().await;
+    for peer in 0..4 {
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            MintConsensusItem::SessionCountVote(10),
+            PeerId::from(peer),
+        )
+        .await
+        .expect("Session count vote is valid");
+    }
+    dbtx.commit_tx().await;
+
+    let refund_out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 1,
+    };
+
+    let mut dbtx = db.begin_transaction_nc().await;
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(refund_out_point, HtlcWitness::Claim(preimage)),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::HashTimeLockExpired)
+    );
+
+    let mut dbtx = db.begin_transaction_nc().await;
+    let input_meta = mint
+        .process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(refund_out_point, HtlcWitness::Refund),
+            in_point,
+        )
+        .await
+        .expect("Refunding the expired hash time lock works");
+    assert_eq!(input_meta.pub_key, htlc.refund_key);
+}
+
+struct Guardian {
+    mint: Mint,
+    session_count: watch::Sender<u64>,
+    db: Database,
+}
+
+/// Orders the consensus items proposed by all guardians and processes them at
+/// every guardian, discarding the items that are rejected.
+async fn run_consensus_round(guardians: &[Guardian]) {
+    run_tampered_consensus_round(guardians, |_, _| {}).await;
+}
+
+/// Like [`run_consensus_round`], but lets the test modify the proposed items
+/// before they are processed to simulate a malicious guardian.
+async fn run_tampered_consensus_round(
+    guardians: &[Guardian],
+    tamper: impl Fn(PeerId, &mut MintConsensusItem),
+) {
+    let mut items = vec![];
+
+    for (peer, guardian) in (0_u16..).zip(guardians) {
+        let mut dbtx = guardian.db.begin_transaction_nc().await;
+
+        for mut item in guardian
+            .mint
+            .consensus_proposal(&mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc())
+            .await
+        {
+            tamper(PeerId::from(peer), &mut item);
+            items.push((PeerId::from(peer), item));
+        }
+    }
+
+    for guardian in guardians {
+        for (peer, item) in &items {
+            let mut dbtx = guardian.db.begin_transaction().await;
+
+            if guardian
+                .mint
+                .process_consensus_item(
+                    &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+                    item.clone(),
+                    *peer,
+                )
+                .await
+                .is_ok()
+            {
+                dbtx.commit_tx().await;
+            }
+        }
+    }
+}
+
+async fn advance_session_count(guardians: &[Guardian], session_count: u64) {
+    for guardian in guardians {
+        guardian.session_count.send_replace(session_count);
+    }
+
+    run_consensus_round(guardians).await;
+}
+
+#[allow(deprecated)]
+#[test_log::test(tokio::test)]
+async fn test_keyset_rotation() {
+    let (mint_server_cfg, _) = build_configs();
+    let guardians = mint_server_cfg
+        .iter()
+        .map(|cfg| {
+            let (session_count, receiver) = watch::channel(0);
+            let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+
+            Guardian {
+                mint: Mint::new(
+                    cfg.to_typed().unwrap(),
+                    db.with_prefix_module_id(42).0,
+                    receiver,
+                    watch::channel(Some(MODULE_CONSENSUS_VERSION)).1,
+                    event_logger(&db),
+                ),
+                session_count,
+                db,
+            }
+        })
+        .collect::<Vec<_>>();
+    let denomination = Amount::from_msats(1);
+    let (_, genesis_note) = issue_note(&mint_server_cfg, denomination);
+    let (_, early_note) = issue_note(&mint_server_cfg, denomination);
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+
+    // A note spent before the rotation is attributed to the genesis keyset
+    let mut dbtx = guardians[0].db.begin_transaction().await;
+    guardians[0]
+        .mint
+        .process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v0(denomination, early_note),
+            in_point,
+        )
+        .await
+        .expect("Note is redeemable");
+    dbtx.commit_tx().await;
+
+    // Notes can only be issued under other keysets than the genesis keyset once
+    // keyset rotation is active
+    let mut dbtx = guardians[0].db.begin_transaction_nc().await;
+    assert_matches!(
+        guardians[0]
+            .mint
+            .process_output(
+                &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+                &MintOutput::new_v2(
+                    denomination,
+                    BlindNonce(blind_message(
+                        early_note.nonce.to_message(),
+                        tbs::BlindingKey::random()
+                    )),
+                    KeysetId::GENESIS
+                ),
+                OutPoint {
+                    txid: TransactionId::all_zeros(),
+                    out_idx: 0,
+                },
+            )
+            .await,
+        Err(MintOutputError::KeysetRotationNotSupported)
+    );
+
+    // Everyone votes for the consensus version all guardians support
+    run_consensus_round(&guardians).await;
+
+    // The deprecation period is the median of the first threshold of votes, the
+    // new keyset has its own amount tiers
+    let tiers = vec![Amount::from_msats(1), Amount::from_msats(10)];
+
+    for (deprecation_sessions, guardian) in (1_u64..).map(|i| i * 100).zip(&guardians) {
+        assert_eq!(
+            guardian
+                .mint
+                .request_keyset_rotation(
+                    guardian.db.with_prefix_module_id(42).0,
+                    RotateKeysetRequest {
+                        deprecation_sessions,
+                        tiers: Some(tiers.clone()),
+                    }
+                )
+                .await,
+            KeysetId(1)
+        );
+    }
+
+    // Rotation votes start the key generation, then everyone publishes an
+    // encryption key
+    run_consensus_round(&guardians).await;
+    run_consensus_round(&guardians).await;
+
+    // Once the key phase is over everyone deals, but guardian 0 corrupts the
+    // share for guardian 1
+    advance_session_count(&guardians, 1).await;
+    run_tampered_consensus_round(&guardians, |peer, item| {
+        if let MintConsensusItem::KeysetDeal(deal) = item {
+            if peer == PeerId::from(0) {
+                *deal
+                    .encrypted_shares
+                    .get_mut(&PeerId::from(1))
+                    .expect("Guardian 1 is a recipient")
+                    .last_mut()
+                    .expect("Ciphertext is not empty") ^= 1;
+            }
+        }
+    })
+    .await;
+
+    // Guardian 1 complains and guardian 0 justifies its deal by revealing the
+    // correct shares
+    run_consensus_round(&guardians).await;
+    run_consensus_round(&guardians).await;
+
+    for guardian in &guardians {
+        let mut dbtx = guardian.db.begin_transaction_nc().await;
+        let mut dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+
+        assert_eq!(
+            dbtx.get_value(&KeysetComplaintKey(
+                KeysetId(1),
+                PeerId::from(0),
+                PeerId::from(1)
+            ))
+            .await,
+            Some(true)
+        );
+    }
+
+    // The keyset is created once the deadline for justifications has passed
+    advance_session_count(&guardians, 5).await;
+
+    let mut keysets = vec![];
+    for guardian in &guardians {
+        let mut dbtx = guardian.db.begin_transaction_nc().await;
+        let mut dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+
+        assert_eq!(guardian.mint.active_keyset_id(&mut dbtx).await, KeysetId(1));
+
+        // Our key shares are only stored encrypted
+        let plaintext = guardian
+            .mint
+            .keyset_secret(&mut dbtx, KeysetId(1))
+            .await
+            .expect("Guardian received its key shares")
+            .consensus_encode_to_vec();
+        let ciphertext = dbtx
+            .get_value(&KeysetSecretKey(KeysetId(1)))
+            .await
+            .expect("Key shares are stored")
+            .0;
+        assert!(
+            !ciphertext
+                .windows(plaintext.len())
+                .any(|window| window == plaintext)
+        );
+
+        keysets.push(guardian.mint.keysets(&mut dbtx).await);
+    }
+    assert!(keysets.iter().all(|other| *other == keysets[0]));
+    assert_eq!(keysets[0][&KeysetId::GENESIS].expiry_session, Some(5 + 300));
+    assert!(keysets[0][&KeysetId(1)].tbs_pks.tiers().eq(tiers.iter()));
+
+    // Notes are only issued under the new keyset
+    let spend_key = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+    let nonce = Nonce(spend_key.public_key());
+    let blinding_key = tbs::BlindingKey::random();
+    let blind_nonce = BlindNonce(blind_message(nonce.to_message(), blinding_key));
+    let out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 0,
+    };
+
+    let mut signature_shares = std::collections::BTreeMap::new();
+    for (peer, guardian) in (0_u64..).zip(&guardians) {
+        let mut dbtx = guardian.db.begin_transaction_nc().await;
+        let mut dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+
+        assert_matches!(
+            guardian
+                .mint
+                .process_output(
+                    &mut dbtx,
+                    &MintOutput::new_v0(denomination, blind_nonce),
+                    out_point
+                )
+                .await,
+            Err(MintOutputError::InactiveKeyset(KeysetId::GENESIS))
+        );
+
+        assert_matches!(
+            guardian
+                .mint
+                .process_output(
+                    &mut dbtx,
+                    &MintOutput::new_v2(Amount::from_msats(2), blind_nonce, KeysetId(1)),
+                    out_point
+                )
+                .await,
+            Err(MintOutputError::InvalidAmountTier(_))
+        );
+
+        guardian
+            .mint
+            .process_output(
+                &mut dbtx,
+                &MintOutput::new_v2(denomination, blind_nonce, KeysetId(1)),
+                out_point,
+            )
+            .await
+            .expect("Issuing under the active keyset works");
+
+        let outcome = guardian
+            .mint
+            .output_status(&mut dbtx, out_point)
+            .await
+            .expect("Output outcome exists");
+
+        signature_shares.insert(peer, outcome.ensure_v0_ref().unwrap().0);
+    }
+
+    let signature = tbs::unblind_signature(
+        blinding_key,
+        tbs::aggregate_signature_shares(&signature_shares.into_iter().take(4).collect()),
+    );
+    let note = Note { nonce, signature };
+    assert!(
+        note.verify(
+            *keysets[0][&KeysetId(1)]
+                .tbs_pks
+                .tier(&denomination)
+                .unwrap()
+        )
+    );
+
+    // A guardian that lost its key share does not sign, instead of contributing
+    // an invalid signature share
+    let mut dbtx = guardians[4].db.begin_transaction_nc().await;
+    let mut dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+    dbtx.remove_entry(&KeysetSecretKey(KeysetId(1))).await;
+
+    let out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 1,
+    };
+    guardians[4]
+        .mint
+        .process_output(
+            &mut dbtx,
+            &MintOutput::new_v2(denomination, blind_nonce, KeysetId(1)),
+            out_point,
+        )
+        .await
+        .expect("Output is still accepted");
+    assert!(
+        guardians[4]
+            .mint
+            .output_status(&mut dbtx, out_point)
+            .await
+            .is_none()
+    );
+
+    // Notes of both keysets are redeemable during the deprecation window
+    let mint = &guardians[0].mint;
+    let db = &guardians[0].db;
+
+    for note in [note, genesis_note] {
+        mint.verify_input(&MintInput::new_v0(denomination, note))
+            .expect("Note signature is valid");
+
+        let mut dbtx = db.begin_transaction().await;
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v0(denomination, note),
+            in_point,
+        )
+        .await
+        .expect("Note is redeemable");
+        dbtx.commit_tx().await;
+    }
+
+    advance_session_count(&guardians, 5 + 300).await;
+
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v0(denomination, genesis_note)),
+        Err(MintInputError::ExpiredKeyset)
+    );
+    mint.verify_input(&MintInput::new_v0(denomination, note))
+        .expect("Notes of the active keyset stay valid");
+
+    // The nonces spent while the expired keyset was active have been
+    // compacted, nonces spent later are kept until the keyset active at that
+    // time has expired as well
+    let mut dbtx = db.begin_transaction_nc().await;
+    let mut dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+
+    assert_eq!(dbtx.get_value(&NonceKey(early_note.nonce)).await, None);
+    for nonce in [genesis_note.nonce, note.nonce] {
+        assert_eq!(dbtx.get_value(&NonceKey(nonce)).await, Some(KeysetId(1)));
+    }
+
+    let compaction = dbtx
+        .get_value(&NonceCompactionKey(KeysetId::GENESIS))
+        .await
+        .expect("Expired keyset was compacted");
+    assert_eq!(compaction.session, 5 + 300);
+    assert_eq!(compaction.spent_nonces, 1);
+    assert!(
+        dbtx.get_value(&NonceCompactionKey(KeysetId(1)))
+            .await
+            .is_none()
+    );
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::time::Duration;
 
 use bls12_381::G1Affine;
@@ -18,7 +19,10 @@
     SelectNotesWithAtleastAmount, SpendOOBState,
 };
 use fedimint_mint_common::config::{FeeConsensus, MintGenParams, MintGenParamsConsensus};
-use fedimint_mint_common::{MintInput, MintInputV0, Nonce};
+use fedimint_mint_common::{
+    LOCKED_NOTE_MODULE_CONSENSUS_VERSION, MAX_NOTES_SPENT_REQUEST, MintInput, MintInputV0, Nonce,
+    NoteSpendStatus,
+};
 use fedimint_mint_server::MintInit;
 use fedimint_testing::fixtures::{Fixtures, TIMEOUT};
 use futures::StreamExt;
@@ -138,6 +142,157 @@ async fn sends_ecash_out_of_band() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn sends_locked_ecash_out_of_band() -> anyhow::Result<()> {
+    // All guardians have to be online to activate locked notes
+    let fed = fixtures().new_fed_not_degraded().await;
+    let (client1, client2) = fed.two_clients().await;
+    let client1_dummy_module = client1.get_first_module::<DummyClientModule>()?;
+    let (op, outpoint) = client1_dummy_module.print_money(sats(1000)).await?;
+    client1.await_primary_module_output(op, outpoint).await?;
+
+    let client1_mint = client1.get_first_module::<MintClientModule>()?;
+    let client2_mint = client2.get_first_module::<MintClientModule>()?;
+
+    info!("### WAIT FOR LOCKED NOTES ACTIVATION");
+    for _ in 0..120 {
+        if LOCKED_NOTE_MODULE_CONSENSUS_VERSION
+            <= client1_mint.api.module_consensus_version().await?
+        {
+            break;
+        }
+        sleep_in_test("waiting for consensus version", Duration::from_millis(500)).await;
+    }
+
+    info!("### SPEND LOCKED NOTES");
+    let recipient = client2_mint.note_lock_keypair().public_key();
+    let (op, notes) = client1_mint
+        .spend_notes_locked(sats(750), recipient, TIMEOUT, false, ())
+        .await?;
+    let sub1 = &mut client1_mint.subscribe_spend_notes(op).await?.into_stream();
+    assert_eq!(sub1.ok().await?, SpendOOBState::Created);
+    assert_eq!(notes.total_amount(), sats(750));
+    assert!(notes.notes().is_empty());
+    assert!(client1.get_balance().await <= sats(250));
+
+    // Every note is locked to a different key derived from the recipient's key
+    let lock_keys = notes
+        .locked_notes()
+        .iter_items()
+        .map(|(_, locked_note)| locked_note.lock.recipient)
+        .collect::<BTreeSet<_>>();
+    assert_eq!(lock_keys.len(), notes.locked_notes().count_items());
+    assert!(!lock_keys.contains(&recipient));
+
+    // Only the recipient can reissue the notes
+    assert!(client1_mint.validate_notes(&notes).is_err());
+    assert!(
+        client1_mint
+            .reissue_external_notes(notes.clone(), ())
+            .await
+            .is_err()
+    );
+
+    info!("### REISSUE");
+    let op = client2_mint.reissue_external_notes(notes, ()).await?;
+    let sub2 = client2_mint.subscribe_reissue_external_notes(op).await?;
+    let mut sub2 = sub2.into_stream();
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Created);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Issuing);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Done);
+    assert_eq!(sub1.ok().await?, SpendOOBState::Success);
+
+    assert!(client2.get_balance().await >= sats(750).saturating_sub(EXPECTED_MAXIMUM_FEE));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checks_spend_status_of_notes() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let (client1, client2) = fed.two_clients().await;
+    let client1_dummy_module = client1.get_first_module::<DummyClientModule>()?;
+    let (op, outpoint) = client1_dummy_module.print_money(sats(1000)).await?;
+    client1.await_primary_module_output(op, outpoint).await?;
+
+    let client1_mint = client1.get_first_module::<MintClientModule>()?;
+    let client2_mint = client2.get_first_module::<MintClientModule>()?;
+
+    // Only the first batch of notes is reissued by the recipient
+    let (op, spent_notes) = client1_mint
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, sats(500), TIMEOUT, false, ())
+        .await?;
+    let sub1 = &mut client1_mint.subscribe_spend_notes(op).await?.into_stream();
+    assert_eq!(sub1.ok().await?, SpendOOBState::Created);
+
+    let (_, unspent_notes) = client1_mint
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, sats(200), TIMEOUT, false, ())
+        .await?;
+
+    let op = client2_mint
+        .reissue_external_notes(spent_notes.clone(), ())
+        .await?;
+    let sub2 = &mut client2_mint
+        .subscribe_reissue_external_notes(op)
+        .await?
+        .into_stream();
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Created);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Issuing);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Done);
+    assert_eq!(sub1.ok().await?, SpendOOBState::Success);
+
+    let spent_status = client2_mint.check_notes_spent(&spent_notes).await?;
+    assert_eq!(spent_status.len(), spent_notes.notes().count_items());
+    assert!(
+        spent_status
+            .iter()
+            .all(|(_, _, status)| *status == NoteSpendStatus::Spent)
+    );
+    assert!(
+        client2_mint
+            .check_notes_spent(&unspent_notes)
+            .await?
+            .is_empty()
+    );
+
+    // Query the endpoint directly with the spent and unspent notes in one request
+    let keysets = client1_mint.api.keysets().await?;
+    assert_eq!(keysets.len(), 1);
+    let keyset_id = *keysets.keys().next().expect("Federation has a keyset");
+
+    let notes = spent_notes
+        .notes()
+        .iter_items()
+        .map(|(_, note)| (note.nonce(), NoteSpendStatus::Spent))
+        .chain(
+            unspent_notes
+                .notes()
+                .iter_items()
+                .map(|(_, note)| (note.nonce(), NoteSpendStatus::Unspent)),
+        )
+        .collect::<Vec<_>>();
+
+    let statuses = client1_mint
+        .api
+        .check_notes_spent(notes.iter().map(|(nonce, _)| (keyset_id, *nonce)).collect())
+        .await?;
+    assert_eq!(
+        statuses,
+        notes.iter().map(|(_, status)| *status).collect::<Vec<_>>()
+    );
+
+    // Requests above the cap are rejected by the guardians
+    let (_, nonce) = notes[0];
+    assert!(
+        client1_mint
+            .api
+            .check_notes_spent(vec![(keyset_id, nonce); MAX_NOTES_SPENT_REQUEST + 1])
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn blind_nonce_index() -> anyhow::Result<()> {
     // Print notes for client1
@@ -514,7 +669,8 @@ mod fedimint_migration_tests {
     };
     use fedimint_core::core::OperationId;
     use fedimint_core::db::{
-        Database, DatabaseVersion, DatabaseVersionKeyV0, IDatabaseTransactionOpsCoreTyped,
+        Database, DatabaseKeyPrefix as _, DatabaseVersion, DatabaseVersionKeyV0,
+        IDatabaseTransactionOpsCore as _, IDatabaseTransactionOpsCoreTyped,
     };
     use fedimint_core::{
         Amount, BitcoinHash, OutPoint, Tiered, TieredMulti, TransactionId, secp256k1,
@@ -532,7 +688,7 @@ mod fedimint_migration_tests {
     };
     use fedimint_mint_client::output::NoteIssuanceRequest;
     use fedimint_mint_client::{MintClientInit, MintClientModule, NoteIndex, SpendableNote};
-    use fedimint_mint_common::{MintCommonInit, MintOutputOutcome, Nonce};
+    use fedimint_mint_common::{KeysetId, MintCommonInit, MintOutputOutcome, Nonce};
     use fedimint_mint_server::db::{
         DbKeyPrefix, MintAuditItemKey, MintAuditItemKeyPrefix, MintOutputOutcomeKey,
         MintOutputOutcomePrefix, NonceKey, NonceKeyPrefix,
@@ -571,7 +727,10 @@ async fn create_server_db_with_v0_data(db: Database) {
 
         let (_, pk) = secp256k1::generate_keypair(&mut OsRng);
         let nonce_key = NonceKey(Nonce(pk));
-        dbtx.insert_new_entry(&nonce_key, &()).await;
+        // Nonces were stored without their keyset before database version 2
+        dbtx.raw_insert_bytes(&nonce_key.to_bytes(), &[])
+            .await
+            .expect("DB error");
 
         let out_point = OutPoint {
             txid: TransactionId::from_slice(&BYTE_32).unwrap(),
@@ -731,6 +890,12 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                             num_nonces > 0,
                             "validate_migrations was not able to read any NoteNonces"
                         );
+                        ensure!(
+                            nonces
+                                .iter()
+                                .all(|(_, keyset_id)| *keyset_id == KeysetId::GENESIS),
+                            "validate_migrations expects all NoteNonces to belong to the genesis keyset"
+                        );
                         info!("Validated NoteNonce");
                     }
                     DbKeyPrefix::OutputOutcome => {
@@ -763,6 +928,22 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                         // Would require an entire re-design of the way we test
                         // here, manually testing instead for now
                     }
+                    // Introduced after the v0 data was created
+                    DbKeyPrefix::Htlc
+                    | DbKeyPrefix::HtlcPreimage
+                    | DbKeyPrefix::SessionCountVote
+                    | DbKeyPrefix::Keyset
+                    | DbKeyPrefix::KeysetSecret
+                    | DbKeyPrefix::KeysetRotationRequest
+                    | DbKeyPrefix::KeysetRotationVote
+                    | DbKeyPrefix::KeysetDkg
+                    | DbKeyPrefix::KeysetEncryptionKey
+                    | DbKeyPrefix::KeysetDkgSecret
+                    | DbKeyPrefix::KeysetDeal
+                    | DbKeyPrefix::KeysetDealShares
+                    | DbKeyPrefix::KeysetComplaint
+                    | DbKeyPrefix::NonceCompaction
+                    | DbKeyPrefix::ConsensusVersionVote => {}
                 }
             }
 
@@ -849,7 +1030,9 @@ async fn test_client_db_migrations() -> anyhow::Result<()> {
                             );
                             info!("Validated RecoveryFinalized");
                         }
-                        fedimint_mint_client::client_db::DbKeyPrefix::ReusedNoteIndices => {}
+                        fedimint_mint_client::client_db::DbKeyPrefix::ReusedNoteIndices
+                        | fedimint_mint_client::client_db::DbKeyPrefix::Keyset
+                        | fedimint_mint_client::client_db::DbKeyPrefix::NoteDistributionPolicy => {}
                         fedimint_mint_client::client_db::DbKeyPrefix::ExternalReservedStart
                         | fedimint_mint_client::client_db::DbKeyPrefix::CoreInternalReservedEnd
                         | fedimint_mint_client::client_db::DbKeyPrefix::CoreInternalReservedStart =>
@@ -1,15 +1,18 @@
-use bitcoin::{Address, Amount};
+use bitcoin::{Address, Amount, ScriptBuf};
 use fedimint_api_client::api::{FederationApiExt, FederationResult, IModuleFederationApi};
 use fedimint_core::envs::BitcoinRpcConfig;
 use fedimint_core::module::{ApiAuth, ApiRequestErased, ModuleConsensusVersion};
 use fedimint_core::task::{MaybeSend, MaybeSync};
 use fedimint_core::{PeerId, apply, async_trait_maybe_send};
 use fedimint_wallet_common::endpoint_constants::{
-    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
-    BLOCK_COUNT_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT, PEG_OUT_FEES_ENDPOINT,
-    UTXO_CONFIRMED_ENDPOINT, WALLET_SUMMARY_ENDPOINT,
+    ACCELERATE_DEPOSIT_ENDPOINT, ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT,
+    BITCOIN_RPC_CONFIG_ENDPOINT, BLOCK_COUNT_ENDPOINT, DEPOSIT_CPFP_FEE_ENDPOINT,
+    MODULE_CONSENSUS_VERSION_ENDPOINT, PEG_OUT_BATCH_FEES_ENDPOINT, PEG_OUT_FEES_ENDPOINT,
+    PEG_OUT_SCRIPT_FEES_ENDPOINT, SIGN_PROOF_OF_RESERVES_ENDPOINT, UTXO_CONFIRMED_ENDPOINT,
+    WALLET_SUMMARY_ENDPOINT,
 };
-use fedimint_wallet_common::{PegOutFees, WalletSummary};
+use fedimint_wallet_common::proof_of_reserves::{ProofOfReserves, ReservesCommitment};
+use fedimint_wallet_common::{DepositCpfpRequest, PegOutFees, WalletSummary};
 
 #[apply(async_trait_maybe_send!)]
 pub trait WalletFederationApi {
@@ -23,6 +26,17 @@ async fn fetch_peg_out_fees(
         amount: Amount,
     ) -> FederationResult<Option<PegOutFees>>;
 
+    async fn fetch_peg_out_batch_fees(
+        &self,
+        address: &Address,
+    ) -> FederationResult<Option<PegOutFees>>;
+
+    async fn fetch_peg_out_script_fees(
+        &self,
+        script_pubkey: &ScriptBuf,
+        amount: Amount,
+    ) -> FederationResult<Option<PegOutFees>>;
+
     async fn fetch_bitcoin_rpc_kind(&self, peer_id: PeerId) -> FederationResult<String>;
 
     async fn fetch_bitcoin_rpc_config(&self, auth: ApiAuth) -> FederationResult<BitcoinRpcConfig>;
@@ -32,6 +46,19 @@ async fn fetch_peg_out_fees(
     async fn is_utxo_confirmed(&self, outpoint: bitcoin::OutPoint) -> FederationResult<bool>;
 
     async fn activate_consensus_version_voting(&self, auth: ApiAuth) -> FederationResult<()>;
+
+    async fn sign_proof_of_reserves(
+        &self,
+        commitment: &ReservesCommitment,
+        auth: ApiAuth,
+    ) -> FederationResult<ProofOfReserves>;
+
+    async fn accelerate_deposit(&self, request: &DepositCpfpRequest) -> FederationResult<()>;
+
+    async fn fetch_deposit_cpfp_fee(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> FederationResult<Option<Amount>>;
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -93,6 +120,29 @@ async fn fetch_peg_out_fees(
         .await
     }
 
+    async fn fetch_peg_out_batch_fees(
+        &self,
+        address: &Address,
+    ) -> FederationResult<Option<PegOutFees>> {
+        self.request_current_consensus(
+            PEG_OUT_BATCH_FEES_ENDPOINT.to_string(),
+            ApiRequestErased::new(address),
+        )
+        .await
+    }
+
+    async fn fetch_peg_out_script_fees(
+        &self,
+        script_pubkey: &ScriptBuf,
+        amount: Amount,
+    ) -> FederationResult<Option<PegOutFees>> {
+        self.request_current_consensus(
+            PEG_OUT_SCRIPT_FEES_ENDPOINT.to_string(),
+            ApiRequestErased::new((script_pubkey, amount.to_sat())),
+        )
+        .await
+    }
+
     async fn fetch_bitcoin_rpc_kind(&self, peer_id: PeerId) -> FederationResult<String> {
         self.request_single_peer_federation(
             BITCOIN_KIND_ENDPOINT.to_string(),
@@ -127,4 +177,45 @@ async fn activate_consensus_version_voting(&self, auth: ApiAuth) -> FederationRe
         )
         .await
     }
+
+    async fn sign_proof_of_reserves(
+        &self,
+        commitment: &ReservesCommitment,
+        auth: ApiAuth,
+    ) -> FederationResult<ProofOfReserves> {
+        self.request_admin(
+            SIGN_PROOF_OF_RESERVES_ENDPOINT,
+            ApiRequestErased::new(commitment),
+            auth,
+        )
+        .await
+    }
+
+    async fn accelerate_deposit(&self, request: &DepositCpfpRequest) -> FederationResult<()> {
+        self.request_current_consensus(
+            ACCELERATE_DEPOSIT_ENDPOINT.to_string(),
+            ApiRequestErased::new(request),
+        )
+        .await
+    }
+
+    async fn fetch_deposit_cpfp_fee(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> FederationResult<Option<Amount>> {
+        let res = self
+            .request_current_consensus(
+                DEPOSIT_CPFP_FEE_ENDPOINT.to_string(),
+                ApiRequestErased::new(outpoint),
+            )
+            .await;
+
+        if let Err(e) = &res {
+            if e.any_peer_error_method_not_found() {
+                return Ok(None);
+            }
+        }
+
+        res
+    }
 }
@@ -3,8 +3,10 @@
 
 use anyhow::bail;
 use bitcoin::address::NetworkUnchecked;
+use bitcoin::secp256k1::SECP256K1;
 use clap::Parser;
 use fedimint_core::core::OperationId;
+use fedimint_wallet_common::proof_of_reserves::ProofOfReserves;
 use serde::Serialize;
 
 use super::WalletClientModule;
@@ -41,6 +43,31 @@ enum Opts {
         #[arg(long)]
         tweak_idx: Option<TweakIdx>,
     },
+    /// Have the federation accelerate unconfirmed deposits via CPFP, paid
+    /// for out of the deposits
+    AccelerateDeposits {
+        operation_id: OperationId,
+    },
+    /// Commit to the federation's liabilities according to the audit of our
+    /// guardian, to be signed as part of a proof of reserves
+    ReservesCommitment,
+    /// Have our guardian sign a proof of reserves for a JSON encoded
+    /// commitment
+    SignProofOfReserves {
+        commitment: String,
+    },
+    /// Merge JSON encoded proofs of reserves signed by a threshold of
+    /// guardians
+    CombineProofsOfReserves {
+        #[arg(required = true)]
+        proofs: Vec<String>,
+    },
+    /// Verify a JSON encoded proof of reserves against the federation's
+    /// wallet descriptor and the blockchain and report whether the reserves
+    /// cover the liabilities
+    VerifyProofOfReserves {
+        proof: String,
+    },
 }
 
 pub(crate) async fn handle_cli_command(
@@ -131,6 +158,33 @@ pub(crate) async fn handle_cli_command(
             }
             serde_json::Value::Bool(true)
         }
+        Opts::AccelerateDeposits { operation_id } => {
+            serde_json::to_value(module.accelerate_deposits(operation_id).await?)
+                .expect("JSON serialization failed")
+        }
+        Opts::ReservesCommitment => serde_json::to_value(module.reserves_commitment().await?)
+            .expect("JSON serialization failed"),
+        Opts::SignProofOfReserves { commitment } => {
+            let commitment = serde_json::from_str(&commitment)?;
+
+            serde_json::to_value(module.sign_proof_of_reserves(&commitment).await?)
+                .expect("JSON serialization failed")
+        }
+        Opts::CombineProofsOfReserves { proofs } => {
+            let proofs = proofs
+                .iter()
+                .map(|proof| serde_json::from_str(proof))
+                .collect::<Result<Vec<ProofOfReserves>, _>>()?;
+
+            serde_json::to_value(ProofOfReserves::combine(proofs, SECP256K1)?)
+                .expect("JSON serialization failed")
+        }
+        Opts::VerifyProofOfReserves { proof } => {
+            let proof = serde_json::from_str(&proof)?;
+
+            serde_json::to_value(module.verify_proof_of_reserves(&proof).await?)
+                .expect("JSON serialization failed")
+        }
         Opts::NewDepositAddress => {
             let (operation_id, address, tweak_idx) =
                 module.allocate_deposit_address_expert_only(()).await?;
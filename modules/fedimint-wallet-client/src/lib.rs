@@ -45,6 +45,7 @@
     ClientOutput, ClientOutputBundle, ClientOutputSM, TransactionBuilder,
 };
 use fedimint_client_module::{DynGlobalClientContext, sm_enum_variant_translation};
+use fedimint_core::config::FederationId;
 use fedimint_core::core::{Decoder, IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId};
 use fedimint_core::db::{
     AutocommitError, Database, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped,
@@ -65,6 +66,10 @@
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_logging::LOG_CLIENT_MODULE_WALLET;
 use fedimint_wallet_common::config::{FeeConsensus, WalletClientConfig};
+use fedimint_wallet_common::proof_of_reserves::{
+    ProofOfReserves, ReservesCommitment, VerifiedReserves,
+};
+use fedimint_wallet_common::silent_payment::SilentPaymentAddress;
 use fedimint_wallet_common::tweakable::Tweakable;
 pub use fedimint_wallet_common::*;
 use futures::{Stream, StreamExt};
@@ -330,6 +335,22 @@ pub enum WalletOperationMetaVariant {
         rbf: Rbf,
         change: Vec<OutPoint>,
     },
+
+    WithdrawToScript {
+        script_pubkey: ScriptBuf,
+        #[serde(with = "bitcoin::amount::serde::as_sat")]
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        change: Vec<OutPoint>,
+    },
+
+    WithdrawToSilentPayment {
+        address: SilentPaymentAddress,
+        #[serde(with = "bitcoin::amount::serde::as_sat")]
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        change: Vec<OutPoint>,
+    },
 }
 
 /// The non-resource, just plain-data parts of [`WalletClientModule`]
@@ -592,6 +613,50 @@ pub async fn get_withdraw_fees(
             .context("Federation didn't return peg-out fees")
     }
 
+    /// Fetches the fee share a batched withdraw request to `address` using
+    /// [`Self::withdraw_batched`] would need to pay *right now*.
+    ///
+    /// Fails if the federation does not support batched peg-outs yet. The same
+    /// caveats as for [`Self::get_withdraw_fees`] apply.
+    pub async fn get_batched_withdraw_fees(
+        &self,
+        address: &bitcoin::Address,
+    ) -> anyhow::Result<PegOutFees> {
+        self.module_api
+            .fetch_peg_out_batch_fees(address)
+            .await?
+            .context("Federation doesn't support batched peg-outs")
+    }
+
+    /// Fetches the fees a withdraw request to `script_pubkey` using
+    /// [`Self::withdraw_to_script`] would need to pay *right now*.
+    ///
+    /// Fails if the federation does not support peg-outs to scripts yet or
+    /// does not consider the script standard. The same caveats as for
+    /// [`Self::get_withdraw_fees`] apply.
+    pub async fn get_withdraw_to_script_fees(
+        &self,
+        script_pubkey: &ScriptBuf,
+        amount: bitcoin::Amount,
+    ) -> anyhow::Result<PegOutFees> {
+        self.module_api
+            .fetch_peg_out_script_fees(script_pubkey, amount)
+            .await?
+            .context("Federation didn't return peg-out fees for script")
+    }
+
+    /// Fetches the fees a withdraw request to the silent payment `address`
+    /// using [`Self::withdraw_to_silent_payment`] would need to pay *right
+    /// now*. The same caveats as for [`Self::get_withdraw_fees`] apply.
+    pub async fn get_withdraw_to_silent_payment_fees(
+        &self,
+        address: &SilentPaymentAddress,
+        amount: bitcoin::Amount,
+    ) -> anyhow::Result<PegOutFees> {
+        self.get_withdraw_to_script_fees(&address.placeholder_script_pubkey(), amount)
+            .await
+    }
+
     /// Returns a summary of the wallet's coins
     pub async fn get_wallet_summary(&self) -> anyhow::Result<WalletSummary> {
         Ok(self.module_api.fetch_wallet_summary().await?)
@@ -606,7 +671,57 @@ pub fn create_withdraw_output(
     ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
         let output = WalletOutput::new_v0_peg_out(address, amount, fees);
 
-        let amount = output.maybe_v0_ref().expect("v0 output").amount().into();
+        Ok(Self::withdraw_output_bundle(operation_id, output))
+    }
+
+    /// Creates a withdraw output that is paid out together with all other
+    /// batched withdrawals of the current session window. The state machine
+    /// only learns the bitcoin transaction id once the batch is closed.
+    pub fn create_batched_withdraw_output(
+        &self,
+        operation_id: OperationId,
+        address: bitcoin::Address,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
+        let output = WalletOutput::new_v1_batched_peg_out(address, amount, fees);
+
+        Ok(Self::withdraw_output_bundle(operation_id, output))
+    }
+
+    /// Creates a withdraw output paying to an explicit output script, see
+    /// [`Self::withdraw_to_script`].
+    pub fn create_withdraw_to_script_output(
+        &self,
+        operation_id: OperationId,
+        script_pubkey: ScriptBuf,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
+        let output = WalletOutput::new_v2_peg_out(script_pubkey, amount, fees);
+
+        Ok(Self::withdraw_output_bundle(operation_id, output))
+    }
+
+    /// Creates a withdraw output paying to a silent payment address, see
+    /// [`Self::withdraw_to_silent_payment`].
+    pub fn create_withdraw_to_silent_payment_output(
+        &self,
+        operation_id: OperationId,
+        address: SilentPaymentAddress,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
+        let output = WalletOutput::new_v3_silent_payment_peg_out(address, amount, fees);
+
+        Ok(Self::withdraw_output_bundle(operation_id, output))
+    }
+
+    fn withdraw_output_bundle(
+        operation_id: OperationId,
+        output: WalletOutput,
+    ) -> ClientOutputBundle<WalletOutput, WalletClientStates> {
+        let amount = output.amount().expect("known output variant").into();
 
         let sm_gen = move |out_point_range: OutPointRange| {
             assert_eq!(out_point_range.count(), 1);
@@ -622,12 +737,12 @@ pub fn create_withdraw_output(
             })]
         };
 
-        Ok(ClientOutputBundle::new(
+        ClientOutputBundle::new(
             vec![ClientOutput::<WalletOutput> { output, amount }],
             vec![ClientOutputSM::<WalletClientStates> {
                 state_machines: Arc::new(sm_gen),
             }],
-        ))
+        )
     }
 
     pub fn create_rbf_withdraw_output(
@@ -1063,6 +1178,45 @@ pub async fn recheck_pegin_address(&self, tweak_idx: TweakIdx) -> anyhow::Result
         Ok(())
     }
 
+    /// Asks the federation to accelerate the unconfirmed deposits to the
+    /// address of `operation_id` by spending them in a child transaction.
+    /// The fee of the child is deducted from each deposit once it is claimed.
+    /// Returns the number of deposits the federation accepted to accelerate.
+    pub async fn accelerate_deposits(&self, operation_id: OperationId) -> anyhow::Result<usize> {
+        let tweak_idx = self.find_tweak_idx_by_operation_id(operation_id).await?;
+        let (script, _, tweak_key, _) = self.data.derive_peg_in_script(tweak_idx);
+
+        self.rpc.watch_script_history(&script).await?;
+        let history = self.rpc.get_script_history(&script).await?;
+
+        let mut accelerated = 0;
+
+        for (transaction, vout) in
+            pegin_monitor::filter_onchain_deposit_outputs(history.into_iter(), &script)
+        {
+            if self
+                .rpc
+                .get_tx_block_height(&transaction.compute_txid())
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+
+            let request = DepositCpfpRequest {
+                transaction,
+                vout,
+                tweak_contract_key: tweak_key.public_key(),
+            };
+
+            self.module_api.accelerate_deposit(&request).await?;
+
+            accelerated += 1;
+        }
+
+        Ok(accelerated)
+    }
+
     /// Await for num deposit by [`OperationId`]
     pub async fn await_num_deposits_by_operation_id(
         &self,
@@ -1141,38 +1295,165 @@ pub async fn withdraw<M: Serialize + MaybeSend + MaybeSync>(
         fee: PegOutFees,
         extra_meta: M,
     ) -> anyhow::Result<OperationId> {
-        {
-            let operation_id = OperationId(thread_rng().r#gen());
-
-            let withdraw_output =
-                self.create_withdraw_output(operation_id, address.clone(), amount, fee)?;
-            let tx_builder = TransactionBuilder::new()
-                .with_outputs(self.client_ctx.make_client_outputs(withdraw_output));
-
-            let extra_meta =
-                serde_json::to_value(extra_meta).expect("Failed to serialize extra meta");
-            self.client_ctx
-                .finalize_and_submit_transaction(
-                    operation_id,
-                    WalletCommonInit::KIND.as_str(),
-                    {
-                        let address = address.clone();
-                        move |change_range: OutPointRange| WalletOperationMeta {
-                            variant: WalletOperationMetaVariant::Withdraw {
-                                address: address.clone().into_unchecked(),
-                                amount,
-                                fee,
-                                change: change_range.into_iter().collect(),
-                            },
-                            extra_meta: extra_meta.clone(),
-                        }
-                    },
-                    tx_builder,
-                )
-                .await?;
+        let operation_id = OperationId(thread_rng().r#gen());
 
-            Ok(operation_id)
-        }
+        let withdraw_output =
+            self.create_withdraw_output(operation_id, address.clone(), amount, fee)?;
+
+        let address = address.clone().into_unchecked();
+
+        self.submit_withdraw(
+            operation_id,
+            withdraw_output,
+            move |change| WalletOperationMetaVariant::Withdraw {
+                address: address.clone(),
+                amount,
+                fee,
+                change,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    /// Attempt to withdraw a given `amount` of Bitcoin to a destination
+    /// `address` as part of a batch of withdrawals that share one on-chain
+    /// transaction. The caller has to supply the fee share to be used which
+    /// can be fetched using [`Self::get_batched_withdraw_fees`].
+    ///
+    /// The withdrawal is only broadcast once the federation closes the
+    /// current batch, so [`Self::subscribe_withdraw_updates`] reports the
+    /// transaction id with a delay of a few sessions.
+    pub async fn withdraw_batched<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        address: &bitcoin::Address,
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let operation_id = OperationId(thread_rng().r#gen());
+
+        let withdraw_output =
+            self.create_batched_withdraw_output(operation_id, address.clone(), amount, fee)?;
+
+        let address = address.clone().into_unchecked();
+
+        self.submit_withdraw(
+            operation_id,
+            withdraw_output,
+            move |change| WalletOperationMetaVariant::Withdraw {
+                address: address.clone(),
+                amount,
+                fee,
+                change,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    /// Attempt to withdraw a given `amount` of Bitcoin to an explicit output
+    /// script, e.g. derived from an output descriptor using
+    /// [`fedimint_wallet_common::descriptor_script_pubkey`]. The caller has to
+    /// supply the fee rate to be used which can be fetched using
+    /// [`Self::get_withdraw_to_script_fees`].
+    pub async fn withdraw_to_script<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        script_pubkey: &ScriptBuf,
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let operation_id = OperationId(thread_rng().r#gen());
+
+        let withdraw_output = self.create_withdraw_to_script_output(
+            operation_id,
+            script_pubkey.clone(),
+            amount,
+            fee,
+        )?;
+
+        let script_pubkey = script_pubkey.clone();
+
+        self.submit_withdraw(
+            operation_id,
+            withdraw_output,
+            move |change| WalletOperationMetaVariant::WithdrawToScript {
+                script_pubkey: script_pubkey.clone(),
+                amount,
+                fee,
+                change,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    /// Attempt to withdraw a given `amount` of Bitcoin to a BIP-352 silent
+    /// payment `address`, which only taproot federations support. The caller
+    /// has to supply the fee rate to be used which can be fetched using
+    /// [`Self::get_withdraw_to_silent_payment_fees`].
+    ///
+    /// The guardians derive the output from the inputs of the peg-out
+    /// transaction in an additional consensus round, so
+    /// [`Self::subscribe_withdraw_updates`] reports the transaction id with a
+    /// delay.
+    pub async fn withdraw_to_silent_payment<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        address: &SilentPaymentAddress,
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let operation_id = OperationId(thread_rng().r#gen());
+
+        let withdraw_output =
+            self.create_withdraw_to_silent_payment_output(operation_id, *address, amount, fee)?;
+
+        let address = *address;
+
+        self.submit_withdraw(
+            operation_id,
+            withdraw_output,
+            move |change| WalletOperationMetaVariant::WithdrawToSilentPayment {
+                address,
+                amount,
+                fee,
+                change,
+            },
+            extra_meta,
+        )
+        .await
+    }
+
+    async fn submit_withdraw<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        operation_id: OperationId,
+        withdraw_output: ClientOutputBundle<WalletOutput, WalletClientStates>,
+        variant_gen: impl Fn(Vec<OutPoint>) -> WalletOperationMetaVariant
+        + Clone
+        + MaybeSend
+        + MaybeSync
+        + 'static,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let tx_builder = TransactionBuilder::new()
+            .with_outputs(self.client_ctx.make_client_outputs(withdraw_output));
+
+        let extra_meta = serde_json::to_value(extra_meta).expect("Failed to serialize extra meta");
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                WalletCommonInit::KIND.as_str(),
+                move |change_range: OutPointRange| WalletOperationMeta {
+                    variant: variant_gen(change_range.into_iter().collect()),
+                    extra_meta: extra_meta.clone(),
+                },
+                tx_builder,
+            )
+            .await?;
+
+        Ok(operation_id)
     }
 
     /// Attempt to increase the fee of a onchain withdraw transaction using
@@ -1230,7 +1511,10 @@ pub async fn subscribe_withdraw_updates(
         let operation_meta = operation.meta::<WalletOperationMeta>();
 
         let (WalletOperationMetaVariant::Withdraw { change, .. }
-        | WalletOperationMetaVariant::RbfWithdraw { change, .. }) = operation_meta.variant
+        | WalletOperationMetaVariant::RbfWithdraw { change, .. }
+        | WalletOperationMetaVariant::WithdrawToScript { change, .. }
+        | WalletOperationMetaVariant::WithdrawToSilentPayment { change, .. }) =
+            operation_meta.variant
         else {
             bail!("Operation is not a withdraw operation");
         };
@@ -1290,6 +1574,89 @@ pub async fn activate_consensus_version_voting(&self) -> anyhow::Result<()> {
 
         Ok(())
     }
+
+    /// Commits to the federation's current liabilities according to the audit
+    /// of our guardian, to be signed by a threshold of guardians as part of a
+    /// proof of reserves
+    pub async fn reserves_commitment(&self) -> anyhow::Result<ReservesCommitment> {
+        let global_api = self.client_ctx.global_api();
+        let audit = global_api.audit(self.admin_auth()?).await?;
+        let session_count = global_api.session_count().await?;
+
+        Ok(ReservesCommitment {
+            federation_id: self.federation_id().await,
+            session_count,
+            liabilities: Amount::from_msats(audit.liabilities()),
+        })
+    }
+
+    /// Has our guardian sign a proof of reserves over the federation's UTXOs
+    /// for `commitment`
+    pub async fn sign_proof_of_reserves(
+        &self,
+        commitment: &ReservesCommitment,
+    ) -> anyhow::Result<ProofOfReserves> {
+        ensure!(
+            commitment.federation_id == self.federation_id().await,
+            "The commitment is for a different federation"
+        );
+
+        Ok(self
+            .module_api
+            .sign_proof_of_reserves(commitment, self.admin_auth()?)
+            .await?)
+    }
+
+    /// Verifies that `proof` was signed by a threshold of guardians and that
+    /// all of its UTXOs are confirmed and unspent
+    pub async fn verify_proof_of_reserves(
+        &self,
+        proof: &ProofOfReserves,
+    ) -> anyhow::Result<VerifiedReserves> {
+        ensure!(
+            proof.commitment.federation_id == self.federation_id().await,
+            "The proof is for a different federation"
+        );
+
+        let verified = proof.verify(&self.cfg().peg_in_descriptor, SECP256K1)?;
+
+        for (outpoint, utxo) in &verified.utxos {
+            ensure!(
+                self.rpc
+                    .get_tx_block_height(&outpoint.txid)
+                    .await?
+                    .is_some(),
+                "UTXO {outpoint} is not confirmed"
+            );
+
+            self.rpc.watch_script_history(&utxo.script_pubkey).await?;
+            let history = self.rpc.get_script_history(&utxo.script_pubkey).await?;
+
+            ensure!(
+                history.iter().any(|tx| tx.compute_txid() == outpoint.txid
+                    && tx.output.get(outpoint.vout as usize) == Some(utxo)),
+                "UTXO {outpoint} does not exist"
+            );
+
+            ensure!(
+                !history
+                    .iter()
+                    .flat_map(|tx| &tx.input)
+                    .any(|input| input.previous_output == *outpoint),
+                "UTXO {outpoint} has been spent"
+            );
+        }
+
+        Ok(verified)
+    }
+
+    async fn federation_id(&self) -> FederationId {
+        self.client_ctx
+            .get_config()
+            .await
+            .global
+            .calculate_federation_id()
+    }
 }
 
 /// Polls the federation checking if the activated module consensus version
@@ -380,6 +380,10 @@ async fn check_idx_pegins(
 
         let tx_out_proof = btc_rpc.get_txout_proof(txid).await?;
         let federation_knows_utxo = module_rpc.is_utxo_confirmed(outpoint).await?;
+        let cpfp_fee = module_rpc
+            .fetch_deposit_cpfp_fee(outpoint)
+            .await?
+            .unwrap_or(bitcoin::Amount::ZERO);
 
         claim_peg_in(
             client_ctx,
@@ -390,6 +394,7 @@ async fn check_idx_pegins(
             outpoint,
             tx_out_proof,
             federation_knows_utxo,
+            cpfp_fee,
         )
         .await?;
         outcomes.push(CheckOutcome::Claimed { outpoint });
@@ -407,6 +412,7 @@ async fn claim_peg_in(
     out_point: bitcoin::OutPoint,
     tx_out_proof: TxOutProof,
     federation_knows_utxo: bool,
+    cpfp_fee: bitcoin::Amount,
 ) -> anyhow::Result<()> {
     async fn claim_peg_in_inner(
         client_ctx: &ClientContext<WalletClientModule>,
@@ -417,6 +423,7 @@ async fn claim_peg_in_inner(
         txout_proof: TxOutProof,
         operation_id: OperationId,
         federation_knows_utxo: bool,
+        cpfp_fee: bitcoin::Amount,
     ) -> OutPointRange {
         let pegin_proof = PegInProof::new(
             txout_proof,
@@ -426,7 +433,8 @@ async fn claim_peg_in_inner(
         )
         .expect("TODO: handle API returning faulty proofs");
 
-        let amount = pegin_proof.tx_output().value.into();
+        // The fee for accelerating the deposit is deducted from it
+        let amount = (pegin_proof.tx_output().value - cpfp_fee).into();
         let wallet_input = if federation_knows_utxo {
             WalletInput::new_v1(&pegin_proof)
         } else {
@@ -478,6 +486,7 @@ async fn claim_peg_in_inner(
                         tx_out_proof.clone(),
                         operation_id,
                         federation_knows_utxo,
+                        cpfp_fee,
                     )
                     .await;
 
@@ -73,6 +73,8 @@ async fn await_withdraw_processed(
         .await_tx_accepted(created.fm_outpoint.txid)
         .await?;
 
+    // Batched withdrawals only have an outcome once the federation closed the
+    // batch, until then the request fails and is retried.
     #[allow(deprecated)]
     let outcome = global_context
         .api()
@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
 
+use std::str::FromStr;
+
+use anyhow::{Context, ensure};
 use bitcoin::Network;
-use bitcoin::secp256k1::SecretKey;
+use bitcoin::secp256k1::{SECP256K1, SecretKey};
 use fedimint_core::core::ModuleKind;
 use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
 use fedimint_core::encoding::{Decodable, Encodable};
@@ -9,17 +12,26 @@
 use fedimint_core::module::serde_json;
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Feerate, PeerId, plugin_types_trait_impl_config};
-use miniscript::descriptor::{Wpkh, Wsh};
+use miniscript::descriptor::{Wpkh, Wsh, WshInner};
+use miniscript::{Descriptor, Terminal};
 use serde::{Deserialize, Serialize};
 
-use crate::envs::FM_PORT_ESPLORA_ENV;
+use crate::envs::{
+    FM_PORT_ESPLORA_ENV, FM_WALLET_RECOVERY_DELAY_ENV, FM_WALLET_RECOVERY_KEYS_ENV,
+    FM_WALLET_RECOVERY_THRESHOLD_ENV,
+};
 use crate::keys::CompressedPublicKey;
+use crate::tweakable::{Contract, Tweakable};
 use crate::{PegInDescriptor, WalletCommonInit};
 
 /// Helps against dust attacks where an attacker deposits UTXOs that, with
 /// higher fee levels, cannot be spent profitably.
 const DEFAULT_DEPOSIT_FEE_SATS: u64 = 1000;
 
+/// Lower bound for the recovery delay, the federation has to refresh its UTXOs
+/// within half of it
+const MIN_RECOVERY_DELAY_BLOCKS: u16 = 144;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalletGenParams {
     pub local: WalletGenParamsLocal,
@@ -42,6 +54,8 @@ pub fn regtest(bitcoin_rpc: BitcoinRpcConfig) -> WalletGenParams {
                     .expect("Failed to parse default esplora server"),
                 },
                 fee_consensus: FeeConsensus::default(),
+                taproot: false,
+                recovery: None,
             },
         }
     }
@@ -63,6 +77,112 @@ pub struct WalletGenParamsConsensus {
     ///
     /// Deposit fees in particular are a protection against dust attacks.
     pub fee_consensus: FeeConsensus,
+    /// Use a taproot peg-in descriptor whose internal key is a FROST threshold
+    /// key instead of a segwit v0 multisig.
+    ///
+    /// **Experimental:** taproot wallets can not have an emergency recovery
+    /// path and guardians can not sign proofs of reserves for them, since both
+    /// would require another interactive FROST signing protocol. The recovery
+    /// tool sweeps them with the group key combined from the shares of a
+    /// threshold of guardians.
+    #[serde(default)]
+    pub taproot: bool,
+    /// Adds a timelocked recovery path to the peg-in descriptor
+    #[serde(default)]
+    pub recovery: Option<EmergencyRecoveryParams>,
+}
+
+/// An emergency recovery path that allows a lower threshold of recovery keys
+/// to spend a UTXO once it is `delay` blocks old, in case the federation lost
+/// too many guardians to sign. Every guardian holds a recovery key derived
+/// from its peg-in key, additional keys can be designated to parties outside
+/// of the federation.
+///
+/// An active federation refreshes its UTXOs long before the recovery path
+/// becomes spendable. Refreshes are paid out of the fee budget and, once UTXOs
+/// are half the delay old, out of the federation's reserves if the budget runs
+/// out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmergencyRecoveryParams {
+    /// Number of recovery keys required to spend via the recovery path
+    pub threshold: usize,
+    /// Recovery keys in addition to the ones of the guardians
+    pub keys: Vec<CompressedPublicKey>,
+    /// Relative timelock in blocks
+    pub delay: u16,
+}
+
+impl EmergencyRecoveryParams {
+    /// Reads the recovery path parameters from the environment, returns `None`
+    /// if no recovery delay is set
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(delay) = std::env::var(FM_WALLET_RECOVERY_DELAY_ENV) else {
+            return Ok(None);
+        };
+
+        let keys = match std::env::var(FM_WALLET_RECOVERY_KEYS_ENV) {
+            Ok(keys) => keys
+                .split(',')
+                .map(CompressedPublicKey::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid recovery key")?,
+            Err(_) => vec![],
+        };
+
+        Ok(Some(EmergencyRecoveryParams {
+            threshold: std::env::var(FM_WALLET_RECOVERY_THRESHOLD_ENV)
+                .context("Recovery threshold is not set")?
+                .parse()
+                .context("Invalid recovery threshold")?,
+            keys,
+            delay: delay.parse().context("Invalid recovery delay")?,
+        }))
+    }
+
+    pub fn validate(&self, num_peers: usize, fee_consensus: &FeeConsensus) -> anyhow::Result<()> {
+        ensure!(self.threshold > 0, "Recovery threshold must not be zero");
+
+        ensure!(
+            self.threshold <= num_peers + self.keys.len(),
+            "Recovery threshold exceeds the number of recovery keys"
+        );
+
+        ensure!(
+            self.delay >= MIN_RECOVERY_DELAY_BLOCKS,
+            "Recovery delay must be at least {MIN_RECOVERY_DELAY_BLOCKS} blocks"
+        );
+
+        // Refreshing UTXOs is paid for by the fee budget, which is only funded by
+        // peg-in and peg-out fees
+        ensure!(
+            fee_consensus.peg_in_abs != fedimint_core::Amount::ZERO
+                || fee_consensus.peg_out_abs != fedimint_core::Amount::ZERO,
+            "A recovery path requires peg-in or peg-out fees to fund the refresh of UTXOs"
+        );
+
+        Ok(())
+    }
+}
+
+/// Contract deriving a guardian's recovery key from its peg-in key, so the
+/// recovery path does not require any additional secrets
+pub struct RecoveryKeyContract;
+
+impl Contract for RecoveryKeyContract {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(b"fedimint-wallet-emergency-recovery")
+    }
+}
+
+/// The recovery key of a guardian with the peg-in key `peg_in_key`
+pub fn guardian_recovery_key(peg_in_key: &CompressedPublicKey) -> CompressedPublicKey {
+    peg_in_key.tweak(&RecoveryKeyContract, SECP256K1)
+}
+
+/// The secret recovery key of a guardian with the secret peg-in key
+/// `peg_in_key`
+pub fn guardian_recovery_secret_key(peg_in_key: &SecretKey) -> SecretKey {
+    peg_in_key.tweak(&RecoveryKeyContract, SECP256K1)
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -80,7 +200,8 @@ pub struct WalletConfigLocal {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WalletConfigPrivate {
-    /// Secret key for signing bitcoin multisig transactions
+    /// Secret key for signing bitcoin multisig transactions, or our share of
+    /// the FROST group key for taproot federations
     pub peg_in_key: SecretKey,
 }
 
@@ -90,7 +211,8 @@ pub struct WalletConfigConsensus {
     pub network: NetworkLegacyEncodingWrapper,
     /// The federations public peg-in-descriptor
     pub peg_in_descriptor: PegInDescriptor,
-    /// The public keys for the bitcoin multisig
+    /// The public keys for the bitcoin multisig, or the verification shares of
+    /// the FROST group key for taproot federations
     pub peer_peg_in_keys: BTreeMap<PeerId, CompressedPublicKey>,
     /// How many bitcoin blocks to wait before considering a transaction
     /// confirmed
@@ -162,8 +284,32 @@ pub fn new(
         bitcoin_rpc: BitcoinRpcConfig,
         client_default_bitcoin_rpc: BitcoinRpcConfig,
         fee_consensus: FeeConsensus,
+        recovery: Option<&EmergencyRecoveryParams>,
     ) -> Self {
-        let peg_in_descriptor = if pubkeys.len() == 1 {
+        let peg_in_descriptor = if let Some(recovery) = recovery {
+            let recovery_keys = pubkeys
+                .values()
+                .map(guardian_recovery_key)
+                .chain(recovery.keys.iter().copied())
+                .collect::<Vec<_>>();
+
+            PegInDescriptor::from_str(&format!(
+                "wsh(or_d(multi({threshold},{}),and_v(v:multi({},{}),older({}))))",
+                pubkeys
+                    .values()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+                recovery.threshold,
+                recovery_keys
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+                recovery.delay
+            ))
+            .expect("Recovery parameters are validated")
+        } else if pubkeys.len() == 1 {
             PegInDescriptor::Wpkh(
                 Wpkh::new(
                     *pubkeys
@@ -193,6 +339,66 @@ pub fn new(
             },
         }
     }
+
+    /// Creates a config with a taproot key spend peg-in descriptor, `group_key`
+    /// being the FROST public key shared by the federation and `share` our
+    /// share of the corresponding secret key
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_taproot(
+        verification_shares: BTreeMap<PeerId, CompressedPublicKey>,
+        group_key: CompressedPublicKey,
+        share: SecretKey,
+        network: Network,
+        finality_delay: u32,
+        bitcoin_rpc: BitcoinRpcConfig,
+        client_default_bitcoin_rpc: BitcoinRpcConfig,
+        fee_consensus: FeeConsensus,
+    ) -> Self {
+        Self {
+            local: WalletConfigLocal { bitcoin_rpc },
+            private: WalletConfigPrivate { peg_in_key: share },
+            consensus: WalletConfigConsensus {
+                network: NetworkLegacyEncodingWrapper(network),
+                peg_in_descriptor: Descriptor::new_tr(group_key, None)
+                    .expect("Key spend only descriptor is valid"),
+                peer_peg_in_keys: verification_shares,
+                finality_delay,
+                default_fee: Feerate { sats_per_kvb: 1000 },
+                fee_consensus,
+                client_default_bitcoin_rpc,
+            },
+        }
+    }
+}
+
+impl WalletConfigConsensus {
+    /// Whether peg-outs are signed with FROST instead of ECDSA multisig
+    pub fn is_taproot(&self) -> bool {
+        matches!(self.peg_in_descriptor, PegInDescriptor::Tr(_))
+    }
+
+    /// The relative timelock of the emergency recovery path in blocks, if the
+    /// peg-in descriptor has one
+    pub fn recovery_delay(&self) -> Option<u32> {
+        recovery_delay(&self.peg_in_descriptor)
+    }
+}
+
+/// The relative timelock of the emergency recovery path of `descriptor` in
+/// blocks, if it has one
+pub fn recovery_delay(descriptor: &PegInDescriptor) -> Option<u32> {
+    let PegInDescriptor::Wsh(wsh) = descriptor else {
+        return None;
+    };
+
+    let WshInner::Ms(miniscript) = wsh.as_inner() else {
+        return None;
+    };
+
+    miniscript.iter().find_map(|node| match node.node {
+        Terminal::Older(delay) => Some(delay.to_consensus_u32()),
+        _ => None,
+    })
 }
 
 plugin_types_trait_impl_config!(
@@ -8,3 +8,8 @@
 pub const ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT: &str = "activate_consensus_version_voting";
 pub const WALLET_SUMMARY_ENDPOINT: &str = "wallet_summary";
 pub const UTXO_CONFIRMED_ENDPOINT: &str = "utxo_confirmed";
+pub const PEG_OUT_BATCH_FEES_ENDPOINT: &str = "peg_out_batch_fees";
+pub const PEG_OUT_SCRIPT_FEES_ENDPOINT: &str = "peg_out_script_fees";
+pub const SIGN_PROOF_OF_RESERVES_ENDPOINT: &str = "sign_proof_of_reserves";
+pub const ACCELERATE_DEPOSIT_ENDPOINT: &str = "accelerate_deposit";
+pub const DEPOSIT_CPFP_FEE_ENDPOINT: &str = "deposit_cpfp_fee";
@@ -1,2 +1,19 @@
 // Env variable to TODO
 pub const FM_PORT_ESPLORA_ENV: &str = "FM_PORT_ESPLORA";
+
+// Env variable to generate configs with an experimental taproot peg-in
+// descriptor backed by a FROST threshold key, see
+// `WalletGenParamsConsensus::taproot` for its limitations
+pub const FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN_ENV: &str = "FM_WALLET_EXPERIMENTAL_TAPROOT_PEG_IN";
+
+// Env variable to add an emergency recovery path with the given relative
+// timelock in blocks to the peg-in descriptor
+pub const FM_WALLET_RECOVERY_DELAY_ENV: &str = "FM_WALLET_RECOVERY_DELAY";
+
+// Env variable to set the number of keys required to spend via the emergency
+// recovery path
+pub const FM_WALLET_RECOVERY_THRESHOLD_ENV: &str = "FM_WALLET_RECOVERY_THRESHOLD";
+
+// Env variable to designate comma separated public keys in addition to the
+// guardians' recovery keys
+pub const FM_WALLET_RECOVERY_KEYS_ENV: &str = "FM_WALLET_RECOVERY_KEYS";
@@ -7,10 +7,14 @@
 #![allow(clippy::return_self_not_must_use)]
 
 use std::hash::Hasher;
+use std::str::FromStr;
 
+use anyhow::ensure;
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::psbt::raw::ProprietaryKey;
-use bitcoin::{Address, Amount, BlockHash, TxOut, Txid, secp256k1};
+use bitcoin::{
+    Address, Amount, BlockHash, Script, ScriptBuf, TxOut, Txid, WitnessVersion, secp256k1,
+};
 use config::WalletClientConfig;
 use fedimint_core::core::{Decoder, ModuleInstanceId, ModuleKind};
 use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
@@ -18,29 +22,66 @@
 use fedimint_core::module::{CommonModuleInit, ModuleCommon, ModuleConsensusVersion};
 use fedimint_core::{Feerate, extensible_associated_module_type, plugin_types_trait_impl_common};
 use impl_tools::autoimpl;
-use miniscript::Descriptor;
+use miniscript::{Descriptor, DescriptorPublicKey};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::error;
 
 use crate::keys::CompressedPublicKey;
+use crate::silent_payment::SilentPaymentAddress;
+use crate::tweakable::Tweakable;
 use crate::txoproof::{PegInProof, PegInProofError};
 
 pub mod config;
 pub mod endpoint_constants;
 pub mod envs;
 pub mod keys;
+pub mod proof_of_reserves;
+pub mod silent_payment;
 pub mod tweakable;
 pub mod txoproof;
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("wallet");
-pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 2);
+pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 8);
 
 /// Module consensus version that introduced support for processing Bitcoin
 /// transactions that exceed the `ALEPH_BFT_UNIT_BYTE_LIMIT`.
 pub const SAFE_DEPOSIT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
     ModuleConsensusVersion::new(2, 2);
 
+/// Module consensus version that introduced batched peg-outs and the session
+/// count votes used to close a batch.
+pub const BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 3);
+
+/// Module consensus version that introduced peg-outs to explicit output
+/// scripts.
+pub const SCRIPT_PEG_OUT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 4);
+
+/// Module consensus version that introduced child-pays-for-parent fee bumping
+/// of stuck federation transactions.
+pub const CPFP_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 5);
+
+/// Module consensus version that introduced the consolidation of spendable
+/// UTXOs while the consensus fee rate is low.
+pub const CONSOLIDATION_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 6);
+
+/// Module consensus version that introduced child-pays-for-parent fee bumping
+/// of unconfirmed deposits on request of the depositor.
+pub const DEPOSIT_CPFP_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 7);
+
+/// Module consensus version that introduced peg-outs to BIP-352 silent payment
+/// addresses.
+pub const SILENT_PAYMENT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 8);
+
+/// Maximum weight of a deposit transaction the federation accelerates, since
+/// guardians exchange it in a consensus item
+pub const MAX_DEPOSIT_CPFP_PARENT_WEIGHT: u64 = 40_000;
+
 /// To further mitigate the risk of a peg-out transaction getting stuck in the
 /// mempool, we multiply the feerate estimate returned from the backend by this
 /// value.
@@ -57,6 +98,23 @@ pub enum WalletConsensusItem {
     Feerate(Feerate),
     PegOutSignature(PegOutSignatureItem),
     ModuleConsensusVersion(ModuleConsensusVersion),
+    SessionCount(u64),
+    /// Vote to accelerate a stuck pending transaction by spending its change
+    /// output in a child transaction paying a higher fee rate
+    Cpfp(Txid),
+    /// Nonce commitments for a FROST signing session of a taproot peg-out
+    FrostNonces(FrostNoncesItem),
+    /// FROST signature shares for a taproot peg-out
+    FrostSignatureShares(FrostSignatureSharesItem),
+    /// The guardian's current fee rate estimate if it is low enough for the
+    /// guardian to want to consolidate spendable UTXOs, zero otherwise
+    ConsolidationFeerate(Feerate),
+    /// Vote to accelerate an unconfirmed deposit on request of its depositor
+    /// by spending the deposit in a child transaction
+    DepositCpfp(DepositCpfpRequest),
+    /// The guardian's share of the ECDH with the scan key of a silent payment
+    /// peg-out
+    SilentPaymentShare(SilentPaymentShareItem),
     #[encodable_default]
     Default {
         variant: u64,
@@ -87,6 +145,39 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                     version.major, version.minor
                 )
             }
+            WalletConsensusItem::SessionCount(session_count) => {
+                write!(f, "Wallet Session Count {session_count}")
+            }
+            WalletConsensusItem::Cpfp(txid) => {
+                write!(f, "Wallet CPFP vote for tx {txid}")
+            }
+            WalletConsensusItem::FrostNonces(nonces) => {
+                write!(f, "Wallet FROST nonces for Bitcoin TxId {}", nonces.txid)
+            }
+            WalletConsensusItem::FrostSignatureShares(shares) => {
+                write!(
+                    f,
+                    "Wallet FROST signature shares for Bitcoin TxId {}",
+                    shares.txid
+                )
+            }
+            WalletConsensusItem::ConsolidationFeerate(feerate) => {
+                write!(
+                    f,
+                    "Wallet Consolidation Feerate with sats per kvb {}",
+                    feerate.sats_per_kvb
+                )
+            }
+            WalletConsensusItem::DepositCpfp(request) => {
+                write!(f, "Wallet CPFP vote for deposit {}", request.outpoint())
+            }
+            WalletConsensusItem::SilentPaymentShare(share) => {
+                write!(
+                    f,
+                    "Wallet silent payment share for peg-out {}",
+                    share.out_point
+                )
+            }
             WalletConsensusItem::Default { variant, .. } => {
                 write!(f, "Unknown Wallet CI variant={variant}")
             }
@@ -100,6 +191,91 @@ pub struct PegOutSignatureItem {
     pub signature: Vec<secp256k1::ecdsa::Signature>,
 }
 
+/// Public nonces of a signer for one input of a taproot peg-out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct FrostNonceCommitment {
+    pub hiding: secp256k1::PublicKey,
+    pub binding: secp256k1::PublicKey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct FrostNoncesItem {
+    pub txid: Txid,
+    /// The signing round of the peg-out, which is restarted with a new signer
+    /// set if the current one fails to sign in time
+    pub round: u64,
+    /// One nonce commitment per transaction input
+    pub nonces: Vec<FrostNonceCommitment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct FrostSignatureSharesItem {
+    pub txid: Txid,
+    /// The signing round of the peg-out the shares were created for
+    pub round: u64,
+    /// One signature share per transaction input
+    pub shares: Vec<[u8; 32]>,
+}
+
+/// A guardian's share `s_i * B_scan` of the product of the federation's
+/// secret key and the scan key of a silent payment peg-out, along with a proof
+/// that it used the same secret as in its verification share
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct SilentPaymentShareItem {
+    /// The federation output of the peg-out
+    pub out_point: fedimint_core::OutPoint,
+    pub share: secp256k1::PublicKey,
+    /// Challenge of the discrete log equality proof
+    pub challenge: [u8; 32],
+    /// Response of the discrete log equality proof
+    pub response: [u8; 32],
+}
+
+/// A depositor's request to accelerate its unconfirmed deposit via CPFP. The
+/// fee of the child transaction is deducted from the deposit once it is
+/// claimed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct DepositCpfpRequest {
+    /// The unconfirmed deposit transaction
+    #[serde(with = "::fedimint_core::encoding::as_hex")]
+    pub transaction: bitcoin::Transaction,
+    /// Index of the deposit output
+    pub vout: u32,
+    /// The tweak of the deposit address, known to the depositor only
+    pub tweak_contract_key: secp256k1::PublicKey,
+}
+
+impl DepositCpfpRequest {
+    pub fn outpoint(&self) -> bitcoin::OutPoint {
+        bitcoin::OutPoint {
+            txid: self.transaction.compute_txid(),
+            vout: self.vout,
+        }
+    }
+
+    /// The deposit output, if the request is well-formed and pays to the
+    /// address `descriptor` derives from its tweak
+    pub fn deposit<C: secp256k1::Verification + secp256k1::Signing>(
+        &self,
+        descriptor: &PegInDescriptor,
+        secp: &secp256k1::Secp256k1<C>,
+    ) -> Option<&TxOut> {
+        if self.transaction.weight().to_wu() > MAX_DEPOSIT_CPFP_PARENT_WEIGHT {
+            return None;
+        }
+
+        self.transaction
+            .output
+            .get(self.vout as usize)
+            .filter(|output| {
+                output.script_pubkey
+                    == descriptor
+                        .tweak(&self.tweak_contract_key, secp)
+                        .script_pubkey()
+            })
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable)]
 pub struct SpendableUTXO {
     #[serde(with = "::fedimint_core::encoding::as_hex")]
@@ -140,6 +316,25 @@ pub struct WalletSummary {
     /// Change UTXOs created from peg-out transactions that have reached
     /// threshold signatures waiting for finality delay confirmations
     pub unconfirmed_change_utxos: Vec<TxOutputSummary>,
+    /// Child-pays-for-parent transactions spending the change of a stuck
+    /// transaction. The spent change is not listed as pending change anymore,
+    /// the child's change is listed instead.
+    #[serde(default)]
+    pub cpfp_txs: Vec<CpfpSummary>,
+}
+
+/// A child-pays-for-parent transaction accelerating a stuck transaction
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct CpfpSummary {
+    /// The stuck transaction whose change output is spent
+    pub parent_txid: Txid,
+    /// The child transaction
+    pub txid: Txid,
+    /// Fees paid by the child transaction on top of the parent's fees
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub fee: Amount,
+    /// Whether the child transaction has reached threshold signatures
+    pub signed: bool,
 }
 
 impl WalletSummary {
@@ -177,6 +372,14 @@ pub fn total_unconfirmed_change_balance(&self) -> Amount {
         WalletSummary::sum(self.unconfirmed_change_utxos.iter())
     }
 
+    /// Total fees paid by child-pays-for-parent transactions that have not
+    /// confirmed yet
+    pub fn total_pending_cpfp_fees(&self) -> Amount {
+        self.cpfp_txs
+            .iter()
+            .fold(Amount::ZERO, |acc, cpfp| cpfp.fee + acc)
+    }
+
     /// Total amount of all transaction outputs from peg-out transactions that
     /// are either waiting for threshold signatures or confirmations. This is
     /// the total in-flight amount leaving the wallet.
@@ -374,11 +577,68 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-extensible_associated_module_type!(
-    WalletOutput,
-    WalletOutputV0,
-    UnknownWalletOutputVariantError
-);
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum WalletOutput {
+    V0(WalletOutputV0),
+    V1(WalletOutputV1),
+    V2(WalletOutputV2),
+    V3(WalletOutputV3),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+impl WalletOutput {
+    pub fn maybe_v0_ref(&self) -> Option<&WalletOutputV0> {
+        match self {
+            WalletOutput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn amount(&self) -> Option<Amount> {
+        match self {
+            WalletOutput::V0(v0) => Some(v0.amount()),
+            WalletOutput::V1(v1) => Some(v1.amount()),
+            WalletOutput::V2(v2) => Some(v2.amount()),
+            WalletOutput::V3(v3) => Some(v3.amount()),
+            WalletOutput::Default { .. } => None,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown {} variant {variant}", stringify!($name))]
+pub struct UnknownWalletOutputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for WalletOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            WalletOutput::V0(inner) => std::fmt::Display::fmt(&inner, f),
+            WalletOutput::V1(inner) => std::fmt::Display::fmt(&inner, f),
+            WalletOutput::V2(inner) => std::fmt::Display::fmt(&inner, f),
+            WalletOutput::V3(inner) => std::fmt::Display::fmt(&inner, f),
+            WalletOutput::Default { variant, .. } => {
+                write!(f, "Unknown variant (variant={variant})")
+            }
+        }
+    }
+}
 
 impl WalletOutput {
     pub fn new_v0_peg_out(
@@ -395,6 +655,39 @@ pub fn new_v0_peg_out(
     pub fn new_v0_rbf(fees: PegOutFees, txid: Txid) -> WalletOutput {
         WalletOutput::V0(WalletOutputV0::Rbf(Rbf { fees, txid }))
     }
+    pub fn new_v1_batched_peg_out(
+        recipient: Address,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> WalletOutput {
+        WalletOutput::V1(WalletOutputV1::BatchedPegOut(PegOut {
+            recipient: recipient.into_unchecked(),
+            amount,
+            fees,
+        }))
+    }
+    pub fn new_v2_peg_out(
+        script_pubkey: ScriptBuf,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> WalletOutput {
+        WalletOutput::V2(WalletOutputV2 {
+            script_pubkey,
+            amount,
+            fees,
+        })
+    }
+    pub fn new_v3_silent_payment_peg_out(
+        address: SilentPaymentAddress,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> WalletOutput {
+        WalletOutput::V3(WalletOutputV3 {
+            address,
+            amount,
+            fees,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -437,6 +730,130 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum WalletOutputV1 {
+    /// A peg-out that is queued and paid out together with all other batched
+    /// peg-outs of the same session window in a single transaction. The
+    /// `fees` only cover this peg-out's share of the batch transaction as
+    /// returned by the batch fee endpoint.
+    BatchedPegOut(PegOut),
+}
+
+impl WalletOutputV1 {
+    pub fn amount(&self) -> Amount {
+        match self {
+            WalletOutputV1::BatchedPegOut(pegout) => pegout.amount + pegout.fees.amount(),
+        }
+    }
+}
+
+impl std::fmt::Display for WalletOutputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletOutputV1::BatchedPegOut(pegout) => {
+                write!(
+                    f,
+                    "Wallet batched PegOut {} to {}",
+                    pegout.amount,
+                    pegout.recipient.clone().assume_checked()
+                )
+            }
+        }
+    }
+}
+
+/// A peg-out to an explicit output script, e.g. derived from an output
+/// descriptor, for destinations that have no address encoding. Silent payment
+/// outputs depend on the inputs of the peg-out transaction and can not be
+/// expressed this way, see [`WalletOutputV3`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct WalletOutputV2 {
+    pub script_pubkey: ScriptBuf,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: bitcoin::Amount,
+    pub fees: PegOutFees,
+}
+
+impl WalletOutputV2 {
+    pub fn amount(&self) -> Amount {
+        self.amount + self.fees.amount()
+    }
+}
+
+impl std::fmt::Display for WalletOutputV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Wallet PegOut {} to script {}",
+            self.amount,
+            self.script_pubkey.to_hex_string()
+        )
+    }
+}
+
+/// A peg-out to a BIP-352 silent payment address. The output key depends on
+/// an ECDH between the sum of the secret keys of the transaction's inputs and
+/// the recipient's scan key, hence the guardians complete the peg-out
+/// transaction only once a threshold of them contributed their share of the
+/// ECDH via consensus. The `fees` are those of a peg-out to
+/// [`SilentPaymentAddress::placeholder_script_pubkey`].
+///
+/// Only supported by taproot federations, since the multisig inputs of legacy
+/// federations are not eligible for silent payments.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct WalletOutputV3 {
+    pub address: SilentPaymentAddress,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: bitcoin::Amount,
+    pub fees: PegOutFees,
+}
+
+impl WalletOutputV3 {
+    pub fn amount(&self) -> Amount {
+        self.amount + self.fees.amount()
+    }
+}
+
+impl std::fmt::Display for WalletOutputV3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Wallet PegOut {} to silent payment spend key {}",
+            self.amount, self.address.spend_key
+        )
+    }
+}
+
+/// Returns the script pubkey to peg-out to for an output descriptor without
+/// wildcards, e.g. `wpkh(02...)` or `tr(...)`.
+pub fn descriptor_script_pubkey(descriptor: &str) -> anyhow::Result<ScriptBuf> {
+    let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor)?;
+
+    ensure!(
+        !descriptor.has_wildcard(),
+        "Descriptor must not contain wildcards"
+    );
+
+    Ok(descriptor
+        .at_derivation_index(0)
+        .expect("Descriptor has no wildcards")
+        .script_pubkey())
+}
+
+/// Whether the wallet will pay out to `script`. Besides legacy P2PKH and P2SH
+/// outputs we accept all witness programs of future segwit versions, since
+/// these are standard outputs for bitcoind to relay. Version zero programs are
+/// only standard as P2WPKH or P2WSH, any other length is unspendable.
+pub fn is_standard_peg_out_script(script: &Script) -> bool {
+    if script.is_witness_program() {
+        return script.witness_version() != Some(WitnessVersion::V0)
+            || script.is_p2wpkh()
+            || script.is_p2wsh();
+    }
+
+    script.is_p2pkh() || script.is_p2sh()
+}
+
 pub struct WalletModuleTypes;
 
 pub fn proprietary_tweak_key() -> ProprietaryKey {
@@ -512,6 +929,18 @@ pub enum WalletOutputError {
     BelowMinRelayFee,
     #[error("The wallet output version is not supported by this federation")]
     UnknownOutputVariant(#[from] UnknownWalletOutputVariantError),
+    #[error("Batched peg-outs are not supported by the active consensus version")]
+    BatchedPegOutNotSupported,
+    #[error("Peg-outs to scripts are not supported by the active consensus version")]
+    ScriptPegOutNotSupported,
+    #[error("Peg-out script is not a standard output script")]
+    NonStandardScript,
+    #[error("Transaction fee exceeds the fee income available to the federation")]
+    FeeBudgetExceeded,
+    #[error("Silent payment peg-outs are not supported by this federation")]
+    SilentPaymentNotSupported,
+    #[error("The peg-out transaction is not eligible for a silent payment")]
+    SilentPaymentIneligible,
 }
 
 // For backwards-compatibility with old clients, we use an UnknownOutputVariant
@@ -0,0 +1,242 @@
+//! [BIP-127] style proofs of reserves of the federation's on-chain wallet
+//!
+//! A proof is a PSBT whose first input spends a non-existent challenge
+//! outpoint derived from a [`ReservesCommitment`], which makes the transaction
+//! unbroadcastable, while all further inputs spend the wallet's UTXOs and are
+//! signed by a threshold of guardians. Since every signature commits to the
+//! challenge input, the proof binds the federation's reserves to the
+//! liabilities it claims.
+//!
+//! [BIP-127]: https://github.com/bitcoin/bips/blob/master/bip-0127.mediawiki
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, anyhow, ensure};
+use bitcoin::hashes::{Hash, sha256};
+use bitcoin::opcodes::all::OP_RETURN;
+use bitcoin::psbt::Psbt;
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{Secp256k1, Signing, Verification};
+use bitcoin::sighash::Prevouts;
+use bitcoin::{OutPoint, ScriptBuf, TxOut, Txid};
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::{Decodable, Encodable};
+use miniscript::Descriptor;
+use miniscript::psbt::PsbtExt;
+use serde::{Deserialize, Serialize};
+
+use crate::tweakable::Tweakable;
+use crate::{PegInDescriptor, proprietary_tweak_key};
+
+/// Prefix of the message hashed into the challenge txid as defined by BIP-127
+const CHALLENGE_PREFIX: &[u8] = b"Proof-of-Reserves: ";
+
+/// The liabilities of the federation a proof of reserves commits to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encodable, Decodable)]
+pub struct ReservesCommitment {
+    pub federation_id: FederationId,
+    /// Number of sessions the guardian had completed when it took the audit
+    pub session_count: u64,
+    /// Total ecash and contract liabilities according to the audit
+    pub liabilities: fedimint_core::Amount,
+}
+
+impl ReservesCommitment {
+    /// The BIP-127 message of the proof, meant to be readable by humans and
+    /// other proof of reserves tooling
+    pub fn message(&self) -> String {
+        format!(
+            "Fedimint federation {} has liabilities of {} msat after session {}",
+            self.federation_id, self.liabilities.msats, self.session_count
+        )
+    }
+
+    /// The outpoint spent by the first input of the proof
+    pub fn challenge_outpoint(&self) -> OutPoint {
+        let mut message = CHALLENGE_PREFIX.to_vec();
+        message.extend(self.message().as_bytes());
+
+        OutPoint {
+            txid: Txid::from_byte_array(sha256::Hash::hash(&message).to_byte_array()),
+            vout: 0,
+        }
+    }
+}
+
+/// The output of a proof of reserves, which can't be spent even if the
+/// challenge input could
+pub fn proof_of_reserves_output_script() -> ScriptBuf {
+    Builder::new().push_opcode(OP_RETURN).into_script()
+}
+
+/// The UTXO the challenge input claims to spend. PSBT finalizers require the
+/// spent output of every input, even though it is not part of any sighash.
+pub fn challenge_utxo() -> TxOut {
+    TxOut {
+        value: bitcoin::Amount::ZERO,
+        script_pubkey: proof_of_reserves_output_script(),
+    }
+}
+
+/// A proof of reserves, signed by a single guardian or a threshold of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encodable, Decodable)]
+pub struct ProofOfReserves {
+    pub commitment: ReservesCommitment,
+    #[serde(with = "::fedimint_core::encoding::as_base64")]
+    pub psbt: Psbt,
+}
+
+/// The result of successfully verifying a [`ProofOfReserves`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiedReserves {
+    pub commitment: ReservesCommitment,
+    /// The UTXOs the federation proved control over
+    pub utxos: Vec<(OutPoint, TxOut)>,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub reserves: bitcoin::Amount,
+    /// Whether the reserves cover the liabilities of the commitment
+    pub solvent: bool,
+}
+
+impl ProofOfReserves {
+    /// Merges the signatures of several partially signed proofs for the same
+    /// commitment and finalizes the resulting PSBT
+    pub fn combine<C: Verification>(
+        proofs: Vec<ProofOfReserves>,
+        secp: &Secp256k1<C>,
+    ) -> anyhow::Result<ProofOfReserves> {
+        let mut proofs = proofs.into_iter();
+
+        let mut combined = proofs.next().context("No proofs to combine")?;
+
+        for proof in proofs {
+            ensure!(
+                proof.commitment == combined.commitment,
+                "Proofs commit to different liabilities"
+            );
+
+            combined
+                .psbt
+                .combine(proof.psbt)
+                .context("Proofs spend different UTXOs")?;
+        }
+
+        // The challenge input can not be satisfied, so every other input is
+        // finalized on its own
+        for idx in 1..combined.psbt.inputs.len() {
+            // The finalizer clears the tweak, which verifiers need to derive the
+            // script of the input's UTXO
+            let proprietary = combined.psbt.inputs[idx].proprietary.clone();
+
+            combined
+                .psbt
+                .finalize_inp_mut(secp, idx)
+                .map_err(|e| {
+                    anyhow!(
+                        "Failed to finalize input {idx}, are there signatures of a threshold of guardians? {e}"
+                    )
+                })?;
+
+            combined.psbt.inputs[idx].proprietary = proprietary;
+        }
+
+        Ok(combined)
+    }
+
+    /// Verifies that the proof is bound to its commitment and that every UTXO
+    /// is controlled by the federation with the peg-in descriptor
+    /// `descriptor`.
+    ///
+    /// Whether the UTXOs actually exist and are unspent has to be checked
+    /// against the blockchain separately.
+    pub fn verify<C: Verification + Signing>(
+        &self,
+        descriptor: &PegInDescriptor,
+        secp: &Secp256k1<C>,
+    ) -> anyhow::Result<VerifiedReserves> {
+        ensure!(
+            !matches!(descriptor, Descriptor::Tr(_)),
+            "Proofs of reserves of taproot wallets are not supported"
+        );
+
+        let tx = &self.psbt.unsigned_tx;
+
+        ensure!(
+            tx.input.first().map(|input| input.previous_output)
+                == Some(self.commitment.challenge_outpoint()),
+            "The first input does not spend the challenge of the commitment"
+        );
+
+        ensure!(
+            tx.output.len() == 1 && tx.output[0].script_pubkey == proof_of_reserves_output_script(),
+            "The proof has to have a single unspendable output"
+        );
+
+        let mut outpoints = BTreeSet::new();
+
+        for (idx, tx_input) in tx.input.iter().enumerate() {
+            ensure!(
+                outpoints.insert(tx_input.previous_output),
+                "Input {idx} spends the same outpoint as a previous input"
+            );
+        }
+
+        let mut utxos = vec![];
+
+        for (idx, (tx_input, psbt_input)) in
+            tx.input.iter().zip(&self.psbt.inputs).enumerate().skip(1)
+        {
+            let tweak = psbt_input
+                .proprietary
+                .get(&proprietary_tweak_key())
+                .with_context(|| format!("Input {idx} is missing its tweak"))?;
+
+            let utxo = psbt_input
+                .witness_utxo
+                .clone()
+                .with_context(|| format!("Input {idx} is missing its UTXO"))?;
+
+            ensure!(
+                descriptor.tweak(tweak, secp).script_pubkey() == utxo.script_pubkey,
+                "Input {idx} does not belong to the federation's wallet"
+            );
+
+            let witness = psbt_input
+                .final_script_witness
+                .as_ref()
+                .with_context(|| format!("Input {idx} is not signed"))?;
+
+            let interpreter = miniscript::Interpreter::from_txdata(
+                &utxo.script_pubkey,
+                &tx_input.script_sig,
+                witness,
+                tx_input.sequence,
+                tx.lock_time,
+            )
+            .map_err(|e| anyhow!("Input {idx} has an invalid witness: {e}"))?;
+
+            for satisfied in interpreter.iter(secp, tx, idx, &Prevouts::One(idx, &utxo)) {
+                satisfied.map_err(|e| anyhow!("Input {idx} has an invalid signature: {e}"))?;
+            }
+
+            utxos.push((tx_input.previous_output, utxo));
+        }
+
+        let reserves = utxos
+            .iter()
+            .map(|(_, utxo)| utxo.value)
+            .sum::<bitcoin::Amount>();
+
+        ensure!(
+            tx.output[0].value == reserves,
+            "The output does not match the total reserves"
+        );
+
+        Ok(VerifiedReserves {
+            commitment: self.commitment.clone(),
+            utxos,
+            reserves,
+            solvent: fedimint_core::Amount::from(reserves) >= self.commitment.liabilities,
+        })
+    }
+}
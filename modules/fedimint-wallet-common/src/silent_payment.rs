@@ -0,0 +1,132 @@
+//! Addresses of BIP-352 silent payments, which encode a scan and a spend key
+//! instead of an output script. The output key of a payment is derived from
+//! the keys of the inputs of the paying transaction, so the address can be
+//! published without every payment to it landing on the same script.
+
+use anyhow::{Context, ensure};
+use bitcoin::bech32::primitives::decode::CheckedHrpstring;
+use bitcoin::bech32::{Bech32m, ByteIterExt, Fe32, Fe32IterExt, Hrp};
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::{Network, ScriptBuf, secp256k1};
+use fedimint_core::encoding::{Decodable, Encodable};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct SilentPaymentAddress {
+    /// The key the recipient scans the chain for payments with
+    pub scan_key: secp256k1::PublicKey,
+    /// The key the recipient spends the payments with
+    pub spend_key: secp256k1::PublicKey,
+}
+
+impl SilentPaymentAddress {
+    /// Parses a version zero silent payment address for `network`
+    pub fn parse(address: &str, network: Network) -> anyhow::Result<Self> {
+        let mut address = CheckedHrpstring::new::<Bech32m>(address)
+            .context("Silent payment address is not valid bech32m")?;
+
+        ensure!(
+            address.hrp() == hrp(network),
+            "Silent payment address is not for network {network}"
+        );
+
+        ensure!(
+            address
+                .remove_witness_version()
+                .context("Silent payment address has no version")?
+                == Fe32::Q,
+            "Only version zero silent payment addresses are supported"
+        );
+
+        let keys = address.byte_iter().collect::<Vec<u8>>();
+
+        ensure!(
+            keys.len() == 66,
+            "Silent payment address does not encode two public keys"
+        );
+
+        Ok(SilentPaymentAddress {
+            scan_key: secp256k1::PublicKey::from_slice(&keys[..33]).context("Invalid scan key")?,
+            spend_key: secp256k1::PublicKey::from_slice(&keys[33..])
+                .context("Invalid spend key")?,
+        })
+    }
+
+    /// Encodes the address as a version zero silent payment address for
+    /// `network`
+    pub fn encode(&self, network: Network) -> String {
+        self.scan_key
+            .serialize()
+            .into_iter()
+            .chain(self.spend_key.serialize())
+            .bytes_to_fes()
+            .with_checksum::<Bech32m>(&hrp(network))
+            .with_witness_version(Fe32::Q)
+            .chars()
+            .collect()
+    }
+
+    /// A taproot output script of the same size as the output of a payment to
+    /// this address, used to estimate fees before its output key is known
+    pub fn placeholder_script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+            self.spend_key.x_only_public_key().0,
+        ))
+    }
+}
+
+fn hrp(network: Network) -> Hrp {
+    match network {
+        Network::Bitcoin => Hrp::parse_unchecked("sp"),
+        Network::Regtest => Hrp::parse_unchecked("sprt"),
+        _ => Hrp::parse_unchecked("tsp"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{Network, secp256k1};
+
+    use super::SilentPaymentAddress;
+
+    // Receiving address of the first test vector of BIP-352
+    const ADDRESS: &str = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+
+    #[test]
+    fn address_should_round_trip() {
+        let address = SilentPaymentAddress::parse(ADDRESS, Network::Bitcoin).unwrap();
+
+        assert_eq!(
+            address.scan_key,
+            secp256k1::PublicKey::from_str(
+                "0220bcfac5b99e04ad1a06ddfb016ee13582609d60b6291e98d01a9bc9a16c96d4"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            address.spend_key,
+            secp256k1::PublicKey::from_str(
+                "025cc9856d6f8375350e123978daac200c260cb5b5ae83106cab90484dcd8fcf36"
+            )
+            .unwrap()
+        );
+        assert_eq!(address.encode(Network::Bitcoin), ADDRESS);
+
+        let regtest = address.encode(Network::Regtest);
+        assert!(regtest.starts_with("sprt1q"));
+        assert_eq!(
+            SilentPaymentAddress::parse(&regtest, Network::Regtest).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn address_should_be_rejected_for_other_network() {
+        assert!(SilentPaymentAddress::parse(ADDRESS, Network::Signet).is_err());
+        assert!(
+            SilentPaymentAddress::parse(&ADDRESS[..ADDRESS.len() - 1], Network::Bitcoin).is_err()
+        );
+    }
+}
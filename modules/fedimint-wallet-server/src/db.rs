@@ -1,3 +1,4 @@
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::secp256k1::ecdsa::Signature;
 use bitcoin::{BlockHash, OutPoint, TxOut, Txid};
 use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
@@ -7,11 +8,17 @@
 use fedimint_server_core::migration::{
     ModuleHistoryItem, ServerModuleDbMigrationFnContext, ServerModuleDbMigrationFnContextExt as _,
 };
+use fedimint_wallet_common::{
+    DepositCpfpRequest, FrostNonceCommitment, SilentPaymentShareItem, WalletOutputV3,
+};
 use futures::StreamExt;
 use serde::Serialize;
 use strum_macros::EnumIter;
 
-use crate::{PendingTransaction, SpendableUTXO, UnsignedTransaction, Wallet, WalletOutputOutcome};
+use crate::frost::SigningNonces;
+use crate::{
+    PegOut, PendingTransaction, SpendableUTXO, UnsignedTransaction, Wallet, WalletOutputOutcome,
+};
 
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
@@ -29,6 +36,25 @@ pub enum DbKeyPrefix {
     ConsensusVersionVote = 0x40,
     UnspentTxOut = 0x41,
     ConsensusVersionVotingActivation = 0x42,
+    SessionCountVote = 0x43,
+    PegOutBatchItem = 0x44,
+    PegOutBatchStart = 0x45,
+    CpfpVote = 0x46,
+    CpfpChild = 0x47,
+    FrostSecretNonces = 0x48,
+    FrostNonce = 0x49,
+    FrostShareCi = 0x4a,
+    FrostShare = 0x4b,
+    UnspentTxOutHeight = 0x4c,
+    ConsolidationFeeRateVote = 0x4d,
+    FeeBudget = 0x4e,
+    FrostSigningRound = 0x4f,
+    DepositCpfpRequest = 0x50,
+    DepositCpfpVote = 0x51,
+    DepositCpfp = 0x52,
+    PendingSilentPayment = 0x53,
+    SilentPaymentShareCi = 0x54,
+    SilentPaymentShare = 0x55,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -259,3 +285,398 @@ pub async fn migrate_to_v1(
     key = ConsensusVersionVotingActivationKey,
     query_prefix = ConsensusVersionVotingActivationPrefix
 );
+
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct SessionCountVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct SessionCountVotePrefix;
+
+impl_db_record!(
+    key = SessionCountVoteKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::SessionCountVote
+);
+
+impl_db_lookup!(
+    key = SessionCountVoteKey,
+    query_prefix = SessionCountVotePrefix
+);
+
+/// A batched peg-out that was accepted but not yet included in a peg-out
+/// transaction, keyed by the out point of the federation output
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PegOutBatchItemKey(pub fedimint_core::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PegOutBatchItemPrefix;
+
+impl_db_record!(
+    key = PegOutBatchItemKey,
+    value = PegOut,
+    db_prefix = DbKeyPrefix::PegOutBatchItem,
+);
+
+impl_db_lookup!(
+    key = PegOutBatchItemKey,
+    query_prefix = PegOutBatchItemPrefix
+);
+
+/// Consensus session count at which the currently open peg-out batch received
+/// its first peg-out
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PegOutBatchStartKey;
+
+impl_db_record!(
+    key = PegOutBatchStartKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::PegOutBatchStart,
+);
+
+/// A peer's vote to accelerate a stuck pending transaction via CPFP
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct CpfpVoteKey(pub Txid, pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct CpfpVoteTxidPrefix(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct CpfpVotePrefix;
+
+impl_db_record!(
+    key = CpfpVoteKey,
+    value = (),
+    db_prefix = DbKeyPrefix::CpfpVote,
+);
+
+impl_db_lookup!(
+    key = CpfpVoteKey,
+    query_prefix = CpfpVotePrefix,
+    query_prefix = CpfpVoteTxidPrefix
+);
+
+/// The child transaction spending the change output of a pending transaction,
+/// keyed by the txid of the parent. Removed once the parent confirms.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct CpfpChildKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct CpfpChild {
+    pub txid: Txid,
+    /// The change of the parent that is spent by the child, so it must not be
+    /// counted as pending change anymore
+    pub spent_change: bitcoin::Amount,
+    /// The fee paid by the child out of the [`FeeBudgetKey`]
+    pub fee: bitcoin::Amount,
+}
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct CpfpChildPrefix;
+
+impl_db_record!(
+    key = CpfpChildKey,
+    value = CpfpChild,
+    db_prefix = DbKeyPrefix::CpfpChild,
+);
+
+impl_db_lookup!(key = CpfpChildKey, query_prefix = CpfpChildPrefix);
+
+/// Our unused secret nonces for a taproot peg-out. Their commitments are
+/// proposed while we are not part of the signer set, they are replaced as soon
+/// as we sign with them.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FrostSecretNoncesKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostSecretNoncesPrefix;
+
+impl_db_record!(
+    key = FrostSecretNoncesKey,
+    value = Vec<SigningNonces>,
+    db_prefix = DbKeyPrefix::FrostSecretNonces,
+);
+impl_db_lookup!(
+    key = FrostSecretNoncesKey,
+    query_prefix = FrostSecretNoncesPrefix
+);
+
+/// The signing round of a taproot peg-out
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FrostSigningRoundKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostSigningRoundPrefix;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encodable, Decodable, Serialize)]
+pub struct FrostSigningRound {
+    /// Incremented whenever the signer set is restarted
+    pub round: u64,
+    /// The session the signer set of this round was completed in
+    pub complete_session: Option<u64>,
+}
+
+impl FrostSigningRound {
+    /// Whether the signer set of this round has had enough time to sign and
+    /// may be replaced by the peers that are not part of it
+    pub fn is_expired(&self, session_count: u64, timeout_sessions: u64) -> bool {
+        self.complete_session
+            .is_some_and(|complete_session| complete_session + timeout_sessions <= session_count)
+    }
+}
+
+impl_db_record!(
+    key = FrostSigningRoundKey,
+    value = FrostSigningRound,
+    db_prefix = DbKeyPrefix::FrostSigningRound,
+);
+impl_db_lookup!(
+    key = FrostSigningRoundKey,
+    query_prefix = FrostSigningRoundPrefix
+);
+
+/// The nonce commitments of the first threshold of peers in the current
+/// signing round of a taproot peg-out, which form its signer set
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FrostNonceKey(pub Txid, pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostNonceTxidPrefix(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostNoncePrefix;
+
+impl_db_record!(
+    key = FrostNonceKey,
+    value = Vec<FrostNonceCommitment>,
+    db_prefix = DbKeyPrefix::FrostNonce,
+);
+
+impl_db_lookup!(
+    key = FrostNonceKey,
+    query_prefix = FrostNoncePrefix,
+    query_prefix = FrostNonceTxidPrefix
+);
+
+/// Our signature shares for a taproot peg-out if we are part of its signer
+/// set
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FrostShareCI(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostShareCIPrefix;
+
+impl_db_record!(
+    key = FrostShareCI,
+    value = Vec<[u8; 32]>,
+    db_prefix = DbKeyPrefix::FrostShareCi,
+);
+impl_db_lookup!(key = FrostShareCI, query_prefix = FrostShareCIPrefix);
+
+/// The verified signature shares of the signer set of a taproot peg-out
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FrostShareKey(pub Txid, pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostShareTxidPrefix(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FrostSharePrefix;
+
+impl_db_record!(
+    key = FrostShareKey,
+    value = Vec<[u8; 32]>,
+    db_prefix = DbKeyPrefix::FrostShare,
+);
+
+impl_db_lookup!(
+    key = FrostShareKey,
+    query_prefix = FrostSharePrefix,
+    query_prefix = FrostShareTxidPrefix
+);
+
+/// The block height an unspent transaction output was confirmed at. Only
+/// tracked if the peg-in descriptor has an emergency recovery path, whose
+/// timelock starts at confirmation.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct UnspentTxOutHeightKey(pub bitcoin::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct UnspentTxOutHeightPrefix;
+
+impl_db_record!(
+    key = UnspentTxOutHeightKey,
+    value = u32,
+    db_prefix = DbKeyPrefix::UnspentTxOutHeight,
+);
+impl_db_lookup!(
+    key = UnspentTxOutHeightKey,
+    query_prefix = UnspentTxOutHeightPrefix
+);
+
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct ConsolidationFeeRateVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct ConsolidationFeeRateVotePrefix;
+
+impl_db_record!(
+    key = ConsolidationFeeRateVoteKey,
+    value = fedimint_core::Feerate,
+    db_prefix = DbKeyPrefix::ConsolidationFeeRateVote
+);
+
+impl_db_lookup!(
+    key = ConsolidationFeeRateVoteKey,
+    query_prefix = ConsolidationFeeRateVotePrefix
+);
+
+/// Fee income from peg-ins and peg-outs that was not spent on transactions the
+/// federation initiates on its own yet. Since this income is part of the
+/// federation's surplus, paying for such transactions out of it can not make
+/// the balance sheet go negative.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct FeeBudgetKey;
+
+impl_db_record!(
+    key = FeeBudgetKey,
+    value = fedimint_core::Amount,
+    db_prefix = DbKeyPrefix::FeeBudget,
+);
+
+/// Requests to accelerate an unconfirmed deposit that were submitted to us and
+/// that we keep proposing until the federation processed them
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct DepositCpfpRequestKey(pub OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DepositCpfpRequestPrefix;
+
+impl_db_record!(
+    key = DepositCpfpRequestKey,
+    value = DepositCpfpRequest,
+    db_prefix = DbKeyPrefix::DepositCpfpRequest,
+);
+
+impl_db_lookup!(
+    key = DepositCpfpRequestKey,
+    query_prefix = DepositCpfpRequestPrefix
+);
+
+/// A peer's vote to accelerate an unconfirmed deposit via CPFP
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct DepositCpfpVoteKey(pub OutPoint, pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DepositCpfpVoteOutpointPrefix(pub OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DepositCpfpVotePrefix;
+
+impl_db_record!(
+    key = DepositCpfpVoteKey,
+    value = (),
+    db_prefix = DbKeyPrefix::DepositCpfpVote,
+);
+
+impl_db_lookup!(
+    key = DepositCpfpVoteKey,
+    query_prefix = DepositCpfpVotePrefix,
+    query_prefix = DepositCpfpVoteOutpointPrefix
+);
+
+/// The child transaction spending an accelerated deposit, keyed by the
+/// outpoint of the deposit. Removed once the deposit is claimed.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct DepositCpfpKey(pub OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct DepositCpfp {
+    /// The deposit transaction, which we broadcast together with the child
+    pub parent: bitcoin::Transaction,
+    pub txid: Txid,
+    /// The fee of the child, deducted from the deposit once it is claimed
+    pub fee: bitcoin::Amount,
+    /// The change of the child, which is not backed by e-cash until the
+    /// deposit is claimed
+    pub change: bitcoin::Amount,
+}
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DepositCpfpPrefix;
+
+impl_db_record!(
+    key = DepositCpfpKey,
+    value = DepositCpfp,
+    db_prefix = DbKeyPrefix::DepositCpfp,
+);
+
+impl_db_lookup!(key = DepositCpfpKey, query_prefix = DepositCpfpPrefix);
+
+/// A silent payment peg-out whose transaction waits for a threshold of shares
+/// of the ECDH with the recipient's scan key, keyed by the out point of the
+/// federation output. Its inputs are reserved already.
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PendingSilentPaymentKey(pub fedimint_core::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PendingSilentPayment {
+    pub output: WalletOutputV3,
+    /// The peg-out transaction paying to the placeholder script of the
+    /// address, which is replaced by the silent payment output
+    pub tx: UnsignedTransaction,
+}
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PendingSilentPaymentPrefix;
+
+impl_db_record!(
+    key = PendingSilentPaymentKey,
+    value = PendingSilentPayment,
+    db_prefix = DbKeyPrefix::PendingSilentPayment,
+);
+
+impl_db_lookup!(
+    key = PendingSilentPaymentKey,
+    query_prefix = PendingSilentPaymentPrefix
+);
+
+/// Our share of the ECDH for a pending silent payment peg-out, proposed until
+/// it was processed
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct SilentPaymentShareCI(pub fedimint_core::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct SilentPaymentShareCIPrefix;
+
+impl_db_record!(
+    key = SilentPaymentShareCI,
+    value = SilentPaymentShareItem,
+    db_prefix = DbKeyPrefix::SilentPaymentShareCi,
+);
+
+impl_db_lookup!(
+    key = SilentPaymentShareCI,
+    query_prefix = SilentPaymentShareCIPrefix
+);
+
+/// The verified shares of the ECDH for a pending silent payment peg-out
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct SilentPaymentShareKey(pub fedimint_core::OutPoint, pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct SilentPaymentShareOutPointPrefix(pub fedimint_core::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct SilentPaymentSharePrefix;
+
+impl_db_record!(
+    key = SilentPaymentShareKey,
+    value = PublicKey,
+    db_prefix = DbKeyPrefix::SilentPaymentShare,
+);
+
+impl_db_lookup!(
+    key = SilentPaymentShareKey,
+    query_prefix = SilentPaymentSharePrefix,
+    query_prefix = SilentPaymentShareOutPointPrefix
+);
@@ -1,3 +1,4 @@
+use fedimint_core::Feerate;
 use fedimint_core::util::FmtCompact as _;
 use fedimint_logging::LOG_MODULE_WALLET;
 use fedimint_wallet_common::FEERATE_MULTIPLIER_DEFAULT;
@@ -5,6 +6,10 @@
 
 pub const FM_WALLET_FEERATE_MULTIPLIER_ENV: &str = "FM_WALLET_FEERATE_MULTIPLIER";
 
+/// Fee rate in sats/vB up to which this guardian votes to consolidate the
+/// federation's spendable UTXOs
+pub const FM_WALLET_CONSOLIDATION_FEERATE_ENV: &str = "FM_WALLET_CONSOLIDATION_FEERATE";
+
 pub fn get_feerate_multiplier() -> f64 {
     if let Ok(mult) = std::env::var(FM_WALLET_FEERATE_MULTIPLIER_ENV) {
         match mult.parse::<f64>() {
@@ -21,3 +26,26 @@ pub fn get_feerate_multiplier() -> f64 {
 
     FEERATE_MULTIPLIER_DEFAULT
 }
+
+/// Returns the fee rate up to which this guardian wants to consolidate UTXOs,
+/// zero if consolidation is disabled
+pub fn get_consolidation_feerate() -> Feerate {
+    if let Ok(sats_per_vbyte) = std::env::var(FM_WALLET_CONSOLIDATION_FEERATE_ENV) {
+        match sats_per_vbyte.parse::<u64>() {
+            Ok(sats_per_vbyte) => {
+                return Feerate {
+                    sats_per_kvb: sats_per_vbyte.saturating_mul(1000),
+                };
+            }
+            Err(err) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    err = %err.fmt_compact(),
+                    "Invalid consolidation fee rate string"
+                );
+            }
+        }
+    }
+
+    Feerate { sats_per_kvb: 0 }
+}
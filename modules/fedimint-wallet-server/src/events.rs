@@ -0,0 +1,123 @@
+use bitcoin::Txid;
+use fedimint_core::core::ModuleKind;
+use fedimint_core::{Amount, OutPoint};
+use fedimint_eventlog::{Event, EventKind};
+use serde::{Deserialize, Serialize};
+
+/// Event that is emitted when a peg-in is claimed by a transaction input
+#[derive(Serialize, Deserialize)]
+pub struct PegInAccepted {
+    /// The bitcoin outpoint that was pegged in
+    pub outpoint: bitcoin::OutPoint,
+
+    /// The amount being pegged in
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for PegInAccepted {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("peg-in-accepted");
+}
+
+/// Event that is emitted when a peg-out transaction was created and signed by
+/// this guardian
+#[derive(Serialize, Deserialize)]
+pub struct PegOutCreated {
+    /// The output of the fedimint transaction requesting the peg-out
+    pub out_point: OutPoint,
+
+    /// The bitcoin transaction ID
+    pub txid: Txid,
+
+    /// The amount being pegged out
+    pub amount: Amount,
+
+    /// The fee charged by the federation
+    pub fee: Amount,
+}
+
+impl Event for PegOutCreated {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("peg-out-created");
+}
+
+/// Event that is emitted when a threshold of guardians signed a peg-out
+/// transaction, so it can be broadcast
+#[derive(Serialize, Deserialize)]
+pub struct PegOutSigned {
+    /// The bitcoin transaction ID
+    pub txid: Txid,
+}
+
+impl Event for PegOutSigned {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("peg-out-signed");
+}
+
+/// Event that is emitted when a threshold of guardians agreed to accelerate a
+/// stuck transaction by spending its change in a child transaction, which was
+/// then created and signed by this guardian
+#[derive(Serialize, Deserialize)]
+pub struct CpfpCreated {
+    /// The bitcoin transaction ID of the stuck transaction
+    pub parent_txid: Txid,
+
+    /// The bitcoin transaction ID of the child transaction
+    pub txid: Txid,
+
+    /// The fee paid by the child transaction
+    pub fee: Amount,
+}
+
+impl Event for CpfpCreated {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("cpfp-created");
+}
+
+/// Event that is emitted when UTXOs approaching the timelock of the emergency
+/// recovery path were spent back to the federation
+#[derive(Serialize, Deserialize)]
+pub struct UtxosRefreshed {
+    /// The bitcoin transaction ID of the refresh transaction
+    pub txid: Txid,
+
+    /// The number of refreshed UTXOs
+    pub utxo_count: u64,
+
+    /// The fee paid by the refresh transaction
+    pub fee: Amount,
+}
+
+impl Event for UtxosRefreshed {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("utxos-refreshed");
+}
+
+/// Event that is emitted when a threshold of guardians considered the
+/// consensus fee rate low enough to consolidate small spendable UTXOs into a
+/// single one
+#[derive(Serialize, Deserialize)]
+pub struct UtxosConsolidated {
+    /// The bitcoin transaction ID of the consolidation transaction
+    pub txid: Txid,
+
+    /// The number of consolidated UTXOs
+    pub utxo_count: u64,
+
+    /// The fee paid by the consolidation transaction
+    pub fee: Amount,
+}
+
+impl Event for UtxosConsolidated {
+    const MODULE: Option<ModuleKind> = Some(fedimint_wallet_common::KIND);
+
+    const KIND: EventKind = EventKind::from_static("utxos-consolidated");
+}
@@ -0,0 +1,753 @@
+//! Threshold Schnorr signatures for taproot peg-in descriptors, using the
+//! FROST implementation of the Zcash Foundation for BIP-340 and key path spends
+//! of tweaked taproot outputs.
+//!
+//! Every peer holds a Shamir share of the group secret key, the group public
+//! key is the internal key of the taproot descriptor. Peg-in tweaks are public
+//! and added to every share, since the Lagrange coefficients of any signer set
+//! sum up to one. The taproot tweak is applied by the FROST implementation.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::{Context, ensure};
+use bitcoin::hashes::{Hash as BitcoinHash, HashEngine, Hmac, HmacEngine, sha256};
+use bitcoin::secp256k1::ecdh::SharedSecret;
+use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, Signing, Verification, schnorr};
+use fedimint_core::encoding::{Decodable, DecodeError, Encodable};
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::{NumPeers, PeerId};
+use fedimint_wallet_common::FrostNonceCommitment;
+use frost_secp256k1_tr::keys::{
+    IdentifierList, KeyPackage, PublicKeyPackage, SigningShare, Tweak, VerifyingShare, dkg,
+};
+use frost_secp256k1_tr::round1::{NonceCommitment, SigningCommitments};
+use frost_secp256k1_tr::round2::SignatureShare;
+use frost_secp256k1_tr::{
+    Identifier, Secp256K1Sha256TR, SigningPackage, VerifyingKey, round1, round2,
+};
+use rand::rngs::OsRng;
+
+/// The FROST identifier of a peer. Offset by one, since the identifier is the
+/// x coordinate of the peer's share and evaluating at zero reveals the secret.
+fn identifier(peer: PeerId) -> Identifier {
+    Identifier::try_from(u16::from(peer) + 1).expect("Identifier is non-zero")
+}
+
+fn to_public_key(bytes: anyhow::Result<Vec<u8>>) -> anyhow::Result<PublicKey> {
+    Ok(PublicKey::from_slice(&bytes?)?)
+}
+
+fn verifying_share(key: &PublicKey) -> anyhow::Result<VerifyingShare> {
+    Ok(VerifyingShare::deserialize(&key.serialize())?)
+}
+
+/// The FROST key material of a peer
+#[derive(Debug, Clone)]
+pub struct FrostKey {
+    /// The public key shared by the federation, used as taproot internal key
+    pub group_key: PublicKey,
+    /// Public keys of every peer's share, used to verify signature shares
+    pub verification_shares: BTreeMap<PeerId, PublicKey>,
+    /// Our share of the group secret key
+    pub share: SecretKey,
+}
+
+impl FrostKey {
+    fn from_packages(
+        key_package: &KeyPackage,
+        public_key_package: &PublicKeyPackage,
+        peers: impl Iterator<Item = PeerId>,
+    ) -> anyhow::Result<Self> {
+        let verification_shares = peers
+            .map(|peer| {
+                let share = public_key_package
+                    .verifying_shares()
+                    .get(&identifier(peer))
+                    .with_context(|| format!("Missing verification share of peer {peer}"))?;
+
+                Ok((peer, to_public_key(share.serialize().map_err(Into::into))?))
+            })
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        Ok(FrostKey {
+            group_key: to_public_key(
+                public_key_package
+                    .verifying_key()
+                    .serialize()
+                    .map_err(Into::into),
+            )?,
+            verification_shares,
+            share: SecretKey::from_slice(&key_package.signing_share().serialize())?,
+        })
+    }
+}
+
+/// Generates the FROST key material of all `peers` with a trusted dealer
+pub fn dealer_keygen(peers: &[PeerId], threshold: usize) -> BTreeMap<PeerId, FrostKey> {
+    // FROST requires at least two signers, a single peer simply holds the group
+    // secret key
+    if let [peer] = peers {
+        let secp = Secp256k1::signing_only();
+        let (share, group_key) = secp.generate_keypair(&mut OsRng);
+
+        return BTreeMap::from([(
+            *peer,
+            FrostKey {
+                group_key,
+                verification_shares: BTreeMap::from([(*peer, group_key)]),
+                share,
+            },
+        )]);
+    }
+
+    let identifiers = peers.iter().copied().map(identifier).collect::<Vec<_>>();
+
+    let (secret_shares, public_key_package) = frost_secp256k1_tr::keys::generate_with_dealer(
+        peers.len().try_into().expect("Too many peers"),
+        threshold.try_into().expect("Too many peers"),
+        IdentifierList::Custom(&identifiers),
+        OsRng,
+    )
+    .expect("Valid threshold and identifiers");
+
+    peers
+        .iter()
+        .map(|peer| {
+            let key_package = KeyPackage::try_from(secret_shares[&identifier(*peer)].clone())
+                .expect("Dealer shares are consistent");
+
+            let key =
+                FrostKey::from_packages(&key_package, &public_key_package, peers.iter().copied())
+                    .expect("Dealer keys are valid");
+
+            (*peer, key)
+        })
+        .collect()
+}
+
+/// Lagrange-interpolates the group secret key from the `shares` of a threshold
+/// of peers. Used to sweep the wallet with the recovery tool, once the
+/// federation can no longer sign.
+pub fn combine_shares(
+    group_key: &PublicKey,
+    shares: &BTreeMap<PeerId, SecretKey>,
+) -> anyhow::Result<SecretKey> {
+    let secp = Secp256k1::signing_only();
+    let verifying_key = VerifyingKey::deserialize(&group_key.serialize())?;
+
+    let key_packages = shares
+        .iter()
+        .map(|(peer, share)| {
+            Ok(KeyPackage::new(
+                identifier(*peer),
+                SigningShare::deserialize(&share.secret_bytes())?,
+                verifying_share(&PublicKey::from_secret_key(&secp, share))?,
+                verifying_key,
+                shares.len().try_into()?,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let group_secret =
+        SecretKey::from_slice(&frost_secp256k1_tr::keys::reconstruct(&key_packages)?.serialize())?;
+
+    // Interpolating less than a threshold of shares yields an unrelated key
+    ensure!(
+        PublicKey::from_secret_key(&secp, &group_secret) == *group_key,
+        "The shares do not combine into the group key, are they shares of a threshold of peers?"
+    );
+
+    Ok(group_secret)
+}
+
+/// Distributed key generation of the FROST implementation. Since modules can
+/// only broadcast messages to all peers, the secret shares of the second round
+/// are encrypted with a one-time pad derived via ECDH from ephemeral keys
+/// exchanged beforehand.
+pub struct Dkg {
+    encryption_secret: SecretKey,
+    /// Ephemeral key the shares for us are encrypted to
+    pub encryption_key: PublicKey,
+}
+
+impl Dkg {
+    pub fn new<C: Signing>(secp: &Secp256k1<C>) -> Self {
+        let (encryption_secret, encryption_key) = secp.generate_keypair(&mut OsRng);
+
+        Dkg {
+            encryption_secret,
+            encryption_key,
+        }
+    }
+
+    fn pad(&self, their_encryption_key: &PublicKey, sender: PeerId, receiver: PeerId) -> [u8; 32] {
+        let shared_secret = SharedSecret::new(their_encryption_key, &self.encryption_secret);
+
+        let mut engine = HmacEngine::<sha256::Hash>::new(b"fedimint/wallet/frost/dkg-pad");
+        engine.input(&shared_secret.secret_bytes());
+        engine.input(&sender.consensus_encode_to_vec());
+        engine.input(&receiver.consensus_encode_to_vec());
+        Hmac::from_engine(engine).to_byte_array()
+    }
+
+    /// Creates our first round package, committing to our secret polynomial
+    pub fn commit(
+        our_id: PeerId,
+        num_peers: NumPeers,
+    ) -> anyhow::Result<(dkg::round1::SecretPackage, Vec<u8>)> {
+        let (secret, package) = dkg::part1(
+            identifier(our_id),
+            num_peers.total().try_into()?,
+            num_peers.threshold().try_into()?,
+            OsRng,
+        )?;
+
+        Ok((secret, package.serialize()?))
+    }
+
+    fn round1_packages(
+        our_id: PeerId,
+        commitments: &BTreeMap<PeerId, Vec<u8>>,
+    ) -> anyhow::Result<BTreeMap<Identifier, dkg::round1::Package>> {
+        commitments
+            .iter()
+            .filter(|(peer, _)| **peer != our_id)
+            .map(|(peer, package)| {
+                let package = dkg::round1::Package::deserialize(package)
+                    .with_context(|| format!("Commitment of peer {peer} is malformed"))?;
+
+                Ok((identifier(*peer), package))
+            })
+            .collect()
+    }
+
+    /// Verifies the first round packages of all peers and returns our share
+    /// for every other peer, encrypted to the peer's encryption key
+    pub fn encrypted_shares(
+        &self,
+        our_id: PeerId,
+        secret: dkg::round1::SecretPackage,
+        encryption_keys: &BTreeMap<PeerId, PublicKey>,
+        commitments: &BTreeMap<PeerId, Vec<u8>>,
+    ) -> anyhow::Result<(dkg::round2::SecretPackage, BTreeMap<PeerId, [u8; 32]>)> {
+        let (secret, packages) = dkg::part2(secret, &Self::round1_packages(our_id, commitments)?)?;
+
+        let mut encrypted_shares = BTreeMap::new();
+
+        for (peer, encryption_key) in encryption_keys.iter().filter(|(p, _)| **p != our_id) {
+            let share: [u8; 32] = packages
+                .get(&identifier(*peer))
+                .context("Missing share for peer")?
+                .signing_share()
+                .serialize()
+                .try_into()
+                .expect("Scalars are 32 bytes");
+
+            let pad = self.pad(encryption_key, our_id, *peer);
+
+            encrypted_shares.insert(*peer, std::array::from_fn(|i| share[i] ^ pad[i]));
+        }
+
+        Ok((secret, encrypted_shares))
+    }
+
+    /// Decrypts and verifies the shares sent to us by all peers and combines
+    /// them into our key material
+    pub fn finish(
+        &self,
+        our_id: PeerId,
+        secret: &dkg::round2::SecretPackage,
+        encryption_keys: &BTreeMap<PeerId, PublicKey>,
+        commitments: &BTreeMap<PeerId, Vec<u8>>,
+        encrypted_shares: &BTreeMap<PeerId, BTreeMap<PeerId, [u8; 32]>>,
+    ) -> anyhow::Result<FrostKey> {
+        let mut packages = BTreeMap::new();
+
+        for (peer, encryption_key) in encryption_keys.iter().filter(|(p, _)| **p != our_id) {
+            let encrypted_share = encrypted_shares
+                .get(peer)
+                .and_then(|shares| shares.get(&our_id))
+                .with_context(|| format!("Peer {peer} did not send us a share"))?;
+
+            let pad = self.pad(encryption_key, *peer, our_id);
+            let share: [u8; 32] = std::array::from_fn(|i| encrypted_share[i] ^ pad[i]);
+
+            let share = SigningShare::deserialize(&share)
+                .with_context(|| format!("Share of peer {peer} is malformed"))?;
+
+            packages.insert(identifier(*peer), dkg::round2::Package::new(share));
+        }
+
+        let (key_package, public_key_package) = dkg::part3(
+            secret,
+            &Self::round1_packages(our_id, commitments)?,
+            &packages,
+        )?;
+
+        FrostKey::from_packages(
+            &key_package,
+            &public_key_package,
+            encryption_keys.keys().copied(),
+        )
+    }
+}
+
+/// The same tweak of the group key for a peg-in as
+/// [`fedimint_wallet_common::tweakable::Tweakable`] derives
+pub fn peg_in_tweak(group_key: &PublicKey, tweak: &[u8; 33]) -> anyhow::Result<Scalar> {
+    let mut hasher = HmacEngine::<sha256::Hash>::new(&group_key.serialize()[..]);
+    hasher.input(tweak);
+
+    Scalar::from_be_bytes(Hmac::from_engine(hasher).to_byte_array())
+        .ok()
+        .context("Tweak is out of range")
+}
+
+/// The public keys of a signer set with the peg-in tweak of a taproot output
+/// applied, the taproot tweak is applied by the FROST implementation
+struct TweakedKeys {
+    public_key_package: PublicKeyPackage,
+    peg_in_tweak: Scalar,
+}
+
+impl TweakedKeys {
+    fn new<C: Verification>(
+        secp: &Secp256k1<C>,
+        group_key: &PublicKey,
+        verification_shares: &BTreeMap<PeerId, PublicKey>,
+        tweak: &[u8; 33],
+    ) -> anyhow::Result<Self> {
+        // Applied to every verification share
+        let peg_in_tweak = peg_in_tweak(group_key, tweak)?;
+
+        let tweak_key = |key: &PublicKey| {
+            key.add_exp_tweak(secp, &peg_in_tweak)
+                .ok()
+                .context("Tweaked key is infinity")
+        };
+
+        let verifying_shares = verification_shares
+            .iter()
+            .map(|(peer, key)| Ok((identifier(*peer), verifying_share(&tweak_key(key)?)?)))
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        let verifying_key = VerifyingKey::deserialize(&tweak_key(group_key)?.serialize())?;
+
+        Ok(TweakedKeys {
+            public_key_package: PublicKeyPackage::new(verifying_shares, verifying_key),
+            peg_in_tweak,
+        })
+    }
+}
+
+/// The secret nonces of a signer for a single input. They are drawn at random
+/// and persisted until they are used, after which they must be discarded, since
+/// signing twice with the same nonces reveals our share.
+#[derive(Clone)]
+pub struct SigningNonces(round1::SigningNonces);
+
+impl SigningNonces {
+    pub fn new(share: &SecretKey) -> Self {
+        let share = SigningShare::deserialize(&share.secret_bytes()).expect("Valid scalar");
+
+        SigningNonces(round1::commit(&share, &mut OsRng).0)
+    }
+
+    pub fn commitment(&self) -> FrostNonceCommitment {
+        let commitments = self.0.commitments();
+        let public_key = |commitment: &NonceCommitment| {
+            to_public_key(commitment.serialize().map_err(Into::into))
+                .expect("Nonce commitments are valid points")
+        };
+
+        FrostNonceCommitment {
+            hiding: public_key(commitments.hiding()),
+            binding: public_key(commitments.binding()),
+        }
+    }
+}
+
+impl fmt::Debug for SigningNonces {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SigningNonces")
+            .field(&self.commitment())
+            .finish()
+    }
+}
+
+impl Encodable for SigningNonces {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.0
+            .serialize()
+            .map_err(std::io::Error::other)?
+            .consensus_encode(writer)
+    }
+}
+
+impl Decodable for SigningNonces {
+    fn consensus_decode_partial<R: std::io::Read>(
+        reader: &mut R,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        let bytes = Vec::<u8>::consensus_decode_partial(reader, modules)?;
+
+        round1::SigningNonces::deserialize(&bytes)
+            .map(SigningNonces)
+            .map_err(|e| DecodeError::new_custom(anyhow::Error::new(e)))
+    }
+}
+
+/// A FROST signing session for a single taproot input, created once the nonce
+/// commitments of a threshold of signers are known
+pub struct SigningSession {
+    keys: TweakedKeys,
+    signing_package: SigningPackage,
+}
+
+impl SigningSession {
+    pub fn new<C: Verification>(
+        secp: &Secp256k1<C>,
+        group_key: &PublicKey,
+        verification_shares: &BTreeMap<PeerId, PublicKey>,
+        tweak: &[u8; 33],
+        sighash: [u8; 32],
+        commitments: &BTreeMap<PeerId, FrostNonceCommitment>,
+    ) -> anyhow::Result<Self> {
+        let keys = TweakedKeys::new(secp, group_key, verification_shares, tweak)?;
+
+        let commitments = commitments
+            .iter()
+            .map(|(peer, commitment)| {
+                let nonce_commitment =
+                    |key: &PublicKey| NonceCommitment::deserialize(&key.serialize());
+
+                Ok((
+                    identifier(*peer),
+                    SigningCommitments::new(
+                        nonce_commitment(&commitment.hiding)?,
+                        nonce_commitment(&commitment.binding)?,
+                    ),
+                ))
+            })
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        Ok(SigningSession {
+            keys,
+            signing_package: SigningPackage::new(commitments, &sighash),
+        })
+    }
+
+    /// Creates our signature share, we need to be part of the signer set
+    pub fn sign(
+        &self,
+        our_id: PeerId,
+        share: &SecretKey,
+        nonces: &SigningNonces,
+    ) -> anyhow::Result<[u8; 32]> {
+        ensure!(
+            self.signing_package
+                .signing_commitments()
+                .contains_key(&identifier(our_id)),
+            "We are not part of the signer set"
+        );
+
+        let key_package = KeyPackage::new(
+            identifier(our_id),
+            SigningShare::deserialize(&share.add_tweak(&self.keys.peg_in_tweak)?.secret_bytes())?,
+            *self
+                .keys
+                .public_key_package
+                .verifying_shares()
+                .get(&identifier(our_id))
+                .context("Missing our verification share")?,
+            *self.keys.public_key_package.verifying_key(),
+            self.signing_package
+                .signing_commitments()
+                .len()
+                .try_into()?,
+        );
+
+        let signature_share =
+            round2::sign_with_tweak(&self.signing_package, &nonces.0, &key_package, None)?;
+
+        Ok(signature_share
+            .serialize()
+            .try_into()
+            .expect("Scalars are 32 bytes"))
+    }
+
+    /// Verifies the signature share of `peer` against its verification share
+    pub fn verify_share(&self, peer: PeerId, signature_share: &[u8; 32]) -> anyhow::Result<()> {
+        ensure!(
+            self.signing_package
+                .signing_commitments()
+                .contains_key(&identifier(peer)),
+            "Peer is not part of the signer set"
+        );
+
+        let signature_share =
+            SignatureShare::deserialize(signature_share).context("Signature share is malformed")?;
+
+        let public_key_package = self.keys.public_key_package.clone().tweak::<&[u8]>(None);
+
+        frost_core::verify_signature_share::<Secp256K1Sha256TR>(
+            identifier(peer),
+            public_key_package
+                .verifying_shares()
+                .get(&identifier(peer))
+                .context("Missing verification share")?,
+            &signature_share,
+            &self.signing_package,
+            public_key_package.verifying_key(),
+        )
+        .context("Signature share is invalid")
+    }
+
+    /// Combines the verified signature shares of all signers into a BIP-340
+    /// signature
+    pub fn aggregate(
+        &self,
+        signature_shares: &BTreeMap<PeerId, [u8; 32]>,
+    ) -> anyhow::Result<schnorr::Signature> {
+        let signature_shares = signature_shares
+            .iter()
+            .map(|(peer, share)| Ok((identifier(*peer), SignatureShare::deserialize(share)?)))
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        let signature = frost_secp256k1_tr::aggregate_with_tweak(
+            &self.signing_package,
+            &signature_shares,
+            &self.keys.public_key_package,
+            None,
+        )?;
+
+        Ok(schnorr::Signature::from_slice(&signature.serialize()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bitcoin::secp256k1::{Message, PublicKey, Secp256k1};
+    use fedimint_core::encoding::{Decodable, Encodable};
+    use fedimint_core::module::registry::ModuleDecoderRegistry;
+    use fedimint_core::{NumPeersExt, PeerId};
+    use fedimint_wallet_common::keys::CompressedPublicKey;
+    use fedimint_wallet_common::tweakable::Tweakable;
+    use miniscript::Descriptor;
+
+    use super::{Dkg, FrostKey, SigningNonces, SigningSession, combine_shares, dealer_keygen};
+
+    fn sign(keys: &BTreeMap<PeerId, FrostKey>, signers: &[PeerId], tweak: &[u8; 33]) {
+        let secp = Secp256k1::new();
+        let any_key = keys.values().next().unwrap();
+        let sighash = [42; 32];
+
+        let nonces = signers
+            .iter()
+            .map(|peer| (*peer, SigningNonces::new(&keys[peer].share)))
+            .collect::<BTreeMap<_, _>>();
+
+        let commitments = nonces
+            .iter()
+            .map(|(peer, nonces)| (*peer, nonces.commitment()))
+            .collect::<BTreeMap<_, _>>();
+
+        let session = SigningSession::new(
+            &secp,
+            &any_key.group_key,
+            &any_key.verification_shares,
+            tweak,
+            sighash,
+            &commitments,
+        )
+        .unwrap();
+
+        let shares = signers
+            .iter()
+            .map(|peer| {
+                let share = session
+                    .sign(*peer, &keys[peer].share, &nonces[peer])
+                    .unwrap();
+
+                session.verify_share(*peer, &share).unwrap();
+
+                (*peer, share)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        // a share does not verify for another signer
+        if let [first, second, ..] = signers {
+            assert!(session.verify_share(*second, &shares[first]).is_err());
+        }
+
+        let signature = session.aggregate(&shares).unwrap();
+
+        // The signature is valid for the address of the tweaked taproot descriptor
+        let descriptor = Descriptor::new_tr(CompressedPublicKey::new(any_key.group_key), None)
+            .unwrap()
+            .tweak(tweak, &secp);
+        let Descriptor::Tr(tr) = descriptor else {
+            panic!("Expected taproot descriptor");
+        };
+
+        secp.verify_schnorr(
+            &signature,
+            &Message::from_digest(sighash),
+            &tr.spend_info().output_key().to_inner(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn threshold_of_dealer_shares_should_sign() {
+        let peers = (0..4).map(PeerId::from).collect::<Vec<_>>();
+        let keys = dealer_keygen(&peers, 3);
+
+        for tweak in [[0; 33], [1; 33], [2; 33], [3; 33]] {
+            sign(&keys, &peers[..3], &tweak);
+            sign(&keys, &peers[1..], &tweak);
+            sign(&keys, &[peers[0], peers[1], peers[3]], &tweak);
+        }
+    }
+
+    #[test]
+    fn single_peer_should_sign() {
+        let peers = vec![PeerId::from(0)];
+        let keys = dealer_keygen(&peers, 1);
+
+        sign(&keys, &peers, &[7; 33]);
+    }
+
+    #[test]
+    fn threshold_of_shares_should_combine_into_group_secret() {
+        let secp = Secp256k1::new();
+        let peers = (0..4).map(PeerId::from).collect::<Vec<_>>();
+        let keys = dealer_keygen(&peers, 3);
+        let group_key = keys[&peers[0]].group_key;
+
+        let shares = |signers: &[PeerId]| {
+            signers
+                .iter()
+                .map(|peer| (*peer, keys[peer].share))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        for signers in [&peers[..3], &peers[1..], &peers[..]] {
+            let group_secret = combine_shares(&group_key, &shares(signers)).unwrap();
+
+            assert_eq!(PublicKey::from_secret_key(&secp, &group_secret), group_key);
+        }
+
+        assert!(combine_shares(&group_key, &shares(&peers[..2])).is_err());
+
+        let key = &dealer_keygen(&peers[..1], 1)[&peers[0]];
+        let group_secret = combine_shares(&key.group_key, &BTreeMap::from([(peers[0], key.share)]));
+        assert_eq!(group_secret.unwrap(), key.share);
+    }
+
+    #[test]
+    fn nonces_should_survive_encoding() {
+        let keys = dealer_keygen(&[PeerId::from(0)], 1);
+        let nonces = SigningNonces::new(&keys[&PeerId::from(0)].share);
+
+        let decoded = SigningNonces::consensus_decode_whole(
+            &nonces.consensus_encode_to_vec(),
+            &ModuleDecoderRegistry::default(),
+        )
+        .unwrap();
+
+        assert_eq!(decoded.commitment(), nonces.commitment());
+        assert_ne!(
+            SigningNonces::new(&keys[&PeerId::from(0)].share).commitment(),
+            nonces.commitment()
+        );
+    }
+
+    #[test]
+    fn dkg_should_produce_consistent_keys() {
+        let secp = Secp256k1::new();
+        let peers = (0..4).map(PeerId::from).collect::<Vec<_>>();
+
+        let dkgs = peers
+            .iter()
+            .map(|peer| (*peer, Dkg::new(&secp)))
+            .collect::<BTreeMap<_, _>>();
+
+        let encryption_keys = dkgs
+            .iter()
+            .map(|(peer, dkg)| (*peer, dkg.encryption_key))
+            .collect::<BTreeMap<_, _>>();
+
+        let (secrets, commitments): (BTreeMap<_, _>, BTreeMap<_, _>) = dkgs
+            .iter()
+            .map(|(peer, dkg)| {
+                let (secret, commitment) = Dkg::commit(*peer, peers.to_num_peers()).unwrap();
+                ((*peer, secret), (*peer, commitment))
+            })
+            .unzip();
+
+        let (secrets, mut encrypted_shares): (BTreeMap<_, _>, BTreeMap<_, _>) = secrets
+            .into_iter()
+            .map(|(peer, secret)| {
+                let (secret, shares) = dkgs[&peer]
+                    .encrypted_shares(peer, secret, &encryption_keys, &commitments)
+                    .unwrap();
+                ((peer, secret), (peer, shares))
+            })
+            .unzip();
+
+        let keys = dkgs
+            .iter()
+            .map(|(peer, dkg)| {
+                (
+                    *peer,
+                    dkg.finish(
+                        *peer,
+                        &secrets[peer],
+                        &encryption_keys,
+                        &commitments,
+                        &encrypted_shares,
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for key in keys.values() {
+            assert_eq!(key.group_key, keys[&peers[0]].group_key);
+            assert_eq!(key.verification_shares, keys[&peers[0]].verification_shares);
+        }
+
+        for (peer, key) in &keys {
+            assert_eq!(
+                PublicKey::from_secret_key(&secp, &key.share),
+                key.verification_shares[peer]
+            );
+        }
+
+        sign(&keys, &peers[..3], &[5; 33]);
+        sign(&keys, &peers[1..], &[5; 33]);
+
+        // a tampered share is rejected by its receiver
+        encrypted_shares
+            .get_mut(&peers[0])
+            .unwrap()
+            .get_mut(&peers[1])
+            .unwrap()[31] ^= 1;
+
+        assert!(
+            dkgs[&peers[1]]
+                .finish(
+                    peers[1],
+                    &secrets[&peers[1]],
+                    &encryption_keys,
+                    &commitments,
+                    &encrypted_shares,
+                )
+                .is_err()
+        );
+    }
+}
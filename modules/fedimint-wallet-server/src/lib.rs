@@ -11,6 +11,9 @@
 
 pub mod db;
 pub mod envs;
+pub mod events;
+mod frost;
+mod silent_payment;
 
 use std::clone::Clone;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
@@ -27,15 +30,18 @@
 use bitcoin::policy::DEFAULT_MIN_RELAY_TX_FEE;
 use bitcoin::psbt::{Input, Psbt};
 use bitcoin::secp256k1::{self, All, Message, Scalar, Secp256k1, Verification};
-use bitcoin::sighash::{EcdsaSighashType, SighashCache};
-use bitcoin::{Address, BlockHash, Network, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{
+    Address, BlockHash, Network, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
 use common::config::WalletConfigConsensus;
 use common::{
-    DEPRECATED_RBF_ERROR, PegOutFees, PegOutSignatureItem, ProcessPegOutSigError, SpendableUTXO,
-    TxOutputSummary, WalletCommonInit, WalletConsensusItem, WalletInput, WalletModuleTypes,
-    WalletOutput, WalletOutputOutcome, WalletSummary, proprietary_tweak_key,
+    DEPRECATED_RBF_ERROR, FrostNonceCommitment, FrostNoncesItem, FrostSignatureSharesItem, PegOut,
+    PegOutFees, PegOutSignatureItem, ProcessPegOutSigError, SpendableUTXO, TxOutputSummary,
+    WalletCommonInit, WalletConsensusItem, WalletInput, WalletModuleTypes, WalletOutput,
+    WalletOutputOutcome, WalletSummary, proprietary_tweak_key,
 };
-use envs::get_feerate_multiplier;
+use envs::{get_consolidation_feerate, get_feerate_multiplier};
 use fedimint_api_client::api::{DynModuleApi, FederationApiExt};
 use fedimint_core::config::{
     ConfigGenModuleParams, ServerModuleConfig, ServerModuleConsensusConfig,
@@ -48,7 +54,7 @@
 use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::envs::{BitcoinRpcConfig, is_rbf_withdrawal_enabled, is_running_in_test_env};
-use fedimint_core::module::audit::Audit;
+use fedimint_core::module::audit::{Audit, AuditSummary};
 use fedimint_core::module::{
     ApiEndpoint, ApiError, ApiRequestErased, ApiVersion, CORE_CONSENSUS_VERSION,
     CoreConsensusVersion, InputMeta, ModuleConsensusVersion, ModuleInit,
@@ -60,34 +66,45 @@
 use fedimint_core::util::{FmtCompact, FmtCompactAnyhow as _, backoff_util, retry};
 use fedimint_core::{
     Feerate, InPoint, NumPeersExt, OutPoint, PeerId, apply, async_trait_maybe_send,
-    get_network_for_address, push_db_key_items, push_db_pair_items,
+    get_network_for_address, push_db_key_items, push_db_pair_items, weight_to_vbytes,
 };
 use fedimint_logging::LOG_MODULE_WALLET;
 use fedimint_server_core::bitcoin_rpc::ServerBitcoinRpcMonitor;
 use fedimint_server_core::config::{PeerHandleOps, PeerHandleOpsExt};
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_server_core::migration::ServerModuleDbMigrationFn;
 use fedimint_server_core::net::check_auth;
 use fedimint_server_core::{ServerModule, ServerModuleInit, ServerModuleInitArgs};
 pub use fedimint_wallet_common as common;
 use fedimint_wallet_common::config::{WalletClientConfig, WalletConfig, WalletGenParams};
 use fedimint_wallet_common::endpoint_constants::{
-    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
-    BLOCK_COUNT_ENDPOINT, BLOCK_COUNT_LOCAL_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT,
-    PEG_OUT_FEES_ENDPOINT, SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT, UTXO_CONFIRMED_ENDPOINT,
-    WALLET_SUMMARY_ENDPOINT,
+    ACCELERATE_DEPOSIT_ENDPOINT, ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT,
+    BITCOIN_RPC_CONFIG_ENDPOINT, BLOCK_COUNT_ENDPOINT, BLOCK_COUNT_LOCAL_ENDPOINT,
+    DEPOSIT_CPFP_FEE_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT, PEG_OUT_BATCH_FEES_ENDPOINT,
+    PEG_OUT_FEES_ENDPOINT, PEG_OUT_SCRIPT_FEES_ENDPOINT, SIGN_PROOF_OF_RESERVES_ENDPOINT,
+    SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT, UTXO_CONFIRMED_ENDPOINT, WALLET_SUMMARY_ENDPOINT,
 };
 use fedimint_wallet_common::keys::CompressedPublicKey;
+use fedimint_wallet_common::proof_of_reserves::{
+    ProofOfReserves, ReservesCommitment, challenge_utxo, proof_of_reserves_output_script,
+};
 use fedimint_wallet_common::tweakable::Tweakable;
 use fedimint_wallet_common::{
-    MODULE_CONSENSUS_VERSION, Rbf, UnknownWalletInputVariantError, WalletInputError,
-    WalletOutputError, WalletOutputV0,
+    BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION, CONSOLIDATION_MODULE_CONSENSUS_VERSION,
+    CPFP_MODULE_CONSENSUS_VERSION, CpfpSummary, DEPOSIT_CPFP_MODULE_CONSENSUS_VERSION,
+    DepositCpfpRequest, MODULE_CONSENSUS_VERSION, Rbf, SCRIPT_PEG_OUT_MODULE_CONSENSUS_VERSION,
+    SILENT_PAYMENT_MODULE_CONSENSUS_VERSION, SilentPaymentShareItem,
+    UnknownWalletInputVariantError, UnknownWalletOutputVariantError, WalletInputError,
+    WalletOutputError, WalletOutputV0, WalletOutputV1, WalletOutputV2, WalletOutputV3,
+    is_standard_peg_out_script,
 };
 use futures::future::join_all;
 use futures::{FutureExt, StreamExt};
 use itertools::Itertools;
 use metrics::{
-    WALLET_INOUT_FEES_SATS, WALLET_INOUT_SATS, WALLET_PEGIN_FEES_SATS, WALLET_PEGIN_SATS,
-    WALLET_PEGOUT_FEES_SATS, WALLET_PEGOUT_SATS,
+    WALLET_CONSOLIDATED_UTXOS, WALLET_CONSOLIDATIONS, WALLET_INOUT_FEES_SATS, WALLET_INOUT_SATS,
+    WALLET_PEGIN_FEES_SATS, WALLET_PEGIN_SATS, WALLET_PEGOUT_FEES_SATS, WALLET_PEGOUT_SATS,
+    WALLET_SPENDABLE_UTXOS,
 };
 use miniscript::psbt::PsbtExt;
 use miniscript::{Descriptor, TranslatePk, translate_hash_fail};
@@ -101,16 +118,65 @@
     BlockCountVoteKey, BlockCountVotePrefix, BlockHashKey, BlockHashKeyPrefix,
     ClaimedPegInOutpointKey, ClaimedPegInOutpointPrefixKey, ConsensusVersionVoteKey,
     ConsensusVersionVotePrefix, ConsensusVersionVotingActivationKey,
-    ConsensusVersionVotingActivationPrefix, DbKeyPrefix, FeeRateVoteKey, FeeRateVotePrefix,
-    PegOutBitcoinTransaction, PegOutBitcoinTransactionPrefix, PegOutNonceKey, PegOutTxSignatureCI,
-    PegOutTxSignatureCIPrefix, PendingTransactionKey, PendingTransactionPrefixKey, UTXOKey,
-    UTXOPrefixKey, UnsignedTransactionKey, UnsignedTransactionPrefixKey, UnspentTxOutKey,
-    UnspentTxOutPrefix, migrate_to_v1,
+    ConsensusVersionVotingActivationPrefix, ConsolidationFeeRateVoteKey,
+    ConsolidationFeeRateVotePrefix, CpfpChild, CpfpChildKey, CpfpChildPrefix, CpfpVoteKey,
+    CpfpVotePrefix, CpfpVoteTxidPrefix, DbKeyPrefix, DepositCpfp, DepositCpfpKey,
+    DepositCpfpPrefix, DepositCpfpRequestKey, DepositCpfpRequestPrefix, DepositCpfpVoteKey,
+    DepositCpfpVoteOutpointPrefix, DepositCpfpVotePrefix, FeeBudgetKey, FeeRateVoteKey,
+    FeeRateVotePrefix, FrostNonceKey, FrostNoncePrefix, FrostNonceTxidPrefix, FrostSecretNoncesKey,
+    FrostSecretNoncesPrefix, FrostShareCI, FrostShareCIPrefix, FrostShareKey, FrostSharePrefix,
+    FrostShareTxidPrefix, FrostSigningRound, FrostSigningRoundKey, FrostSigningRoundPrefix,
+    PegOutBatchItemKey, PegOutBatchItemPrefix, PegOutBatchStartKey, PegOutBitcoinTransaction,
+    PegOutBitcoinTransactionPrefix, PegOutNonceKey, PegOutTxSignatureCI, PegOutTxSignatureCIPrefix,
+    PendingSilentPayment, PendingSilentPaymentKey, PendingSilentPaymentPrefix,
+    PendingTransactionKey, PendingTransactionPrefixKey, SessionCountVoteKey,
+    SessionCountVotePrefix, SilentPaymentShareCI, SilentPaymentShareCIPrefix,
+    SilentPaymentShareKey, SilentPaymentShareOutPointPrefix, SilentPaymentSharePrefix, UTXOKey,
+    UTXOPrefixKey, UnsignedTransactionKey, UnsignedTransactionPrefixKey, UnspentTxOutHeightKey,
+    UnspentTxOutHeightPrefix, UnspentTxOutKey, UnspentTxOutPrefix, migrate_to_v1,
 };
+use crate::events::{
+    CpfpCreated, PegInAccepted, PegOutCreated, PegOutSigned, UtxosConsolidated, UtxosRefreshed,
+};
+pub use crate::frost::combine_shares;
+use crate::frost::{FrostKey, SigningNonces, SigningSession};
 use crate::metrics::WALLET_BLOCK_COUNT;
+use crate::silent_payment::SilentPaymentInputs;
 
 mod metrics;
 
+/// Number of consensus sessions a peg-out batch stays open after its first
+/// batched peg-out was accepted
+const PEG_OUT_BATCH_WINDOW_SESSIONS: u64 = 6;
+
+/// Number of consensus sessions the signer set of a taproot peg-out has to
+/// create its signature before the remaining peers may replace it
+const FROST_SIGNING_TIMEOUT_SESSIONS: u64 = 2;
+
+/// Number of sessions a [`ReservesCommitment`] may lag behind our own session
+/// count for us to still sign a proof of reserves for it
+const PROOF_OF_RESERVES_MAX_AGE_SESSIONS: u64 = 2;
+
+/// A pending transaction is considered stuck once our fee rate estimate rose to
+/// this multiple of the fee rate it pays, at which point we vote to accelerate
+/// it via CPFP
+const CPFP_STUCK_FEE_RATE_MULTIPLIER: u64 = 2;
+
+/// Maximum number of UTXOs spent by a single transaction refreshing the
+/// timelock of the emergency recovery path
+const MAX_REFRESH_INPUTS: usize = 100;
+
+/// Number of spendable UTXOs the wallet needs to hold before a low consensus
+/// fee rate triggers a consolidation
+const MIN_CONSOLIDATION_UTXOS: usize = 20;
+
+/// Maximum number of UTXOs spent by a single consolidation transaction
+const MAX_CONSOLIDATION_INPUTS: usize = 100;
+
+/// Maximum number of accelerated deposits that were not claimed yet, which
+/// bounds the deposit transactions we store and rebroadcast
+const MAX_UNCLAIMED_DEPOSIT_CPFPS: usize = 100;
+
 #[derive(Debug, Clone)]
 pub struct WalletInit;
 
@@ -246,6 +312,177 @@ async fn dump_database(
                         "Consensus Version Voting Activation Key"
                     );
                 }
+                DbKeyPrefix::SessionCountVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        SessionCountVotePrefix,
+                        SessionCountVoteKey,
+                        u64,
+                        wallet,
+                        "Session Count Votes"
+                    );
+                }
+                DbKeyPrefix::PegOutBatchItem => {
+                    push_db_pair_items!(
+                        dbtx,
+                        PegOutBatchItemPrefix,
+                        PegOutBatchItemKey,
+                        PegOut,
+                        wallet,
+                        "Peg Out Batch"
+                    );
+                }
+                DbKeyPrefix::PegOutBatchStart => {
+                    if let Some(start) = dbtx.get_value(&PegOutBatchStartKey).await {
+                        wallet.insert("Peg Out Batch Start".to_string(), Box::new(start));
+                    }
+                }
+                DbKeyPrefix::FeeBudget => {
+                    if let Some(budget) = dbtx.get_value(&FeeBudgetKey).await {
+                        wallet.insert("Fee Budget".to_string(), Box::new(budget));
+                    }
+                }
+                DbKeyPrefix::CpfpVote => {
+                    push_db_key_items!(dbtx, CpfpVotePrefix, CpfpVoteKey, wallet, "CPFP Votes");
+                }
+                DbKeyPrefix::CpfpChild => {
+                    push_db_pair_items!(
+                        dbtx,
+                        CpfpChildPrefix,
+                        CpfpChildKey,
+                        CpfpChild,
+                        wallet,
+                        "CPFP Children"
+                    );
+                }
+                DbKeyPrefix::FrostSecretNonces => {
+                    push_db_key_items!(
+                        dbtx,
+                        FrostSecretNoncesPrefix,
+                        FrostSecretNoncesKey,
+                        wallet,
+                        "FROST Secret Nonces"
+                    );
+                }
+                DbKeyPrefix::FrostSigningRound => {
+                    push_db_pair_items!(
+                        dbtx,
+                        FrostSigningRoundPrefix,
+                        FrostSigningRoundKey,
+                        FrostSigningRound,
+                        wallet,
+                        "FROST Signing Rounds"
+                    );
+                }
+                DbKeyPrefix::FrostNonce => {
+                    push_db_pair_items!(
+                        dbtx,
+                        FrostNoncePrefix,
+                        FrostNonceKey,
+                        Vec<FrostNonceCommitment>,
+                        wallet,
+                        "FROST Nonce Commitments"
+                    );
+                }
+                DbKeyPrefix::FrostShareCi => {
+                    push_db_pair_items!(
+                        dbtx,
+                        FrostShareCIPrefix,
+                        FrostShareCI,
+                        Vec<[u8; 32]>,
+                        wallet,
+                        "FROST Signature Shares CI"
+                    );
+                }
+                DbKeyPrefix::UnspentTxOutHeight => {
+                    push_db_pair_items!(
+                        dbtx,
+                        UnspentTxOutHeightPrefix,
+                        UnspentTxOutHeightKey,
+                        u32,
+                        wallet,
+                        "Unspent Transaction Output Heights"
+                    );
+                }
+                DbKeyPrefix::ConsolidationFeeRateVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ConsolidationFeeRateVotePrefix,
+                        ConsolidationFeeRateVoteKey,
+                        Feerate,
+                        wallet,
+                        "Consolidation Fee Rate Votes"
+                    );
+                }
+                DbKeyPrefix::FrostShare => {
+                    push_db_pair_items!(
+                        dbtx,
+                        FrostSharePrefix,
+                        FrostShareKey,
+                        Vec<[u8; 32]>,
+                        wallet,
+                        "FROST Signature Shares"
+                    );
+                }
+                DbKeyPrefix::DepositCpfpRequest => {
+                    push_db_pair_items!(
+                        dbtx,
+                        DepositCpfpRequestPrefix,
+                        DepositCpfpRequestKey,
+                        DepositCpfpRequest,
+                        wallet,
+                        "Deposit CPFP Requests"
+                    );
+                }
+                DbKeyPrefix::DepositCpfpVote => {
+                    push_db_key_items!(
+                        dbtx,
+                        DepositCpfpVotePrefix,
+                        DepositCpfpVoteKey,
+                        wallet,
+                        "Deposit CPFP Votes"
+                    );
+                }
+                DbKeyPrefix::DepositCpfp => {
+                    push_db_pair_items!(
+                        dbtx,
+                        DepositCpfpPrefix,
+                        DepositCpfpKey,
+                        DepositCpfp,
+                        wallet,
+                        "Deposit CPFP Children"
+                    );
+                }
+                DbKeyPrefix::PendingSilentPayment => {
+                    push_db_pair_items!(
+                        dbtx,
+                        PendingSilentPaymentPrefix,
+                        PendingSilentPaymentKey,
+                        PendingSilentPayment,
+                        wallet,
+                        "Pending Silent Payments"
+                    );
+                }
+                DbKeyPrefix::SilentPaymentShareCi => {
+                    push_db_pair_items!(
+                        dbtx,
+                        SilentPaymentShareCIPrefix,
+                        SilentPaymentShareCI,
+                        SilentPaymentShareItem,
+                        wallet,
+                        "Silent Payment Shares CI"
+                    );
+                }
+                DbKeyPrefix::SilentPaymentShare => {
+                    push_db_pair_items!(
+                        dbtx,
+                        SilentPaymentSharePrefix,
+                        SilentPaymentShareKey,
+                        secp256k1::PublicKey,
+                        wallet,
+                        "Silent Payment Shares"
+                    );
+                }
             }
         }
 
@@ -269,7 +506,7 @@ fn supported_api_versions(&self) -> SupportedModuleApiVersions {
                 MODULE_CONSENSUS_VERSION.major,
                 MODULE_CONSENSUS_VERSION.minor,
             ),
-            &[(0, 2)],
+            &[(0, 6)],
         )
     }
 
@@ -295,6 +532,9 @@ async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::
             args.our_peer_id(),
             args.module_api().clone(),
             args.server_bitcoin_rpc_monitor(),
+            args.event_logger(),
+            args.session_count(),
+            args.audit(),
         )
         .await?)
     }
@@ -305,6 +545,25 @@ fn trusted_dealer_gen(
         params: &ConfigGenModuleParams,
     ) -> BTreeMap<PeerId, ServerModuleConfig> {
         let params = self.parse_params(params).unwrap();
+
+        if let Some(recovery) = &params.consensus.recovery {
+            assert!(
+                !params.consensus.taproot,
+                "Taproot peg-in descriptors do not support a recovery path"
+            );
+
+            recovery
+                .validate(peers.len(), &params.consensus.fee_consensus)
+                .expect("Invalid recovery parameters");
+        }
+
+        if params.consensus.taproot {
+            return frost::dealer_keygen(peers, peers.to_num_peers().threshold())
+                .into_iter()
+                .map(|(id, key)| (id, taproot_wallet_config(key, &params).to_erased()))
+                .collect();
+        }
+
         let secp = bitcoin::secp256k1::Secp256k1::new();
 
         let btc_pegin_keys = peers
@@ -327,6 +586,7 @@ fn trusted_dealer_gen(
                     params.local.bitcoin_rpc.clone(),
                     params.consensus.client_default_bitcoin_rpc.clone(),
                     params.consensus.fee_consensus,
+                    params.consensus.recovery.as_ref(),
                 );
                 (*id, cfg)
             })
@@ -345,6 +605,58 @@ async fn distributed_gen(
     ) -> anyhow::Result<ServerModuleConfig> {
         let params = self.parse_params(params).unwrap();
         let secp = secp256k1::Secp256k1::new();
+
+        if let Some(recovery) = &params.consensus.recovery {
+            ensure!(
+                !params.consensus.taproot,
+                "Taproot peg-in descriptors do not support a recovery path"
+            );
+
+            recovery.validate(peers.num_peers().total(), &params.consensus.fee_consensus)?;
+        }
+
+        if params.consensus.taproot {
+            let dkg = frost::Dkg::new(&secp);
+
+            let encryption_keys = peers.exchange_encodable(dkg.encryption_key).await?;
+
+            // Peers are not told their own id during config generation, so we find it by
+            // our freshly generated encryption key
+            let our_id = encryption_keys
+                .iter()
+                .filter(|(_, key)| **key == dkg.encryption_key)
+                .map(|(peer, _)| *peer)
+                .exactly_one()
+                .map_err(|_| {
+                    format_err!("Our DKG encryption key was not exchanged exactly once")
+                })?;
+
+            if peers.num_peers().total() == 1 {
+                let key = frost::dealer_keygen(&[our_id], 1).remove(&our_id);
+
+                return Ok(taproot_wallet_config(key.expect("Key for our id"), &params).to_erased());
+            }
+
+            let (secret, our_commitment) = frost::Dkg::commit(our_id, peers.num_peers())?;
+
+            let commitments = peers.exchange_encodable(our_commitment).await?;
+
+            let (secret, encrypted_shares) =
+                dkg.encrypted_shares(our_id, secret, &encryption_keys, &commitments)?;
+
+            let encrypted_shares = peers.exchange_encodable(encrypted_shares).await?;
+
+            let key = dkg.finish(
+                our_id,
+                &secret,
+                &encryption_keys,
+                &commitments,
+                &encrypted_shares,
+            )?;
+
+            return Ok(taproot_wallet_config(key, &params).to_erased());
+        }
+
         let (sk, pk) = secp.generate_keypair(&mut OsRng);
         let our_key = CompressedPublicKey { key: pk };
         let peer_peg_in_keys: BTreeMap<PeerId, CompressedPublicKey> = peers
@@ -363,6 +675,7 @@ async fn distributed_gen(
             params.local.bitcoin_rpc.clone(),
             params.consensus.client_default_bitcoin_rpc.clone(),
             params.consensus.fee_consensus,
+            params.consensus.recovery.as_ref(),
         );
 
         Ok(wallet_cfg.to_erased())
@@ -417,6 +730,22 @@ fn used_db_prefixes(&self) -> Option<BTreeSet<u8>> {
     }
 }
 
+fn taproot_wallet_config(key: FrostKey, params: &WalletGenParams) -> WalletConfig {
+    WalletConfig::new_taproot(
+        key.verification_shares
+            .into_iter()
+            .map(|(peer, key)| (peer, CompressedPublicKey { key }))
+            .collect(),
+        CompressedPublicKey { key: key.group_key },
+        key.share,
+        params.consensus.network,
+        params.consensus.finality_delay,
+        params.local.bitcoin_rpc.clone(),
+        params.consensus.client_default_bitcoin_rpc.clone(),
+        params.consensus.fee_consensus,
+    )
+}
+
 #[apply(async_trait_maybe_send!)]
 impl ServerModule for Wallet {
     type Common = WalletModuleTypes;
@@ -438,6 +767,79 @@ async fn consensus_proposal<'a>(
             .collect::<Vec<WalletConsensusItem>>()
             .await;
 
+        let session_count = self.consensus_session_count(dbtx).await;
+
+        let secret_nonces = dbtx
+            .find_by_prefix(&FrostSecretNoncesPrefix)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        // We join the signer set of a peg-out while it is incomplete, or replace it
+        // if it failed to sign in time
+        for (key, nonces) in secret_nonces {
+            let round = dbtx
+                .get_value(&FrostSigningRoundKey(key.0))
+                .await
+                .expect("Signing round is created with the secret nonces");
+
+            let signers = dbtx
+                .find_by_prefix(&FrostNonceTxidPrefix(key.0))
+                .await
+                .map(|(key, _)| key.1)
+                .collect::<Vec<PeerId>>()
+                .await;
+
+            if signers.contains(&self.our_peer_id) {
+                continue;
+            }
+
+            if signers.len()
+                < self
+                    .cfg
+                    .consensus
+                    .peer_peg_in_keys
+                    .to_num_peers()
+                    .threshold()
+                || round.is_expired(session_count, FROST_SIGNING_TIMEOUT_SESSIONS)
+            {
+                items.push(WalletConsensusItem::FrostNonces(FrostNoncesItem {
+                    txid: key.0,
+                    round: round.round,
+                    nonces: nonces.iter().map(SigningNonces::commitment).collect(),
+                }));
+            }
+        }
+
+        let shares = dbtx
+            .find_by_prefix(&FrostShareCIPrefix)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        for (key, shares) in shares {
+            let round = dbtx
+                .get_value(&FrostSigningRoundKey(key.0))
+                .await
+                .expect("Signing round is created with the secret nonces");
+
+            items.push(WalletConsensusItem::FrostSignatureShares(
+                FrostSignatureSharesItem {
+                    txid: key.0,
+                    round: round.round,
+                    shares,
+                },
+            ));
+        }
+
+        items.extend(
+            dbtx.find_by_prefix(&SilentPaymentShareCIPrefix)
+                .await
+                .map(|(_, item)| WalletConsensusItem::SilentPaymentShare(item))
+                .collect::<Vec<_>>()
+                .await,
+        );
+
         // If we are unable to get a block count from the node we skip adding a block
         // count vote to consensus items.
         //
@@ -486,6 +888,60 @@ async fn consensus_proposal<'a>(
             });
 
         let active_consensus_version = self.consensus_module_consensus_version(dbtx).await;
+
+        // Older peers can not process session count votes, so we only start voting once
+        // batched peg-outs have been activated
+        if BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            items.push(WalletConsensusItem::SessionCount(
+                *self.session_count.borrow(),
+            ));
+        }
+
+        // Older peers can not process CPFP votes
+        if CPFP_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            items.extend(
+                self.stuck_pending_transactions(dbtx, fee_rate_proposal)
+                    .await
+                    .into_iter()
+                    .map(WalletConsensusItem::Cpfp),
+            );
+        }
+
+        // Older peers can not process deposit CPFP votes
+        if DEPOSIT_CPFP_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            let requests = dbtx
+                .find_by_prefix(&DepositCpfpRequestPrefix)
+                .await
+                .map(|(_, request)| request)
+                .collect::<Vec<DepositCpfpRequest>>()
+                .await;
+
+            for request in requests {
+                // The deposit might have confirmed or been claimed in the meantime
+                if dbtx
+                    .get_value(&DepositCpfpVoteKey(request.outpoint(), self.our_peer_id))
+                    .await
+                    .is_none()
+                    && self
+                        .validate_deposit_cpfp_request(dbtx, &request)
+                        .await
+                        .is_ok()
+                {
+                    items.push(WalletConsensusItem::DepositCpfp(request));
+                }
+            }
+        }
+
+        // Older peers can not process consolidation fee rate votes
+        if CONSOLIDATION_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            items.push(WalletConsensusItem::ConsolidationFeerate(
+                consolidation_vote(
+                    self.btc_rpc.status().map(|status| status.fee_rate),
+                    get_consolidation_feerate(),
+                ),
+            ));
+        }
+
         let automatic_vote = self.peer_supported_consensus_version.borrow().and_then(
             |supported_consensus_version| {
                 // Only automatically vote if the commonly supported version is higher than the
@@ -569,6 +1025,11 @@ async fn process_consensus_item<'a, 'b>(
                 }
             }
             WalletConsensusItem::PegOutSignature(peg_out_signature) => {
+                ensure!(
+                    !self.cfg.consensus.is_taproot(),
+                    "Taproot peg-outs are signed with FROST"
+                );
+
                 let txid = peg_out_signature.txid;
 
                 if dbtx.get_value(&PendingTransactionKey(txid)).await.is_some() {
@@ -587,21 +1048,18 @@ async fn process_consensus_item<'a, 'b>(
                     .await;
 
                 if let Ok(pending_tx) = self.finalize_peg_out_psbt(unsigned) {
-                    // We were able to finalize the transaction, so we will delete the
-                    // PSBT and instead keep the extracted tx for periodic transmission
-                    // as well as to accept the change into our wallet eventually once
-                    // it confirms.
-                    dbtx.insert_new_entry(&PendingTransactionKey(txid), &pending_tx)
-                        .await;
-
-                    dbtx.remove_entry(&PegOutTxSignatureCI(txid)).await;
-                    dbtx.remove_entry(&UnsignedTransactionKey(txid)).await;
-                    let broadcast_pending = self.broadcast_pending.clone();
-                    dbtx.on_commit(move || {
-                        broadcast_pending.notify_one();
-                    });
+                    self.complete_peg_out(dbtx, txid, &pending_tx).await;
                 }
             }
+            WalletConsensusItem::FrostNonces(nonces) => {
+                self.process_frost_nonces(dbtx, peer, nonces).await?;
+            }
+            WalletConsensusItem::FrostSignatureShares(shares) => {
+                self.process_frost_shares(dbtx, peer, shares).await?;
+            }
+            WalletConsensusItem::SilentPaymentShare(share) => {
+                self.process_silent_payment_share(dbtx, peer, share).await?;
+            }
             WalletConsensusItem::ModuleConsensusVersion(module_consensus_version) => {
                 let current_vote = dbtx
                     .get_value(&ConsensusVersionVoteKey(peer))
@@ -621,6 +1079,120 @@ async fn process_consensus_item<'a, 'b>(
                     "Wallet module does not support new consensus version, please upgrade the module"
                 );
             }
+            WalletConsensusItem::SessionCount(session_count_vote) => {
+                ensure!(
+                    BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Session count votes are not active yet"
+                );
+
+                let current_vote = dbtx
+                    .get_value(&SessionCountVoteKey(peer))
+                    .await
+                    .unwrap_or(0);
+
+                ensure!(
+                    session_count_vote > current_vote,
+                    "Session count vote is redundant"
+                );
+
+                dbtx.insert_entry(&SessionCountVoteKey(peer), &session_count_vote)
+                    .await;
+
+                self.flush_peg_out_batch(dbtx).await;
+            }
+            WalletConsensusItem::Cpfp(txid) => {
+                ensure!(
+                    CPFP_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "CPFP votes are not active yet"
+                );
+
+                ensure!(
+                    dbtx.get_value(&PendingTransactionKey(txid)).await.is_some(),
+                    "Transaction is not pending"
+                );
+
+                ensure!(
+                    dbtx.get_value(&CpfpChildKey(txid)).await.is_none(),
+                    "Transaction was already accelerated"
+                );
+
+                if dbtx
+                    .insert_entry(&CpfpVoteKey(txid, peer), &())
+                    .await
+                    .is_some()
+                {
+                    bail!("CPFP vote is redundant");
+                }
+
+                let votes = dbtx
+                    .find_by_prefix(&CpfpVoteTxidPrefix(txid))
+                    .await
+                    .count()
+                    .await;
+
+                if votes
+                    >= self
+                        .cfg
+                        .consensus
+                        .peer_peg_in_keys
+                        .to_num_peers()
+                        .threshold()
+                {
+                    self.accelerate_stuck_tx(dbtx, txid).await;
+                }
+            }
+            WalletConsensusItem::ConsolidationFeerate(feerate) => {
+                ensure!(
+                    CONSOLIDATION_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Consolidation fee rate votes are not active yet"
+                );
+
+                if Some(feerate)
+                    == dbtx
+                        .insert_entry(&ConsolidationFeeRateVoteKey(peer), &feerate)
+                        .await
+                {
+                    bail!("Consolidation fee rate vote is redundant");
+                }
+            }
+            WalletConsensusItem::DepositCpfp(request) => {
+                ensure!(
+                    DEPOSIT_CPFP_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Deposit CPFP votes are not active yet"
+                );
+
+                let deposit = self.validate_deposit_cpfp_request(dbtx, &request).await?;
+                let outpoint = request.outpoint();
+
+                if dbtx
+                    .insert_entry(&DepositCpfpVoteKey(outpoint, peer), &())
+                    .await
+                    .is_some()
+                {
+                    bail!("Deposit CPFP vote is redundant");
+                }
+
+                let votes = dbtx
+                    .find_by_prefix(&DepositCpfpVoteOutpointPrefix(outpoint))
+                    .await
+                    .count()
+                    .await;
+
+                if votes
+                    >= self
+                        .cfg
+                        .consensus
+                        .peer_peg_in_keys
+                        .to_num_peers()
+                        .threshold()
+                {
+                    self.accelerate_deposit(dbtx, request, deposit).await;
+                }
+            }
             WalletConsensusItem::Default { variant, .. } => {
                 panic!("Received wallet consensus item with unknown variant {variant}");
             }
@@ -693,20 +1265,48 @@ async fn process_input<'a, 'b, 'c>(
             return Err(WalletInputError::PegInAlreadyClaimed);
         }
 
-        dbtx.insert_new_entry(
-            &UTXOKey(outpoint),
-            &SpendableUTXO {
-                tweak: pub_key.serialize(),
-                amount: value,
-            },
-        )
-        .await;
+        dbtx.remove_entry(&DepositCpfpRequestKey(outpoint)).await;
+        dbtx.remove_by_prefix(&DepositCpfpVoteOutpointPrefix(outpoint))
+            .await;
+
+        // An accelerated deposit was already spent by its CPFP child, whose change
+        // becomes spendable once it confirms. The depositor pays for the child.
+        let cpfp_fee =
+            if let Some(deposit_cpfp) = dbtx.remove_entry(&DepositCpfpKey(outpoint)).await {
+                dbtx.remove_entry(&UnspentTxOutKey(outpoint)).await;
+                dbtx.remove_entry(&UnspentTxOutHeightKey(outpoint)).await;
+
+                deposit_cpfp.fee
+            } else {
+                dbtx.insert_new_entry(
+                    &UTXOKey(outpoint),
+                    &SpendableUTXO {
+                        tweak: pub_key.serialize(),
+                        amount: value,
+                    },
+                )
+                .await;
+
+                bitcoin::Amount::ZERO
+            };
 
-        let amount = value.into();
+        let amount = (value - cpfp_fee).into();
 
         let fee = self.cfg.consensus.fee_consensus.peg_in_abs;
 
         calculate_pegin_metrics(dbtx, amount, fee);
+        self.add_fee_income(dbtx, fee).await;
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                PegInAccepted {
+                    outpoint,
+                    amount,
+                    fee,
+                },
+            )
+            .await;
 
         Ok(InputMeta {
             amount: TransactionItemAmount { amount, fee },
@@ -720,7 +1320,25 @@ async fn process_output<'a, 'b>(
         output: &'a WalletOutput,
         out_point: OutPoint,
     ) -> Result<TransactionItemAmount, WalletOutputError> {
-        let output = output.ensure_v0_ref()?;
+        let output = match output {
+            WalletOutput::V0(output) => output,
+            WalletOutput::V1(WalletOutputV1::BatchedPegOut(peg_out)) => {
+                return self.queue_batched_peg_out(dbtx, peg_out, out_point).await;
+            }
+            WalletOutput::V2(output) => {
+                return self.process_script_peg_out(dbtx, output, out_point).await;
+            }
+            WalletOutput::V3(output) => {
+                return self
+                    .process_silent_payment_peg_out(dbtx, output, out_point)
+                    .await;
+            }
+            WalletOutput::Default { variant, .. } => {
+                return Err(WalletOutputError::UnknownOutputVariant(
+                    UnknownWalletOutputVariantError { variant: *variant },
+                ));
+            }
+        };
 
         // In 0.4.0 we began preventing RBF withdrawals. Once we reach EoL support
         // for 0.4.0, we can safely remove RBF withdrawal logic.
@@ -740,68 +1358,15 @@ async fn process_output<'a, 'b>(
 
         let change_tweak = self.consensus_nonce(dbtx).await;
 
-        let mut tx = self.create_peg_out_tx(dbtx, output, &change_tweak).await?;
+        let tx = self.create_peg_out_tx(dbtx, output, &change_tweak).await?;
 
         let fee_rate = self.consensus_fee_rate(dbtx).await;
 
         StatelessWallet::validate_tx(&tx, output, fee_rate, self.cfg.consensus.network.0)?;
 
-        self.offline_wallet().sign_psbt(&mut tx.psbt);
-
-        let txid = tx.psbt.unsigned_tx.compute_txid();
-
-        info!(
-            target: LOG_MODULE_WALLET,
-            %txid,
-            "Signing peg out",
-        );
-
-        let sigs = tx
-            .psbt
-            .inputs
-            .iter_mut()
-            .map(|input| {
-                assert_eq!(
-                    input.partial_sigs.len(),
-                    1,
-                    "There was already more than one (our) or no signatures in input"
-                );
-
-                // TODO: don't put sig into PSBT in the first place
-                // We actually take out our own signature so everyone finalizes the tx in the
-                // same epoch.
-                let sig = std::mem::take(&mut input.partial_sigs)
-                    .into_values()
-                    .next()
-                    .expect("asserted previously");
-
-                // We drop SIGHASH_ALL, because we always use that and it is only present in the
-                // PSBT for compatibility with other tools.
-                secp256k1::ecdsa::Signature::from_der(&sig.to_vec()[..sig.to_vec().len() - 1])
-                    .expect("we serialized it ourselves that way")
-            })
-            .collect::<Vec<_>>();
-
-        // Delete used UTXOs
-        for input in &tx.psbt.unsigned_tx.input {
-            dbtx.remove_entry(&UTXOKey(input.previous_output)).await;
-        }
-
-        dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
-            .await;
-
-        dbtx.insert_new_entry(&PegOutTxSignatureCI(txid), &sigs)
-            .await;
-
-        dbtx.insert_new_entry(
-            &PegOutBitcoinTransaction(out_point),
-            &WalletOutputOutcome::new_v0(txid),
-        )
-        .await;
-        let amount: fedimint_core::Amount = output.amount().into();
-        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
-        calculate_pegout_metrics(dbtx, amount, fee);
-        Ok(TransactionItemAmount { amount, fee })
+        Ok(self
+            .record_peg_out(dbtx, tx, out_point, output.amount())
+            .await)
     }
 
     async fn output_status(
@@ -845,6 +1410,29 @@ async fn audit(
                 },
             )
             .await;
+        audit
+            .add_items(
+                dbtx,
+                module_instance_id,
+                &PendingSilentPaymentPrefix,
+                |_, v| v.tx.change.to_sat() as i64 * 1000,
+            )
+            .await;
+        audit
+            .add_items(dbtx, module_instance_id, &CpfpChildPrefix, |_, v| {
+                v.spent_change.to_sat() as i64 * -1000
+            })
+            .await;
+        audit
+            .add_items(dbtx, module_instance_id, &DepositCpfpPrefix, |_, v| {
+                v.change.to_sat() as i64 * -1000
+            })
+            .await;
+        audit
+            .add_items(dbtx, module_instance_id, &PegOutBatchItemPrefix, |_, v| {
+                (v.amount + v.fees.amount()).to_sat() as i64 * -1000
+            })
+            .await;
     }
 
     fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
@@ -868,32 +1456,43 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                 ApiVersion::new(0, 0),
                 async |module: &Wallet, context, params: (Address<NetworkUnchecked>, u64)| -> Option<PegOutFees> {
                     let (address, sats) = params;
-                    let feerate = module.consensus_fee_rate(&mut context.dbtx().into_nc()).await;
 
-                    // Since we are only calculating the tx size we can use an arbitrary dummy nonce.
-                    let dummy_tweak = [0; 33];
-
-                    let tx = module.offline_wallet().create_tx(
-                        bitcoin::Amount::from_sat(sats),
-                        // Note: While calling `assume_checked()` is generally unwise, it's fine
-                        // here since we're only returning a fee estimate, and we would still
-                        // reject a transaction with the wrong network upon attempted peg-out.
+                    // Note: While calling `assume_checked()` is generally unwise, it's fine
+                    // here since we're only returning a fee estimate, and we would still
+                    // reject a transaction with the wrong network upon attempted peg-out.
+                    Ok(module.peg_out_fees(
+                        &mut context.dbtx().into_nc(),
                         address.assume_checked().script_pubkey(),
-                        vec![],
-                        module.available_utxos(&mut context.dbtx().into_nc()).await,
-                        feerate,
-                        &dummy_tweak,
-                        None
-                    );
-
-                    match tx {
-                        Err(error) => {
-                            // Usually from not enough spendable UTXOs
-                            warn!(target: LOG_MODULE_WALLET, "Error returning peg-out fees {error}");
-                            Ok(None)
-                        }
-                        Ok(tx) => Ok(Some(tx.fees))
+                        bitcoin::Amount::from_sat(sats),
+                    ).await)
+                }
+            },
+            api_endpoint! {
+                PEG_OUT_SCRIPT_FEES_ENDPOINT,
+                ApiVersion::new(0, 4),
+                async |module: &Wallet, context, params: (ScriptBuf, u64)| -> Option<PegOutFees> {
+                    let (script_pubkey, sats) = params;
+                    let mut dbtx = context.dbtx().into_nc();
+
+                    if module.consensus_module_consensus_version(&mut dbtx).await
+                        < SCRIPT_PEG_OUT_MODULE_CONSENSUS_VERSION
+                        || !is_standard_peg_out_script(&script_pubkey)
+                    {
+                        return Ok(None);
                     }
+
+                    Ok(module.peg_out_fees(
+                        &mut dbtx,
+                        script_pubkey,
+                        bitcoin::Amount::from_sat(sats),
+                    ).await)
+                }
+            },
+            api_endpoint! {
+                PEG_OUT_BATCH_FEES_ENDPOINT,
+                ApiVersion::new(0, 3),
+                async |module: &Wallet, context, address: Address<NetworkUnchecked>| -> Option<PegOutFees> {
+                    Ok(module.batched_peg_out_fees(&mut context.dbtx().into_nc(), &address).await)
                 }
             },
             api_endpoint! {
@@ -954,6 +1553,35 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                     Ok(())
                 }
             },
+            api_endpoint! {
+                SIGN_PROOF_OF_RESERVES_ENDPOINT,
+                ApiVersion::new(0, 5),
+                async |module: &Wallet, context, commitment: ReservesCommitment| -> ProofOfReserves {
+                    check_auth(context)?;
+
+                    module
+                        .sign_proof_of_reserves(&mut context.dbtx().into_nc(), commitment)
+                        .await
+                        .map_err(|e| ApiError::bad_request(e.to_string()))
+                }
+            },
+            api_endpoint! {
+                ACCELERATE_DEPOSIT_ENDPOINT,
+                ApiVersion::new(0, 6),
+                async |module: &Wallet, context, request: DepositCpfpRequest| -> () {
+                    module
+                        .submit_deposit_cpfp_request(&mut context.dbtx(), request)
+                        .await
+                        .map_err(|e| ApiError::bad_request(e.to_string()))
+                }
+            },
+            api_endpoint! {
+                DEPOSIT_CPFP_FEE_ENDPOINT,
+                ApiVersion::new(0, 6),
+                async |module: &Wallet, context, outpoint: bitcoin::OutPoint| -> Option<bitcoin::Amount> {
+                    Ok(module.deposit_cpfp_fee(&mut context.dbtx().into_nc(), outpoint).await)
+                }
+            },
             api_endpoint! {
                 UTXO_CONFIRMED_ENDPOINT,
                 ApiVersion::new(0, 2),
@@ -1013,9 +1641,18 @@ pub struct Wallet {
     /// automatically activate new consensus versions as soon as everyone
     /// upgrades.
     peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+    event_logger: ServerModuleEventLogger,
+    /// Our local session count, voted on to close peg-out batches
+    session_count: watch::Receiver<u64>,
+    /// Our local audit, checked against the liabilities of proofs of reserves
+    audit: watch::Receiver<Option<AuditSummary>>,
+    /// Stuck transactions we already warned about not being able to
+    /// accelerate, so we do not warn again on every proposal
+    unaffordable_cpfp_txids: std::sync::Mutex<BTreeSet<Txid>>,
 }
 
 impl Wallet {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         cfg: WalletConfig,
         db: &Database,
@@ -1023,6 +1660,9 @@ pub async fn new(
         our_peer_id: PeerId,
         module_api: DynModuleApi,
         server_bitcoin_rpc_monitor: ServerBitcoinRpcMonitor,
+        event_logger: ServerModuleEventLogger,
+        session_count: watch::Receiver<u64>,
+        audit: watch::Receiver<Option<AuditSummary>>,
     ) -> anyhow::Result<Wallet> {
         let broadcast_pending = Arc::new(Notify::new());
         Self::spawn_broadcast_pending_task(
@@ -1055,11 +1695,104 @@ pub async fn new(
             task_group: task_group.clone(),
             peer_supported_consensus_version,
             broadcast_pending,
+            event_logger,
+            session_count,
+            audit,
+            unaffordable_cpfp_txids: std::sync::Mutex::default(),
         };
 
         Ok(wallet)
     }
 
+    /// Signs a newly created peg-out transaction with our key, reserves its
+    /// inputs and queues our signatures for submission to consensus.
+    async fn sign_peg_out_tx(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        mut tx: UnsignedTransaction,
+    ) -> Txid {
+        let txid = tx.psbt.unsigned_tx.compute_txid();
+
+        // Delete used UTXOs
+        for input in &tx.psbt.unsigned_tx.input {
+            dbtx.remove_entry(&UTXOKey(input.previous_output)).await;
+        }
+
+        if self.cfg.consensus.is_taproot() {
+            info!(
+                target: LOG_MODULE_WALLET,
+                %txid,
+                "Committing to FROST nonces for peg out",
+            );
+
+            let nonces = tx
+                .psbt
+                .inputs
+                .iter()
+                .map(|_| SigningNonces::new(&self.cfg.private.peg_in_key))
+                .collect::<Vec<_>>();
+
+            dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
+                .await;
+
+            dbtx.insert_new_entry(
+                &FrostSigningRoundKey(txid),
+                &FrostSigningRound {
+                    round: 0,
+                    complete_session: None,
+                },
+            )
+            .await;
+
+            dbtx.insert_new_entry(&FrostSecretNoncesKey(txid), &nonces)
+                .await;
+
+            return txid;
+        }
+
+        self.offline_wallet().sign_psbt(&mut tx.psbt);
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            "Signing peg out",
+        );
+
+        let sigs = tx
+            .psbt
+            .inputs
+            .iter_mut()
+            .map(|input| {
+                assert_eq!(
+                    input.partial_sigs.len(),
+                    1,
+                    "There was already more than one (our) or no signatures in input"
+                );
+
+                // TODO: don't put sig into PSBT in the first place
+                // We actually take out our own signature so everyone finalizes the tx in the
+                // same epoch.
+                let sig = std::mem::take(&mut input.partial_sigs)
+                    .into_values()
+                    .next()
+                    .expect("asserted previously");
+
+                // We drop SIGHASH_ALL, because we always use that and it is only present in the
+                // PSBT for compatibility with other tools.
+                secp256k1::ecdsa::Signature::from_der(&sig.to_vec()[..sig.to_vec().len() - 1])
+                    .expect("we serialized it ourselves that way")
+            })
+            .collect::<Vec<_>>();
+
+        dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
+            .await;
+
+        dbtx.insert_new_entry(&PegOutTxSignatureCI(txid), &sigs)
+            .await;
+
+        txid
+    }
+
     /// Try to attach signatures to a pending peg-out tx.
     fn sign_peg_out_psbt(
         &self,
@@ -1143,8 +1876,12 @@ fn finalize_peg_out_psbt(
             .try_into()
             .map_err(|_| ProcessPegOutSigError::MissingOrMalformedChangeTweak)?;
 
-        if let Err(error) = unsigned.psbt.finalize_mut(&self.secp) {
-            return Err(ProcessPegOutSigError::ErrorFinalizingPsbt(error));
+        // Taproot inputs are finalized once their FROST signature is aggregated
+        if !self.cfg.consensus.is_taproot() {
+            unsigned
+                .psbt
+                .finalize_mut(&self.secp)
+                .map_err(ProcessPegOutSigError::ErrorFinalizingPsbt)?;
         }
 
         let tx = unsigned.psbt.clone().extract_tx_unchecked_fee_rate();
@@ -1161,63 +1898,416 @@ fn finalize_peg_out_psbt(
         })
     }
 
-    fn get_block_count(&self) -> anyhow::Result<u32> {
-        self.btc_rpc
-            .status()
-            .context("No bitcoin rpc connection")
-            .and_then(|status| {
-                status
-                    .block_count
+    /// Replaces a fully signed peg-out's PSBT with the extracted transaction,
+    /// which is broadcast periodically and whose change we accept into our
+    /// wallet once it confirms
+    async fn complete_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        txid: Txid,
+        pending_tx: &PendingTransaction,
+    ) {
+        dbtx.insert_new_entry(&PendingTransactionKey(txid), pending_tx)
+            .await;
+
+        self.remove_signing_state(dbtx, txid).await;
+        self.event_logger
+            .log_event(dbtx, PegOutSigned { txid })
+            .await;
+        let broadcast_pending = self.broadcast_pending.clone();
+        dbtx.on_commit(move || {
+            broadcast_pending.notify_one();
+        });
+    }
+
+    /// Removes the PSBT of a peg-out together with all signatures, nonces and
+    /// signature shares collected for it
+    async fn remove_signing_state(&self, dbtx: &mut DatabaseTransaction<'_>, txid: Txid) {
+        dbtx.remove_entry(&UnsignedTransactionKey(txid)).await;
+        dbtx.remove_entry(&PegOutTxSignatureCI(txid)).await;
+        dbtx.remove_entry(&FrostSigningRoundKey(txid)).await;
+        dbtx.remove_entry(&FrostSecretNoncesKey(txid)).await;
+        dbtx.remove_by_prefix(&FrostNonceTxidPrefix(txid)).await;
+        dbtx.remove_entry(&FrostShareCI(txid)).await;
+        dbtx.remove_by_prefix(&FrostShareTxidPrefix(txid)).await;
+    }
+
+    /// Creates the FROST signing sessions for every input of a taproot
+    /// peg-out, given the nonce commitments of its signer set
+    fn frost_signing_sessions(
+        &self,
+        psbt: &Psbt,
+        commitments: &BTreeMap<PeerId, Vec<FrostNonceCommitment>>,
+    ) -> anyhow::Result<Vec<SigningSession>> {
+        let Descriptor::Tr(descriptor) = &self.cfg.consensus.peg_in_descriptor else {
+            bail!("Peg-in descriptor is not taproot");
+        };
+
+        let verification_shares = self
+            .cfg
+            .consensus
+            .peer_peg_in_keys
+            .iter()
+            .map(|(peer, key)| (*peer, key.key))
+            .collect::<BTreeMap<_, _>>();
+
+        taproot_sighashes(psbt)
+            .into_iter()
+            .zip(&psbt.inputs)
+            .enumerate()
+            .map(|(idx, (sighash, input))| {
+                let tweak: [u8; 33] = input
+                    .proprietary
+                    .get(&proprietary_tweak_key())
+                    .context("Missing tweak")?
+                    .as_slice()
                     .try_into()
-                    .map_err(|_| format_err!("Block count exceeds u32 limits"))
+                    .context("Malformed tweak")?;
+
+                let commitments = commitments
+                    .iter()
+                    .map(|(peer, nonces)| (*peer, nonces[idx]))
+                    .collect();
+
+                SigningSession::new(
+                    &self.secp,
+                    &descriptor.internal_key().key,
+                    &verification_shares,
+                    &tweak,
+                    sighash,
+                    &commitments,
+                )
             })
+            .collect()
     }
 
-    pub fn get_fee_rate_opt(&self) -> Feerate {
-        // `get_feerate_multiplier` is clamped and can't be negative
-        // feerate sources as clamped and can't be negative or too large
-        #[allow(clippy::cast_precision_loss)]
-        #[allow(clippy::cast_sign_loss)]
-        Feerate {
-            sats_per_kvb: ((self
-                .btc_rpc
-                .status()
-                .map_or(self.cfg.consensus.default_fee, |status| status.fee_rate)
-                .sats_per_kvb as f64
-                * get_feerate_multiplier())
-            .round()) as u64,
-        }
-    }
+    /// Records the nonce commitments of a peer for a taproot peg-out. The first
+    /// threshold of peers to commit in a signing round form the signer set,
+    /// after which its members submit their signature shares. If the signer
+    /// set fails to sign in time, the first peer outside of it to commit again
+    /// starts the next signing round.
+    async fn process_frost_nonces(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peer: PeerId,
+        item: FrostNoncesItem,
+    ) -> anyhow::Result<()> {
+        let txid = item.txid;
 
-    pub async fn consensus_block_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u32 {
-        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+        ensure!(
+            self.cfg.consensus.is_taproot(),
+            "Peg-outs are signed with ECDSA"
+        );
 
-        let mut counts = dbtx
-            .find_by_prefix(&BlockCountVotePrefix)
+        let unsigned = dbtx
+            .get_value(&UnsignedTransactionKey(txid))
             .await
-            .map(|entry| entry.1)
-            .collect::<Vec<u32>>()
-            .await;
+            .context("Unsigned transaction does not exist")?;
 
-        assert!(counts.len() <= peer_count);
-
-        while counts.len() < peer_count {
-            counts.push(0);
-        }
+        ensure!(
+            unsigned.psbt.inputs.len() == item.nonces.len(),
+            "Expected {} nonce commitments, got {}",
+            unsigned.psbt.inputs.len(),
+            item.nonces.len()
+        );
 
-        counts.sort_unstable();
+        let mut round = dbtx
+            .get_value(&FrostSigningRoundKey(txid))
+            .await
+            .context("Signing round does not exist")?;
 
-        counts[peer_count / 2]
-    }
+        ensure!(
+            item.round == round.round,
+            "Nonce commitments are for signing round {}, current round is {}",
+            item.round,
+            round.round
+        );
 
-    pub async fn consensus_fee_rate(&self, dbtx: &mut DatabaseTransaction<'_>) -> Feerate {
-        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+        let threshold = self
+            .cfg
+            .consensus
+            .peer_peg_in_keys
+            .to_num_peers()
+            .threshold();
 
-        let mut rates = dbtx
-            .find_by_prefix(&FeeRateVotePrefix)
+        let mut commitments = dbtx
+            .find_by_prefix(&FrostNonceTxidPrefix(txid))
             .await
-            .map(|(.., rate)| rate)
-            .collect::<Vec<_>>()
+            .map(|(key, nonces)| (key.1, nonces))
+            .collect::<BTreeMap<PeerId, Vec<FrostNonceCommitment>>>()
+            .await;
+
+        ensure!(
+            !commitments.contains_key(&peer),
+            "Nonce commitments are redundant"
+        );
+
+        if commitments.len() >= threshold {
+            let session_count = self.consensus_session_count(dbtx).await;
+
+            ensure!(
+                round.is_expired(session_count, FROST_SIGNING_TIMEOUT_SESSIONS),
+                "Signer set is already complete"
+            );
+
+            warn!(
+                target: LOG_MODULE_WALLET,
+                %txid,
+                round = round.round,
+                "Signer set failed to sign peg-out in time, starting next signing round",
+            );
+
+            // Shares created for the previous signer set are useless now. Since every
+            // signer replaced its nonces when signing, no nonces are signed with twice.
+            dbtx.remove_by_prefix(&FrostNonceTxidPrefix(txid)).await;
+            dbtx.remove_by_prefix(&FrostShareTxidPrefix(txid)).await;
+            dbtx.remove_entry(&FrostShareCI(txid)).await;
+
+            commitments.clear();
+
+            round = FrostSigningRound {
+                round: round.round + 1,
+                complete_session: None,
+            };
+        }
+
+        dbtx.insert_new_entry(&FrostNonceKey(txid, peer), &item.nonces)
+            .await;
+
+        commitments.insert(peer, item.nonces);
+
+        if commitments.len() == threshold {
+            round.complete_session = Some(self.consensus_session_count(dbtx).await);
+        }
+
+        dbtx.insert_entry(&FrostSigningRoundKey(txid), &round).await;
+
+        if commitments.len() < threshold {
+            return Ok(());
+        }
+
+        let Some(our_commitments) = commitments.get(&self.our_peer_id) else {
+            return Ok(());
+        };
+
+        let nonces = dbtx
+            .get_value(&FrostSecretNoncesKey(txid))
+            .await
+            .context("Our secret nonces do not exist")?;
+
+        // Our commitments from a previous round may be replayed after we already
+        // signed with their nonces, in which case we must not sign again
+        if nonces
+            .iter()
+            .map(SigningNonces::commitment)
+            .collect::<Vec<_>>()
+            != *our_commitments
+        {
+            warn!(
+                target: LOG_MODULE_WALLET,
+                %txid,
+                "Signer set contains stale nonce commitments of ours, not signing",
+            );
+
+            return Ok(());
+        }
+
+        let shares = self
+            .frost_signing_sessions(&unsigned.psbt, &commitments)?
+            .iter()
+            .zip(&nonces)
+            .map(|(session, nonces)| {
+                session.sign(self.our_peer_id, &self.cfg.private.peg_in_key, nonces)
+            })
+            .collect::<anyhow::Result<Vec<[u8; 32]>>>()?;
+
+        // Our nonces are used up, fresh ones are needed in case the signer set has to
+        // be replaced
+        let fresh_nonces = nonces
+            .iter()
+            .map(|_| SigningNonces::new(&self.cfg.private.peg_in_key))
+            .collect::<Vec<_>>();
+
+        dbtx.insert_entry(&FrostSecretNoncesKey(txid), &fresh_nonces)
+            .await;
+
+        dbtx.insert_new_entry(&FrostShareCI(txid), &shares).await;
+
+        Ok(())
+    }
+
+    /// Verifies and records the signature shares of a member of the signer set
+    /// of a taproot peg-out and finalizes it once all shares are present
+    async fn process_frost_shares(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peer: PeerId,
+        item: FrostSignatureSharesItem,
+    ) -> anyhow::Result<()> {
+        let txid = item.txid;
+
+        ensure!(
+            self.cfg.consensus.is_taproot(),
+            "Peg-outs are signed with ECDSA"
+        );
+
+        let mut unsigned = dbtx
+            .get_value(&UnsignedTransactionKey(txid))
+            .await
+            .context("Unsigned transaction does not exist")?;
+
+        ensure!(
+            unsigned.psbt.inputs.len() == item.shares.len(),
+            "Expected {} signature shares, got {}",
+            unsigned.psbt.inputs.len(),
+            item.shares.len()
+        );
+
+        let round = dbtx
+            .get_value(&FrostSigningRoundKey(txid))
+            .await
+            .context("Signing round does not exist")?;
+
+        ensure!(
+            item.round == round.round,
+            "Signature shares are for signing round {}, current round is {}",
+            item.round,
+            round.round
+        );
+
+        let commitments = dbtx
+            .find_by_prefix(&FrostNonceTxidPrefix(txid))
+            .await
+            .map(|(key, nonces)| (key.1, nonces))
+            .collect::<BTreeMap<PeerId, Vec<FrostNonceCommitment>>>()
+            .await;
+
+        ensure!(
+            commitments.len()
+                == self
+                    .cfg
+                    .consensus
+                    .peer_peg_in_keys
+                    .to_num_peers()
+                    .threshold(),
+            "Signer set is not complete yet"
+        );
+
+        ensure!(
+            commitments.contains_key(&peer),
+            "Peer is not part of the signer set"
+        );
+
+        ensure!(
+            dbtx.get_value(&FrostShareKey(txid, peer)).await.is_none(),
+            "Signature shares are redundant"
+        );
+
+        let sessions = self.frost_signing_sessions(&unsigned.psbt, &commitments)?;
+
+        for (session, share) in sessions.iter().zip(&item.shares) {
+            session.verify_share(peer, share)?;
+        }
+
+        dbtx.insert_new_entry(&FrostShareKey(txid, peer), &item.shares)
+            .await;
+
+        let shares = dbtx
+            .find_by_prefix(&FrostShareTxidPrefix(txid))
+            .await
+            .map(|(key, shares)| (key.1, shares))
+            .collect::<BTreeMap<PeerId, Vec<[u8; 32]>>>()
+            .await;
+
+        if shares.len() < commitments.len() {
+            return Ok(());
+        }
+
+        for (idx, (session, input)) in sessions
+            .iter()
+            .zip(unsigned.psbt.inputs.iter_mut())
+            .enumerate()
+        {
+            let input_shares = shares
+                .iter()
+                .map(|(peer, shares)| (*peer, shares[idx]))
+                .collect();
+
+            let signature = bitcoin::taproot::Signature {
+                signature: session.aggregate(&input_shares)?,
+                sighash_type: TapSighashType::Default,
+            };
+
+            input.tap_key_sig = Some(signature);
+            input.final_script_witness = Some(Witness::p2tr_key_spend(&signature));
+        }
+
+        let pending_tx = self
+            .finalize_peg_out_psbt(unsigned)
+            .context("Failed to finalize peg-out")?;
+
+        self.complete_peg_out(dbtx, txid, &pending_tx).await;
+
+        Ok(())
+    }
+
+    fn get_block_count(&self) -> anyhow::Result<u32> {
+        self.btc_rpc
+            .status()
+            .context("No bitcoin rpc connection")
+            .and_then(|status| {
+                status
+                    .block_count
+                    .try_into()
+                    .map_err(|_| format_err!("Block count exceeds u32 limits"))
+            })
+    }
+
+    pub fn get_fee_rate_opt(&self) -> Feerate {
+        // `get_feerate_multiplier` is clamped and can't be negative
+        // feerate sources as clamped and can't be negative or too large
+        #[allow(clippy::cast_precision_loss)]
+        #[allow(clippy::cast_sign_loss)]
+        Feerate {
+            sats_per_kvb: ((self
+                .btc_rpc
+                .status()
+                .map_or(self.cfg.consensus.default_fee, |status| status.fee_rate)
+                .sats_per_kvb as f64
+                * get_feerate_multiplier())
+            .round()) as u64,
+        }
+    }
+
+    pub async fn consensus_block_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u32 {
+        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+
+        let mut counts = dbtx
+            .find_by_prefix(&BlockCountVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u32>>()
+            .await;
+
+        assert!(counts.len() <= peer_count);
+
+        while counts.len() < peer_count {
+            counts.push(0);
+        }
+
+        counts.sort_unstable();
+
+        counts[peer_count / 2]
+    }
+
+    pub async fn consensus_fee_rate(&self, dbtx: &mut DatabaseTransaction<'_>) -> Feerate {
+        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+
+        let mut rates = dbtx
+            .find_by_prefix(&FeeRateVotePrefix)
+            .await
+            .map(|(.., rate)| rate)
+            .collect::<Vec<_>>()
             .await;
 
         assert!(rates.len() <= peer_count);
@@ -1231,6 +2321,66 @@ pub async fn consensus_fee_rate(&self, dbtx: &mut DatabaseTransaction<'_>) -> Fe
         rates[peer_count / 2]
     }
 
+    /// The fee income that is available to pay for transactions the federation
+    /// initiates on its own, see [`FeeBudgetKey`]
+    async fn fee_budget(&self, dbtx: &mut DatabaseTransaction<'_>) -> fedimint_core::Amount {
+        dbtx.get_value(&FeeBudgetKey)
+            .await
+            .unwrap_or(fedimint_core::Amount::ZERO)
+    }
+
+    async fn add_fee_income(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        income: fedimint_core::Amount,
+    ) {
+        let budget = self.fee_budget(dbtx).await + income;
+
+        dbtx.insert_entry(&FeeBudgetKey, &budget).await;
+    }
+
+    /// Pays `fee` out of the fee budget, fails without changing the budget if
+    /// it is insufficient
+    async fn spend_fee_budget(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        fee: bitcoin::Amount,
+    ) -> Result<(), WalletOutputError> {
+        let budget = self.fee_budget(dbtx).await;
+
+        let remaining = budget
+            .checked_sub(fee.into())
+            .ok_or(WalletOutputError::FeeBudgetExceeded)?;
+
+        dbtx.insert_entry(&FeeBudgetKey, &remaining).await;
+
+        Ok(())
+    }
+
+    /// The fee rate a consolidation pays right now, `None` if less than a
+    /// threshold of peers want to consolidate at their current fee rate
+    /// estimate
+    async fn consensus_consolidation_fee_rate(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Option<Feerate> {
+        let votes = dbtx
+            .find_by_prefix(&ConsolidationFeeRateVotePrefix)
+            .await
+            .map(|(.., rate)| rate)
+            .collect::<Vec<_>>()
+            .await;
+
+        aggregate_consolidation_votes(
+            votes,
+            self.cfg
+                .consensus
+                .peer_peg_in_keys
+                .to_num_peers()
+                .threshold(),
+        )
+    }
+
     async fn consensus_module_consensus_version(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -1257,6 +2407,25 @@ async fn consensus_module_consensus_version(
         versions[num_peers.max_evil()]
     }
 
+    async fn consensus_session_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u64 {
+        let num_peers = self.cfg.consensus.peer_peg_in_keys.to_num_peers();
+
+        let mut counts = dbtx
+            .find_by_prefix(&SessionCountVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u64>>()
+            .await;
+
+        counts.sort_unstable();
+
+        counts.reverse();
+
+        // Any threshold of correct peers can increase the consensus session count and
+        // any consensus session count has been confirmed by a threshold of peers.
+        counts.get(num_peers.threshold() - 1).copied().unwrap_or(0)
+    }
+
     pub async fn consensus_nonce(&self, dbtx: &mut DatabaseTransaction<'_>) -> [u8; 33] {
         let nonce_idx = dbtx.get_value(&PegOutNonceKey).await.unwrap_or(0);
         dbtx.insert_entry(&PegOutNonceKey, &(nonce_idx + 1)).await;
@@ -1306,21 +2475,34 @@ async fn sync_up_to_consensus_count<'a>(
             if self.consensus_module_consensus_version(dbtx).await
                 >= ModuleConsensusVersion::new(2, 2)
             {
+                // Accelerated deposits are spent by their CPFP child before they are claimed,
+                // so we keep tracking them until the depositor claims them
+                let accelerated_deposits = dbtx
+                    .find_by_prefix(&DepositCpfpPrefix)
+                    .await
+                    .map(|(key, _)| key.0)
+                    .collect::<BTreeSet<bitcoin::OutPoint>>()
+                    .await;
+
                 for transaction in block.txdata.clone() {
                     // We maintain the subset of unspent P2WSH transaction outputs created
                     // since the module was running on the new consensus version, which might be
                     // the same time as the genesis session.
 
                     for tx_in in &transaction.input {
-                        dbtx.remove_entry(&UnspentTxOutKey(tx_in.previous_output))
+                        if !accelerated_deposits.contains(&tx_in.previous_output) {
+                            dbtx.remove_entry(&UnspentTxOutKey(tx_in.previous_output))
+                                .await;
+                        }
+                        dbtx.remove_entry(&UnspentTxOutHeightKey(tx_in.previous_output))
                             .await;
                     }
 
                     for (vout, tx_out) in transaction.output.iter().enumerate() {
-                        let should_track_utxo = if self.cfg.consensus.peer_peg_in_keys.len() > 1 {
-                            tx_out.script_pubkey.is_p2wsh()
-                        } else {
-                            tx_out.script_pubkey.is_p2wpkh()
+                        let should_track_utxo = match &self.cfg.consensus.peg_in_descriptor {
+                            Descriptor::Tr(_) => tx_out.script_pubkey.is_p2tr(),
+                            Descriptor::Wpkh(_) => tx_out.script_pubkey.is_p2wpkh(),
+                            _ => tx_out.script_pubkey.is_p2wsh(),
                         };
 
                         if should_track_utxo {
@@ -1331,6 +2513,11 @@ async fn sync_up_to_consensus_count<'a>(
 
                             dbtx.insert_new_entry(&UnspentTxOutKey(outpoint), tx_out)
                                 .await;
+
+                            if self.cfg.consensus.recovery_delay().is_some() {
+                                dbtx.insert_new_entry(&UnspentTxOutHeightKey(outpoint), &height)
+                                    .await;
+                            }
                         }
                     }
                 }
@@ -1372,74 +2559,342 @@ async fn sync_up_to_consensus_count<'a>(
 
             dbtx.insert_new_entry(&BlockHashKey(block_hash), &()).await;
         }
-    }
-
-    /// Add a change UTXO to our spendable UTXO database after it was included
-    /// in a block that we got consensus on.
-    async fn recognize_change_utxo<'a>(
-        &self,
-        dbtx: &mut DatabaseTransaction<'a>,
-        pending_tx: &PendingTransaction,
-    ) {
-        self.remove_rbf_transactions(dbtx, pending_tx).await;
 
-        let script_pk = self
-            .cfg
-            .consensus
-            .peg_in_descriptor
-            .tweak(&pending_tx.tweak, &self.secp)
-            .script_pubkey();
-        for (idx, output) in pending_tx.tx.output.iter().enumerate() {
-            if output.script_pubkey == script_pk {
-                dbtx.insert_entry(
-                    &UTXOKey(bitcoin::OutPoint {
-                        txid: pending_tx.tx.compute_txid(),
-                        vout: idx as u32,
-                    }),
-                    &SpendableUTXO {
-                        tweak: pending_tx.tweak,
-                        amount: output.value,
-                    },
-                )
+        if let Some(delay) = self.cfg.consensus.recovery_delay() {
+            self.refresh_recovery_timelocks(dbtx, new_count, delay)
                 .await;
-            }
         }
+
+        self.consolidate_utxos(dbtx).await;
     }
 
-    /// Removes the `PendingTransaction` and any transactions tied to it via RBF
-    async fn remove_rbf_transactions<'a>(
-        &self,
-        dbtx: &mut DatabaseTransaction<'a>,
-        pending_tx: &PendingTransaction,
-    ) {
-        let mut all_transactions: BTreeMap<Txid, PendingTransaction> = dbtx
-            .find_by_prefix(&PendingTransactionPrefixKey)
+    /// Spends many small UTXOs back to the federation in a single transaction
+    /// while the fee rate estimates of a threshold of peers are below the fee
+    /// rate they are willing to consolidate at, which keeps later peg-outs
+    /// small and cheap. The fee is paid out of the fee budget.
+    async fn consolidate_utxos(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let utxos = dbtx
+            .find_by_prefix(&UTXOPrefixKey)
             .await
-            .map(|(key, val)| (key.0, val))
-            .collect::<BTreeMap<Txid, PendingTransaction>>()
+            .collect::<Vec<(UTXOKey, SpendableUTXO)>>()
             .await;
 
-        // We need to search and remove all `PendingTransactions` invalidated by RBF
-        let mut pending_to_remove = vec![pending_tx.clone()];
-        while let Some(removed) = pending_to_remove.pop() {
-            all_transactions.remove(&removed.tx.compute_txid());
-            dbtx.remove_entry(&PendingTransactionKey(removed.tx.compute_txid()))
-                .await;
+        WALLET_SPENDABLE_UTXOS.set(utxos.len() as i64);
 
-            // Search for tx that this `removed` has as RBF
-            if let Some(rbf) = &removed.rbf {
-                if let Some(tx) = all_transactions.get(&rbf.txid) {
-                    pending_to_remove.push(tx.clone());
-                }
-            }
+        if utxos.len() < MIN_CONSOLIDATION_UTXOS {
+            return;
+        }
 
-            // Search for tx that wanted to RBF the `removed` one
-            for tx in all_transactions.values() {
-                if let Some(rbf) = &tx.rbf {
-                    if rbf.txid == removed.tx.compute_txid() {
-                        pending_to_remove.push(tx.clone());
-                    }
-                }
+        let Some(fee_rate) = self.consensus_consolidation_fee_rate(dbtx).await else {
+            return;
+        };
+
+        // Wait for previous self spends to confirm, otherwise the newly deposited
+        // UTXOs would be consolidated on every block
+        let is_unsigned_self_spend = dbtx
+            .find_by_prefix(&UnsignedTransactionPrefixKey)
+            .await
+            .any(|(_, tx)| async move { tx.peg_out_amount == bitcoin::Amount::ZERO })
+            .await;
+
+        let is_pending_self_spend = dbtx
+            .find_by_prefix(&PendingTransactionPrefixKey)
+            .await
+            .any(|(_, tx)| async move { tx.peg_out_amount == bitcoin::Amount::ZERO })
+            .await;
+
+        if is_unsigned_self_spend || is_pending_self_spend {
+            return;
+        }
+
+        let mut selected = select_consolidation_utxos(utxos);
+        let fee_budget = bitcoin::Amount::from_sat(self.fee_budget(dbtx).await.sats_round_down());
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        // Consolidate as many of the smallest UTXOs as our fee budget allows, a
+        // consolidation of less UTXOs is not worth its fixed overhead
+        let tx = loop {
+            match self.offline_wallet().create_self_spend_tx(
+                selected.clone(),
+                fee_rate,
+                fee_budget,
+                &change_tweak,
+            ) {
+                Err(WalletOutputError::FeeBudgetExceeded)
+                    if MIN_CONSOLIDATION_UTXOS < selected.len() =>
+                {
+                    selected.pop();
+                }
+                result => break result,
+            }
+        };
+
+        let utxo_count = selected.len() as u64;
+
+        let tx = match tx {
+            Ok(tx) => tx,
+            Err(error) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    %error,
+                    utxo_count,
+                    "Failed to consolidate spendable UTXOs"
+                );
+                return;
+            }
+        };
+
+        let fee = tx.fees.amount();
+
+        self.spend_fee_budget(dbtx, fee)
+            .await
+            .expect("The consolidation pays at most the fee budget");
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            utxo_count,
+            fee_sats = fee.to_sat(),
+            "Consolidating spendable UTXOs"
+        );
+
+        WALLET_CONSOLIDATIONS.inc();
+        WALLET_CONSOLIDATED_UTXOS.inc_by(utxo_count);
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                UtxosConsolidated {
+                    txid,
+                    utxo_count,
+                    fee: fee.into(),
+                },
+            )
+            .await;
+    }
+
+    /// Spends all UTXOs older than a quarter of the recovery delay back to the
+    /// federation, so the emergency recovery path never becomes spendable while
+    /// the federation is operational. The refresh is paid out of the fee
+    /// budget, but once UTXOs are older than half the delay and the budget
+    /// does not suffice, we pay the fee out of our reserves instead.
+    async fn refresh_recovery_timelocks(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        consensus_block_count: u32,
+        delay: u32,
+    ) {
+        let utxos = dbtx
+            .find_by_prefix(&UTXOPrefixKey)
+            .await
+            .collect::<Vec<(UTXOKey, SpendableUTXO)>>()
+            .await;
+
+        let mut confirmed_utxos = vec![];
+
+        for (key, utxo) in utxos {
+            // If we did not observe the confirmation we assume the worst
+            let height = dbtx
+                .get_value(&UnspentTxOutHeightKey(key.0))
+                .await
+                .unwrap_or(0);
+
+            confirmed_utxos.push((key, utxo, height));
+        }
+
+        let mut due =
+            select_refresh_utxos(confirmed_utxos.clone(), consensus_block_count, delay / 4);
+
+        if due.is_empty() {
+            return;
+        }
+
+        let overdue = select_refresh_utxos(confirmed_utxos, consensus_block_count, delay / 2);
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+        let fee_budget = bitcoin::Amount::from_sat(self.fee_budget(dbtx).await.sats_round_down());
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        // Refresh as many of the oldest UTXOs as our fee budget allows, the rest is
+        // refreshed once we collected more fees
+        let tx = loop {
+            match self.offline_wallet().create_self_spend_tx(
+                due.clone(),
+                fee_rate,
+                fee_budget,
+                &change_tweak,
+            ) {
+                Err(WalletOutputError::FeeBudgetExceeded) if 1 < due.len() => {
+                    due.pop();
+                }
+                Err(WalletOutputError::FeeBudgetExceeded) if !overdue.is_empty() => {
+                    due.clone_from(&overdue);
+
+                    break self.offline_wallet().create_self_spend_tx(
+                        due.clone(),
+                        fee_rate,
+                        bitcoin::Amount::MAX_MONEY,
+                        &change_tweak,
+                    );
+                }
+                result => break result,
+            }
+        };
+
+        let utxo_count = due.len() as u64;
+
+        let tx = match tx {
+            Ok(tx) => tx,
+            Err(error) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    %error,
+                    utxo_count,
+                    "Failed to refresh UTXOs approaching the recovery timelock"
+                );
+                return;
+            }
+        };
+
+        let fee = tx.fees.amount();
+
+        // Whatever the fee budget does not cover is paid out of our reserves
+        let reserves_fee = fee.checked_sub(fee_budget).unwrap_or(bitcoin::Amount::ZERO);
+
+        self.spend_fee_budget(dbtx, fee - reserves_fee)
+            .await
+            .expect("The refresh pays at most the fee budget");
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        if reserves_fee != bitcoin::Amount::ZERO {
+            warn!(
+                target: LOG_MODULE_WALLET,
+                %txid,
+                utxo_count,
+                reserves_fee_sats = reserves_fee.to_sat(),
+                "Fee budget is exhausted, paying the refresh of overdue UTXOs from our reserves"
+            );
+        }
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            utxo_count,
+            fee_sats = fee.to_sat(),
+            "Refreshing UTXOs approaching the recovery timelock"
+        );
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                UtxosRefreshed {
+                    txid,
+                    utxo_count,
+                    fee: fee.into(),
+                },
+            )
+            .await;
+    }
+
+    /// Add a change UTXO to our spendable UTXO database after it was included
+    /// in a block that we got consensus on.
+    async fn recognize_change_utxo<'a>(
+        &self,
+        dbtx: &mut DatabaseTransaction<'a>,
+        pending_tx: &PendingTransaction,
+    ) {
+        self.remove_rbf_transactions(dbtx, pending_tx).await;
+
+        // The change was already spent by a CPFP child, which will bring its own
+        // change once it confirms
+        if dbtx
+            .remove_entry(&CpfpChildKey(pending_tx.tx.compute_txid()))
+            .await
+            .is_some()
+        {
+            return;
+        }
+
+        let script_pk = self
+            .cfg
+            .consensus
+            .peg_in_descriptor
+            .tweak(&pending_tx.tweak, &self.secp)
+            .script_pubkey();
+        for (idx, output) in pending_tx.tx.output.iter().enumerate() {
+            if output.script_pubkey == script_pk {
+                dbtx.insert_entry(
+                    &UTXOKey(bitcoin::OutPoint {
+                        txid: pending_tx.tx.compute_txid(),
+                        vout: idx as u32,
+                    }),
+                    &SpendableUTXO {
+                        tweak: pending_tx.tweak,
+                        amount: output.value,
+                    },
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Removes the `PendingTransaction` and any transactions tied to it via RBF,
+    /// including CPFP children of transactions that can no longer confirm
+    async fn remove_rbf_transactions<'a>(
+        &self,
+        dbtx: &mut DatabaseTransaction<'a>,
+        pending_tx: &PendingTransaction,
+    ) {
+        let mut all_transactions: BTreeMap<Txid, PendingTransaction> = dbtx
+            .find_by_prefix(&PendingTransactionPrefixKey)
+            .await
+            .map(|(key, val)| (key.0, val))
+            .collect::<BTreeMap<Txid, PendingTransaction>>()
+            .await;
+
+        // We need to search and remove all `PendingTransactions` invalidated by RBF
+        let confirmed_txid = pending_tx.tx.compute_txid();
+        let mut pending_to_remove = vec![pending_tx.clone()];
+        while let Some(removed) = pending_to_remove.pop() {
+            let removed_txid = removed.tx.compute_txid();
+            all_transactions.remove(&removed_txid);
+            dbtx.remove_entry(&PendingTransactionKey(removed_txid))
+                .await;
+            dbtx.remove_by_prefix(&CpfpVoteTxidPrefix(removed_txid))
+                .await;
+
+            // A CPFP child spends the change of its parent, so it can only confirm
+            // together with it
+            if removed_txid != confirmed_txid {
+                if let Some(child) = dbtx.remove_entry(&CpfpChildKey(removed_txid)).await {
+                    self.remove_signing_state(dbtx, child.txid).await;
+
+                    // The child can never confirm, so its fee was not spent
+                    self.add_fee_income(dbtx, child.fee.into()).await;
+
+                    if let Some(child) = all_transactions.get(&child.txid) {
+                        pending_to_remove.push(child.clone());
+                    }
+                }
+            }
+
+            // Search for tx that this `removed` has as RBF
+            if let Some(rbf) = &removed.rbf {
+                if let Some(tx) = all_transactions.get(&rbf.txid) {
+                    pending_to_remove.push(tx.clone());
+                }
+            }
+
+            // Search for tx that wanted to RBF the `removed` one
+            for tx in all_transactions.values() {
+                if let Some(rbf) = &tx.rbf {
+                    if rbf.txid == removed.tx.compute_txid() {
+                        pending_to_remove.push(tx.clone());
+                    }
+                }
             }
         }
     }
@@ -1490,771 +2945,2763 @@ async fn create_peg_out_tx(
         }
     }
 
-    async fn available_utxos(
+    /// Validates a peg-out to an explicit output script and creates its
+    /// peg-out transaction.
+    async fn process_script_peg_out(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
-    ) -> Vec<(UTXOKey, SpendableUTXO)> {
-        dbtx.find_by_prefix(&UTXOPrefixKey)
-            .await
-            .collect::<Vec<(UTXOKey, SpendableUTXO)>>()
-            .await
-    }
-
-    pub async fn get_wallet_value(&self, dbtx: &mut DatabaseTransaction<'_>) -> bitcoin::Amount {
-        let sat_sum = self
-            .available_utxos(dbtx)
-            .await
-            .into_iter()
-            .map(|(_, utxo)| utxo.amount.to_sat())
-            .sum();
-        bitcoin::Amount::from_sat(sat_sum)
-    }
-
-    async fn get_wallet_summary(&self, dbtx: &mut DatabaseTransaction<'_>) -> WalletSummary {
-        fn partition_peg_out_and_change(
-            transactions: Vec<Transaction>,
-        ) -> (Vec<TxOutputSummary>, Vec<TxOutputSummary>) {
-            let mut peg_out_txos: Vec<TxOutputSummary> = Vec::new();
-            let mut change_utxos: Vec<TxOutputSummary> = Vec::new();
-
-            for tx in transactions {
-                let txid = tx.compute_txid();
-
-                // to identify outputs for the peg_out (idx = 0) and change (idx = 1), we lean
-                // on how the wallet constructs the transaction
-                let peg_out_output = tx
-                    .output
-                    .first()
-                    .expect("tx must contain withdrawal output");
-
-                let change_output = tx.output.last().expect("tx must contain change output");
-
-                peg_out_txos.push(TxOutputSummary {
-                    outpoint: bitcoin::OutPoint { txid, vout: 0 },
-                    amount: peg_out_output.value,
-                });
-
-                change_utxos.push(TxOutputSummary {
-                    outpoint: bitcoin::OutPoint { txid, vout: 1 },
-                    amount: change_output.value,
-                });
-            }
-
-            (peg_out_txos, change_utxos)
+        output: &WalletOutputV2,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmount, WalletOutputError> {
+        if self.consensus_module_consensus_version(dbtx).await
+            < SCRIPT_PEG_OUT_MODULE_CONSENSUS_VERSION
+        {
+            return Err(WalletOutputError::ScriptPegOutNotSupported);
         }
 
-        let spendable_utxos = self
-            .available_utxos(dbtx)
-            .await
-            .iter()
-            .map(|(utxo_key, spendable_utxo)| TxOutputSummary {
-                outpoint: utxo_key.0,
-                amount: spendable_utxo.amount,
-            })
-            .collect::<Vec<_>>();
+        if !is_standard_peg_out_script(&output.script_pubkey) {
+            return Err(WalletOutputError::NonStandardScript);
+        }
 
-        // constructed peg-outs without threshold signatures
-        let unsigned_transactions = dbtx
-            .find_by_prefix(&UnsignedTransactionPrefixKey)
-            .await
-            .map(|(_tx_key, tx)| tx.psbt.unsigned_tx)
-            .collect::<Vec<_>>()
-            .await;
+        let change_tweak = self.consensus_nonce(dbtx).await;
 
-        // peg-outs with threshold signatures, awaiting finality delay confirmations
-        let unconfirmed_transactions = dbtx
-            .find_by_prefix(&PendingTransactionPrefixKey)
-            .await
-            .map(|(_tx_key, tx)| tx.tx)
-            .collect::<Vec<_>>()
-            .await;
+        let tx = self.offline_wallet().create_tx(
+            output.amount,
+            output.script_pubkey.clone(),
+            vec![],
+            self.available_utxos(dbtx).await,
+            output.fees.fee_rate,
+            &change_tweak,
+            None,
+        )?;
 
-        let (unsigned_peg_out_txos, unsigned_change_utxos) =
-            partition_peg_out_and_change(unsigned_transactions);
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
 
-        let (unconfirmed_peg_out_txos, unconfirmed_change_utxos) =
-            partition_peg_out_and_change(unconfirmed_transactions);
+        StatelessWallet::validate_tx_fees(&tx, output.fees, fee_rate)?;
 
-        WalletSummary {
-            spendable_utxos,
-            unsigned_peg_out_txos,
-            unsigned_change_utxos,
-            unconfirmed_peg_out_txos,
-            unconfirmed_change_utxos,
-        }
+        Ok(self
+            .record_peg_out(dbtx, tx, out_point, output.amount())
+            .await)
     }
 
-    async fn is_utxo_confirmed(
+    /// Signs a validated peg-out transaction and records it as the outcome of
+    /// the peg-out output at `out_point`.
+    async fn record_peg_out(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
-        outpoint: bitcoin::OutPoint,
-    ) -> bool {
-        dbtx.get_value(&UnspentTxOutKey(outpoint)).await.is_some()
+        tx: UnsignedTransaction,
+        out_point: OutPoint,
+        amount: bitcoin::Amount,
+    ) -> TransactionItemAmount {
+        let amount = self.charge_peg_out(dbtx, amount).await;
+        self.sign_peg_out_output(dbtx, tx, out_point, amount).await;
+        amount
     }
 
-    fn offline_wallet(&self) -> StatelessWallet {
-        StatelessWallet {
-            descriptor: &self.cfg.consensus.peg_in_descriptor,
-            secret_key: &self.cfg.private.peg_in_key,
-            secp: &self.secp,
-        }
+    /// Charges the peg-out fee for a peg-out output of `amount`
+    async fn charge_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        amount: bitcoin::Amount,
+    ) -> TransactionItemAmount {
+        let amount: fedimint_core::Amount = amount.into();
+        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
+        calculate_pegout_metrics(dbtx, amount, fee);
+        self.add_fee_income(dbtx, fee).await;
+        TransactionItemAmount { amount, fee }
     }
 
-    fn spawn_broadcast_pending_task(
-        task_group: &TaskGroup,
-        server_bitcoin_rpc_monitor: &ServerBitcoinRpcMonitor,
-        db: &Database,
-        broadcast_pending_notify: Arc<Notify>,
+    /// Signs the peg-out transaction of the peg-out output at `out_point` and
+    /// records its txid as the outcome of the output
+    async fn sign_peg_out_output(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        tx: UnsignedTransaction,
+        out_point: OutPoint,
+        TransactionItemAmount { amount, fee }: TransactionItemAmount,
     ) {
-        task_group.spawn_cancellable("broadcast pending", {
-            let btc_rpc = server_bitcoin_rpc_monitor.clone();
-            let db = db.clone();
-            run_broadcast_pending_tx(db, btc_rpc, broadcast_pending_notify)
-        });
-    }
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
 
-    /// Get the bitcoin network for UI display
-    pub fn network_ui(&self) -> Network {
-        self.cfg.consensus.network.0
+        dbtx.insert_new_entry(
+            &PegOutBitcoinTransaction(out_point),
+            &WalletOutputOutcome::new_v0(txid),
+        )
+        .await;
+        self.event_logger
+            .log_event(
+                dbtx,
+                PegOutCreated {
+                    out_point,
+                    txid,
+                    amount,
+                    fee,
+                },
+            )
+            .await;
     }
 
-    /// Get the current consensus block count for UI display
-    pub async fn consensus_block_count_ui(&self) -> u32 {
-        self.consensus_block_count(&mut self.db.begin_transaction_nc().await)
-            .await
-    }
+    /// Validates a peg-out to a silent payment address and creates its
+    /// peg-out transaction to the placeholder script of the address. Since
+    /// the output key depends on the group secret key, the transaction is only
+    /// signed once a threshold of guardians contributed their share of the
+    /// ECDH with the scan key in [`Self::process_silent_payment_share`].
+    async fn process_silent_payment_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        output: &WalletOutputV3,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmount, WalletOutputError> {
+        if !self.cfg.consensus.is_taproot()
+            || self.consensus_module_consensus_version(dbtx).await
+                < SILENT_PAYMENT_MODULE_CONSENSUS_VERSION
+        {
+            return Err(WalletOutputError::SilentPaymentNotSupported);
+        }
 
-    /// Get the current consensus fee rate for UI display
-    pub async fn consensus_feerate_ui(&self) -> Feerate {
-        self.consensus_fee_rate(&mut self.db.begin_transaction_nc().await)
-            .await
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let tx = self.offline_wallet().create_tx(
+            output.amount,
+            output.address.placeholder_script_pubkey(),
+            vec![],
+            self.available_utxos(dbtx).await,
+            output.fees.fee_rate,
+            &change_tweak,
+            None,
+        )?;
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        StatelessWallet::validate_tx_fees(&tx, output.fees, fee_rate)?;
+
+        self.silent_payment_inputs(&tx)
+            .map_err(|_| WalletOutputError::SilentPaymentIneligible)?;
+
+        // Reserve the inputs while we wait for the shares
+        for input in &tx.psbt.unsigned_tx.input {
+            dbtx.remove_entry(&UTXOKey(input.previous_output)).await;
+        }
+
+        let (share, challenge, response) =
+            silent_payment::share(&self.cfg.private.peg_in_key, &output.address.scan_key);
+
+        dbtx.insert_new_entry(
+            &SilentPaymentShareCI(out_point),
+            &SilentPaymentShareItem {
+                out_point,
+                share,
+                challenge,
+                response,
+            },
+        )
+        .await;
+
+        dbtx.insert_new_entry(
+            &PendingSilentPaymentKey(out_point),
+            &PendingSilentPayment {
+                output: output.clone(),
+                tx,
+            },
+        )
+        .await;
+
+        Ok(self.charge_peg_out(dbtx, output.amount()).await)
     }
 
-    /// Get the current wallet summary for UI display
-    pub async fn get_wallet_summary_ui(&self) -> WalletSummary {
-        self.get_wallet_summary(&mut self.db.begin_transaction_nc().await)
+    /// Records the share of a peer of the ECDH for a silent payment peg-out.
+    /// Once a threshold of peers contributed, the placeholder output of the
+    /// peg-out transaction is replaced with the silent payment output and the
+    /// transaction is signed.
+    async fn process_silent_payment_share(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peer: PeerId,
+        item: SilentPaymentShareItem,
+    ) -> anyhow::Result<()> {
+        let out_point = item.out_point;
+
+        let pending = dbtx
+            .get_value(&PendingSilentPaymentKey(out_point))
             .await
-    }
+            .context("No pending silent payment peg-out")?;
 
-    /// Shutdown the task group shared throughout fedimintd, giving 60 seconds
-    /// for other services to gracefully shutdown.
-    async fn graceful_shutdown(&self) {
-        if let Err(e) = self
-            .task_group
-            .clone()
-            .shutdown_join_all(Some(Duration::from_secs(60)))
+        ensure!(
+            dbtx.get_value(&SilentPaymentShareKey(out_point, peer))
+                .await
+                .is_none(),
+            "Silent payment share is redundant"
+        );
+
+        silent_payment::verify_share(
+            &self
+                .cfg
+                .consensus
+                .peer_peg_in_keys
+                .get(&peer)
+                .context("Unknown peer")?
+                .key,
+            &pending.output.address.scan_key,
+            &item.share,
+            &item.challenge,
+            &item.response,
+        )?;
+
+        dbtx.insert_new_entry(&SilentPaymentShareKey(out_point, peer), &item.share)
+            .await;
+
+        if peer == self.our_peer_id {
+            dbtx.remove_entry(&SilentPaymentShareCI(out_point)).await;
+        }
+
+        let shares = dbtx
+            .find_by_prefix(&SilentPaymentShareOutPointPrefix(out_point))
             .await
+            .map(|(key, share)| (key.1, share))
+            .collect::<BTreeMap<_, _>>()
+            .await;
+
+        if shares.len()
+            < self
+                .cfg
+                .consensus
+                .peer_peg_in_keys
+                .to_num_peers()
+                .threshold()
         {
-            panic!("Error while shutting down fedimintd task group: {e}");
+            return Ok(());
         }
+
+        let PendingSilentPayment { output, mut tx } = pending;
+
+        // Both only fail with negligible probability, since the inputs were
+        // checked when the peg-out was processed and the shares are valid
+        let script_pubkey = self
+            .silent_payment_inputs(&tx)
+            .and_then(|inputs| {
+                inputs.output_script(&output.address, &silent_payment::combine_shares(&shares)?)
+            })
+            .expect("Failed to derive silent payment output");
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %out_point,
+            "Derived silent payment output of peg out",
+        );
+
+        tx.psbt.unsigned_tx.output[0].script_pubkey = script_pubkey.clone();
+        tx.destination = script_pubkey;
+
+        dbtx.remove_entry(&PendingSilentPaymentKey(out_point)).await;
+        dbtx.remove_entry(&SilentPaymentShareCI(out_point)).await;
+        dbtx.remove_by_prefix(&SilentPaymentShareOutPointPrefix(out_point))
+            .await;
+
+        let amount = TransactionItemAmount {
+            amount: output.amount().into(),
+            fee: self.cfg.consensus.fee_consensus.peg_out_abs,
+        };
+
+        self.sign_peg_out_output(dbtx, tx, out_point, amount).await;
+
+        Ok(())
     }
 
-    /// Returns once our bitcoin backend observes finality delay confirmations
-    /// of the consensus block count. If we don't observe enough confirmations
-    /// after one hour, we gracefully shutdown fedimintd. This is necessary
-    /// since we can no longer participate in consensus if our bitcoin backend
-    /// is unable to observe the same chain tip as our peers.
-    async fn wait_for_finality_confs_or_shutdown(&self, consensus_block_count: u32) {
-        let backoff = if is_running_in_test_env() {
-            // every 100ms for 60s
-            backoff_util::custom_backoff(
-                Duration::from_millis(100),
-                Duration::from_millis(100),
-                Some(10 * 60),
-            )
-        } else {
-            // every max 10s for 1 hour
-            backoff_util::fibonacci_max_one_hour()
+    /// The inputs of a peg-out transaction of a taproot federation as inputs
+    /// of a silent payment
+    fn silent_payment_inputs(
+        &self,
+        tx: &UnsignedTransaction,
+    ) -> anyhow::Result<SilentPaymentInputs> {
+        let Descriptor::Tr(descriptor) = &self.cfg.consensus.peg_in_descriptor else {
+            bail!("Peg-in descriptor is not taproot");
         };
 
-        let wait_for_finality_confs = || async {
-            let our_chain_tip_block_count = self.get_block_count()?;
-            let consensus_chain_tip_block_count =
-                consensus_block_count + self.cfg.consensus.finality_delay;
+        SilentPaymentInputs::new(
+            &descriptor.internal_key().key,
+            tx.selected_utxos
+                .iter()
+                .map(|(key, utxo)| (key.0, utxo.tweak)),
+        )
+    }
 
-            if consensus_chain_tip_block_count <= our_chain_tip_block_count {
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!("not enough confirmations"))
+    /// Fees a peg-out of `amount` to `destination` has to pay right now, or
+    /// `None` if the wallet can not fund it.
+    async fn peg_out_fees(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        destination: ScriptBuf,
+        amount: bitcoin::Amount,
+    ) -> Option<PegOutFees> {
+        let feerate = self.consensus_fee_rate(dbtx).await;
+
+        // Since we are only calculating the tx size we can use an arbitrary dummy nonce.
+        let dummy_tweak = [0; 33];
+
+        let tx = self.offline_wallet().create_tx(
+            amount,
+            destination,
+            vec![],
+            self.available_utxos(dbtx).await,
+            feerate,
+            &dummy_tweak,
+            None,
+        );
+
+        match tx {
+            Err(error) => {
+                // Usually from not enough spendable UTXOs
+                warn!(target: LOG_MODULE_WALLET, "Error returning peg-out fees {error}");
+                None
             }
-        };
+            Ok(tx) => Some(tx.fees),
+        }
+    }
 
-        if retry("wait_for_finality_confs", backoff, wait_for_finality_confs)
-            .await
-            .is_err()
+    /// Fees a batched peg-out to `address` has to pay right now, or `None` if
+    /// batched peg-outs are not active yet.
+    async fn batched_peg_out_fees(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        address: &Address<NetworkUnchecked>,
+    ) -> Option<PegOutFees> {
+        if self.consensus_module_consensus_version(dbtx).await
+            < BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION
         {
-            self.graceful_shutdown().await;
+            return None;
         }
+
+        // Note: the network is checked once the peg-out is submitted, we only need the
+        // script length here.
+        let destination = address.clone().assume_checked().script_pubkey();
+
+        Some(PegOutFees {
+            fee_rate: self.consensus_fee_rate(dbtx).await,
+            total_weight: self.offline_wallet().batched_peg_out_weight(&destination),
+        })
     }
 
-    fn spawn_peer_supported_consensus_version_task(
-        api_client: DynModuleApi,
-        task_group: &TaskGroup,
-        our_peer_id: PeerId,
-    ) -> watch::Receiver<Option<ModuleConsensusVersion>> {
-        let (sender, receiver) = watch::channel(None);
-        task_group.spawn_cancellable("fetch-peer-consensus-versions", async move {
-            loop {
-                let request_futures = api_client.all_peers().iter().filter_map(|&peer| {
-                    if peer == our_peer_id {
-                        return None;
-                    }
+    /// Validates a batched peg-out and queues it until the current batch is
+    /// closed by [`Self::flush_peg_out_batch`].
+    async fn queue_batched_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peg_out: &PegOut,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmount, WalletOutputError> {
+        if self.consensus_module_consensus_version(dbtx).await
+            < BATCHED_PEG_OUT_MODULE_CONSENSUS_VERSION
+        {
+            return Err(WalletOutputError::BatchedPegOutNotSupported);
+        }
 
-                    let api_client_inner = api_client.clone();
-                    Some(async move {
-                        api_client_inner
-                            .request_single_peer::<ModuleConsensusVersion>(
-                                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT.to_owned(),
-                                ApiRequestErased::default(),
-                                peer,
-                            )
-                            .await
-                            .inspect(|res| debug!(
-                                target: LOG_MODULE_WALLET,
-                                %peer,
-                                %our_peer_id,
-                                ?res,
-                                "Fetched supported module consensus version from peer"
-                            ))
-                            .inspect_err(|err| warn!(
-                                target: LOG_MODULE_WALLET,
-                                 %peer,
-                                 err=%err.fmt_compact(),
-                                "Failed to fetch consensus version from peer"
-                            ))
-                            .ok()
-                    })
-                });
+        let network = self.cfg.consensus.network.0;
 
-                let peer_consensus_versions = join_all(request_futures)
-                    .await
-                    .into_iter()
-                    .flatten()
-                    .collect::<Vec<_>>();
+        if !peg_out.recipient.is_valid_for_network(network) {
+            return Err(WalletOutputError::WrongNetwork(
+                NetworkLegacyEncodingWrapper(network),
+                NetworkLegacyEncodingWrapper(get_network_for_address(&peg_out.recipient)),
+            ));
+        }
 
-                let sorted_consensus_versions = peer_consensus_versions
-                    .into_iter()
-                    .chain(std::iter::once(MODULE_CONSENSUS_VERSION))
-                    .sorted()
-                    .collect::<Vec<_>>();
-                let all_peers_supported_version =
-                    if sorted_consensus_versions.len() == api_client.all_peers().len() {
-                        let min_supported_version = *sorted_consensus_versions
-                            .first()
-                            .expect("at least one element");
+        let destination = peg_out.recipient.clone().assume_checked().script_pubkey();
 
-                        debug!(
-                            target: LOG_MODULE_WALLET,
-                            ?sorted_consensus_versions,
-                            "Fetched supported consensus versions from peers"
-                        );
+        if peg_out.amount < destination.minimal_non_dust() {
+            return Err(WalletOutputError::PegOutUnderDustLimit);
+        }
 
-                        Some(min_supported_version)
-                    } else {
-                        assert!(
-                            sorted_consensus_versions.len() <= api_client.all_peers().len(),
-                            "Too many peer responses",
-                        );
-                        trace!(
-                            target: LOG_MODULE_WALLET,
-                            ?sorted_consensus_versions,
-                            "Not all peers have reported their consensus version yet"
-                        );
-                        None
-                    };
+        let consensus_fee_rate = self.consensus_fee_rate(dbtx).await;
 
-                #[allow(clippy::disallowed_methods)]
-                if sender.send(all_peers_supported_version).is_err() {
-                    warn!(target: LOG_MODULE_WALLET, "Failed to send consensus version to watch channel, stopping task");
-                    break;
-                }
+        if peg_out.fees.fee_rate < consensus_fee_rate {
+            return Err(WalletOutputError::PegOutFeeBelowConsensus(
+                peg_out.fees.fee_rate,
+                consensus_fee_rate,
+            ));
+        }
 
-                if is_running_in_test_env() {
-                    // Even in tests we don't want to spam the federation with requests about it
-                    sleep(Duration::from_secs(5)).await;
-                } else {
-                    sleep(Duration::from_secs(600)).await;
-                }
-            }
-        });
-        receiver
-    }
-}
-
-#[instrument(target = LOG_MODULE_WALLET, level = "debug", skip_all)]
-pub async fn run_broadcast_pending_tx(
-    db: Database,
-    rpc: ServerBitcoinRpcMonitor,
-    broadcast: Arc<Notify>,
-) {
-    loop {
-        // Unless something new happened, we broadcast once a minute
-        let _ = tokio::time::timeout(Duration::from_secs(60), broadcast.notified()).await;
-        broadcast_pending_tx(db.begin_transaction_nc().await, &rpc).await;
-    }
-}
+        if peg_out.fees.fee_rate.sats_per_kvb < u64::from(DEFAULT_MIN_RELAY_TX_FEE) {
+            return Err(WalletOutputError::BelowMinRelayFee);
+        }
 
-pub async fn broadcast_pending_tx(
-    mut dbtx: DatabaseTransaction<'_>,
-    rpc: &ServerBitcoinRpcMonitor,
-) {
-    let pending_tx: Vec<PendingTransaction> = dbtx
-        .find_by_prefix(&PendingTransactionPrefixKey)
-        .await
-        .map(|(_, val)| val)
-        .collect::<Vec<_>>()
-        .await;
-    let rbf_txids: BTreeSet<Txid> = pending_tx
-        .iter()
-        .filter_map(|tx| tx.rbf.clone().map(|rbf| rbf.txid))
-        .collect();
-    if !pending_tx.is_empty() {
-        debug!(
-            target: LOG_MODULE_WALLET,
-            "Broadcasting pending transactions (total={}, rbf={})",
-            pending_tx.len(),
-            rbf_txids.len()
-        );
-    }
+        let share_weight = self.offline_wallet().batched_peg_out_weight(&destination);
 
-    for PendingTransaction { tx, .. } in pending_tx {
-        if !rbf_txids.contains(&tx.compute_txid()) {
-            debug!(
-                target: LOG_MODULE_WALLET,
-                tx = %tx.compute_txid(),
-                weight = tx.weight().to_wu(),
-                output = ?tx.output,
-                "Broadcasting peg-out",
-            );
-            trace!(transaction = ?tx);
-            rpc.submit_transaction(tx).await;
+        if peg_out.fees.total_weight != share_weight {
+            return Err(WalletOutputError::TxWeightIncorrect(
+                peg_out.fees.total_weight,
+                share_weight,
+            ));
         }
-    }
-}
 
-struct StatelessWallet<'a> {
-    descriptor: &'a Descriptor<CompressedPublicKey>,
-    secret_key: &'a secp256k1::SecretKey,
-    secp: &'a secp256k1::Secp256k1<secp256k1::All>,
-}
+        // Every queued peg-out has to be covered by our spendable UTXOs, otherwise we
+        // could not build the batch transaction once the batch is closed.
+        let queued = dbtx
+            .find_by_prefix(&PegOutBatchItemPrefix)
+            .await
+            .fold(bitcoin::Amount::ZERO, |acc, (_, item)| async move {
+                acc + item.amount + item.fees.amount()
+            })
+            .await;
 
-impl<'a> StatelessWallet<'a> {
-    /// Given a tx created from an `WalletOutput`, validate there will be no
-    /// issues submitting the transaction to the Bitcoin network
-    fn validate_tx(
-        tx: &UnsignedTransaction,
-        output: &WalletOutputV0,
-        consensus_fee_rate: Feerate,
-        network: Network,
-    ) -> Result<(), WalletOutputError> {
-        if let WalletOutputV0::PegOut(peg_out) = output {
-            if !peg_out.recipient.is_valid_for_network(network) {
-                return Err(WalletOutputError::WrongNetwork(
-                    NetworkLegacyEncodingWrapper(network),
-                    NetworkLegacyEncodingWrapper(get_network_for_address(&peg_out.recipient)),
-                ));
-            }
+        if self.get_wallet_value(dbtx).await < queued + peg_out.amount + peg_out.fees.amount() {
+            return Err(WalletOutputError::NotEnoughSpendableUTXO);
         }
 
-        // Validate the tx amount is over the dust limit
-        if tx.peg_out_amount < tx.destination.minimal_non_dust() {
-            return Err(WalletOutputError::PegOutUnderDustLimit);
-        }
+        dbtx.insert_new_entry(&PegOutBatchItemKey(out_point), peg_out)
+            .await;
 
-        // Validate tx fee rate is above the consensus fee rate
-        if tx.fees.fee_rate < consensus_fee_rate {
-            return Err(WalletOutputError::PegOutFeeBelowConsensus(
-                tx.fees.fee_rate,
-                consensus_fee_rate,
-            ));
-        }
+        if dbtx.get_value(&PegOutBatchStartKey).await.is_none() {
+            let session_count = self.consensus_session_count(dbtx).await;
 
-        // Validate added fees are above the min relay tx fee
-        // BIP-0125 requires 1 sat/vb for RBF by default (same as normal txs)
-        let fees = match output {
-            WalletOutputV0::PegOut(pegout) => pegout.fees,
-            WalletOutputV0::Rbf(rbf) => rbf.fees,
-        };
-        if fees.fee_rate.sats_per_kvb < u64::from(DEFAULT_MIN_RELAY_TX_FEE) {
-            return Err(WalletOutputError::BelowMinRelayFee);
+            dbtx.insert_new_entry(&PegOutBatchStartKey, &session_count)
+                .await;
         }
 
-        // Validate fees weight matches the actual weight
-        if fees.total_weight != tx.fees.total_weight {
-            return Err(WalletOutputError::TxWeightIncorrect(
-                fees.total_weight,
-                tx.fees.total_weight,
-            ));
-        }
+        debug!(
+            target: LOG_MODULE_WALLET,
+            %out_point,
+            amount = %peg_out.amount,
+            "Queued batched peg-out"
+        );
 
-        Ok(())
+        let amount: fedimint_core::Amount = (peg_out.amount + peg_out.fees.amount()).into();
+        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
+        calculate_pegout_metrics(dbtx, amount, fee);
+        self.add_fee_income(dbtx, fee).await;
+        Ok(TransactionItemAmount { amount, fee })
     }
 
-    /// Attempts to create a tx ready to be signed from available UTXOs.
-    //
-    // * `peg_out_amount`: How much the peg-out should be
-    // * `destination`: The address the user is pegging-out to
-    // * `included_utxos`: UXTOs that must be included (for RBF)
-    // * `remaining_utxos`: All other spendable UXTOs
-    // * `fee_rate`: How much needs to be spent on fees
-    // * `change_tweak`: How the federation can recognize it's change UTXO
-    // * `rbf`: If this is an RBF transaction
-    #[allow(clippy::too_many_arguments)]
-    fn create_tx(
-        &self,
-        peg_out_amount: bitcoin::Amount,
-        destination: ScriptBuf,
-        mut included_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-        mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-        mut fee_rate: Feerate,
-        change_tweak: &[u8; 33],
-        rbf: Option<Rbf>,
-    ) -> Result<UnsignedTransaction, WalletOutputError> {
-        // Add the rbf fees to the existing tx fees
-        if let Some(rbf) = &rbf {
-            fee_rate.sats_per_kvb += rbf.fees.fee_rate.sats_per_kvb;
+    /// Once the peg-out batch window has passed, pays out all queued batched
+    /// peg-outs in a single transaction. The fee shares prepaid by the
+    /// individual peg-outs only cover their own output and one input, so if
+    /// they fall short of the lowest fee rate the peg-outs committed to, the
+    /// remainder is paid from the fee budget. Otherwise the batch stays queued
+    /// until further peg-outs or fee income cover it.
+    async fn flush_peg_out_batch(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let Some(batch_start) = dbtx.get_value(&PegOutBatchStartKey).await else {
+            return;
+        };
+
+        if self.consensus_session_count(dbtx).await < batch_start + PEG_OUT_BATCH_WINDOW_SESSIONS {
+            return;
         }
 
-        // When building a transaction we need to take care of two things:
-        //  * We need enough input amount to fund all outputs
-        //  * We need to keep an eye on the tx weight so we can factor the fees into out
-        //    calculation
-        // We then go on to calculate the base size of the transaction `total_weight`
-        // and the maximum weight per added input which we will add every time
-        // we select an input.
-        let change_script = self.derive_script(change_tweak);
-        let out_weight = (destination.len() * 4 + 1 + 32
-            // Add change script weight, it's very likely to be needed if not we just overpay in fees
-            + 1 // script len varint, 1 byte for all addresses we accept
-            + change_script.len() * 4 // script len
-            + 32) as u64; // value
-        let mut total_weight = 16 + // version
-            12 + // up to 2**16-1 inputs
-            12 + // up to 2**16-1 outputs
-            out_weight + // weight of all outputs
-            16; // lock time
-        // https://github.com/fedimint/fedimint/issues/4590
-        #[allow(deprecated)]
-        let max_input_weight = (self
-            .descriptor
-            .max_satisfaction_weight()
-            .expect("is satisfyable") +
-            128 + // TxOutHash
-            16 + // TxOutIndex
-            16) as u64; // sequence
+        // The prefix scan is ordered by out point, so all peers agree on the output order
+        let items = dbtx
+            .find_by_prefix(&PegOutBatchItemPrefix)
+            .await
+            .map(|(key, peg_out)| (key.0, peg_out))
+            .collect::<Vec<(OutPoint, PegOut)>>()
+            .await;
 
-        // Ensure deterministic ordering of UTXOs for all peers
-        included_utxos.sort_by_key(|(_, utxo)| utxo.amount);
-        remaining_utxos.sort_by_key(|(_, utxo)| utxo.amount);
-        included_utxos.extend(remaining_utxos);
+        let payouts = items
+            .iter()
+            .map(|(_, peg_out)| {
+                (
+                    // Note: the network was validated when the peg-out was queued
+                    peg_out.recipient.clone().assume_checked().script_pubkey(),
+                    peg_out.amount,
+                )
+            })
+            .collect::<Vec<_>>();
 
-        // Finally we initialize our accumulator for selected input amounts
-        let mut total_selected_value = bitcoin::Amount::from_sat(0);
-        let mut selected_utxos: Vec<(UTXOKey, SpendableUTXO)> = vec![];
-        let mut fees = fee_rate.calculate_fee(total_weight);
+        let prepaid_fees = items
+            .iter()
+            .fold(bitcoin::Amount::ZERO, |acc, (_, peg_out)| {
+                acc + peg_out.fees.amount()
+            });
 
-        while total_selected_value < peg_out_amount + change_script.minimal_non_dust() + fees {
-            match included_utxos.pop() {
-                Some((utxo_key, utxo)) => {
-                    total_selected_value += utxo.amount;
-                    total_weight += max_input_weight;
-                    fees = fee_rate.calculate_fee(total_weight);
-                    selected_utxos.push((utxo_key, utxo));
-                }
-                _ => return Err(WalletOutputError::NotEnoughSpendableUTXO), // Not enough UTXOs
+        let Some(fee_rate) = items.iter().map(|(_, peg_out)| peg_out.fees.fee_rate).min() else {
+            dbtx.remove_entry(&PegOutBatchStartKey).await;
+            return;
+        };
+
+        let fee_budget = bitcoin::Amount::from_sat(self.fee_budget(dbtx).await.sats_round_down());
+
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let tx = match self.offline_wallet().create_batch_tx(
+            payouts,
+            prepaid_fees,
+            fee_rate,
+            prepaid_fees + fee_budget,
+            self.available_utxos(dbtx).await,
+            &change_tweak,
+        ) {
+            Ok(tx) => tx,
+            Err(error) => {
+                // The queued peg-outs stay in the batch and are retried with the next
+                // session count increase
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    %error,
+                    peg_outs = items.len(),
+                    "Failed to create batched peg-out tx"
+                );
+                return;
             }
-        }
+        };
 
-        // We always pay ourselves change back to ensure that we don't lose anything due
-        // to dust
-        let change = total_selected_value - fees - peg_out_amount;
-        let output: Vec<TxOut> = vec![
-            TxOut {
-                value: peg_out_amount,
-                script_pubkey: destination.clone(),
-            },
-            TxOut {
-                value: change,
-                script_pubkey: change_script,
-            },
-        ];
-        let mut change_out = bitcoin::psbt::Output::default();
-        change_out
-            .proprietary
-            .insert(proprietary_tweak_key(), change_tweak.to_vec());
+        let fees = tx
+            .selected_utxos
+            .iter()
+            .fold(bitcoin::Amount::ZERO, |acc, (_, utxo)| acc + utxo.amount)
+            - tx.peg_out_amount
+            - tx.change;
+
+        self.spend_fee_budget(dbtx, fees - prepaid_fees)
+            .await
+            .expect("The batch transaction does not exceed the fee budget");
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
 
         info!(
             target: LOG_MODULE_WALLET,
-            inputs = selected_utxos.len(),
-            input_sats = total_selected_value.to_sat(),
-            peg_out_sats = peg_out_amount.to_sat(),
-            ?total_weight,
+            %txid,
+            peg_outs = items.len(),
             fees_sats = fees.to_sat(),
-            fee_rate = fee_rate.sats_per_kvb,
-            change_sats = change.to_sat(),
-            "Creating peg-out tx",
+            subsidy_sats = (fees - prepaid_fees).to_sat(),
+            "Closed peg-out batch"
         );
 
-        let transaction = Transaction {
-            version: bitcoin::transaction::Version(2),
-            lock_time: LockTime::ZERO,
-            input: selected_utxos
-                .iter()
-                .map(|(utxo_key, _utxo)| TxIn {
-                    previous_output: utxo_key.0,
-                    script_sig: Default::default(),
-                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
-                    witness: bitcoin::Witness::new(),
-                })
-                .collect(),
-            output,
-        };
-        info!(
-            target: LOG_MODULE_WALLET,
-            txid = %transaction.compute_txid(), "Creating peg-out tx"
-        );
+        for (out_point, peg_out) in items {
+            dbtx.remove_entry(&PegOutBatchItemKey(out_point)).await;
 
-        // FIXME: use custom data structure that guarantees more invariants and only
-        // convert to PSBT for finalization
-        let psbt = Psbt {
-            unsigned_tx: transaction,
-            version: 0,
-            xpub: Default::default(),
-            proprietary: Default::default(),
-            unknown: Default::default(),
-            inputs: selected_utxos
-                .iter()
-                .map(|(_utxo_key, utxo)| {
-                    let script_pubkey = self
-                        .descriptor
-                        .tweak(&utxo.tweak, self.secp)
-                        .script_pubkey();
-                    Input {
-                        non_witness_utxo: None,
-                        witness_utxo: Some(TxOut {
-                            value: utxo.amount,
-                            script_pubkey,
-                        }),
-                        partial_sigs: Default::default(),
-                        sighash_type: None,
-                        redeem_script: None,
-                        witness_script: Some(
-                            self.descriptor
-                                .tweak(&utxo.tweak, self.secp)
-                                .script_code()
-                                .expect("Failed to tweak descriptor"),
-                        ),
-                        bip32_derivation: Default::default(),
-                        final_script_sig: None,
-                        final_script_witness: None,
-                        ripemd160_preimages: Default::default(),
-                        sha256_preimages: Default::default(),
-                        hash160_preimages: Default::default(),
-                        hash256_preimages: Default::default(),
-                        proprietary: vec![(proprietary_tweak_key(), utxo.tweak.to_vec())]
-                            .into_iter()
-                            .collect(),
-                        tap_key_sig: Default::default(),
-                        tap_script_sigs: Default::default(),
-                        tap_scripts: Default::default(),
-                        tap_key_origins: Default::default(),
-                        tap_internal_key: Default::default(),
-                        tap_merkle_root: Default::default(),
-                        unknown: Default::default(),
-                    }
-                })
-                .collect(),
-            outputs: vec![Default::default(), change_out],
-        };
+            dbtx.insert_new_entry(
+                &PegOutBitcoinTransaction(out_point),
+                &WalletOutputOutcome::new_v0(txid),
+            )
+            .await;
 
-        Ok(UnsignedTransaction {
-            psbt,
-            signatures: vec![],
-            change,
-            fees: PegOutFees {
-                fee_rate,
-                total_weight,
-            },
-            destination,
-            selected_utxos,
-            peg_out_amount,
-            rbf,
-        })
+            self.event_logger
+                .log_event(
+                    dbtx,
+                    PegOutCreated {
+                        out_point,
+                        txid,
+                        amount: (peg_out.amount + peg_out.fees.amount()).into(),
+                        fee: self.cfg.consensus.fee_consensus.peg_out_abs,
+                    },
+                )
+                .await;
+        }
+
+        dbtx.remove_entry(&PegOutBatchStartKey).await;
     }
 
-    fn sign_psbt(&self, psbt: &mut Psbt) {
-        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+    /// The pending transaction `txid` followed by its unconfirmed CPFP
+    /// ancestors, which all have to be mined together with a child of it
+    async fn cpfp_package(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        txid: Txid,
+    ) -> Vec<PendingTransaction> {
+        let parents = dbtx
+            .find_by_prefix(&CpfpChildPrefix)
+            .await
+            .map(|(key, child)| (child.txid, key.0))
+            .collect::<BTreeMap<Txid, Txid>>()
+            .await;
 
-        for (idx, (psbt_input, _tx_input)) in psbt
-            .inputs
-            .iter_mut()
-            .zip(psbt.unsigned_tx.input.iter())
-            .enumerate()
-        {
-            let tweaked_secret = {
-                let tweak = psbt_input
-                    .proprietary
-                    .get(&proprietary_tweak_key())
-                    .expect("Malformed PSBT: expected tweak");
+        let mut package = vec![];
+        let mut next = Some(txid);
 
-                self.secret_key.tweak(tweak, self.secp)
+        while let Some(txid) = next {
+            let Some(tx) = dbtx.get_value(&PendingTransactionKey(txid)).await else {
+                break;
             };
 
-            let tx_hash = tx_hasher
-                .p2wsh_signature_hash(
-                    idx,
-                    psbt_input
-                        .witness_script
-                        .as_ref()
-                        .expect("Missing witness script"),
-                    psbt_input
-                        .witness_utxo
-                        .as_ref()
-                        .expect("Missing UTXO")
-                        .value,
-                    EcdsaSighashType::All,
-                )
-                .expect("Failed to create segwit sighash");
+            package.push(tx);
+            next = parents.get(&txid).copied();
+        }
 
-            let signature = self.secp.sign_ecdsa(
-                &Message::from_digest_slice(&tx_hash[..]).unwrap(),
-                &tweaked_secret,
-            );
+        package
+    }
 
-            psbt_input.partial_sigs.insert(
-                bitcoin::PublicKey {
-                    compressed: true,
-                    inner: secp256k1::PublicKey::from_secret_key(self.secp, &tweaked_secret),
-                },
-                EcdsaSig::sighash_all(signature),
-            );
-        }
+    /// Creates a child transaction spending the change of the first
+    /// transaction in `package` that brings the whole package up to `fee_rate`
+    /// while paying at most `max_fee`
+    fn create_cpfp_child(
+        &self,
+        package: &[PendingTransaction],
+        fee_rate: Feerate,
+        max_fee: bitcoin::Amount,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let parent = package.first().expect("package contains the parent");
+
+        let parent_change_script = self
+            .cfg
+            .consensus
+            .peg_in_descriptor
+            .tweak(&parent.tweak, &self.secp)
+            .script_pubkey();
+
+        let (vout, parent_change) = parent
+            .tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, output)| output.script_pubkey == parent_change_script)
+            .ok_or(WalletOutputError::NotEnoughSpendableUTXO)?;
+
+        let parent_change = (
+            UTXOKey(bitcoin::OutPoint {
+                txid: parent.tx.compute_txid(),
+                vout: vout as u32,
+            }),
+            SpendableUTXO {
+                tweak: parent.tweak,
+                amount: parent_change.value,
+            },
+        );
+
+        self.offline_wallet().create_cpfp_tx(
+            parent_change,
+            package.iter().map(PendingTransaction::fee).sum(),
+            package.iter().map(|tx| tx.fees.total_weight).sum(),
+            fee_rate,
+            max_fee,
+            change_tweak,
+        )
     }
 
-    fn derive_script(&self, tweak: &[u8]) -> ScriptBuf {
-        struct CompressedPublicKeyTranslator<'t, 's, Ctx: Verification> {
-            tweak: &'t [u8],
-            secp: &'s Secp256k1<Ctx>,
-        }
+    /// Pending transactions that we did not vote to accelerate yet, but that
+    /// are stuck given our current fee rate estimate and can be accelerated by
+    /// spending their change
+    async fn stuck_pending_transactions(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        fee_rate_estimate: Feerate,
+    ) -> Vec<Txid> {
+        let pending_txids = dbtx
+            .find_by_prefix(&PendingTransactionPrefixKey)
+            .await
+            .map(|(key, _)| key.0)
+            .collect::<Vec<Txid>>()
+            .await;
 
-        impl<'t, 's, Ctx: Verification>
-            miniscript::Translator<CompressedPublicKey, CompressedPublicKey, Infallible>
-            for CompressedPublicKeyTranslator<'t, 's, Ctx>
-        {
-            fn pk(&mut self, pk: &CompressedPublicKey) -> Result<CompressedPublicKey, Infallible> {
-                let hashed_tweak = {
-                    let mut hasher = HmacEngine::<sha256::Hash>::new(&pk.key.serialize()[..]);
-                    hasher.input(self.tweak);
-                    Hmac::from_engine(hasher).to_byte_array()
-                };
+        let consensus_fee_rate = self.consensus_fee_rate(dbtx).await;
+        let fee_budget = bitcoin::Amount::from_sat(self.fee_budget(dbtx).await.sats_round_down());
 
-                Ok(CompressedPublicKey {
-                    key: pk
-                        .key
-                        .add_exp_tweak(
-                            self.secp,
-                            &Scalar::from_be_bytes(hashed_tweak).expect("can't fail"),
-                        )
-                        .expect("tweaking failed"),
-                })
+        // The depositor pays for accelerating its deposit, not our fee budget
+        let deposit_cpfp_txids = dbtx
+            .find_by_prefix(&DepositCpfpPrefix)
+            .await
+            .map(|(_, deposit_cpfp)| deposit_cpfp.txid)
+            .collect::<BTreeSet<Txid>>()
+            .await;
+
+        let mut stuck = vec![];
+
+        for txid in pending_txids {
+            if deposit_cpfp_txids.contains(&txid)
+                || dbtx.get_value(&CpfpChildKey(txid)).await.is_some()
+                || dbtx
+                    .get_value(&CpfpVoteKey(txid, self.our_peer_id))
+                    .await
+                    .is_some()
+            {
+                continue;
             }
-            translate_hash_fail!(CompressedPublicKey, CompressedPublicKey, Infallible);
+
+            let package = self.cpfp_package(dbtx, txid).await;
+
+            let package_fee = package
+                .iter()
+                .map(PendingTransaction::fee)
+                .sum::<bitcoin::Amount>();
+
+            let package_weight = package.iter().map(|tx| tx.fees.total_weight).sum();
+
+            let package_fee_rate = package_fee.to_sat() * 1000 / weight_to_vbytes(package_weight);
+
+            if fee_rate_estimate.sats_per_kvb < package_fee_rate * CPFP_STUCK_FEE_RATE_MULTIPLIER {
+                continue;
+            }
+
+            // Since we only check whether the change and our fee budget can pay for the
+            // child we can use an arbitrary dummy nonce. The child will pay the consensus
+            // fee rate, so we only vote if we can afford that.
+            let dummy_tweak = [0; 33];
+
+            if let Err(error) =
+                self.create_cpfp_child(&package, consensus_fee_rate, fee_budget, &dummy_tweak)
+            {
+                if self
+                    .unaffordable_cpfp_txids
+                    .lock()
+                    .expect("lock poisoned")
+                    .insert(txid)
+                {
+                    warn!(
+                        target: LOG_MODULE_WALLET,
+                        %txid,
+                        %error,
+                        "Pending transaction is stuck but can not be accelerated"
+                    );
+                }
+                continue;
+            }
+
+            stuck.push(txid);
         }
 
-        let descriptor = self
-            .descriptor
-            .translate_pk(&mut CompressedPublicKeyTranslator {
-                tweak,
-                secp: self.secp,
-            })
-            .expect("can't fail");
+        stuck
+    }
 
-        descriptor.script_pubkey()
+    /// Creates a CPFP child for the pending transaction `parent_txid` after a
+    /// threshold of peers voted it to be stuck. The child is paid for out of
+    /// the fee budget, since its fee is not covered by any user.
+    async fn accelerate_stuck_tx(&self, dbtx: &mut DatabaseTransaction<'_>, parent_txid: Txid) {
+        // Whether we succeed or not, the peers have to vote again to retry with the
+        // then current fee rate and budget
+        dbtx.remove_by_prefix(&CpfpVoteTxidPrefix(parent_txid))
+            .await;
+
+        let package = self.cpfp_package(dbtx, parent_txid).await;
+        let spent_change = package.first().expect("parent is pending").change;
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+        let fee_budget = bitcoin::Amount::from_sat(self.fee_budget(dbtx).await.sats_round_down());
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let tx = match self.create_cpfp_child(&package, fee_rate, fee_budget, &change_tweak) {
+            Ok(tx) => tx,
+            Err(error) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    %parent_txid,
+                    %error,
+                    "Failed to create CPFP tx"
+                );
+                return;
+            }
+        };
+
+        let fee = spent_change - tx.change;
+
+        self.spend_fee_budget(dbtx, fee)
+            .await
+            .expect("The child pays at most the fee budget");
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %parent_txid,
+            %txid,
+            fee_sats = fee.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            "Accelerating stuck transaction via CPFP"
+        );
+
+        dbtx.insert_new_entry(
+            &CpfpChildKey(parent_txid),
+            &CpfpChild {
+                txid,
+                spent_change,
+                fee,
+            },
+        )
+        .await;
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                CpfpCreated {
+                    parent_txid,
+                    txid,
+                    fee: fee.into(),
+                },
+            )
+            .await;
     }
-}
 
-pub fn nonce_from_idx(nonce_idx: u64) -> [u8; 33] {
-    let mut nonce: [u8; 33] = [0; 33];
-    // Make it look like a compressed pubkey, has to be either 0x02 or 0x03
-    nonce[0] = 0x02;
-    nonce[1..].copy_from_slice(&nonce_idx.consensus_hash::<bitcoin::hashes::sha256::Hash>()[..]);
+    /// Checks that `request` accelerates an unconfirmed deposit that was
+    /// neither accelerated nor claimed yet and returns the deposited amount
+    async fn validate_deposit_cpfp_request(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        request: &DepositCpfpRequest,
+    ) -> anyhow::Result<bitcoin::Amount> {
+        let outpoint = request.outpoint();
 
-    nonce
-}
+        let deposit = request
+            .deposit(&self.cfg.consensus.peg_in_descriptor, &self.secp)
+            .context("Request does not accelerate a deposit to the federation")?;
 
-/// A peg-out tx that is ready to be broadcast with a tweak for the change UTXO
-#[derive(Clone, Debug, Encodable, Decodable)]
-pub struct PendingTransaction {
-    pub tx: bitcoin::Transaction,
-    pub tweak: [u8; 33],
-    pub change: bitcoin::Amount,
-    pub destination: ScriptBuf,
-    pub fees: PegOutFees,
-    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-    pub peg_out_amount: bitcoin::Amount,
-    pub rbf: Option<Rbf>,
-}
+        ensure!(
+            dbtx.get_value(&DepositCpfpKey(outpoint)).await.is_none(),
+            "Deposit was already accelerated"
+        );
 
-impl Serialize for PendingTransaction {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        if serializer.is_human_readable() {
-            serializer.serialize_str(&self.consensus_encode_to_hex())
-        } else {
-            serializer.serialize_bytes(&self.consensus_encode_to_vec())
-        }
+        ensure!(
+            dbtx.get_value(&ClaimedPegInOutpointKey(outpoint))
+                .await
+                .is_none(),
+            "Deposit was already claimed"
+        );
+
+        ensure!(
+            dbtx.get_value(&UnspentTxOutKey(outpoint)).await.is_none(),
+            "Deposit is already confirmed"
+        );
+
+        ensure!(
+            dbtx.find_by_prefix(&DepositCpfpPrefix).await.count().await
+                < MAX_UNCLAIMED_DEPOSIT_CPFPS,
+            "Too many accelerated deposits are waiting to be claimed"
+        );
+
+        Ok(deposit.value)
     }
-}
 
-/// A PSBT that is awaiting enough signatures from the federation to becoming a
-/// `PendingTransaction`
-#[derive(Clone, Debug, Eq, PartialEq, Encodable, Decodable)]
-pub struct UnsignedTransaction {
-    pub psbt: Psbt,
-    pub signatures: Vec<(PeerId, PegOutSignatureItem)>,
-    pub change: bitcoin::Amount,
-    pub fees: PegOutFees,
-    pub destination: ScriptBuf,
-    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-    pub peg_out_amount: bitcoin::Amount,
-    pub rbf: Option<Rbf>,
-}
+    /// Creates a child transaction spending the deposit of `request` that
+    /// pays `fee_rate` for both itself and the deposit transaction. Since we
+    /// can not tell the fee the deposit transaction pays without its spent
+    /// outputs, the child pays as if it paid none. The fee is deducted from
+    /// the deposit.
+    fn create_deposit_cpfp_child(
+        &self,
+        request: &DepositCpfpRequest,
+        deposit: bitcoin::Amount,
+        fee_rate: Feerate,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        self.offline_wallet().create_cpfp_tx(
+            (
+                UTXOKey(request.outpoint()),
+                SpendableUTXO {
+                    tweak: request.tweak_contract_key.serialize(),
+                    amount: deposit,
+                },
+            ),
+            bitcoin::Amount::ZERO,
+            request.transaction.weight().to_wu(),
+            fee_rate,
+            deposit,
+            change_tweak,
+        )
+    }
 
-impl Serialize for UnsignedTransaction {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        if serializer.is_human_readable() {
-            serializer.serialize_str(&self.consensus_encode_to_hex())
-        } else {
-            serializer.serialize_bytes(&self.consensus_encode_to_vec())
-        }
+    /// Stores a depositor's request to accelerate its deposit, which we
+    /// propose until the federation processed it. We only accept requests for
+    /// deposits in our node's mempool, so a threshold of votes proves that the
+    /// deposit transaction exists.
+    async fn submit_deposit_cpfp_request(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        request: DepositCpfpRequest,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            DEPOSIT_CPFP_MODULE_CONSENSUS_VERSION
+                <= self.consensus_module_consensus_version(dbtx).await,
+            "Accelerating deposits is not active yet"
+        );
+
+        let deposit = self.validate_deposit_cpfp_request(dbtx, &request).await?;
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        self.create_deposit_cpfp_child(&request, deposit, fee_rate, &[0; 33])
+            .map_err(|error| format_err!("Deposit can not pay for its acceleration: {error}"))?;
+
+        let txid = request.outpoint().txid;
+
+        // The deposit might have been evicted from our mempool
+        self.btc_rpc
+            .submit_transaction(request.transaction.clone())
+            .await;
+
+        ensure!(
+            self.btc_rpc.is_tx_in_mempool(&txid).await?,
+            "Deposit transaction is not in our mempool"
+        );
+
+        dbtx.insert_entry(&DepositCpfpRequestKey(request.outpoint()), &request)
+            .await;
+
+        Ok(())
+    }
+
+    /// Creates the CPFP child for an unconfirmed deposit after a threshold of
+    /// peers voted to accelerate it
+    async fn accelerate_deposit(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        request: DepositCpfpRequest,
+        deposit: bitcoin::Amount,
+    ) {
+        let outpoint = request.outpoint();
+
+        // Whether we succeed or not, the depositor has to submit the request again to
+        // retry with the then current fee rate
+        dbtx.remove_by_prefix(&DepositCpfpVoteOutpointPrefix(outpoint))
+            .await;
+        dbtx.remove_entry(&DepositCpfpRequestKey(outpoint)).await;
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let tx = match self.create_deposit_cpfp_child(&request, deposit, fee_rate, &change_tweak) {
+            Ok(tx) => tx,
+            Err(error) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    %outpoint,
+                    %error,
+                    "Failed to create deposit CPFP tx"
+                );
+                return;
+            }
+        };
+
+        let fee = deposit - tx.change;
+        let change = tx.change;
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %outpoint,
+            %txid,
+            fee_sats = fee.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            "Accelerating deposit via CPFP"
+        );
+
+        dbtx.insert_new_entry(
+            &DepositCpfpKey(outpoint),
+            &DepositCpfp {
+                parent: request.transaction,
+                txid,
+                fee,
+                change,
+            },
+        )
+        .await;
+
+        self.event_logger
+            .log_event(
+                dbtx,
+                CpfpCreated {
+                    parent_txid: outpoint.txid,
+                    txid,
+                    fee: fee.into(),
+                },
+            )
+            .await;
+    }
+
+    /// The fee deducted from an accelerated deposit once it is claimed
+    pub async fn deposit_cpfp_fee(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        outpoint: bitcoin::OutPoint,
+    ) -> Option<bitcoin::Amount> {
+        dbtx.get_value(&DepositCpfpKey(outpoint))
+            .await
+            .map(|deposit_cpfp| deposit_cpfp.fee)
+    }
+
+    async fn available_utxos(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Vec<(UTXOKey, SpendableUTXO)> {
+        dbtx.find_by_prefix(&UTXOPrefixKey)
+            .await
+            .collect::<Vec<(UTXOKey, SpendableUTXO)>>()
+            .await
+    }
+
+    pub async fn get_wallet_value(&self, dbtx: &mut DatabaseTransaction<'_>) -> bitcoin::Amount {
+        let sat_sum = self
+            .available_utxos(dbtx)
+            .await
+            .into_iter()
+            .map(|(_, utxo)| utxo.amount.to_sat())
+            .sum();
+        bitcoin::Amount::from_sat(sat_sum)
+    }
+
+    async fn get_wallet_summary(&self, dbtx: &mut DatabaseTransaction<'_>) -> WalletSummary {
+        fn partition_peg_out_and_change(
+            transactions: Vec<Transaction>,
+            cpfp: &BTreeMap<Txid, CpfpChild>,
+        ) -> (Vec<TxOutputSummary>, Vec<TxOutputSummary>) {
+            let mut peg_out_txos: Vec<TxOutputSummary> = Vec::new();
+            let mut change_utxos: Vec<TxOutputSummary> = Vec::new();
+
+            for tx in transactions {
+                let txid = tx.compute_txid();
+
+                // to identify outputs for the peg-outs (all but the last) and change (the
+                // last), we lean on how the wallet constructs the transaction
+                let (change_output, peg_out_outputs) = tx
+                    .output
+                    .split_last()
+                    .expect("tx must contain change output");
+
+                // CPFP children only pay the federation back
+                assert!(
+                    !peg_out_outputs.is_empty() || cpfp.values().any(|child| child.txid == txid),
+                    "tx must contain withdrawal output"
+                );
+
+                for (vout, peg_out_output) in peg_out_outputs.iter().enumerate() {
+                    peg_out_txos.push(TxOutputSummary {
+                        outpoint: bitcoin::OutPoint {
+                            txid,
+                            vout: vout as u32,
+                        },
+                        amount: peg_out_output.value,
+                    });
+                }
+
+                // The change was spent by a CPFP child, which brings its own change
+                if cpfp.contains_key(&txid) {
+                    continue;
+                }
+
+                change_utxos.push(TxOutputSummary {
+                    outpoint: bitcoin::OutPoint {
+                        txid,
+                        vout: peg_out_outputs.len() as u32,
+                    },
+                    amount: change_output.value,
+                });
+            }
+
+            (peg_out_txos, change_utxos)
+        }
+
+        let spendable_utxos = self
+            .available_utxos(dbtx)
+            .await
+            .iter()
+            .map(|(utxo_key, spendable_utxo)| TxOutputSummary {
+                outpoint: utxo_key.0,
+                amount: spendable_utxo.amount,
+            })
+            .collect::<Vec<_>>();
+
+        // constructed peg-outs without threshold signatures
+        let unsigned_transactions = dbtx
+            .find_by_prefix(&UnsignedTransactionPrefixKey)
+            .await
+            .map(|(_tx_key, tx)| tx.psbt.unsigned_tx)
+            .collect::<Vec<_>>()
+            .await;
+
+        // peg-outs with threshold signatures, awaiting finality delay confirmations
+        let unconfirmed_transactions = dbtx
+            .find_by_prefix(&PendingTransactionPrefixKey)
+            .await
+            .map(|(_tx_key, tx)| tx.tx)
+            .collect::<Vec<_>>()
+            .await;
+
+        let cpfp = dbtx
+            .find_by_prefix(&CpfpChildPrefix)
+            .await
+            .map(|(key, child)| (key.0, child))
+            .collect::<BTreeMap<Txid, CpfpChild>>()
+            .await;
+
+        let mut cpfp_txs = vec![];
+
+        for (parent_txid, child) in &cpfp {
+            let (change, signed) = match dbtx.get_value(&PendingTransactionKey(child.txid)).await {
+                Some(tx) => (Some(tx.change), true),
+                None => (
+                    dbtx.get_value(&UnsignedTransactionKey(child.txid))
+                        .await
+                        .map(|tx| tx.change),
+                    false,
+                ),
+            };
+
+            if let Some(change) = change {
+                cpfp_txs.push(CpfpSummary {
+                    parent_txid: *parent_txid,
+                    txid: child.txid,
+                    fee: child.spent_change - change,
+                    signed,
+                });
+            }
+        }
+
+        let (unsigned_peg_out_txos, unsigned_change_utxos) =
+            partition_peg_out_and_change(unsigned_transactions, &cpfp);
+
+        let (unconfirmed_peg_out_txos, unconfirmed_change_utxos) =
+            partition_peg_out_and_change(unconfirmed_transactions, &cpfp);
+
+        WalletSummary {
+            spendable_utxos,
+            unsigned_peg_out_txos,
+            unsigned_change_utxos,
+            unconfirmed_peg_out_txos,
+            unconfirmed_change_utxos,
+            cpfp_txs,
+        }
+    }
+
+    async fn is_utxo_confirmed(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        outpoint: bitcoin::OutPoint,
+    ) -> bool {
+        dbtx.get_value(&UnspentTxOutKey(outpoint)).await.is_some()
+    }
+
+    /// Signs a proof of reserves over all our spendable UTXOs. Guardians
+    /// signing the same commitment with the same UTXO set produce proofs that
+    /// can be combined.
+    async fn sign_proof_of_reserves(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        commitment: ReservesCommitment,
+    ) -> anyhow::Result<ProofOfReserves> {
+        ensure!(
+            !self.cfg.consensus.is_taproot(),
+            "Proofs of reserves of taproot wallets are not supported"
+        );
+
+        let session_count = *self.session_count.borrow();
+
+        ensure!(
+            commitment.session_count <= session_count
+                && session_count - commitment.session_count <= PROOF_OF_RESERVES_MAX_AGE_SESSIONS,
+            "The commitment was taken after session {} but we are at session {session_count}",
+            commitment.session_count
+        );
+
+        let liabilities = self
+            .audit
+            .borrow()
+            .as_ref()
+            .map(AuditSummary::liabilities)
+            .context("The federation has not been audited yet")?;
+
+        ensure!(
+            commitment.liabilities.msats >= liabilities,
+            "The commitment claims liabilities of {} msat but our audit shows {liabilities} msat",
+            commitment.liabilities.msats
+        );
+
+        let utxos = self.available_utxos(dbtx).await;
+
+        ensure!(!utxos.is_empty(), "There are no UTXOs to prove");
+
+        Ok(self
+            .offline_wallet()
+            .create_proof_of_reserves(commitment, utxos))
+    }
+
+    fn offline_wallet(&self) -> StatelessWallet {
+        StatelessWallet {
+            descriptor: &self.cfg.consensus.peg_in_descriptor,
+            secret_key: &self.cfg.private.peg_in_key,
+            secp: &self.secp,
+        }
+    }
+
+    fn spawn_broadcast_pending_task(
+        task_group: &TaskGroup,
+        server_bitcoin_rpc_monitor: &ServerBitcoinRpcMonitor,
+        db: &Database,
+        broadcast_pending_notify: Arc<Notify>,
+    ) {
+        task_group.spawn_cancellable("broadcast pending", {
+            let btc_rpc = server_bitcoin_rpc_monitor.clone();
+            let db = db.clone();
+            run_broadcast_pending_tx(db, btc_rpc, broadcast_pending_notify)
+        });
+    }
+
+    /// Get the bitcoin network for UI display
+    pub fn network_ui(&self) -> Network {
+        self.cfg.consensus.network.0
+    }
+
+    /// Get the current consensus block count for UI display
+    pub async fn consensus_block_count_ui(&self) -> u32 {
+        self.consensus_block_count(&mut self.db.begin_transaction_nc().await)
+            .await
+    }
+
+    /// Get the current consensus fee rate for UI display
+    pub async fn consensus_feerate_ui(&self) -> Feerate {
+        self.consensus_fee_rate(&mut self.db.begin_transaction_nc().await)
+            .await
+    }
+
+    /// Get the fee rate UTXOs are consolidated at right now for UI display,
+    /// `None` if there is no consolidation
+    pub async fn consensus_consolidation_feerate_ui(&self) -> Option<Feerate> {
+        self.consensus_consolidation_fee_rate(&mut self.db.begin_transaction_nc().await)
+            .await
+    }
+
+    /// Get the current wallet summary for UI display
+    pub async fn get_wallet_summary_ui(&self) -> WalletSummary {
+        self.get_wallet_summary(&mut self.db.begin_transaction_nc().await)
+            .await
+    }
+
+    /// Shutdown the task group shared throughout fedimintd, giving 60 seconds
+    /// for other services to gracefully shutdown.
+    async fn graceful_shutdown(&self) {
+        if let Err(e) = self
+            .task_group
+            .clone()
+            .shutdown_join_all(Some(Duration::from_secs(60)))
+            .await
+        {
+            panic!("Error while shutting down fedimintd task group: {e}");
+        }
+    }
+
+    /// Returns once our bitcoin backend observes finality delay confirmations
+    /// of the consensus block count. If we don't observe enough confirmations
+    /// after one hour, we gracefully shutdown fedimintd. This is necessary
+    /// since we can no longer participate in consensus if our bitcoin backend
+    /// is unable to observe the same chain tip as our peers.
+    async fn wait_for_finality_confs_or_shutdown(&self, consensus_block_count: u32) {
+        let backoff = if is_running_in_test_env() {
+            // every 100ms for 60s
+            backoff_util::custom_backoff(
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+                Some(10 * 60),
+            )
+        } else {
+            // every max 10s for 1 hour
+            backoff_util::fibonacci_max_one_hour()
+        };
+
+        let wait_for_finality_confs = || async {
+            let our_chain_tip_block_count = self.get_block_count()?;
+            let consensus_chain_tip_block_count =
+                consensus_block_count + self.cfg.consensus.finality_delay;
+
+            if consensus_chain_tip_block_count <= our_chain_tip_block_count {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("not enough confirmations"))
+            }
+        };
+
+        if retry("wait_for_finality_confs", backoff, wait_for_finality_confs)
+            .await
+            .is_err()
+        {
+            self.graceful_shutdown().await;
+        }
+    }
+
+    fn spawn_peer_supported_consensus_version_task(
+        api_client: DynModuleApi,
+        task_group: &TaskGroup,
+        our_peer_id: PeerId,
+    ) -> watch::Receiver<Option<ModuleConsensusVersion>> {
+        let (sender, receiver) = watch::channel(None);
+        task_group.spawn_cancellable("fetch-peer-consensus-versions", async move {
+            loop {
+                let request_futures = api_client.all_peers().iter().filter_map(|&peer| {
+                    if peer == our_peer_id {
+                        return None;
+                    }
+
+                    let api_client_inner = api_client.clone();
+                    Some(async move {
+                        api_client_inner
+                            .request_single_peer::<ModuleConsensusVersion>(
+                                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT.to_owned(),
+                                ApiRequestErased::default(),
+                                peer,
+                            )
+                            .await
+                            .inspect(|res| debug!(
+                                target: LOG_MODULE_WALLET,
+                                %peer,
+                                %our_peer_id,
+                                ?res,
+                                "Fetched supported module consensus version from peer"
+                            ))
+                            .inspect_err(|err| warn!(
+                                target: LOG_MODULE_WALLET,
+                                 %peer,
+                                 err=%err.fmt_compact(),
+                                "Failed to fetch consensus version from peer"
+                            ))
+                            .ok()
+                    })
+                });
+
+                let peer_consensus_versions = join_all(request_futures)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+
+                let sorted_consensus_versions = peer_consensus_versions
+                    .into_iter()
+                    .chain(std::iter::once(MODULE_CONSENSUS_VERSION))
+                    .sorted()
+                    .collect::<Vec<_>>();
+                let all_peers_supported_version =
+                    if sorted_consensus_versions.len() == api_client.all_peers().len() {
+                        let min_supported_version = *sorted_consensus_versions
+                            .first()
+                            .expect("at least one element");
+
+                        debug!(
+                            target: LOG_MODULE_WALLET,
+                            ?sorted_consensus_versions,
+                            "Fetched supported consensus versions from peers"
+                        );
+
+                        Some(min_supported_version)
+                    } else {
+                        assert!(
+                            sorted_consensus_versions.len() <= api_client.all_peers().len(),
+                            "Too many peer responses",
+                        );
+                        trace!(
+                            target: LOG_MODULE_WALLET,
+                            ?sorted_consensus_versions,
+                            "Not all peers have reported their consensus version yet"
+                        );
+                        None
+                    };
+
+                #[allow(clippy::disallowed_methods)]
+                if sender.send(all_peers_supported_version).is_err() {
+                    warn!(target: LOG_MODULE_WALLET, "Failed to send consensus version to watch channel, stopping task");
+                    break;
+                }
+
+                if is_running_in_test_env() {
+                    // Even in tests we don't want to spam the federation with requests about it
+                    sleep(Duration::from_secs(5)).await;
+                } else {
+                    sleep(Duration::from_secs(600)).await;
+                }
+            }
+        });
+        receiver
+    }
+}
+
+#[instrument(target = LOG_MODULE_WALLET, level = "debug", skip_all)]
+pub async fn run_broadcast_pending_tx(
+    db: Database,
+    rpc: ServerBitcoinRpcMonitor,
+    broadcast: Arc<Notify>,
+) {
+    loop {
+        // Unless something new happened, we broadcast once a minute
+        let _ = tokio::time::timeout(Duration::from_secs(60), broadcast.notified()).await;
+        broadcast_pending_tx(db.begin_transaction_nc().await, &rpc).await;
+    }
+}
+
+pub async fn broadcast_pending_tx(
+    mut dbtx: DatabaseTransaction<'_>,
+    rpc: &ServerBitcoinRpcMonitor,
+) {
+    let pending_tx: Vec<PendingTransaction> = dbtx
+        .find_by_prefix(&PendingTransactionPrefixKey)
+        .await
+        .map(|(_, val)| val)
+        .collect::<Vec<_>>()
+        .await;
+    let rbf_txids: BTreeSet<Txid> = pending_tx
+        .iter()
+        .filter_map(|tx| tx.rbf.clone().map(|rbf| rbf.txid))
+        .collect();
+    // The deposits accelerated by pending CPFP children were not submitted by us,
+    // so we make sure they reach the mempool along with their child
+    let deposit_parents: BTreeMap<Txid, bitcoin::Transaction> = dbtx
+        .find_by_prefix(&DepositCpfpPrefix)
+        .await
+        .map(|(_, deposit_cpfp)| (deposit_cpfp.txid, deposit_cpfp.parent))
+        .collect()
+        .await;
+    if !pending_tx.is_empty() {
+        debug!(
+            target: LOG_MODULE_WALLET,
+            "Broadcasting pending transactions (total={}, rbf={})",
+            pending_tx.len(),
+            rbf_txids.len()
+        );
+    }
+
+    for PendingTransaction { tx, .. } in pending_tx {
+        if !rbf_txids.contains(&tx.compute_txid()) {
+            debug!(
+                target: LOG_MODULE_WALLET,
+                tx = %tx.compute_txid(),
+                weight = tx.weight().to_wu(),
+                output = ?tx.output,
+                "Broadcasting peg-out",
+            );
+            trace!(transaction = ?tx);
+            if let Some(parent) = deposit_parents.get(&tx.compute_txid()) {
+                rpc.submit_transaction(parent.clone()).await;
+            }
+            rpc.submit_transaction(tx).await;
+        }
+    }
+}
+
+struct StatelessWallet<'a> {
+    descriptor: &'a Descriptor<CompressedPublicKey>,
+    secret_key: &'a secp256k1::SecretKey,
+    secp: &'a secp256k1::Secp256k1<secp256k1::All>,
+}
+
+impl<'a> StatelessWallet<'a> {
+    /// Given a tx created from an `WalletOutput`, validate there will be no
+    /// issues submitting the transaction to the Bitcoin network
+    fn validate_tx(
+        tx: &UnsignedTransaction,
+        output: &WalletOutputV0,
+        consensus_fee_rate: Feerate,
+        network: Network,
+    ) -> Result<(), WalletOutputError> {
+        if let WalletOutputV0::PegOut(peg_out) = output {
+            if !peg_out.recipient.is_valid_for_network(network) {
+                return Err(WalletOutputError::WrongNetwork(
+                    NetworkLegacyEncodingWrapper(network),
+                    NetworkLegacyEncodingWrapper(get_network_for_address(&peg_out.recipient)),
+                ));
+            }
+        }
+
+        let fees = match output {
+            WalletOutputV0::PegOut(pegout) => pegout.fees,
+            WalletOutputV0::Rbf(rbf) => rbf.fees,
+        };
+
+        Self::validate_tx_fees(tx, fees, consensus_fee_rate)
+    }
+
+    /// Validates a created tx against the `fees` requested by the user
+    fn validate_tx_fees(
+        tx: &UnsignedTransaction,
+        fees: PegOutFees,
+        consensus_fee_rate: Feerate,
+    ) -> Result<(), WalletOutputError> {
+        // Validate the tx amount is over the dust limit
+        if tx.peg_out_amount < tx.destination.minimal_non_dust() {
+            return Err(WalletOutputError::PegOutUnderDustLimit);
+        }
+
+        // Validate tx fee rate is above the consensus fee rate
+        if tx.fees.fee_rate < consensus_fee_rate {
+            return Err(WalletOutputError::PegOutFeeBelowConsensus(
+                tx.fees.fee_rate,
+                consensus_fee_rate,
+            ));
+        }
+
+        // Validate added fees are above the min relay tx fee
+        // BIP-0125 requires 1 sat/vb for RBF by default (same as normal txs)
+        if fees.fee_rate.sats_per_kvb < u64::from(DEFAULT_MIN_RELAY_TX_FEE) {
+            return Err(WalletOutputError::BelowMinRelayFee);
+        }
+
+        // Validate fees weight matches the actual weight
+        if fees.total_weight != tx.fees.total_weight {
+            return Err(WalletOutputError::TxWeightIncorrect(
+                fees.total_weight,
+                tx.fees.total_weight,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Attempts to create a tx ready to be signed from available UTXOs.
+    //
+    // * `peg_out_amount`: How much the peg-out should be
+    // * `destination`: The address the user is pegging-out to
+    // * `included_utxos`: UXTOs that must be included (for RBF)
+    // * `remaining_utxos`: All other spendable UXTOs
+    // * `fee_rate`: How much needs to be spent on fees
+    // * `change_tweak`: How the federation can recognize it's change UTXO
+    // * `rbf`: If this is an RBF transaction
+    #[allow(clippy::too_many_arguments)]
+    fn create_tx(
+        &self,
+        peg_out_amount: bitcoin::Amount,
+        destination: ScriptBuf,
+        mut included_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        mut fee_rate: Feerate,
+        change_tweak: &[u8; 33],
+        rbf: Option<Rbf>,
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        // Add the rbf fees to the existing tx fees
+        if let Some(rbf) = &rbf {
+            fee_rate.sats_per_kvb += rbf.fees.fee_rate.sats_per_kvb;
+        }
+
+        // When building a transaction we need to take care of two things:
+        //  * We need enough input amount to fund all outputs
+        //  * We need to keep an eye on the tx weight so we can factor the fees into out
+        //    calculation
+        // We then go on to calculate the base size of the transaction `total_weight`
+        // and the maximum weight per added input which we will add every time
+        // we select an input.
+        let change_script = self.derive_script(change_tweak);
+        let out_weight = (destination.len() * 4 + 1 + 32
+            // Add change script weight, it's very likely to be needed if not we just overpay in fees
+            + 1 // script len varint, 1 byte for all addresses we accept
+            + change_script.len() * 4 // script len
+            + 32) as u64; // value
+        let mut total_weight = 16 + // version
+            12 + // up to 2**16-1 inputs
+            12 + // up to 2**16-1 outputs
+            out_weight + // weight of all outputs
+            16; // lock time
+        let max_input_weight = self.max_input_weight();
+
+        // Ensure deterministic ordering of UTXOs for all peers
+        included_utxos.sort_by_key(|(_, utxo)| utxo.amount);
+        remaining_utxos.sort_by_key(|(_, utxo)| utxo.amount);
+        included_utxos.extend(remaining_utxos);
+
+        // Finally we initialize our accumulator for selected input amounts
+        let mut total_selected_value = bitcoin::Amount::from_sat(0);
+        let mut selected_utxos: Vec<(UTXOKey, SpendableUTXO)> = vec![];
+        let mut fees = fee_rate.calculate_fee(total_weight);
+
+        while total_selected_value < peg_out_amount + change_script.minimal_non_dust() + fees {
+            match included_utxos.pop() {
+                Some((utxo_key, utxo)) => {
+                    total_selected_value += utxo.amount;
+                    total_weight += max_input_weight;
+                    fees = fee_rate.calculate_fee(total_weight);
+                    selected_utxos.push((utxo_key, utxo));
+                }
+                _ => return Err(WalletOutputError::NotEnoughSpendableUTXO), // Not enough UTXOs
+            }
+        }
+
+        // We always pay ourselves change back to ensure that we don't lose anything due
+        // to dust
+        let change = total_selected_value - fees - peg_out_amount;
+        let output: Vec<TxOut> = vec![
+            TxOut {
+                value: peg_out_amount,
+                script_pubkey: destination.clone(),
+            },
+            TxOut {
+                value: change,
+                script_pubkey: change_script,
+            },
+        ];
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            inputs = selected_utxos.len(),
+            input_sats = total_selected_value.to_sat(),
+            peg_out_sats = peg_out_amount.to_sat(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating peg-out tx",
+        );
+
+        let psbt = self.create_psbt(&selected_utxos, output, change_tweak);
+
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination,
+            selected_utxos,
+            peg_out_amount,
+            rbf,
+        })
+    }
+
+    /// Attempts to create a tx paying out a batch of peg-outs from available
+    /// UTXOs, spending the prepaid fees or, if they fall short, enough to pay
+    /// `fee_rate` for the entire transaction.
+    //
+    // * `payouts`: The destinations and amounts of the batched peg-outs, in output order
+    // * `prepaid_fees`: The sum of the fee shares paid by the batched peg-outs
+    // * `fee_rate`: The minimum fee rate the transaction has to pay
+    // * `max_fees`: The prepaid fees plus the budget available to make up for a shortfall
+    // * `remaining_utxos`: All spendable UXTOs
+    // * `change_tweak`: How the federation can recognize it's change UTXO
+    fn create_batch_tx(
+        &self,
+        payouts: Vec<(ScriptBuf, bitcoin::Amount)>,
+        prepaid_fees: bitcoin::Amount,
+        fee_rate: Feerate,
+        max_fees: bitcoin::Amount,
+        mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let change_script = self.derive_script(change_tweak);
+        let payout_amount = payouts
+            .iter()
+            .fold(bitcoin::Amount::ZERO, |acc, (_, amount)| acc + *amount);
+        let out_weight = payouts
+            .iter()
+            .map(|(destination, _)| (destination.len() * 4 + 1 + 32) as u64)
+            .sum::<u64>()
+            + (1 + change_script.len() * 4 + 32) as u64;
+        let mut total_weight = 16 + // version
+            12 + // up to 2**16-1 inputs
+            12 + // up to 2**16-1 outputs
+            out_weight + // weight of all outputs
+            16; // lock time
+
+        // Ensure deterministic ordering of UTXOs for all peers
+        remaining_utxos.sort_by_key(|(_, utxo)| utxo.amount);
+
+        let mut total_selected_value = bitcoin::Amount::from_sat(0);
+        let mut selected_utxos: Vec<(UTXOKey, SpendableUTXO)> = vec![];
+        let mut fees = prepaid_fees.max(fee_rate.calculate_fee(total_weight));
+
+        while total_selected_value < payout_amount + change_script.minimal_non_dust() + fees {
+            match remaining_utxos.pop() {
+                Some((utxo_key, utxo)) => {
+                    total_selected_value += utxo.amount;
+                    total_weight += self.max_input_weight();
+                    fees = prepaid_fees.max(fee_rate.calculate_fee(total_weight));
+                    selected_utxos.push((utxo_key, utxo));
+                }
+                _ => return Err(WalletOutputError::NotEnoughSpendableUTXO),
+            }
+        }
+
+        if max_fees < fees {
+            return Err(WalletOutputError::FeeBudgetExceeded);
+        }
+
+        let change = total_selected_value - fees - payout_amount;
+        let fee_rate = Feerate {
+            sats_per_kvb: fees.to_sat() * 1000 / weight_to_vbytes(total_weight),
+        };
+
+        let destination = payouts
+            .first()
+            .map(|(destination, _)| destination.clone())
+            .expect("A batch contains at least one peg-out");
+
+        let output = payouts
+            .into_iter()
+            .map(|(script_pubkey, value)| TxOut {
+                value,
+                script_pubkey,
+            })
+            .chain(std::iter::once(TxOut {
+                value: change,
+                script_pubkey: change_script,
+            }))
+            .collect::<Vec<TxOut>>();
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            inputs = selected_utxos.len(),
+            input_sats = total_selected_value.to_sat(),
+            peg_outs = output.len() - 1,
+            peg_out_sats = payout_amount.to_sat(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating batched peg-out tx",
+        );
+
+        let psbt = self.create_psbt(&selected_utxos, output, change_tweak);
+
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination,
+            selected_utxos,
+            peg_out_amount: payout_amount,
+            rbf: None,
+        })
+    }
+
+    /// Attempts to create a child-pays-for-parent tx spending the change of a
+    /// stuck transaction back to the federation, paying enough fees to bring
+    /// the unconfirmed package up to `fee_rate`.
+    //
+    // * `parent_change`: The change output of the stuck transaction
+    // * `package_fee`: The fees paid by the stuck transaction and its unconfirmed ancestors
+    // * `package_weight`: The weight of the stuck transaction and its unconfirmed ancestors
+    // * `fee_rate`: The fee rate the package should pay including the child
+    // * `change_tweak`: How the federation can recognize it's change UTXO
+    fn create_cpfp_tx(
+        &self,
+        parent_change: (UTXOKey, SpendableUTXO),
+        package_fee: bitcoin::Amount,
+        package_weight: u64,
+        fee_rate: Feerate,
+        max_fee: bitcoin::Amount,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let change_script = self.derive_script(change_tweak);
+        let total_weight = 16 + // version
+            12 + // up to 2**16-1 inputs
+            12 + // up to 2**16-1 outputs
+            (1 + change_script.len() * 4 + 32) as u64 + // change output
+            16 + // lock time
+            self.max_input_weight();
+
+        // The child pays for itself and for whatever its ancestors fall short of the
+        // target fee rate
+        let fees = fee_rate
+            .calculate_fee(package_weight + total_weight)
+            .checked_sub(package_fee)
+            .unwrap_or(bitcoin::Amount::ZERO)
+            .max(fee_rate.calculate_fee(total_weight));
+
+        if max_fee < fees {
+            return Err(WalletOutputError::FeeBudgetExceeded);
+        }
+
+        let input_amount = parent_change.1.amount;
+
+        if input_amount < fees + change_script.minimal_non_dust() {
+            return Err(WalletOutputError::NotEnoughSpendableUTXO);
+        }
+
+        let change = input_amount - fees;
+        let output = vec![TxOut {
+            value: change,
+            script_pubkey: change_script.clone(),
+        }];
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            parent_txid = %parent_change.0.0.txid,
+            input_sats = input_amount.to_sat(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating CPFP tx",
+        );
+
+        let selected_utxos = vec![parent_change];
+        let psbt = self.create_psbt(&selected_utxos, output, change_tweak);
+
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate: Feerate {
+                    sats_per_kvb: fees.to_sat() * 1000 / weight_to_vbytes(total_weight),
+                },
+                total_weight,
+            },
+            // There is no peg-out, the child only pays the federation back
+            destination: change_script,
+            selected_utxos,
+            peg_out_amount: bitcoin::Amount::ZERO,
+            rbf: None,
+        })
+    }
+
+    /// Creates a transaction spending `selected_utxos` to a single change
+    /// output derived from `change_tweak` that pays at most `max_fee`
+    fn create_self_spend_tx(
+        &self,
+        selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        fee_rate: Feerate,
+        max_fee: bitcoin::Amount,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let change_script = self.derive_script(change_tweak);
+        let total_weight = 16 + // version
+            12 + // up to 2**16-1 inputs
+            12 + // up to 2**16-1 outputs
+            (1 + change_script.len() * 4 + 32) as u64 + // change output
+            16 + // lock time
+            self.max_input_weight() * selected_utxos.len() as u64;
+
+        let fees = fee_rate.calculate_fee(total_weight);
+
+        if max_fee < fees {
+            return Err(WalletOutputError::FeeBudgetExceeded);
+        }
+
+        let input_amount = selected_utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>();
+
+        if input_amount < fees + change_script.minimal_non_dust() {
+            return Err(WalletOutputError::NotEnoughSpendableUTXO);
+        }
+
+        let change = input_amount - fees;
+        let output = vec![TxOut {
+            value: change,
+            script_pubkey: change_script.clone(),
+        }];
+
+        let psbt = self.create_psbt(&selected_utxos, output, change_tweak);
+
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination: change_script,
+            selected_utxos,
+            peg_out_amount: bitcoin::Amount::ZERO,
+            rbf: None,
+        })
+    }
+
+    /// Creates the PSBT spending `selected_utxos` to `output`, the last output
+    /// being the change output derived from `change_tweak`.
+    fn create_psbt(
+        &self,
+        selected_utxos: &[(UTXOKey, SpendableUTXO)],
+        output: Vec<TxOut>,
+        change_tweak: &[u8; 33],
+    ) -> Psbt {
+        let transaction = Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: LockTime::ZERO,
+            input: selected_utxos
+                .iter()
+                .map(|(utxo_key, _utxo)| TxIn {
+                    previous_output: utxo_key.0,
+                    script_sig: Default::default(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: bitcoin::Witness::new(),
+                })
+                .collect(),
+            output,
+        };
+        info!(
+            target: LOG_MODULE_WALLET,
+            txid = %transaction.compute_txid(), "Creating peg-out tx"
+        );
+
+        // Every output except for the trailing change output pays out a peg-out
+        let mut outputs = vec![bitcoin::psbt::Output::default(); transaction.output.len() - 1];
+        let mut change_out = bitcoin::psbt::Output::default();
+        change_out
+            .proprietary
+            .insert(proprietary_tweak_key(), change_tweak.to_vec());
+        outputs.push(change_out);
+
+        // FIXME: use custom data structure that guarantees more invariants and only
+        // convert to PSBT for finalization
+        Psbt {
+            unsigned_tx: transaction,
+            version: 0,
+            xpub: Default::default(),
+            proprietary: Default::default(),
+            unknown: Default::default(),
+            inputs: selected_utxos
+                .iter()
+                .map(|(_utxo_key, utxo)| self.psbt_input(utxo))
+                .collect(),
+            outputs,
+        }
+    }
+
+    /// Creates the PSBT input spending `utxo`
+    fn psbt_input(&self, utxo: &SpendableUTXO) -> Input {
+        let descriptor = self.descriptor.tweak(&utxo.tweak, self.secp);
+        let script_pubkey = descriptor.script_pubkey();
+        Input {
+            non_witness_utxo: None,
+            witness_utxo: Some(TxOut {
+                value: utxo.amount,
+                script_pubkey,
+            }),
+            partial_sigs: Default::default(),
+            sighash_type: None,
+            redeem_script: None,
+            // Taproot key spends do not commit to a script
+            witness_script: match &descriptor {
+                Descriptor::Tr(_) => None,
+                descriptor => Some(
+                    descriptor
+                        .script_code()
+                        .expect("Failed to tweak descriptor"),
+                ),
+            },
+            bip32_derivation: Default::default(),
+            final_script_sig: None,
+            final_script_witness: None,
+            ripemd160_preimages: Default::default(),
+            sha256_preimages: Default::default(),
+            hash160_preimages: Default::default(),
+            hash256_preimages: Default::default(),
+            proprietary: vec![(proprietary_tweak_key(), utxo.tweak.to_vec())]
+                .into_iter()
+                .collect(),
+            tap_key_sig: Default::default(),
+            tap_script_sigs: Default::default(),
+            tap_scripts: Default::default(),
+            tap_key_origins: Default::default(),
+            tap_internal_key: match &descriptor {
+                Descriptor::Tr(tr) => Some(tr.internal_key().key.x_only_public_key().0),
+                _ => None,
+            },
+            tap_merkle_root: Default::default(),
+            unknown: Default::default(),
+        }
+    }
+
+    /// Creates a BIP-127 proof of reserves over `utxos` for `commitment`,
+    /// signed with our key only
+    fn create_proof_of_reserves(
+        &self,
+        commitment: ReservesCommitment,
+        mut utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    ) -> ProofOfReserves {
+        // Ensure deterministic ordering of UTXOs for all peers
+        utxos.sort_by_key(|(utxo_key, _)| utxo_key.0);
+
+        let reserves = utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>();
+
+        let transaction = Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: LockTime::ZERO,
+            input: std::iter::once(commitment.challenge_outpoint())
+                .chain(utxos.iter().map(|(utxo_key, _)| utxo_key.0))
+                .map(|previous_output| TxIn {
+                    previous_output,
+                    script_sig: Default::default(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: bitcoin::Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: reserves,
+                script_pubkey: proof_of_reserves_output_script(),
+            }],
+        };
+
+        let mut psbt = Psbt::from_unsigned_tx(transaction).expect("Transaction is unsigned");
+
+        psbt.inputs[0].witness_utxo = Some(challenge_utxo());
+
+        for (psbt_input, (_, utxo)) in psbt.inputs.iter_mut().skip(1).zip(&utxos) {
+            *psbt_input = self.psbt_input(utxo);
+        }
+
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+        for (idx, psbt_input) in psbt.inputs.iter_mut().enumerate().skip(1) {
+            self.sign_psbt_input(&mut tx_hasher, idx, psbt_input);
+        }
+
+        ProofOfReserves { commitment, psbt }
+    }
+
+    /// Weight a batched peg-out to `destination` pays fees for: its own output
+    /// plus one input. Since a batch rarely needs one input per peg-out the
+    /// shares usually also cover the base transaction and the change output,
+    /// any shortfall is paid from the fee budget when the batch is closed.
+    fn batched_peg_out_weight(&self, destination: &ScriptBuf) -> u64 {
+        (destination.len() * 4 + 1 + 32) as u64 + self.max_input_weight()
+    }
+
+    fn max_input_weight(&self) -> u64 {
+        // https://github.com/fedimint/fedimint/issues/4590
+        #[allow(deprecated)]
+        let max_input_weight = (self
+            .descriptor
+            .max_satisfaction_weight()
+            .expect("is satisfyable") +
+            128 + // TxOutHash
+            16 + // TxOutIndex
+            16) as u64; // sequence
+
+        max_input_weight
+    }
+
+    fn sign_psbt(&self, psbt: &mut Psbt) {
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+        for (idx, (psbt_input, _tx_input)) in psbt
+            .inputs
+            .iter_mut()
+            .zip(psbt.unsigned_tx.input.iter())
+            .enumerate()
+        {
+            self.sign_psbt_input(&mut tx_hasher, idx, psbt_input);
+        }
+    }
+
+    fn sign_psbt_input(
+        &self,
+        tx_hasher: &mut SighashCache<&Transaction>,
+        idx: usize,
+        psbt_input: &mut Input,
+    ) {
+        let tweaked_secret = {
+            let tweak = psbt_input
+                .proprietary
+                .get(&proprietary_tweak_key())
+                .expect("Malformed PSBT: expected tweak");
+
+            self.secret_key.tweak(tweak, self.secp)
+        };
+
+        let tx_hash = tx_hasher
+            .p2wsh_signature_hash(
+                idx,
+                psbt_input
+                    .witness_script
+                    .as_ref()
+                    .expect("Missing witness script"),
+                psbt_input
+                    .witness_utxo
+                    .as_ref()
+                    .expect("Missing UTXO")
+                    .value,
+                EcdsaSighashType::All,
+            )
+            .expect("Failed to create segwit sighash");
+
+        let signature = self.secp.sign_ecdsa(
+            &Message::from_digest_slice(&tx_hash[..]).unwrap(),
+            &tweaked_secret,
+        );
+
+        psbt_input.partial_sigs.insert(
+            bitcoin::PublicKey {
+                compressed: true,
+                inner: secp256k1::PublicKey::from_secret_key(self.secp, &tweaked_secret),
+            },
+            EcdsaSig::sighash_all(signature),
+        );
+    }
+
+    fn derive_script(&self, tweak: &[u8]) -> ScriptBuf {
+        struct CompressedPublicKeyTranslator<'t, 's, Ctx: Verification> {
+            tweak: &'t [u8],
+            secp: &'s Secp256k1<Ctx>,
+        }
+
+        impl<'t, 's, Ctx: Verification>
+            miniscript::Translator<CompressedPublicKey, CompressedPublicKey, Infallible>
+            for CompressedPublicKeyTranslator<'t, 's, Ctx>
+        {
+            fn pk(&mut self, pk: &CompressedPublicKey) -> Result<CompressedPublicKey, Infallible> {
+                let hashed_tweak = {
+                    let mut hasher = HmacEngine::<sha256::Hash>::new(&pk.key.serialize()[..]);
+                    hasher.input(self.tweak);
+                    Hmac::from_engine(hasher).to_byte_array()
+                };
+
+                Ok(CompressedPublicKey {
+                    key: pk
+                        .key
+                        .add_exp_tweak(
+                            self.secp,
+                            &Scalar::from_be_bytes(hashed_tweak).expect("can't fail"),
+                        )
+                        .expect("tweaking failed"),
+                })
+            }
+            translate_hash_fail!(CompressedPublicKey, CompressedPublicKey, Infallible);
+        }
+
+        let descriptor = self
+            .descriptor
+            .translate_pk(&mut CompressedPublicKeyTranslator {
+                tweak,
+                secp: self.secp,
+            })
+            .expect("can't fail");
+
+        descriptor.script_pubkey()
+    }
+}
+
+/// The BIP-341 key spend sighashes of all inputs of a taproot peg-out
+fn taproot_sighashes(psbt: &Psbt) -> Vec<[u8; 32]> {
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .map(|input| input.witness_utxo.clone().expect("Missing UTXO"))
+        .collect::<Vec<TxOut>>();
+
+    let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+    (0..psbt.inputs.len())
+        .map(|idx| {
+            tx_hasher
+                .taproot_key_spend_signature_hash(
+                    idx,
+                    &Prevouts::All(&prevouts),
+                    TapSighashType::Default,
+                )
+                .expect("Failed to create taproot sighash")
+                .to_byte_array()
+        })
+        .collect()
+}
+
+/// Selects the spendable UTXOs that are at least `min_age` blocks old given
+/// their confirmation height, oldest first, so the recovery path of none of
+/// them becomes spendable while the federation is operational.
+fn select_refresh_utxos(
+    mut utxos: Vec<(UTXOKey, SpendableUTXO, u32)>,
+    consensus_block_count: u32,
+    min_age: u32,
+) -> Vec<(UTXOKey, SpendableUTXO)> {
+    utxos.retain(|(_, _, height)| height.saturating_add(min_age) <= consensus_block_count);
+
+    // Ensure deterministic selection for all peers
+    utxos.sort_by_key(|(key, _, height)| (*height, key.0));
+    utxos.truncate(MAX_REFRESH_INPUTS);
+
+    utxos
+        .into_iter()
+        .map(|(key, utxo, _)| (key, utxo))
+        .collect()
+}
+
+/// Our consolidation vote, which is our fee rate estimate while it is not
+/// above `max_fee_rate` and zero otherwise. A `max_fee_rate` of zero disables
+/// consolidation.
+fn consolidation_vote(fee_rate_estimate: Option<Feerate>, max_fee_rate: Feerate) -> Feerate {
+    match fee_rate_estimate {
+        Some(estimate)
+            if max_fee_rate.sats_per_kvb != 0
+                && estimate.sats_per_kvb <= max_fee_rate.sats_per_kvb =>
+        {
+            Feerate {
+                sats_per_kvb: estimate
+                    .sats_per_kvb
+                    .max(u64::from(DEFAULT_MIN_RELAY_TX_FEE)),
+            }
+        }
+        _ => Feerate { sats_per_kvb: 0 },
+    }
+}
+
+/// The fee rate of a consolidation given the peers' consolidation votes, which
+/// is the highest fee rate estimate of which a threshold of peers votes for a
+/// consolidation. Hence, no consolidation pays more than the estimate of a peer
+/// that wants to consolidate.
+fn aggregate_consolidation_votes(mut votes: Vec<Feerate>, threshold: usize) -> Option<Feerate> {
+    votes.retain(|vote| vote.sats_per_kvb != 0);
+    votes.sort_unstable();
+
+    // The threshold-th highest vote, such that a threshold of peers is willing to pay
+    // at least this fee rate
+    votes.iter().rev().nth(threshold - 1).copied()
+}
+
+/// Selects the smallest spendable UTXOs for a consolidation. The largest UTXO
+/// is never selected, so peg-outs can still be funded while the consolidation
+/// confirms.
+fn select_consolidation_utxos(
+    mut utxos: Vec<(UTXOKey, SpendableUTXO)>,
+) -> Vec<(UTXOKey, SpendableUTXO)> {
+    // Ensure deterministic selection for all peers
+    utxos.sort_by_key(|(key, utxo)| (utxo.amount, key.0));
+
+    utxos.pop();
+    utxos.truncate(MAX_CONSOLIDATION_INPUTS);
+
+    utxos
+}
+
+pub fn nonce_from_idx(nonce_idx: u64) -> [u8; 33] {
+    let mut nonce: [u8; 33] = [0; 33];
+    // Make it look like a compressed pubkey, has to be either 0x02 or 0x03
+    nonce[0] = 0x02;
+    nonce[1..].copy_from_slice(&nonce_idx.consensus_hash::<bitcoin::hashes::sha256::Hash>()[..]);
+
+    nonce
+}
+
+/// A peg-out tx that is ready to be broadcast with a tweak for the change UTXO
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PendingTransaction {
+    pub tx: bitcoin::Transaction,
+    pub tweak: [u8; 33],
+    pub change: bitcoin::Amount,
+    pub destination: ScriptBuf,
+    pub fees: PegOutFees,
+    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    pub peg_out_amount: bitcoin::Amount,
+    pub rbf: Option<Rbf>,
+}
+
+impl PendingTransaction {
+    /// The absolute fee paid by the transaction
+    pub fn fee(&self) -> bitcoin::Amount {
+        let input_amount = self
+            .selected_utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>();
+
+        let output_amount = self
+            .tx
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<bitcoin::Amount>();
+
+        input_amount - output_amount
+    }
+}
+
+impl Serialize for PendingTransaction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.consensus_encode_to_hex())
+        } else {
+            serializer.serialize_bytes(&self.consensus_encode_to_vec())
+        }
+    }
+}
+
+/// A PSBT that is awaiting enough signatures from the federation to becoming a
+/// `PendingTransaction`
+#[derive(Clone, Debug, Eq, PartialEq, Encodable, Decodable)]
+pub struct UnsignedTransaction {
+    pub psbt: Psbt,
+    pub signatures: Vec<(PeerId, PegOutSignatureItem)>,
+    pub change: bitcoin::Amount,
+    pub fees: PegOutFees,
+    pub destination: ScriptBuf,
+    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    pub peg_out_amount: bitcoin::Amount,
+    pub rbf: Option<Rbf>,
+}
+
+impl Serialize for UnsignedTransaction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.consensus_encode_to_hex())
+        } else {
+            serializer.serialize_bytes(&self.consensus_encode_to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use bitcoin::Network::{Bitcoin, Testnet};
+    use bitcoin::hashes::Hash;
+    use bitcoin::script::Instruction;
+    use bitcoin::{Address, Amount, OutPoint, ScriptBuf, TxOut, Txid, secp256k1};
+    use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
+    use fedimint_core::envs::BitcoinRpcConfig;
+    use fedimint_core::util::SafeUrl;
+    use fedimint_core::{Feerate, PeerId};
+    use fedimint_wallet_common::config::{
+        EmergencyRecoveryParams, FeeConsensus, WalletConfig, guardian_recovery_key,
+        guardian_recovery_secret_key,
+    };
+    use fedimint_wallet_common::tweakable::Tweakable;
+    use fedimint_wallet_common::{
+        DepositCpfpRequest, PegOut, PegOutFees, Rbf, WalletOutputV0, descriptor_script_pubkey,
+        is_standard_peg_out_script,
+    };
+    use miniscript::ForEachKey;
+    use miniscript::descriptor::Wsh;
+
+    use crate::common::PegInDescriptor;
+    use crate::db::FrostSigningRound;
+    use crate::{
+        CompressedPublicKey, FROST_SIGNING_TIMEOUT_SESSIONS, OsRng, ProofOfReserves,
+        ReservesCommitment, SpendableUTXO, StatelessWallet, UTXOKey, WalletOutputError,
+        aggregate_consolidation_votes, consolidation_vote, proprietary_tweak_key,
+        select_consolidation_utxos, select_refresh_utxos,
+    };
+
+    #[test]
+    fn create_tx_should_validate_amounts() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let spendable = SpendableUTXO {
+            tweak: [0; 33],
+            amount: bitcoin::Amount::from_sat(3000),
+        };
+
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
+
+        let fee = Feerate { sats_per_kvb: 1000 };
+        let weight = 875;
+
+        // not enough SpendableUTXO
+        // tx fee = ceil(875 / 4) * 1 sat/vb = 219
+        // change script dust = 330
+        // spendable sats = 3000 - 219 - 330 = 2451
+        let tx = wallet.create_tx(
+            Amount::from_sat(2452),
+            recipient.clone().assume_checked().script_pubkey(),
+            vec![],
+            vec![(UTXOKey(OutPoint::null()), spendable.clone())],
+            fee,
+            &[0; 33],
+            None,
+        );
+        assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
+
+        // successful tx creation
+        let mut tx = wallet
+            .create_tx(
+                Amount::from_sat(1000),
+                recipient.clone().assume_checked().script_pubkey(),
+                vec![],
+                vec![(UTXOKey(OutPoint::null()), spendable)],
+                fee,
+                &[0; 33],
+                None,
+            )
+            .expect("is ok");
+
+        // peg out weight is incorrectly set to 0
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, 0), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::TxWeightIncorrect(0, weight)));
+
+        // fee rate set below min relay fee to 0
+        let res = StatelessWallet::validate_tx(&tx, &rbf(0, weight), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::BelowMinRelayFee));
+
+        // fees are okay
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(res, Ok(()));
+
+        // tx has fee below consensus
+        tx.fees = PegOutFees::new(0, weight);
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(
+            res,
+            Err(WalletOutputError::PegOutFeeBelowConsensus(
+                Feerate { sats_per_kvb: 0 },
+                fee
+            ))
+        );
+
+        // tx has peg-out amount under dust limit
+        tx.peg_out_amount = bitcoin::Amount::ZERO;
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::PegOutUnderDustLimit));
+
+        // tx is invalid for network
+        let output = WalletOutputV0::PegOut(PegOut {
+            recipient,
+            amount: bitcoin::Amount::from_sat(1000),
+            fees: PegOutFees::new(100, weight),
+        });
+        let res = StatelessWallet::validate_tx(&tx, &output, fee, Testnet);
+        assert_eq!(
+            res,
+            Err(WalletOutputError::WrongNetwork(
+                NetworkLegacyEncodingWrapper(Testnet),
+                NetworkLegacyEncodingWrapper(Bitcoin)
+            ))
+        );
+    }
+
+    #[test]
+    fn create_batch_tx_should_pay_all_peg_outs() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let utxos = (0..3)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: [0; 33],
+                        amount: bitcoin::Amount::from_sat(2000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+
+        let payouts = vec![
+            (recipient.clone(), Amount::from_sat(1000)),
+            (recipient.clone(), Amount::from_sat(2000)),
+        ];
+
+        let fee_rate = Feerate { sats_per_kvb: 1000 };
+
+        // not enough SpendableUTXO
+        let tx = wallet.create_batch_tx(
+            payouts.clone(),
+            Amount::from_sat(3000),
+            fee_rate,
+            Amount::from_sat(3000),
+            utxos.clone(),
+            &[0; 33],
+        );
+        assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
+
+        let tx = wallet
+            .create_batch_tx(
+                payouts,
+                Amount::from_sat(500),
+                fee_rate,
+                Amount::from_sat(500),
+                utxos,
+                &[0; 33],
+            )
+            .expect("is ok");
+
+        // two inputs cover both peg-outs, the fees and the change dust limit
+        assert_eq!(tx.selected_utxos.len(), 2);
+        assert_eq!(tx.peg_out_amount, Amount::from_sat(3000));
+        assert_eq!(tx.change, Amount::from_sat(500));
+
+        let outputs = &tx.psbt.unsigned_tx.output;
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[0].value, Amount::from_sat(1000));
+        assert_eq!(outputs[1].value, Amount::from_sat(2000));
+        assert_eq!(outputs[2].value, tx.change);
+        assert_eq!(outputs[2].script_pubkey, wallet.derive_script(&[0; 33]));
+        assert_eq!(tx.psbt.outputs.len(), 3);
+        assert!(
+            tx.psbt.outputs[2]
+                .proprietary
+                .contains_key(&proprietary_tweak_key())
+        );
+    }
+
+    #[test]
+    fn create_batch_tx_should_make_up_for_single_fee_share() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let utxos = vec![(
+            UTXOKey(OutPoint::null()),
+            SpendableUTXO {
+                tweak: [0; 33],
+                amount: bitcoin::Amount::from_sat(10_000),
+            },
+        )];
+
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+
+        let fee_rate = Feerate { sats_per_kvb: 1000 };
+        let share = fee_rate.calculate_fee(wallet.batched_peg_out_weight(&recipient));
+        let payouts = vec![(recipient, Amount::from_sat(1000))];
+
+        // A single share does not pay for the base transaction and the change output
+        let tx = wallet.create_batch_tx(
+            payouts.clone(),
+            share,
+            fee_rate,
+            share,
+            utxos.clone(),
+            &[0; 33],
+        );
+        assert_eq!(tx, Err(WalletOutputError::FeeBudgetExceeded));
+
+        let tx = wallet
+            .create_batch_tx(
+                payouts,
+                share,
+                fee_rate,
+                share + Amount::from_sat(1000),
+                utxos,
+                &[0; 33],
+            )
+            .expect("is ok");
+
+        let fees = Amount::from_sat(10_000) - tx.peg_out_amount - tx.change;
+        assert!(share < fees);
+        assert_eq!(fees, fee_rate.calculate_fee(tx.fees.total_weight));
+    }
+
+    #[test]
+    fn create_cpfp_tx_should_pay_for_package() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let parent_change = |sats| {
+            (
+                UTXOKey(OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout: 1,
+                }),
+                SpendableUTXO {
+                    tweak: [0; 33],
+                    amount: bitcoin::Amount::from_sat(sats),
+                },
+            )
+        };
+
+        let fee_rate = Feerate { sats_per_kvb: 4000 };
+
+        // change can not pay for the package
+        let tx = wallet.create_cpfp_tx(
+            parent_change(1000),
+            Amount::from_sat(200),
+            1000,
+            fee_rate,
+            Amount::MAX_MONEY,
+            &[0; 33],
+        );
+        assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
+
+        let tx = wallet
+            .create_cpfp_tx(
+                parent_change(10_000),
+                Amount::from_sat(200),
+                1000,
+                fee_rate,
+                Amount::MAX_MONEY,
+                &[0; 33],
+            )
+            .expect("is ok");
+
+        // the child tops up the fees of the parent to the target fee rate
+        let child_weight = tx.fees.total_weight;
+        let fees = fee_rate.calculate_fee(1000 + child_weight) - Amount::from_sat(200);
+        assert_eq!(tx.change, Amount::from_sat(10_000) - fees);
+        assert_eq!(tx.peg_out_amount, Amount::ZERO);
+        assert_eq!(tx.selected_utxos, vec![parent_change(10_000)]);
+
+        let unsigned_tx = &tx.psbt.unsigned_tx;
+        assert_eq!(unsigned_tx.input.len(), 1);
+        assert_eq!(unsigned_tx.input[0].previous_output, parent_change(0).0.0);
+        assert_eq!(unsigned_tx.output.len(), 1);
+        assert_eq!(unsigned_tx.output[0].value, tx.change);
+        assert_eq!(
+            unsigned_tx.output[0].script_pubkey,
+            wallet.derive_script(&[0; 33])
+        );
+
+        // the child always pays for its own weight
+        let tx = wallet
+            .create_cpfp_tx(
+                parent_change(10_000),
+                Amount::from_sat(5000),
+                1000,
+                fee_rate,
+                Amount::MAX_MONEY,
+                &[0; 33],
+            )
+            .expect("is ok");
+
+        assert_eq!(
+            tx.change,
+            Amount::from_sat(10_000) - fee_rate.calculate_fee(child_weight)
+        );
+
+        // the child never pays more than the fee budget allows
+        let tx = wallet.create_cpfp_tx(
+            parent_change(10_000),
+            Amount::from_sat(200),
+            1000,
+            fee_rate,
+            fees - Amount::from_sat(1),
+            &[0; 33],
+        );
+        assert_eq!(tx, Err(WalletOutputError::FeeBudgetExceeded));
+
+        let tx = wallet
+            .create_cpfp_tx(
+                parent_change(10_000),
+                Amount::from_sat(200),
+                1000,
+                fee_rate,
+                fees,
+                &[0; 33],
+            )
+            .expect("is ok");
+        assert_eq!(tx.change, Amount::from_sat(10_000) - fees);
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn deposit_cpfp_request_should_only_accept_deposits() {
+        let secp = secp256k1::Secp256k1::new();
 
-    use std::str::FromStr;
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
 
-    use bitcoin::Network::{Bitcoin, Testnet};
-    use bitcoin::hashes::Hash;
-    use bitcoin::{Address, Amount, OutPoint, Txid, secp256k1};
-    use fedimint_core::Feerate;
-    use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
-    use fedimint_wallet_common::{PegOut, PegOutFees, Rbf, WalletOutputV0};
-    use miniscript::descriptor::Wsh;
+        let (_, tweak_contract_key) = secp.generate_keypair(&mut OsRng);
 
-    use crate::common::PegInDescriptor;
-    use crate::{
-        CompressedPublicKey, OsRng, SpendableUTXO, StatelessWallet, UTXOKey, WalletOutputError,
-    };
+        let deposit = TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: descriptor.tweak(&tweak_contract_key, &secp).script_pubkey(),
+        };
+
+        let request = |output: Vec<TxOut>, vout| DepositCpfpRequest {
+            transaction: bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![],
+                output,
+            },
+            vout,
+            tweak_contract_key,
+        };
+
+        assert_eq!(
+            request(vec![deposit.clone()], 0).deposit(&descriptor, &secp),
+            Some(&deposit)
+        );
+
+        // the output does not exist
+        assert_eq!(
+            request(vec![deposit.clone()], 1).deposit(&descriptor, &secp),
+            None
+        );
+
+        // the output does not pay to the tweaked descriptor
+        let other = TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: descriptor
+                .tweak(&secp.generate_keypair(&mut OsRng).1, &secp)
+                .script_pubkey(),
+        };
+
+        assert_eq!(
+            request(vec![deposit.clone(), other], 1).deposit(&descriptor, &secp),
+            None
+        );
+
+        // the deposit transaction is too heavy to exchange in consensus
+        assert_eq!(
+            request(vec![deposit; 300], 0).deposit(&descriptor, &secp),
+            None
+        );
+    }
 
     #[test]
-    fn create_tx_should_validate_amounts() {
+    fn script_peg_out_should_validate_script_and_fees() {
         let secp = secp256k1::Secp256k1::new();
 
         let descriptor = PegInDescriptor::Wsh(
@@ -2276,36 +5723,40 @@ fn create_tx_should_validate_amounts() {
             secp: &secp,
         };
 
+        let (_, key) = secp.generate_keypair(&mut OsRng);
+        let script_pubkey = descriptor_script_pubkey(&format!("tr({})", key.x_only_public_key().0))
+            .expect("valid descriptor");
+
+        assert!(is_standard_peg_out_script(&script_pubkey));
+        assert!(!is_standard_peg_out_script(&ScriptBuf::new_op_return(
+            [0; 32]
+        )));
+        // Version zero witness programs of any other length are unspendable
+        assert!(!is_standard_peg_out_script(
+            &ScriptBuf::builder()
+                .push_opcode(bitcoin::opcodes::all::OP_PUSHBYTES_0)
+                .push_slice([0; 24])
+                .into_script()
+        ));
+        assert!(is_standard_peg_out_script(
+            &ScriptBuf::builder()
+                .push_opcode(bitcoin::opcodes::all::OP_PUSHNUM_2)
+                .push_slice([0; 24])
+                .into_script()
+        ));
+        assert!(descriptor_script_pubkey(&format!("wpkh({key})")).is_ok());
+
         let spendable = SpendableUTXO {
             tweak: [0; 33],
             amount: bitcoin::Amount::from_sat(3000),
         };
 
-        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
-
         let fee = Feerate { sats_per_kvb: 1000 };
-        let weight = 875;
-
-        // not enough SpendableUTXO
-        // tx fee = ceil(875 / 4) * 1 sat/vb = 219
-        // change script dust = 330
-        // spendable sats = 3000 - 219 - 330 = 2451
-        let tx = wallet.create_tx(
-            Amount::from_sat(2452),
-            recipient.clone().assume_checked().script_pubkey(),
-            vec![],
-            vec![(UTXOKey(OutPoint::null()), spendable.clone())],
-            fee,
-            &[0; 33],
-            None,
-        );
-        assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
 
-        // successful tx creation
-        let mut tx = wallet
+        let tx = wallet
             .create_tx(
                 Amount::from_sat(1000),
-                recipient.clone().assume_checked().script_pubkey(),
+                script_pubkey,
                 vec![],
                 vec![(UTXOKey(OutPoint::null()), spendable)],
                 fee,
@@ -2314,48 +5765,358 @@ fn create_tx_should_validate_amounts() {
             )
             .expect("is ok");
 
-        // peg out weight is incorrectly set to 0
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, 0), fee, Bitcoin);
+        let weight = tx.fees.total_weight;
+
+        let res = StatelessWallet::validate_tx_fees(&tx, PegOutFees::new(1000, weight), fee);
+        assert_eq!(res, Ok(()));
+
+        let res = StatelessWallet::validate_tx_fees(&tx, PegOutFees::new(1000, 0), fee);
         assert_eq!(res, Err(WalletOutputError::TxWeightIncorrect(0, weight)));
+    }
 
-        // fee rate set below min relay fee to 0
-        let res = StatelessWallet::validate_tx(&tx, &rbf(0, weight), fee, Bitcoin);
-        assert_eq!(res, Err(WalletOutputError::BelowMinRelayFee));
+    #[test]
+    fn recovery_path_should_require_fee_income() {
+        let recovery = EmergencyRecoveryParams {
+            threshold: 2,
+            keys: vec![],
+            delay: 4320,
+        };
 
-        // fees are okay
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(res, Ok(()));
+        assert!(recovery.validate(4, &FeeConsensus::default()).is_ok());
 
-        // tx has fee below consensus
-        tx.fees = PegOutFees::new(0, weight);
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(
-            res,
-            Err(WalletOutputError::PegOutFeeBelowConsensus(
-                Feerate { sats_per_kvb: 0 },
-                fee
-            ))
+        // Without any fee income the fee budget could never fund a refresh
+        assert!(
+            recovery
+                .validate(
+                    4,
+                    &FeeConsensus {
+                        peg_in_abs: fedimint_core::Amount::ZERO,
+                        peg_out_abs: fedimint_core::Amount::ZERO,
+                    }
+                )
+                .is_err()
         );
+    }
 
-        // tx has peg-out amount under dust limit
-        tx.peg_out_amount = bitcoin::Amount::ZERO;
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(res, Err(WalletOutputError::PegOutUnderDustLimit));
+    #[test]
+    fn recovery_path_utxos_should_be_refreshable() {
+        let secp = secp256k1::Secp256k1::new();
 
-        // tx is invalid for network
-        let output = WalletOutputV0::PegOut(PegOut {
-            recipient,
-            amount: bitcoin::Amount::from_sat(1000),
-            fees: PegOutFees::new(100, weight),
+        let keys = (0..4)
+            .map(|_| secp.generate_keypair(&mut OsRng))
+            .collect::<Vec<_>>();
+
+        let (_, designated_key) = secp.generate_keypair(&mut OsRng);
+
+        let rpc = BitcoinRpcConfig {
+            kind: "esplora".to_string(),
+            url: SafeUrl::parse("http://127.0.0.1/").unwrap(),
+        };
+
+        let cfg = WalletConfig::new(
+            keys.iter()
+                .enumerate()
+                .map(|(idx, (_, key))| {
+                    (PeerId::from(idx as u16), CompressedPublicKey { key: *key })
+                })
+                .collect(),
+            keys[0].0,
+            3,
+            Bitcoin,
+            10,
+            rpc.clone(),
+            rpc,
+            FeeConsensus::default(),
+            Some(&EmergencyRecoveryParams {
+                threshold: 2,
+                keys: vec![CompressedPublicKey {
+                    key: designated_key,
+                }],
+                delay: 4320,
+            }),
+        );
+
+        assert_eq!(cfg.consensus.recovery_delay(), Some(4320));
+
+        // The guardian's recovery key is part of the descriptor and derivable from
+        // its secret peg-in key
+        let recovery_key = guardian_recovery_key(&CompressedPublicKey { key: keys[0].1 });
+        assert_eq!(
+            recovery_key.key,
+            secp256k1::PublicKey::from_secret_key(&secp, &guardian_recovery_secret_key(&keys[0].0))
+        );
+
+        let mut descriptor_keys = vec![];
+        cfg.consensus.peg_in_descriptor.for_each_key(|key| {
+            descriptor_keys.push(*key);
+            true
         });
-        let res = StatelessWallet::validate_tx(&tx, &output, fee, Testnet);
+        assert_eq!(descriptor_keys.len(), 9);
+        assert!(descriptor_keys.contains(&recovery_key));
+
+        let wallet = StatelessWallet {
+            descriptor: &cfg.consensus.peg_in_descriptor,
+            secret_key: &cfg.private.peg_in_key,
+            secp: &secp,
+        };
+
+        let utxos = (0..3)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: [vout as u8; 33],
+                        amount: Amount::from_sat(1000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // dust can not pay for its own refresh
+        let fee_rate = Feerate {
+            sats_per_kvb: 10_000,
+        };
         assert_eq!(
-            res,
-            Err(WalletOutputError::WrongNetwork(
-                NetworkLegacyEncodingWrapper(Testnet),
-                NetworkLegacyEncodingWrapper(Bitcoin)
-            ))
+            wallet
+                .create_self_spend_tx(utxos.clone(), fee_rate, Amount::MAX_MONEY, &[0; 33])
+                .map(|_| ()),
+            Err(WalletOutputError::NotEnoughSpendableUTXO)
+        );
+
+        let fee_rate = Feerate { sats_per_kvb: 1000 };
+        let tx = wallet
+            .create_self_spend_tx(utxos.clone(), fee_rate, Amount::MAX_MONEY, &[0; 33])
+            .expect("is ok");
+
+        // the refresh never pays more than the fee budget allows
+        assert_eq!(
+            wallet
+                .create_self_spend_tx(
+                    utxos.clone(),
+                    fee_rate,
+                    tx.fees.amount() - Amount::from_sat(1),
+                    &[0; 33]
+                )
+                .map(|_| ()),
+            Err(WalletOutputError::FeeBudgetExceeded)
+        );
+
+        assert_eq!(tx.change, Amount::from_sat(3000) - tx.fees.amount());
+        assert_eq!(tx.selected_utxos, utxos);
+
+        let unsigned_tx = &tx.psbt.unsigned_tx;
+        assert_eq!(unsigned_tx.input.len(), 3);
+        assert_eq!(unsigned_tx.output.len(), 1);
+        assert_eq!(
+            unsigned_tx.output[0].script_pubkey,
+            wallet.derive_script(&[0; 33])
+        );
+
+        // Every input commits to the timelocked recovery path
+        for input in &tx.psbt.inputs {
+            assert!(
+                input
+                    .witness_script
+                    .as_ref()
+                    .expect("witness script is set")
+                    .instructions()
+                    .any(|instruction| instruction
+                        == Ok(Instruction::Op(bitcoin::opcodes::all::OP_CSV)))
+            );
+        }
+    }
+
+    #[test]
+    fn refresh_should_select_oldest_due_utxos() {
+        let utxo = |vout: u32, height: u32| {
+            (
+                UTXOKey(OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout,
+                }),
+                SpendableUTXO {
+                    tweak: [0; 33],
+                    amount: Amount::from_sat(1000),
+                },
+                height,
+            )
+        };
+
+        // UTXOs confirmed at least the minimum age ago are due
+        let selected = select_refresh_utxos(
+            vec![utxo(0, 500), utxo(1, 100), utxo(2, 300), utxo(3, 301)],
+            800,
+            500,
+        );
+        assert_eq!(
+            selected
+                .iter()
+                .map(|(key, _)| key.0.vout)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        assert!(select_refresh_utxos(vec![utxo(0, 500)], 999, 500).is_empty());
+
+        // If there are too many the oldest are refreshed first
+        let selected = select_refresh_utxos(
+            (0..150).map(|vout| utxo(vout, 150 - vout)).collect(),
+            10_000,
+            500,
+        );
+        assert_eq!(selected.len(), 100);
+        assert_eq!(selected[0].0.0.vout, 149);
+        assert_eq!(selected[99].0.0.vout, 50);
+    }
+
+    #[test]
+    fn signer_set_should_expire_after_timeout() {
+        let incomplete = FrostSigningRound {
+            round: 0,
+            complete_session: None,
+        };
+        assert!(!incomplete.is_expired(1000, FROST_SIGNING_TIMEOUT_SESSIONS));
+
+        let complete = FrostSigningRound {
+            round: 3,
+            complete_session: Some(10),
+        };
+        assert!(!complete.is_expired(10, FROST_SIGNING_TIMEOUT_SESSIONS));
+        assert!(!complete.is_expired(
+            10 + FROST_SIGNING_TIMEOUT_SESSIONS - 1,
+            FROST_SIGNING_TIMEOUT_SESSIONS
+        ));
+        assert!(complete.is_expired(
+            10 + FROST_SIGNING_TIMEOUT_SESSIONS,
+            FROST_SIGNING_TIMEOUT_SESSIONS
+        ));
+    }
+
+    #[test]
+    fn consolidation_votes_should_follow_fee_rate_estimate() {
+        let sats_per_vbyte = |sats: u64| Feerate {
+            sats_per_kvb: sats * 1000,
+        };
+
+        // we vote with our raw estimate while it is below our limit
+        assert_eq!(
+            consolidation_vote(Some(sats_per_vbyte(3)), sats_per_vbyte(5)),
+            sats_per_vbyte(3)
+        );
+        assert_eq!(
+            consolidation_vote(Some(sats_per_vbyte(5)), sats_per_vbyte(5)),
+            sats_per_vbyte(5)
+        );
+        assert_eq!(
+            consolidation_vote(Some(sats_per_vbyte(6)), sats_per_vbyte(5)),
+            sats_per_vbyte(0)
+        );
+
+        // but never below the min relay fee
+        assert_eq!(
+            consolidation_vote(Some(Feerate { sats_per_kvb: 100 }), sats_per_vbyte(5)),
+            sats_per_vbyte(1)
+        );
+
+        // we do not vote without an estimate or if consolidation is disabled
+        assert_eq!(
+            consolidation_vote(None, sats_per_vbyte(5)),
+            sats_per_vbyte(0)
+        );
+        assert_eq!(
+            consolidation_vote(Some(sats_per_vbyte(0)), sats_per_vbyte(0)),
+            sats_per_vbyte(0)
+        );
+    }
+
+    #[test]
+    fn consolidation_should_require_threshold_of_votes() {
+        let sats_per_vbyte = |sats: u64| Feerate {
+            sats_per_kvb: sats * 1000,
+        };
+
+        // no consolidation without a threshold of peers voting for it
+        assert_eq!(aggregate_consolidation_votes(vec![], 3), None);
+        assert_eq!(
+            aggregate_consolidation_votes(
+                vec![
+                    sats_per_vbyte(2),
+                    sats_per_vbyte(0),
+                    sats_per_vbyte(3),
+                    sats_per_vbyte(0)
+                ],
+                3
+            ),
+            None
+        );
+
+        // the consolidation pays the highest estimate a threshold of peers agrees on
+        assert_eq!(
+            aggregate_consolidation_votes(
+                vec![
+                    sats_per_vbyte(2),
+                    sats_per_vbyte(4),
+                    sats_per_vbyte(3),
+                    sats_per_vbyte(0)
+                ],
+                3
+            ),
+            Some(sats_per_vbyte(2))
+        );
+        assert_eq!(
+            aggregate_consolidation_votes(
+                vec![
+                    sats_per_vbyte(2),
+                    sats_per_vbyte(4),
+                    sats_per_vbyte(3),
+                    sats_per_vbyte(5)
+                ],
+                3
+            ),
+            Some(sats_per_vbyte(3))
+        );
+    }
+
+    #[test]
+    fn consolidation_should_select_smallest_utxos() {
+        let utxos = (0..150u32)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: [0; 33],
+                        amount: Amount::from_sat(u64::from(150 - vout) * 1000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let selected = select_consolidation_utxos(utxos.clone());
+
+        assert_eq!(selected.len(), 100);
+        assert_eq!(selected[0].1.amount, Amount::from_sat(1000));
+        assert_eq!(selected[99].1.amount, Amount::from_sat(100_000));
+
+        // The selection does not depend on the order of the UTXOs
+        let mut reversed = utxos[140..].to_vec();
+        reversed.reverse();
+
+        let selected = select_consolidation_utxos(reversed);
+
+        // The largest UTXO is kept to fund peg-outs
+        assert_eq!(selected.len(), 9);
+        assert!(
+            selected
+                .iter()
+                .all(|(_, utxo)| utxo.amount < Amount::from_sat(10_000))
         );
+        assert!(selected.windows(2).all(|w| w[0].1.amount < w[1].1.amount));
     }
 
     fn rbf(sats_per_kvb: u64, total_weight: u64) -> WalletOutputV0 {
@@ -2364,4 +6125,82 @@ fn rbf(sats_per_kvb: u64, total_weight: u64) -> WalletOutputV0 {
             txid: Txid::all_zeros(),
         })
     }
+
+    #[test]
+    fn proof_of_reserves_requires_threshold_signatures() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let secret_keys = (0..4)
+            .map(|_| secp.generate_keypair(&mut OsRng).0)
+            .collect::<Vec<_>>();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                secret_keys
+                    .iter()
+                    .map(|secret_key| CompressedPublicKey {
+                        key: secret_key.public_key(&secp),
+                    })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let utxos = (0..3u8)
+            .map(|idx| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout: u32::from(idx),
+                    }),
+                    SpendableUTXO {
+                        tweak: [idx; 33],
+                        amount: bitcoin::Amount::from_sat(1000 * u64::from(idx + 1)),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let commitment = ReservesCommitment {
+            federation_id: fedimint_core::config::FederationId::dummy(),
+            session_count: 42,
+            liabilities: fedimint_core::Amount::from_sats(5000),
+        };
+
+        let proofs = secret_keys
+            .iter()
+            .map(|secret_key| {
+                StatelessWallet {
+                    descriptor: &descriptor,
+                    secret_key,
+                    secp: &secp,
+                }
+                .create_proof_of_reserves(commitment.clone(), utxos.clone())
+            })
+            .collect::<Vec<_>>();
+
+        assert!(ProofOfReserves::combine(proofs[..2].to_vec(), &secp).is_err());
+
+        let proof = ProofOfReserves::combine(proofs[1..].to_vec(), &secp).expect("is ok");
+        let verified = proof.verify(&descriptor, &secp).expect("is ok");
+
+        assert_eq!(verified.commitment, commitment);
+        assert_eq!(verified.reserves, Amount::from_sat(6000));
+        assert_eq!(verified.utxos.len(), 3);
+        assert!(verified.solvent);
+
+        // The proof is bound to the liabilities it was signed for
+        let mut forged = proof.clone();
+        forged.commitment.liabilities = fedimint_core::Amount::from_sats(1000);
+        assert!(forged.verify(&descriptor, &secp).is_err());
+
+        // Spending a UTXO twice must not count it twice
+        let mut duplicated = proof.clone();
+        let input = duplicated.psbt.unsigned_tx.input[1].clone();
+        duplicated.psbt.unsigned_tx.input.push(input);
+        let psbt_input = duplicated.psbt.inputs[1].clone();
+        duplicated.psbt.inputs.push(psbt_input);
+        assert!(duplicated.verify(&descriptor, &secp).is_err());
+    }
 }
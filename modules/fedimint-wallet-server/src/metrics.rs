@@ -1,10 +1,11 @@
 use std::sync::LazyLock;
 
 use fedimint_metrics::prometheus::{
-    IntGauge, register_histogram_vec_with_registry, register_int_gauge_with_registry,
+    IntGauge, register_histogram_vec_with_registry, register_int_counter_with_registry,
+    register_int_gauge_with_registry,
 };
 use fedimint_metrics::{
-    AMOUNTS_BUCKETS_SATS, Histogram, HistogramVec, REGISTRY, histogram_opts, opts,
+    AMOUNTS_BUCKETS_SATS, Histogram, HistogramVec, IntCounter, REGISTRY, histogram_opts, opts,
     register_histogram_with_registry,
 };
 
@@ -86,3 +87,33 @@
     )
     .unwrap()
 });
+pub(crate) static WALLET_CONSOLIDATIONS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter_with_registry!(
+        opts!(
+            "wallet_consolidations_total",
+            "Number of transactions consolidating spendable UTXOs",
+        ),
+        REGISTRY
+    )
+    .unwrap()
+});
+pub(crate) static WALLET_CONSOLIDATED_UTXOS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter_with_registry!(
+        opts!(
+            "wallet_consolidated_utxos_total",
+            "Number of spendable UTXOs spent by consolidation transactions",
+        ),
+        REGISTRY
+    )
+    .unwrap()
+});
+pub(crate) static WALLET_SPENDABLE_UTXOS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge_with_registry!(
+        opts!(
+            "wallet_spendable_utxos",
+            "Number of spendable UTXOs of the federation's wallet",
+        ),
+        REGISTRY
+    )
+    .unwrap()
+});
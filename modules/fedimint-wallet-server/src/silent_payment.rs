@@ -0,0 +1,485 @@
+//! BIP-352 silent payments from the key path inputs of taproot peg-outs.
+//!
+//! The secret key of a peg-out input is the group secret key `s` plus the
+//! public peg-in tweak of the input, negated if the resulting internal key has
+//! an odd y coordinate, plus the taproot tweak, negated again if the output key
+//! has an odd y coordinate. Hence, the sum of the secret keys of all inputs is
+//! `a = k * s + c` for a small integer `k` and a public scalar `c`, and its
+//! ECDH with the scan key `B` of the recipient is `k * (s * B) + c * B`.
+//!
+//! Every guardian publishes `s_i * B` for its Shamir share `s_i` of `s`, along
+//! with a Chaum-Pedersen proof that `s_i` is also the discrete log of its
+//! verification share. Any threshold of valid shares interpolates to `s * B`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, ensure};
+use bitcoin::hashes::{Hash as BitcoinHash, HashEngine, sha256};
+use bitcoin::key::{Parity, TweakedPublicKey};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use bitcoin::taproot::TapTweakHash;
+use bitcoin::{OutPoint, ScriptBuf};
+use fedimint_core::PeerId;
+use fedimint_wallet_common::silent_payment::SilentPaymentAddress;
+use frost_secp256k1_tr::{Field, Group, Secp256K1Group, Secp256K1ScalarField};
+use rand::rngs::OsRng;
+
+use crate::frost::peg_in_tweak;
+
+type Scalar = <Secp256K1ScalarField as Field>::Scalar;
+
+type Point = <Secp256K1Group as Group>::Element;
+
+const DLEQ_TAG: &str = "fedimint/wallet/silent-payment/dleq";
+
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+
+    for data in data {
+        engine.input(data);
+    }
+
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+fn scalar(bytes: &[u8; 32]) -> anyhow::Result<Scalar> {
+    Secp256K1ScalarField::deserialize(bytes)
+        .ok()
+        .context("Scalar is out of range")
+}
+
+fn point(key: &PublicKey) -> Point {
+    Secp256K1Group::deserialize(&key.serialize()).expect("Public keys are valid points")
+}
+
+fn public_key(point: &Point) -> anyhow::Result<PublicKey> {
+    let bytes = Secp256K1Group::serialize(point)
+        .ok()
+        .context("Point is infinity")?;
+
+    Ok(PublicKey::from_slice(bytes.as_ref())?)
+}
+
+/// The x coordinate of the share of `peer`, see [`crate::frost`]
+fn peer_scalar(peer: PeerId) -> Scalar {
+    let mut bytes = [0; 32];
+    bytes[30..].copy_from_slice(&(u16::from(peer) + 1).to_be_bytes());
+
+    scalar(&bytes).expect("Small integers are in range")
+}
+
+/// The sum of the secret keys of the inputs of a taproot peg-out in terms of
+/// the group secret key, along with the hash of the inputs that enters the
+/// shared secret with the recipient
+#[derive(Debug)]
+pub struct SilentPaymentInputs {
+    k: Scalar,
+    c: Scalar,
+    input_hash: Scalar,
+}
+
+impl SilentPaymentInputs {
+    /// Sums up the secret keys of `inputs`, the outpoints and peg-in tweaks of
+    /// UTXOs of the peg-in descriptor with the internal key `group_key`
+    pub fn new(
+        group_key: &PublicKey,
+        inputs: impl IntoIterator<Item = (OutPoint, [u8; 33])>,
+    ) -> anyhow::Result<Self> {
+        let secp = Secp256k1::verification_only();
+        let sign = |parity: Parity| match parity {
+            Parity::Even => Scalar::ONE,
+            Parity::Odd => -Scalar::ONE,
+        };
+
+        let mut k = Scalar::ZERO;
+        let mut c = Scalar::ZERO;
+        let mut sum = Point::IDENTITY;
+        let mut smallest_outpoint: Option<Vec<u8>> = None;
+
+        for (outpoint, tweak) in inputs {
+            let peg_in_tweak = peg_in_tweak(group_key, &tweak)?;
+            let (internal_key, internal_parity) = group_key
+                .add_exp_tweak(&secp, &peg_in_tweak)?
+                .x_only_public_key();
+
+            let tap_tweak = TapTweakHash::from_key_and_tweak(internal_key, None).to_scalar();
+            let (output_key, output_parity) = internal_key.add_tweak(&secp, &tap_tweak)?;
+
+            let internal_sign = sign(internal_parity);
+            let output_sign = sign(output_parity);
+
+            k += output_sign * internal_sign;
+            c += output_sign
+                * (internal_sign * scalar(&peg_in_tweak.to_be_bytes())?
+                    + scalar(&tap_tweak.to_be_bytes())?);
+            sum += point(&output_key.public_key(Parity::Even));
+
+            let outpoint = bitcoin::consensus::serialize(&outpoint);
+
+            if smallest_outpoint
+                .as_ref()
+                .is_none_or(|smallest| outpoint < *smallest)
+            {
+                smallest_outpoint = Some(outpoint);
+            }
+        }
+
+        let smallest_outpoint = smallest_outpoint.context("Transaction has no inputs")?;
+        let sum = public_key(&sum).context("Input keys sum up to zero")?;
+
+        let input_hash = scalar(&tagged_hash(
+            "BIP0352/Inputs",
+            &[&smallest_outpoint, &sum.serialize()],
+        ))?;
+
+        Ok(SilentPaymentInputs { k, c, input_hash })
+    }
+
+    /// The output script of the payment to `address`, given the product of
+    /// the group secret key and the scan key of the address
+    pub fn output_script(
+        &self,
+        address: &SilentPaymentAddress,
+        secret_scan_product: &PublicKey,
+    ) -> anyhow::Result<ScriptBuf> {
+        let shared_secret = (point(secret_scan_product) * self.k
+            + point(&address.scan_key) * self.c)
+            * self.input_hash;
+
+        let output_tweak = scalar(&tagged_hash(
+            "BIP0352/SharedSecret",
+            &[
+                &public_key(&shared_secret)?.serialize(),
+                &0_u32.to_be_bytes(),
+            ],
+        ))?;
+
+        let output_key =
+            public_key(&(point(&address.spend_key) + Secp256K1Group::generator() * output_tweak))?;
+
+        Ok(ScriptBuf::new_p2tr_tweaked(
+            TweakedPublicKey::dangerous_assume_tweaked(output_key.x_only_public_key().0),
+        ))
+    }
+}
+
+/// Our share of the product of the group secret key and `scan_key`, along with
+/// the challenge and response of the proof that it has the same discrete log
+/// as our verification share
+pub fn share(secret: &SecretKey, scan_key: &PublicKey) -> (PublicKey, [u8; 32], [u8; 32]) {
+    let secret = scalar(&secret.secret_bytes()).expect("Secret keys are in range");
+    let nonce = Secp256K1ScalarField::random(&mut OsRng);
+
+    let share = point(scan_key) * secret;
+
+    let challenge = dleq_challenge(
+        &(Secp256K1Group::generator() * secret),
+        scan_key,
+        &share,
+        &(Secp256K1Group::generator() * nonce),
+        &(point(scan_key) * nonce),
+    )
+    .expect("Commitments are not infinity");
+
+    (
+        public_key(&share).expect("Share is not infinity"),
+        Secp256K1ScalarField::serialize(&challenge),
+        Secp256K1ScalarField::serialize(&(nonce + challenge * secret)),
+    )
+}
+
+/// Verifies the share of a peer with the verification share
+/// `verification_share`
+pub fn verify_share(
+    verification_share: &PublicKey,
+    scan_key: &PublicKey,
+    share: &PublicKey,
+    challenge: &[u8; 32],
+    response: &[u8; 32],
+) -> anyhow::Result<()> {
+    let challenge = scalar(challenge)?;
+    let response = scalar(response)?;
+
+    let expected = dleq_challenge(
+        &point(verification_share),
+        scan_key,
+        &point(share),
+        &(Secp256K1Group::generator() * response - point(verification_share) * challenge),
+        &(point(scan_key) * response - point(share) * challenge),
+    )?;
+
+    ensure!(expected == challenge, "Silent payment share is invalid");
+
+    Ok(())
+}
+
+fn dleq_challenge(
+    verification_share: &Point,
+    scan_key: &PublicKey,
+    share: &Point,
+    base_commitment: &Point,
+    scan_commitment: &Point,
+) -> anyhow::Result<Scalar> {
+    scalar(&tagged_hash(
+        DLEQ_TAG,
+        &[
+            &public_key(verification_share)?.serialize(),
+            &scan_key.serialize(),
+            &public_key(share)?.serialize(),
+            &public_key(base_commitment)?.serialize(),
+            &public_key(scan_commitment)?.serialize(),
+        ],
+    ))
+}
+
+/// Lagrange-interpolates the product of the group secret key and the scan key
+/// from the verified shares of a threshold of peers
+pub fn combine_shares(shares: &BTreeMap<PeerId, PublicKey>) -> anyhow::Result<PublicKey> {
+    let mut sum = Point::IDENTITY;
+
+    for (peer, share) in shares {
+        let mut coefficient = Scalar::ONE;
+
+        for other in shares.keys().filter(|other| *other != peer) {
+            coefficient *= peer_scalar(*other)
+                * Secp256K1ScalarField::invert(&(peer_scalar(*other) - peer_scalar(*peer)))
+                    .expect("Peers are distinct");
+        }
+
+        sum += point(share) * coefficient;
+    }
+
+    public_key(&sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::TweakedPublicKey;
+    use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1};
+    use bitcoin::{OutPoint, ScriptBuf, Txid};
+    use fedimint_core::PeerId;
+    use fedimint_wallet_common::keys::CompressedPublicKey;
+    use fedimint_wallet_common::silent_payment::SilentPaymentAddress;
+    use fedimint_wallet_common::tweakable::Tweakable;
+    use miniscript::Descriptor;
+
+    use super::{SilentPaymentInputs, combine_shares, share, tagged_hash, verify_share};
+    use crate::frost::{FrostKey, combine_shares as combine_secret_shares, dealer_keygen};
+
+    fn inputs(count: u8) -> Vec<(OutPoint, [u8; 33])> {
+        (0..count)
+            .map(|i| {
+                let outpoint = OutPoint {
+                    txid: Txid::from_byte_array([42 - i; 32]),
+                    vout: u32::from(i),
+                };
+
+                (outpoint, [i; 33])
+            })
+            .collect()
+    }
+
+    fn secret_scan_product(
+        keys: &BTreeMap<PeerId, FrostKey>,
+        signers: &[PeerId],
+        scan_key: &PublicKey,
+    ) -> PublicKey {
+        let shares = signers
+            .iter()
+            .map(|peer| {
+                let (share, challenge, response) = share(&keys[peer].share, scan_key);
+
+                verify_share(
+                    &keys[peer].verification_shares[peer],
+                    scan_key,
+                    &share,
+                    &challenge,
+                    &response,
+                )
+                .unwrap();
+
+                (*peer, share)
+            })
+            .collect();
+
+        combine_shares(&shares).unwrap()
+    }
+
+    /// The output script as the recipient derives it from the sum of the
+    /// output keys of the peg-in descriptor
+    fn recipient_output_script(
+        group_key: &PublicKey,
+        inputs: &[(OutPoint, [u8; 33])],
+        scan_secret: &bitcoin::secp256k1::SecretKey,
+        spend_key: &PublicKey,
+    ) -> ScriptBuf {
+        let secp = Secp256k1::new();
+
+        let output_keys = inputs
+            .iter()
+            .map(|(_, tweak)| {
+                let Descriptor::Tr(tr) =
+                    Descriptor::new_tr(CompressedPublicKey::new(*group_key), None)
+                        .unwrap()
+                        .tweak(tweak, &secp)
+                else {
+                    panic!("Expected taproot descriptor");
+                };
+
+                tr.spend_info()
+                    .output_key()
+                    .to_inner()
+                    .public_key(bitcoin::key::Parity::Even)
+            })
+            .collect::<Vec<_>>();
+
+        let sum = PublicKey::combine_keys(&output_keys.iter().collect::<Vec<_>>()).unwrap();
+
+        let smallest_outpoint = inputs
+            .iter()
+            .map(|(outpoint, _)| bitcoin::consensus::serialize(outpoint))
+            .min()
+            .unwrap();
+
+        let input_hash = tagged_hash("BIP0352/Inputs", &[&smallest_outpoint, &sum.serialize()]);
+
+        let shared_secret = sum
+            .mul_tweak(&secp, &Scalar::from_be_bytes(input_hash).unwrap())
+            .unwrap()
+            .mul_tweak(&secp, &Scalar::from(*scan_secret))
+            .unwrap();
+
+        let output_tweak = tagged_hash(
+            "BIP0352/SharedSecret",
+            &[&shared_secret.serialize(), &0_u32.to_be_bytes()],
+        );
+
+        let output_key = spend_key
+            .add_exp_tweak(&secp, &Scalar::from_be_bytes(output_tweak).unwrap())
+            .unwrap();
+
+        ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+            output_key.x_only_public_key().0,
+        ))
+    }
+
+    #[test]
+    fn threshold_of_shares_should_derive_recipient_output() {
+        let secp = Secp256k1::new();
+        let peers = (0..4).map(PeerId::from).collect::<Vec<_>>();
+        let keys = dealer_keygen(&peers, 3);
+        let group_key = keys[&peers[0]].group_key;
+
+        let (scan_secret, scan_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let (_, spend_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let address = SilentPaymentAddress {
+            scan_key,
+            spend_key,
+        };
+
+        // The product of the group secret with the scan key does not depend on
+        // the signer set
+        let product = secret_scan_product(&keys, &peers[..3], &scan_key);
+        assert_eq!(product, secret_scan_product(&keys, &peers[1..], &scan_key));
+
+        let group_secret = combine_secret_shares(
+            &group_key,
+            &peers[..3]
+                .iter()
+                .map(|peer| (*peer, keys[peer].share))
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            product,
+            scan_key
+                .mul_tweak(&secp, &Scalar::from(group_secret))
+                .unwrap()
+        );
+
+        // Enough inputs to cover all parities of internal and output keys
+        for count in 1..=8 {
+            let inputs = inputs(count);
+
+            let output_script = SilentPaymentInputs::new(&group_key, inputs.clone())
+                .unwrap()
+                .output_script(&address, &product)
+                .unwrap();
+
+            assert_eq!(
+                output_script,
+                recipient_output_script(&group_key, &inputs, &scan_secret, &spend_key)
+            );
+        }
+    }
+
+    #[test]
+    fn single_peer_should_derive_recipient_output() {
+        let secp = Secp256k1::new();
+        let peers = vec![PeerId::from(0)];
+        let keys = dealer_keygen(&peers, 1);
+        let group_key = keys[&peers[0]].group_key;
+
+        let (scan_secret, scan_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let (_, spend_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let address = SilentPaymentAddress {
+            scan_key,
+            spend_key,
+        };
+
+        let product = secret_scan_product(&keys, &peers, &scan_key);
+
+        let output_script = SilentPaymentInputs::new(&group_key, inputs(3))
+            .unwrap()
+            .output_script(&address, &product)
+            .unwrap();
+
+        assert_eq!(
+            output_script,
+            recipient_output_script(&group_key, &inputs(3), &scan_secret, &spend_key)
+        );
+    }
+
+    #[test]
+    fn share_should_not_verify_for_other_peer() {
+        let secp = Secp256k1::new();
+        let peers = (0..4).map(PeerId::from).collect::<Vec<_>>();
+        let keys = dealer_keygen(&peers, 3);
+        let (_, scan_key) = secp.generate_keypair(&mut rand::thread_rng());
+
+        let (share, challenge, response) = share(&keys[&peers[0]].share, &scan_key);
+        let verification_shares = &keys[&peers[0]].verification_shares;
+
+        assert!(
+            verify_share(
+                &verification_shares[&peers[1]],
+                &scan_key,
+                &share,
+                &challenge,
+                &response
+            )
+            .is_err()
+        );
+
+        // A share of another secret does not verify with the proof
+        let (other_share, ..) = super::share(&keys[&peers[1]].share, &scan_key);
+
+        assert!(
+            verify_share(
+                &verification_shares[&peers[0]],
+                &scan_key,
+                &other_share,
+                &challenge,
+                &response
+            )
+            .is_err()
+        );
+    }
+}
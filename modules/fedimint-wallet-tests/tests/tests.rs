@@ -19,8 +19,10 @@
 use fedimint_dummy_client::DummyClientInit;
 use fedimint_dummy_common::config::DummyGenParams;
 use fedimint_dummy_server::DummyInit;
+use fedimint_eventlog::EventLogNamespace;
 use fedimint_server::core::ServerModule;
 use fedimint_server_core::bitcoin_rpc::ServerBitcoinRpcMonitor;
+use fedimint_server_core::event_log::ServerModuleEventLogger;
 use fedimint_testing::btc::BitcoinTest;
 use fedimint_testing::envs::{FM_TEST_BACKEND_BITCOIN_RPC_KIND_ENV, FM_TEST_USE_REAL_DAEMONS_ENV};
 use fedimint_testing::federation::FederationTest;
@@ -36,6 +38,7 @@
 use futures::stream::StreamExt;
 use secp256k1::rand::rngs::OsRng;
 use tokio::select;
+use tokio::sync::watch;
 use tracing::{info, warn};
 
 fn fixtures() -> Fixtures {
@@ -710,6 +713,14 @@ async fn peg_ins_that_are_unconfirmed_are_rejected() -> anyhow::Result<()> {
             Duration::from_secs(1),
             &TaskGroup::new(),
         ),
+        ServerModuleEventLogger::new(
+            module_instance_id,
+            db.with_prefix_module_id(module_instance_id).1,
+            EventLogNamespace::root(),
+            watch::channel(()).0,
+        ),
+        watch::channel(0).1,
+        watch::channel(None).1,
     )
     .await?;
 
@@ -1061,6 +1072,8 @@ fn build_wallet_server_configs(
                 finality_delay: 10,
                 client_default_bitcoin_rpc: bitcoin_rpc.clone(),
                 fee_consensus: Default::default(),
+                taproot: false,
+                recovery: None,
             },
         })?,
     );
@@ -1506,6 +1519,28 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                         );
                         info!("Validated ConsensusVersionVotingActivation");
                     }
+                    // Batched peg-outs were introduced after the snapshot
+                    DbKeyPrefix::SessionCountVote
+                    | DbKeyPrefix::PegOutBatchItem
+                    | DbKeyPrefix::PegOutBatchStart => {}
+                    // CPFP fee bumping was introduced after the snapshot
+                    DbKeyPrefix::CpfpVote | DbKeyPrefix::CpfpChild => {}
+                    // Taproot peg-in descriptors were introduced after the snapshot
+                    DbKeyPrefix::FrostSecretNonces
+                    | DbKeyPrefix::FrostSigningRound
+                    | DbKeyPrefix::FrostNonce
+                    | DbKeyPrefix::FrostShareCi
+                    | DbKeyPrefix::FrostShare => {}
+                    // Emergency recovery paths were introduced after the snapshot
+                    DbKeyPrefix::UnspentTxOutHeight => {}
+                    // UTXO consolidation was introduced after the snapshot
+                    DbKeyPrefix::ConsolidationFeeRateVote => {}
+                    // The fee budget was introduced after the snapshot
+                    DbKeyPrefix::FeeBudget => {}
+                    // Accelerating deposits was introduced after the snapshot
+                    DbKeyPrefix::DepositCpfpRequest
+                    | DbKeyPrefix::DepositCpfpVote
+                    | DbKeyPrefix::DepositCpfp => {}
                 }
             }
             Ok(())